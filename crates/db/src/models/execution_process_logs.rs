@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use executors::encryption::{self, Encryptor};
+use serde::{Deserialize, Serialize, de::Error as _};
 use sqlx::{FromRow, SqlitePool};
 use ts_rs::TS;
 use utils::log_msg::LogMsg;
@@ -35,24 +36,29 @@ impl ExecutionProcessLogs {
         .await
     }
 
-    /// Parse JSONL logs back into Vec<LogMsg>
+    /// Parse JSONL logs back into Vec<LogMsg>, opening lines that were sealed
+    /// by encryption at rest
     pub fn parse_logs(records: &[Self]) -> Result<Vec<LogMsg>, serde_json::Error> {
         let mut messages = Vec::new();
         for line in records.iter().flat_map(|record| record.logs.lines()) {
             if !line.trim().is_empty() {
-                let msg: LogMsg = serde_json::from_str(line)?;
+                let line = encryption::open_line(line).map_err(serde_json::Error::custom)?;
+                let msg: LogMsg = serde_json::from_str(&line)?;
                 messages.push(msg);
             }
         }
         Ok(messages)
     }
 
-    /// Append a JSONL line to the logs for an execution process
+    /// Append a JSONL line to the logs for an execution process. The line is
+    /// sealed first if encryption at rest is configured.
     pub async fn append_log_line(
         pool: &SqlitePool,
         execution_id: Uuid,
         jsonl_line: &str,
     ) -> Result<(), sqlx::Error> {
+        let jsonl_line = seal_lines(jsonl_line)?;
+        let jsonl_line = jsonl_line.as_str();
         let byte_size = jsonl_line.len() as i64;
         sqlx::query!(
             r#"INSERT INTO execution_process_logs (execution_id, logs, byte_size, inserted_at)
@@ -66,4 +72,63 @@ impl ExecutionProcessLogs {
 
         Ok(())
     }
+
+    /// Re-seal stored logs with the current encryption key, returning how many
+    /// records changed. Run after adding a new key so retired keys can be
+    /// removed.
+    pub async fn rotate_encryption(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+        #[derive(FromRow)]
+        struct LogRow {
+            rowid: i64,
+            logs: String,
+        }
+
+        let Some(encryptor) = Encryptor::global() else {
+            return Ok(0);
+        };
+        let rows = sqlx::query_as::<_, LogRow>("SELECT rowid, logs FROM execution_process_logs")
+            .fetch_all(pool)
+            .await?;
+
+        let mut rotated = 0;
+        for row in rows {
+            let mut changed = false;
+            let mut logs = String::with_capacity(row.logs.len());
+            for line in row.logs.lines().filter(|line| !line.trim().is_empty()) {
+                match encryptor
+                    .rotate_line(line)
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?
+                {
+                    Some(sealed) => {
+                        changed = true;
+                        logs.push_str(&sealed);
+                    }
+                    None => logs.push_str(line),
+                }
+                logs.push('\n');
+            }
+            if changed {
+                sqlx::query(
+                    "UPDATE execution_process_logs SET logs = $1, byte_size = $2 WHERE rowid = $3",
+                )
+                .bind(&logs)
+                .bind(logs.len() as i64)
+                .bind(row.rowid)
+                .execute(pool)
+                .await?;
+                rotated += 1;
+            }
+        }
+        Ok(rotated)
+    }
+}
+
+fn seal_lines(jsonl: &str) -> Result<String, sqlx::Error> {
+    let mut sealed = String::with_capacity(jsonl.len());
+    for line in jsonl.lines().filter(|line| !line.trim().is_empty()) {
+        let line = encryption::seal_line(line).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        sealed.push_str(&line);
+        sealed.push('\n');
+    }
+    Ok(sealed)
 }
@@ -5,11 +5,18 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::{
-    actions::Executable,
-    approvals::ExecutorApprovalService,
+    actions::{Executable, agent_dir, resolve_agent},
+    approvals::{ExecutorApprovalService, policy::PolicyApprovalService},
+    attachments::{Attachment, mention_attachments},
+    budget,
+    cancellation::CancelScope,
+    dry_run::spawn_explanation,
     env::ExecutionEnv,
     executors::{BaseCodingAgent, ExecutorError, SpawnedChild, StandardCodingAgentExecutor},
     profile::{ExecutorConfigs, ExecutorProfileId},
+    retry::RetryPolicy,
+    runtime_limits::RuntimeLimits,
+    sessions::SessionManifest,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
@@ -24,6 +31,16 @@ pub struct CodingAgentFollowUpRequest {
     /// If None, uses the container_ref directory directly.
     #[serde(default)]
     pub working_dir: Option<String>,
+    /// Subdirectory of the working directory to run the agent in, taking
+    /// precedence over the profile's `cwd_relative`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd_relative: Option<String>,
+    /// Branch into a new session from `session_id` instead of continuing it
+    #[serde(default)]
+    pub fork: bool,
+    /// Files and images attached to the prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
 }
 
 impl CodingAgentFollowUpRequest {
@@ -51,20 +68,65 @@ impl Executable for CodingAgentFollowUpRequest {
         current_dir: &Path,
         approvals: Arc<dyn ExecutorApprovalService>,
         env: &ExecutionEnv,
+        cancel: &CancelScope,
     ) -> Result<SpawnedChild, ExecutorError> {
         let effective_dir = self.effective_dir(current_dir);
+        SessionManifest::for_worktree(&effective_dir)
+            .validate(self.base_executor(), &self.session_id)?;
 
         let executor_profile_id = self.get_executor_profile_id();
-        let mut agent = ExecutorConfigs::get_cached()
-            .get_coding_agent(&executor_profile_id)
-            .ok_or(ExecutorError::UnknownExecutorType(
-                executor_profile_id.to_string(),
-            ))?;
+        let configs = ExecutorConfigs::get_cached_for(env.tenant.as_ref());
+        let mut agent = resolve_agent(&configs, &executor_profile_id, None, env)?;
+        let agent_dir = agent_dir(
+            &effective_dir,
+            self.cwd_relative
+                .as_deref()
+                .or(agent.run_overrides().cwd_relative.as_deref()),
+        )?;
+        if agent.run_overrides().dry_run.unwrap_or(false) {
+            let explanation = agent.explain(&agent_dir, env).await?;
+            return spawn_explanation(&explanation, &agent_dir).await;
+        }
+        agent.ensure_supported_version().await?;
 
-        agent.use_approvals(approvals.clone());
+        agent.use_approvals(PolicyApprovalService::wrap(
+            approvals.clone(),
+            agent.approval_policy(),
+            env.approval_audit.as_ref(),
+        ));
 
-        agent
-            .spawn_follow_up(&effective_dir, &self.prompt, &self.session_id, env)
-            .await
+        // Forks are told where to find attachments in the prompt
+        let fork_prompt = if self.fork {
+            mention_attachments(&self.prompt, &self.attachments, &agent_dir)?
+        } else {
+            self.prompt.clone()
+        };
+        let mut spawned = RetryPolicy::from(&agent.run_overrides().runtime)
+            .run(cancel, env.spawn_timeout, || {
+                if self.fork {
+                    agent.spawn_fork(&agent_dir, &fork_prompt, &self.session_id, env)
+                } else {
+                    agent.spawn_follow_up_with_attachments(
+                        &agent_dir,
+                        &self.prompt,
+                        &self.session_id,
+                        &self.attachments,
+                        env,
+                    )
+                }
+            })
+            .await?;
+        if let Some(supervisor) = &agent.run_overrides().runtime.supervisor {
+            spawned = supervisor.supervise(spawned, &agent, &agent_dir, env)?;
+        }
+        RuntimeLimits::from(&agent.run_overrides().runtime).enforce(&mut spawned)?;
+        budget::enforce(
+            &mut spawned,
+            agent.run_overrides().runtime.budget.as_ref(),
+            env.tenant
+                .as_ref()
+                .map(|tenant| (tenant, &configs.tenant_budget)),
+        )?;
+        Ok(spawned)
     }
 }
@@ -5,11 +5,19 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::{
-    actions::Executable,
-    approvals::ExecutorApprovalService,
+    actions::{Executable, agent_dir, resolve_agent},
+    approvals::{ExecutorApprovalService, policy::PolicyApprovalService},
+    attachments::Attachment,
+    budget,
+    cancellation::CancelScope,
+    dry_run::spawn_explanation,
     env::ExecutionEnv,
     executors::{BaseCodingAgent, ExecutorError, SpawnedChild, StandardCodingAgentExecutor},
     profile::{ExecutorConfigs, ExecutorProfileId},
+    retry::RetryPolicy,
+    review::ReviewTarget,
+    runtime_limits::RuntimeLimits,
+    standby::{StandbyKey, StandbyPool},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
@@ -23,6 +31,21 @@ pub struct CodingAgentInitialRequest {
     /// If None, uses the container_ref directory directly.
     #[serde(default)]
     pub working_dir: Option<String>,
+    /// Subdirectory of the working directory to run the agent in, taking
+    /// precedence over the profile's `cwd_relative`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd_relative: Option<String>,
+    /// Run template whose permission level and environment apply on top of
+    /// the profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_template: Option<String>,
+    /// Files and images attached to the prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Review the workspace's changes since this target instead of
+    /// implementing; the prompt holds the reviewer's instructions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<ReviewTarget>,
 }
 
 impl CodingAgentInitialRequest {
@@ -45,18 +68,82 @@ impl Executable for CodingAgentInitialRequest {
         current_dir: &Path,
         approvals: Arc<dyn ExecutorApprovalService>,
         env: &ExecutionEnv,
+        cancel: &CancelScope,
     ) -> Result<SpawnedChild, ExecutorError> {
         let effective_dir = self.effective_dir(current_dir);
 
         let executor_profile_id = self.executor_profile_id.clone();
-        let mut agent = ExecutorConfigs::get_cached()
-            .get_coding_agent(&executor_profile_id)
-            .ok_or(ExecutorError::UnknownExecutorType(
-                executor_profile_id.to_string(),
-            ))?;
+        let configs = ExecutorConfigs::get_cached_for(env.tenant.as_ref());
+        let mut agent = resolve_agent(
+            &configs,
+            &executor_profile_id,
+            self.run_template.as_deref(),
+            env,
+        )?;
+        let agent_dir = agent_dir(
+            &effective_dir,
+            self.cwd_relative
+                .as_deref()
+                .or(agent.run_overrides().cwd_relative.as_deref()),
+        )?;
+        if agent.run_overrides().dry_run.unwrap_or(false) {
+            let explanation = agent.explain(&agent_dir, env).await?;
+            return spawn_explanation(&explanation, &agent_dir).await;
+        }
+        agent.ensure_supported_version().await?;
+        let prompt = match &self.review {
+            Some(review) => review.prompt(&self.prompt, &agent_dir).await?,
+            None => self.prompt.clone(),
+        };
+
+        cancel.check()?;
+        // Warm sessions are sent a plain prompt, so attachments need a cold spawn
+        let standby = (agent.warm_standby().is_some() && self.attachments.is_empty()).then(|| {
+            let key = StandbyKey::new(executor_profile_id.clone(), &agent_dir, env);
+            let session = StandbyPool::global().take(&key, &agent);
+            (key, agent.clone(), session)
+        });
 
-        agent.use_approvals(approvals.clone());
+        agent.use_approvals(PolicyApprovalService::wrap(
+            approvals.clone(),
+            agent.approval_policy(),
+            env.approval_audit.as_ref(),
+        ));
 
-        agent.spawn(&effective_dir, &self.prompt, env).await
+        let warm = match standby {
+            Some((key, config, session)) => {
+                // Keep a session ready for the next task in this workspace
+                StandbyPool::global().replenish(key, config, env.clone());
+                session.and_then(|session| {
+                    session
+                        .claim(&prompt, env)
+                        .inspect_err(|e| tracing::warn!("Falling back to a cold spawn: {e}"))
+                        .ok()
+                })
+            }
+            None => None,
+        };
+        let mut spawned = match warm {
+            Some(spawned) => spawned,
+            None => {
+                RetryPolicy::from(&agent.run_overrides().runtime)
+                    .run(cancel, env.spawn_timeout, || {
+                        agent.spawn_with_attachments(&agent_dir, &prompt, &self.attachments, env)
+                    })
+                    .await?
+            }
+        };
+        if let Some(supervisor) = &agent.run_overrides().runtime.supervisor {
+            spawned = supervisor.supervise(spawned, &agent, &agent_dir, env)?;
+        }
+        RuntimeLimits::from(&agent.run_overrides().runtime).enforce(&mut spawned)?;
+        budget::enforce(
+            &mut spawned,
+            agent.run_overrides().runtime.budget.as_ref(),
+            env.tenant
+                .as_ref()
+                .map(|tenant| (tenant, &configs.tenant_budget)),
+        )?;
+        Ok(spawned)
     }
 }
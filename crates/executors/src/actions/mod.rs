@@ -1,4 +1,7 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use enum_dispatch::enum_dispatch;
@@ -8,14 +11,20 @@ use ts_rs::TS;
 use crate::{
     actions::{
         coding_agent_follow_up::CodingAgentFollowUpRequest,
-        coding_agent_initial::CodingAgentInitialRequest, script::ScriptRequest,
+        coding_agent_initial::CodingAgentInitialRequest, pipeline::RunCondition,
+        script::ScriptRequest,
     },
     approvals::ExecutorApprovalService,
+    authorization::{SpawnRequest, authorize_spawn},
+    cancellation::CancelScope,
     env::ExecutionEnv,
-    executors::{BaseCodingAgent, ExecutorError, SpawnedChild},
+    executors::{BaseCodingAgent, CodingAgent, ExecutorError, SpawnedChild},
+    profile::{ExecutorConfigs, ExecutorProfileId},
+    tenant::TenantId,
 };
 pub mod coding_agent_follow_up;
 pub mod coding_agent_initial;
+pub mod pipeline;
 pub mod script;
 
 #[enum_dispatch]
@@ -31,12 +40,39 @@ pub enum ExecutorActionType {
 pub struct ExecutorAction {
     pub typ: ExecutorActionType,
     pub next_action: Option<Box<ExecutorAction>>,
+    /// Whether the action runs after the action before it succeeded, failed,
+    /// or either way
+    #[serde(default)]
+    pub run_if: RunCondition,
+    /// Tenant whose profiles, secrets and budget the action runs with;
+    /// `None` runs it as the server's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<TenantId>,
 }
 
 impl ExecutorAction {
     pub fn new(typ: ExecutorActionType, next_action: Option<Box<ExecutorAction>>) -> Self {
-        Self { typ, next_action }
+        Self {
+            typ,
+            next_action,
+            run_if: RunCondition::default(),
+            tenant: None,
+        }
+    }
+
+    pub fn with_run_if(mut self, run_if: RunCondition) -> Self {
+        self.run_if = run_if;
+        self
     }
+    /// Run this action and every one chained after it as `tenant`
+    pub fn with_tenant(mut self, tenant: Option<TenantId>) -> Self {
+        self.next_action = self
+            .next_action
+            .map(|next| Box::new(next.with_tenant(tenant.clone())));
+        self.tenant = tenant;
+        self
+    }
+
     pub fn append_action(mut self, action: ExecutorAction) -> Self {
         if let Some(next) = self.next_action {
             self.next_action = Some(Box::new(next.append_action(action)));
@@ -54,6 +90,39 @@ impl ExecutorAction {
         self.next_action.as_deref()
     }
 
+    /// Action to start once this one ended: the first one down the chain
+    /// whose condition matches the outcome
+    pub fn next_action_after(&self, succeeded: bool) -> Option<&ExecutorAction> {
+        let mut next = self.next_action();
+        while let Some(action) = next {
+            if action.run_if.matches(succeeded) {
+                return Some(action);
+            }
+            next = action.next_action();
+        }
+        None
+    }
+
+    /// Action to start once this one exited. Like [`Self::next_action_after`],
+    /// except that a failed run never falls through to the cleanup scripts
+    /// chained after it: those clean up after a successful run, then follow
+    /// one another whatever each one's outcome.
+    pub fn next_action_on_exit(&self, succeeded: bool) -> Option<&ExecutorAction> {
+        let next = self.next_action_after(succeeded)?;
+        if !succeeded && next.is_cleanup_script() && !self.is_cleanup_script() {
+            return None;
+        }
+        Some(next)
+    }
+
+    fn is_cleanup_script(&self) -> bool {
+        matches!(
+            self.typ(),
+            ExecutorActionType::ScriptRequest(script)
+                if script.context == script::ScriptContext::CleanupScript
+        )
+    }
+
     pub fn base_executor(&self) -> Option<BaseCodingAgent> {
         match self.typ() {
             ExecutorActionType::CodingAgentInitialRequest(request) => Some(request.base_executor()),
@@ -63,6 +132,35 @@ impl ExecutorAction {
             ExecutorActionType::ScriptRequest(_) => None,
         }
     }
+
+    /// Coding agent the action runs, resolved and authorized as its spawn
+    /// does it; `None` for scripts
+    pub fn coding_agent(&self, env: &ExecutionEnv) -> Result<Option<CodingAgent>, ExecutorError> {
+        let (profile, run_template) = match self.typ() {
+            ExecutorActionType::CodingAgentInitialRequest(request) => (
+                &request.executor_profile_id,
+                request.run_template.as_deref(),
+            ),
+            ExecutorActionType::CodingAgentFollowUpRequest(request) => {
+                (&request.executor_profile_id, None)
+            }
+            ExecutorActionType::ScriptRequest(_) => return Ok(None),
+        };
+        let configs = ExecutorConfigs::get_cached_for(env.tenant.as_ref());
+        resolve_agent(&configs, profile, run_template, env).map(Some)
+    }
+
+    pub fn executor_profile_id(&self) -> Option<&ExecutorProfileId> {
+        match self.typ() {
+            ExecutorActionType::CodingAgentInitialRequest(request) => {
+                Some(&request.executor_profile_id)
+            }
+            ExecutorActionType::CodingAgentFollowUpRequest(request) => {
+                Some(&request.executor_profile_id)
+            }
+            ExecutorActionType::ScriptRequest(_) => None,
+        }
+    }
 }
 
 #[async_trait]
@@ -73,6 +171,7 @@ pub trait Executable {
         current_dir: &Path,
         approvals: Arc<dyn ExecutorApprovalService>,
         env: &ExecutionEnv,
+        cancel: &CancelScope,
     ) -> Result<SpawnedChild, ExecutorError>;
 }
 
@@ -83,7 +182,83 @@ impl Executable for ExecutorAction {
         current_dir: &Path,
         approvals: Arc<dyn ExecutorApprovalService>,
         env: &ExecutionEnv,
+        cancel: &CancelScope,
     ) -> Result<SpawnedChild, ExecutorError> {
-        self.typ.spawn(current_dir, approvals, env).await
+        self.typ.spawn(current_dir, approvals, env, cancel).await
+    }
+}
+
+/// Agent `profile` runs with `run_template` and its permission mode applied,
+/// once `env`'s principal is allowed to spawn it
+pub(crate) fn resolve_agent(
+    configs: &ExecutorConfigs,
+    profile: &ExecutorProfileId,
+    run_template: Option<&str>,
+    env: &ExecutionEnv,
+) -> Result<CodingAgent, ExecutorError> {
+    let mut agent = configs
+        .get_coding_agent(profile)
+        .ok_or(ExecutorError::UnknownExecutorType(profile.to_string()))?;
+    if let Some(name) = run_template {
+        match configs.get_template(name) {
+            Some(template) => template.apply(&mut agent),
+            None => tracing::warn!("Run template '{name}' no longer exists, ignoring it"),
+        }
+    }
+    if let Some(mode) = agent.run_overrides().permission_mode {
+        mode.apply(&mut agent)?;
+    }
+    authorize_spawn(
+        env,
+        configs,
+        SpawnRequest {
+            profile,
+            agent: &agent,
+        },
+    )?;
+    Ok(agent)
+}
+
+/// Directory a coding agent runs in: `effective_dir`, or its `cwd_relative`
+/// subdirectory, e.g. one package of a monorepo. Logs are still normalized
+/// against `effective_dir`.
+pub(crate) fn agent_dir(
+    effective_dir: &Path,
+    cwd_relative: Option<&str>,
+) -> Result<PathBuf, ExecutorError> {
+    let Some(rel) = cwd_relative.filter(|rel| !rel.is_empty()) else {
+        return Ok(effective_dir.to_path_buf());
+    };
+    let inside = Path::new(rel)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    let dir = effective_dir.join(rel);
+    if !inside || !dir.is_dir() {
+        return Err(ExecutorError::InvalidWorkingDir(rel.to_string()));
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_dir_stays_inside_the_worktree() {
+        let worktree = std::env::temp_dir().join(format!("vk-agent-dir-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(worktree.join("packages/web")).unwrap();
+
+        assert_eq!(agent_dir(&worktree, None).unwrap(), worktree);
+        assert_eq!(
+            agent_dir(&worktree, Some("packages/web")).unwrap(),
+            worktree.join("packages/web")
+        );
+        for rel in ["../other", "/etc", "packages/missing"] {
+            assert!(matches!(
+                agent_dir(&worktree, Some(rel)),
+                Err(ExecutorError::InvalidWorkingDir(_))
+            ));
+        }
+        std::fs::remove_dir_all(worktree).unwrap();
     }
 }
@@ -0,0 +1,179 @@
+//! Actions chained into a pipeline, each started depending on how the action
+//! before it ended.
+//!
+//! A pipeline such as setup script → coding agent → test script → review
+//! agent is stored like any other chain of [`ExecutorAction`]s. Each action's
+//! [`RunCondition`] says whether it runs after the action before it succeeded,
+//! failed, or either way. When an action ends,
+//! [`ExecutorAction::next_action_after`] picks the next one to start, skipping
+//! the actions whose condition doesn't match.
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::actions::{ExecutorAction, ExecutorActionType};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum RunCondition {
+    #[default]
+    OnSuccess,
+    OnFailure,
+    Always,
+}
+
+impl RunCondition {
+    pub fn matches(self, succeeded: bool) -> bool {
+        match self {
+            RunCondition::OnSuccess => succeeded,
+            RunCondition::OnFailure => !succeeded,
+            RunCondition::Always => true,
+        }
+    }
+}
+
+/// Builder for a chain of actions with conditional continuation
+#[derive(Debug, Clone)]
+pub struct ExecutorActionPipeline {
+    steps: Vec<(RunCondition, ExecutorActionType)>,
+}
+
+impl ExecutorActionPipeline {
+    /// Pipeline starting with `first`, which runs when whatever it is
+    /// chained after succeeded
+    pub fn new(first: ExecutorActionType) -> Self {
+        Self {
+            steps: vec![(RunCondition::OnSuccess, first)],
+        }
+    }
+
+    /// Run `action` once the step before it succeeded
+    pub fn then(self, action: ExecutorActionType) -> Self {
+        self.then_if(RunCondition::OnSuccess, action)
+    }
+
+    /// Run `action` when the last step that ran ended as `condition` asks
+    pub fn then_if(mut self, condition: RunCondition, action: ExecutorActionType) -> Self {
+        self.steps.push((condition, action));
+        self
+    }
+
+    /// The pipeline as a chain of actions, starting with its first step
+    pub fn build(self) -> ExecutorAction {
+        let mut steps = self.steps.into_iter().rev();
+        let (condition, last) = steps.next().expect("pipelines have a first step");
+        let chain = ExecutorAction::new(last, None).with_run_if(condition);
+        steps.fold(chain, |next, (condition, action)| {
+            ExecutorAction::new(action, Some(Box::new(next))).with_run_if(condition)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        actions::{
+            coding_agent_initial::CodingAgentInitialRequest,
+            script::{ScriptContext, ScriptRequest, ScriptRequestLanguage},
+        },
+        executors::BaseCodingAgent,
+        profile::ExecutorProfileId,
+    };
+
+    fn script(script: &str, context: ScriptContext) -> ExecutorActionType {
+        ExecutorActionType::ScriptRequest(ScriptRequest {
+            script: script.to_string(),
+            language: ScriptRequestLanguage::Bash,
+            context,
+            working_dir: None,
+            timeout_secs: None,
+        })
+    }
+
+    fn agent(prompt: &str) -> ExecutorActionType {
+        ExecutorActionType::CodingAgentInitialRequest(CodingAgentInitialRequest {
+            prompt: prompt.to_string(),
+            executor_profile_id: ExecutorProfileId::new(BaseCodingAgent::ClaudeCode),
+            working_dir: None,
+            cwd_relative: None,
+            run_template: None,
+            attachments: Vec::new(),
+            review: None,
+        })
+    }
+
+    fn name(action: &ExecutorAction) -> &str {
+        match action.typ() {
+            ExecutorActionType::ScriptRequest(request) => &request.script,
+            ExecutorActionType::CodingAgentInitialRequest(request) => &request.prompt,
+            ExecutorActionType::CodingAgentFollowUpRequest(request) => &request.prompt,
+        }
+    }
+
+    /// Names of the actions started from `action` when each ends as
+    /// `outcome` says
+    fn run(action: &ExecutorAction, outcome: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut started = vec![name(action).to_string()];
+        let mut current = action;
+        while let Some(next) = current.next_action_after(outcome(name(current))) {
+            started.push(name(next).to_string());
+            current = next;
+        }
+        started
+    }
+
+    #[test]
+    fn test_pipeline_continues_on_matching_outcome() {
+        let pipeline = ExecutorActionPipeline::new(script("npm ci", ScriptContext::SetupScript))
+            .then(agent("implement"))
+            .then(script("npm test", ScriptContext::TestScript))
+            .then(agent("review"))
+            .then_if(RunCondition::OnFailure, agent("fix"))
+            .then_if(
+                RunCondition::Always,
+                script("cleanup", ScriptContext::CleanupScript),
+            )
+            .build();
+
+        let round_trip: ExecutorAction =
+            serde_json::from_value(serde_json::to_value(&pipeline).unwrap()).unwrap();
+        assert_eq!(run(&round_trip, |_| true), run(&pipeline, |_| true));
+
+        assert_eq!(
+            run(&pipeline, |_| true),
+            ["npm ci", "implement", "npm test", "review", "cleanup"]
+        );
+        assert_eq!(
+            run(&pipeline, |name| name != "npm test"),
+            ["npm ci", "implement", "npm test", "fix", "cleanup"]
+        );
+        assert_eq!(
+            run(&pipeline, |name| name != "npm ci"),
+            ["npm ci", "fix", "cleanup"]
+        );
+    }
+
+    #[test]
+    fn test_failed_runs_skip_cleanup_until_one_ran() {
+        let pipeline = ExecutorActionPipeline::new(agent("implement"))
+            .then(script("api cleanup", ScriptContext::CleanupScript))
+            .then_if(
+                RunCondition::Always,
+                script("web cleanup", ScriptContext::CleanupScript),
+            )
+            .build();
+        let cleanup = pipeline.next_action().unwrap();
+
+        assert!(pipeline.next_action_on_exit(false).is_none());
+        assert_eq!(
+            pipeline.next_action_on_exit(true).map(name),
+            Some("api cleanup")
+        );
+        // A failed cleanup still lets the next repo clean up
+        assert_eq!(
+            cleanup.next_action_on_exit(false).map(name),
+            Some("web cleanup")
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{path::Path, sync::Arc};
+use std::{path::Path, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use command_group::AsyncCommandGroup;
@@ -10,8 +10,10 @@ use workspace_utils::shell::get_shell_command;
 use crate::{
     actions::Executable,
     approvals::ExecutorApprovalService,
+    cancellation::CancelScope,
     env::ExecutionEnv,
     executors::{ExecutorError, SpawnedChild},
+    runtime_limits::RuntimeLimits,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
@@ -25,6 +27,7 @@ pub enum ScriptContext {
     CleanupScript,
     DevServer,
     ToolInstallScript,
+    TestScript,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
@@ -36,6 +39,10 @@ pub struct ScriptRequest {
     /// If None, uses the container_ref directory directly.
     #[serde(default)]
     pub working_dir: Option<String>,
+    /// Stop the script once it has run this many seconds, e.g. a hanging
+    /// test suite. Dev servers are left without a limit.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
 }
 
 #[async_trait]
@@ -45,7 +52,10 @@ impl Executable for ScriptRequest {
         current_dir: &Path,
         _approvals: Arc<dyn ExecutorApprovalService>,
         env: &ExecutionEnv,
+        cancel: &CancelScope,
     ) -> Result<SpawnedChild, ExecutorError> {
+        cancel.check()?;
+
         // Use working_dir if specified, otherwise use current_dir
         let effective_dir = match &self.working_dir {
             Some(rel_path) => current_dir.join(rel_path),
@@ -64,10 +74,54 @@ impl Executable for ScriptRequest {
             .current_dir(&effective_dir);
 
         // Apply environment variables
-        env.apply_to_command(&mut command);
+        env.apply_to_command(&mut command)?;
+
+        let mut spawned = SpawnedChild::from(command.group_spawn()?);
+        RuntimeLimits {
+            timeout: self.timeout_secs.map(Duration::from_secs),
+            idle_timeout: None,
+        }
+        .enforce(&mut spawned)?;
+        Ok(spawned)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        approvals::NoopExecutorApprovalService, executors::ExecutorExitResult,
+        runtime_limits::TimeoutKind,
+    };
+
+    #[tokio::test]
+    async fn test_test_script_stopped_after_timeout() {
+        let request = ScriptRequest {
+            script: "echo running tests; sleep 30".to_string(),
+            language: ScriptRequestLanguage::Bash,
+            context: ScriptContext::TestScript,
+            working_dir: None,
+            timeout_secs: Some(1),
+        };
+        let mut spawned = request
+            .spawn(
+                &std::env::temp_dir(),
+                Arc::new(NoopExecutorApprovalService),
+                &ExecutionEnv::new(),
+                &CancelScope::new(),
+            )
+            .await
+            .unwrap();
 
-        let child = command.group_spawn()?;
+        let result = tokio::time::timeout(Duration::from_secs(5), spawned.exit_signal.unwrap())
+            .await
+            .expect("timeout should fire")
+            .unwrap();
+        assert!(matches!(
+            result,
+            ExecutorExitResult::TimedOut(TimeoutKind::Total(limit)) if limit.as_secs() == 1
+        ));
 
-        Ok(child.into())
+        spawned.child.kill().await.unwrap();
     }
 }
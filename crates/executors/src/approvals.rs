@@ -1,3 +1,6 @@
+pub mod audit;
+pub mod policy;
+
 use std::fmt;
 
 use async_trait::async_trait;
@@ -0,0 +1,342 @@
+//! Append-only audit trail of tool approvals.
+//!
+//! Every approval an agent asks for is written to
+//! `approval-audit/<session id>.jsonl` in the asset directory together with
+//! its outcome, how long it took and who settled it: an approval policy, a
+//! person, or an executor that approves everything. Tool inputs are stored
+//! as a SHA-256 hash only; the inputs themselves are already in the
+//! execution logs. Executions denied at spawn are recorded here too, as
+//! entries of the [`SPAWN_TOOL_NAME`] pseudo tool.
+
+use std::{
+    fs::OpenOptions,
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use ts_rs::TS;
+use uuid::Uuid;
+use workspace_utils::approvals::ApprovalStatus;
+
+use super::{ExecutorApprovalError, ExecutorApprovalService};
+use crate::{authorization::AuthorizationDenied, encryption, tenant::TenantId};
+
+const AUDIT_DIR: &str = "approval-audit";
+
+/// Tool name of the entries recording executions denied at spawn
+pub const SPAWN_TOOL_NAME: &str = "spawn";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecider {
+    // A rule of the profile's approval policy
+    Policy,
+    // A person answering the approval request
+    User,
+    // An executor without approval support, which runs every tool
+    Auto,
+    // A role policy or authorizer, before the execution spawned
+    Authorization,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDecision {
+    Approved,
+    Denied,
+    TimedOut,
+    // The request failed before anyone decided
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ApprovalAuditEntry {
+    pub session_id: Uuid,
+    pub execution_process_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<TenantId>,
+    pub tool_name: String,
+    pub tool_call_id: String,
+    /// Hex SHA-256 of the tool input as JSON
+    pub input_hash: String,
+    pub decision: AuditDecision,
+    pub decider: ApprovalDecider,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub latency_ms: u64,
+    pub requested_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+pub struct ApprovalAuditQuery {
+    pub tool_name: Option<String>,
+    pub decision: Option<AuditDecision>,
+    pub decider: Option<ApprovalDecider>,
+}
+
+impl ApprovalAuditQuery {
+    fn matches(&self, entry: &ApprovalAuditEntry) -> bool {
+        self.tool_name
+            .as_ref()
+            .is_none_or(|tool| tool.eq_ignore_ascii_case(&entry.tool_name))
+            && self.decision.is_none_or(|d| d == entry.decision)
+            && self.decider.is_none_or(|d| d == entry.decider)
+    }
+}
+
+/// The audit file of one session
+#[derive(Debug, Clone)]
+pub struct ApprovalAuditLog {
+    path: PathBuf,
+    session_id: Uuid,
+    /// Execution process new entries are attributed to
+    execution_process_id: Uuid,
+    tenant: Option<TenantId>,
+}
+
+impl ApprovalAuditLog {
+    pub fn new(session_id: Uuid) -> Self {
+        Self::in_dir(
+            workspace_utils::assets::asset_dir().join(AUDIT_DIR),
+            session_id,
+        )
+    }
+
+    pub(crate) fn in_dir(dir: PathBuf, session_id: Uuid) -> Self {
+        Self {
+            path: dir.join(format!("{session_id}.jsonl")),
+            session_id,
+            execution_process_id: Uuid::nil(),
+            tenant: None,
+        }
+    }
+
+    pub fn for_execution_process(mut self, execution_process_id: Uuid) -> Self {
+        self.execution_process_id = execution_process_id;
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: Option<TenantId>) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    /// Append the outcome of an approval request made at `started`
+    pub fn record(
+        &self,
+        tool_name: &str,
+        tool_input: &Value,
+        tool_call_id: &str,
+        outcome: &Result<ApprovalStatus, ExecutorApprovalError>,
+        decider: ApprovalDecider,
+        started: Instant,
+    ) {
+        let latency = started.elapsed();
+        let (decision, reason) = match outcome {
+            Ok(ApprovalStatus::Approved) => (AuditDecision::Approved, None),
+            Ok(ApprovalStatus::Denied { reason }) => (AuditDecision::Denied, reason.clone()),
+            Ok(ApprovalStatus::TimedOut) => (AuditDecision::TimedOut, None),
+            Ok(ApprovalStatus::Pending) => (
+                AuditDecision::Failed,
+                Some("approval still pending".to_string()),
+            ),
+            Err(e) => (AuditDecision::Failed, Some(e.to_string())),
+        };
+        let entry = ApprovalAuditEntry {
+            session_id: self.session_id,
+            execution_process_id: self.execution_process_id,
+            tenant: self.tenant.clone(),
+            tool_name: tool_name.to_string(),
+            tool_call_id: tool_call_id.to_string(),
+            input_hash: input_hash(tool_input),
+            decision,
+            decider,
+            reason,
+            latency_ms: latency.as_millis() as u64,
+            requested_at: Utc::now() - chrono::Duration::from_std(latency).unwrap_or_default(),
+        };
+        self.write(&entry);
+    }
+
+    /// Append an execution of `profile` denied at spawn; the principal and
+    /// profile are hashed like a tool input
+    pub fn record_spawn_denial(&self, profile: &str, denied: &AuthorizationDenied) {
+        let input = serde_json::json!({ "principal": denied.principal, "profile": profile });
+        let entry = ApprovalAuditEntry {
+            session_id: self.session_id,
+            execution_process_id: self.execution_process_id,
+            tenant: self.tenant.clone(),
+            tool_name: SPAWN_TOOL_NAME.to_string(),
+            tool_call_id: String::new(),
+            input_hash: input_hash(&input),
+            decision: AuditDecision::Denied,
+            decider: ApprovalDecider::Authorization,
+            reason: Some(denied.to_string()),
+            latency_ms: 0,
+            requested_at: Utc::now(),
+        };
+        self.write(&entry);
+    }
+
+    fn write(&self, entry: &ApprovalAuditEntry) {
+        if let Err(e) = self.append(entry) {
+            tracing::warn!(
+                "Failed to write approval audit to {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+
+    fn append(&self, entry: &ApprovalAuditEntry) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let line = serde_json::to_string(entry)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", encryption::seal_line(&line)?)
+    }
+
+    /// Entries matching `query`, oldest first. Unset query fields match
+    /// everything.
+    pub fn query(&self, query: &ApprovalAuditQuery) -> io::Result<Vec<ApprovalAuditEntry>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match encryption::open_line(&line)
+                .map_err(io::Error::from)
+                .and_then(|line| serde_json::from_str(&line).map_err(io::Error::from))
+            {
+                Ok(entry) if query.matches(&entry) => entries.push(entry),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Skipping audit entry in {}: {e}", self.path.display()),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+fn input_hash(tool_input: &Value) -> String {
+    Sha256::digest(tool_input.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Records every decision `inner` makes, attributed to `decider`
+pub struct AuditedApprovalService {
+    inner: Arc<dyn ExecutorApprovalService>,
+    log: ApprovalAuditLog,
+    decider: ApprovalDecider,
+}
+
+impl AuditedApprovalService {
+    pub fn new(
+        inner: Arc<dyn ExecutorApprovalService>,
+        log: ApprovalAuditLog,
+        decider: ApprovalDecider,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            inner,
+            log,
+            decider,
+        })
+    }
+}
+
+#[async_trait]
+impl ExecutorApprovalService for AuditedApprovalService {
+    async fn request_tool_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalStatus, ExecutorApprovalError> {
+        let started = Instant::now();
+        let outcome = self
+            .inner
+            .request_tool_approval(tool_name, tool_input.clone(), tool_call_id)
+            .await;
+        self.log.record(
+            tool_name,
+            &tool_input,
+            tool_call_id,
+            &outcome,
+            self.decider,
+            started,
+        );
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::approvals::NoopExecutorApprovalService;
+
+    #[tokio::test]
+    async fn test_records_and_queries_decisions() {
+        let dir = std::env::temp_dir().join(format!("vk-audit-{}", uuid::Uuid::new_v4()));
+        let session_id = Uuid::new_v4();
+        let log =
+            ApprovalAuditLog::in_dir(dir.clone(), session_id).for_execution_process(Uuid::new_v4());
+
+        let service = AuditedApprovalService::new(
+            Arc::new(NoopExecutorApprovalService),
+            log.clone(),
+            ApprovalDecider::Auto,
+        );
+        let input = json!({ "command": "cargo test" });
+        service
+            .request_tool_approval("Bash", input.clone(), "call-1")
+            .await
+            .unwrap();
+        log.record(
+            "Write",
+            &json!({ "file_path": ".env" }),
+            "call-2",
+            &Ok(ApprovalStatus::Denied {
+                reason: Some("No secrets".to_string()),
+            }),
+            ApprovalDecider::Policy,
+            Instant::now(),
+        );
+
+        let all = log.query(&ApprovalAuditQuery::default()).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].session_id, session_id);
+        assert_eq!(all[0].decision, AuditDecision::Approved);
+        assert_eq!(all[0].input_hash, input_hash(&input));
+        assert_eq!(all[0].input_hash.len(), 64);
+
+        let denied = log
+            .query(&ApprovalAuditQuery {
+                decider: Some(ApprovalDecider::Policy),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].tool_name, "Write");
+        assert_eq!(denied[0].reason.as_deref(), Some("No secrets"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+//! Declarative approval policies.
+//!
+//! A profile variant can carry an [`ApprovalPolicy`]: an ordered list of
+//! rules that allow, deny or ask about a tool call based on the tool name,
+//! the file paths it touches and the shell command it runs. The first
+//! matching rule decides; calls that match no rule use the policy's default,
+//! which is to ask. Only calls that end up as `ask` reach the user.
+
+use std::{sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+use globset::Glob;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+use workspace_utils::approvals::ApprovalStatus;
+
+use super::{
+    ExecutorApprovalError, ExecutorApprovalService,
+    audit::{ApprovalAuditLog, ApprovalDecider},
+};
+
+/// Keys of tool inputs that hold file paths
+const PATH_KEYS: &[&str] = &["file_path", "path", "notebook_path", "filePath"];
+/// Keys of tool inputs that hold shell commands
+const COMMAND_KEYS: &[&str] = &["command", "cmd"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyDecision {
+    Allow,
+    Deny,
+    Ask,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, JsonSchema)]
+pub struct ApprovalRule {
+    pub decision: PolicyDecision,
+    /// Tool names the rule applies to, case-insensitive; empty matches any tool
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Globs of which one must match a file path in the tool input, as the
+    /// agent reports it (usually absolute)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Regex that must match the shell command in the tool input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Told to the agent when the rule denies a call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ApprovalRule {
+    fn matches(&self, tool_name: &str, input: &Value) -> bool {
+        if !self.tools.is_empty()
+            && !self
+                .tools
+                .iter()
+                .any(|tool| tool.eq_ignore_ascii_case(tool_name))
+        {
+            return false;
+        }
+        if !self.paths.is_empty() {
+            let paths = strings_at(input, PATH_KEYS);
+            let matched = self.paths.iter().any(|pattern| match Glob::new(pattern) {
+                Ok(glob) => {
+                    let glob = glob.compile_matcher();
+                    paths.iter().any(|path| glob.is_match(path))
+                }
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid approval path glob '{pattern}': {e}");
+                    false
+                }
+            });
+            if !matched {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.command {
+            let regex = match Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid approval command regex '{pattern}': {e}");
+                    return false;
+                }
+            };
+            if !strings_at(input, COMMAND_KEYS)
+                .iter()
+                .any(|command| regex.is_match(command))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS, JsonSchema)]
+pub struct ApprovalPolicy {
+    /// Checked in order; the first matching rule decides
+    #[serde(default)]
+    pub rules: Vec<ApprovalRule>,
+    /// Decision for calls no rule matches; asks when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<PolicyDecision>,
+}
+
+impl ApprovalPolicy {
+    /// The decision for a tool call, with the reason of the deciding rule
+    pub fn evaluate(&self, tool_name: &str, input: &Value) -> (PolicyDecision, Option<&str>) {
+        match self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(tool_name, input))
+        {
+            Some(rule) => (rule.decision, rule.reason.as_deref()),
+            None => (self.default.unwrap_or(PolicyDecision::Ask), None),
+        }
+    }
+}
+
+/// String values under `keys` in a tool input; command arrays are joined
+/// with spaces
+fn strings_at(input: &Value, keys: &[&str]) -> Vec<String> {
+    keys.iter()
+        .filter_map(|key| match input.get(*key)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Array(parts) => Some(
+                parts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Approvals that settle calls by policy and only ask `inner` about the rest
+pub struct PolicyApprovalService {
+    policy: ApprovalPolicy,
+    inner: Arc<dyn ExecutorApprovalService>,
+    /// Records the calls the policy settles; `inner` audits the rest
+    audit: Option<ApprovalAuditLog>,
+}
+
+impl PolicyApprovalService {
+    /// Wrap `inner` in `policy`, if there is one
+    pub fn wrap(
+        inner: Arc<dyn ExecutorApprovalService>,
+        policy: Option<&ApprovalPolicy>,
+        audit: Option<&ApprovalAuditLog>,
+    ) -> Arc<dyn ExecutorApprovalService> {
+        match policy {
+            Some(policy) => Arc::new(Self {
+                policy: policy.clone(),
+                inner,
+                audit: audit.cloned(),
+            }),
+            None => inner,
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutorApprovalService for PolicyApprovalService {
+    async fn request_tool_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalStatus, ExecutorApprovalError> {
+        let started = Instant::now();
+        let outcome = match self.policy.evaluate(tool_name, &tool_input) {
+            (PolicyDecision::Allow, _) => Ok(ApprovalStatus::Approved),
+            (PolicyDecision::Deny, reason) => Ok(ApprovalStatus::Denied {
+                reason: Some(reason.unwrap_or("Denied by approval policy").to_string()),
+            }),
+            (PolicyDecision::Ask, _) => {
+                return self
+                    .inner
+                    .request_tool_approval(tool_name, tool_input, tool_call_id)
+                    .await;
+            }
+        };
+        if let Some(audit) = &self.audit {
+            audit.record(
+                tool_name,
+                &tool_input,
+                tool_call_id,
+                &outcome,
+                ApprovalDecider::Policy,
+                started,
+            );
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_first_matching_rule_decides() {
+        let policy: ApprovalPolicy = serde_json::from_value(json!({
+            "rules": [
+                { "decision": "deny", "tools": ["Bash"], "command": "rm\\s+-rf", "reason": "No recursive deletes" },
+                { "decision": "ask", "tools": ["Edit", "Write"], "paths": ["**/.env*"] },
+                { "decision": "allow", "tools": ["Read", "Grep", "Edit"] },
+            ],
+            "default": "ask",
+        }))
+        .unwrap();
+
+        assert_eq!(
+            policy.evaluate("Read", &json!({ "file_path": "/repo/src/main.rs" })),
+            (PolicyDecision::Allow, None)
+        );
+        assert_eq!(
+            policy.evaluate("bash", &json!({ "command": "rm -rf target" })),
+            (PolicyDecision::Deny, Some("No recursive deletes"))
+        );
+        assert_eq!(
+            policy
+                .evaluate("Bash", &json!({ "command": ["ls", "-la"] }))
+                .0,
+            PolicyDecision::Ask
+        );
+        assert_eq!(
+            policy
+                .evaluate("Edit", &json!({ "file_path": "/repo/.env.local" }))
+                .0,
+            PolicyDecision::Ask
+        );
+        assert_eq!(
+            policy
+                .evaluate("Edit", &json!({ "file_path": "/repo/src/lib.rs" }))
+                .0,
+            PolicyDecision::Allow
+        );
+    }
+}
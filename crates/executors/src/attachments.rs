@@ -0,0 +1,180 @@
+//! Files and images attached to a prompt.
+//!
+//! Screenshot-driven tasks need the agent to see the image, not just read
+//! about it. An [`Attachment`] is either a file the agent can reach by path or
+//! an inline base64 image, e.g. one pasted into the UI. Agents that take image
+//! content blocks (Claude's stream-json) get images inline through
+//! [`Attachment::image_source`]; every other agent gets the paths of the
+//! attachments listed after the prompt, with inline images written to a
+//! worktree directory that git ignores.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{executors::ExecutorError, prompt::scratch_dir};
+
+/// Worktree directory inline images are written to
+const ATTACHMENT_DIR: &str = ".vibe-kanban/attachments";
+
+/// Image types agents accept as content blocks, by file extension
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Attachment {
+    // File relative to the agent's working directory, or absolute
+    Path {
+        path: String,
+    },
+    // Base64-encoded image
+    Image {
+        media_type: String,
+        data: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+}
+
+impl Attachment {
+    /// Media type and base64 data of an inline image, or of a file attachment
+    /// with an image extension; `None` for other files
+    pub fn image_source(
+        &self,
+        current_dir: &Path,
+    ) -> Result<Option<(String, String)>, ExecutorError> {
+        match self {
+            Self::Image {
+                media_type, data, ..
+            } => {
+                decode(data)?;
+                Ok(Some((media_type.clone(), data.clone())))
+            }
+            Self::Path { path } => {
+                let path = current_dir.join(path);
+                let Some(media_type) = media_type_of(&path) else {
+                    return Ok(None);
+                };
+                let bytes = fs::read(&path).map_err(|e| {
+                    ExecutorError::InvalidAttachment(format!("{}: {e}", path.display()))
+                })?;
+                Ok(Some((media_type.to_string(), STANDARD.encode(bytes))))
+            }
+        }
+    }
+
+    /// Path the agent can read the attachment from, writing inline images to
+    /// the worktree first
+    pub fn materialize(&self, current_dir: &Path) -> Result<PathBuf, ExecutorError> {
+        match self {
+            Self::Path { path } => Ok(current_dir.join(path)),
+            Self::Image {
+                media_type, data, ..
+            } => {
+                let extension = IMAGE_TYPES
+                    .iter()
+                    .find(|(_, ty)| ty == media_type)
+                    .map_or("bin", |(ext, _)| ext);
+                let dir = scratch_dir(current_dir, ATTACHMENT_DIR).map_err(ExecutorError::Io)?;
+                let path = dir.join(format!("{}.{extension}", Uuid::new_v4()));
+                fs::write(&path, decode(data)?).map_err(ExecutorError::Io)?;
+                Ok(path)
+            }
+        }
+    }
+}
+
+/// `prompt` followed by the paths of `attachments`, for agents that read
+/// attachments themselves
+pub fn mention_attachments(
+    prompt: &str,
+    attachments: &[Attachment],
+    current_dir: &Path,
+) -> Result<String, ExecutorError> {
+    if attachments.is_empty() {
+        return Ok(prompt.to_string());
+    }
+    let mut framed = format!("{prompt}\n\nAttached files:");
+    for attachment in attachments {
+        let path = attachment.materialize(current_dir)?;
+        framed.push_str(&format!("\n- {}", path.display()));
+    }
+    Ok(framed)
+}
+
+fn media_type_of(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    IMAGE_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, ty)| *ty)
+}
+
+fn decode(data: &str) -> Result<Vec<u8>, ExecutorError> {
+    STANDARD
+        .decode(data)
+        .map_err(|e| ExecutorError::InvalidAttachment(format!("image is not base64: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_encoding() {
+        let dir = std::env::temp_dir().join(format!("vk-attachments-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        fs::write(dir.join("docs/shot.PNG"), b"\x89PNG").unwrap();
+        fs::write(dir.join("docs/notes.txt"), "steps").unwrap();
+
+        let screenshot = Attachment::Path {
+            path: "docs/shot.PNG".to_string(),
+        };
+        assert_eq!(
+            screenshot.image_source(&dir).unwrap(),
+            Some(("image/png".to_string(), STANDARD.encode(b"\x89PNG")))
+        );
+        let notes = Attachment::Path {
+            path: "docs/notes.txt".to_string(),
+        };
+        assert_eq!(notes.image_source(&dir).unwrap(), None);
+
+        let pasted = Attachment::Image {
+            media_type: "image/jpeg".to_string(),
+            data: STANDARD.encode(b"jpeg"),
+            name: Some("pasted.jpg".to_string()),
+        };
+        let prompt = mention_attachments("Fix the layout", &[notes, pasted], &dir).unwrap();
+        let paths: Vec<&str> = prompt
+            .strip_prefix("Fix the layout\n\nAttached files:\n- ")
+            .unwrap()
+            .split("\n- ")
+            .collect();
+        assert_eq!(paths[0], dir.join("docs/notes.txt").display().to_string());
+        assert!(paths[1].ends_with(".jpg"));
+        assert_eq!(fs::read(paths[1]).unwrap(), b"jpeg");
+
+        let broken = Attachment::Image {
+            media_type: "image/png".to_string(),
+            data: "not base64!".to_string(),
+            name: None,
+        };
+        assert!(matches!(
+            broken.image_source(&dir),
+            Err(ExecutorError::InvalidAttachment(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+//! Authorization of executions at spawn time.
+//!
+//! Hosts that know who started an execution attach a [`Principal`] to its
+//! [`ExecutionEnv`]. Before the agent is spawned, the role policies in
+//! `profiles.json` and any registered [`SpawnAuthorizer`] hooks decide
+//! whether that principal may use the resolved profile at its permission
+//! level and budget. Denials are recorded in the execution's approval audit
+//! log. Executions without a principal are not checked.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use ts_rs::TS;
+
+use crate::{
+    budget::Budget,
+    env::ExecutionEnv,
+    executors::{CodingAgent, ExecutorError},
+    profile::{ExecutorConfigs, ExecutorProfileId},
+    run_template::PermissionLevel,
+};
+
+static AUTHORIZERS: LazyLock<RwLock<Vec<Arc<dyn SpawnAuthorizer>>>> =
+    LazyLock::new(Default::default);
+
+/// Who an execution runs on behalf of
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct Principal {
+    pub id: String,
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TS)]
+pub struct RolePolicy {
+    /// Profiles the role may use; empty allows any. An entry without a
+    /// variant allows every variant of its executor.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<ExecutorProfileId>,
+    /// Most permissive level the role may run agents at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_permission_level: Option<PermissionLevel>,
+    /// Most the role may spend per run; profiles must set a budget within
+    /// each of its limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_budget: Option<Budget>,
+}
+
+impl RolePolicy {
+    fn check(&self, request: &SpawnRequest<'_>) -> Result<(), Denial> {
+        let profile_allowed = self.profiles.is_empty()
+            || self.profiles.iter().any(|allowed| {
+                allowed.executor == request.profile.executor
+                    && (allowed.variant.is_none() || allowed.variant == request.profile.variant)
+            });
+        if !profile_allowed {
+            return Err(Denial::Profile(request.profile.clone()));
+        }
+
+        let requested = request.permission_level();
+        if let Some(allowed) = self.max_permission_level
+            && allowed != PermissionLevel::Profile
+            && requested > allowed
+        {
+            return Err(Denial::PermissionLevel { requested, allowed });
+        }
+
+        if let Some(limit) = &self.max_budget {
+            let budget = request.agent.run_overrides().runtime.budget.as_ref();
+            let cost_within = limit.max_cost_usd.is_none_or(|max| {
+                budget
+                    .and_then(|budget| budget.max_cost_usd)
+                    .is_some_and(|cost| cost <= max)
+            });
+            let tokens_within = limit.max_tokens.is_none_or(|max| {
+                budget
+                    .and_then(|budget| budget.max_tokens)
+                    .is_some_and(|tokens| tokens <= max)
+            });
+            if !(cost_within && tokens_within) {
+                return Err(Denial::Budget);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The execution being authorized
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnRequest<'a> {
+    pub profile: &'a ExecutorProfileId,
+    /// Agent as it will be spawned, after run templates were applied
+    pub agent: &'a CodingAgent,
+}
+
+impl SpawnRequest<'_> {
+    pub fn permission_level(&self) -> PermissionLevel {
+        PermissionLevel::of(self.agent)
+    }
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum Denial {
+    #[error("none of the principal's roles may run executions")]
+    NoRole,
+    #[error("profile {0} is not allowed")]
+    Profile(ExecutorProfileId),
+    #[error("permission level {requested:?} exceeds the allowed {allowed:?}")]
+    PermissionLevel {
+        requested: PermissionLevel,
+        allowed: PermissionLevel,
+    },
+    #[error("the profile's budget is missing or exceeds the role's")]
+    Budget,
+    #[error("{0}")]
+    Other(String),
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("{principal} is not allowed to start this execution: {denial}")]
+pub struct AuthorizationDenied {
+    pub principal: String,
+    pub denial: Denial,
+}
+
+/// A check run before every execution that has a principal
+pub trait SpawnAuthorizer: Send + Sync {
+    fn authorize(&self, principal: &Principal, request: &SpawnRequest<'_>) -> Result<(), Denial>;
+}
+
+/// Role policies keyed by role name. With no policies configured every
+/// principal is allowed; otherwise at least one of the principal's roles
+/// must allow the request.
+impl SpawnAuthorizer for HashMap<String, RolePolicy> {
+    fn authorize(&self, principal: &Principal, request: &SpawnRequest<'_>) -> Result<(), Denial> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut denial = Denial::NoRole;
+        for policy in principal.roles.iter().filter_map(|role| self.get(role)) {
+            match policy.check(request) {
+                Ok(()) => return Ok(()),
+                Err(e) => denial = e,
+            }
+        }
+        Err(denial)
+    }
+}
+
+/// Run `authorizer` before every execution, after the role policies
+pub fn register_authorizer(authorizer: Arc<dyn SpawnAuthorizer>) {
+    AUTHORIZERS.write().unwrap().push(authorizer);
+}
+
+/// Check the execution's principal, if it has one, against the role
+/// policies in `configs` and the registered authorizers, recording a denial
+/// in the execution's approval audit log
+pub fn authorize_spawn(
+    env: &ExecutionEnv,
+    configs: &ExecutorConfigs,
+    request: SpawnRequest<'_>,
+) -> Result<(), ExecutorError> {
+    let Some(principal) = &env.principal else {
+        return Ok(());
+    };
+    let result = configs.roles.authorize(principal, &request).and_then(|()| {
+        AUTHORIZERS
+            .read()
+            .unwrap()
+            .iter()
+            .try_for_each(|authorizer| authorizer.authorize(principal, &request))
+    });
+    result.map_err(|denial| {
+        tracing::warn!(
+            principal = %principal.id,
+            profile = %request.profile,
+            "Execution denied: {denial}"
+        );
+        let denied = AuthorizationDenied {
+            principal: principal.id.clone(),
+            denial,
+        };
+        if let Some(audit) = &env.approval_audit {
+            audit.record_spawn_denial(&request.profile.to_string(), &denied);
+        }
+        ExecutorError::Unauthorized(denied)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        approvals::audit::{
+            ApprovalAuditLog, ApprovalAuditQuery, ApprovalDecider, AuditDecision, SPAWN_TOOL_NAME,
+        },
+        executors::BaseCodingAgent,
+    };
+
+    fn claude(level: PermissionLevel) -> CodingAgent {
+        let mut agent = CodingAgent::ClaudeCode(serde_json::from_str("{}").unwrap());
+        level.apply(&mut agent);
+        agent
+    }
+
+    #[test]
+    fn test_role_policies() {
+        let policies: HashMap<String, RolePolicy> = serde_json::from_value(serde_json::json!({
+            "intern": {
+                "profiles": [{ "executor": "CLAUDE_CODE" }],
+                "max_permission_level": "SUPERVISED",
+            },
+            "admin": {},
+        }))
+        .unwrap();
+        let intern = Principal {
+            id: "sam".to_string(),
+            roles: vec!["intern".to_string()],
+        };
+        let profile = ExecutorProfileId::with_variant(BaseCodingAgent::ClaudeCode, "PLAN".into());
+        let autonomous = claude(PermissionLevel::Autonomous);
+        let supervised = claude(PermissionLevel::Supervised);
+
+        let request = |agent| SpawnRequest {
+            profile: &profile,
+            agent,
+        };
+        assert!(policies.authorize(&intern, &request(&supervised)).is_ok());
+        assert_eq!(
+            policies.authorize(&intern, &request(&autonomous)),
+            Err(Denial::PermissionLevel {
+                requested: PermissionLevel::Autonomous,
+                allowed: PermissionLevel::Supervised,
+            })
+        );
+
+        let amp = ExecutorProfileId::new(BaseCodingAgent::Amp);
+        let denied = SpawnRequest {
+            profile: &amp,
+            agent: &supervised,
+        };
+        assert_eq!(
+            policies.authorize(&intern, &denied),
+            Err(Denial::Profile(amp.clone()))
+        );
+
+        let admin = Principal {
+            id: "kim".to_string(),
+            roles: vec!["intern".to_string(), "admin".to_string()],
+        };
+        assert!(policies.authorize(&admin, &request(&autonomous)).is_ok());
+        let nobody = Principal {
+            id: "guest".to_string(),
+            roles: vec![],
+        };
+        assert_eq!(
+            policies.authorize(&nobody, &request(&supervised)),
+            Err(Denial::NoRole)
+        );
+    }
+
+    #[test]
+    fn test_role_budgets() {
+        let policies: HashMap<String, RolePolicy> = serde_json::from_value(serde_json::json!({
+            "intern": { "max_budget": { "max_cost_usd": 5.0 } },
+        }))
+        .unwrap();
+        let intern = Principal {
+            id: "sam".to_string(),
+            roles: vec!["intern".to_string()],
+        };
+        let profile = ExecutorProfileId::new(BaseCodingAgent::ClaudeCode);
+        let agent = |budget: serde_json::Value| -> CodingAgent {
+            serde_json::from_value(serde_json::json!({ "CLAUDE_CODE": { "budget": budget } }))
+                .unwrap()
+        };
+        let authorize = |agent: &CodingAgent| {
+            policies.authorize(
+                &intern,
+                &SpawnRequest {
+                    profile: &profile,
+                    agent,
+                },
+            )
+        };
+
+        assert!(authorize(&agent(serde_json::json!({ "max_cost_usd": 2.5 }))).is_ok());
+        assert_eq!(
+            authorize(&agent(serde_json::json!({ "max_cost_usd": 20.0 }))),
+            Err(Denial::Budget)
+        );
+        // A profile without a cost limit could spend anything
+        assert_eq!(
+            authorize(&agent(serde_json::json!({ "max_tokens": 1000 }))),
+            Err(Denial::Budget)
+        );
+        assert_eq!(
+            authorize(&claude(PermissionLevel::Profile)),
+            Err(Denial::Budget)
+        );
+    }
+
+    #[test]
+    fn test_denials_are_audited() {
+        let dir = std::env::temp_dir().join(format!("vk-authz-{}", Uuid::new_v4()));
+        let log = ApprovalAuditLog::in_dir(dir.clone(), Uuid::new_v4());
+        let env = ExecutionEnv::new()
+            .with_approval_audit(log.clone())
+            .with_principal(Principal {
+                id: "guest".to_string(),
+                roles: vec![],
+            });
+        let mut configs = ExecutorConfigs::from_defaults();
+        configs
+            .roles
+            .insert("admin".to_string(), RolePolicy::default());
+        let profile = ExecutorProfileId::new(BaseCodingAgent::ClaudeCode);
+
+        let result = authorize_spawn(
+            &env,
+            &configs,
+            SpawnRequest {
+                profile: &profile,
+                agent: &claude(PermissionLevel::Supervised),
+            },
+        );
+        assert!(matches!(result, Err(ExecutorError::Unauthorized(_))));
+
+        let entries = log.query(&ApprovalAuditQuery::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].tool_name, SPAWN_TOOL_NAME);
+        assert_eq!(entries[0].decision, AuditDecision::Denied);
+        assert_eq!(entries[0].decider, ApprovalDecider::Authorization);
+        assert!(entries[0].reason.as_deref().unwrap().contains("guest"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+//! Commit messages written from what the agent reported.
+//!
+//! Profiles with `auto_commit` set commit a run's changes under a message
+//! taken from the agent's result summary: its first line becomes the subject,
+//! the rest the body. Runs whose agent reported nothing fall back to the
+//! task's title and description. Either way a `Co-authored-by` trailer
+//! credits the executor, so the agent's share of the history can be told
+//! apart from the user's.
+
+use crate::executors::BaseCodingAgent;
+
+/// Subjects longer than this are cut at a word and repeated in full in the
+/// body
+const MAX_SUBJECT_LEN: usize = 72;
+
+/// Address co-author trailers credit executors under
+const CO_AUTHOR_EMAIL: &str = "noreply@vibekanban.com";
+
+/// Name an executor is credited under
+pub fn agent_name(executor: BaseCodingAgent) -> &'static str {
+    match executor {
+        BaseCodingAgent::ClaudeCode => "Claude Code",
+        BaseCodingAgent::ClaudeFlow => "Claude Flow",
+        BaseCodingAgent::Amp => "Amp",
+        BaseCodingAgent::Gemini => "Gemini CLI",
+        BaseCodingAgent::Codex => "Codex",
+        BaseCodingAgent::Opencode => "OpenCode",
+        BaseCodingAgent::CursorAgent => "Cursor Agent",
+        BaseCodingAgent::QwenCode => "Qwen Code",
+        BaseCodingAgent::Copilot => "GitHub Copilot",
+        BaseCodingAgent::Droid => "Droid",
+        BaseCodingAgent::ClaudeApi => "Claude API",
+    }
+}
+
+/// `Co-authored-by` trailer crediting `executor`
+pub fn co_author_trailer(executor: BaseCodingAgent) -> String {
+    format!(
+        "Co-authored-by: {} <{CO_AUTHOR_EMAIL}>",
+        agent_name(executor)
+    )
+}
+
+/// Message committing the changes of a run by `executor` that reported
+/// `summary`, or of the task `task_title` when it reported none
+pub fn commit_message(
+    summary: Option<&str>,
+    task_title: &str,
+    task_description: Option<&str>,
+    executor: BaseCodingAgent,
+) -> String {
+    let (subject, body) = match summary.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(summary) => {
+            let mut lines = summary.lines().map(str::trim).skip_while(|l| l.is_empty());
+            let first = plain(lines.next().unwrap_or_default());
+            let rest = lines.collect::<Vec<_>>().join("\n");
+            (first, rest.trim().to_string())
+        }
+        None => (
+            task_title.trim().to_string(),
+            task_description.unwrap_or_default().trim().to_string(),
+        ),
+    };
+
+    let (subject, body) = match shorten(&subject) {
+        Some(short) if body.is_empty() => (short, subject),
+        Some(short) => (short, format!("{subject}\n\n{body}")),
+        None => (subject, body),
+    };
+    let trailer = co_author_trailer(executor);
+    if body.is_empty() {
+        format!("{subject}\n\n{trailer}")
+    } else {
+        format!("{subject}\n\n{body}\n\n{trailer}")
+    }
+}
+
+/// `line` without the Markdown heading, list or emphasis markers agents
+/// like to start summaries with
+fn plain(line: &str) -> String {
+    let line = line.trim_start_matches('#').trim_start();
+    let line = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .unwrap_or(line);
+    line.replace("**", "").replace('`', "").trim().to_string()
+}
+
+/// `subject` cut at the last word that fits, if it is too long
+fn shorten(subject: &str) -> Option<String> {
+    if subject.chars().count() <= MAX_SUBJECT_LEN {
+        return None;
+    }
+    let cut: String = subject.chars().take(MAX_SUBJECT_LEN).collect();
+    let short = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => &cut,
+    };
+    Some(
+        short
+            .trim_end_matches([',', ';', ':', '.', ' '])
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_message_from_summary_or_task() {
+        let message = commit_message(
+            Some("## **Fixed** the login redirect\n\nThe callback now keeps `next`.\n"),
+            "Login redirect",
+            None,
+            BaseCodingAgent::ClaudeCode,
+        );
+        assert_eq!(
+            message,
+            "Fixed the login redirect\n\nThe callback now keeps `next`.\n\n\
+             Co-authored-by: Claude Code <noreply@vibekanban.com>"
+        );
+
+        // Without a summary the task stands in
+        let message = commit_message(
+            Some("  "),
+            "Add dark mode",
+            Some("Follow the system theme"),
+            BaseCodingAgent::Codex,
+        );
+        assert_eq!(
+            message,
+            "Add dark mode\n\nFollow the system theme\n\nCo-authored-by: Codex <noreply@vibekanban.com>"
+        );
+
+        // Long subjects are cut at a word and kept in full below
+        let long = "Refactored the configuration loader so that profiles, overrides and \
+                    environment variables are merged in one place";
+        let message = commit_message(Some(long), "", None, BaseCodingAgent::Gemini);
+        let subject = message.lines().next().unwrap();
+        assert_eq!(
+            subject,
+            "Refactored the configuration loader so that profiles, overrides and"
+        );
+        assert!(message.contains(&format!("\n\n{long}\n\n")));
+    }
+}
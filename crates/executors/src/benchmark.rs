@@ -0,0 +1,400 @@
+//! A/B benchmarks of profile variants on a fixed prompt suite.
+//!
+//! A [`Benchmark`] runs every prompt of a [`BenchmarkSuite`] with each of its
+//! variants, `repeats` times over, one run at a time so that runs don't slow
+//! each other down. Variants are usually profiles run like any coding agent,
+//! but a [`MockExecutor`] can stand in for one to try a suite out without
+//! calling a real agent. Each run goes through the same spawn governor and
+//! normalization pipeline as a [`FanOut`](crate::fan_out::FanOut) run, and is
+//! recorded with its outcome, cost and the [`RunMetrics`] the pipeline
+//! measured. The [`BenchmarkReport`] puts them side by side, per variant.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::{
+    actions::{Executable, coding_agent_initial::CodingAgentInitialRequest},
+    approvals::ExecutorApprovalService,
+    cancellation::CancelScope,
+    env::ExecutionEnv,
+    executors::StandardCodingAgentExecutor,
+    fan_out::{FanOutRun, run_target},
+    logs::{
+        NormalizedEntry, NormalizedEntryType, RunSummary,
+        pipeline::{NormalizationPipeline, NormalizationStage},
+        run_metrics::RunMetrics,
+        utils::patch::extract_normalized_entry_from_patch,
+    },
+    outcome::ExecutionOutcome,
+    profile::{ExecutorConfigs, ExecutorProfileId},
+    rate_limit::AgentProvider,
+    runtime::ExecutorsRuntime,
+    testing::MockExecutor,
+};
+
+/// How long the pipeline may take to report a run's metrics once its
+/// output has ended
+const METRICS_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn default_repeats() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkPrompt {
+    /// Name the prompt's runs are reported under
+    pub name: String,
+    pub prompt: String,
+}
+
+/// Prompts every variant of a benchmark is run on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkSuite {
+    pub prompts: Vec<BenchmarkPrompt>,
+    /// Runs per prompt and variant
+    #[serde(default = "default_repeats")]
+    pub repeats: u32,
+}
+
+/// What runs a variant's prompts
+#[derive(Debug, Clone)]
+pub enum BenchmarkAgent {
+    /// The variant's profile, as configured
+    Profile,
+    /// A scripted agent standing in for the profile
+    Mock(MockExecutor),
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchmarkVariant {
+    /// Label the variant is reported under
+    pub tag: String,
+    pub profile: ExecutorProfileId,
+    pub agent: BenchmarkAgent,
+}
+
+/// One run of a benchmark, to find a working directory for
+#[derive(Debug, Clone, Copy)]
+pub struct BenchmarkCase<'a> {
+    pub prompt: &'a BenchmarkPrompt,
+    pub variant: &'a BenchmarkVariant,
+    /// Which of the suite's repeats this is, from 0
+    pub repeat: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkRun {
+    pub prompt: String,
+    pub tag: String,
+    pub repeat: u32,
+    pub outcome: ExecutionOutcome,
+    /// `None` if the pipeline didn't report any, e.g. for a run that didn't
+    /// start
+    pub metrics: Option<RunMetrics>,
+}
+
+/// Aggregates of one variant's runs; means leave out runs without the value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariantStats {
+    pub tag: String,
+    pub runs: u32,
+    pub succeeded: u32,
+    pub success_rate: f64,
+    pub mean_cost_usd: Option<f64>,
+    pub mean_duration_ms: Option<f64>,
+    pub mean_time_to_first_output_ms: Option<f64>,
+    pub mean_tokens_per_sec: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BenchmarkReport {
+    /// Every run, in the order they ran
+    pub runs: Vec<BenchmarkRun>,
+    /// One entry per variant, in the order the variants were added
+    pub variants: Vec<VariantStats>,
+}
+
+impl BenchmarkReport {
+    fn new(runs: Vec<BenchmarkRun>, variants: &[BenchmarkVariant]) -> Self {
+        let variants = variants
+            .iter()
+            .map(|variant| {
+                let runs: Vec<_> = runs.iter().filter(|run| run.tag == variant.tag).collect();
+                let succeeded = runs.iter().filter(|run| run.outcome.is_success()).count();
+                let mean = |value: &dyn Fn(&BenchmarkRun) -> Option<f64>| {
+                    let values: Vec<_> = runs.iter().filter_map(|run| value(run)).collect();
+                    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+                };
+                let metric = |value: fn(&RunMetrics) -> Option<f64>| {
+                    mean(&|run: &BenchmarkRun| run.metrics.as_ref().and_then(value))
+                };
+                VariantStats {
+                    tag: variant.tag.clone(),
+                    runs: runs.len() as u32,
+                    succeeded: succeeded as u32,
+                    success_rate: if runs.is_empty() {
+                        0.0
+                    } else {
+                        succeeded as f64 / runs.len() as f64
+                    },
+                    mean_cost_usd: mean(&|run| run.outcome.stats.total_cost_usd),
+                    mean_duration_ms: mean(&|run| {
+                        run.outcome.stats.duration_ms.map(|ms| ms as f64)
+                    }),
+                    mean_time_to_first_output_ms: metric(|metrics| {
+                        metrics.time_to_first_output_ms.map(|ms| ms as f64)
+                    }),
+                    mean_tokens_per_sec: metric(|metrics| metrics.tokens_per_sec),
+                }
+            })
+            .collect();
+        Self { runs, variants }
+    }
+}
+
+/// Builder for a benchmark of several variants on one suite
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    suite: BenchmarkSuite,
+    variants: Vec<BenchmarkVariant>,
+}
+
+impl Benchmark {
+    pub fn new(suite: BenchmarkSuite) -> Self {
+        Self {
+            suite,
+            variants: Vec::new(),
+        }
+    }
+
+    /// Run the suite with `profile`, tagged with the profile
+    pub fn profile(self, profile: ExecutorProfileId) -> Self {
+        let tag = profile.to_string();
+        self.variant(tag, profile, BenchmarkAgent::Profile)
+    }
+
+    /// Run the suite with `agent` for `profile`, tagged with `tag`
+    pub fn variant(
+        mut self,
+        tag: impl Into<String>,
+        profile: ExecutorProfileId,
+        agent: BenchmarkAgent,
+    ) -> Self {
+        self.variants.push(BenchmarkVariant {
+            tag: tag.into(),
+            profile,
+            agent,
+        });
+        self
+    }
+
+    /// Run every prompt with every variant, in the directory `dir_for` gives
+    /// for the run, and report how they did. Firing `cancel` stops the run
+    /// going and skips the rest.
+    pub async fn run(
+        &self,
+        runtime: &ExecutorsRuntime,
+        approvals: Arc<dyn ExecutorApprovalService>,
+        env: &ExecutionEnv,
+        cancel: &CancelScope,
+        dir_for: impl Fn(&BenchmarkCase<'_>) -> PathBuf,
+    ) -> BenchmarkReport {
+        let configs = ExecutorConfigs::get_cached_for(env.tenant.as_ref());
+        let mut runs = Vec::new();
+        'suite: for prompt in &self.suite.prompts {
+            for repeat in 0..self.suite.repeats {
+                for variant in &self.variants {
+                    if cancel.is_cancelled() {
+                        break 'suite;
+                    }
+                    let case = BenchmarkCase {
+                        prompt,
+                        variant,
+                        repeat,
+                    };
+                    let run = FanOutRun {
+                        tag: variant.tag.clone(),
+                        profile: variant.profile.clone(),
+                        dir: dir_for(&case),
+                        msg_store: Arc::new(MsgStore::new()),
+                    };
+                    let result = match &variant.agent {
+                        BenchmarkAgent::Profile => {
+                            let request = CodingAgentInitialRequest {
+                                prompt: prompt.prompt.clone(),
+                                executor_profile_id: run.profile.clone(),
+                                working_dir: None,
+                                cwd_relative: None,
+                                run_template: None,
+                                attachments: Vec::new(),
+                                review: None,
+                            };
+                            let agent = configs.get_coding_agent(&run.profile);
+                            let spawn = async {
+                                runtime
+                                    .rate_limiter()
+                                    .wait(AgentProvider::from(run.profile.executor), cancel)
+                                    .await?;
+                                request
+                                    .spawn(&run.dir, approvals.clone(), env, cancel)
+                                    .await
+                            };
+                            run_target(
+                                &run,
+                                runtime,
+                                &configs.spawn_limits,
+                                spawn,
+                                |msg_store| {
+                                    if let Some(agent) = agent {
+                                        agent.normalize_logs_with_pipeline(
+                                            msg_store, &run.dir, cancel,
+                                        );
+                                    }
+                                },
+                                cancel,
+                            )
+                            .await
+                        }
+                        BenchmarkAgent::Mock(agent) => {
+                            run_target(
+                                &run,
+                                runtime,
+                                &configs.spawn_limits,
+                                agent.spawn(&run.dir, &prompt.prompt, env),
+                                |msg_store| {
+                                    NormalizationPipeline::new(&[
+                                        NormalizationStage::DeltaStreaming,
+                                    ])
+                                    .with_cancel(cancel.clone())
+                                    .run(msg_store, |staging| {
+                                        agent.normalize_logs(staging, &run.dir)
+                                    })
+                                },
+                                cancel,
+                            )
+                            .await
+                        }
+                    };
+                    runs.push(BenchmarkRun {
+                        prompt: prompt.name.clone(),
+                        tag: result.tag,
+                        repeat,
+                        outcome: result.outcome,
+                        metrics: reported_metrics(&run.msg_store).await,
+                    });
+                }
+            }
+        }
+        BenchmarkReport::new(runs, &self.variants)
+    }
+}
+
+/// Metrics the pipeline reported in a run's store, waiting a while for them
+/// to follow the end of the output
+async fn reported_metrics(msg_store: &MsgStore) -> Option<RunMetrics> {
+    let mut stream = msg_store.history_plus_stream();
+    let found = async {
+        while let Some(Ok(msg)) = stream.next().await {
+            let LogMsg::JsonPatch(patch) = msg else {
+                continue;
+            };
+            if let Some((
+                _,
+                NormalizedEntry {
+                    entry_type:
+                        NormalizedEntryType::RunSummary {
+                            summary:
+                                RunSummary {
+                                    metrics: Some(metrics),
+                                    ..
+                                },
+                        },
+                    ..
+                },
+            )) = extract_normalized_entry_from_patch(&patch)
+            {
+                return Some(metrics);
+            }
+        }
+        None
+    };
+    tokio::time::timeout(METRICS_TIMEOUT, found)
+        .await
+        .ok()
+        .flatten()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{approvals::NoopExecutorApprovalService, executors::BaseCodingAgent};
+
+    #[tokio::test]
+    async fn test_variants_compared_on_suite() {
+        let suite: BenchmarkSuite = serde_json::from_str(
+            r#"{"prompts": [
+                {"name": "fix", "prompt": "Fix the flaky test"},
+                {"name": "docs", "prompt": "Document the API"}
+            ], "repeats": 2}"#,
+        )
+        .unwrap();
+        let profile = ExecutorProfileId::new(BaseCodingAgent::ClaudeCode);
+        let fast = MockExecutor::new()
+            .stdout(format!("{}\n", "x".repeat(400)))
+            .after(Duration::from_millis(100))
+            .stdout("done\n");
+        let slow = MockExecutor::new()
+            .after(Duration::from_millis(300))
+            .stdout("done\n")
+            .exit_code(1);
+        let benchmark = Benchmark::new(suite)
+            .variant("fast", profile.clone(), BenchmarkAgent::Mock(fast.clone()))
+            .variant("slow", profile, BenchmarkAgent::Mock(slow));
+
+        let report = benchmark
+            .run(
+                &ExecutorsRuntime::new(),
+                Arc::new(NoopExecutorApprovalService),
+                &ExecutionEnv::new(),
+                &CancelScope::new(),
+                |_| std::env::temp_dir(),
+            )
+            .await;
+
+        let order: Vec<_> = report
+            .runs
+            .iter()
+            .map(|run| (run.prompt.as_str(), run.repeat, run.tag.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("fix", 0, "fast"),
+                ("fix", 0, "slow"),
+                ("fix", 1, "fast"),
+                ("fix", 1, "slow"),
+                ("docs", 0, "fast"),
+                ("docs", 0, "slow"),
+                ("docs", 1, "fast"),
+                ("docs", 1, "slow"),
+            ]
+        );
+        assert_eq!(fast.calls()[2].prompt, "Document the API");
+        assert!(report.runs.iter().all(|run| run.metrics.is_some()));
+
+        let [fast, slow] = report.variants.as_slice() else {
+            panic!("expected two variants, got {:?}", report.variants);
+        };
+        assert_eq!((fast.runs, fast.succeeded, fast.success_rate), (4, 4, 1.0));
+        assert_eq!((slow.runs, slow.succeeded, slow.success_rate), (4, 0, 0.0));
+        assert!(
+            slow.mean_time_to_first_output_ms.unwrap()
+                > fast.mean_time_to_first_output_ms.unwrap() + 200.0
+        );
+        assert!(fast.mean_tokens_per_sec.is_some());
+        assert_eq!(fast.mean_cost_usd, None);
+    }
+}
@@ -0,0 +1,409 @@
+//! Cost and token budgets for executions.
+//!
+//! A swarm left to itself can spend a lot before anyone looks. Profiles set a
+//! [`Budget`] in US dollars, tokens or both, and a [`UsageTracker`] totals
+//! what an execution has used from the usage its agent reports on stdout:
+//! per-message token counts and running cost in Claude Code and claude-flow
+//! stream-json, token counts in Codex events. Like the runtime limits, the
+//! budget is enforced by a watchdog on the exit signal: once a limit is
+//! crossed it fails the run with [`FailureReason::BudgetExceeded`], and the
+//! container stops the process.
+//!
+//! A [tenant](crate::tenant)'s profiles can also set a `tenant_budget` for
+//! all of its runs together. What each tenant's runs used is added up in
+//! [`TenantUsage`], kept in `usage.json` in the tenant's directory; deleting
+//! it starts the count over.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+use futures::{FutureExt, StreamExt, future::BoxFuture};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+use ts_rs::TS;
+
+use crate::{
+    executors::{ExecutorError, ExecutorExitResult, SpawnedChild},
+    logs::utils::find_key,
+    outcome::{FailureReason, OutcomeStats, RunSummary},
+    stdout_dup::duplicate_stdout,
+    tenant::TenantId,
+};
+
+static TENANT_USAGE: LazyLock<TenantUsage> = LazyLock::new(|| TenantUsage {
+    persist: true,
+    ..TenantUsage::default()
+});
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
+pub struct Budget {
+    /// Stop the run once it has cost more than this many US dollars, as
+    /// reported by the agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+    /// Stop the run once it has used more than this many tokens, input
+    /// (cached included) and output together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+}
+
+/// What an execution has used so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub tokens: u64,
+    pub cost_usd: Option<f64>,
+}
+
+impl Usage {
+    /// Add what was used between `before` and `after`, two readings of the
+    /// same run
+    fn add_since(&mut self, before: &Usage, after: &Usage) {
+        self.tokens = self
+            .tokens
+            .saturating_add(after.tokens.saturating_sub(before.tokens));
+        if let Some(cost) = after.cost_usd {
+            let spent = cost - before.cost_usd.unwrap_or(0.0);
+            self.cost_usd = Some(self.cost_usd.unwrap_or(0.0) + spent.max(0.0));
+        }
+    }
+}
+
+/// What each tenant's runs have used together
+#[derive(Default)]
+pub struct TenantUsage {
+    /// Whether totals are kept in the tenants' directories
+    persist: bool,
+    totals: Mutex<HashMap<TenantId, Usage>>,
+}
+
+impl TenantUsage {
+    pub fn global() -> &'static TenantUsage {
+        &TENANT_USAGE
+    }
+
+    fn path(tenant: &TenantId) -> PathBuf {
+        tenant.dir().join("usage.json")
+    }
+
+    /// What `tenant`'s runs have used so far
+    pub fn used(&self, tenant: &TenantId) -> Usage {
+        self.update(tenant, |_| ())
+    }
+
+    /// Count what a run of `tenant` used between two readings, returning
+    /// the tenant's new total
+    fn record(&self, tenant: &TenantId, before: &Usage, after: &Usage) -> Usage {
+        self.update(tenant, |total| total.add_since(before, after))
+    }
+
+    fn update(&self, tenant: &TenantId, change: impl FnOnce(&mut Usage)) -> Usage {
+        let mut totals = self.totals.lock().unwrap();
+        let total = totals.entry(tenant.clone()).or_insert_with(|| {
+            if !self.persist {
+                return Usage::default();
+            }
+            std::fs::read_to_string(Self::path(tenant))
+                .ok()
+                .and_then(|raw| serde_json::from_str(&raw).ok())
+                .unwrap_or_default()
+        });
+        let before = *total;
+        change(total);
+        if self.persist
+            && *total != before
+            && let Err(e) = std::fs::create_dir_all(tenant.dir())
+                .and_then(|_| Ok(serde_json::to_string(total)?))
+                .and_then(|raw| std::fs::write(Self::path(tenant), raw))
+        {
+            tracing::warn!("Failed to save the usage of tenant {tenant}: {e}");
+        }
+        *total
+    }
+}
+
+/// Usage of one execution, from the agent's stdout
+#[derive(Debug, Clone, Default)]
+pub struct UsageTracker {
+    /// Tokens per model message; a message is reported again with each of
+    /// its content blocks
+    messages: HashMap<String, u64>,
+    /// Running total the agent reported itself, if it reports one
+    reported_tokens: Option<u64>,
+    cost_usd: Option<f64>,
+}
+
+impl UsageTracker {
+    pub fn usage(&self) -> Usage {
+        let counted = self.messages.values().sum::<u64>();
+        Usage {
+            tokens: self.reported_tokens.unwrap_or(0).max(counted),
+            cost_usd: self.cost_usd,
+        }
+    }
+
+    /// Take the usage reported in a line of stdout, if it reports any
+    pub fn apply_line(&mut self, line: &str) {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            return;
+        };
+        // Running cost, in Claude Code and claude-flow result events
+        if let Some(cost) = ["total_cost_usd", "totalCostUsd", "cost_usd"]
+            .iter()
+            .find_map(|key| event.get(key).and_then(Value::as_f64))
+        {
+            self.cost_usd = Some(self.cost_usd.map_or(cost, |known| known.max(cost)));
+        }
+        // Codex token_count events carry the session's running total
+        if let Some(total) = find_key(&event, "total_token_usage") {
+            self.report_tokens(tokens(total));
+        }
+        // Result events carry the run's total, assistant messages their own
+        if let Some(usage) = event.get("usage") {
+            self.report_tokens(tokens(usage));
+        }
+        if let Some(message) = event.get("message")
+            && let (Some(id), Some(usage)) = (
+                message.get("id").and_then(Value::as_str),
+                message.get("usage"),
+            )
+        {
+            self.messages.insert(id.to_string(), tokens(usage));
+        }
+    }
+
+    fn report_tokens(&mut self, total: u64) {
+        self.reported_tokens = Some(self.reported_tokens.unwrap_or(0).max(total));
+    }
+}
+
+/// Tokens in a usage object, in Anthropic's or OpenAI's field names
+fn tokens(usage: &Value) -> u64 {
+    let field = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+    if let Some(total) = usage.get("total_tokens").and_then(Value::as_u64) {
+        return total;
+    }
+    field("input_tokens")
+        + field("cache_creation_input_tokens")
+        + field("cache_read_input_tokens")
+        + field("output_tokens")
+}
+
+impl Budget {
+    pub fn is_unbounded(&self) -> bool {
+        self.max_cost_usd.is_none() && self.max_tokens.is_none()
+    }
+
+    /// Why `usage` is over budget, if it is
+    pub fn exceeded_by(&self, usage: &Usage) -> Option<String> {
+        self.exceeded(usage, "the run")
+    }
+
+    /// Why `usage`, of `what`, is over budget, if it is
+    fn exceeded(&self, usage: &Usage, what: &str) -> Option<String> {
+        if let (Some(max), Some(cost)) = (self.max_cost_usd, usage.cost_usd)
+            && cost > max
+        {
+            return Some(format!(
+                "Budget exceeded: {what} cost ${cost:.2}, over its ${max:.2} limit"
+            ));
+        }
+        if let Some(max) = self.max_tokens
+            && usage.tokens > max
+        {
+            return Some(format!(
+                "Budget exceeded: {what} used {} tokens, over its {max} token limit",
+                usage.tokens
+            ));
+        }
+        None
+    }
+}
+
+/// Start enforcing `budget` on a freshly spawned child and, for a run of a
+/// tenant, the tenant's budget on what all of its runs used.
+///
+/// The child's exit signal is replaced by one that resolves either with the
+/// executor's own result or with a [`FailureReason::BudgetExceeded`] failure.
+/// Usage is read from stdout, which is duplicated rather than consumed.
+pub fn enforce(
+    spawned: &mut SpawnedChild,
+    budget: Option<&Budget>,
+    tenant: Option<(&TenantId, &Budget)>,
+) -> Result<(), ExecutorError> {
+    let budget = budget.filter(|budget| !budget.is_unbounded()).cloned();
+    let tenant = tenant
+        .filter(|(_, budget)| !budget.is_unbounded())
+        .map(|(tenant, budget)| (tenant.clone(), budget.clone()));
+    if budget.is_none() && tenant.is_none() {
+        return Ok(());
+    }
+
+    let mut stdout = duplicate_stdout(&mut spawned.child)?;
+    let (mut exit_tx, exit_rx) = oneshot::channel();
+    let executor_exit = spawned.exit_signal.replace(exit_rx);
+    let has_executor_signal = executor_exit.is_some();
+    let mut executor_exit: BoxFuture<'static, _> = match executor_exit {
+        Some(rx) => rx.boxed(),
+        None => std::future::pending().boxed(),
+    };
+
+    tokio::spawn(async move {
+        let mut tracker = UsageTracker::default();
+        // Usage already added to the tenant's total
+        let mut counted = Usage::default();
+        let mut line = String::new();
+        let mut reading = true;
+        // A tenant over its budget gets no new runs
+        let mut exceeded = tenant.as_ref().and_then(|(tenant, tenant_budget)| {
+            let used = TenantUsage::global().used(tenant);
+            tenant_budget.exceeded(&used, &format!("tenant {tenant}'s runs"))
+        });
+        while exceeded.is_none() {
+            tokio::select! {
+                // Container stopped listening (process exited or was stopped)
+                _ = exit_tx.closed() => return,
+                result = &mut executor_exit => {
+                    if let Ok(result) = result {
+                        let _ = exit_tx.send(result);
+                    }
+                    return;
+                }
+                chunk = stdout.next(), if reading => {
+                    match chunk {
+                        Some(Ok(chunk)) => line.push_str(&chunk),
+                        _ => {
+                            // Without an executor signal, stdout EOF means
+                            // the process is exiting on its own
+                            if !has_executor_signal {
+                                return;
+                            }
+                            reading = false;
+                            line.push('\n');
+                        }
+                    }
+                    while let Some(end) = line.find('\n') {
+                        tracker.apply_line(line[..end].trim());
+                        line.drain(..=end);
+                    }
+                    let usage = tracker.usage();
+                    exceeded = budget.as_ref().and_then(|budget| budget.exceeded_by(&usage));
+                    if let Some((tenant, tenant_budget)) = &tenant {
+                        let used = TenantUsage::global().record(tenant, &counted, &usage);
+                        counted = usage;
+                        exceeded = exceeded.or_else(|| {
+                            tenant_budget.exceeded(&used, &format!("tenant {tenant}'s runs"))
+                        });
+                    }
+                }
+            }
+        }
+        let Some(reason) = exceeded else { return };
+        tracing::warn!("{reason}; stopping executor");
+        let _ = exit_tx.send(ExecutorExitResult::Failed(FailureReason::BudgetExceeded(
+            RunSummary {
+                summary: Some(reason),
+                stats: OutcomeStats {
+                    total_cost_usd: tracker.usage().cost_usd,
+                    ..Default::default()
+                },
+            },
+        )));
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_totals_from_agent_events() {
+        let mut tracker = UsageTracker::default();
+        // Claude Code reports a message once per content block
+        let assistant = r#"{"type":"assistant","message":{"id":"msg_1","usage":{"input_tokens":10,"cache_read_input_tokens":1000,"output_tokens":50}}}"#;
+        tracker.apply_line(assistant);
+        tracker.apply_line(assistant);
+        tracker.apply_line(
+            r#"{"type":"assistant","message":{"id":"msg_2","usage":{"input_tokens":20,"output_tokens":30}}}"#,
+        );
+        tracker.apply_line("not json");
+        assert_eq!(
+            tracker.usage(),
+            Usage {
+                tokens: 1110,
+                cost_usd: None,
+            }
+        );
+
+        tracker.apply_line(r#"{"type":"result","total_cost_usd":0.42,"usage":{"input_tokens":40,"cache_read_input_tokens":1100,"output_tokens":90}}"#);
+        assert_eq!(tracker.usage().tokens, 1230);
+        assert_eq!(tracker.usage().cost_usd, Some(0.42));
+
+        let budget = Budget {
+            max_cost_usd: Some(0.25),
+            max_tokens: None,
+        };
+        assert_eq!(
+            budget.exceeded_by(&tracker.usage()).as_deref(),
+            Some("Budget exceeded: the run cost $0.42, over its $0.25 limit")
+        );
+
+        let mut codex = UsageTracker::default();
+        codex.apply_line(r#"{"method":"codex/event/token_count","params":{"msg":{"type":"token_count","info":{"total_token_usage":{"input_tokens":900,"output_tokens":200,"total_tokens":1100}}}}}"#);
+        let budget = Budget {
+            max_cost_usd: None,
+            max_tokens: Some(1000),
+        };
+        assert!(budget.exceeded_by(&codex.usage()).is_some());
+        assert!(budget.exceeded_by(&Usage::default()).is_none());
+    }
+
+    #[test]
+    fn test_tenant_usage_adds_up_runs() {
+        let ledger = TenantUsage::default();
+        let acme = TenantId::new("acme").unwrap();
+        let first = Usage {
+            tokens: 400,
+            cost_usd: Some(0.1),
+        };
+        ledger.record(&acme, &Usage::default(), &first);
+        // A later reading of the same run only adds what it used since
+        ledger.record(
+            &acme,
+            &first,
+            &Usage {
+                tokens: 700,
+                cost_usd: Some(0.25),
+            },
+        );
+        let used = ledger.record(
+            &acme,
+            &Usage::default(),
+            &Usage {
+                tokens: 500,
+                cost_usd: None,
+            },
+        );
+        assert_eq!(used.tokens, 1200);
+        assert!((used.cost_usd.unwrap() - 0.25).abs() < 1e-9);
+        assert_eq!(
+            ledger.used(&TenantId::new("globex").unwrap()),
+            Usage::default()
+        );
+
+        let budget = Budget {
+            max_cost_usd: None,
+            max_tokens: Some(1000),
+        };
+        assert_eq!(
+            budget.exceeded(&used, "tenant acme's runs").as_deref(),
+            Some("Budget exceeded: tenant acme's runs used 1200 tokens, over its 1000 token limit")
+        );
+    }
+}
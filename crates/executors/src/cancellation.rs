@@ -0,0 +1,236 @@
+//! Cancellation of executions and the work chained from them.
+//!
+//! A [`CancelScope`] wraps a [`CancellationToken`] and records why it was
+//! cancelled. Hosts keep one root scope per task and derive a child scope for
+//! each execution; cancelling the root stops every execution in the tree,
+//! including ones still waiting to spawn or retry, and the children report
+//! [`CancelReason::ParentCancelled`] unless they were cancelled themselves.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::executors::ExecutorError;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    UserRequested,
+    Timeout,
+    ParentCancelled,
+    Shutdown,
+    LoopDetected,
+}
+
+impl std::fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CancelReason::UserRequested => "cancelled by user",
+            CancelReason::Timeout => "timed out",
+            CancelReason::ParentCancelled => "parent task cancelled",
+            CancelReason::Shutdown => "shutting down",
+            CancelReason::LoopDetected => "stuck in a loop",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelScope {
+    token: CancellationToken,
+    reason: Arc<OnceLock<CancelReason>>,
+}
+
+impl CancelScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A scope that is cancelled along with this one, but can also be
+    /// cancelled on its own
+    pub fn child(&self) -> Self {
+        Self {
+            token: self.token.child_token(),
+            reason: Arc::default(),
+        }
+    }
+
+    /// Cancel the scope and its children. Only the first reason is kept.
+    pub fn cancel(&self, reason: CancelReason) {
+        let _ = self.reason.set(reason);
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub fn reason(&self) -> Option<CancelReason> {
+        match self.reason.get() {
+            Some(reason) => Some(*reason),
+            None => self.is_cancelled().then_some(CancelReason::ParentCancelled),
+        }
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Resolves once the scope is cancelled
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Fail with [`ExecutorError::Cancelled`] if the scope was cancelled
+    pub fn check(&self) -> Result<(), ExecutorError> {
+        match self.reason() {
+            Some(reason) => Err(ExecutorError::Cancelled(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Run `future` unless the scope is cancelled first
+    pub async fn run<T, F>(&self, future: F) -> Result<T, ExecutorError>
+    where
+        F: Future<Output = Result<T, ExecutorError>>,
+    {
+        self.check()?;
+        tokio::select! {
+            biased;
+            _ = self.cancelled() => Err(ExecutorError::Cancelled(
+                self.reason().unwrap_or(CancelReason::ParentCancelled),
+            )),
+            result = future => result,
+        }
+    }
+}
+
+/// Cancel scopes of running executions, grouped into trees by a root id
+/// such as the workspace they run in
+#[derive(Debug, Clone, Default)]
+pub struct CancelTree {
+    roots: Arc<Mutex<HashMap<Uuid, CancelScope>>>,
+    executions: Arc<Mutex<HashMap<Uuid, CancelScope>>>,
+}
+
+impl CancelTree {
+    /// Register an execution under `root_id`. A root that was cancelled
+    /// earlier is replaced, so a new run after a stop starts uncancelled.
+    pub fn start_execution(&self, root_id: Uuid, execution_id: Uuid) -> CancelScope {
+        let scope = {
+            let mut roots = self.roots.lock().unwrap();
+            let root = roots.entry(root_id).or_default();
+            if root.is_cancelled() {
+                *root = CancelScope::new();
+            }
+            root.child()
+        };
+        self.executions
+            .lock()
+            .unwrap()
+            .insert(execution_id, scope.clone());
+        scope
+    }
+
+    pub fn execution(&self, execution_id: &Uuid) -> Option<CancelScope> {
+        self.executions.lock().unwrap().get(execution_id).cloned()
+    }
+
+    pub fn cancel_execution(&self, execution_id: &Uuid, reason: CancelReason) {
+        if let Some(scope) = self.execution(execution_id) {
+            scope.cancel(reason);
+        }
+    }
+
+    /// Cancel every execution under `root_id`
+    pub fn cancel_tree(&self, root_id: &Uuid, reason: CancelReason) {
+        if let Some(root) = self.roots.lock().unwrap().get(root_id) {
+            root.cancel(reason);
+        }
+    }
+
+    pub fn cancel_all(&self, reason: CancelReason) {
+        for root in self.roots.lock().unwrap().values() {
+            root.cancel(reason);
+        }
+    }
+
+    /// Ids of executions that have not finished yet
+    pub fn running(&self) -> Vec<Uuid> {
+        self.executions.lock().unwrap().keys().copied().collect()
+    }
+
+    /// Forget a finished execution, returning its scope
+    pub fn finish_execution(&self, execution_id: &Uuid) -> Option<CancelScope> {
+        self.executions.lock().unwrap().remove(execution_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancelling_root_cancels_tree() {
+        let root = CancelScope::new();
+        let execution = root.child();
+        let sibling = root.child();
+
+        sibling.cancel(CancelReason::Timeout);
+        assert!(!root.is_cancelled());
+        assert!(!execution.is_cancelled());
+        assert_eq!(sibling.reason(), Some(CancelReason::Timeout));
+
+        let pending = {
+            let execution = execution.clone();
+            tokio::spawn(async move {
+                execution
+                    .run(async {
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        root.cancel(CancelReason::UserRequested);
+        root.cancel(CancelReason::Shutdown);
+
+        assert_eq!(root.reason(), Some(CancelReason::UserRequested));
+        assert_eq!(execution.reason(), Some(CancelReason::ParentCancelled));
+        assert!(matches!(
+            pending.await.unwrap(),
+            Err(ExecutorError::Cancelled(CancelReason::ParentCancelled))
+        ));
+        assert!(matches!(
+            execution.check(),
+            Err(ExecutorError::Cancelled(_))
+        ));
+    }
+
+    #[test]
+    fn test_cancel_tree_restarts_cancelled_roots() {
+        let tree = CancelTree::default();
+        let (workspace, first, second) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let first_scope = tree.start_execution(workspace, first);
+        tree.cancel_tree(&workspace, CancelReason::UserRequested);
+        assert_eq!(first_scope.reason(), Some(CancelReason::ParentCancelled));
+
+        let second_scope = tree.start_execution(workspace, second);
+        assert!(!second_scope.is_cancelled());
+        tree.cancel_execution(&second, CancelReason::Timeout);
+        assert_eq!(
+            tree.finish_execution(&second).unwrap().reason(),
+            Some(CancelReason::Timeout)
+        );
+        assert!(tree.execution(&second).is_none());
+    }
+}
@@ -0,0 +1,400 @@
+//! Checkpoints of the worktree taken before risky tool calls.
+//!
+//! When a [`CheckpointProvider`] is attached to an execution, the approvals
+//! layer snapshots the worktree right after a file-editing or shell tool call
+//! is approved and before the agent runs it. [`GitCheckpoints`] stores each
+//! snapshot as a commit built from a temporary index, so tracked changes and
+//! untracked files are captured without touching the branch, the real index
+//! or the working tree. The commits are kept alive by per-worktree refs under
+//! `refs/worktree/vibe-kanban/checkpoints/`, and rolling back restores the
+//! working tree to a snapshot.
+
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::process::Command;
+use ts_rs::TS;
+use workspace_utils::{approvals::ApprovalStatus, shell::resolve_executable_path};
+
+use crate::approvals::{ExecutorApprovalError, ExecutorApprovalService};
+
+const CHECKPOINT_REF_PREFIX: &str = "refs/worktree/vibe-kanban/checkpoints/";
+/// Oldest checkpoints beyond this many are dropped
+const MAX_CHECKPOINTS: usize = 50;
+/// Tools that edit files or run commands, compared case-insensitively
+const RISKY_TOOLS: &[&str] = &[
+    "edit",
+    "multiedit",
+    "write",
+    "notebookedit",
+    "bash",
+    "shell",
+    "apply_patch",
+];
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("git executable not found")]
+    GitNotAvailable,
+    #[error("git {command} failed: {stderr}")]
+    Git { command: String, stderr: String },
+    #[error("Checkpoint {0} not found")]
+    NotFound(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// A snapshot of the worktree taken before a tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct Checkpoint {
+    pub id: String,
+    /// Tool call the checkpoint was taken for
+    pub tool_name: String,
+    pub tool_call_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait CheckpointProvider: Send + Sync {
+    /// Snapshot the worktree before `tool_name` runs
+    async fn create(
+        &self,
+        tool_name: &str,
+        tool_call_id: &str,
+    ) -> Result<Checkpoint, CheckpointError>;
+
+    /// Checkpoints that can be rolled back to, newest first
+    async fn list(&self) -> Result<Vec<Checkpoint>, CheckpointError>;
+
+    /// Restore the worktree to the state captured by `checkpoint_id`
+    async fn rollback(&self, checkpoint_id: &str) -> Result<(), CheckpointError>;
+}
+
+/// Whether a tool call can change the worktree and deserves a checkpoint
+pub fn is_risky_tool(tool_name: &str) -> bool {
+    let tool_name = tool_name.to_ascii_lowercase();
+    RISKY_TOOLS.contains(&tool_name.as_str())
+}
+
+/// Checkpoints stored as commits in the git repositories of a workspace
+#[derive(Debug, Clone)]
+pub struct GitCheckpoints {
+    worktrees: Vec<PathBuf>,
+}
+
+impl GitCheckpoints {
+    /// Checkpoint every repository worktree of a workspace together
+    pub fn new(worktrees: Vec<PathBuf>) -> Self {
+        Self { worktrees }
+    }
+
+    async fn snapshot(
+        &self,
+        worktree: &Path,
+        checkpoint: &Checkpoint,
+    ) -> Result<(), CheckpointError> {
+        let index = TempIndex::new();
+        git(worktree, ["read-tree", "HEAD"], Some(&index.path)).await?;
+        git(worktree, ["add", "-A"], Some(&index.path)).await?;
+        let tree = git(worktree, ["write-tree"], Some(&index.path)).await?;
+        let message = format!(
+            "vibe-kanban checkpoint\n\n{}",
+            serde_json::to_string(checkpoint)?
+        );
+        let commit = git(
+            worktree,
+            [
+                "-c",
+                "user.name=Vibe Kanban",
+                "-c",
+                "user.email=noreply@vibekanban.com",
+                "commit-tree",
+                tree.as_str(),
+                "-p",
+                "HEAD",
+                "-m",
+                message.as_str(),
+            ],
+            None,
+        )
+        .await?;
+        let refname = format!("{CHECKPOINT_REF_PREFIX}{}", checkpoint.id);
+        git(
+            worktree,
+            ["update-ref", refname.as_str(), commit.as_str()],
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn list_in(worktree: &Path) -> Result<Vec<Checkpoint>, CheckpointError> {
+        let out = git(
+            worktree,
+            [
+                "for-each-ref",
+                "--format=%(contents:body)",
+                CHECKPOINT_REF_PREFIX,
+            ],
+            None,
+        )
+        .await?;
+        let mut checkpoints: Vec<Checkpoint> = out
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        checkpoints.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+        Ok(checkpoints)
+    }
+
+    async fn prune(&self, worktree: &Path) -> Result<(), CheckpointError> {
+        for old in Self::list_in(worktree).await?.iter().skip(MAX_CHECKPOINTS) {
+            let refname = format!("{CHECKPOINT_REF_PREFIX}{}", old.id);
+            git(worktree, ["update-ref", "-d", refname.as_str()], None).await?;
+        }
+        Ok(())
+    }
+
+    async fn restore(&self, worktree: &Path, checkpoint_id: &str) -> Result<(), CheckpointError> {
+        let refname = format!("{CHECKPOINT_REF_PREFIX}{checkpoint_id}");
+        if git(
+            worktree,
+            ["rev-parse", "--verify", "-q", refname.as_str()],
+            None,
+        )
+        .await
+        .is_err()
+        {
+            // The repository had no checkpoint taken at that point
+            return Ok(());
+        }
+        // Stage the current state in a temporary index, then switch it to the
+        // snapshot, which updates files that changed and removes files that
+        // did not exist yet. Ignored files are left alone.
+        let index = TempIndex::new();
+        git(worktree, ["read-tree", "HEAD"], Some(&index.path)).await?;
+        git(worktree, ["add", "-A"], Some(&index.path)).await?;
+        let current = git(worktree, ["write-tree"], Some(&index.path)).await?;
+        let target = format!("{refname}^{{tree}}");
+        git(
+            worktree,
+            ["read-tree", "-m", "-u", current.as_str(), target.as_str()],
+            Some(&index.path),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CheckpointProvider for GitCheckpoints {
+    async fn create(
+        &self,
+        tool_name: &str,
+        tool_call_id: &str,
+    ) -> Result<Checkpoint, CheckpointError> {
+        let created_at = Utc::now();
+        let checkpoint = Checkpoint {
+            id: format!(
+                "{}-{}",
+                created_at.format("%Y%m%dT%H%M%S%3f"),
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            ),
+            tool_name: tool_name.to_string(),
+            tool_call_id: tool_call_id.to_string(),
+            created_at,
+        };
+        for worktree in &self.worktrees {
+            self.snapshot(worktree, &checkpoint).await?;
+            self.prune(worktree).await?;
+        }
+        Ok(checkpoint)
+    }
+
+    async fn list(&self) -> Result<Vec<Checkpoint>, CheckpointError> {
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+        for worktree in &self.worktrees {
+            for checkpoint in Self::list_in(worktree).await? {
+                if !checkpoints.iter().any(|c| c.id == checkpoint.id) {
+                    checkpoints.push(checkpoint);
+                }
+            }
+        }
+        checkpoints.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+        Ok(checkpoints)
+    }
+
+    async fn rollback(&self, checkpoint_id: &str) -> Result<(), CheckpointError> {
+        if !self.list().await?.iter().any(|c| c.id == checkpoint_id) {
+            return Err(CheckpointError::NotFound(checkpoint_id.to_string()));
+        }
+        for worktree in &self.worktrees {
+            self.restore(worktree, checkpoint_id).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Approvals that take a checkpoint whenever a risky tool call is approved
+pub struct CheckpointingApprovalService {
+    inner: Arc<dyn ExecutorApprovalService>,
+    checkpoints: Arc<dyn CheckpointProvider>,
+}
+
+impl CheckpointingApprovalService {
+    pub fn new(
+        inner: Arc<dyn ExecutorApprovalService>,
+        checkpoints: Arc<dyn CheckpointProvider>,
+    ) -> Arc<Self> {
+        Arc::new(Self { inner, checkpoints })
+    }
+}
+
+#[async_trait]
+impl ExecutorApprovalService for CheckpointingApprovalService {
+    async fn request_tool_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalStatus, ExecutorApprovalError> {
+        let status = self
+            .inner
+            .request_tool_approval(tool_name, tool_input, tool_call_id)
+            .await?;
+        if matches!(status, ApprovalStatus::Approved) && is_risky_tool(tool_name) {
+            // A failed snapshot must not block the agent
+            if let Err(e) = self.checkpoints.create(tool_name, tool_call_id).await {
+                tracing::warn!("Failed to checkpoint before {tool_name}: {e}");
+            }
+        }
+        Ok(status)
+    }
+}
+
+/// Path for a temporary git index, removed on drop
+struct TempIndex {
+    path: PathBuf,
+}
+
+impl TempIndex {
+    fn new() -> Self {
+        Self {
+            path: std::env::temp_dir()
+                .join(format!("vk-checkpoint-{}.index", uuid::Uuid::new_v4())),
+        }
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn git<I, S>(
+    worktree: &Path,
+    args: I,
+    index: Option<&Path>,
+) -> Result<String, CheckpointError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let git = resolve_executable_path("git")
+        .await
+        .ok_or(CheckpointError::GitNotAvailable)?;
+    let args: Vec<_> = args
+        .into_iter()
+        .map(|a| a.as_ref().to_os_string())
+        .collect();
+    let mut command = Command::new(git);
+    command
+        .arg("-C")
+        .arg(worktree)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+    let output = command.output().await?;
+    if !output.status.success() {
+        return Err(CheckpointError::Git {
+            command: args
+                .first()
+                .map(|a| a.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_and_rollback() {
+        let dir = std::env::temp_dir().join(format!("vk-checkpoints-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        run_git(&dir, &["init", "-q"]);
+        std::fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+        run_git(&dir, &["add", "."]);
+        run_git(&dir, &["commit", "-q", "-m", "init"]);
+
+        std::fs::write(dir.join("main.rs"), "fn main() { work() }\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "draft\n").unwrap();
+        let checkpoints = GitCheckpoints::new(vec![dir.clone()]);
+        let checkpoint = checkpoints.create("Edit", "toolu_1").await.unwrap();
+
+        std::fs::write(dir.join("main.rs"), "broken").unwrap();
+        std::fs::remove_file(dir.join("notes.md")).unwrap();
+        std::fs::write(dir.join("junk.txt"), "junk").unwrap();
+        assert_eq!(checkpoints.list().await.unwrap(), vec![checkpoint.clone()]);
+
+        checkpoints.rollback(&checkpoint.id).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("main.rs")).unwrap(),
+            "fn main() { work() }\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("notes.md")).unwrap(),
+            "draft\n"
+        );
+        assert!(!dir.join("junk.txt").exists());
+        assert!(matches!(
+            checkpoints.rollback("missing").await,
+            Err(CheckpointError::NotFound(_))
+        ));
+        assert!(is_risky_tool("MultiEdit") && !is_risky_tool("Read"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -6,7 +11,7 @@ use thiserror::Error;
 use ts_rs::TS;
 use workspace_utils::shell::resolve_executable_path;
 
-use crate::executors::ExecutorError;
+use crate::{env::ExecutionEnv, executors::ExecutorError, npx_cache, profile::RunOverrides};
 
 #[derive(Debug, Error)]
 pub enum CommandBuildError {
@@ -22,15 +27,39 @@ pub enum CommandBuildError {
 pub struct CommandParts {
     program: String,
     args: Vec<String>,
+    remote: bool,
 }
 
 impl CommandParts {
     pub fn new(program: String, args: Vec<String>) -> Self {
-        Self { program, args }
+        Self {
+            program,
+            args,
+            remote: false,
+        }
     }
 
+    /// Resolve the program on PATH. `npx -y <package>` commands run the
+    /// package's binary directly when npx has already installed it. Commands
+    /// run on a remote host are resolved there.
     pub async fn into_resolved(self) -> Result<(PathBuf, Vec<String>), ExecutorError> {
-        let CommandParts { program, args } = self;
+        let CommandParts {
+            program,
+            args,
+            remote,
+        } = self;
+        if remote {
+            return Ok((PathBuf::from(program), args));
+        }
+        if Path::new(&program)
+            .file_stem()
+            .is_some_and(|stem| stem == "npx")
+            && let Some((spec, rest)) = npx_cache::split_npx_args(&args)
+            && let Some(bin) = npx_cache::cached_npx_bin(spec)
+        {
+            tracing::debug!("Running cached {} instead of npx", bin.display());
+            return Ok((bin, rest.to_vec()));
+        }
         let executable = resolve_executable_path(&program)
             .await
             .ok_or(ExecutorError::ExecutableNotFound { program })?;
@@ -38,7 +67,9 @@ impl CommandParts {
     }
 }
 
+/// Overrides of the command an executor is started with
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema, Default)]
+#[schemars(transform = without_description)]
 pub struct CmdOverrides {
     #[schemars(
         title = "Base Command Override",
@@ -58,14 +89,107 @@ pub struct CmdOverrides {
     )]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
+    #[schemars(
+        title = "Shell",
+        description = "Shell that runs a base command override using shell syntax such as pipes, `&&` or variables. Defaults to cmd on Windows, zsh on macOS and bash elsewhere"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<ScriptShell>,
+}
+
+/// Keep a flattened struct's doc comment out of the schemas of the structs it
+/// is flattened into, which would take it as their own description
+pub(crate) fn without_description(schema: &mut schemars::Schema) {
+    schema.remove("description");
+}
+
+/// Shell a base command override with shell syntax is run by
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptShell {
+    Bash,
+    Zsh,
+    Pwsh,
+    Cmd,
+}
+
+impl ScriptShell {
+    /// The shell overrides are run by when the profile doesn't pick one
+    pub fn os_default() -> Self {
+        if cfg!(windows) {
+            Self::Cmd
+        } else if cfg!(target_os = "macos") {
+            Self::Zsh
+        } else {
+            Self::Bash
+        }
+    }
+
+    /// Program and arguments that run `script`
+    pub(crate) fn command(self, script: String) -> (String, Vec<String>) {
+        let (program, flags): (&str, &[&str]) = match self {
+            Self::Bash => ("bash", &["-c"]),
+            Self::Zsh => ("zsh", &["-c"]),
+            Self::Pwsh => ("pwsh", &["-NoLogo", "-NoProfile", "-Command"]),
+            Self::Cmd => ("cmd", &["/d", "/s", "/c"]),
+        };
+        let mut args: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+        args.push(script);
+        (program.to_string(), args)
+    }
+
+    /// `arg` quoted so the shell passes it on as one argument
+    fn quote<'a>(self, arg: &'a str) -> std::borrow::Cow<'a, str> {
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+".contains(c));
+        match self {
+            Self::Bash | Self::Zsh => quote_arg(arg),
+            _ if plain => arg.into(),
+            Self::Pwsh => format!("'{}'", arg.replace('\'', "''")).into(),
+            // Quoted for the program's own argument parsing; cmd leaves
+            // double-quoted text alone, save for `%` variables
+            Self::Cmd => format!("\"{}\"", arg.replace('"', "\\\"")).into(),
+        }
+    }
+}
+
+/// Whether `command` uses syntax only a shell understands, such as pipes,
+/// `&&`, redirections or variables
+fn has_shell_syntax(command: &str) -> bool {
+    command.contains(['|', '&', ';', '<', '>', '(', ')', '$', '`', '%', '\n'])
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
 pub struct CommandBuilder {
     /// Base executable command (e.g., "npx -y @anthropic-ai/claude-code@latest")
     pub base: String,
-    /// Optional parameters to append to the base command
+    /// Optional parameters to append to the base command, one argument each
     pub params: Option<Vec<String>>,
+    /// Whether the command runs on a remote host
+    #[serde(skip)]
+    #[ts(skip)]
+    #[schemars(skip)]
+    pub remote: bool,
+    /// Shell the base is run by, for bases using shell syntax
+    #[serde(skip)]
+    #[ts(skip)]
+    #[schemars(skip)]
+    pub shell: Option<ScriptShell>,
+    /// What a profile's overrides changed
+    #[serde(skip)]
+    #[ts(skip)]
+    #[schemars(skip)]
+    applied: AppliedOverrides,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct AppliedOverrides {
+    /// Base command the override replaced
+    default_base: Option<String>,
+    /// Parameters added from `additional_params`
+    params: Option<Range<usize>>,
 }
 
 impl CommandBuilder {
@@ -73,9 +197,20 @@ impl CommandBuilder {
         Self {
             base: base.into(),
             params: None,
+            remote: false,
+            shell: None,
+            applied: AppliedOverrides::default(),
         }
     }
 
+    /// Leave resolving the program to the remote host it runs on
+    pub fn on_remote(mut self) -> Self {
+        self.remote = true;
+        self
+    }
+
+    /// Set the parameters; each item is passed as one argument, spaces and
+    /// quotes included
     pub fn params<I>(mut self, params: I) -> Self
     where
         I: IntoIterator,
@@ -90,6 +225,8 @@ impl CommandBuilder {
         self
     }
 
+    /// Append parameters; each item is passed as one argument, spaces and
+    /// quotes included
     pub fn extend_params<I>(mut self, more: I) -> Self
     where
         I: IntoIterator,
@@ -114,23 +251,202 @@ impl CommandBuilder {
         self.build(additional_args)
     }
 
+    /// Only the base is split like a command line; parameters and
+    /// `additional_args` are taken as they are. A base run by a shell is
+    /// handed to it as a script, with the arguments quoted for it.
     fn build(&self, additional_args: &[String]) -> Result<CommandParts, CommandBuildError> {
-        let mut parts = split_command_line(&self.simple_join(additional_args))?;
+        if let Some(shell) = self.shell {
+            if self.base.trim().is_empty() {
+                return Err(CommandBuildError::EmptyCommand);
+            }
+            let mut script = self.base.clone();
+            for arg in self.params.iter().flatten().chain(additional_args) {
+                script.push(' ');
+                script.push_str(&shell.quote(arg));
+            }
+            let (program, args) = shell.command(script);
+            return Ok(CommandParts {
+                program,
+                args,
+                remote: self.remote,
+            });
+        }
+        let mut args = split_command_line(&self.base)?.into_iter();
+        let program = args.next().ok_or(CommandBuildError::EmptyCommand)?;
+        let args = args
+            .chain(self.params.iter().flatten().cloned())
+            .chain(additional_args.iter().cloned())
+            .collect();
+        Ok(CommandParts {
+            program,
+            args,
+            remote: self.remote,
+        })
+    }
+
+    /// Where the parts of the command come from, with the variables `env`
+    /// and the profile's `overrides` set on the agent
+    pub fn explain(
+        &self,
+        env: &ExecutionEnv,
+        overrides: &CmdOverrides,
+    ) -> Result<CommandExplanation, CommandBuildError> {
+        let CommandParts { program, args, .. } = self.build_initial()?;
+        let params = self.params.as_deref().unwrap_or_default();
+        let (default_params, override_params) = match self.applied.params.clone() {
+            Some(range) => (
+                params[..range.start]
+                    .iter()
+                    .chain(&params[range.end..])
+                    .cloned()
+                    .collect(),
+                params[range].to_vec(),
+            ),
+            None => (params.to_vec(), Vec::new()),
+        };
 
-        let program = parts.remove(0);
-        Ok(CommandParts::new(program, parts))
+        let profile_env = overrides.env.clone().unwrap_or_default();
+        let mut env: Vec<EnvMutation> = env
+            .vars
+            .iter()
+            .filter(|(key, _)| !profile_env.contains_key(*key))
+            .map(|(key, value)| {
+                EnvMutation::new(key, value, EnvSource::Execution, env.is_secret(key))
+            })
+            .chain(
+                profile_env
+                    .iter()
+                    .map(|(key, value)| EnvMutation::new(key, value, EnvSource::Profile, false)),
+            )
+            .collect();
+        env.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(CommandExplanation {
+            base: self.base.clone(),
+            default_base: self.applied.default_base.clone(),
+            default_params,
+            override_params,
+            env,
+            command_line: shell_join(
+                std::iter::once(program.as_str()).chain(args.iter().map(String::as_str)),
+            ),
+        })
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvSource {
+    /// Set for every execution, e.g. `VK_TASK_ID`
+    Execution,
+    /// Set by the profile's `env`, winning over execution variables
+    Profile,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvMutation {
+    pub key: String,
+    /// Values of secret variables, and of ones that look like secrets, are
+    /// masked
+    pub value: String,
+    pub source: EnvSource,
+}
 
-    fn simple_join(&self, additional_args: &[String]) -> String {
-        let mut parts = vec![self.base.clone()];
-        if let Some(ref params) = self.params {
-            parts.extend(params.clone());
+impl EnvMutation {
+    fn new(key: &str, value: &str, source: EnvSource, secret: bool) -> Self {
+        let upper = key.to_ascii_uppercase();
+        let secret = secret
+            || ["KEY", "TOKEN", "SECRET", "PASSWORD", "CREDENTIAL"]
+                .iter()
+                .any(|word| upper.contains(word));
+        Self {
+            key: key.to_string(),
+            value: if secret {
+                "<redacted>".to_string()
+            } else {
+                value.to_string()
+            },
+            source,
         }
-        parts.extend(additional_args.iter().cloned());
-        parts.join(" ")
     }
 }
 
+/// Breakdown of the command an executor spawns its agent with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandExplanation {
+    /// Base command, after any override
+    pub base: String,
+    /// The executor's own base command, if the profile replaced it
+    pub default_base: Option<String>,
+    /// Parameters the executor adds
+    pub default_params: Vec<String>,
+    /// Parameters from the profile's `additional_params`
+    pub override_params: Vec<String>,
+    pub env: Vec<EnvMutation>,
+    /// The whole command, quoted like a shell command line
+    pub command_line: String,
+}
+
+impl fmt::Display for CommandExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "$ {}", self.command_line)?;
+        match &self.default_base {
+            Some(default) => writeln!(f, "Base command: {} (instead of {default})", self.base)?,
+            None => writeln!(f, "Base command: {}", self.base)?,
+        }
+        let params = [
+            ("Executor parameters", &self.default_params),
+            ("Profile parameters", &self.override_params),
+        ];
+        for (label, params) in params {
+            if !params.is_empty() {
+                writeln!(
+                    f,
+                    "{label}: {}",
+                    shell_join(params.iter().map(String::as_str))
+                )?;
+            }
+        }
+        if !self.env.is_empty() {
+            writeln!(f, "Environment:")?;
+        }
+        for var in &self.env {
+            let source = match var.source {
+                EnvSource::Execution => "execution",
+                EnvSource::Profile => "profile",
+            };
+            writeln!(f, "  {}={} ({source})", var.key, var.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The command as a shell would take it, with parameters quoted where needed
+impl fmt::Display for CommandBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.base)?;
+        for param in self.params.iter().flatten() {
+            write!(f, " {}", quote_arg(param))?;
+        }
+        Ok(())
+    }
+}
+
+/// `words` joined into a command line a POSIX shell splits back into them
+pub fn shell_join<'a>(words: impl IntoIterator<Item = &'a str>) -> String {
+    words
+        .into_iter()
+        .map(quote_arg)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `arg` quoted for a shell; arguments with NUL bytes can't be quoted and
+/// are left as they are
+fn quote_arg(arg: &str) -> std::borrow::Cow<'_, str> {
+    shlex::try_quote(arg).unwrap_or(std::borrow::Cow::Borrowed(arg))
+}
+
 fn split_command_line(input: &str) -> Result<Vec<String>, CommandBuildError> {
     #[cfg(windows)]
     {
@@ -148,15 +464,184 @@ fn split_command_line(input: &str) -> Result<Vec<String>, CommandBuildError> {
     }
 }
 
-pub fn apply_overrides(builder: CommandBuilder, overrides: &CmdOverrides) -> CommandBuilder {
+/// Apply a profile's command overrides; runs on a remote host leave resolving
+/// the program to it
+pub fn apply_overrides(
+    builder: CommandBuilder,
+    overrides: &CmdOverrides,
+    run: &RunOverrides,
+) -> CommandBuilder {
     let builder = if let Some(ref base) = overrides.base_command_override {
-        builder.override_base(base.clone())
+        let default_base = builder.base.clone();
+        let mut builder = builder.override_base(base.clone());
+        builder.applied.default_base = Some(default_base);
+        if has_shell_syntax(base) {
+            builder.shell = Some(overrides.shell.unwrap_or_else(ScriptShell::os_default));
+        }
+        builder
+    } else {
+        builder
+    };
+    let builder = if run.remote.is_some() {
+        builder.on_remote()
     } else {
         builder
     };
     if let Some(ref extra) = overrides.additional_params {
-        builder.extend_params(extra.clone())
+        // Profiles write each entry like a command line, e.g. `--model sonnet`
+        // or `--append-system-prompt "Be terse"`
+        let start = builder.params.as_ref().map_or(0, Vec::len);
+        let mut builder =
+            builder.extend_params(extra.iter().flat_map(|param| {
+                split_command_line(param).unwrap_or_else(|_| vec![param.clone()])
+            }));
+        builder.applied.params = Some(start..builder.params.as_ref().map_or(0, Vec::len));
+        builder
     } else {
         builder
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_are_passed_as_single_arguments() {
+        let builder = CommandBuilder::new("npx -y claude-flow@alpha")
+            .params(["--output-format", "stream-json"])
+            .extend_params(["--task", "Fix the \"login\" page's layout"]);
+        let builder = apply_overrides(
+            builder,
+            &CmdOverrides {
+                additional_params: Some(vec![
+                    "--model sonnet".to_string(),
+                    "--append-system-prompt 'Be terse'".to_string(),
+                ]),
+                ..Default::default()
+            },
+            &RunOverrides::default(),
+        );
+
+        let CommandParts { program, args, .. } = builder
+            .build_follow_up(&["--resume".to_string(), "a b".to_string()])
+            .unwrap();
+        assert_eq!(program, "npx");
+        assert_eq!(
+            args,
+            [
+                "-y",
+                "claude-flow@alpha",
+                "--output-format",
+                "stream-json",
+                "--task",
+                "Fix the \"login\" page's layout",
+                "--model",
+                "sonnet",
+                "--append-system-prompt",
+                "Be terse",
+                "--resume",
+                "a b",
+            ]
+        );
+        assert_eq!(
+            builder.to_string(),
+            "npx -y claude-flow@alpha --output-format stream-json --task \
+             \"Fix the \\\"login\\\" page's layout\" --model sonnet \
+             --append-system-prompt 'Be terse'"
+        );
+        assert!(matches!(
+            CommandBuilder::new("  ").build_initial(),
+            Err(CommandBuildError::EmptyCommand)
+        ));
+    }
+
+    #[test]
+    fn test_explain_separates_defaults_from_overrides() {
+        let overrides = CmdOverrides {
+            base_command_override: Some("npx -y @anthropic-ai/claude-code@2.0.0".to_string()),
+            additional_params: Some(vec!["--model opus".to_string()]),
+            env: Some(HashMap::from([
+                ("ANTHROPIC_API_KEY".to_string(), "sk-ant-123".to_string()),
+                ("VK_TASK_TITLE".to_string(), "From profile".to_string()),
+            ])),
+            ..Default::default()
+        };
+        let builder = apply_overrides(
+            CommandBuilder::new("npx -y @anthropic-ai/claude-code@latest").params(["-p"]),
+            &overrides,
+            &RunOverrides::default(),
+        )
+        .extend_params(["--verbose"]);
+        let mut env = ExecutionEnv::new();
+        env.insert("VK_TASK_ID", "1234");
+        env.insert("VK_TASK_TITLE", "Fix login");
+
+        let explanation = builder.explain(&env, &overrides).unwrap();
+        assert_eq!(
+            explanation.default_base.as_deref(),
+            Some("npx -y @anthropic-ai/claude-code@latest")
+        );
+        assert_eq!(explanation.default_params, ["-p", "--verbose"]);
+        assert_eq!(explanation.override_params, ["--model", "opus"]);
+        assert_eq!(
+            explanation.env,
+            [
+                EnvMutation {
+                    key: "ANTHROPIC_API_KEY".to_string(),
+                    value: "<redacted>".to_string(),
+                    source: EnvSource::Profile,
+                },
+                EnvMutation {
+                    key: "VK_TASK_ID".to_string(),
+                    value: "1234".to_string(),
+                    source: EnvSource::Execution,
+                },
+                EnvMutation {
+                    key: "VK_TASK_TITLE".to_string(),
+                    value: "From profile".to_string(),
+                    source: EnvSource::Profile,
+                },
+            ]
+        );
+        assert_eq!(
+            explanation.to_string().lines().next(),
+            Some("$ npx -y @anthropic-ai/claude-code@2.0.0 -p --model opus --verbose")
+        );
+    }
+
+    #[test]
+    fn test_override_with_shell_syntax_runs_in_shell() {
+        let builder = || CommandBuilder::new("npx -y @google/gemini-cli@latest").params(["--yolo"]);
+        let overrides = CmdOverrides {
+            base_command_override: Some("source .env && gemini".to_string()),
+            shell: Some(ScriptShell::Pwsh),
+            ..Default::default()
+        };
+        let CommandParts { program, args, .. } =
+            apply_overrides(builder(), &overrides, &RunOverrides::default())
+                .build_follow_up(&["--prompt".to_string(), "Fix the user's login".to_string()])
+                .unwrap();
+        assert_eq!(program, "pwsh");
+        assert_eq!(
+            args,
+            [
+                "-NoLogo",
+                "-NoProfile",
+                "-Command",
+                "source .env && gemini --yolo --prompt 'Fix the user''s login'",
+            ]
+        );
+
+        // Plain overrides are run directly
+        let overrides = CmdOverrides {
+            base_command_override: Some("gemini --sandbox".to_string()),
+            ..Default::default()
+        };
+        let CommandParts { program, .. } =
+            apply_overrides(builder(), &overrides, &RunOverrides::default())
+                .build_initial()
+                .unwrap();
+        assert_eq!(program, "gemini");
+    }
+}
@@ -0,0 +1,207 @@
+//! Recovery from runs that overflow the agent's context window.
+//!
+//! A long session eventually outgrows the model's context window, after which
+//! every follow-up in it fails the same way. When a run fails with
+//! [`ExecutorFailureReason::ContextOverflow`], [`overflow_retry`] builds the
+//! action that retries it. Agents with a compaction command get it as a
+//! follow-up in the same session first, with the failed prompt chained after
+//! it. Other agents, and sessions that overflow again right after being
+//! compacted, start over in a fresh session whose prompt carries a digest of
+//! the failed run in place of its full history.
+//!
+//! [`ExecutorFailureReason::ContextOverflow`]: crate::logs::stderr_processor::ExecutorFailureReason::ContextOverflow
+
+use workspace_utils::log_msg::LogMsg;
+
+use crate::{
+    actions::{
+        ExecutorAction, ExecutorActionType, coding_agent_follow_up::CodingAgentFollowUpRequest,
+        coding_agent_initial::CodingAgentInitialRequest,
+    },
+    executors::StandardCodingAgentExecutor,
+    logs::{
+        NormalizedConversation,
+        transcript::{DigestOptions, TranscriptOptions},
+    },
+    profile::ExecutorConfigs,
+};
+
+/// Budget of the digest a fresh session starts from
+const DIGEST_TOKENS: usize = 4_000;
+
+const CONTINUATION_PREAMBLE: &str = "The previous session on this task ran out of context. \
+     This is a digest of what happened in it; continue from there.";
+
+/// Whether `action` only compacts its session, as issued by [`overflow_retry`]
+pub fn is_compaction(action: &ExecutorAction, configs: &ExecutorConfigs) -> bool {
+    let ExecutorActionType::CodingAgentFollowUpRequest(request) = action.typ() else {
+        return false;
+    };
+    configs
+        .get_coding_agent(&request.executor_profile_id)
+        .and_then(|agent| agent.compact_command())
+        .is_some_and(|command| request.prompt == command)
+}
+
+/// Action retrying `action`, whose run logged `history` before overflowing
+/// the agent's context window. A session `compacted` right before that run
+/// isn't compacted again but left for a fresh one.
+pub fn overflow_retry(
+    action: &ExecutorAction,
+    history: &[LogMsg],
+    compacted: bool,
+    configs: &ExecutorConfigs,
+) -> Option<ExecutorAction> {
+    // Either request, as a follow-up to whichever session the run was in
+    let (mut request, run_template) = match action.typ() {
+        ExecutorActionType::CodingAgentInitialRequest(request) => (
+            CodingAgentFollowUpRequest {
+                prompt: request.prompt.clone(),
+                session_id: String::new(),
+                executor_profile_id: request.executor_profile_id.clone(),
+                working_dir: request.working_dir.clone(),
+                cwd_relative: request.cwd_relative.clone(),
+                fork: false,
+                attachments: request.attachments.clone(),
+            },
+            request.run_template.clone(),
+        ),
+        ExecutorActionType::CodingAgentFollowUpRequest(request) => (
+            CodingAgentFollowUpRequest {
+                fork: false,
+                ..request.clone()
+            },
+            None,
+        ),
+        ExecutorActionType::ScriptRequest(_) => return None,
+    };
+    if let Some(session_id) = history.iter().rev().find_map(|msg| match msg {
+        LogMsg::SessionId(id) => Some(id.clone()),
+        _ => None,
+    }) {
+        request.session_id = session_id;
+    }
+
+    let compact_command = configs
+        .get_coding_agent(&request.executor_profile_id)
+        .and_then(|agent| agent.compact_command());
+    if let Some(command) = compact_command
+        && !compacted
+        && !request.session_id.is_empty()
+    {
+        let compaction = CodingAgentFollowUpRequest {
+            prompt: command.to_string(),
+            attachments: Vec::new(),
+            ..request.clone()
+        };
+        let retry = ExecutorAction::new(
+            ExecutorActionType::CodingAgentFollowUpRequest(request),
+            action.next_action.clone(),
+        );
+        return Some(ExecutorAction::new(
+            ExecutorActionType::CodingAgentFollowUpRequest(compaction),
+            Some(Box::new(retry)),
+        ));
+    }
+
+    let digest = NormalizedConversation::from_history(
+        history,
+        request.executor_profile_id.executor.to_string(),
+        None,
+        &TranscriptOptions::default(),
+    )
+    .digest(&DigestOptions {
+        max_tokens: DIGEST_TOKENS,
+        include_thinking: false,
+    });
+    let fresh = CodingAgentInitialRequest {
+        prompt: format!(
+            "{CONTINUATION_PREAMBLE}\n\n{}\n\n{}",
+            digest.text, request.prompt
+        ),
+        executor_profile_id: request.executor_profile_id,
+        working_dir: request.working_dir,
+        cwd_relative: request.cwd_relative,
+        run_template,
+        attachments: request.attachments,
+        review: None,
+    };
+    Some(ExecutorAction::new(
+        ExecutorActionType::CodingAgentInitialRequest(fresh),
+        action.next_action.clone(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        executors::BaseCodingAgent,
+        logs::{NormalizedEntry, NormalizedEntryType, utils::ConversationPatch},
+        profile::ExecutorProfileId,
+    };
+
+    fn follow_up(executor: BaseCodingAgent, prompt: &str) -> ExecutorAction {
+        ExecutorAction::new(
+            ExecutorActionType::CodingAgentFollowUpRequest(CodingAgentFollowUpRequest {
+                prompt: prompt.to_string(),
+                session_id: "session-1".to_string(),
+                executor_profile_id: ExecutorProfileId::new(executor),
+                working_dir: None,
+                cwd_relative: None,
+                fork: false,
+                attachments: Vec::new(),
+            }),
+            None,
+        )
+    }
+
+    fn prompt_of(action: &ExecutorAction) -> &str {
+        match action.typ() {
+            ExecutorActionType::CodingAgentInitialRequest(request) => &request.prompt,
+            ExecutorActionType::CodingAgentFollowUpRequest(request) => &request.prompt,
+            ExecutorActionType::ScriptRequest(_) => panic!("expected a coding agent request"),
+        }
+    }
+
+    #[test]
+    fn test_overflow_retry_compacts_then_starts_over() {
+        let configs = ExecutorConfigs::from_defaults();
+        let history = [
+            LogMsg::SessionId("session-2".to_string()),
+            LogMsg::JsonPatch(ConversationPatch::add_normalized_entry(
+                0,
+                NormalizedEntry {
+                    timestamp: None,
+                    entry_type: NormalizedEntryType::AssistantMessage,
+                    content: "Renamed the config loader".to_string(),
+                    metadata: None,
+                    agent_id: None,
+                    agent_role: None,
+                },
+            )),
+        ];
+        let action = follow_up(BaseCodingAgent::ClaudeCode, "now update the docs");
+
+        let compaction = overflow_retry(&action, &history, false, &configs).unwrap();
+        assert!(is_compaction(&compaction, &configs));
+        let retry = compaction.next_action().unwrap();
+        assert_eq!(prompt_of(retry), "now update the docs");
+        assert!(matches!(
+            retry.typ(),
+            ExecutorActionType::CodingAgentFollowUpRequest(request)
+                if request.session_id == "session-2"
+        ));
+
+        // Overflowing again right after compacting starts a fresh session
+        let fresh = overflow_retry(&action, &history, true, &configs).unwrap();
+        assert!(matches!(
+            fresh.typ(),
+            ExecutorActionType::CodingAgentInitialRequest(_)
+        ));
+        let prompt = prompt_of(&fresh);
+        assert!(prompt.contains("Renamed the config loader"));
+        assert!(prompt.ends_with("now update the docs"));
+        assert!(!is_compaction(&fresh, &configs));
+    }
+}
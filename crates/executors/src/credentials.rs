@@ -0,0 +1,136 @@
+//! Whether an agent's saved login is still good.
+//!
+//! Availability checks take an agent's credentials file as a sign it is
+//! logged in. OAuth logins expire, though, and a run started on an expired
+//! one fails mid-task once the agent first calls its API. [`SavedLogin`]
+//! reads when the saved access token expires and whether the agent can renew
+//! it, so availability checks can report
+//! [`AvailabilityInfo::LoginExpired`] and the UI can ask for a new login
+//! before the run starts. A login with a refresh token, or an API key next to
+//! it, is renewed by the agent itself and never counts as expired.
+
+use std::path::Path;
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::executors::AvailabilityInfo;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavedLogin {
+    /// When the access token expires, in milliseconds since the epoch
+    pub expires_at_ms: Option<i64>,
+    pub refreshable: bool,
+    /// Whether an API key is configured, which the agent falls back to
+    pub api_key: bool,
+}
+
+impl SavedLogin {
+    /// Claude Code's OAuth login, kept in `~/.claude/.credentials.json`
+    /// where the system keychain isn't used
+    pub fn claude(home: &Path) -> Option<Self> {
+        let credentials = read_json(&home.join(".claude").join(".credentials.json"))?;
+        let mut login = Self::from_claude_credentials(&credentials)?;
+        login.api_key |= std::env::var_os("ANTHROPIC_API_KEY").is_some()
+            || read_json(&home.join(".claude.json"))
+                .is_some_and(|config| config["primaryApiKey"].is_string());
+        Some(login)
+    }
+
+    /// Gemini CLI's Google login, kept in `~/.gemini/oauth_creds.json`
+    pub fn gemini(home: &Path) -> Option<Self> {
+        let credentials = read_json(&home.join(".gemini").join("oauth_creds.json"))?;
+        let mut login = Self::from_google_credentials(&credentials);
+        login.api_key |= std::env::var_os("GEMINI_API_KEY").is_some();
+        Some(login)
+    }
+
+    pub fn from_claude_credentials(credentials: &Value) -> Option<Self> {
+        let oauth = credentials.get("claudeAiOauth")?;
+        Some(Self {
+            expires_at_ms: oauth["expiresAt"].as_i64(),
+            refreshable: has_token(&oauth["refreshToken"]),
+            api_key: false,
+        })
+    }
+
+    pub fn from_google_credentials(credentials: &Value) -> Self {
+        Self {
+            expires_at_ms: credentials["expiry_date"].as_i64(),
+            refreshable: has_token(&credentials["refresh_token"]),
+            api_key: false,
+        }
+    }
+
+    /// When the login expired, in seconds since the epoch, if it has
+    /// expired by `now_ms` and the agent can't renew it
+    pub fn expired_at(&self, now_ms: i64) -> Option<i64> {
+        if self.refreshable || self.api_key {
+            return None;
+        }
+        self.expires_at_ms
+            .filter(|&expires_at| expires_at <= now_ms)
+            .map(|expires_at| expires_at / 1000)
+    }
+
+    /// [`AvailabilityInfo::LoginExpired`] if the login has expired
+    pub fn expired(&self) -> Option<AvailabilityInfo> {
+        self.expired_at(Utc::now().timestamp_millis())
+            .map(|expired_at| AvailabilityInfo::LoginExpired { expired_at })
+    }
+}
+
+fn has_token(value: &Value) -> bool {
+    value.as_str().is_some_and(|token| !token.is_empty())
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_expires_only_without_a_way_to_renew_it() {
+        let now_ms = 1_750_000_000_000;
+        let token = |expires_at: i64, refresh_token: &str| {
+            SavedLogin::from_claude_credentials(&serde_json::json!({
+                "claudeAiOauth": {
+                    "accessToken": "sk-ant-oat01-...",
+                    "refreshToken": refresh_token,
+                    "expiresAt": expires_at,
+                    "scopes": ["user:inference"],
+                },
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            token(now_ms - 60_000, "").expired_at(now_ms),
+            Some(1_749_999_940)
+        );
+        assert_eq!(token(now_ms + 60_000, "").expired_at(now_ms), None);
+        assert_eq!(
+            token(now_ms - 60_000, "sk-ant-ort01-...").expired_at(now_ms),
+            None
+        );
+        let with_api_key = SavedLogin {
+            api_key: true,
+            ..token(now_ms - 60_000, "")
+        };
+        assert_eq!(with_api_key.expired_at(now_ms), None);
+        assert_eq!(
+            SavedLogin::from_claude_credentials(&serde_json::json!({})),
+            None
+        );
+
+        let google = SavedLogin::from_google_credentials(&serde_json::json!({
+            "access_token": "ya29....",
+            "expiry_date": now_ms - 1,
+            "token_type": "Bearer",
+        }));
+        assert_eq!(google.expired_at(now_ms), Some(1_749_999_999));
+    }
+}
@@ -0,0 +1,151 @@
+//! A single self-describing document of what this build of the executors
+//! crate offers, so clients can list agents, render their config forms and
+//! pick personas without hardcoding which executors exist.
+
+use std::{collections::HashMap, str::FromStr};
+
+use schemars::{JsonSchema, generate::SchemaSettings};
+use serde::{Deserialize, Serialize};
+use strum::VariantNames;
+use ts_rs::TS;
+
+use crate::{
+    encryption::Encryptor,
+    executors::{
+        BaseAgentCapability, BaseCodingAgent, CodingAgent, amp::Amp, claude::ClaudeCode,
+        claude_api::ClaudeApi, claude_flow::ClaudeFlow, codex::Codex, copilot::Copilot,
+        cursor::CursorAgent, droid::Droid, gemini::Gemini, opencode::Opencode, qwen::QwenCode,
+    },
+    profile::{ExecutorConfigs, ExecutorProfileId},
+    replay,
+    run_template::RunTemplate,
+    telemetry::Telemetry,
+    tenant::TenantId,
+};
+
+/// Version of the [`CrateDescription`] format, bumped on breaking changes
+pub const DESCRIPTION_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct CrateDescription {
+    pub version: u32,
+    /// Version of the executors crate that produced the document
+    pub crate_version: String,
+    pub executors: Vec<ExecutorDescription>,
+    /// Run templates by name, usable as personas on top of any profile
+    pub personas: HashMap<String, RunTemplate>,
+    pub features: FeatureFlags,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct ExecutorDescription {
+    pub executor: BaseCodingAgent,
+    /// Configured variants, `DEFAULT` first
+    pub variants: Vec<String>,
+    pub capabilities: Vec<BaseAgentCapability>,
+    /// JSON schema (draft-07) of the executor's configuration
+    #[ts(type = "JsonValue")]
+    pub config_schema: serde_json::Value,
+}
+
+/// Optional features and whether they are active
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct FeatureFlags {
+    pub encryption_at_rest: bool,
+    pub telemetry: bool,
+    pub recording: bool,
+    pub role_policies: bool,
+}
+
+/// Describe the executors and configuration visible to `tenant`, or to the
+/// server itself when `None`
+pub fn describe(tenant: Option<&TenantId>) -> CrateDescription {
+    let configs = ExecutorConfigs::get_cached_for(tenant);
+    let executors = CodingAgent::VARIANTS
+        .iter()
+        .filter_map(|name| BaseCodingAgent::from_str(name).ok())
+        .filter_map(|executor| {
+            let config = configs.executors.get(&executor)?;
+            let mut variants: Vec<String> = config.configurations.keys().cloned().collect();
+            variants.sort_by_key(|variant| (variant != "DEFAULT", variant.clone()));
+            let capabilities = configs
+                .get_coding_agent(&ExecutorProfileId::new(executor))
+                .map(|agent| agent.capabilities())
+                .unwrap_or_default();
+            Some(ExecutorDescription {
+                executor,
+                variants,
+                capabilities,
+                config_schema: config_schema(executor),
+            })
+        })
+        .collect();
+
+    CrateDescription {
+        version: DESCRIPTION_VERSION,
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        executors,
+        features: FeatureFlags {
+            encryption_at_rest: Encryptor::global().is_some(),
+            telemetry: Telemetry::global().is_enabled(),
+            recording: replay::record_dir().is_some(),
+            role_policies: !configs.roles.is_empty(),
+        },
+        personas: configs.templates,
+    }
+}
+
+/// JSON schema of an executor's configuration, in the form used for the
+/// settings forms
+pub fn config_schema(executor: BaseCodingAgent) -> serde_json::Value {
+    match executor {
+        BaseCodingAgent::ClaudeCode => schema_for::<ClaudeCode>(),
+        BaseCodingAgent::ClaudeFlow => schema_for::<ClaudeFlow>(),
+        BaseCodingAgent::Amp => schema_for::<Amp>(),
+        BaseCodingAgent::Gemini => schema_for::<Gemini>(),
+        BaseCodingAgent::Codex => schema_for::<Codex>(),
+        BaseCodingAgent::Opencode => schema_for::<Opencode>(),
+        BaseCodingAgent::CursorAgent => schema_for::<CursorAgent>(),
+        BaseCodingAgent::QwenCode => schema_for::<QwenCode>(),
+        BaseCodingAgent::Copilot => schema_for::<Copilot>(),
+        BaseCodingAgent::Droid => schema_for::<Droid>(),
+        BaseCodingAgent::ClaudeApi => schema_for::<ClaudeApi>(),
+    }
+}
+
+fn schema_for<T: JsonSchema>() -> serde_json::Value {
+    // Draft-07 with everything inlined, like the generated schema files
+    let mut settings = SchemaSettings::draft07();
+    settings.inline_subschemas = true;
+    let schema = settings.into_generator().into_root_schema_for::<T>();
+    let mut value = serde_json::to_value(schema).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("title");
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describes_every_default_executor() {
+        let description = describe(None);
+        assert_eq!(description.version, DESCRIPTION_VERSION);
+
+        let claude = description
+            .executors
+            .iter()
+            .find(|e| e.executor == BaseCodingAgent::ClaudeCode)
+            .unwrap();
+        assert_eq!(claude.variants[0], "DEFAULT");
+        assert!(claude.variants.contains(&"PLAN".to_string()));
+        assert!(
+            claude
+                .capabilities
+                .contains(&BaseAgentCapability::SessionFork)
+        );
+        assert!(claude.config_schema["properties"]["dangerously_skip_permissions"].is_object());
+    }
+}
@@ -0,0 +1,86 @@
+//! Dry runs of coding agent actions, for debugging profile configs.
+//!
+//! When a profile sets `dry_run`, the action doesn't start the agent. It
+//! starts a stand-in process instead that prints the agent's
+//! [`CommandExplanation`] and exits, so the explanation shows up in the
+//! execution's logs like any other output.
+
+use std::{path::Path, process::Stdio};
+
+use command_group::AsyncCommandGroup;
+use tokio::{io::AsyncWriteExt, process::Command};
+use workspace_utils::shell::get_shell_command;
+
+use crate::{
+    command::CommandExplanation,
+    executors::{ExecutorError, SpawnedChild},
+};
+
+/// Spawn a process in `current_dir` that prints `explanation` and exits
+pub async fn spawn_explanation(
+    explanation: &CommandExplanation,
+    current_dir: &Path,
+) -> Result<SpawnedChild, ExecutorError> {
+    let (shell, shell_arg) = get_shell_command();
+    let mut command = Command::new(shell);
+    command
+        .kill_on_drop(true)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .current_dir(current_dir)
+        .arg(shell_arg)
+        .arg(if cfg!(windows) { "more" } else { "cat" });
+
+    let mut child = command.group_spawn()?;
+    let mut stdin = child
+        .inner()
+        .stdin
+        .take()
+        .ok_or_else(|| ExecutorError::Io(std::io::Error::other("dry run missing stdin")))?;
+    let text = format!("Dry run, the agent was not started\n{explanation}");
+    // Written in the background so a full stdout pipe can't block the spawn
+    tokio::spawn(async move {
+        if let Err(e) = stdin.write_all(text.as_bytes()).await {
+            tracing::warn!("Failed to write dry run output: {e}");
+        }
+        let _ = stdin.shutdown().await;
+    });
+    Ok(child.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::{
+        command::{CmdOverrides, CommandBuilder},
+        env::ExecutionEnv,
+    };
+
+    #[tokio::test]
+    async fn test_stand_in_prints_the_explanation() {
+        let mut env = ExecutionEnv::new();
+        env.insert("ANTHROPIC_API_KEY", "sk-ant-123");
+        let explanation = CommandBuilder::new("npx -y @anthropic-ai/claude-code@latest")
+            .params(["-p", "--verbose"])
+            .explain(&env, &CmdOverrides::default())
+            .unwrap();
+
+        let mut spawned = spawn_explanation(&explanation, &std::env::temp_dir())
+            .await
+            .unwrap();
+        let mut stdout = spawned.child.inner().stdout.take().unwrap();
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).await.unwrap();
+        assert!(spawned.child.wait().await.unwrap().success());
+
+        assert_eq!(
+            output,
+            format!("Dry run, the agent was not started\n{explanation}")
+        );
+        assert!(output.contains("$ npx -y @anthropic-ai/claude-code@latest -p --verbose\n"));
+        assert!(!output.contains("sk-ant-123"));
+    }
+}
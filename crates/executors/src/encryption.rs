@@ -0,0 +1,333 @@
+//! Encryption of persisted transcripts and raw captures at rest.
+//!
+//! Agent output routinely contains proprietary code and secrets, so when a
+//! [`KeyProvider`] is configured every persisted line (execution logs in the
+//! database and on disk, recordings) is sealed with AES-256-GCM before it is
+//! written. Sealed lines carry the id of the key they were written with, so
+//! keys can be rotated: put the new key first and keep the old ones around
+//! until [`Encryptor::rotate_line`] has re-sealed the existing data. Lines
+//! written before encryption was enabled are read back unchanged.
+//!
+//! Without a registered provider, keys are read from `VK_ENCRYPTION_KEYS` as
+//! a comma-separated list of `<key id>:<base64 secret>`, current key first.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+};
+use base64::{
+    Engine as _,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use workspace_utils::msg_store::LineCodec;
+
+pub const ENCRYPTION_KEYS_ENV: &str = "VK_ENCRYPTION_KEYS";
+
+/// Prefix of sealed lines, followed by `<key id>:<base64 nonce + ciphertext>`
+const SEALED_PREFIX: &str = "vkenc1:";
+const NONCE_SIZE: usize = 12; // 96 bits for AES-256-GCM
+
+static ENCRYPTOR: LazyLock<RwLock<Option<Arc<Encryptor>>>> = LazyLock::new(|| {
+    let encryptor = match KeyRing::from_env() {
+        Ok(keys) => keys.map(|keys| Arc::new(Encryptor::new(Arc::new(keys)))),
+        Err(e) => {
+            tracing::error!("Ignoring {ENCRYPTION_KEYS_ENV}: {e}");
+            None
+        }
+    };
+    RwLock::new(encryptor)
+});
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("Invalid key specification: {0}")]
+    InvalidKeySpec(String),
+    #[error("Unknown encryption key '{0}'")]
+    UnknownKey(String),
+    #[error("Malformed sealed data")]
+    Malformed,
+    #[error("Failed to encrypt data")]
+    Encrypt,
+    #[error("Failed to decrypt data with key '{0}'")]
+    Decrypt(String),
+    #[error("Data is encrypted but no encryption keys are configured")]
+    NotConfigured,
+}
+
+impl From<EncryptionError> for io::Error {
+    fn from(e: EncryptionError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Source of the keys used to seal persisted data
+pub trait KeyProvider: Send + Sync {
+    /// Id of the key new data is sealed with
+    fn current_key_id(&self) -> &str;
+
+    /// A key by id, including retired keys that existing data may still be
+    /// sealed with
+    fn key(&self, id: &str) -> Option<[u8; 32]>;
+}
+
+/// Fixed set of keys, the first of which is current
+#[derive(Clone)]
+pub struct KeyRing {
+    current: String,
+    keys: HashMap<String, [u8; 32]>,
+}
+
+impl KeyRing {
+    /// Parse `<key id>:<base64 secret>[,...]`. Secrets are hashed into
+    /// 256-bit keys.
+    pub fn parse(spec: &str) -> Result<Self, EncryptionError> {
+        let mut current = None;
+        let mut keys = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, secret) = entry.split_once(':').ok_or_else(|| {
+                EncryptionError::InvalidKeySpec("expected <key id>:<base64 secret>".to_string())
+            })?;
+            if id.is_empty() || id.contains(':') {
+                return Err(EncryptionError::InvalidKeySpec(format!(
+                    "invalid key id '{id}'"
+                )));
+            }
+            let secret = STANDARD.decode(secret).map_err(|_| {
+                EncryptionError::InvalidKeySpec(format!("secret of key '{id}' is not base64"))
+            })?;
+            keys.insert(id.to_string(), Sha256::digest(&secret).into());
+            current.get_or_insert_with(|| id.to_string());
+        }
+        let current =
+            current.ok_or_else(|| EncryptionError::InvalidKeySpec("no keys given".to_string()))?;
+        Ok(Self { current, keys })
+    }
+
+    /// Keys from `VK_ENCRYPTION_KEYS`, if set
+    pub fn from_env() -> Result<Option<Self>, EncryptionError> {
+        match std::env::var(ENCRYPTION_KEYS_ENV) {
+            Ok(spec) if !spec.trim().is_empty() => Self::parse(&spec).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl std::fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("current", &self.current)
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl KeyProvider for KeyRing {
+    fn current_key_id(&self) -> &str {
+        &self.current
+    }
+
+    fn key(&self, id: &str) -> Option<[u8; 32]> {
+        self.keys.get(id).copied()
+    }
+}
+
+pub struct Encryptor {
+    provider: Arc<dyn KeyProvider>,
+}
+
+impl Encryptor {
+    pub fn new(provider: Arc<dyn KeyProvider>) -> Self {
+        Self { provider }
+    }
+
+    /// The encryptor for data at rest, if keys are configured
+    pub fn global() -> Option<Arc<Encryptor>> {
+        ENCRYPTOR.read().unwrap().clone()
+    }
+
+    /// Replace the configured keys; `None` turns encryption of new data off
+    pub fn set_key_provider(provider: Option<Arc<dyn KeyProvider>>) {
+        *ENCRYPTOR.write().unwrap() = provider.map(|provider| Arc::new(Encryptor::new(provider)));
+    }
+
+    pub fn is_sealed(line: &str) -> bool {
+        line.starts_with(SEALED_PREFIX)
+    }
+
+    /// Seal `plaintext` with the current key into a single line
+    pub fn seal(&self, plaintext: &str) -> Result<String, EncryptionError> {
+        let key_id = self.provider.current_key_id();
+        let key_bytes = self
+            .provider
+            .key(key_id)
+            .ok_or_else(|| EncryptionError::UnknownKey(key_id.to_string()))?;
+        let cipher = Aes256Gcm::new(&Key::<Aes256Gcm>::from(key_bytes));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| EncryptionError::Encrypt)?;
+
+        let mut combined = nonce.to_vec();
+        combined.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{SEALED_PREFIX}{key_id}:{}",
+            URL_SAFE_NO_PAD.encode(combined)
+        ))
+    }
+
+    /// Open a line written by [`Self::seal`]. Lines that were never sealed
+    /// are returned as they are.
+    pub fn open<'a>(&self, line: &'a str) -> Result<Cow<'a, str>, EncryptionError> {
+        let Some((key_id, payload)) = split_sealed(line)? else {
+            return Ok(Cow::Borrowed(line));
+        };
+        let key_bytes = self
+            .provider
+            .key(key_id)
+            .ok_or_else(|| EncryptionError::UnknownKey(key_id.to_string()))?;
+        let decoded = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| EncryptionError::Malformed)?;
+        if decoded.len() < NONCE_SIZE {
+            return Err(EncryptionError::Malformed);
+        }
+        let (nonce_bytes, ciphertext) = decoded.split_at(NONCE_SIZE);
+        let nonce_bytes: [u8; NONCE_SIZE] = nonce_bytes
+            .try_into()
+            .map_err(|_| EncryptionError::Malformed)?;
+        let cipher = Aes256Gcm::new(&Key::<Aes256Gcm>::from(key_bytes));
+        let plaintext = cipher
+            .decrypt(&Nonce::from(nonce_bytes), ciphertext)
+            .map_err(|_| EncryptionError::Decrypt(key_id.to_string()))?;
+        String::from_utf8(plaintext)
+            .map(Cow::Owned)
+            .map_err(|_| EncryptionError::Malformed)
+    }
+
+    /// Re-seal `line` with the current key. Returns `None` when it already
+    /// uses the current key.
+    pub fn rotate_line(&self, line: &str) -> Result<Option<String>, EncryptionError> {
+        if let Some((key_id, _)) = split_sealed(line)?
+            && key_id == self.provider.current_key_id()
+        {
+            return Ok(None);
+        }
+        let plaintext = self.open(line)?;
+        self.seal(&plaintext).map(Some)
+    }
+
+    /// Re-seal every line of a file with the current key, returning how many
+    /// lines changed
+    pub fn rotate_file(&self, path: &Path) -> io::Result<usize> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        let mut lines = Vec::new();
+        let mut rotated = 0;
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match self.rotate_line(&line)? {
+                Some(sealed) => {
+                    rotated += 1;
+                    lines.push(sealed);
+                }
+                None => lines.push(line),
+            }
+        }
+        if rotated > 0 {
+            let tmp = path.with_extension("rotating");
+            let mut writer = BufWriter::new(std::fs::File::create(&tmp)?);
+            for line in &lines {
+                writer.write_all(line.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+            std::fs::rename(tmp, path)?;
+        }
+        Ok(rotated)
+    }
+}
+
+/// Split a sealed line into its key id and payload
+fn split_sealed(line: &str) -> Result<Option<(&str, &str)>, EncryptionError> {
+    match line.strip_prefix(SEALED_PREFIX) {
+        Some(rest) => rest
+            .split_once(':')
+            .map(Some)
+            .ok_or(EncryptionError::Malformed),
+        None => Ok(None),
+    }
+}
+
+/// Seal a line for persisting, if encryption at rest is configured
+pub fn seal_line(line: &str) -> Result<Cow<'_, str>, EncryptionError> {
+    match Encryptor::global() {
+        Some(encryptor) => encryptor.seal(line).map(Cow::Owned),
+        None => Ok(Cow::Borrowed(line)),
+    }
+}
+
+/// Open a persisted line, which may or may not be sealed
+pub fn open_line(line: &str) -> Result<Cow<'_, str>, EncryptionError> {
+    if !Encryptor::is_sealed(line) {
+        return Ok(Cow::Borrowed(line));
+    }
+    match Encryptor::global() {
+        Some(encryptor) => encryptor
+            .open(line)
+            .map(|plaintext| Cow::Owned(plaintext.into_owned())),
+        None => Err(EncryptionError::NotConfigured),
+    }
+}
+
+/// Codec of persisted log stores, sealing their lines like other data
+pub const LINE_CODEC: LineCodec = LineCodec {
+    seal: |line| Ok(seal_line(line)?.into_owned()),
+    open: |line| Ok(open_line(line)?.into_owned()),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_and_rotate() {
+        let old = Encryptor::new(Arc::new(KeyRing::parse("2024:b2xkLXNlY3JldA==").unwrap()));
+        let rotated = Encryptor::new(Arc::new(
+            KeyRing::parse("2025:bmV3LXNlY3JldA==, 2024:b2xkLXNlY3JldA==").unwrap(),
+        ));
+        let line = r#"{"Stdout":"API_KEY=sk-123"}"#;
+
+        let sealed = old.seal(line).unwrap();
+        assert!(sealed.starts_with("vkenc1:2024:"));
+        assert!(!sealed.contains("sk-123"));
+        assert_eq!(old.open(&sealed).unwrap(), line);
+        assert_eq!(old.open(line).unwrap(), line);
+
+        let resealed = rotated.rotate_line(&sealed).unwrap().unwrap();
+        assert!(resealed.starts_with("vkenc1:2025:"));
+        assert_eq!(rotated.open(&resealed).unwrap(), line);
+        assert!(rotated.rotate_line(&resealed).unwrap().is_none());
+        assert!(matches!(
+            old.open(&resealed),
+            Err(EncryptionError::UnknownKey(id)) if id == "2025"
+        ));
+
+        let mut tampered = resealed.clone();
+        tampered.pop();
+        tampered.push(if resealed.ends_with('A') { 'B' } else { 'A' });
+        assert!(rotated.open(&tampered).is_err());
+        assert!(KeyRing::parse("no-id").is_err());
+    }
+}
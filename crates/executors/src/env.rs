@@ -1,27 +1,125 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::PathBuf,
+    time::Duration,
+};
 
 use tokio::process::Command;
 
-use crate::command::CmdOverrides;
+use crate::{
+    approvals::audit::ApprovalAuditLog,
+    authorization::Principal,
+    command::{CmdOverrides, CommandBuildError},
+    mcp_config::McpConfigScope,
+    profile::RunOverrides,
+    remote::SshRemote,
+    resource_limits::ResourceLimits,
+    tenant::TenantId,
+    worktree::AttemptWorktree,
+};
 
 /// Environment variables to inject into executor processes
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct ExecutionEnv {
     pub vars: HashMap<String, String>,
+    /// Keys of `vars` holding secrets, whose values are never shown
+    pub secrets: HashSet<String>,
+    /// Tenant whose profiles the execution runs with; `None` uses the
+    /// server's own
+    pub tenant: Option<TenantId>,
+    /// Who started the execution; `None` skips authorization
+    pub principal: Option<Principal>,
+    /// Where approval decisions are recorded; `None` keeps no audit trail
+    pub approval_audit: Option<ApprovalAuditLog>,
+    pub mcp_scope: McpConfigScope,
+    /// CPU, memory and process limits on the executor process
+    pub resource_limits: Option<ResourceLimits>,
+    /// Cgroup the executor process is limited in; `None` limits it with
+    /// rlimits only
+    pub cgroup_dir: Option<PathBuf>,
+    /// Host the executor runs on over SSH; `None` runs it locally
+    pub remote: Option<SshRemote>,
+    /// How long each attempt at starting the executor process may take;
+    /// `None` waits as long as it takes
+    pub spawn_timeout: Option<Duration>,
 }
 
 impl ExecutionEnv {
     pub fn new() -> Self {
         Self {
             vars: HashMap::new(),
+            secrets: HashSet::new(),
+            tenant: None,
+            principal: None,
+            approval_audit: None,
+            mcp_scope: McpConfigScope::User,
+            resource_limits: None,
+            cgroup_dir: None,
+            remote: None,
+            spawn_timeout: None,
         }
     }
 
+    pub fn with_principal(mut self, principal: Principal) -> Self {
+        self.principal = Some(principal);
+        self
+    }
+
+    pub fn with_approval_audit(mut self, log: ApprovalAuditLog) -> Self {
+        self.approval_audit = Some(log);
+        self
+    }
+
+    pub fn with_mcp_scope(mut self, scope: McpConfigScope) -> Self {
+        self.mcp_scope = scope;
+        self
+    }
+
+    pub fn with_spawn_timeout(mut self, timeout: Duration) -> Self {
+        self.spawn_timeout = Some(timeout);
+        self
+    }
+
+    /// Limit the executor in the cgroup at `dir`
+    pub fn with_cgroup_dir(mut self, dir: PathBuf) -> Self {
+        self.cgroup_dir = Some(dir);
+        self
+    }
+
+    /// Run as `tenant`, exposing it to the agent as `VK_TENANT_ID`
+    pub fn with_tenant(mut self, tenant: TenantId) -> Self {
+        self.insert("VK_TENANT_ID", tenant.as_str());
+        self.tenant = Some(tenant);
+        self
+    }
+
+    /// Run in `worktree`, exposing its branch, base branch and path to the
+    /// agent
+    pub fn with_worktree(mut self, worktree: &AttemptWorktree) -> Self {
+        self.insert("VK_WORKSPACE_BRANCH", &worktree.branch);
+        self.insert("VK_BASE_BRANCH", &worktree.base_branch);
+        self.insert("VK_WORKTREE_PATH", worktree.path.to_string_lossy());
+        self
+    }
+
     /// Insert an environment variable
     pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
         self.vars.insert(key.into(), value.into());
     }
 
+    /// Insert a variable whose value is kept out of debug output and command
+    /// explanations
+    pub fn insert_secret(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        self.secrets.insert(key.clone());
+        self.vars.insert(key, value.into());
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.secrets.contains(key)
+    }
+
     /// Merge additional vars into this env. Incoming keys overwrite existing ones.
     pub fn merge(&mut self, other: &HashMap<String, String>) {
         self.vars
@@ -34,8 +132,18 @@ impl ExecutionEnv {
         self
     }
 
-    /// Return a new env with profile env from CmdOverrides merged in.
-    pub fn with_profile(self, cmd: &CmdOverrides) -> Self {
+    /// Return a new env with the command's env, and the profile's resource
+    /// limits and remote host, merged in.
+    pub fn with_profile(mut self, cmd: &CmdOverrides, run: &RunOverrides) -> Self {
+        if let Some(remote) = &run.remote {
+            self.remote = Some(remote.clone());
+        }
+        if let Some(limits) = &run.runtime.resource_limits {
+            self.resource_limits = Some(match &self.resource_limits {
+                Some(base) => limits.or(base),
+                None => limits.clone(),
+            });
+        }
         if let Some(ref profile_env) = cmd.env {
             self.with_overrides(profile_env)
         } else {
@@ -43,11 +151,28 @@ impl ExecutionEnv {
         }
     }
 
-    /// Apply all environment variables to a Command
-    pub fn apply_to_command(&self, command: &mut Command) {
+    /// Apply all environment variables and resource limits to a Command.
+    /// With a remote host, the Command is replaced by one that runs it there;
+    /// variables set on it afterwards only reach the local `ssh`. Fails when it
+    /// can't be, so the agent is never spawned locally by mistake.
+    pub fn apply_to_command(&self, command: &mut Command) -> Result<(), CommandBuildError> {
         for (key, value) in &self.vars {
             command.env(key, value);
         }
+        if let Some(remote) = &self.remote {
+            *command = remote.wrap(command)?;
+            // Limits would apply to the local `ssh` only
+            return Ok(());
+        }
+        #[cfg(unix)]
+        if let Some(limits) = self
+            .resource_limits
+            .as_ref()
+            .filter(|limits| !limits.is_unlimited())
+        {
+            limits.apply(command, self.cgroup_dir.as_deref());
+        }
+        Ok(())
     }
 
     pub fn contains_key(&self, key: &str) -> bool {
@@ -55,12 +180,40 @@ impl ExecutionEnv {
     }
 }
 
+impl fmt::Debug for ExecutionEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let vars: HashMap<_, _> = self
+            .vars
+            .iter()
+            .map(|(key, value)| {
+                let value = if self.is_secret(key) {
+                    "<redacted>"
+                } else {
+                    value.as_str()
+                };
+                (key, value)
+            })
+            .collect();
+        f.debug_struct("ExecutionEnv")
+            .field("vars", &vars)
+            .field("tenant", &self.tenant)
+            .field("principal", &self.principal)
+            .field("approval_audit", &self.approval_audit)
+            .field("mcp_scope", &self.mcp_scope)
+            .field("resource_limits", &self.resource_limits)
+            .field("cgroup_dir", &self.cgroup_dir)
+            .field("remote", &self.remote)
+            .field("spawn_timeout", &self.spawn_timeout)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn profile_overrides_runtime_env() {
+    fn run_overrides_runtime_env() {
         let mut base = ExecutionEnv::default();
         base.insert("VK_PROJECT_NAME", "runtime");
         base.insert("FOO", "runtime");
@@ -20,9 +20,10 @@ use workspace_utils::{approvals::ApprovalStatus, stream_lines::LinesStreamExt};
 use super::{AcpClient, SessionManager};
 use crate::{
     approvals::ExecutorApprovalService,
-    command::{CmdOverrides, CommandParts},
+    command::CommandParts,
     env::ExecutionEnv,
     executors::{ExecutorError, ExecutorExitResult, SpawnedChild, acp::AcpEvent},
+    outcome::{FailureReason, RunSummary},
 };
 
 /// Reusable harness for ACP-based conns (Gemini, Qwen, etc.)
@@ -74,7 +75,6 @@ impl AcpAgentHarness {
         prompt: String,
         command_parts: CommandParts,
         env: &ExecutionEnv,
-        cmd_overrides: &CmdOverrides,
         approvals: Option<std::sync::Arc<dyn ExecutorApprovalService>>,
     ) -> Result<SpawnedChild, ExecutorError> {
         let (program_path, args) = command_parts.into_resolved().await?;
@@ -88,9 +88,7 @@ impl AcpAgentHarness {
             .args(&args)
             .env("NODE_NO_WARNINGS", "1");
 
-        env.clone()
-            .with_profile(cmd_overrides)
-            .apply_to_command(&mut command);
+        env.apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -112,6 +110,7 @@ impl AcpAgentHarness {
             child,
             exit_signal: Some(exit_rx),
             interrupt_sender: None,
+            input_sender: None,
         })
     }
 
@@ -123,7 +122,6 @@ impl AcpAgentHarness {
         session_id: &str,
         command_parts: CommandParts,
         env: &ExecutionEnv,
-        cmd_overrides: &CmdOverrides,
         approvals: Option<std::sync::Arc<dyn ExecutorApprovalService>>,
     ) -> Result<SpawnedChild, ExecutorError> {
         let (program_path, args) = command_parts.into_resolved().await?;
@@ -137,9 +135,7 @@ impl AcpAgentHarness {
             .args(&args)
             .env("NODE_NO_WARNINGS", "1");
 
-        env.clone()
-            .with_profile(cmd_overrides)
-            .apply_to_command(&mut command);
+        env.apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -161,6 +157,7 @@ impl AcpAgentHarness {
             child,
             exit_signal: Some(exit_rx),
             interrupt_sender: None,
+            input_sender: None,
         })
     }
 
@@ -435,6 +432,7 @@ impl AcpAgentHarness {
                         );
 
                         let mut current_req = Some(initial_req);
+                        let mut exit_result = ExecutorExitResult::Success(RunSummary::default());
 
                         while let Some(req) = current_req.take() {
                             tracing::trace!(?req, "sending ACP prompt request");
@@ -445,6 +443,12 @@ impl AcpAgentHarness {
                                     let stop_reason = serde_json::to_string(&resp.stop_reason)
                                         .unwrap_or_default();
                                     let _ = log_tx.send(AcpEvent::Done(stop_reason).to_string());
+                                    exit_result = match resp.stop_reason {
+                                        proto::StopReason::Cancelled => {
+                                            ExecutorExitResult::Interrupted
+                                        }
+                                        _ => ExecutorExitResult::Success(RunSummary::default()),
+                                    };
                                 }
                                 Err(e) => {
                                     tracing::debug!("error {} {e} {:?}", e.code, e.data);
@@ -459,6 +463,9 @@ impl AcpAgentHarness {
                                         let _ = log_tx
                                             .send(AcpEvent::Error(format!("{e}")).to_string());
                                     }
+                                    exit_result = ExecutorExitResult::Failed(FailureReason::Error(
+                                        e.to_string(),
+                                    ));
                                 }
                             }
 
@@ -484,7 +491,7 @@ impl AcpAgentHarness {
 
                         // Notify container of completion
                         if let Some(tx) = exit_signal_tx.take() {
-                            let _ = tx.send(ExecutorExitResult::Success);
+                            let _ = tx.send(exit_result);
                         }
 
                         // Cancel session work
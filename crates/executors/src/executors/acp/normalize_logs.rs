@@ -57,6 +57,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             },
                             content: msg,
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
                         msg_store.push_patch(ConversationPatch::add_normalized_entry(idx, entry));
                     }
@@ -85,6 +87,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                                     entry_type: NormalizedEntryType::AssistantMessage,
                                     content: s.content.clone(),
                                     metadata: None,
+                                    agent_id: None,
+                                    agent_role: None,
                                 };
                                 let patch = if is_new {
                                     ConversationPatch::add_normalized_entry(s.index, entry)
@@ -113,6 +117,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                                     entry_type: NormalizedEntryType::Thinking,
                                     content: s.content.clone(),
                                     metadata: None,
+                                    agent_id: None,
+                                    agent_role: None,
                                 };
                                 let patch = if is_new {
                                     ConversationPatch::add_normalized_entry(s.index, entry)
@@ -154,6 +160,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             },
                             content: "Plan updated".to_string(),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
                         msg_store.push_patch(ConversationPatch::add_normalized_entry(idx, entry));
                     }
@@ -168,6 +176,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             entry_type: NormalizedEntryType::SystemMessage,
                             content: body,
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
                         msg_store.push_patch(ConversationPatch::add_normalized_entry(idx, entry));
                     }
@@ -178,6 +188,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             entry_type: NormalizedEntryType::SystemMessage,
                             content: format!("Current mode: {}", mode_id.0),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
                         msg_store.push_patch(ConversationPatch::add_normalized_entry(idx, entry));
                     }
@@ -247,6 +259,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                                     .trim()
                                     .to_string(),
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
                             msg_store
                                 .push_patch(ConversationPatch::add_normalized_entry(idx, entry));
@@ -287,6 +301,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                     tool_call_id: tool_data.id.0.to_string(),
                 })
                 .ok(),
+                agent_id: None,
+                agent_role: None,
             };
             let patch = if is_new {
                 ConversationPatch::add_normalized_entry(tool_data.index, entry)
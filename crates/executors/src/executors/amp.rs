@@ -9,15 +9,18 @@ use ts_rs::TS;
 use workspace_utils::msg_store::MsgStore;
 
 use crate::{
-    command::{CmdOverrides, CommandBuilder, apply_overrides},
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, apply_overrides},
     env::ExecutionEnv,
     executors::{
-        AppendPrompt, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
+        AppendPrompt, AvailabilityInfo, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
         claude::{ClaudeLogProcessor, HistoryStrategy},
     },
     logs::{stderr_processor::normalize_stderr_logs, utils::EntryIndexProvider},
+    profile::RunOverrides,
 };
 
+const BASE_COMMAND: &str = "npx -y @sourcegraph/amp@0.0.1764777697-g907e30";
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
 pub struct Amp {
     #[serde(default)]
@@ -30,16 +33,43 @@ pub struct Amp {
     pub dangerously_allow_all: Option<bool>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
 }
 
 impl Amp {
     fn build_command_builder(&self) -> CommandBuilder {
-        let mut builder = CommandBuilder::new("npx -y @sourcegraph/amp@0.0.1764777697-g907e30")
-            .params(["--execute", "--stream-json"]);
+        let mut builder = CommandBuilder::new(BASE_COMMAND).params(["--execute", "--stream-json"]);
         if self.dangerously_allow_all.unwrap_or(false) {
             builder = builder.extend_params(["--dangerously-allow-all"]);
         }
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
+    }
+}
+
+/// Availability from Amp's directories under `home`: it keeps its API key in
+/// its data directory once logged in, and its settings in its config directory
+fn availability_in(home: &Path) -> AvailabilityInfo {
+    if let Some(timestamp) = std::fs::metadata(
+        home.join(".local")
+            .join("share")
+            .join("amp")
+            .join("secrets.json"),
+    )
+    .ok()
+    .and_then(|m| m.modified().ok())
+    .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+    .map(|d| d.as_secs() as i64)
+    {
+        return AvailabilityInfo::LoginDetected {
+            last_auth_timestamp: timestamp,
+        };
+    }
+
+    if home.join(".config").join("amp").is_dir() {
+        AvailabilityInfo::InstallationFound
+    } else {
+        AvailabilityInfo::NotFound
     }
 }
 
@@ -54,7 +84,9 @@ impl StandardCodingAgentExecutor for Amp {
         let command_parts = self.build_command_builder().build_initial()?;
         let (executable_path, args) = command_parts.into_resolved().await?;
 
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let mut command = Command::new(executable_path);
         command
@@ -66,8 +98,8 @@ impl StandardCodingAgentExecutor for Amp {
             .args(&args);
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -127,7 +159,9 @@ impl StandardCodingAgentExecutor for Amp {
         ])?;
         let (continue_program, continue_args) = continue_line.into_resolved().await?;
 
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let mut command = Command::new(continue_program);
         command
@@ -139,8 +173,8 @@ impl StandardCodingAgentExecutor for Amp {
             .args(&continue_args);
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -172,4 +206,47 @@ impl StandardCodingAgentExecutor for Amp {
     fn default_mcp_config_path(&self) -> Option<std::path::PathBuf> {
         dirs::home_dir().map(|home| home.join(".config").join("amp").join("settings.json"))
     }
+
+    fn get_availability_info(&self) -> AvailabilityInfo {
+        dirs::home_dir().map_or(AvailabilityInfo::NotFound, |home| availability_in(&home))
+    }
+
+    fn login_command(&self) -> Option<String> {
+        Some(format!("{BASE_COMMAND} login"))
+    }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        Ok(self.build_command_builder().explain(env, &self.cmd)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_availability_from_amp_directories() {
+        let home = std::env::temp_dir().join(format!("vk-amp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&home).unwrap();
+        assert!(matches!(availability_in(&home), AvailabilityInfo::NotFound));
+
+        std::fs::create_dir_all(home.join(".config").join("amp")).unwrap();
+        assert!(matches!(
+            availability_in(&home),
+            AvailabilityInfo::InstallationFound
+        ));
+
+        let data_dir = home.join(".local").join("share").join("amp");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("secrets.json"), "{}").unwrap();
+        assert!(matches!(
+            availability_in(&home),
+            AvailabilityInfo::LoginDetected { .. }
+        ));
+        std::fs::remove_dir_all(home).unwrap();
+    }
 }
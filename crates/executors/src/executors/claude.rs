@@ -1,16 +1,22 @@
 // SDK submodules
 pub mod client;
 pub mod protocol;
+pub mod transcript;
 pub mod types;
 
-use std::{collections::HashMap, path::Path, process::Stdio, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    process::Stdio,
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use command_group::AsyncCommandGroup;
 use futures::StreamExt;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::process::Command;
+use tokio::{process::Command, sync::oneshot};
 use ts_rs::TS;
 use workspace_utils::{
     approvals::ApprovalStatus, diff::create_unified_diff, log_msg::LogMsg, msg_store::MsgStore,
@@ -24,18 +30,33 @@ use self::{
 };
 use crate::{
     approvals::ExecutorApprovalService,
-    command::{CmdOverrides, CommandBuilder, CommandParts, apply_overrides},
+    approvals::policy::ApprovalPolicy,
+    attachments::{Attachment, mention_attachments},
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, CommandParts, apply_overrides},
+    credentials::SavedLogin,
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
-        codex::client::LogWriter,
+        claude_flow, codex::client::LogWriter,
     },
     logs::{
-        ActionType, FileChange, NormalizedEntry, NormalizedEntryError, NormalizedEntryType,
-        TodoItem, ToolStatus,
+        ActionType, CommandRunResult, FileChange, MemoryOperation, NormalizedEntry,
+        NormalizedEntryError, NormalizedEntryType, TodoItem, ToolResult, ToolResultValueType,
+        ToolStatus,
         stderr_processor::normalize_stderr_logs,
-        utils::{EntryIndexProvider, patch::ConversationPatch},
+        transcript::{estimate_tokens, truncate_middle},
+        utils::{
+            EntryIndexProvider,
+            json_recovery::{Recovered, recover_json_line},
+            patch::{ConversationPatch, attribute_entries},
+        },
     },
+    permission_mode::AgentPermissionMode,
+    probe,
+    profile::RunOverrides,
+    secrets,
+    standby::StandbyConfig,
+    standby::WarmSession,
     stdout_dup::create_stdout_pipe_writer,
 };
 
@@ -49,6 +70,76 @@ fn base_command(claude_code_router: bool) -> &'static str {
 
 use derivative::Derivative;
 
+/// Tools that change the worktree, which read-only runs go without
+const READ_ONLY_DISALLOWED_TOOLS: &str = "Bash,Edit,MultiEdit,Write,NotebookEdit";
+
+/// Model a Claude-family agent runs, by the alias of its latest version or
+/// by its full name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaudeModel {
+    Sonnet,
+    Opus,
+    Haiku,
+    /// Full model name, e.g. `claude-sonnet-4-5-20250929`
+    #[serde(untagged)]
+    Other(#[schemars(pattern(r"^claude-"))] String),
+}
+
+impl ClaudeModel {
+    /// Value of the CLI's `--model` and `--fallback-model` flags
+    pub fn as_str(&self) -> &str {
+        match self {
+            ClaudeModel::Sonnet => "sonnet",
+            ClaudeModel::Opus => "opus",
+            ClaudeModel::Haiku => "haiku",
+            ClaudeModel::Other(name) => name,
+        }
+    }
+}
+
+/// How hard a Claude-family agent thinks before it answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaudeReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ClaudeReasoningEffort {
+    /// Thinking budget of the effort, as Claude Code gives its `think`,
+    /// `think hard` and `ultrathink` keywords
+    pub fn thinking_tokens(self) -> u32 {
+        match self {
+            ClaudeReasoningEffort::Low => 4_000,
+            ClaudeReasoningEffort::Medium => 10_000,
+            ClaudeReasoningEffort::High => 31_999,
+        }
+    }
+}
+
+/// Value of the CLI's `--max-thinking-tokens` flag; an explicit budget wins
+/// over an effort
+pub(crate) fn thinking_budget(
+    max_thinking_tokens: Option<u32>,
+    reasoning_effort: Option<ClaudeReasoningEffort>,
+) -> Option<u32> {
+    max_thinking_tokens.or(reasoning_effort.map(ClaudeReasoningEffort::thinking_tokens))
+}
+
+/// `env` with `ANTHROPIC_API_KEY` set from `api_key_secret`, if given
+pub(crate) fn with_api_key(
+    mut env: ExecutionEnv,
+    api_key_secret: Option<&str>,
+) -> Result<ExecutionEnv, ExecutorError> {
+    if let Some(name) = api_key_secret {
+        let secret = secrets::resolve(name, env.tenant.as_ref())?;
+        env.insert_secret("ANTHROPIC_API_KEY", secret);
+    }
+    Ok(env)
+}
+
 #[derive(Derivative, Clone, Serialize, Deserialize, TS, JsonSchema)]
 #[derivative(Debug, PartialEq)]
 pub struct ClaudeCode {
@@ -61,13 +152,46 @@ pub struct ClaudeCode {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub approvals: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub model: Option<String>,
+    pub model: Option<ClaudeModel>,
+    /// Model to switch to when the main one is overloaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_model: Option<ClaudeModel>,
+    /// Tokens the model may spend thinking before each answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_thinking_tokens: Option<u32>,
+    /// Thinking budget by level, when `max_thinking_tokens` isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ClaudeReasoningEffort>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub dangerously_skip_permissions: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_api_key: Option<bool>,
+    /// Secret holding the `ANTHROPIC_API_KEY` to run with, for headless
+    /// deployments without a Claude login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_secret: Option<String>,
+    #[schemars(
+        title = "Warm Standby",
+        description = "Keep an idle pre-spawned session ready so the next task in the same workspace starts immediately. Not used with plan or approvals, which need the prompt before starting"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_standby: Option<StandbyConfig>,
+    #[schemars(
+        title = "Approval Policy",
+        description = "Rules that allow, deny or ask about tool calls by tool name, file path globs and command regexes, checked in order before asking for approval"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalPolicy>,
+    #[schemars(
+        title = "Interactive",
+        description = "Keep the agent's input open so messages can be sent while it runs"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interactive: Option<bool>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
 
     #[serde(skip)]
     #[ts(skip)]
@@ -76,6 +200,16 @@ pub struct ClaudeCode {
 }
 
 impl ClaudeCode {
+    /// [`AvailabilityInfo::LoginExpired`] if the saved Claude login has
+    /// expired; the router logs in to its own providers, and headless runs
+    /// use an API key instead
+    fn login_expired(&self) -> Option<AvailabilityInfo> {
+        if self.claude_code_router.unwrap_or(false) || self.api_key_secret.is_some() {
+            return None;
+        }
+        SavedLogin::claude(&dirs::home_dir()?)?.expired()
+    }
+
     async fn build_command_builder(&self) -> CommandBuilder {
         // If base_command_override is provided and claude_code_router is also set, log a warning
         if self.cmd.base_command_override.is_some() && self.claude_code_router.is_some() {
@@ -93,7 +227,7 @@ impl ClaudeCode {
         if plan && approvals {
             tracing::warn!("Both plan and approvals are enabled. Plan will take precedence.");
         }
-        if plan || approvals {
+        if self.permission_mode() != PermissionMode::BypassPermissions {
             // Enable bypass at startup, otherwise we cannot change to it after exiting plan mode
             builder = builder.extend_params(["--permission-prompt-tool=stdio"]);
             builder = builder.extend_params([format!(
@@ -105,20 +239,41 @@ impl ClaudeCode {
             builder = builder.extend_params(["--dangerously-skip-permissions"]);
         }
         if let Some(model) = &self.model {
-            builder = builder.extend_params(["--model", model]);
+            builder = builder.extend_params(["--model", model.as_str()]);
         }
+        if let Some(model) = &self.fallback_model {
+            builder = builder.extend_params(["--fallback-model", model.as_str()]);
+        }
+        if let Some(tokens) = thinking_budget(self.max_thinking_tokens, self.reasoning_effort) {
+            builder =
+                builder.extend_params(["--max-thinking-tokens".to_string(), tokens.to_string()]);
+        }
+        // Plan mode alone doesn't stop tools the hooks approve, so read-only
+        // runs don't get the tools that change anything, nor a way out
+        let disallowed_tools = if self.run.permission_mode == Some(AgentPermissionMode::ReadOnly) {
+            format!("AskUserQuestion,ExitPlanMode,{READ_ONLY_DISALLOWED_TOOLS}")
+        } else {
+            "AskUserQuestion".to_string()
+        };
         builder = builder.extend_params([
-            "--verbose",
-            "--output-format=stream-json",
-            "--input-format=stream-json",
-            "--include-partial-messages",
-            "--disallowedTools=AskUserQuestion",
+            "--verbose".to_string(),
+            "--output-format=stream-json".to_string(),
+            "--input-format=stream-json".to_string(),
+            "--include-partial-messages".to_string(),
+            format!("--disallowedTools={disallowed_tools}"),
         ]);
 
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
     }
 
     pub fn permission_mode(&self) -> PermissionMode {
+        if let Some(mode) = self.run.permission_mode {
+            return match mode {
+                AgentPermissionMode::Plan | AgentPermissionMode::ReadOnly => PermissionMode::Plan,
+                AgentPermissionMode::AcceptEdits => PermissionMode::AcceptEdits,
+                AgentPermissionMode::Bypass => PermissionMode::BypassPermissions,
+            };
+        }
         if self.plan.unwrap_or(false) {
             PermissionMode::Plan
         } else if self.approvals.unwrap_or(false) {
@@ -129,8 +284,8 @@ impl ClaudeCode {
     }
 
     pub fn get_hooks(&self) -> Option<serde_json::Value> {
-        if self.plan.unwrap_or(false) {
-            Some(serde_json::json!({
+        match self.permission_mode() {
+            PermissionMode::Plan => Some(serde_json::json!({
                 "PreToolUse": [
                     {
                         "matcher": "^ExitPlanMode$",
@@ -141,18 +296,24 @@ impl ClaudeCode {
                         "hookCallbackIds": [AUTO_APPROVE_CALLBACK_ID],
                     }
                 ]
-            }))
-        } else if self.approvals.unwrap_or(false) {
-            Some(serde_json::json!({
+            })),
+            PermissionMode::Default => Some(serde_json::json!({
                 "PreToolUse": [
                     {
                         "matcher": "^(?!(Glob|Grep|NotebookRead|Read|Task|TodoWrite)$).*",
                         "hookCallbackIds": ["tool_approval"],
                     }
                 ]
-            }))
-        } else {
-            None
+            })),
+            PermissionMode::AcceptEdits => Some(serde_json::json!({
+                "PreToolUse": [
+                    {
+                        "matcher": "^(?!(Glob|Grep|NotebookRead|Read|Task|TodoWrite|Edit|MultiEdit|Write|NotebookEdit)$).*",
+                        "hookCallbackIds": ["tool_approval"],
+                    }
+                ]
+            })),
+            PermissionMode::BypassPermissions => None,
         }
     }
 }
@@ -169,26 +330,63 @@ impl StandardCodingAgentExecutor for ClaudeCode {
         prompt: &str,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
+        self.spawn_with_attachments(current_dir, prompt, &[], env)
+            .await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        session_id: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        // Follow-ups fork too, so retrying from an earlier turn never rewrites
+        // the session it resumes
+        self.spawn_fork(current_dir, prompt, session_id, env).await
+    }
+
+    async fn spawn_fork(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        parent_session_id: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        self.spawn_follow_up_with_attachments(current_dir, prompt, parent_session_id, &[], env)
+            .await
+    }
+
+    async fn spawn_with_attachments(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        attachments: &[Attachment],
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let (prompt, images) = split_attachments(current_dir, prompt, attachments)?;
         let command_builder = self.build_command_builder().await;
         let command_parts = command_builder.build_initial()?;
-        self.spawn_internal(current_dir, prompt, command_parts, env)
+        self.spawn_internal(current_dir, &prompt, images, command_parts, env)
             .await
     }
 
-    async fn spawn_follow_up(
+    async fn spawn_follow_up_with_attachments(
         &self,
         current_dir: &Path,
         prompt: &str,
         session_id: &str,
+        attachments: &[Attachment],
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
+        let (prompt, images) = split_attachments(current_dir, prompt, attachments)?;
         let command_builder = self.build_command_builder().await;
         let command_parts = command_builder.build_follow_up(&[
             "--fork-session".to_string(),
             "--resume".to_string(),
             session_id.to_string(),
         ])?;
-        self.spawn_internal(current_dir, prompt, command_parts, env)
+        self.spawn_internal(current_dir, &prompt, images, command_parts, env)
             .await
     }
 
@@ -213,6 +411,16 @@ impl StandardCodingAgentExecutor for ClaudeCode {
     }
 
     fn get_availability_info(&self) -> AvailabilityInfo {
+        // Headless runs need their API key rather than a login
+        if let Some(name) = &self.api_key_secret {
+            return match secrets::resolve(name, None) {
+                Ok(_) => AvailabilityInfo::InstallationFound,
+                Err(_) => AvailabilityInfo::NotFound,
+            };
+        }
+        if let Some(expired) = self.login_expired() {
+            return expired;
+        }
         let auth_file_path = dirs::home_dir().map(|home| home.join(".claude.json"));
 
         if let Some(path) = auth_file_path
@@ -228,6 +436,49 @@ impl StandardCodingAgentExecutor for ClaudeCode {
         }
         AvailabilityInfo::NotFound
     }
+
+    async fn probe(&self) -> AvailabilityInfo {
+        let base = self
+            .cmd
+            .base_command_override
+            .as_deref()
+            .unwrap_or_else(|| base_command(self.claude_code_router.unwrap_or(false)));
+        // An installed CLI is no use without its API key, or while its login
+        // has expired
+        if let Some(name) = &self.api_key_secret
+            && secrets::resolve(name, None).is_err()
+        {
+            return AvailabilityInfo::NotFound;
+        }
+        if let Some(expired) = self.login_expired() {
+            return expired;
+        }
+        match probe::probe_availability(base, self.min_supported_version()).await {
+            Some(info) => info,
+            None => self.get_availability_info(),
+        }
+    }
+
+    fn min_supported_version(&self) -> Option<&'static str> {
+        // First release with stream-json input and output
+        Some("1.0.0")
+    }
+
+    fn compact_command(&self) -> Option<&'static str> {
+        Some("/compact")
+    }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        let env = with_api_key(env.clone(), self.api_key_secret.as_deref())?;
+        Ok(self
+            .build_command_builder()
+            .await
+            .explain(&env, &self.cmd)?)
+    }
 }
 
 impl ClaudeCode {
@@ -235,11 +486,55 @@ impl ClaudeCode {
         &self,
         current_dir: &Path,
         prompt: &str,
+        images: Vec<(String, String)>,
         command_parts: CommandParts,
         env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let (prompt_tx, prompt_rx) = oneshot::channel();
+        let _ = prompt_tx.send(self.append_prompt.frame_prompt(
+            self.run.prompt.as_ref(),
+            prompt,
+            env,
+        ));
+        self.start_session(current_dir, command_parts, env, prompt_rx, images)
+            .await
+    }
+
+    /// Start a session for a warm standby pool: the process is spawned and
+    /// initialized now, and waits for the prompt of the task that claims it.
+    ///
+    /// Returns `None` when tool calls would need approval, since approvals are
+    /// bound to the execution that spawns the process.
+    pub async fn spawn_warm(
+        &self,
+        current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<Option<WarmSession>, ExecutorError> {
+        if self.permission_mode() != PermissionMode::BypassPermissions {
+            return Ok(None);
+        }
+        let mut agent = self.clone();
+        agent.approvals_service = None;
+        let command_parts = agent.build_command_builder().await.build_initial()?;
+        let (prompt_tx, prompt_rx) = oneshot::channel();
+        let spawned = agent
+            .start_session(current_dir, command_parts, env, prompt_rx, Vec::new())
+            .await?;
+        Ok(Some(
+            WarmSession::new(spawned, prompt_tx, self.append_prompt.clone())
+                .with_template(self.run.prompt.clone()),
+        ))
+    }
+
+    async fn start_session(
+        &self,
+        current_dir: &Path,
+        command_parts: CommandParts,
+        env: &ExecutionEnv,
+        prompt_rx: oneshot::Receiver<String>,
+        images: Vec<(String, String)>,
     ) -> Result<SpawnedChild, ExecutorError> {
         let (program_path, args) = command_parts.into_resolved().await?;
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
 
         let mut command = Command::new(program_path);
         command
@@ -250,12 +545,15 @@ impl ClaudeCode {
             .current_dir(current_dir)
             .args(&args);
 
-        env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+        with_api_key(
+            env.clone().with_profile(&self.cmd, &self.run),
+            self.api_key_secret.as_deref(),
+        )?
+        .apply_to_command(&mut command)?;
 
-        // Remove ANTHROPIC_API_KEY if disable_api_key is enabled
-        if self.disable_api_key.unwrap_or(false) {
+        // Remove ANTHROPIC_API_KEY if disable_api_key is enabled, unless the
+        // profile runs headless with its own
+        if self.disable_api_key.unwrap_or(false) && self.api_key_secret.is_none() {
             command.env_remove("ANTHROPIC_API_KEY");
             tracing::info!("ANTHROPIC_API_KEY removed from environment");
         }
@@ -275,15 +573,25 @@ impl ClaudeCode {
 
         // Create interrupt channel for graceful shutdown
         let (interrupt_tx, interrupt_rx) = tokio::sync::oneshot::channel::<()>();
+        // Interactive sessions take user messages while they run
+        let (input_tx, input_rx) = self
+            .interactive
+            .unwrap_or(false)
+            .then(tokio::sync::mpsc::unbounded_channel)
+            .unzip();
 
         // Spawn task to handle the SDK client with control protocol
-        let prompt_clone = combined_prompt.clone();
         let approvals_clone = self.approvals_service.clone();
         tokio::spawn(async move {
             let log_writer = LogWriter::new(new_stdout);
             let client = ClaudeAgentClient::new(log_writer.clone(), approvals_clone);
-            let protocol_peer =
-                ProtocolPeer::spawn(child_stdin, child_stdout, client.clone(), interrupt_rx);
+            let protocol_peer = ProtocolPeer::spawn(
+                child_stdin,
+                child_stdout,
+                client.clone(),
+                interrupt_rx,
+                input_rx,
+            );
 
             // Initialize control protocol
             if let Err(e) = protocol_peer.initialize(hooks).await {
@@ -298,8 +606,17 @@ impl ClaudeCode {
                 tracing::warn!("Failed to set permission mode to {permission_mode}: {e}");
             }
 
+            // Wait for the prompt; a warm session that is recycled unclaimed
+            // drops the sender
+            let Ok(prompt) = prompt_rx.await else {
+                return;
+            };
+
             // Send user message
-            if let Err(e) = protocol_peer.send_user_message(prompt_clone).await {
+            if let Err(e) = protocol_peer
+                .send_user_message_with_images(prompt, images)
+                .await
+            {
                 tracing::error!("Failed to send prompt: {e}");
                 let _ = log_writer
                     .log_raw(&format!("Error: Failed to send prompt - {e}"))
@@ -311,18 +628,47 @@ impl ClaudeCode {
             child,
             exit_signal: None,
             interrupt_sender: Some(interrupt_tx),
+            input_sender: input_tx,
         })
     }
 }
 
+/// Images among `attachments`, sent as content blocks, and `prompt` with the
+/// remaining files mentioned
+fn split_attachments(
+    current_dir: &Path,
+    prompt: &str,
+    attachments: &[Attachment],
+) -> Result<(String, Vec<(String, String)>), ExecutorError> {
+    let mut images = Vec::new();
+    let mut files = Vec::new();
+    for attachment in attachments {
+        match attachment.image_source(current_dir)? {
+            Some(image) => images.push(image),
+            None => files.push(attachment.clone()),
+        }
+    }
+    Ok((mention_attachments(prompt, &files, current_dir)?, images))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HistoryStrategy {
     // Claude-code format
     Default,
     // Amp threads format which includes logs from previous executions
     AmpResume,
+    // Resumed sessions replaying their earlier turns, all kept as they were
+    FullReplay,
+    // Resumed sessions replaying their earlier turns, only the last n kept
+    LastTurns(usize),
+    // Resumed sessions replaying their earlier turns, tool outputs of all but
+    // the current one compacted
+    Compacted,
 }
 
+/// Tokens of a tool's output kept once its turn is compacted
+const COMPACTED_OUTPUT_TOKENS: usize = 200;
+
 /// Handles log processing and interpretation for Claude executor
 pub struct ClaudeLogProcessor {
     model_name: Option<String>,
@@ -332,6 +678,12 @@ pub struct ClaudeLogProcessor {
     strategy: HistoryStrategy,
     streaming_messages: HashMap<String, StreamingMessageState>,
     streaming_message_id: Option<String>,
+    // claude-flow swarm agent the output currently comes from
+    swarm_agent: Option<claude_flow::SwarmAgent>,
+    // Entry index each turn kept under HistoryStrategy::LastTurns starts at
+    turn_starts: VecDeque<usize>,
+    // Tool results of the current turn, to compact under HistoryStrategy::Compacted
+    turn_tool_results: Vec<(usize, NormalizedEntry)>,
 }
 
 impl ClaudeLogProcessor {
@@ -347,6 +699,9 @@ impl ClaudeLogProcessor {
             strategy,
             streaming_messages: HashMap::new(),
             streaming_message_id: None,
+            swarm_agent: None,
+            turn_starts: VecDeque::new(),
+            turn_tool_results: Vec::new(),
         }
     }
 
@@ -364,6 +719,60 @@ impl ClaudeLogProcessor {
             let worktree_path = current_dir_clone.to_string_lossy().to_string();
             let mut session_id_extracted = false;
             let mut processor = Self::new_with_strategy(strategy);
+            let mut process_line = |line: &str| {
+                // Non-JSON output, e.g. npm warnings, can share a line with events
+                let parts = match serde_json::from_str::<ClaudeJson>(line) {
+                    Ok(claude_json) => vec![Recovered::Json(claude_json)],
+                    Err(_) => recover_json_line(line),
+                };
+                for part in parts {
+                    let claude_json = match part {
+                        Recovered::Json(claude_json) => claude_json,
+                        Recovered::Raw(raw) => {
+                            let entry = NormalizedEntry {
+                                timestamp: None,
+                                entry_type: NormalizedEntryType::RawOutput,
+                                content: raw.to_string(),
+                                metadata: None,
+                                agent_id: None,
+                                agent_role: None,
+                            };
+                            let patch_id = entry_index_provider.next();
+                            let patch = ConversationPatch::add_normalized_entry(patch_id, entry);
+                            msg_store.push_patch(patch);
+                            continue;
+                        }
+                    };
+
+                    // Extract session ID if present
+                    if !session_id_extracted
+                        && let Some(session_id) = Self::extract_session_id(&claude_json)
+                    {
+                        msg_store.push_session_id(session_id);
+                        session_id_extracted = true;
+                    }
+
+                    if let ClaudeJson::Unknown { data } = &claude_json
+                        && let Some(agent) = claude_flow::chaining_agent(data)
+                    {
+                        // Only announces where the output that follows comes from
+                        processor.swarm_agent = Some(agent);
+                        continue;
+                    }
+
+                    let patches = processor.normalize_entries(
+                        &claude_json,
+                        &worktree_path,
+                        &entry_index_provider,
+                    );
+                    for mut patch in patches {
+                        if let Some(agent) = &processor.swarm_agent {
+                            attribute_entries(&mut patch, &agent.id, agent.role.as_deref());
+                        }
+                        msg_store.push_patch(patch);
+                    }
+                }
+            };
 
             while let Some(Ok(msg)) = stream.next().await {
                 let chunk = match msg {
@@ -371,7 +780,8 @@ impl ClaudeLogProcessor {
                     LogMsg::JsonPatch(_)
                     | LogMsg::SessionId(_)
                     | LogMsg::Stderr(_)
-                    | LogMsg::Ready => continue,
+                    | LogMsg::Ready
+                    | LogMsg::Heartbeat(_) => continue,
                     LogMsg::Finished => break,
                 };
 
@@ -397,42 +807,7 @@ impl ClaudeLogProcessor {
                         continue;
                     }
 
-                    match serde_json::from_str::<ClaudeJson>(trimmed) {
-                        Ok(claude_json) => {
-                            // Extract session ID if present
-                            if !session_id_extracted
-                                && let Some(session_id) = Self::extract_session_id(&claude_json)
-                            {
-                                msg_store.push_session_id(session_id);
-                                session_id_extracted = true;
-                            }
-
-                            let patches = processor.normalize_entries(
-                                &claude_json,
-                                &worktree_path,
-                                &entry_index_provider,
-                            );
-                            for patch in patches {
-                                msg_store.push_patch(patch);
-                            }
-                        }
-                        Err(_) => {
-                            // Handle non-JSON output as raw system message
-                            if !trimmed.is_empty() {
-                                let entry = NormalizedEntry {
-                                    timestamp: None,
-                                    entry_type: NormalizedEntryType::SystemMessage,
-                                    content: trimmed.to_string(),
-                                    metadata: None,
-                                };
-
-                                let patch_id = entry_index_provider.next();
-                                let patch =
-                                    ConversationPatch::add_normalized_entry(patch_id, entry);
-                                msg_store.push_patch(patch);
-                            }
-                        }
-                    }
+                    process_line(trimmed);
                 }
 
                 // Keep the partial line in the buffer
@@ -441,20 +816,46 @@ impl ClaudeLogProcessor {
 
             // Handle any remaining content in buffer
             if !buffer.trim().is_empty() {
-                let entry = NormalizedEntry {
-                    timestamp: None,
-                    entry_type: NormalizedEntryType::SystemMessage,
-                    content: buffer.trim().to_string(),
-                    metadata: None,
-                };
-
-                let patch_id = entry_index_provider.next();
-                let patch = ConversationPatch::add_normalized_entry(patch_id, entry);
-                msg_store.push_patch(patch);
+                process_line(buffer.trim());
             }
         });
     }
 
+    /// Start a turn under [`HistoryStrategy::LastTurns`], removing the entries
+    /// of the oldest turns so that at most `turns` remain
+    fn drop_oldest_turns(
+        &mut self,
+        turns: usize,
+        entry_index_provider: &EntryIndexProvider,
+        patches: &mut Vec<json_patch::Patch>,
+    ) {
+        self.turn_starts.push_back(entry_index_provider.current());
+        while self.turn_starts.len() > turns.max(1) {
+            self.turn_starts.pop_front();
+            // Everything before the turn that is now the oldest goes
+            let dropped = self.turn_starts.front().copied().unwrap_or_default();
+            for _ in 0..dropped {
+                patches.push(ConversationPatch::remove_diff(0.to_string()));
+            }
+            entry_index_provider.rewind(dropped);
+            for start in &mut self.turn_starts {
+                *start -= dropped;
+            }
+            self.tool_map.retain(|_, info| info.entry_index >= dropped);
+            for info in self.tool_map.values_mut() {
+                info.entry_index -= dropped;
+            }
+            self.streaming_messages.clear();
+        }
+    }
+
+    /// Remember a tool result of the current turn to compact once it ends
+    fn record_tool_result(&mut self, index: usize, entry: &NormalizedEntry) {
+        if self.strategy == HistoryStrategy::Compacted {
+            self.turn_tool_results.push((index, entry.clone()));
+        }
+    }
+
     /// Extract session ID from Claude JSON
     fn extract_session_id(claude_json: &ClaudeJson) -> Option<String> {
         match claude_json {
@@ -483,6 +884,8 @@ impl ClaudeLogProcessor {
                     },
                     content: "Claude Code + ANTHROPIC_API_KEY detected. Usage will be billed via Anthropic pay-as-you-go instead of your Claude subscription. If this is unintended, please select the `disable_api_key` checkbox in the conding-agent-configurations settings page.".to_string(),
                     metadata: None,
+                    agent_id: None,
+                    agent_role: None,
                 })
             }
             _ => None,
@@ -546,6 +949,8 @@ impl ClaudeLogProcessor {
                     metadata: Some(
                         serde_json::to_value(content_item).unwrap_or(serde_json::Value::Null),
                     ),
+                    agent_id: None,
+                    agent_role: None,
                 })
             }
             ClaudeContentItem::Thinking { thinking } => Some(NormalizedEntry {
@@ -555,6 +960,16 @@ impl ClaudeLogProcessor {
                 metadata: Some(
                     serde_json::to_value(content_item).unwrap_or(serde_json::Value::Null),
                 ),
+                agent_id: None,
+                agent_role: None,
+            }),
+            ClaudeContentItem::RedactedThinking { .. } => Some(NormalizedEntry {
+                timestamp: None,
+                entry_type: NormalizedEntryType::Thinking,
+                content: "Thinking redacted by the model provider".to_string(),
+                metadata: None,
+                agent_id: None,
+                agent_role: None,
             }),
             ClaudeContentItem::ToolUse { tool_data, id } => {
                 let name = tool_data.get_name();
@@ -581,6 +996,8 @@ impl ClaudeLogProcessor {
                     },
                     content,
                     metadata: Some(metadata),
+                    agent_id: None,
+                    agent_role: None,
                 })
             }
             ClaudeContentItem::ToolResult { .. } => {
@@ -725,6 +1142,9 @@ impl ClaudeLogProcessor {
                         .and_then(|v| serde_json::from_value::<ClaudeToolWithInput>(v).ok())
                         .map(|w| w.input)
                         .unwrap_or(serde_json::Value::Null);
+                    if let Some(memory) = claude_flow::memory_action(name, &args) {
+                        return memory;
+                    }
                     ActionType::Tool {
                         tool_name: label,
                         arguments: Some(args),
@@ -774,6 +1194,8 @@ impl ClaudeLogProcessor {
                                 serde_json::to_value(claude_json)
                                     .unwrap_or(serde_json::Value::Null),
                             ),
+                            agent_id: None,
+                            agent_role: None,
                         };
                         let idx = entry_index_provider.next();
                         patches.push(ConversationPatch::add_normalized_entry(idx, entry));
@@ -787,6 +1209,8 @@ impl ClaudeLogProcessor {
                                 serde_json::to_value(claude_json)
                                     .unwrap_or(serde_json::Value::Null),
                             ),
+                            agent_id: None,
+                            agent_role: None,
                         };
                         let idx = entry_index_provider.next();
                         patches.push(ConversationPatch::add_normalized_entry(idx, entry));
@@ -837,6 +1261,8 @@ impl ClaudeLogProcessor {
                                 },
                                 content: content_text.clone(),
                                 metadata: Some(metadata),
+                                agent_id: None,
+                                agent_role: None,
                             };
                             let is_new = entry_index.is_none();
                             let id_num = entry_index.unwrap_or_else(|| entry_index_provider.next());
@@ -856,7 +1282,9 @@ impl ClaudeLogProcessor {
                             };
                             patches.push(patch);
                         }
-                        ClaudeContentItem::Text { .. } | ClaudeContentItem::Thinking { .. } => {
+                        ClaudeContentItem::Text { .. }
+                        | ClaudeContentItem::Thinking { .. }
+                        | ClaudeContentItem::RedactedThinking { .. } => {
                             if let Some(entry) = Self::content_item_to_normalized_entry(
                                 item,
                                 &message.role,
@@ -878,19 +1306,34 @@ impl ClaudeLogProcessor {
                 }
             }
             ClaudeJson::User { message, .. } => {
-                if matches!(self.strategy, HistoryStrategy::AmpResume)
+                if self.strategy != HistoryStrategy::Default
                     && message
                         .content
                         .iter()
                         .any(|c| matches!(c, ClaudeContentItem::Text { .. }))
                 {
-                    let cur = entry_index_provider.current();
-                    if cur > 0 {
-                        for _ in 0..cur {
-                            patches.push(ConversationPatch::remove_diff(0.to_string()));
+                    match self.strategy {
+                        HistoryStrategy::AmpResume => {
+                            let cur = entry_index_provider.current();
+                            if cur > 0 {
+                                for _ in 0..cur {
+                                    patches.push(ConversationPatch::remove_diff(0.to_string()));
+                                }
+                                entry_index_provider.reset();
+                                self.tool_map.clear();
+                            }
                         }
-                        entry_index_provider.reset();
-                        self.tool_map.clear();
+                        HistoryStrategy::LastTurns(turns) => {
+                            self.drop_oldest_turns(turns, entry_index_provider, &mut patches);
+                        }
+                        HistoryStrategy::Compacted => {
+                            for (index, entry) in self.turn_tool_results.drain(..) {
+                                if let Some(entry) = compact_tool_output(entry) {
+                                    patches.push(ConversationPatch::replace(index, entry));
+                                }
+                            }
+                        }
+                        HistoryStrategy::Default | HistoryStrategy::FullReplay => {}
                     }
 
                     for item in &message.content {
@@ -902,6 +1345,8 @@ impl ClaudeLogProcessor {
                                 metadata: Some(
                                     serde_json::to_value(item).unwrap_or(serde_json::Value::Null),
                                 ),
+                                agent_id: None,
+                                agent_role: None,
                             };
                             let id = entry_index_provider.next();
                             patches.push(ConversationPatch::add_normalized_entry(id, entry));
@@ -980,7 +1425,10 @@ impl ClaudeLogProcessor {
                                 },
                                 content: info.content.clone(),
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
+                            self.record_tool_result(info.entry_index, &entry);
                             patches.push(ConversationPatch::replace(info.entry_index, entry));
                         } else if matches!(
                             info.tool_data,
@@ -1018,23 +1466,28 @@ impl ClaudeLogProcessor {
                                 ToolStatus::Success
                             };
 
+                            let action_type = claude_flow::memory_action(&tool_name, &args_to_show)
+                                .unwrap_or_else(|| ActionType::Tool {
+                                    tool_name: label.clone(),
+                                    arguments: Some(args_to_show),
+                                    result: Some(crate::logs::ToolResult {
+                                        r#type: res_type,
+                                        value: res_value,
+                                    }),
+                                });
                             let entry = NormalizedEntry {
                                 timestamp: None,
                                 entry_type: NormalizedEntryType::ToolUse {
-                                    tool_name: label.clone(),
-                                    action_type: ActionType::Tool {
-                                        tool_name: label,
-                                        arguments: Some(args_to_show),
-                                        result: Some(crate::logs::ToolResult {
-                                            r#type: res_type,
-                                            value: res_value,
-                                        }),
-                                    },
+                                    tool_name: label,
+                                    action_type,
                                     status,
                                 },
                                 content: info.content.clone(),
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
+                            self.record_tool_result(info.entry_index, &entry);
                             patches.push(ConversationPatch::replace(info.entry_index, entry));
                         }
                         // Note: With control protocol, denials are handled via protocol messages
@@ -1059,6 +1512,8 @@ impl ClaudeLogProcessor {
                     metadata: Some(
                         serde_json::to_value(claude_json).unwrap_or(serde_json::Value::Null),
                     ),
+                    agent_id: None,
+                    agent_role: None,
                 };
                 let idx = entry_index_provider.next();
                 patches.push(ConversationPatch::add_normalized_entry(idx, entry));
@@ -1136,6 +1591,8 @@ impl ClaudeLogProcessor {
                         metadata: Some(
                             serde_json::to_value(claude_json).unwrap_or(serde_json::Value::Null),
                         ),
+                        agent_id: None,
+                        agent_role: None,
                     };
                     let idx = entry_index_provider.next();
                     patches.push(ConversationPatch::add_normalized_entry(idx, entry));
@@ -1161,6 +1618,8 @@ impl ClaudeLogProcessor {
                             .filter(|s| !s.is_empty())
                             .unwrap_or_else(|| "User denied this tool use request".to_string()),
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     }),
                     ApprovalStatus::TimedOut => Some(NormalizedEntry {
                         timestamp: None,
@@ -1169,6 +1628,8 @@ impl ClaudeLogProcessor {
                         },
                         content: format!("Approval timed out for tool {tool_name}"),
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     }),
                 };
 
@@ -1186,6 +1647,8 @@ impl ClaudeLogProcessor {
                         serde_json::to_value(data).unwrap_or_default()
                     ),
                     metadata: None,
+                    agent_id: None,
+                    agent_role: None,
                 };
                 let idx = entry_index_provider.next();
                 patches.push(ConversationPatch::add_normalized_entry(idx, entry));
@@ -1230,6 +1693,27 @@ impl ClaudeLogProcessor {
             },
             ActionType::PlanPresentation { plan } => plan.clone(),
             ActionType::TodoManagement { .. } => "TODO list updated".to_string(),
+            ActionType::Memory {
+                operation,
+                namespace,
+                key,
+            } => {
+                let verb = match operation {
+                    MemoryOperation::Store => "Stored",
+                    MemoryOperation::Retrieve => "Retrieved",
+                    MemoryOperation::Search => "Searched",
+                    MemoryOperation::List => "Listed",
+                    MemoryOperation::Delete => "Deleted",
+                };
+                let mut content = format!("{verb} memory");
+                if let Some(key) = key {
+                    content.push_str(&format!(" `{key}`"));
+                }
+                if let Some(namespace) = namespace {
+                    content.push_str(&format!(" in {namespace}"));
+                }
+                content
+            }
             ActionType::Other { description: _ } => match tool_data {
                 ClaudeToolData::LS { path } => {
                     let relative_path = make_path_relative(path, worktree_path);
@@ -1287,6 +1771,47 @@ impl ClaudeLogProcessor {
     }
 }
 
+/// `entry` with its tool output shortened to [`COMPACTED_OUTPUT_TOKENS`], if
+/// it was any longer
+fn compact_tool_output(mut entry: NormalizedEntry) -> Option<NormalizedEntry> {
+    let NormalizedEntryType::ToolUse { action_type, .. } = &mut entry.entry_type else {
+        return None;
+    };
+    match action_type {
+        ActionType::CommandRun {
+            result:
+                Some(CommandRunResult {
+                    output: Some(output),
+                    ..
+                }),
+            ..
+        } => {
+            if estimate_tokens(output) <= COMPACTED_OUTPUT_TOKENS {
+                return None;
+            }
+            *output = truncate_middle(output, COMPACTED_OUTPUT_TOKENS);
+        }
+        ActionType::Tool {
+            result: Some(result),
+            ..
+        } => {
+            let output = match &result.value {
+                serde_json::Value::String(output) => output.clone(),
+                value => value.to_string(),
+            };
+            if estimate_tokens(&output) <= COMPACTED_OUTPUT_TOKENS {
+                return None;
+            }
+            *result = ToolResult {
+                r#type: ToolResultValueType::Markdown,
+                value: serde_json::Value::String(truncate_middle(&output, COMPACTED_OUTPUT_TOKENS)),
+            };
+        }
+        _ => return None,
+    }
+    Some(entry)
+}
+
 fn extract_model_name(
     processor: &mut ClaudeLogProcessor,
     message: &ClaudeMessage,
@@ -1301,6 +1826,8 @@ fn extract_model_name(
             entry_type: NormalizedEntryType::SystemMessage,
             content: format!("System initialized with model: {model}"),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         };
         let id = entry_index_provider.next();
         Some(ConversationPatch::add_normalized_entry(id, entry))
@@ -1540,6 +2067,12 @@ pub enum ClaudeContentItem {
     Text { text: String },
     #[serde(rename = "thinking")]
     Thinking { thinking: String },
+    /// Thinking the API returns encrypted; only its presence can be shown
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking {
+        #[serde(default)]
+        data: String,
+    },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -1918,6 +2451,21 @@ mod tests {
             NormalizedEntryType::Thinking
         ));
         assert_eq!(entries[0].content, "Let me think about this...");
+
+        let redacted_json = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"thinking","thinking":"Plan","signature":"abc"},{"type":"redacted_thinking","data":"EuYBCkQY"}]}}"#;
+        let parsed: ClaudeJson = serde_json::from_str(redacted_json).unwrap();
+
+        let entries = normalize(&parsed, "");
+        assert_eq!(entries.len(), 2);
+        assert!(
+            entries
+                .iter()
+                .all(|entry| matches!(entry.entry_type, NormalizedEntryType::Thinking))
+        );
+        assert_eq!(
+            entries[1].content,
+            "Thinking redacted by the model provider"
+        );
     }
 
     #[test]
@@ -2015,15 +2563,24 @@ mod tests {
             plan: None,
             approvals: None,
             model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
             append_prompt: AppendPrompt::default(),
             dangerously_skip_permissions: None,
+            warm_standby: None,
+            approval_policy: None,
+            interactive: None,
             cmd: crate::command::CmdOverrides {
                 base_command_override: None,
                 additional_params: None,
                 env: None,
+                ..Default::default()
             },
+            run: RunOverrides::default(),
             approvals_service: None,
             disable_api_key: None,
+            api_key_secret: None,
         };
         let msg_store = Arc::new(MsgStore::new());
         let current_dir = std::path::PathBuf::from("/tmp/test-worktree");
@@ -2053,6 +2610,197 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_swarm_agent_attribution() {
+        use std::sync::Arc;
+
+        use crate::logs::utils::patch::latest_normalized_entries;
+
+        let msg_store = Arc::new(MsgStore::new());
+        for line in [
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Planning"}]}}"#,
+            r#"{"type":"chain","agent":{"id":"agent-2","type":"tester"}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Testing"}]}}"#,
+        ] {
+            msg_store.push_stdout(format!("{line}\n"));
+        }
+        msg_store.push_finished();
+
+        ClaudeLogProcessor::process_logs(
+            msg_store.clone(),
+            Path::new("/tmp/test-worktree"),
+            EntryIndexProvider::test_new(),
+            HistoryStrategy::Default,
+        );
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let attribution: Vec<_> = latest_normalized_entries(&msg_store.get_history())
+            .into_values()
+            .map(|entry| (entry.content, entry.agent_id, entry.agent_role))
+            .collect();
+        assert_eq!(
+            attribution,
+            [
+                ("Planning".to_string(), None, None),
+                (
+                    "Testing".to_string(),
+                    Some("agent-2".to_string()),
+                    Some("tester".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_recovered_from_npm_noise() {
+        use std::sync::Arc;
+
+        use crate::logs::{
+            tool_stats::ToolStatsCollector, utils::patch::latest_normalized_entries,
+        };
+
+        let msg_store = Arc::new(MsgStore::new());
+        msg_store.push_stdout(concat!(
+            "npm warn deprecated inflight@1.0.6",
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Hello"}]}}"#,
+            "\n",
+        ));
+        msg_store.push_finished();
+
+        ClaudeLogProcessor::process_logs(
+            msg_store.clone(),
+            Path::new("/tmp/test-worktree"),
+            EntryIndexProvider::test_new(),
+            HistoryStrategy::Default,
+        );
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let history = msg_store.get_history();
+        let entries: Vec<_> = latest_normalized_entries(&history).into_values().collect();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(
+            entries[0].entry_type,
+            NormalizedEntryType::RawOutput
+        ));
+        assert_eq!(entries[0].content, "npm warn deprecated inflight@1.0.6");
+        assert_eq!(entries[1].content, "Hello");
+        assert_eq!(
+            ToolStatsCollector::from_history(&history)
+                .session_stats()
+                .parse_errors,
+            1
+        );
+    }
+
+    #[test]
+    fn test_replay_history_strategies() {
+        use serde_json::json;
+
+        // Replays turns of a resumed session, each running a command, and
+        // returns the conversation the patches build
+        fn replay(strategy: HistoryStrategy, outputs: &[String]) -> Vec<NormalizedEntry> {
+            let mut processor = ClaudeLogProcessor::new_with_strategy(strategy);
+            let provider = EntryIndexProvider::test_new();
+            let mut conversation = json!({ "entries": [] });
+            for (turn, output) in outputs.iter().enumerate() {
+                let id = format!("toolu_{turn}");
+                for line in [
+                    json!({"type": "user", "message": {"role": "user",
+                        "content": [{"type": "text", "text": format!("turn {turn}")}]}}),
+                    json!({"type": "assistant", "message": {"role": "assistant",
+                        "content": [{"type": "tool_use", "id": id, "name": "Bash",
+                            "input": {"command": "ls"}}]}}),
+                    json!({"type": "user", "message": {"role": "user",
+                        "content": [{"type": "tool_result", "tool_use_id": id,
+                            "content": output, "is_error": false}]}}),
+                ] {
+                    let claude_json = serde_json::from_value(line).unwrap();
+                    for patch in processor.normalize_entries(&claude_json, "/tmp", &provider) {
+                        json_patch::patch(&mut conversation, &patch).unwrap();
+                    }
+                }
+            }
+            conversation["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| serde_json::from_value(entry["content"].clone()).unwrap())
+                .collect()
+        }
+        fn command_output(entry: &NormalizedEntry) -> &str {
+            match &entry.entry_type {
+                NormalizedEntryType::ToolUse {
+                    action_type:
+                        ActionType::CommandRun {
+                            result: Some(result),
+                            ..
+                        },
+                    ..
+                } => result.output.as_deref().unwrap(),
+                _ => panic!("expected a command run"),
+            }
+        }
+
+        let outputs = ["a".repeat(2000), "b".repeat(2000), "c".repeat(2000)];
+
+        let full = replay(HistoryStrategy::FullReplay, &outputs);
+        assert_eq!(full.len(), 6);
+        assert!(
+            full.iter()
+                .skip(1)
+                .step_by(2)
+                .all(|e| command_output(e).len() == 2000)
+        );
+
+        let last = replay(HistoryStrategy::LastTurns(2), &outputs);
+        let prompts: Vec<_> = last.iter().step_by(2).map(|e| e.content.as_str()).collect();
+        assert_eq!(prompts, ["turn 1", "turn 2"]);
+        assert!(command_output(&last[3]).starts_with('c'));
+
+        let compacted = replay(HistoryStrategy::Compacted, &outputs);
+        assert_eq!(compacted.len(), 6);
+        assert!(command_output(&compacted[1]).contains("tokens truncated"));
+        assert!(command_output(&compacted[3]).contains("tokens truncated"));
+        // The current turn is left alone
+        assert_eq!(command_output(&compacted[5]).len(), 2000);
+    }
+
+    #[test]
+    fn test_claude_model_aliases_and_full_names() {
+        let models: Vec<ClaudeModel> =
+            serde_json::from_str(r#"["opus", "haiku", "claude-sonnet-4-5-20250929"]"#).unwrap();
+        assert_eq!(
+            models,
+            [
+                ClaudeModel::Opus,
+                ClaudeModel::Haiku,
+                ClaudeModel::Other("claude-sonnet-4-5-20250929".to_string())
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&models).unwrap(),
+            r#"["opus","haiku","claude-sonnet-4-5-20250929"]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_thinking_budget_flag() {
+        let mut executor: ClaudeCode =
+            serde_json::from_str(r#"{"reasoning_effort": "high"}"#).unwrap();
+        let command = executor.build_command_builder().await.to_string();
+        assert!(command.contains("--max-thinking-tokens 31999"));
+
+        // An explicit budget wins over the effort
+        executor.max_thinking_tokens = Some(2_048);
+        let command = executor.build_command_builder().await.to_string();
+        assert!(command.contains("--max-thinking-tokens 2048"));
+
+        executor.max_thinking_tokens = None;
+        executor.reasoning_effort = None;
+        let command = executor.build_command_builder().await.to_string();
+        assert!(!command.contains("--max-thinking-tokens"));
+    }
+
     #[test]
     fn test_session_id_extraction() {
         let system_json = r#"{"type":"system","session_id":"test-session-123"}"#;
@@ -2179,6 +2927,48 @@ mod tests {
         assert_eq!(entries[0].content, "Undo edit: `README.md`");
     }
 
+    #[test]
+    fn test_claude_flow_memory_tools() {
+        let store_json = r#"{
+            "type":"assistant",
+            "message":{
+                "role":"assistant",
+                "content":[
+                    {"type":"tool_use","id":"m1","name":"mcp__claude-flow__memory_usage","input":{"action":"store","key":"api-design","value":"REST","namespace":"vk-task-1"}}
+                ]
+            }
+        }"#;
+        let parsed: ClaudeJson = serde_json::from_str(store_json).unwrap();
+        let entries = normalize(&parsed, "/tmp/work");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].content,
+            "Stored memory `api-design` in vk-task-1"
+        );
+        match &entries[0].entry_type {
+            NormalizedEntryType::ToolUse {
+                action_type:
+                    ActionType::Memory {
+                        operation,
+                        namespace,
+                        key,
+                    },
+                ..
+            } => {
+                assert_eq!(*operation, MemoryOperation::Store);
+                assert_eq!(namespace.as_deref(), Some("vk-task-1"));
+                assert_eq!(key.as_deref(), Some("api-design"));
+            }
+            other => panic!("Expected a memory action, got {other:?}"),
+        }
+
+        // Other claude-flow tools stay generic
+        assert!(
+            claude_flow::memory_action("mcp__claude-flow__swarm_init", &serde_json::json!({}))
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_amp_bash_and_task_content() {
         // Bash with alias field cmd
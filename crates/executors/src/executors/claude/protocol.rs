@@ -4,7 +4,7 @@ use futures::FutureExt;
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
     process::{ChildStdin, ChildStdout},
-    sync::{Mutex, oneshot},
+    sync::{Mutex, mpsc, oneshot},
 };
 
 use super::types::{CLIMessage, ControlRequestType, ControlResponseMessage, ControlResponseType};
@@ -28,6 +28,7 @@ impl ProtocolPeer {
         stdout: ChildStdout,
         client: Arc<ClaudeAgentClient>,
         interrupt_rx: oneshot::Receiver<()>,
+        input_rx: Option<mpsc::UnboundedReceiver<String>>,
     ) -> Self {
         let peer = Self {
             stdin: Arc::new(Mutex::new(stdin)),
@@ -35,7 +36,10 @@ impl ProtocolPeer {
 
         let reader_peer = peer.clone();
         tokio::spawn(async move {
-            if let Err(e) = reader_peer.read_loop(stdout, client, interrupt_rx).await {
+            if let Err(e) = reader_peer
+                .read_loop(stdout, client, interrupt_rx, input_rx)
+                .await
+            {
                 tracing::error!("Protocol reader loop error: {}", e);
             }
         });
@@ -48,11 +52,15 @@ impl ProtocolPeer {
         stdout: ChildStdout,
         client: Arc<ClaudeAgentClient>,
         interrupt_rx: oneshot::Receiver<()>,
+        mut input_rx: Option<mpsc::UnboundedReceiver<String>>,
     ) -> Result<(), ExecutorError> {
         let mut reader = BufReader::new(stdout);
         let mut buffer = String::new();
         // Fuse the receiver so it returns Pending forever after completing
         let mut interrupt_rx = interrupt_rx.fuse();
+        // Claude reports a result per turn: the prompt's, then one for each
+        // injected message. Stdin closes after the last, ending the session.
+        let mut pending_turns = 1usize;
 
         loop {
             buffer.clear();
@@ -77,7 +85,10 @@ impl ProtocolPeer {
                                 Ok(CLIMessage::ControlResponse { .. }) => {}
                                 Ok(CLIMessage::Result(_)) => {
                                     client.on_non_control(line).await?;
-                                    break;
+                                    pending_turns = pending_turns.saturating_sub(1);
+                                    if pending_turns == 0 {
+                                        break;
+                                    }
                                 }
                                 _ => {
                                     client.on_non_control(line).await?;
@@ -95,6 +106,15 @@ impl ProtocolPeer {
                         tracing::debug!("Failed to send interrupt to Claude: {e}");
                     }
                 }
+                message = next_input(&mut input_rx) => match message {
+                    Some(message) => {
+                        pending_turns += 1;
+                        if let Err(e) = self.send_user_message(message).await {
+                            tracing::warn!("Failed to send user input to Claude: {e}");
+                        }
+                    }
+                    None => input_rx = None,
+                },
             }
         }
         Ok(())
@@ -193,6 +213,16 @@ impl ProtocolPeer {
         self.send_json(&message).await
     }
 
+    /// Send `content` with images, given as media type and base64 data
+    pub async fn send_user_message_with_images(
+        &self,
+        content: String,
+        images: Vec<(String, String)>,
+    ) -> Result<(), ExecutorError> {
+        self.send_json(&Message::new_user_with_images(content, images))
+            .await
+    }
+
     pub async fn initialize(&self, hooks: Option<serde_json::Value>) -> Result<(), ExecutorError> {
         self.send_json(&SDKControlRequest::new(SDKControlRequestType::Initialize {
             hooks,
@@ -211,3 +241,11 @@ impl ProtocolPeer {
         .await
     }
 }
+
+/// Next injected user message; pending forever without interactive input
+async fn next_input(input_rx: &mut Option<mpsc::UnboundedReceiver<String>>) -> Option<String> {
+    match input_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,178 @@
+//! Import of Claude Code session transcripts.
+//!
+//! Claude Code records every session it runs as JSONL under
+//! `~/.claude/projects`, one message per line. [`import_transcript`] replays
+//! such a transcript into a [`MsgStore`] as normalized history along with its
+//! session id, so a conversation started outside vibe kanban can be shown and
+//! then continued with a follow-up that resumes the session.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Value, json};
+use workspace_utils::msg_store::MsgStore;
+
+use super::{ClaudeContentItem, ClaudeJson, ClaudeLogProcessor, HistoryStrategy};
+use crate::logs::{
+    NormalizedEntry, NormalizedEntryType,
+    utils::{EntryIndexProvider, patch::ConversationPatch},
+};
+
+/// Directory Claude Code keeps its session transcripts in, one per project
+pub fn claude_projects_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join("projects"))
+}
+
+/// Transcript of session `session_id`, in whichever project it ran
+pub fn find_transcript(session_id: &str) -> Option<PathBuf> {
+    let file_name = format!("{session_id}.jsonl");
+    fs::read_dir(claude_projects_dir()?)
+        .ok()?
+        .flatten()
+        .map(|project| project.path().join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Normalized history of the transcript at `path`, finished and carrying the
+/// session id to resume
+pub fn import_transcript(path: &Path, worktree_path: &Path) -> io::Result<MsgStore> {
+    import_from_reader(BufReader::new(File::open(path)?), worktree_path)
+}
+
+fn import_from_reader(reader: impl BufRead, worktree_path: &Path) -> io::Result<MsgStore> {
+    let msg_store = MsgStore::new();
+    let entry_index_provider = EntryIndexProvider::start_from(&msg_store);
+    let mut processor = ClaudeLogProcessor::new_with_strategy(HistoryStrategy::Default);
+    let worktree_path = worktree_path.to_string_lossy();
+    let mut session_id = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        let Some((id, claude_json)) = parse_line(&line) else {
+            continue;
+        };
+        if session_id.is_none() {
+            session_id = id;
+        }
+
+        // Prompts aren't part of the output of a run, so the processor leaves
+        // them out; here they're the only record of what the user asked
+        if let ClaudeJson::User { message, .. } = &claude_json {
+            for item in &message.content {
+                if let ClaudeContentItem::Text { text } = item {
+                    let entry = NormalizedEntry {
+                        timestamp: None,
+                        entry_type: NormalizedEntryType::UserMessage,
+                        content: text.clone(),
+                        metadata: None,
+                        agent_id: None,
+                        agent_role: None,
+                    };
+                    msg_store.push_patch(ConversationPatch::add_normalized_entry(
+                        entry_index_provider.next(),
+                        entry,
+                    ));
+                }
+            }
+        }
+        for patch in
+            processor.normalize_entries(&claude_json, &worktree_path, &entry_index_provider)
+        {
+            msg_store.push_patch(patch);
+        }
+    }
+
+    match session_id {
+        Some(session_id) => msg_store.push_session_id(session_id),
+        None => tracing::warn!("Imported Claude transcript has no session id"),
+    }
+    msg_store.push_finished();
+    Ok(msg_store)
+}
+
+/// Session id and message of a transcript line, if it is part of the main
+/// conversation. Summaries, sidechains of subagents and meta messages that
+/// Claude Code adds itself are skipped.
+fn parse_line(line: &str) -> Option<(Option<String>, ClaudeJson)> {
+    let mut value: Value = serde_json::from_str(line.trim()).ok()?;
+    if !matches!(value["type"].as_str(), Some("user" | "assistant"))
+        || value["isSidechain"].as_bool() == Some(true)
+        || value["isMeta"].as_bool() == Some(true)
+    {
+        return None;
+    }
+    let session_id = value["sessionId"].as_str().map(str::to_string);
+
+    // Prompts are recorded as plain strings rather than content blocks
+    let content = &mut value["message"]["content"];
+    if let Some(text) = content.as_str() {
+        *content = json!([{ "type": "text", "text": text }]);
+    }
+    match serde_json::from_value(value) {
+        Ok(claude_json) => Some((session_id, claude_json)),
+        Err(e) => {
+            tracing::debug!("Skipping unreadable Claude transcript line: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use workspace_utils::log_msg::LogMsg;
+
+    use super::*;
+    use crate::logs::{ToolStatus, utils::patch::latest_normalized_entries};
+
+    #[test]
+    fn test_import_transcript() {
+        let transcript = [
+            json!({"type": "summary", "summary": "Listing files", "leafUuid": "a"}),
+            json!({"type": "user", "sessionId": "session-1", "isMeta": true,
+                "message": {"role": "user", "content": "Caveat: local command output"}}),
+            json!({"type": "user", "sessionId": "session-1",
+                "message": {"role": "user", "content": "list files"}}),
+            json!({"type": "assistant", "sessionId": "session-1", "message": {
+                "id": "msg_1", "role": "assistant", "model": "claude-sonnet-4",
+                "content": [{"type": "tool_use", "id": "toolu_1", "name": "Bash",
+                    "input": {"command": "ls"}}]}}),
+            json!({"type": "user", "sessionId": "session-1", "message": {
+                "role": "user", "content": [{"type": "tool_result",
+                    "tool_use_id": "toolu_1", "content": "README.md", "is_error": false}]}}),
+            json!({"type": "assistant", "sessionId": "session-1", "isSidechain": true,
+                "message": {"role": "assistant", "content": [{"type": "text", "text": "aside"}]}}),
+            json!({"type": "assistant", "sessionId": "session-1", "message": {
+                "id": "msg_2", "role": "assistant",
+                "content": [{"type": "text", "text": "Just a README."}]}}),
+        ]
+        .map(|line| line.to_string())
+        .join("\n");
+
+        let store = import_from_reader(transcript.as_bytes(), Path::new("/repo")).unwrap();
+        let history = store.get_history();
+        assert!(matches!(history.last(), Some(LogMsg::Finished)));
+        assert!(
+            history
+                .iter()
+                .any(|msg| matches!(msg, LogMsg::SessionId(id) if id == "session-1"))
+        );
+
+        let entries: Vec<_> = latest_normalized_entries(&history)
+            .into_values()
+            .filter(|entry| !matches!(entry.entry_type, NormalizedEntryType::SystemMessage))
+            .collect();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].content, "list files");
+        assert!(matches!(
+            entries[1].entry_type,
+            NormalizedEntryType::ToolUse {
+                status: ToolStatus::Success,
+                ..
+            }
+        ));
+        assert_eq!(entries[2].content, "Just a README.");
+    }
+}
@@ -150,7 +150,14 @@ pub enum Message {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeUserMessage {
     role: String,
-    content: String,
+    content: ClaudeUserContent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ClaudeUserContent {
+    Text(String),
+    Blocks(Vec<Value>),
 }
 
 impl Message {
@@ -158,7 +165,28 @@ impl Message {
         Self::User {
             message: ClaudeUserMessage {
                 role: "user".to_string(),
-                content,
+                content: ClaudeUserContent::Text(content),
+            },
+        }
+    }
+
+    /// User message of `text` followed by image content blocks, given as
+    /// media type and base64 data
+    pub fn new_user_with_images(text: String, images: Vec<(String, String)>) -> Self {
+        if images.is_empty() {
+            return Self::new_user(text);
+        }
+        let mut blocks = vec![serde_json::json!({ "type": "text", "text": text })];
+        blocks.extend(images.into_iter().map(|(media_type, data)| {
+            serde_json::json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": media_type, "data": data },
+            })
+        }));
+        Self::User {
+            message: ClaudeUserMessage {
+                role: "user".to_string(),
+                content: ClaudeUserContent::Blocks(blocks),
             },
         }
     }
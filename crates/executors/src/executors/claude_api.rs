@@ -0,0 +1,697 @@
+//! Claude through the Anthropic API, without a CLI.
+//!
+//! Some deployments can't install the Claude Code CLI, e.g. hosts without
+//! Node or without access to npm. [`ClaudeApi`] runs the agent in process
+//! instead: it sends the conversation to the Messages API, runs the tools the
+//! model calls in the worktree and repeats until the model answers without
+//! calling any. The tools carry Claude Code's names and inputs and the run is
+//! logged as Claude Code stream-json, so it is normalized, shown and budgeted
+//! like a Claude Code run. A stand-in process holds the execution's place in
+//! the container, which reads the log from its stdout. Conversations are kept
+//! in the asset directory so follow-ups can continue them.
+
+mod tools;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use derivative::Derivative;
+use futures::FutureExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::oneshot;
+use ts_rs::TS;
+use uuid::Uuid;
+use workspace_utils::{approvals::ApprovalStatus, msg_store::MsgStore};
+
+use self::tools::{ToolContext, ToolOutput};
+use crate::{
+    approvals::policy::ApprovalPolicy,
+    approvals::{ExecutorApprovalError, ExecutorApprovalService},
+    command::CmdOverrides,
+    env::ExecutionEnv,
+    executors::{
+        AppendPrompt, AvailabilityInfo, ExecutorError, ExecutorExitResult, SpawnedChild,
+        StandardCodingAgentExecutor,
+        claude::{ClaudeLogProcessor, ClaudeModel, HistoryStrategy},
+        codex::client::LogWriter,
+        opencode::ensure_rustls_crypto_provider,
+    },
+    logs::{stderr_processor::normalize_stderr_logs, utils::EntryIndexProvider},
+    outcome::{FailureReason, OutcomeStats, RunSummary},
+    profile::RunOverrides,
+    secrets,
+    stdout_dup::{create_stdout_pipe_writer, spawn_stand_in},
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 8_192;
+const DEFAULT_MAX_TURNS: u32 = 100;
+/// Attempts at a request the API is overloaded or rate limited for
+const MAX_ATTEMPTS: u32 = 4;
+/// Asset directory conversations are kept in, one file per session
+const SESSION_DIR: &str = "claude_api_sessions";
+
+#[derive(Derivative, Clone, Serialize, Deserialize, TS, JsonSchema)]
+#[derivative(Debug, PartialEq)]
+pub struct ClaudeApi {
+    #[serde(default)]
+    pub append_prompt: AppendPrompt,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<ClaudeModel>,
+    /// Secret holding the Anthropic API key; without one, `ANTHROPIC_API_KEY`
+    /// from the environment is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_secret: Option<String>,
+    /// Messages API endpoint to use instead of Anthropic's, e.g. a gateway
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Tokens each response may take; defaults to 8192
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Requests to the model a run may make before it is stopped; defaults
+    /// to 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+    /// Only give the model the tools that read the worktree
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+    /// Run commands and edit files without asking for approval
+    #[serde(default = "default_to_true")]
+    pub auto_approve: bool,
+    #[schemars(
+        title = "Approval Policy",
+        description = "Rules that allow, deny or ask about tool calls by tool name, file path globs and command regexes, checked in order before asking for approval"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalPolicy>,
+    #[serde(flatten)]
+    pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
+    #[serde(skip)]
+    #[ts(skip)]
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub approvals: Option<Arc<dyn ExecutorApprovalService>>,
+}
+
+impl ClaudeApi {
+    /// Model name the API takes for the configured model
+    fn model_id(&self) -> &str {
+        match &self.model {
+            None | Some(ClaudeModel::Sonnet) => "claude-sonnet-4-5",
+            Some(ClaudeModel::Opus) => "claude-opus-4-1",
+            Some(ClaudeModel::Haiku) => "claude-haiku-4-5",
+            Some(ClaudeModel::Other(name)) => name,
+        }
+    }
+
+    fn api_key(&self, env: &ExecutionEnv) -> Result<String, ExecutorError> {
+        if let Some(name) = &self.api_key_secret {
+            return Ok(secrets::resolve(name, env.tenant.as_ref())?);
+        }
+        env.vars
+            .get("ANTHROPIC_API_KEY")
+            .cloned()
+            .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| {
+                ExecutorError::AuthRequired(
+                    "set api_key_secret or ANTHROPIC_API_KEY to use the Anthropic API".to_string(),
+                )
+            })
+    }
+
+    async fn spawn_inner(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        parent_session_id: Option<&str>,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        // Tools run in the local worktree
+        if self.run.remote.is_some() {
+            return Err(ExecutorError::RemoteNotSupported);
+        }
+        let env = env.clone().with_profile(&self.cmd, &self.run);
+        let api_key = self.api_key(&env)?;
+        let mut messages = match parent_session_id {
+            Some(session_id) => load_session(session_id)?,
+            None => Vec::new(),
+        };
+        let prompt = self
+            .append_prompt
+            .frame_prompt(self.run.prompt.as_ref(), prompt, &env);
+        messages.push(json!({ "role": "user", "content": prompt }));
+
+        ensure_rustls_crypto_provider();
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(|err| ExecutorError::Io(std::io::Error::other(err)))?;
+        let session = Session {
+            client,
+            base_url: self
+                .base_url
+                .as_deref()
+                .unwrap_or(DEFAULT_BASE_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            api_key,
+            model: self.model_id().to_string(),
+            max_tokens: self.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            max_turns: self.max_turns.unwrap_or(DEFAULT_MAX_TURNS),
+            // Each run continues in a session of its own, leaving the one
+            // it follows up on as it was
+            session_id: Uuid::new_v4().to_string(),
+            tools: ToolContext {
+                current_dir: current_dir.to_path_buf(),
+                env,
+                read_only: self.read_only.unwrap_or(false),
+            },
+            approvals: if self.auto_approve {
+                None
+            } else {
+                self.approvals.clone()
+            },
+        };
+
+        let mut child = spawn_stand_in(current_dir, Stdio::piped())?;
+        let log_writer = LogWriter::new(create_stdout_pipe_writer(&mut child)?);
+        let (exit_signal_tx, exit_signal_rx) = oneshot::channel();
+        let (interrupt_tx, interrupt_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let exit_result = match session.run(messages, &log_writer, interrupt_rx).await {
+                Ok(exit_result) => exit_result,
+                Err(err) => {
+                    let _ = log_writer
+                        .log_raw(&format!("Claude API error: {err}"))
+                        .await;
+                    ExecutorExitResult::Failed(FailureReason::from(&err))
+                }
+            };
+            let _ = exit_signal_tx.send(exit_result);
+        });
+
+        Ok(SpawnedChild {
+            child,
+            exit_signal: Some(exit_signal_rx),
+            interrupt_sender: Some(interrupt_tx),
+            input_sender: None,
+        })
+    }
+}
+
+fn session_path(session_id: &str) -> Result<PathBuf, ExecutorError> {
+    // Ids are only ever ones this executor made
+    Uuid::parse_str(session_id)
+        .map_err(|_| ExecutorError::SessionNotFound(session_id.to_string()))?;
+    Ok(workspace_utils::assets::asset_dir()
+        .join(SESSION_DIR)
+        .join(format!("{session_id}.json")))
+}
+
+fn load_session(session_id: &str) -> Result<Vec<Value>, ExecutorError> {
+    let content = fs::read_to_string(session_path(session_id)?)
+        .map_err(|_| ExecutorError::SessionNotFound(session_id.to_string()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn save_session(session_id: &str, messages: &[Value]) -> Result<(), ExecutorError> {
+    let path = session_path(session_id)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(ExecutorError::Io)?;
+    }
+    fs::write(path, serde_json::to_string(messages)?).map_err(ExecutorError::Io)
+}
+
+/// A tool call the model made
+struct ToolCall {
+    id: String,
+    name: String,
+    input: Value,
+}
+
+/// Tool calls and text of an assistant message's content
+fn parse_content(content: &Value) -> (Vec<ToolCall>, String) {
+    let mut calls = Vec::new();
+    let mut text = Vec::new();
+    for block in content.as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("tool_use") => calls.push(ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                input: block["input"].clone(),
+            }),
+            Some("text") => text.extend(block["text"].as_str()),
+            _ => {}
+        }
+    }
+    (calls, text.join("\n"))
+}
+
+/// One run of the tool loop
+struct Session {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+    max_turns: u32,
+    session_id: String,
+    tools: ToolContext,
+    /// Service to ask before tools that change the worktree; none approves
+    /// them all
+    approvals: Option<Arc<dyn ExecutorApprovalService>>,
+}
+
+impl Session {
+    async fn log(&self, log_writer: &LogWriter, mut event: Value) -> Result<(), ExecutorError> {
+        event["session_id"] = json!(self.session_id);
+        log_writer.log_raw(&event.to_string()).await
+    }
+
+    async fn run(
+        self,
+        mut messages: Vec<Value>,
+        log_writer: &LogWriter,
+        interrupt_rx: oneshot::Receiver<()>,
+    ) -> Result<ExecutorExitResult, ExecutorError> {
+        let started = Instant::now();
+        let definitions = tools::definitions(self.tools.read_only);
+        let tool_names: Vec<&Value> = definitions.iter().map(|tool| &tool["name"]).collect();
+        self.log(
+            log_writer,
+            json!({
+                "type": "system",
+                "subtype": "init",
+                "cwd": self.tools.current_dir,
+                "model": self.model,
+                "tools": tool_names,
+            }),
+        )
+        .await?;
+
+        let mut interrupt_rx = interrupt_rx.fuse();
+        let mut usage = json!({
+            "input_tokens": 0,
+            "cache_creation_input_tokens": 0,
+            "cache_read_input_tokens": 0,
+            "output_tokens": 0,
+        });
+        let mut num_turns = 0;
+        let mut result = String::new();
+        let subtype = loop {
+            if num_turns == self.max_turns {
+                break "error_max_turns";
+            }
+            let response = tokio::select! {
+                Ok(()) = &mut interrupt_rx => break "interrupted",
+                response = self.send(&messages, &definitions) => response?,
+            };
+            num_turns += 1;
+            for (key, total) in usage.as_object_mut().into_iter().flatten() {
+                let used = response["usage"][key.as_str()].as_u64().unwrap_or(0);
+                *total = json!(total.as_u64().unwrap_or(0) + used);
+            }
+            self.log(
+                log_writer,
+                json!({ "type": "assistant", "message": response }),
+            )
+            .await?;
+            messages.push(json!({ "role": "assistant", "content": response["content"] }));
+
+            let (calls, text) = parse_content(&response["content"]);
+            result = text;
+            if calls.is_empty() {
+                save_session(&self.session_id, &messages)?;
+                break "success";
+            }
+            // Every call gets a result, or the conversation can't continue
+            let mut interrupted = false;
+            let mut results = Vec::new();
+            for call in &calls {
+                let output = if interrupted {
+                    ToolOutput::error("Interrupted by the user")
+                } else {
+                    tokio::select! {
+                        Ok(()) = &mut interrupt_rx => {
+                            interrupted = true;
+                            ToolOutput::error("Interrupted by the user")
+                        }
+                        output = self.call_tool(log_writer, call) => output?,
+                    }
+                };
+                results.push(output.to_block(&call.id));
+            }
+            self.log(
+                log_writer,
+                json!({ "type": "user", "message": { "role": "user", "content": results } }),
+            )
+            .await?;
+            messages.push(json!({ "role": "user", "content": results }));
+            save_session(&self.session_id, &messages)?;
+            if interrupted {
+                break "interrupted";
+            }
+        };
+
+        let is_error = subtype == "error_max_turns";
+        let stats = OutcomeStats {
+            duration_ms: Some(started.elapsed().as_millis() as u64),
+            num_turns: Some(num_turns),
+            total_cost_usd: None,
+        };
+        if is_error {
+            result = format!(
+                "Stopped after {num_turns} requests to the model without finishing; raise max_turns to let runs go on longer"
+            );
+            log_writer.log_raw(&result).await?;
+        }
+        self.log(
+            log_writer,
+            json!({
+                "type": "result",
+                "subtype": subtype,
+                "is_error": is_error,
+                "duration_ms": stats.duration_ms,
+                "num_turns": num_turns,
+                "result": result,
+                "usage": usage,
+            }),
+        )
+        .await?;
+        let summary = RunSummary {
+            summary: Some(result).filter(|result| !result.is_empty()),
+            stats,
+        };
+        Ok(match subtype {
+            "interrupted" => ExecutorExitResult::Interrupted,
+            _ if is_error => ExecutorExitResult::Failed(FailureReason::TaskFailed(summary)),
+            _ => ExecutorExitResult::Success(summary),
+        })
+    }
+
+    /// Send the conversation to the Messages API, retrying while the API is
+    /// overloaded or rate limited
+    async fn send(
+        &self,
+        messages: &[Value],
+        definitions: &[Value],
+    ) -> Result<Value, ExecutorError> {
+        let body = json!({
+            "model": self.model,
+            "max_tokens": self.max_tokens,
+            "system": format!(
+                "You are a coding agent working in the repository at {}. Use the tools to inspect and change its files and to run commands. Keep going until the task is done, then summarize what you changed.",
+                self.tools.current_dir.display()
+            ),
+            "tools": definitions,
+            "messages": messages,
+        });
+        let mut attempt = 1;
+        loop {
+            let response = self
+                .client
+                .post(format!("{}/v1/messages", self.base_url))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", API_VERSION)
+                .json(&body)
+                .send()
+                .await
+                .map_err(|err| ExecutorError::Io(std::io::Error::other(err)))?;
+            let status = response.status();
+            let retry = status.as_u16() == 429 || status.is_server_error();
+            if retry && attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                attempt += 1;
+                continue;
+            }
+            let response: Value = response
+                .json()
+                .await
+                .map_err(|err| ExecutorError::Io(std::io::Error::other(err)))?;
+            if status.is_success() {
+                return Ok(response);
+            }
+            let message = response
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("request failed");
+            return Err(match status.as_u16() {
+                401 | 403 => ExecutorError::AuthRequired(message.to_string()),
+                _ => ExecutorError::Io(std::io::Error::other(format!(
+                    "the Anthropic API answered {status}: {message}"
+                ))),
+            });
+        }
+    }
+
+    async fn call_tool(
+        &self,
+        log_writer: &LogWriter,
+        call: &ToolCall,
+    ) -> Result<ToolOutput, ExecutorError> {
+        if tools::changes_worktree(&call.name)
+            && let Some(approvals) = &self.approvals
+        {
+            let status = match approvals
+                .request_tool_approval(&call.name, call.input.clone(), &call.id)
+                .await
+            {
+                Ok(status) => status,
+                Err(
+                    ExecutorApprovalError::ServiceUnavailable
+                    | ExecutorApprovalError::SessionNotRegistered,
+                ) => ApprovalStatus::Approved,
+                Err(err) => ApprovalStatus::Denied {
+                    reason: Some(format!("Approval request failed: {err}")),
+                },
+            };
+            if !matches!(status, ApprovalStatus::Approved) {
+                log_writer
+                    .log_raw(
+                        &json!({
+                            "type": "approval_response",
+                            "call_id": call.id,
+                            "tool_name": call.name,
+                            "approval_status": status,
+                        })
+                        .to_string(),
+                    )
+                    .await?;
+                return Ok(ToolOutput::error(match status {
+                    ApprovalStatus::Denied {
+                        reason: Some(reason),
+                    } => format!("The user denied this tool call: {reason}"),
+                    ApprovalStatus::TimedOut => "The approval request timed out".to_string(),
+                    _ => "The user denied this tool call".to_string(),
+                }));
+            }
+        }
+        Ok(tools::run(&self.tools, &call.name, &call.input).await)
+    }
+}
+
+#[async_trait]
+impl StandardCodingAgentExecutor for ClaudeApi {
+    fn use_approvals(&mut self, approvals: Arc<dyn ExecutorApprovalService>) {
+        self.approvals = Some(approvals);
+    }
+
+    async fn spawn(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        self.spawn_inner(current_dir, prompt, None, env).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        session_id: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        self.spawn_inner(current_dir, prompt, Some(session_id), env)
+            .await
+    }
+
+    async fn spawn_fork(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        parent_session_id: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        self.spawn_inner(current_dir, prompt, Some(parent_session_id), env)
+            .await
+    }
+
+    fn normalize_logs(&self, msg_store: Arc<MsgStore>, current_dir: &Path) {
+        let entry_index_provider = EntryIndexProvider::start_from(&msg_store);
+        ClaudeLogProcessor::process_logs(
+            msg_store.clone(),
+            current_dir,
+            entry_index_provider.clone(),
+            HistoryStrategy::Default,
+        );
+        normalize_stderr_logs(msg_store, entry_index_provider);
+    }
+
+    fn default_mcp_config_path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn get_availability_info(&self) -> AvailabilityInfo {
+        match self.api_key(&ExecutionEnv::default()) {
+            Ok(_) => AvailabilityInfo::InstallationFound,
+            Err(_) => AvailabilityInfo::NotFound,
+        }
+    }
+}
+
+fn default_to_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executors::claude::{ClaudeContentItem, ClaudeJson};
+
+    #[tokio::test]
+    async fn test_tools_run_in_the_worktree() {
+        let dir = std::env::temp_dir().join(format!("vk-claude-api-{}", Uuid::new_v4()));
+        let ctx = ToolContext {
+            current_dir: dir.clone(),
+            env: ExecutionEnv::default(),
+            read_only: false,
+        };
+        let run = |name: &'static str, input: Value| {
+            let ctx = ctx.clone();
+            async move { tools::run(&ctx, name, &input).await }
+        };
+
+        let written = run(
+            "Write",
+            json!({ "file_path": "src/lib.rs", "content": "fn a() {}\nfn b() {}\nfn b() {}\n" }),
+        )
+        .await;
+        assert!(!written.is_error, "{}", written.content);
+        let ambiguous = run(
+            "Edit",
+            json!({ "file_path": "src/lib.rs", "old_string": "fn b", "new_string": "fn c" }),
+        )
+        .await;
+        assert!(ambiguous.is_error);
+        assert!(ambiguous.content.contains("occurs 2 times"));
+        let edited = run(
+            "Edit",
+            json!({ "file_path": "src/lib.rs", "old_string": "fn a", "new_string": "fn z" }),
+        )
+        .await;
+        assert!(!edited.is_error);
+        assert_eq!(
+            run("Read", json!({ "file_path": "src/lib.rs", "limit": 1 }))
+                .await
+                .content,
+            "     1\tfn z() {}"
+        );
+        assert_eq!(
+            run("Glob", json!({ "pattern": "**/*.rs" })).await.content,
+            "src/lib.rs"
+        );
+        assert_eq!(
+            run("Grep", json!({ "pattern": "fn z", "glob": "*.rs" }))
+                .await
+                .content,
+            "src/lib.rs:1:fn z() {}"
+        );
+        let failed = run("Bash", json!({ "command": "echo out; exit 3" })).await;
+        assert_eq!(failed, ToolOutput::error("out\n\nExit code 3"));
+
+        // Read-only runs only get the tools that look
+        let read_only = ToolContext {
+            read_only: true,
+            ..ctx.clone()
+        };
+        assert!(
+            tools::run(&read_only, "Bash", &json!({ "command": "ls" }))
+                .await
+                .is_error
+        );
+        assert_eq!(tools::definitions(true).len(), 3);
+
+        // Results are logged as Claude Code logs them
+        let line = json!({
+            "type": "user",
+            "message": { "role": "user", "content": [edited.to_block("toolu_1")] },
+            "session_id": "s",
+        });
+        let ClaudeJson::User { message, .. } = serde_json::from_value(line).unwrap() else {
+            panic!("not a user message");
+        };
+        assert!(matches!(
+            &message.content[..],
+            [ClaudeContentItem::ToolResult { tool_use_id, is_error: Some(false), .. }] if tool_use_id == "toolu_1"
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_at_max_turns() {
+        ensure_rustls_crypto_provider();
+        let session = Session {
+            client: reqwest::Client::new(),
+            // Never sent to: the limit is checked before each request
+            base_url: "http://127.0.0.1:9".to_string(),
+            api_key: "sk-ant-test".to_string(),
+            model: "claude-sonnet-4-5".to_string(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+            max_turns: 0,
+            session_id: "s".to_string(),
+            tools: ToolContext {
+                current_dir: std::env::temp_dir(),
+                env: ExecutionEnv::default(),
+                read_only: true,
+            },
+            approvals: None,
+        };
+        let (writer, mut reader) = tokio::io::duplex(64 * 1024);
+        let log_writer = LogWriter::new(writer);
+        let (_interrupt_tx, interrupt_rx) = oneshot::channel();
+
+        let exit_result = session
+            .run(
+                vec![json!({ "role": "user", "content": "Fix the login page" })],
+                &log_writer,
+                interrupt_rx,
+            )
+            .await
+            .unwrap();
+        let ExecutorExitResult::Failed(FailureReason::TaskFailed(summary)) = exit_result else {
+            panic!("run did not fail at the turn limit");
+        };
+        assert_eq!(summary.stats.num_turns, Some(0));
+        assert!(summary.summary.unwrap().contains("raise max_turns"));
+
+        drop(log_writer);
+        let mut logs = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut reader, &mut logs)
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(logs.lines().last().unwrap()).unwrap();
+        assert_eq!(result["subtype"], "error_max_turns");
+        assert_eq!(result["is_error"], true);
+    }
+}
@@ -0,0 +1,592 @@
+//! Tools the model works in the worktree with.
+//!
+//! Names and inputs are Claude Code's, so the model calls them the way it is
+//! used to and the logged calls normalize like Claude Code's own.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tokio::process::Command;
+use workspace_utils::shell::get_shell_command;
+
+use crate::{env::ExecutionEnv, logs::transcript::truncate_middle};
+
+/// Tools that only look at the worktree
+const READ_ONLY_TOOLS: [&str; 3] = ["Read", "Glob", "Grep"];
+/// Tokens of a tool's output the model gets back
+const MAX_OUTPUT_TOKENS: usize = 8_000;
+const DEFAULT_BASH_TIMEOUT_MS: u64 = 120_000;
+const MAX_BASH_TIMEOUT_MS: u64 = 600_000;
+const DEFAULT_READ_LIMIT: usize = 2_000;
+/// Paths or lines a search returns at most
+const MAX_MATCHES: usize = 200;
+/// Directories searches don't descend into
+const SKIPPED_DIRS: [&str; 3] = [".git", "node_modules", "target"];
+
+/// Where and how tools run
+#[derive(Debug, Clone)]
+pub(super) struct ToolContext {
+    pub current_dir: PathBuf,
+    pub env: ExecutionEnv,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ToolOutput {
+    pub content: String,
+    pub is_error: bool,
+}
+
+impl ToolOutput {
+    pub fn error(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            is_error: true,
+        }
+    }
+
+    /// `tool_result` block answering the call `tool_use_id`
+    pub fn to_block(&self, tool_use_id: &str) -> Value {
+        json!({
+            "type": "tool_result",
+            "tool_use_id": tool_use_id,
+            "content": self.content,
+            "is_error": self.is_error,
+        })
+    }
+}
+
+pub(super) fn changes_worktree(name: &str) -> bool {
+    !READ_ONLY_TOOLS.contains(&name)
+}
+
+/// Tool definitions sent with each request
+pub(super) fn definitions(read_only: bool) -> Vec<Value> {
+    let tools = [
+        json!({
+            "name": "Bash",
+            "description": "Run a shell command in the repository and return its output.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string" },
+                    "description": {
+                        "type": "string",
+                        "description": "What the command does, in a few words",
+                    },
+                    "timeout": {
+                        "type": "integer",
+                        "description": "Timeout in milliseconds, up to 600000; defaults to 120000",
+                    },
+                },
+                "required": ["command"],
+            },
+        }),
+        json!({
+            "name": "Read",
+            "description": "Read a file, with line numbers.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "file_path": { "type": "string" },
+                    "offset": { "type": "integer", "description": "Line to start at, from 1" },
+                    "limit": { "type": "integer", "description": "Lines to read; defaults to 2000" },
+                },
+                "required": ["file_path"],
+            },
+        }),
+        json!({
+            "name": "Write",
+            "description": "Write a file, replacing it if it exists.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "file_path": { "type": "string" },
+                    "content": { "type": "string" },
+                },
+                "required": ["file_path", "content"],
+            },
+        }),
+        json!({
+            "name": "Edit",
+            "description": "Replace text in a file. old_string must occur exactly once unless replace_all is set.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "file_path": { "type": "string" },
+                    "old_string": { "type": "string" },
+                    "new_string": { "type": "string" },
+                    "replace_all": { "type": "boolean" },
+                },
+                "required": ["file_path", "old_string", "new_string"],
+            },
+        }),
+        json!({
+            "name": "Glob",
+            "description": "Find files by glob pattern, e.g. `src/**/*.rs`.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string" },
+                    "path": { "type": "string", "description": "Directory to search in" },
+                },
+                "required": ["pattern"],
+            },
+        }),
+        json!({
+            "name": "Grep",
+            "description": "Search file contents for a regular expression, returning matching lines.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string" },
+                    "path": { "type": "string", "description": "File or directory to search in" },
+                    "glob": { "type": "string", "description": "Only search files matching this glob" },
+                },
+                "required": ["pattern"],
+            },
+        }),
+    ];
+    tools
+        .into_iter()
+        .filter(|tool| !read_only || !changes_worktree(tool["name"].as_str().unwrap_or_default()))
+        .collect()
+}
+
+/// Run the tool `name` with the model's `input`
+pub(super) async fn run(ctx: &ToolContext, name: &str, input: &Value) -> ToolOutput {
+    if ctx.read_only && changes_worktree(name) {
+        return ToolOutput::error(format!("{name} is not available in read-only runs"));
+    }
+    let output = match name {
+        "Bash" => bash(ctx, input).await,
+        "Read" | "Write" | "Edit" | "Glob" | "Grep" => {
+            let (dir, name, input) = (ctx.current_dir.clone(), name.to_string(), input.clone());
+            tokio::task::spawn_blocking(move || file_tool(&dir, &name, &input))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()))
+        }
+        _ => Err(format!("Unknown tool {name}")),
+    };
+    match output {
+        Ok(content) => ToolOutput {
+            content: truncate_middle(&content, MAX_OUTPUT_TOKENS),
+            is_error: false,
+        },
+        Err(message) => ToolOutput::error(truncate_middle(&message, MAX_OUTPUT_TOKENS)),
+    }
+}
+
+#[derive(Deserialize)]
+struct BashInput {
+    command: String,
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ReadInput {
+    file_path: String,
+    #[serde(default)]
+    offset: Option<usize>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct WriteInput {
+    file_path: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct EditInput {
+    file_path: String,
+    old_string: String,
+    new_string: String,
+    #[serde(default)]
+    replace_all: bool,
+}
+
+#[derive(Deserialize)]
+struct GlobInput {
+    pattern: String,
+    #[serde(default)]
+    path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GrepInput {
+    pattern: String,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    glob: Option<String>,
+}
+
+fn parse<T: DeserializeOwned>(input: &Value) -> Result<T, String> {
+    T::deserialize(input).map_err(|e| format!("Invalid input: {e}"))
+}
+
+async fn bash(ctx: &ToolContext, input: &Value) -> Result<String, String> {
+    let input: BashInput = parse(input)?;
+    let timeout = Duration::from_millis(
+        input
+            .timeout
+            .unwrap_or(DEFAULT_BASH_TIMEOUT_MS)
+            .min(MAX_BASH_TIMEOUT_MS),
+    );
+    let (shell, shell_arg) = get_shell_command();
+    let mut command = Command::new(shell);
+    command
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .current_dir(&ctx.current_dir)
+        .arg(shell_arg)
+        .arg(&input.command);
+    ctx.env
+        .apply_to_command(&mut command)
+        .map_err(|e| format!("Failed to run the command: {e}"))?;
+
+    let output = match tokio::time::timeout(timeout, command.output()).await {
+        Ok(output) => output.map_err(|e| format!("Failed to run the command: {e}"))?,
+        Err(_) => return Err(format!("Command timed out after {}ms", timeout.as_millis())),
+    };
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.is_empty() {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&stderr);
+    }
+    if output.status.success() {
+        return Ok(text);
+    }
+    let status = match output.status.code() {
+        Some(code) => format!("Exit code {code}"),
+        None => "Killed by a signal".to_string(),
+    };
+    Err(format!("{text}\n{status}").trim_start().to_string())
+}
+
+fn file_tool(current_dir: &Path, name: &str, input: &Value) -> Result<String, String> {
+    let resolve = |path: &str| current_dir.join(path);
+    match name {
+        "Read" => {
+            let input: ReadInput = parse(input)?;
+            let content = fs::read_to_string(resolve(&input.file_path))
+                .map_err(|e| format!("Failed to read {}: {e}", input.file_path))?;
+            let start = input.offset.unwrap_or(1).max(1);
+            let limit = input.limit.unwrap_or(DEFAULT_READ_LIMIT);
+            let lines: Vec<String> = content
+                .lines()
+                .enumerate()
+                .skip(start - 1)
+                .take(limit)
+                .map(|(index, line)| format!("{:>6}\t{line}", index + 1))
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        "Write" => {
+            let input: WriteInput = parse(input)?;
+            let path = resolve(&input.file_path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+            }
+            fs::write(&path, &input.content)
+                .map_err(|e| format!("Failed to write {}: {e}", input.file_path))?;
+            Ok(format!(
+                "Wrote {} bytes to {}",
+                input.content.len(),
+                input.file_path
+            ))
+        }
+        "Edit" => {
+            let input: EditInput = parse(input)?;
+            let path = resolve(&input.file_path);
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {e}", input.file_path))?;
+            let found = if input.old_string.is_empty() {
+                0
+            } else {
+                content.matches(&input.old_string).count()
+            };
+            match found {
+                0 => return Err(format!("old_string not found in {}", input.file_path)),
+                1 => {}
+                _ if input.replace_all => {}
+                _ => {
+                    return Err(format!(
+                        "old_string occurs {found} times in {}; add context to make it unique or set replace_all",
+                        input.file_path
+                    ));
+                }
+            }
+            fs::write(&path, content.replace(&input.old_string, &input.new_string))
+                .map_err(|e| format!("Failed to write {}: {e}", input.file_path))?;
+            Ok(format!(
+                "Replaced {found} occurrence(s) in {}",
+                input.file_path
+            ))
+        }
+        "Glob" => {
+            let input: GlobInput = parse(input)?;
+            let root = resolve(input.path.as_deref().unwrap_or("."));
+            let matcher = glob_matcher(&input.pattern)?;
+            let mut paths: Vec<String> = walk(&root)
+                .into_iter()
+                .filter_map(|path| {
+                    let relative = path.strip_prefix(&root).ok()?;
+                    matcher
+                        .is_match(relative)
+                        .then(|| relative.to_string_lossy().into_owned())
+                })
+                .collect();
+            paths.sort();
+            Ok(limit_matches(paths, "files"))
+        }
+        "Grep" => {
+            let input: GrepInput = parse(input)?;
+            let regex = Regex::new(&input.pattern).map_err(|e| format!("Invalid pattern: {e}"))?;
+            let matcher = input.glob.as_deref().map(glob_matcher).transpose()?;
+            let root = resolve(input.path.as_deref().unwrap_or("."));
+            let mut lines = Vec::new();
+            for path in walk(&root) {
+                let relative = path.strip_prefix(current_dir).unwrap_or(&path);
+                if let Some(matcher) = &matcher
+                    && !path.file_name().is_some_and(|name| matcher.is_match(name))
+                    && !matcher.is_match(relative)
+                {
+                    continue;
+                }
+                // Binary and unreadable files are skipped
+                let Ok(content) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                lines.extend(
+                    content
+                        .lines()
+                        .enumerate()
+                        .filter(|(_, line)| regex.is_match(line))
+                        .map(|(index, line)| {
+                            format!("{}:{}:{line}", relative.display(), index + 1)
+                        }),
+                );
+                if lines.len() > MAX_MATCHES {
+                    break;
+                }
+            }
+            Ok(limit_matches(lines, "matching lines"))
+        }
+        _ => Err(format!("Unknown tool {name}")),
+    }
+}
+
+fn glob_matcher(pattern: &str) -> Result<GlobMatcher, String> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| format!("Invalid glob: {e}"))
+}
+
+/// Files under `root`, or `root` itself if it is a file
+fn walk(root: &Path) -> Vec<PathBuf> {
+    if root.is_file() {
+        return vec![root.to_path_buf()];
+    }
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+fn limit_matches(mut found: Vec<String>, what: &str) -> String {
+    if found.is_empty() {
+        return format!("No {what} found");
+    }
+    if found.len() > MAX_MATCHES {
+        found.truncate(MAX_MATCHES);
+        found.push(format!("(only the first {MAX_MATCHES} {what} are shown)"));
+    }
+    found.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn context(dir: &TempDir) -> ToolContext {
+        ToolContext {
+            current_dir: dir.path().to_path_buf(),
+            env: ExecutionEnv::default(),
+            read_only: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tools_are_dispatched_by_name() {
+        let dir = TempDir::new().unwrap();
+        let mut ctx = context(&dir);
+        ctx.env.insert("VK_TASK_TITLE", "Fix login");
+
+        let written = run(
+            &ctx,
+            "Write",
+            &json!({ "file_path": "notes/todo.md", "content": "one\ntwo\nthree\n" }),
+        )
+        .await;
+        assert_eq!(
+            written,
+            ToolOutput {
+                content: "Wrote 14 bytes to notes/todo.md".to_string(),
+                is_error: false,
+            }
+        );
+        assert_eq!(
+            run(
+                &ctx,
+                "Read",
+                &json!({ "file_path": "notes/todo.md", "offset": 2, "limit": 1 })
+            )
+            .await
+            .content,
+            "     2\ttwo"
+        );
+        assert_eq!(
+            run(
+                &ctx,
+                "Bash",
+                &json!({ "command": "echo \"$VK_TASK_TITLE\"" })
+            )
+            .await
+            .content,
+            "Fix login\n"
+        );
+        assert_eq!(
+            run(&ctx, "WebFetch", &json!({})).await,
+            ToolOutput::error("Unknown tool WebFetch")
+        );
+
+        // Read-only runs may look but not change anything
+        ctx.read_only = true;
+        assert_eq!(
+            run(&ctx, "Edit", &json!({})).await,
+            ToolOutput::error("Edit is not available in read-only runs")
+        );
+        assert_eq!(
+            run(&ctx, "Grep", &json!({ "pattern": "^t" })).await.content,
+            "notes/todo.md:2:two\nnotes/todo.md:3:three"
+        );
+        assert!(changes_worktree("Bash") && !changes_worktree("Glob"));
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_error_results() {
+        let dir = TempDir::new().unwrap();
+        let ctx = context(&dir);
+        fs::write(dir.path().join("a.txt"), "alpha\n").unwrap();
+
+        let missing_input = run(&ctx, "Read", &json!({ "limit": 1 })).await;
+        assert!(missing_input.is_error);
+        assert!(missing_input.content.starts_with("Invalid input: "));
+        let not_found = run(
+            &ctx,
+            "Edit",
+            &json!({ "file_path": "a.txt", "old_string": "beta", "new_string": "gamma" }),
+        )
+        .await;
+        assert_eq!(
+            not_found,
+            ToolOutput::error("old_string not found in a.txt")
+        );
+        assert!(
+            run(&ctx, "Grep", &json!({ "pattern": "(" }))
+                .await
+                .content
+                .starts_with("Invalid pattern: ")
+        );
+        assert_eq!(
+            run(
+                &ctx,
+                "Bash",
+                &json!({ "command": "sleep 5", "timeout": 50 })
+            )
+            .await,
+            ToolOutput::error("Command timed out after 50ms")
+        );
+
+        // The model is told which call failed
+        assert_eq!(
+            not_found.to_block("toolu_7"),
+            json!({
+                "type": "tool_result",
+                "tool_use_id": "toolu_7",
+                "content": "old_string not found in a.txt",
+                "is_error": true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_searches_stop_at_the_match_limit() {
+        let dir = TempDir::new().unwrap();
+        let ctx = context(&dir);
+        for i in 0..MAX_MATCHES + 5 {
+            fs::write(dir.path().join(format!("{i:03}.txt")), "match\n").unwrap();
+        }
+        fs::create_dir(dir.path().join("node_modules")).unwrap();
+        fs::write(dir.path().join("node_modules/dep.js"), "match\n").unwrap();
+
+        let found = run(&ctx, "Glob", &json!({ "pattern": "*.txt" }))
+            .await
+            .content;
+        let lines: Vec<&str> = found.lines().collect();
+        assert_eq!(lines.len(), MAX_MATCHES + 1);
+        assert_eq!(lines[0], "000.txt");
+        assert_eq!(
+            lines[MAX_MATCHES],
+            format!("(only the first {MAX_MATCHES} files are shown)")
+        );
+        let matched = run(&ctx, "Grep", &json!({ "pattern": "match" }))
+            .await
+            .content;
+        assert_eq!(matched.lines().count(), MAX_MATCHES + 1);
+        assert!(!matched.contains("node_modules"));
+        assert_eq!(
+            run(&ctx, "Glob", &json!({ "pattern": "**/*.js" }))
+                .await
+                .content,
+            "No files found"
+        );
+    }
+}
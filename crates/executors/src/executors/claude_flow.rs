@@ -1,24 +1,68 @@
-use std::{path::Path, process::Stdio, sync::Arc};
+pub mod workflow;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use command_group::AsyncCommandGroup;
+use futures::StreamExt;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::{io::AsyncWriteExt, process::Command};
+use serde_json::Value;
+use tokio::{
+    io::AsyncWriteExt,
+    process::{ChildStdin, Command},
+};
 use ts_rs::TS;
 use workspace_utils::msg_store::MsgStore;
 
+use self::workflow::{WorkflowProgress, WorkflowSpec};
 use crate::{
-    command::{CmdOverrides, CommandBuilder, apply_overrides},
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, CommandParts, apply_overrides},
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
-        claude::{ClaudeLogProcessor, HistoryStrategy},
+        claude::{
+            ClaudeLogProcessor, ClaudeModel, ClaudeReasoningEffort, HistoryStrategy,
+            thinking_budget, types::Message, with_api_key,
+        },
+    },
+    interrupt::sigint_interrupt_sender,
+    logs::{
+        ActionType, MemoryOperation, NormalizedEntry, NormalizedEntryType, RunSummary,
+        stderr_processor::normalize_stderr_logs,
+        utils::{EntryIndexProvider, patch::add_normalized_entry},
     },
-    logs::{stderr_processor::normalize_stderr_logs, utils::EntryIndexProvider},
+    mcp_config::McpConfigScope,
+    outcome::{ExecutionOutcome, OutcomeStats, OutcomeStatus, report_outcome},
+    probe,
+    profile::RunOverrides,
+    prompt::{PromptDelivery, write_prompt_file},
+    secrets,
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
+const BASE_COMMAND: &str = "npx -y claude-flow";
+/// Turns of replayed history shown when `history_turns` isn't set
+const DEFAULT_HISTORY_TURNS: u32 = 3;
+
+/// How much of its history a resumed claude-flow session shows when it
+/// replays it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayHistory {
+    /// Every earlier turn as it was
+    Full,
+    /// Only the last `history_turns` turns
+    LastTurns,
+    /// Every earlier turn, with long tool outputs shortened
+    Compacted,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
 pub struct ClaudeFlow {
     #[serde(default)]
     pub append_prompt: AppendPrompt,
@@ -47,22 +91,96 @@ pub struct ClaudeFlow {
     )]
     pub workflow_file: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Workflow",
+        description = "Workflow defined inline, used instead of the workflow file"
+    )]
+    pub workflow: Option<WorkflowSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[schemars(
         title = "Task Description",
         description = "Task description for automation commands"
     )]
     pub task_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Memory Namespace",
+        description = "claude-flow memory namespace to read and write"
+    )]
+    pub memory_namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Persist Memory",
+        description = "Share memory between all attempts of a task when no namespace is set"
+    )]
+    pub persist_memory: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Resumed History",
+        description = "How much of the history a resumed session replays to show"
+    )]
+    pub history: Option<ReplayHistory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "History Turns",
+        description = "Turns of replayed history to show with the last_turns history"
+    )]
+    pub history_turns: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Model",
+        description = "Model the agents run: sonnet, opus, haiku or a full model name"
+    )]
+    pub model: Option<ClaudeModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Fallback Model",
+        description = "Model to switch to when the main one is overloaded"
+    )]
+    pub fallback_model: Option<ClaudeModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Max Thinking Tokens",
+        description = "Tokens the agents may spend thinking before each answer"
+    )]
+    pub max_thinking_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Reasoning Effort",
+        description = "Thinking budget by level: low, medium or high, when max thinking tokens isn't set"
+    )]
+    pub reasoning_effort: Option<ClaudeReasoningEffort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "API Key Secret",
+        description = "Secret holding the ANTHROPIC_API_KEY to run with, for headless deployments without a Claude login"
+    )]
+    pub api_key_secret: Option<String>,
+    #[schemars(
+        title = "Prompt Delivery",
+        description = "How the prompt reaches the agent: auto uses a file for prompts of 256 KiB or more"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_delivery: Option<PromptDelivery>,
+    #[schemars(
+        title = "Interactive",
+        description = "Keep the agent's input open so messages can be sent while it runs"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interactive: Option<bool>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
 }
 
 impl ClaudeFlow {
     fn build_command_builder(&self) -> CommandBuilder {
         // Base command - use claude-flow automation for non-interactive mode
         let base_cmd = if self.non_interactive.unwrap_or(false) {
-            "npx -y claude-flow automation"
+            format!("{BASE_COMMAND} automation")
         } else {
-            "npx -y claude-flow"
+            BASE_COMMAND.to_string()
         };
 
         let mut builder = CommandBuilder::new(base_cmd)
@@ -79,8 +197,11 @@ impl ClaudeFlow {
             builder = builder.extend_params(["--agent", agent_id]);
         }
 
-        // Add workflow file if specified
-        if let Some(workflow) = &self.workflow_file {
+        // Add workflow file if specified; an inline workflow is written to
+        // one per worktree
+        if self.workflow.is_none()
+            && let Some(workflow) = &self.workflow_file
+        {
             builder = builder.extend_params(["--workflow", workflow]);
         }
 
@@ -89,22 +210,108 @@ impl ClaudeFlow {
             builder = builder.extend_params(["--task", task]);
         }
 
-        apply_overrides(builder, &self.cmd)
+        if let Some(model) = &self.model {
+            builder = builder.extend_params(["--model", model.as_str()]);
+        }
+        if let Some(model) = &self.fallback_model {
+            builder = builder.extend_params(["--fallback-model", model.as_str()]);
+        }
+        if let Some(tokens) = thinking_budget(self.max_thinking_tokens, self.reasoning_effort) {
+            builder =
+                builder.extend_params(["--max-thinking-tokens".to_string(), tokens.to_string()]);
+        }
+
+        apply_overrides(builder, &self.cmd, &self.run)
     }
-}
 
-#[async_trait]
-impl StandardCodingAgentExecutor for ClaudeFlow {
-    async fn spawn(
+    fn history_strategy(&self) -> HistoryStrategy {
+        match self.history {
+            None => HistoryStrategy::Default,
+            Some(ReplayHistory::Full) => HistoryStrategy::FullReplay,
+            Some(ReplayHistory::LastTurns) => HistoryStrategy::LastTurns(
+                self.history_turns.unwrap_or(DEFAULT_HISTORY_TURNS) as usize,
+            ),
+            Some(ReplayHistory::Compacted) => HistoryStrategy::Compacted,
+        }
+    }
+
+    /// Memory namespace for this run: the configured one, or one per task
+    /// when memory is persisted across attempts
+    fn resolve_memory_namespace(&self, env: &ExecutionEnv) -> Option<String> {
+        if let Some(namespace) = &self.memory_namespace {
+            return Some(namespace.clone());
+        }
+        if !self.persist_memory.unwrap_or(false) {
+            return None;
+        }
+        env.vars
+            .get("VK_TASK_ID")
+            .map(|task_id| format!("vk-task-{task_id}"))
+    }
+
+    /// Progress of the run's workflow before it starts: the tasks of the
+    /// inline workflow, or of the workflow file if it can be read. Tasks of
+    /// other runs are picked up from their events.
+    fn initial_workflow_progress(&self, current_dir: &Path) -> WorkflowProgress {
+        let spec = match (&self.workflow, &self.workflow_file) {
+            (Some(workflow), _) => Some(workflow.clone()),
+            (None, Some(file)) => std::fs::read_to_string(current_dir.join(file))
+                .ok()
+                .and_then(|content| serde_json::from_str::<WorkflowSpec>(&content).ok()),
+            (None, None) => None,
+        };
+        spec.as_ref()
+            .map(WorkflowProgress::from_spec)
+            .unwrap_or_default()
+    }
+
+    /// MCP config checked into a worktree
+    pub fn project_mcp_config_path(worktree: &Path) -> PathBuf {
+        worktree.join(".claude-flow").join("config.json")
+    }
+
+    /// Config to point claude-flow at instead of the one in the home
+    /// directory: the worktree's own when the env asks for project scope and
+    /// the worktree has one
+    fn resolve_mcp_config_path(&self, current_dir: &Path, env: &ExecutionEnv) -> Option<PathBuf> {
+        if env.mcp_scope != McpConfigScope::Project {
+            return None;
+        }
+        let path = Self::project_mcp_config_path(current_dir);
+        path.is_file().then_some(path)
+    }
+
+    fn command_builder_for(
+        &self,
+        current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandBuilder, ExecutorError> {
+        let mut builder = self.build_command_builder();
+        if let Some(workflow) = &self.workflow {
+            let path = workflow.write_file(current_dir)?;
+            builder = builder.extend_params(["--workflow", &path.to_string_lossy()]);
+        }
+        if let Some(namespace) = self.resolve_memory_namespace(env) {
+            builder = builder.extend_params(["--memory-namespace", namespace.as_str()]);
+        }
+        if let Some(config_path) = self.resolve_mcp_config_path(current_dir, env) {
+            builder = builder.extend_params(["--config", &config_path.to_string_lossy()]);
+        }
+        Ok(builder)
+    }
+
+    async fn spawn_internal(
         &self,
         current_dir: &Path,
         prompt: &str,
+        command_parts: CommandParts,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
-        let command_parts = self.build_command_builder().build_initial()?;
         let (executable_path, args) = command_parts.into_resolved().await?;
 
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let mut command = Command::new(executable_path);
         command
@@ -115,19 +322,263 @@ impl StandardCodingAgentExecutor for ClaudeFlow {
             .current_dir(current_dir)
             .args(&args);
 
-        env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+        let delivery = self.prompt_delivery.unwrap_or_default();
+        let prompt_file = match self.prompt_file_flag() {
+            Some(flag) if delivery.uses_file(&combined_prompt) => {
+                let path =
+                    write_prompt_file(current_dir, &combined_prompt).map_err(ExecutorError::Io)?;
+                command.arg(flag).arg(&path);
+                Some(path)
+            }
+            _ => None,
+        };
+
+        with_api_key(
+            env.clone().with_profile(&self.cmd, &self.run),
+            self.api_key_secret.as_deref(),
+        )?
+        .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
-        // Feed the prompt in, then close the pipe so claude-flow sees EOF
+        let mut input_sender = None;
         if let Some(mut stdin) = child.inner().stdin.take() {
-            stdin.write_all(combined_prompt.as_bytes()).await?;
-            stdin.shutdown().await?;
+            if self.interactive.unwrap_or(false) {
+                // Messages are stream-json user events, one per line, so the
+                // pipe stays open for the ones sent mid-run
+                if prompt_file.is_none() {
+                    write_user_event(&mut stdin, combined_prompt).await?;
+                }
+                let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+                tokio::spawn(async move {
+                    while let Some(message) = rx.recv().await {
+                        if let Err(e) = write_user_event(&mut stdin, message).await {
+                            tracing::warn!("Failed to send user input to claude-flow: {e}");
+                            break;
+                        }
+                    }
+                });
+                input_sender = Some(tx);
+            } else {
+                // Feed the prompt in, unless it is in a file, then close the
+                // pipe so claude-flow sees EOF
+                if prompt_file.is_none() {
+                    stdin.write_all(combined_prompt.as_bytes()).await?;
+                }
+                stdin.shutdown().await?;
+            }
+        }
+
+        // Interrupts go through SIGINT, as there is no control protocol
+        let interrupt_sender = sigint_interrupt_sender(&child);
+
+        let mut spawned = SpawnedChild {
+            child,
+            exit_signal: None,
+            interrupt_sender,
+            input_sender,
+        };
+        // claude-flow exits with 0 even when the task failed
+        report_outcome(&mut spawned, parse_result_event)?;
+        Ok(spawned)
+    }
+}
+
+async fn write_user_event(stdin: &mut ChildStdin, content: String) -> std::io::Result<()> {
+    let event = serde_json::to_string(&Message::new_user(content))?;
+    stdin.write_all(event.as_bytes()).await?;
+    stdin.write_all(b"\n").await?;
+    stdin.flush().await
+}
+
+/// Outcome from claude-flow's terminal `result` event. claude-flow reports a
+/// `status` and `summary`; results relayed from Claude Code carry
+/// `is_error`/`subtype` and `result` instead.
+pub(crate) fn parse_result_event(line: &str) -> Option<ExecutionOutcome> {
+    let event: Value = serde_json::from_str(line).ok()?;
+    if event.get("type").and_then(Value::as_str) != Some("result") {
+        return None;
+    }
+    let field = |keys: &[&str]| keys.iter().find_map(|key| event.get(*key));
+    let text = |key: &str| event.get(key).and_then(Value::as_str).map(str::to_string);
+
+    let failed = match event.get("status").and_then(Value::as_str) {
+        Some(status) => !matches!(status, "success" | "completed"),
+        None => {
+            field(&["is_error", "isError"]).and_then(Value::as_bool) == Some(true)
+                || text("subtype").is_some_and(|subtype| subtype != "success")
+        }
+    };
+    Some(ExecutionOutcome {
+        status: if failed {
+            OutcomeStatus::Error
+        } else {
+            OutcomeStatus::Success
+        },
+        summary: text("summary")
+            .or_else(|| text("result"))
+            .or_else(|| text("error")),
+        stats: OutcomeStats {
+            duration_ms: field(&["duration_ms", "durationMs"]).and_then(Value::as_u64),
+            num_turns: field(&["num_turns", "numTurns"])
+                .and_then(Value::as_u64)
+                .map(|turns| turns as u32),
+            total_cost_usd: field(&["total_cost_usd", "totalCostUsd"]).and_then(Value::as_f64),
+        },
+    })
+}
+
+/// Digest of a claude-flow automation run from its terminal `result` event,
+/// as an entry. Only events that report tasks or agents make one, so results
+/// relayed from Claude Code don't.
+pub(crate) fn parse_run_summary(line: &str) -> Option<NormalizedEntry> {
+    let event: Value = serde_json::from_str(line).ok()?;
+    if event.get("type").and_then(Value::as_str) != Some("result") {
+        return None;
+    }
+    let field = |keys: &[&str]| keys.iter().find_map(|key| event.get(*key));
+    let count = |value: &Value| value.as_u64().map(|n| n as u32);
+
+    // Tasks are either counted at the top level or grouped under `tasks`
+    let tasks = event.get("tasks").filter(|tasks| tasks.is_object());
+    let tasks_completed = field(&["tasks_completed", "tasksCompleted", "completed_tasks"])
+        .or_else(|| tasks?.get("completed"))
+        .and_then(count);
+    let tasks_total = field(&["tasks_total", "tasksTotal", "total_tasks"])
+        .or_else(|| tasks?.get("total"))
+        .and_then(count);
+    let agents: Vec<String> = field(&["agents", "agents_used", "agentsUsed"])
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|agent| match agent {
+            Value::String(name) => Some(name.clone()),
+            agent => ["name", "type", "id"]
+                .iter()
+                .find_map(|key| agent.get(*key)?.as_str())
+                .map(str::to_string),
+        })
+        .collect();
+    if tasks_completed.is_none() && tasks_total.is_none() && agents.is_empty() {
+        return None;
+    }
+    let summary = RunSummary {
+        tasks_completed,
+        tasks_total,
+        agents,
+        duration_ms: field(&["duration_ms", "durationMs"]).and_then(Value::as_u64),
+        metrics: None,
+    };
+
+    let content = match event.get("summary").and_then(Value::as_str) {
+        Some(text) => text.to_string(),
+        None => {
+            let mut parts = Vec::new();
+            match (summary.tasks_completed, summary.tasks_total) {
+                (Some(done), Some(total)) => parts.push(format!("{done}/{total} tasks completed")),
+                (Some(done), None) => parts.push(format!("{done} tasks completed")),
+                (None, Some(total)) => parts.push(format!("{total} tasks")),
+                (None, None) => {}
+            }
+            if !summary.agents.is_empty() {
+                parts.push(format!("agents: {}", summary.agents.join(", ")));
+            }
+            if let Some(ms) = summary.duration_ms {
+                parts.push(format!("{:.1}s", ms as f64 / 1000.0));
+            }
+            parts.join(", ")
         }
+    };
+    Some(NormalizedEntry {
+        timestamp: None,
+        entry_type: NormalizedEntryType::RunSummary { summary },
+        content,
+        metadata: None,
+        agent_id: None,
+        agent_role: None,
+    })
+}
+
+/// Swarm agent announced by a claude-flow chaining event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SwarmAgent {
+    pub id: String,
+    pub role: Option<String>,
+}
+
+/// Agent that output following a chaining event comes from. With chaining,
+/// claude-flow interleaves the stream-json of its agents and announces each
+/// one with an event carrying its id and type, either at the top level or
+/// under `agent`.
+pub(crate) fn chaining_agent(event: &HashMap<String, Value>) -> Option<SwarmAgent> {
+    let text = |value: &Value, keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| value.get(*key)?.as_str())
+            .map(str::to_string)
+    };
+    let (id, role) = match event.get("agent") {
+        Some(agent @ Value::Object(_)) => (
+            text(agent, &["id", "agentId", "agent_id", "name"]),
+            text(agent, &["type", "role", "agentType", "agent_type"]),
+        ),
+        _ => {
+            let field = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| event.get(*key)?.as_str())
+                    .map(str::to_string)
+            };
+            (
+                field(&["agentId", "agent_id"]),
+                field(&["agentType", "agent_type", "agentRole", "agent_role"]),
+            )
+        }
+    };
+    Some(SwarmAgent { id: id?, role })
+}
 
-        Ok(child.into())
+/// Normalize a call to claude-flow's memory tools, such as
+/// `mcp__claude-flow__memory_usage`, into a memory action
+pub(crate) fn memory_action(tool_name: &str, input: &Value) -> Option<ActionType> {
+    let mut parts = tool_name.split("__");
+    let (Some("mcp"), Some(server), Some(tool)) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    if !server.starts_with("claude-flow") {
+        return None;
+    }
+    let operation = match tool {
+        "memory_usage" => match input.get("action").and_then(Value::as_str)? {
+            "store" => MemoryOperation::Store,
+            "retrieve" => MemoryOperation::Retrieve,
+            "search" => MemoryOperation::Search,
+            "list" => MemoryOperation::List,
+            "delete" => MemoryOperation::Delete,
+            _ => return None,
+        },
+        "memory_search" => MemoryOperation::Search,
+        _ => return None,
+    };
+    let field = |name: &str| input.get(name).and_then(Value::as_str).map(str::to_string);
+    Some(ActionType::Memory {
+        operation,
+        namespace: field("namespace"),
+        key: field("key").or_else(|| field("pattern")),
+    })
+}
+
+#[async_trait]
+impl StandardCodingAgentExecutor for ClaudeFlow {
+    async fn spawn(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let command_parts = self
+            .command_builder_for(current_dir, env)?
+            .build_initial()?;
+        self.spawn_internal(current_dir, prompt, command_parts, env)
+            .await
     }
 
     async fn spawn_follow_up(
@@ -137,40 +588,29 @@ impl StandardCodingAgentExecutor for ClaudeFlow {
         session_id: &str,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
-        // Claude-flow doesn't support follow-up with session_id like ClaudeCode
-        // We need to use a different approach for continuing conversations
-        // For now, we'll spawn a new process with the session context
-
-        let command_parts = self.build_command_builder().build_follow_up(&[
-            "--resume".to_string(),
-            session_id.to_string(),
-        ])?;
-        let (executable_path, args) = command_parts.into_resolved().await?;
-
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
-
-        let mut command = Command::new(executable_path);
-        command
-            .kill_on_drop(true)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .current_dir(current_dir)
-            .args(&args);
-
-        env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
-
-        let mut child = command.group_spawn()?;
-
-        // Feed the prompt in, then close the pipe so claude-flow sees EOF
-        if let Some(mut stdin) = child.inner().stdin.take() {
-            stdin.write_all(combined_prompt.as_bytes()).await?;
-            stdin.shutdown().await?;
-        }
+        let command_parts = self
+            .command_builder_for(current_dir, env)?
+            .build_follow_up(&["--resume".to_string(), session_id.to_string()])?;
+        self.spawn_internal(current_dir, prompt, command_parts, env)
+            .await
+    }
 
-        Ok(child.into())
+    async fn spawn_fork(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        parent_session_id: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let command_parts = self
+            .command_builder_for(current_dir, env)?
+            .build_follow_up(&[
+                "--fork-session".to_string(),
+                "--resume".to_string(),
+                parent_session_id.to_string(),
+            ])?;
+        self.spawn_internal(current_dir, prompt, command_parts, env)
+            .await
     }
 
     fn normalize_logs(&self, msg_store: Arc<MsgStore>, current_dir: &Path) {
@@ -182,7 +622,25 @@ impl StandardCodingAgentExecutor for ClaudeFlow {
             msg_store.clone(),
             current_dir,
             entry_index_provider.clone(),
-            HistoryStrategy::Default,
+            self.history_strategy(),
+        );
+
+        // The final summary of an automation run, which Claude's processor
+        // doesn't know about
+        let summary_store = msg_store.clone();
+        let summary_index_provider = entry_index_provider.clone();
+        tokio::spawn(async move {
+            let mut lines = summary_store.stdout_lines_stream();
+            while let Some(Ok(line)) = lines.next().await {
+                if let Some(entry) = parse_run_summary(&line) {
+                    add_normalized_entry(&summary_store, &summary_index_provider, entry);
+                }
+            }
+        });
+
+        workflow::spawn_progress_tracker(
+            msg_store.clone(),
+            self.initial_workflow_progress(current_dir),
         );
 
         // Process stderr logs using the standard stderr processor
@@ -195,6 +653,13 @@ impl StandardCodingAgentExecutor for ClaudeFlow {
     }
 
     fn get_availability_info(&self) -> AvailabilityInfo {
+        // Headless runs need their API key rather than a login
+        if let Some(name) = &self.api_key_secret {
+            return match secrets::resolve(name, None) {
+                Ok(_) => AvailabilityInfo::InstallationFound,
+                Err(_) => AvailabilityInfo::NotFound,
+            };
+        }
         let config_file_path = self.default_mcp_config_path();
 
         if let Some(path) = config_file_path
@@ -210,12 +675,58 @@ impl StandardCodingAgentExecutor for ClaudeFlow {
         }
         AvailabilityInfo::NotFound
     }
+
+    async fn probe(&self) -> AvailabilityInfo {
+        let base = self
+            .cmd
+            .base_command_override
+            .as_deref()
+            .unwrap_or(BASE_COMMAND);
+        if let Some(name) = &self.api_key_secret
+            && secrets::resolve(name, None).is_err()
+        {
+            return AvailabilityInfo::NotFound;
+        }
+        match probe::probe_availability(base, self.min_supported_version()).await {
+            Some(info) => info,
+            None => self.get_availability_info(),
+        }
+    }
+
+    fn min_supported_version(&self) -> Option<&'static str> {
+        // The 2.0 line added stream-json output
+        Some("2.0.0")
+    }
+
+    fn prompt_file_flag(&self) -> Option<&'static str> {
+        Some("--prompt-file")
+    }
+
+    fn install_command(&self) -> Option<&'static str> {
+        // `npx -y claude-flow` prefers a global install over downloading
+        Some("npm i -g claude-flow")
+    }
+
+    async fn explain(
+        &self,
+        current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        let env = with_api_key(env.clone(), self.api_key_secret.as_deref())?;
+        Ok(self
+            .command_builder_for(current_dir, &env)?
+            .explain(&env, &self.cmd)?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Mutex;
+
     use super::*;
-    use std::path::PathBuf;
+
+    /// Held by tests that depend on `HOME`, which one of them points elsewhere
+    static HOME_LOCK: Mutex<()> = Mutex::new(());
 
     #[test]
     fn test_claude_flow_deserialization() {
@@ -264,8 +775,21 @@ mod tests {
             enable_chaining: Some(true),
             agent_id: Some("test-agent".to_string()),
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let builder = flow.build_command_builder();
@@ -286,8 +810,21 @@ mod tests {
             enable_chaining: None,
             agent_id: None,
             workflow_file: Some("test.json".to_string()),
+            workflow: None,
             task_description: Some("my task".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let builder = flow.build_command_builder();
@@ -297,7 +834,7 @@ mod tests {
         assert!(cmd_str.contains("--output-format stream-json"));
         assert!(cmd_str.contains("--input-format stream-json"));
         assert!(cmd_str.contains("--workflow test.json"));
-        assert!(cmd_str.contains("--task my task"));
+        assert!(cmd_str.contains("--task 'my task'"));
     }
 
     #[test]
@@ -308,8 +845,21 @@ mod tests {
             enable_chaining: None,
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let builder = flow.build_command_builder();
@@ -328,8 +878,21 @@ mod tests {
             enable_chaining: None,
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let combined = flow.append_prompt.combine_prompt("Base prompt");
@@ -344,8 +907,21 @@ mod tests {
             enable_chaining: None,
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let combined = flow.append_prompt.combine_prompt("Base prompt");
@@ -360,8 +936,21 @@ mod tests {
             enable_chaining: None,
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let config_path = flow.default_mcp_config_path();
@@ -380,8 +969,21 @@ mod tests {
             enable_chaining: Some(false),
             agent_id: Some("agent1".to_string()),
             workflow_file: Some("workflow.json".to_string()),
+            workflow: None,
             task_description: Some("test task".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let serialized = serde_json::to_string(&original).unwrap();
@@ -403,7 +1005,19 @@ mod tests {
             enable_chaining: Some(true),
             agent_id: Some("swarm-coordinator".to_string()),
             workflow_file: Some("complex-workflow.json".to_string()),
+            workflow: None,
             task_description: Some("Complex multi-agent task".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: Some(ClaudeModel::Opus),
+            fallback_model: Some(ClaudeModel::Other("claude-sonnet-4-5".to_string())),
+            max_thinking_tokens: None,
+            reasoning_effort: Some(ClaudeReasoningEffort::Medium),
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides {
                 base_command_override: Some("custom-claude-flow".to_string()),
                 additional_params: Some(vec!["--param1".to_string(), "--param2".to_string()]),
@@ -411,7 +1025,9 @@ mod tests {
                     ("ENV_VAR1".to_string(), "value1".to_string()),
                     ("ENV_VAR2".to_string(), "value2".to_string()),
                 ])),
+                ..Default::default()
             },
+            run: RunOverrides::default(),
         };
 
         let builder = flow.build_command_builder();
@@ -426,7 +1042,10 @@ mod tests {
         assert!(cmd_str.contains("--chaining"));
         assert!(cmd_str.contains("--agent swarm-coordinator"));
         assert!(cmd_str.contains("--workflow complex-workflow.json"));
-        assert!(cmd_str.contains("--task Complex multi-agent task"));
+        assert!(cmd_str.contains("--task 'Complex multi-agent task'"));
+        assert!(cmd_str.contains("--model opus"));
+        assert!(cmd_str.contains("--fallback-model claude-sonnet-4-5"));
+        assert!(cmd_str.contains("--max-thinking-tokens 10000"));
 
         // Check additional params
         assert!(cmd_str.contains("--param1"));
@@ -441,8 +1060,21 @@ mod tests {
             enable_chaining: Some(false), // Explicitly disabled
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let builder = flow.build_command_builder();
@@ -467,7 +1099,10 @@ mod tests {
         fs::write(&config_path, r#"{"auth": "test"}"#).unwrap();
 
         // Mock the home directory by temporarily setting an environment variable
-        std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
+        let _home = HOME_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original_home = std::env::var_os("HOME");
+        // SAFETY: other tests reading HOME wait for the lock
+        unsafe { std::env::set_var("HOME", temp_dir.path()) };
 
         let flow = ClaudeFlow {
             append_prompt: AppendPrompt(None),
@@ -475,21 +1110,44 @@ mod tests {
             enable_chaining: None,
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let availability = flow.get_availability_info();
 
         // Clean up
-        std::env::remove_var("HOME");
+        // SAFETY: as above
+        unsafe {
+            match original_home {
+                Some(home) => std::env::set_var("HOME", home),
+                None => std::env::remove_var("HOME"),
+            }
+        }
 
         // Should detect the config file
         match availability {
-            AvailabilityInfo::LoginDetected { .. } | AvailabilityInfo::InstallationFound => {
+            AvailabilityInfo::LoginDetected { .. }
+            | AvailabilityInfo::InstallationFound
+            | AvailabilityInfo::InstalledVersion { .. } => {
                 // Success - config file was detected
             }
-            AvailabilityInfo::NotFound => {
+            AvailabilityInfo::NotFound
+            | AvailabilityInfo::LoginExpired { .. }
+            | AvailabilityInfo::VersionTooOld { .. } => {
                 panic!("Expected config file to be detected");
             }
         }
@@ -497,14 +1155,28 @@ mod tests {
 
     #[test]
     fn test_get_availability_info_without_config() {
+        let _home = HOME_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         let flow = ClaudeFlow {
             append_prompt: AppendPrompt(None),
             non_interactive: None,
             enable_chaining: None,
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let availability = flow.get_availability_info();
@@ -531,13 +1203,11 @@ mod tests {
     #[test]
     fn test_schemars_json_schema() {
         // Test that JSON schema can be generated
-        let flow = ClaudeFlow::default();
-
         // This should compile without errors if JsonSchema derivation works
         let _schema = schemars::schema_for!(ClaudeFlow);
 
         // Verify that the schema can be generated
-        assert!(_schema.title.is_some());
+        assert!(_schema.get("title").is_some());
     }
 
     #[test]
@@ -548,8 +1218,21 @@ mod tests {
             enable_chaining: Some(false),
             agent_id: Some("".to_string()),
             workflow_file: Some("".to_string()),
+            workflow: None,
             task_description: Some("".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         // Test that empty strings are handled properly
@@ -573,8 +1256,21 @@ mod tests {
             enable_chaining: Some(true),
             agent_id: Some("agent-with-dashes_and_underscores".to_string()),
             workflow_file: Some("/path/to/workflow.json".to_string()),
+            workflow: None,
             task_description: Some("Task with \"quotes\" and 'apostrophes'".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         // Test serialization/deserialization with special characters
@@ -587,6 +1283,54 @@ mod tests {
         assert_eq!(flow.task_description, deserialized.task_description);
     }
 
+    #[test]
+    fn test_memory_namespace() {
+        let mut env = ExecutionEnv::new();
+        env.insert("VK_TASK_ID", "1234");
+        let mut flow: ClaudeFlow = serde_json::from_str(r#"{"persist_memory": true}"#).unwrap();
+        assert_eq!(
+            flow.resolve_memory_namespace(&env),
+            Some("vk-task-1234".to_string())
+        );
+
+        flow.memory_namespace = Some("shared".to_string());
+        assert_eq!(
+            flow.resolve_memory_namespace(&env),
+            Some("shared".to_string())
+        );
+
+        flow.memory_namespace = None;
+        flow.persist_memory = None;
+        assert_eq!(flow.resolve_memory_namespace(&env), None);
+    }
+
+    #[test]
+    fn test_project_mcp_config() {
+        let worktree = std::env::temp_dir().join(format!("vk-flow-mcp-{}", uuid::Uuid::new_v4()));
+        let flow: ClaudeFlow = serde_json::from_str("{}").unwrap();
+        let project_env = ExecutionEnv::new().with_mcp_scope(McpConfigScope::Project);
+        assert_eq!(flow.resolve_mcp_config_path(&worktree, &project_env), None);
+
+        let config_path = ClaudeFlow::project_mcp_config_path(&worktree);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, r#"{"mcpServers": {}}"#).unwrap();
+        assert_eq!(
+            flow.resolve_mcp_config_path(&worktree, &project_env),
+            Some(config_path.clone())
+        );
+        assert_eq!(
+            flow.resolve_mcp_config_path(&worktree, &ExecutionEnv::new()),
+            None
+        );
+
+        let cmd_str = format!(
+            "{}",
+            flow.command_builder_for(&worktree, &project_env).unwrap()
+        );
+        assert!(cmd_str.contains(&format!("--config {}", config_path.display())));
+        std::fs::remove_dir_all(worktree).unwrap();
+    }
+
     #[test]
     fn test_derivative_traits() {
         let flow1 = ClaudeFlow {
@@ -595,8 +1339,21 @@ mod tests {
             enable_chaining: Some(false),
             agent_id: Some("agent1".to_string()),
             workflow_file: Some("workflow.json".to_string()),
+            workflow: None,
             task_description: Some("task".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let flow2 = ClaudeFlow {
@@ -605,8 +1362,21 @@ mod tests {
             enable_chaining: Some(false),
             agent_id: Some("agent1".to_string()),
             workflow_file: Some("workflow.json".to_string()),
+            workflow: None,
             task_description: Some("task".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let flow3 = ClaudeFlow {
@@ -615,8 +1385,21 @@ mod tests {
             enable_chaining: Some(false),
             agent_id: Some("agent1".to_string()),
             workflow_file: Some("workflow.json".to_string()),
+            workflow: None,
             task_description: Some("task".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         // Test PartialEq
@@ -631,4 +1414,59 @@ mod tests {
         let cloned = flow1.clone();
         assert_eq!(flow1, cloned);
     }
+
+    #[test]
+    fn test_parse_result_event() {
+        let outcome = parse_result_event(
+            r#"{"type":"result","status":"success","summary":"Updated main.rs with hello world"}"#,
+        )
+        .unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(
+            outcome.summary.as_deref(),
+            Some("Updated main.rs with hello world")
+        );
+
+        let relayed = parse_result_event(
+            r#"{"type":"result","subtype":"error_max_turns","is_error":true,"duration_ms":6059,"num_turns":30}"#,
+        )
+        .unwrap();
+        assert_eq!(relayed.status, OutcomeStatus::Error);
+        assert_eq!(relayed.stats.duration_ms, Some(6059));
+        assert_eq!(relayed.stats.num_turns, Some(30));
+
+        assert!(parse_result_event(r#"{"type":"message","content":"done"}"#).is_none());
+        assert!(parse_result_event("not json").is_none());
+    }
+
+    #[test]
+    fn test_parse_run_summary() {
+        let entry = parse_run_summary(
+            r#"{"type":"result","status":"success","tasks":{"completed":4,"total":5},"agents":["coder",{"type":"tester"}],"durationMs":83000}"#,
+        )
+        .unwrap();
+        let NormalizedEntryType::RunSummary { summary } = entry.entry_type else {
+            panic!("expected a run summary, got {:?}", entry.entry_type);
+        };
+        assert_eq!(
+            summary,
+            RunSummary {
+                tasks_completed: Some(4),
+                tasks_total: Some(5),
+                agents: vec!["coder".to_string(), "tester".to_string()],
+                duration_ms: Some(83000),
+                metrics: None,
+            }
+        );
+        assert_eq!(
+            entry.content,
+            "4/5 tasks completed, agents: coder, tester, 83.0s"
+        );
+
+        // Plain results relayed from Claude Code have nothing to summarize
+        assert!(
+            parse_run_summary(r#"{"type":"result","subtype":"success","duration_ms":6059}"#)
+                .is_none()
+        );
+    }
 }
@@ -0,0 +1,557 @@
+//! Workflows defined inline in a claude-flow profile.
+//!
+//! claude-flow reads its multi-agent workflows from a file, which a profile
+//! could only point to with `workflow_file`. A [`WorkflowSpec`] holds the same
+//! definition as typed config instead: it is checked before spawning, so a
+//! task assigned to an undeclared agent or a dependency cycle fails the run
+//! up front rather than halfway through, and written to a scratch file in the
+//! worktree for claude-flow to read. Fields use claude-flow's own names, so
+//! an existing workflow file can be pasted in as it is.
+//!
+//! While a workflow runs, claude-flow reports each task starting, completing
+//! or failing. [`WorkflowProgress`] follows those events for the tasks the
+//! workflow declares, publishes them at `/workflow_progress` in the run's
+//! logs and keeps the latest state per agent session while the run lasts, so
+//! the kanban can show how far along each subtask is. Finished runs keep
+//! their last progress in their logs only.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use futures::StreamExt;
+use json_patch::Patch;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use ts_rs::TS;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::{executors::ExecutorError, prompt::scratch_dir};
+
+/// Worktree directory inline workflows are written to
+const WORKFLOW_DIR: &str = ".vibe-kanban/workflows";
+
+/// Latest progress of the running workflow in each agent session
+static PROGRESS: LazyLock<RwLock<HashMap<String, WorkflowProgress>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WorkflowError {
+    #[error("the workflow has no tasks")]
+    NoTasks,
+    #[error("task id '{0}' is used more than once")]
+    DuplicateTask(String),
+    #[error("agent id '{0}' is used more than once")]
+    DuplicateAgent(String),
+    #[error("task '{0}' has neither a description nor a prompt")]
+    EmptyTask(String),
+    #[error("task '{task}' is assigned to undeclared agent '{agent}'")]
+    UnknownAgent { task: String, agent: String },
+    #[error("task '{task}' depends on unknown task '{dependency}'")]
+    UnknownDependency { task: String, dependency: String },
+    #[error("tasks depend on each other in a cycle: {0}")]
+    DependencyCycle(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowSpec {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Agents tasks can be assigned to; without any, claude-flow picks them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<WorkflowAgent>,
+    pub tasks: Vec<WorkflowTask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowAgent {
+    pub id: String,
+    /// claude-flow agent type, e.g. `researcher` or `coder`
+    #[serde(rename = "type")]
+    pub agent_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowTask {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Id of the agent that runs the task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assign_to: Option<String>,
+    /// Ids of the tasks that must finish first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends: Vec<String>,
+    /// Prompt the assigned agent runs the task with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_prompt: Option<String>,
+}
+
+impl WorkflowSpec {
+    /// Check that the workflow can run: ids are unique, every task has
+    /// something to do, and assignments and dependencies point at what the
+    /// workflow declares without going round in a cycle
+    pub fn validate(&self) -> Result<(), WorkflowError> {
+        if self.tasks.is_empty() {
+            return Err(WorkflowError::NoTasks);
+        }
+        let mut agents = HashSet::new();
+        for agent in &self.agents {
+            if !agents.insert(agent.id.as_str()) {
+                return Err(WorkflowError::DuplicateAgent(agent.id.clone()));
+            }
+        }
+        let mut tasks = HashMap::new();
+        for task in &self.tasks {
+            if tasks.insert(task.id.as_str(), task).is_some() {
+                return Err(WorkflowError::DuplicateTask(task.id.clone()));
+            }
+        }
+
+        for task in &self.tasks {
+            let has_work = [&task.description, &task.claude_prompt]
+                .into_iter()
+                .flatten()
+                .any(|text| !text.trim().is_empty());
+            if !has_work {
+                return Err(WorkflowError::EmptyTask(task.id.clone()));
+            }
+            if let Some(agent) = &task.assign_to
+                && !self.agents.is_empty()
+                && !agents.contains(agent.as_str())
+            {
+                return Err(WorkflowError::UnknownAgent {
+                    task: task.id.clone(),
+                    agent: agent.clone(),
+                });
+            }
+            if let Some(dependency) = task
+                .depends
+                .iter()
+                .find(|d| !tasks.contains_key(d.as_str()))
+            {
+                return Err(WorkflowError::UnknownDependency {
+                    task: task.id.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+
+        // Depth-first search, keeping the path to report the cycle with
+        let mut done = HashSet::new();
+        for task in &self.tasks {
+            let mut path = Vec::new();
+            if let Some(cycle) = find_cycle(task.id.as_str(), &tasks, &mut path, &mut done) {
+                return Err(WorkflowError::DependencyCycle(cycle.join(" -> ")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the workflow and write it under `current_dir` for claude-flow
+    /// to read, returning the file's absolute path. Files are named by their
+    /// content, so runs of the same workflow share one.
+    pub fn write_file(&self, current_dir: &Path) -> Result<PathBuf, ExecutorError> {
+        self.validate()?;
+        let content = serde_json::to_string_pretty(self)?;
+        let digest = Sha256::digest(content.as_bytes());
+        let name: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+        let path = scratch_dir(current_dir, WORKFLOW_DIR)
+            .map_err(ExecutorError::Io)?
+            .join(format!("{name}.json"));
+        fs::write(&path, content).map_err(ExecutorError::Io)?;
+        Ok(path)
+    }
+}
+
+fn find_cycle<'a>(
+    id: &'a str,
+    tasks: &HashMap<&'a str, &'a WorkflowTask>,
+    path: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+) -> Option<Vec<&'a str>> {
+    if done.contains(id) {
+        return None;
+    }
+    if let Some(start) = path.iter().position(|seen| *seen == id) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(id);
+        return Some(cycle);
+    }
+    path.push(id);
+    for dependency in &tasks[id].depends {
+        if let Some(cycle) = find_cycle(dependency, tasks, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.insert(id);
+    None
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowTaskStatus {
+    #[default]
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct WorkflowTaskProgress {
+    pub id: String,
+    pub name: Option<String>,
+    /// Agent running the task, as assigned or as claude-flow reports it
+    pub agent: Option<String>,
+    pub status: WorkflowTaskStatus,
+    /// Why the task failed
+    pub error: Option<String>,
+}
+
+/// Where each task of a workflow run is at, published at
+/// `/workflow_progress` as claude-flow reports it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct WorkflowProgress {
+    pub workflow: Option<String>,
+    /// Tasks in the order the workflow declares them, then ones only seen in
+    /// events
+    pub tasks: Vec<WorkflowTaskProgress>,
+}
+
+/// Transition of one task, from a claude-flow task lifecycle event
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TaskEvent {
+    id: String,
+    status: WorkflowTaskStatus,
+    agent: Option<String>,
+    error: Option<String>,
+}
+
+impl WorkflowProgress {
+    /// Progress of a run of `spec` before any task has started
+    pub fn from_spec(spec: &WorkflowSpec) -> Self {
+        Self {
+            workflow: Some(spec.name.clone()),
+            tasks: spec
+                .tasks
+                .iter()
+                .map(|task| WorkflowTaskProgress {
+                    id: task.id.clone(),
+                    name: task.name.clone(),
+                    agent: task.assign_to.clone(),
+                    status: WorkflowTaskStatus::Pending,
+                    error: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Apply the task event on `line`, if it is one. Returns whether the
+    /// progress changed.
+    pub fn apply(&mut self, line: &str) -> bool {
+        let Some(event) = task_event(line) else {
+            return false;
+        };
+        let index = match self.tasks.iter().position(|task| task.id == event.id) {
+            Some(index) => index,
+            None => {
+                self.tasks.push(WorkflowTaskProgress {
+                    id: event.id.clone(),
+                    name: None,
+                    agent: None,
+                    status: WorkflowTaskStatus::Pending,
+                    error: None,
+                });
+                self.tasks.len() - 1
+            }
+        };
+        let task = &mut self.tasks[index];
+        // Events can arrive late from agents running in parallel; a finished
+        // task doesn't start again
+        if task.status == WorkflowTaskStatus::Completed
+            && event.status != WorkflowTaskStatus::Completed
+        {
+            return false;
+        }
+        let before = task.clone();
+        task.status = event.status;
+        task.agent = event.agent.or(task.agent.take());
+        task.error = match event.status {
+            WorkflowTaskStatus::Failed => event.error,
+            _ => None,
+        };
+        *task != before
+    }
+
+    pub fn count(&self, status: WorkflowTaskStatus) -> usize {
+        self.tasks
+            .iter()
+            .filter(|task| task.status == status)
+            .count()
+    }
+
+    pub fn to_patch(&self) -> Patch {
+        serde_json::from_value(serde_json::json!([{
+            "op": "add",
+            "path": "/workflow_progress",
+            "value": self,
+        }]))
+        .unwrap()
+    }
+}
+
+/// Task lifecycle event on `line`: claude-flow emits `task_started`,
+/// `task_completed` and `task_failed` events, or `task` events carrying the
+/// transition in `status`, with the task's id at the top level or under
+/// `task`
+fn task_event(line: &str) -> Option<TaskEvent> {
+    let event: Value = serde_json::from_str(line.trim()).ok()?;
+    let kind = event.get("type")?.as_str()?;
+    let transition = match kind.strip_prefix("task")? {
+        "" => ["status", "event", "state"]
+            .iter()
+            .find_map(|key| event.get(*key)?.as_str())?,
+        rest => rest.strip_prefix(['_', ':', '.'])?,
+    };
+    let status = match transition {
+        "started" | "start" | "running" | "in_progress" => WorkflowTaskStatus::Running,
+        "completed" | "complete" | "succeeded" | "success" | "done" => {
+            WorkflowTaskStatus::Completed
+        }
+        "failed" | "failure" | "error" | "cancelled" => WorkflowTaskStatus::Failed,
+        _ => return None,
+    };
+
+    let text = |value: &Value, keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| value.get(*key)?.as_str())
+            .map(str::to_string)
+    };
+    let task = event.get("task").filter(|task| task.is_object());
+    let id = text(&event, &["taskId", "task_id"])
+        .or_else(|| text(task?, &["id", "taskId"]))
+        .or_else(|| text(&event, &["id"]))?;
+    let agent = text(&event, &["agentId", "agent_id", "assignTo", "assignedTo"])
+        .or_else(|| text(task?, &["assignTo", "assignedTo", "agentId"]));
+    let error = text(&event, &["error", "message"]).or_else(|| {
+        event
+            .get("error")
+            .and_then(|error| text(error, &["message"]))
+    });
+    Some(TaskEvent {
+        id,
+        status,
+        agent,
+        error,
+    })
+}
+
+/// Session id reported on a stream-json `line`
+fn session_id(line: &str) -> Option<String> {
+    let event: Value = serde_json::from_str(line.trim()).ok()?;
+    ["session_id", "sessionId"]
+        .iter()
+        .find_map(|key| event.get(*key)?.as_str())
+        .map(str::to_string)
+}
+
+/// Latest progress of the workflow running in the agent session
+/// `session_id`
+pub fn workflow_progress(session_id: &str) -> Option<WorkflowProgress> {
+    PROGRESS.read().unwrap().get(session_id).cloned()
+}
+
+/// Last progress a run published in its logs
+pub fn workflow_progress_from_history(history: &[LogMsg]) -> Option<WorkflowProgress> {
+    history.iter().rev().find_map(|msg| {
+        let LogMsg::JsonPatch(patch) = msg else {
+            return None;
+        };
+        let operations = serde_json::to_value(patch).ok()?;
+        operations
+            .as_array()?
+            .iter()
+            .rev()
+            .find(|operation| operation["path"] == "/workflow_progress")
+            .and_then(|operation| serde_json::from_value(operation["value"].clone()).ok())
+    })
+}
+
+/// Follow the task events in the stdout of `msg_store`, publishing the
+/// progress as it changes and recording it for the run's session until its
+/// output ends
+pub(crate) fn spawn_progress_tracker(
+    msg_store: Arc<MsgStore>,
+    mut progress: WorkflowProgress,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if !progress.tasks.is_empty() {
+            msg_store.push_patch(progress.to_patch());
+        }
+        let mut session = None;
+        let mut lines = msg_store.stdout_lines_stream();
+        while let Some(Ok(line)) = lines.next().await {
+            let changed = progress.apply(&line);
+            if changed {
+                msg_store.push_patch(progress.to_patch());
+            }
+            if session.is_none() {
+                session = session_id(&line);
+            }
+            if let Some(session) = &session
+                && (changed || !PROGRESS.read().unwrap().contains_key(session))
+                && !progress.tasks.is_empty()
+            {
+                PROGRESS
+                    .write()
+                    .unwrap()
+                    .insert(session.clone(), progress.clone());
+            }
+        }
+        if let Some(session) = session {
+            PROGRESS.write().unwrap().remove(&session);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workflow_is_checked_before_it_is_written() {
+        let mut workflow: WorkflowSpec = serde_json::from_value(serde_json::json!({
+            "name": "Research then build",
+            "agents": [
+                { "id": "researcher", "type": "researcher" },
+                { "id": "coder", "type": "coder", "name": "Implementer" },
+            ],
+            "tasks": [
+                {
+                    "id": "research",
+                    "assignTo": "researcher",
+                    "claudePrompt": "Survey the existing caching code",
+                },
+                {
+                    "id": "build",
+                    "assignTo": "coder",
+                    "depends": ["research"],
+                    "description": "Add the cache",
+                },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(workflow.validate(), Ok(()));
+
+        let dir = std::env::temp_dir().join(format!("vk-workflow-{}", uuid::Uuid::new_v4()));
+        let path = workflow.write_file(&dir).unwrap();
+        assert!(path.starts_with(dir.join(WORKFLOW_DIR)));
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["tasks"][1]["assignTo"], "coder");
+        assert_eq!(workflow.write_file(&dir).unwrap(), path);
+
+        workflow.tasks[0].depends = vec!["build".to_string()];
+        assert_eq!(
+            workflow.validate(),
+            Err(WorkflowError::DependencyCycle(
+                "research -> build -> research".to_string()
+            ))
+        );
+        workflow.tasks[0].depends.clear();
+        workflow.tasks[1].assign_to = Some("tester".to_string());
+        assert!(matches!(
+            workflow.validate(),
+            Err(WorkflowError::UnknownAgent { .. })
+        ));
+        assert!(workflow.write_file(&dir).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_workflow_progress_follows_task_events() {
+        let workflow: WorkflowSpec = serde_json::from_value(serde_json::json!({
+            "name": "Research then build",
+            "tasks": [
+                { "id": "research", "assignTo": "researcher", "description": "Survey" },
+                { "id": "build", "depends": ["research"], "description": "Build" },
+            ],
+        }))
+        .unwrap();
+        let progress = WorkflowProgress::from_spec(&workflow);
+        assert_eq!(progress.count(WorkflowTaskStatus::Pending), 2);
+
+        let session = format!("session-{}", uuid::Uuid::new_v4());
+        let msg_store = Arc::new(MsgStore::new());
+        let tracker = spawn_progress_tracker(msg_store.clone(), progress);
+        for line in [
+            format!(r#"{{"type":"system","subtype":"init","session_id":"{session}"}}"#),
+            r#"{"type":"task_started","taskId":"research"}"#.to_string(),
+            r#"{"type":"task","status":"completed","task":{"id":"research"}}"#.to_string(),
+            r#"{"type":"task:started","taskId":"build","agentId":"coder-1"}"#.to_string(),
+            r#"{"type":"task_failed","taskId":"build","error":"tests failed"}"#.to_string(),
+            // A late start doesn't undo a completion
+            r#"{"type":"task_started","taskId":"research"}"#.to_string(),
+            r#"{"type":"task_completed","taskId":"docs"}"#.to_string(),
+            r#"{"type":"assistant","message":{"content":[]}}"#.to_string(),
+        ] {
+            msg_store.push_stdout(format!("{line}\n"));
+        }
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while workflow_progress(&session).is_none_or(|progress| progress.tasks.len() < 3) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("progress of the running workflow is recorded");
+        msg_store.push_finished();
+        tracker.await.unwrap();
+
+        // Once the run exits its progress is only in its logs
+        assert_eq!(workflow_progress(&session), None);
+        let progress = workflow_progress_from_history(&msg_store.get_history()).unwrap();
+        assert_eq!(progress.workflow.as_deref(), Some("Research then build"));
+        let statuses: Vec<_> = progress
+            .tasks
+            .iter()
+            .map(|task| (task.id.as_str(), task.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("research", WorkflowTaskStatus::Completed),
+                ("build", WorkflowTaskStatus::Failed),
+                ("docs", WorkflowTaskStatus::Completed),
+            ]
+        );
+        assert_eq!(progress.tasks[0].agent.as_deref(), Some("researcher"));
+        assert_eq!(progress.tasks[1].agent.as_deref(), Some("coder-1"));
+        assert_eq!(progress.tasks[1].error.as_deref(), Some("tests failed"));
+        assert_eq!(
+            msg_store
+                .filter_map_history(|msg| match msg {
+                    LogMsg::JsonPatch(_) => Some(()),
+                    _ => None,
+                })
+                .len(),
+            6
+        );
+    }
+}
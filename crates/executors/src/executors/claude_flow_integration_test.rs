@@ -48,8 +48,21 @@ mod claude_flow_integration_tests {
             enable_chaining: Some(true),
             agent_id: Some("test-agent".to_string()),
             workflow_file: None,
+            workflow: None,
             task_description: Some("Integration test".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         // Create ExecutorActionType with ClaudeFlow
@@ -58,6 +71,7 @@ mod claude_flow_integration_tests {
                 prompt: "Test prompt".to_string(),
                 executor_profile_id: ExecutorProfileId::new(BaseCodingAgent::ClaudeFlow),
                 working_dir: None,
+                cwd_relative: None,
             }
         );
 
@@ -81,8 +95,21 @@ mod claude_flow_integration_tests {
             enable_chaining: Some(true),
             agent_id: Some("swarm-agent".to_string()),
             workflow_file: Some("workflow.json".to_string()),
+            workflow: None,
             task_description: Some("Test task".to_string()),
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         let builder = flow.build_command_builder();
@@ -95,7 +122,7 @@ mod claude_flow_integration_tests {
         assert!(cmd_str.contains("--chaining"));
         assert!(cmd_str.contains("--agent swarm-agent"));
         assert!(cmd_str.contains("--workflow workflow.json"));
-        assert!(cmd_str.contains("--task Test task"));
+        assert!(cmd_str.contains("--task 'Test task'"));
     }
 
     #[test]
@@ -109,8 +136,21 @@ mod claude_flow_integration_tests {
             enable_chaining: Some(false),
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         // Verify it has the normalize_logs method (trait implementation)
@@ -133,8 +173,21 @@ mod claude_flow_integration_tests {
             enable_chaining: Some(true),
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         // Test spawn method exists and can be called (though it may fail in test environment)
@@ -161,8 +214,21 @@ mod claude_flow_integration_tests {
             enable_chaining: Some(false),
             agent_id: None,
             workflow_file: None,
+            workflow: None,
             task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
             cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
         };
 
         // Test MCP configuration methods
@@ -31,13 +31,16 @@ use self::{
 };
 use crate::{
     approvals::ExecutorApprovalService,
-    command::{CmdOverrides, CommandBuilder, CommandParts, apply_overrides},
+    approvals::policy::ApprovalPolicy,
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, CommandParts, apply_overrides},
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, ExecutorExitResult, SpawnedChild,
         StandardCodingAgentExecutor,
         codex::{jsonrpc::ExitSignalSender, normalize_logs::Error},
     },
+    outcome::FailureReason,
+    profile::RunOverrides,
     stdout_dup::create_stdout_pipe_writer,
 };
 
@@ -111,6 +114,13 @@ pub struct Codex {
     pub sandbox: Option<SandboxMode>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub ask_for_approval: Option<AskForApproval>,
+    /// Let commands reach the network in the workspace-write sandbox
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox_network_access: Option<bool>,
+    /// Directories outside the workspace that commands may write to in the
+    /// workspace-write sandbox
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox_writable_roots: Option<Vec<String>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub oss: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -133,8 +143,16 @@ pub struct Codex {
     pub compact_prompt: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub developer_instructions: Option<String>,
+    #[schemars(
+        title = "Approval Policy",
+        description = "Rules that allow, deny or ask about tool calls by tool name, file path globs and command regexes, checked in order before asking for approval"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalPolicy>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
 
     #[serde(skip)]
     #[ts(skip)]
@@ -206,6 +224,19 @@ impl StandardCodingAgentExecutor for Codex {
             AvailabilityInfo::NotFound
         }
     }
+
+    fn login_command(&self) -> Option<String> {
+        // Device code flow, which needs neither a terminal nor a local callback
+        Some(format!("{} login --device-auth", Self::base_command()))
+    }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        Ok(self.build_command_builder().explain(env, &self.cmd)?)
+    }
 }
 
 impl Codex {
@@ -220,7 +251,7 @@ impl Codex {
             builder = builder.extend_params(["--oss"]);
         }
 
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
     }
 
     fn build_new_conversation_params(&self, cwd: &Path) -> NewConversationParams {
@@ -284,6 +315,25 @@ impl Codex {
             );
         }
 
+        // Auto runs in the workspace-write sandbox too
+        if matches!(
+            self.sandbox,
+            None | Some(SandboxMode::Auto | SandboxMode::WorkspaceWrite)
+        ) {
+            if let Some(network_access) = self.sandbox_network_access {
+                overrides.insert(
+                    "sandbox_workspace_write.network_access".to_string(),
+                    Value::Bool(network_access),
+                );
+            }
+            if let Some(roots) = &self.sandbox_writable_roots {
+                overrides.insert(
+                    "sandbox_workspace_write.writable_roots".to_string(),
+                    Value::from(roots.clone()),
+                );
+            }
+        }
+
         if overrides.is_empty() {
             None
         } else {
@@ -299,7 +349,9 @@ impl Codex {
         resume_session: Option<&str>,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
         let (program_path, args) = command_parts.into_resolved().await?;
 
         let mut process = Command::new(program_path);
@@ -315,8 +367,8 @@ impl Codex {
             .env("RUST_LOG", "error");
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut process);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut process)?;
 
         let mut child = process.group_spawn()?;
 
@@ -367,7 +419,7 @@ impl Codex {
                             .ok();
                         // Send failure signal so the process is marked as failed
                         exit_signal_tx
-                            .send_exit_signal(ExecutorExitResult::Failure)
+                            .send_exit_signal(ExecutorExitResult::Failed(FailureReason::from(&err)))
                             .await;
                         return;
                     }
@@ -381,7 +433,7 @@ impl Codex {
                 }
                 // For other errors, also send failure signal
                 exit_signal_tx
-                    .send_exit_signal(ExecutorExitResult::Failure)
+                    .send_exit_signal(ExecutorExitResult::Failed(FailureReason::from(&err)))
                     .await;
             }
         });
@@ -390,6 +442,7 @@ impl Codex {
             child,
             exit_signal: Some(exit_signal_rx),
             interrupt_sender: None,
+            input_sender: None,
         })
     }
 
@@ -451,3 +504,37 @@ impl Codex {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_workspace_write_settings_become_config_overrides() {
+        let codex: Codex = serde_json::from_value(json!({
+            "sandbox": "workspace-write",
+            "sandbox_network_access": true,
+            "sandbox_writable_roots": ["/var/cache/build"],
+        }))
+        .unwrap();
+        let overrides = codex.build_config_overrides().unwrap();
+        assert_eq!(
+            overrides["sandbox_workspace_write.network_access"],
+            Value::Bool(true)
+        );
+        assert_eq!(
+            overrides["sandbox_workspace_write.writable_roots"],
+            json!(["/var/cache/build"])
+        );
+
+        // Other sandboxes have no such settings
+        let read_only: Codex = serde_json::from_value(json!({
+            "sandbox": "read-only",
+            "sandbox_network_access": true,
+        }))
+        .unwrap();
+        assert_eq!(read_only.build_config_overrides(), None);
+    }
+}
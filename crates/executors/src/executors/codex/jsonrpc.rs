@@ -27,7 +27,10 @@ use tokio::{
     sync::{Mutex, oneshot},
 };
 
-use crate::executors::{ExecutorError, ExecutorExitResult};
+use crate::{
+    executors::{ExecutorError, ExecutorExitResult},
+    outcome::RunSummary,
+};
 
 #[derive(Debug)]
 pub enum PendingResponse {
@@ -156,7 +159,9 @@ impl JsonRpcPeer {
                 }
             }
 
-            exit_tx.send_exit_signal(ExecutorExitResult::Success).await;
+            exit_tx
+                .send_exit_signal(ExecutorExitResult::Success(RunSummary::default()))
+                .await;
             let _ = reader_peer.shutdown().await;
         });
 
@@ -106,6 +106,8 @@ impl ToNormalizedEntry for CommandState {
                 tool_call_id: self.call_id.clone(),
             })
             .ok(),
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -133,6 +135,8 @@ impl ToNormalizedEntry for McpToolState {
             },
             content: self.invocation.tool.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -166,6 +170,8 @@ impl ToNormalizedEntry for WebSearchState {
                 .clone()
                 .unwrap_or_else(|| "Web search".to_string()),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -203,6 +209,8 @@ impl ToNormalizedEntry for PatchEntry {
                 tool_call_id: self.call_id.clone(),
             })
             .ok(),
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -269,6 +277,8 @@ impl LogState {
             },
             content: content.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         };
         (normalized_entry, index, is_new)
     }
@@ -649,6 +659,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             entry_type: NormalizedEntryType::SystemMessage,
                             content: format!("Background event: {message}"),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         },
                     );
                 }
@@ -666,6 +678,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             },
                             content: format!("Stream error: {message} {codex_error_info:?}"),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         },
                     );
                 }
@@ -895,6 +909,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             },
                             content: relative_path.to_string(),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         },
                     );
                 }
@@ -935,6 +951,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             },
                             content,
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         },
                     );
                 }
@@ -949,6 +967,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             },
                             content: message,
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         },
                     );
                 }
@@ -966,6 +986,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             },
                             content: format!("Error: {message} {codex_error_info:?}"),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         },
                     );
                 }
@@ -983,6 +1005,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                             entry_type: NormalizedEntryType::SystemMessage,
                             content: "Context compacted".to_string(),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         },
                     );
                 }
@@ -1060,6 +1084,8 @@ fn handle_model_params(
             entry_type: NormalizedEntryType::SystemMessage,
             content: params.join("  ").to_string(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         },
     );
 }
@@ -1120,6 +1146,8 @@ impl ToNormalizedEntry for Error {
                 },
                 content: error.clone(),
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             },
             Error::AuthRequired { error } => NormalizedEntry {
                 timestamp: None,
@@ -1128,6 +1156,8 @@ impl ToNormalizedEntry for Error {
                 },
                 content: error.clone(),
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             },
         }
     }
@@ -1192,6 +1222,8 @@ impl ToNormalizedEntryOpt for Approval {
                     .trim()
                     .to_string(),
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             }),
             ApprovalStatus::TimedOut => Some(NormalizedEntry {
                 timestamp: None,
@@ -1200,6 +1232,8 @@ impl ToNormalizedEntryOpt for Approval {
                 },
                 content: format!("Approval timed out for tool {tool_name}"),
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             }),
         }
     }
@@ -22,7 +22,7 @@ use uuid::Uuid;
 use workspace_utils::{msg_store::MsgStore, path::get_vibe_kanban_temp_dir};
 
 use crate::{
-    command::{CmdOverrides, CommandBuilder, apply_overrides},
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, apply_overrides},
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
@@ -31,6 +31,7 @@ use crate::{
         NormalizedEntry, NormalizedEntryType, plain_text_processor::PlainTextLogProcessor,
         stderr_processor::normalize_stderr_logs, utils::EntryIndexProvider,
     },
+    profile::RunOverrides,
     stdout_dup::{self, StdoutAppender},
 };
 
@@ -52,6 +53,8 @@ pub struct Copilot {
     pub disable_mcp_server: Option<Vec<String>>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
 }
 
 impl Copilot {
@@ -92,7 +95,7 @@ impl Copilot {
             }
         }
 
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
     }
 }
 
@@ -104,13 +107,19 @@ impl StandardCodingAgentExecutor for Copilot {
         prompt: &str,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
+        // Session ids are read from a local log directory
+        if self.run.remote.is_some() {
+            return Err(ExecutorError::RemoteNotSupported);
+        }
         let log_dir = Self::create_temp_log_dir(current_dir).await?;
         let command_parts = self
             .build_command_builder(&log_dir.to_string_lossy())
             .build_initial()?;
         let (program_path, args) = command_parts.into_resolved().await?;
 
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let mut command = Command::new(program_path);
         command
@@ -123,8 +132,8 @@ impl StandardCodingAgentExecutor for Copilot {
             .env("NODE_NO_WARNINGS", "1");
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -147,13 +156,19 @@ impl StandardCodingAgentExecutor for Copilot {
         session_id: &str,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
+        // Session ids are read from a local log directory
+        if self.run.remote.is_some() {
+            return Err(ExecutorError::RemoteNotSupported);
+        }
         let log_dir = Self::create_temp_log_dir(current_dir).await?;
         let command_parts = self
             .build_command_builder(&log_dir.to_string_lossy())
             .build_follow_up(&["--resume".to_string(), session_id.to_string()])?;
         let (program_path, args) = command_parts.into_resolved().await?;
 
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let mut command = Command::new(program_path);
 
@@ -167,8 +182,8 @@ impl StandardCodingAgentExecutor for Copilot {
             .env("NODE_NO_WARNINGS", "1");
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -231,6 +246,16 @@ impl StandardCodingAgentExecutor for Copilot {
             AvailabilityInfo::NotFound
         }
     }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        // The log directory is only created when spawning
+        let builder = self.build_command_builder("<log dir>");
+        Ok(builder.explain(env, &self.cmd)?)
+    }
 }
 
 impl Copilot {
@@ -243,6 +268,8 @@ impl Copilot {
                 entry_type: NormalizedEntryType::AssistantMessage,
                 content,
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             }))
             .transform_lines(Box::new(|lines| {
                 lines.iter_mut().for_each(|line| {
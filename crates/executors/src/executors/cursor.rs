@@ -16,7 +16,7 @@ use workspace_utils::{
 };
 
 use crate::{
-    command::{CmdOverrides, CommandBuilder, apply_overrides},
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, apply_overrides},
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
@@ -27,6 +27,7 @@ use crate::{
         plain_text_processor::PlainTextLogProcessor,
         utils::{ConversationPatch, EntryIndexProvider},
     },
+    profile::RunOverrides,
 };
 
 mod mcp;
@@ -46,6 +47,8 @@ pub struct CursorAgent {
     pub model: Option<String>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
 }
 
 impl CursorAgent {
@@ -65,7 +68,7 @@ impl CursorAgent {
             builder = builder.extend_params(["--model", model]);
         }
 
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
     }
 }
 
@@ -83,7 +86,9 @@ impl StandardCodingAgentExecutor for CursorAgent {
 
         let (executable_path, args) = command_parts.into_resolved().await?;
 
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let mut command = Command::new(executable_path);
         command
@@ -95,8 +100,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
             .args(&args);
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -122,7 +127,9 @@ impl StandardCodingAgentExecutor for CursorAgent {
             .build_follow_up(&["--resume".to_string(), session_id.to_string()])?;
         let (executable_path, args) = command_parts.into_resolved().await?;
 
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let mut command = Command::new(executable_path);
         command
@@ -134,8 +141,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
             .args(&args);
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
 
@@ -166,6 +173,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                         },
                         content,
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     }
                 }))
                 .time_gap(Duration::from_secs(2))
@@ -182,6 +191,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                         },
                         content: content.to_string(),
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     };
                     let id = entry_index_provider_stderr.next();
                     msg_store_stderr
@@ -227,6 +238,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                                 entry_type: NormalizedEntryType::SystemMessage,
                                 content: line.to_string(),
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
 
                             let patch_id = entry_index_provider.next();
@@ -263,6 +276,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                                 entry_type: NormalizedEntryType::SystemMessage,
                                 content: format!("System initialized with model: {model}"),
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
                             let id = entry_index_provider.next();
                             msg_store
@@ -281,6 +296,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                                 entry_type: NormalizedEntryType::AssistantMessage,
                                 content: current_assistant_message_buffer.clone(),
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
                             if let Some(id) = current_assistant_message_index {
                                 msg_store.push_patch(ConversationPatch::replace(id, replace_entry))
@@ -304,6 +321,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                                 entry_type: NormalizedEntryType::Thinking,
                                 content: current_thinking_message_buffer.clone(),
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
                             if let Some(id) = current_thinking_message_index {
                                 msg_store.push_patch(ConversationPatch::replace(id, entry));
@@ -341,6 +360,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                                 },
                                 content,
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
                             let id = entry_index_provider.next();
                             if let Some(cid) = call_id.as_ref() {
@@ -462,6 +483,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                                 },
                                 content: content_str,
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             };
                             msg_store.push_patch(ConversationPatch::replace(idx, entry));
                         }
@@ -477,6 +500,8 @@ impl StandardCodingAgentExecutor for CursorAgent {
                             entry_type: NormalizedEntryType::SystemMessage,
                             content: line,
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
                         let id = entry_index_provider.next();
                         msg_store.push_patch(ConversationPatch::add_normalized_entry(id, entry));
@@ -507,6 +532,18 @@ impl StandardCodingAgentExecutor for CursorAgent {
             AvailabilityInfo::NotFound
         }
     }
+
+    fn login_command(&self) -> Option<String> {
+        Some(format!("{} login", Self::base_command()))
+    }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        Ok(self.build_command_builder().explain(env, &self.cmd)?)
+    }
 }
 /* ===========================
 Typed Cursor JSON structures
@@ -1227,6 +1264,7 @@ mod tests {
             force: None,
             model: None,
             cmd: Default::default(),
+            run: Default::default(),
         };
         let msg_store = Arc::new(MsgStore::new());
         let current_dir = std::path::PathBuf::from("/tmp/test-worktree");
@@ -10,7 +10,7 @@ use ts_rs::TS;
 use workspace_utils::msg_store::MsgStore;
 
 use crate::{
-    command::CommandParts,
+    command::{CommandExplanation, CommandParts},
     env::ExecutionEnv,
     executors::{AppendPrompt, ExecutorError, SpawnedChild, StandardCodingAgentExecutor},
     logs::utils::EntryIndexProvider,
@@ -80,6 +80,8 @@ pub struct Droid {
 
     #[serde(flatten)]
     pub cmd: crate::command::CmdOverrides,
+    #[serde(flatten)]
+    pub run: crate::profile::RunOverrides,
 }
 
 impl Droid {
@@ -101,7 +103,7 @@ impl Droid {
             builder = builder.extend_params(["--reasoning-effort", effort.as_ref()]);
         }
 
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
     }
 }
 
@@ -110,7 +112,6 @@ async fn spawn_droid(
     prompt: &String,
     current_dir: &Path,
     env: &ExecutionEnv,
-    cmd_overrides: &crate::command::CmdOverrides,
 ) -> Result<SpawnedChild, ExecutorError> {
     let (program_path, args) = command_parts.into_resolved().await?;
 
@@ -123,9 +124,7 @@ async fn spawn_droid(
         .current_dir(current_dir)
         .args(args);
 
-    env.clone()
-        .with_profile(cmd_overrides)
-        .apply_to_command(&mut command);
+    env.apply_to_command(&mut command)?;
 
     let mut child = command.group_spawn()?;
 
@@ -146,9 +145,12 @@ impl StandardCodingAgentExecutor for Droid {
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
         let droid_command = self.build_command_builder().build_initial()?;
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
-        spawn_droid(droid_command, &combined_prompt, current_dir, env, &self.cmd).await
+        let env = env.clone().with_profile(&self.cmd, &self.run);
+        spawn_droid(droid_command, &combined_prompt, current_dir, &env).await
     }
 
     async fn spawn_follow_up(
@@ -166,9 +168,12 @@ impl StandardCodingAgentExecutor for Droid {
         let continue_cmd = self
             .build_command_builder()
             .build_follow_up(&["--session-id".to_string(), forked_session_id.clone()])?;
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
-        spawn_droid(continue_cmd, &combined_prompt, current_dir, env, &self.cmd).await
+        let env = env.clone().with_profile(&self.cmd, &self.run);
+        spawn_droid(continue_cmd, &combined_prompt, current_dir, &env).await
     }
 
     fn normalize_logs(&self, msg_store: Arc<MsgStore>, current_dir: &Path) {
@@ -182,4 +187,12 @@ impl StandardCodingAgentExecutor for Droid {
     fn default_mcp_config_path(&self) -> Option<std::path::PathBuf> {
         dirs::home_dir().map(|home| home.join(".factory").join("mcp.json"))
     }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        Ok(self.build_command_builder().explain(env, &self.cmd)?)
+    }
 }
@@ -55,6 +55,8 @@ pub fn normalize_logs(
                             },
                             content: error.message,
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
                         add_normalized_entry(&msg_store, &entry_index_provider, entry);
                         continue;
@@ -66,6 +68,8 @@ pub fn normalize_logs(
                             entry_type: NormalizedEntryType::SystemMessage,
                             content: strip_ansi_escapes::strip_str(trimmed).to_string(),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
 
                         add_normalized_entry(&msg_store, &entry_index_provider, entry);
@@ -92,6 +96,8 @@ pub fn normalize_logs(
                             entry_type: NormalizedEntryType::SystemMessage,
                             content: format!("model: {model}"),
                             metadata: None,
+                            agent_id: None,
+                            agent_role: None,
                         };
                         add_normalized_entry(&msg_store, &entry_index_provider, entry);
                     }
@@ -113,6 +119,8 @@ pub fn normalize_logs(
                         entry_type,
                         content: text.clone(),
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     };
 
                     add_normalized_entry(&msg_store, &entry_index_provider, entry);
@@ -647,6 +655,8 @@ pub fn normalize_logs(
                         entry_type: NormalizedEntryType::AssistantMessage,
                         content: final_text.clone(),
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     };
                     add_normalized_entry(&msg_store, &entry_index_provider, entry);
                     sent_completion = true;
@@ -660,6 +670,8 @@ pub fn normalize_logs(
                         },
                         content: message.clone(),
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     };
                     add_normalized_entry(&msg_store, &state.entry_index, entry);
                 }
@@ -680,6 +692,8 @@ fn normalize_stderr_logs(msg_store: Arc<MsgStore>, entry_index_provider: EntryIn
                 },
                 content,
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             }))
             .transform_lines(Box::new(|lines| {
                 lines.iter_mut().for_each(|line| {
@@ -1025,6 +1039,8 @@ impl ToNormalizedEntry for FileReadState {
             },
             content: self.path.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -1051,6 +1067,8 @@ impl ToNormalizedEntry for FileEditState {
             },
             content: self.path.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -1093,6 +1111,8 @@ impl ToNormalizedEntry for CommandRunState {
             },
             content: self.command.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -1124,6 +1144,8 @@ impl ToNormalizedEntry for TodoManagementState {
             },
             content,
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -1148,6 +1170,8 @@ impl ToNormalizedEntry for SearchState {
             },
             content: self.query.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -1172,6 +1196,8 @@ impl ToNormalizedEntry for WebFetchState {
             },
             content: self.url.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
@@ -1206,6 +1232,8 @@ impl ToNormalizedEntry for GenericToolState {
             },
             content: self.name.clone(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         }
     }
 }
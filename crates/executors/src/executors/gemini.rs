@@ -10,11 +10,14 @@ use workspace_utils::msg_store::MsgStore;
 pub use super::acp::AcpAgentHarness;
 use crate::{
     approvals::ExecutorApprovalService,
-    command::{CmdOverrides, CommandBuilder, apply_overrides},
+    approvals::policy::ApprovalPolicy,
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, apply_overrides},
+    credentials::SavedLogin,
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
     },
+    profile::RunOverrides,
 };
 
 #[derive(Derivative, Clone, Serialize, Deserialize, TS, JsonSchema)]
@@ -26,8 +29,16 @@ pub struct Gemini {
     pub model: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub yolo: Option<bool>,
+    #[schemars(
+        title = "Approval Policy",
+        description = "Rules that allow, deny or ask about tool calls by tool name, file path globs and command regexes, checked in order before asking for approval"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalPolicy>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
     #[serde(skip)]
     #[ts(skip)]
     #[derivative(Debug = "ignore", PartialEq = "ignore")]
@@ -49,7 +60,7 @@ impl Gemini {
 
         builder = builder.extend_params(["--experimental-acp"]);
 
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
     }
 }
 
@@ -66,7 +77,9 @@ impl StandardCodingAgentExecutor for Gemini {
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
         let harness = AcpAgentHarness::new();
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
         let gemini_command = self.build_command_builder().build_initial()?;
         let approvals = if self.yolo.unwrap_or(false) {
             None
@@ -78,8 +91,7 @@ impl StandardCodingAgentExecutor for Gemini {
                 current_dir,
                 combined_prompt,
                 gemini_command,
-                env,
-                &self.cmd,
+                &env.clone().with_profile(&self.cmd, &self.run),
                 approvals,
             )
             .await
@@ -93,7 +105,9 @@ impl StandardCodingAgentExecutor for Gemini {
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
         let harness = AcpAgentHarness::new();
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
         let gemini_command = self.build_command_builder().build_follow_up(&[])?;
         let approvals = if self.yolo.unwrap_or(false) {
             None
@@ -106,8 +120,7 @@ impl StandardCodingAgentExecutor for Gemini {
                 combined_prompt,
                 session_id,
                 gemini_command,
-                env,
-                &self.cmd,
+                &env.clone().with_profile(&self.cmd, &self.run),
                 approvals,
             )
             .await
@@ -122,6 +135,12 @@ impl StandardCodingAgentExecutor for Gemini {
     }
 
     fn get_availability_info(&self) -> AvailabilityInfo {
+        if let Some(expired) = dirs::home_dir()
+            .and_then(|home| SavedLogin::gemini(&home))
+            .and_then(|login| login.expired())
+        {
+            return expired;
+        }
         if let Some(timestamp) = dirs::home_dir()
             .and_then(|home| std::fs::metadata(home.join(".gemini").join("oauth_creds.json")).ok())
             .and_then(|m| m.modified().ok())
@@ -148,4 +167,12 @@ impl StandardCodingAgentExecutor for Gemini {
             AvailabilityInfo::NotFound
         }
     }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        Ok(self.build_command_builder().explain(env, &self.cmd)?)
+    }
 }
@@ -14,19 +14,42 @@ use workspace_utils::msg_store::MsgStore;
 
 use crate::{
     actions::ExecutorAction,
-    approvals::ExecutorApprovalService,
-    command::CommandBuildError,
+    approvals::{ExecutorApprovalService, policy::ApprovalPolicy},
+    attachments::{Attachment, mention_attachments},
+    authorization::AuthorizationDenied,
+    cancellation::{CancelReason, CancelScope},
+    command::{CmdOverrides, CommandBuildError, CommandExplanation},
     env::ExecutionEnv,
     executors::{
-        amp::Amp, claude::ClaudeCode, claude_flow::ClaudeFlow, codex::Codex, copilot::Copilot,
-        cursor::CursorAgent, droid::Droid, gemini::Gemini, opencode::Opencode, qwen::QwenCode,
+        amp::Amp,
+        claude::ClaudeCode,
+        claude_api::ClaudeApi,
+        claude_flow::{ClaudeFlow, workflow::WorkflowError},
+        codex::Codex,
+        copilot::Copilot,
+        cursor::CursorAgent,
+        droid::Droid,
+        gemini::Gemini,
+        opencode::Opencode,
+        qwen::QwenCode,
     },
+    logs::pipeline::{NormalizationPipeline, NormalizationStage},
     mcp_config::McpConfig,
+    outcome::{FailureReason, RunSummary},
+    permission_mode::AgentPermissionMode,
+    profile::RunOverrides,
+    prompt::{self, PromptTemplate},
+    run_hooks::HookStage,
+    runtime_limits::TimeoutKind,
+    secrets::SecretError,
+    standby::{StandbyConfig, WarmSession},
+    worktree::WorktreeError,
 };
 
 pub mod acp;
 pub mod amp;
 pub mod claude;
+pub mod claude_api;
 pub mod claude_flow;
 pub mod codex;
 pub mod copilot;
@@ -40,9 +63,28 @@ pub mod qwen;
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[ts(use_ts_enum)]
 pub enum BaseAgentCapability {
+    /// Agent can branch a new session off an earlier one
     SessionFork,
     /// Agent requires a setup script before it can run (e.g., login, installation)
     SetupHelper,
+    /// Agent can install its CLI through its documented install command
+    Installer,
+    /// Agent can read its prompt from a file
+    PromptFile,
+    /// Agent accepts user messages while it runs, in interactive mode
+    LiveInput,
+    /// Agent can continue an earlier session with a follow-up
+    ResumeSession,
+    /// Agent reads its input as stream-json messages on stdin
+    StreamJsonInput,
+    /// A running agent can be interrupted gracefully, ending its turn
+    Interrupt,
+    /// Agent asks an approval service before running tools
+    ApprovalsHook,
+    /// Agent takes images attached to the prompt as images
+    ImageInput,
+    /// Agent reports the cost of its runs in its logs
+    CostReporting,
 }
 
 #[derive(Debug, Error)]
@@ -69,8 +111,53 @@ pub enum ExecutorError {
     ExecutableNotFound { program: String },
     #[error("Setup helper not supported")]
     SetupHelperNotSupported,
+    #[error("Session fork not supported")]
+    ForkNotSupported,
     #[error("Auth required: {0}")]
     AuthRequired(String),
+    #[error("Execution cancelled: {0}")]
+    Cancelled(CancelReason),
+    #[error("Timeout: process took more than {} seconds to start", .0.as_secs())]
+    SpawnTimeout(std::time::Duration),
+    #[error(transparent)]
+    Unauthorized(#[from] AuthorizationDenied),
+    #[error("Session {0} was not started in this worktree")]
+    SessionNotFound(String),
+    #[error("Installed version {found} is older than the minimum supported version {required}")]
+    VersionTooOld { found: String, required: String },
+    #[error("Installer not supported")]
+    InstallNotSupported,
+    #[error("Install failed: {0}")]
+    InstallFailed(String),
+    #[error("Invalid attachment: {0}")]
+    InvalidAttachment(String),
+    #[error("Remote execution not supported")]
+    RemoteNotSupported,
+    #[error("Dry run not supported")]
+    DryRunNotSupported,
+    #[error("Working directory {0} is not a subdirectory of the worktree")]
+    InvalidWorkingDir(String),
+    #[error("{executor} cannot enforce the {mode} permission mode")]
+    PermissionModeNotSupported {
+        executor: BaseCodingAgent,
+        mode: AgentPermissionMode,
+    },
+    #[error("{stage} hook `{command}` failed")]
+    HookFailed { stage: HookStage, command: String },
+    #[error("Could not gather the changes to review: {0}")]
+    ReviewDiff(WorktreeError),
+    #[error("No changes to review since {0}")]
+    NothingToReview(String),
+    #[error("Only coding agent runs can be handed off")]
+    HandoffNotSupported,
+    #[error("Login not supported")]
+    LoginNotSupported,
+    #[error("Login failed: {0}")]
+    LoginFailed(String),
+    #[error(transparent)]
+    Secret(#[from] SecretError),
+    #[error("Invalid workflow: {0}")]
+    InvalidWorkflow(#[from] WorkflowError),
 }
 
 #[enum_dispatch]
@@ -104,6 +191,7 @@ pub enum CodingAgent {
     QwenCode,
     Copilot,
     Droid,
+    ClaudeApi,
 }
 
 impl CodingAgent {
@@ -153,27 +241,200 @@ impl CodingAgent {
         }
     }
 
+    /// Refuse to run a CLI older than [`StandardCodingAgentExecutor::min_supported_version`]
+    pub async fn ensure_supported_version(&self) -> Result<(), ExecutorError> {
+        if self.min_supported_version().is_none() {
+            return Ok(());
+        }
+        match self.probe().await {
+            AvailabilityInfo::VersionTooOld { found, required } => {
+                Err(ExecutorError::VersionTooOld { found, required })
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn supports_mcp(&self) -> bool {
         self.default_mcp_config_path().is_some()
     }
 
-    pub fn capabilities(&self) -> Vec<BaseAgentCapability> {
+    pub fn cmd_overrides(&self) -> &CmdOverrides {
+        match self {
+            Self::ClaudeCode(agent) => &agent.cmd,
+            Self::ClaudeFlow(agent) => &agent.cmd,
+            Self::Amp(agent) => &agent.cmd,
+            Self::Gemini(agent) => &agent.cmd,
+            Self::Codex(agent) => &agent.cmd,
+            Self::Opencode(agent) => &agent.cmd,
+            Self::CursorAgent(agent) => &agent.cmd,
+            Self::QwenCode(agent) => &agent.cmd,
+            Self::Copilot(agent) => &agent.cmd,
+            Self::Droid(agent) => &agent.cmd,
+            Self::ClaudeApi(agent) => &agent.cmd,
+        }
+    }
+
+    pub fn cmd_overrides_mut(&mut self) -> &mut CmdOverrides {
         match self {
-            Self::ClaudeCode(_)
-            | Self::ClaudeFlow(_)
+            Self::ClaudeCode(agent) => &mut agent.cmd,
+            Self::ClaudeFlow(agent) => &mut agent.cmd,
+            Self::Amp(agent) => &mut agent.cmd,
+            Self::Gemini(agent) => &mut agent.cmd,
+            Self::Codex(agent) => &mut agent.cmd,
+            Self::Opencode(agent) => &mut agent.cmd,
+            Self::CursorAgent(agent) => &mut agent.cmd,
+            Self::QwenCode(agent) => &mut agent.cmd,
+            Self::Copilot(agent) => &mut agent.cmd,
+            Self::Droid(agent) => &mut agent.cmd,
+            Self::ClaudeApi(agent) => &mut agent.cmd,
+        }
+    }
+
+    pub fn run_overrides(&self) -> &RunOverrides {
+        match self {
+            Self::ClaudeCode(agent) => &agent.run,
+            Self::ClaudeFlow(agent) => &agent.run,
+            Self::Amp(agent) => &agent.run,
+            Self::Gemini(agent) => &agent.run,
+            Self::Codex(agent) => &agent.run,
+            Self::Opencode(agent) => &agent.run,
+            Self::CursorAgent(agent) => &agent.run,
+            Self::QwenCode(agent) => &agent.run,
+            Self::Copilot(agent) => &agent.run,
+            Self::Droid(agent) => &agent.run,
+            Self::ClaudeApi(agent) => &agent.run,
+        }
+    }
+
+    pub fn run_overrides_mut(&mut self) -> &mut RunOverrides {
+        match self {
+            Self::ClaudeCode(agent) => &mut agent.run,
+            Self::ClaudeFlow(agent) => &mut agent.run,
+            Self::Amp(agent) => &mut agent.run,
+            Self::Gemini(agent) => &mut agent.run,
+            Self::Codex(agent) => &mut agent.run,
+            Self::Opencode(agent) => &mut agent.run,
+            Self::CursorAgent(agent) => &mut agent.run,
+            Self::QwenCode(agent) => &mut agent.run,
+            Self::Copilot(agent) => &mut agent.run,
+            Self::Droid(agent) => &mut agent.run,
+            Self::ClaudeApi(agent) => &mut agent.run,
+        }
+    }
+
+    /// Rules checked before asking for approval, on executors that ask
+    pub fn approval_policy(&self) -> Option<&ApprovalPolicy> {
+        match self {
+            Self::ClaudeCode(agent) => agent.approval_policy.as_ref(),
+            Self::Gemini(agent) => agent.approval_policy.as_ref(),
+            Self::Codex(agent) => agent.approval_policy.as_ref(),
+            Self::Opencode(agent) => agent.approval_policy.as_ref(),
+            Self::QwenCode(agent) => agent.approval_policy.as_ref(),
+            Self::ClaudeApi(agent) => agent.approval_policy.as_ref(),
+            Self::ClaudeFlow(_)
             | Self::Amp(_)
-            | Self::Gemini(_)
-            | Self::QwenCode(_)
-            | Self::Droid(_)
-            | Self::Opencode(_) => vec![BaseAgentCapability::SessionFork],
-            Self::Codex(_) => vec![
-                BaseAgentCapability::SessionFork,
-                BaseAgentCapability::SetupHelper,
+            | Self::CursorAgent(_)
+            | Self::Copilot(_)
+            | Self::Droid(_) => None,
+        }
+    }
+
+    /// Warm standby pool settings, on executors that can start before they
+    /// receive the prompt
+    pub fn warm_standby(&self) -> Option<&StandbyConfig> {
+        match self {
+            Self::ClaudeCode(agent) => agent.warm_standby.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Normalize logs through the normalization pipeline, with the profile's
+    /// stages and thinking visibility, which also adds the agent's todo list
+    /// to the conversation. The pipeline stops taking raw output once
+    /// `cancel` fires.
+    pub fn normalize_logs_with_pipeline(
+        &self,
+        msg_store: Arc<MsgStore>,
+        worktree_path: &Path,
+        cancel: &CancelScope,
+    ) {
+        let run = self.run_overrides();
+        let thinking = run.log_pipeline.thinking_visibility.unwrap_or_default();
+        let stages = match &run.log_pipeline.normalization {
+            Some(stages) => stages.clone(),
+            // Keep the executor's own streaming behaviour
+            None => vec![NormalizationStage::DeltaStreaming],
+        };
+        NormalizationPipeline::new(&stages)
+            .with_thinking_visibility(thinking)
+            .with_loop_detection(run.runtime.loop_detection.clone())
+            .with_cancel(cancel.clone())
+            .run(msg_store, |staging| {
+                self.normalize_logs(staging, worktree_path)
+            });
+    }
+
+    /// Start an idle session for the warm standby pool, if the executor can
+    /// start before it receives the prompt
+    pub async fn spawn_warm(
+        &self,
+        current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<Option<WarmSession>, ExecutorError> {
+        match self {
+            Self::ClaudeCode(agent) => agent.spawn_warm(current_dir, env).await,
+            _ => Ok(None),
+        }
+    }
+
+    pub fn capabilities(&self) -> Vec<BaseAgentCapability> {
+        BaseCodingAgent::from(self).capabilities()
+    }
+
+    pub fn supports(&self, capability: BaseAgentCapability) -> bool {
+        BaseCodingAgent::from(self).supports(capability)
+    }
+}
+
+impl BaseCodingAgent {
+    pub fn capabilities(self) -> Vec<BaseAgentCapability> {
+        use BaseAgentCapability::*;
+
+        match self {
+            Self::ClaudeFlow => vec![
+                SessionFork,
+                Installer,
+                PromptFile,
+                LiveInput,
+                ResumeSession,
+                StreamJsonInput,
+                CostReporting,
             ],
-            Self::CursorAgent(_) => vec![BaseAgentCapability::SetupHelper],
-            Self::Copilot(_) => vec![],
+            Self::ClaudeCode => vec![
+                SessionFork,
+                LiveInput,
+                ResumeSession,
+                StreamJsonInput,
+                Interrupt,
+                ApprovalsHook,
+                ImageInput,
+                CostReporting,
+            ],
+            Self::Amp | Self::Droid => vec![ResumeSession],
+            Self::Gemini | Self::QwenCode => vec![ResumeSession, ApprovalsHook],
+            Self::Opencode => vec![ResumeSession, Interrupt, ApprovalsHook],
+            Self::Codex => vec![SetupHelper, ResumeSession, ApprovalsHook],
+            Self::CursorAgent => vec![SetupHelper, ResumeSession],
+            Self::Copilot => vec![ResumeSession],
+            Self::ClaudeApi => vec![SessionFork, ResumeSession, Interrupt, ApprovalsHook],
         }
     }
+
+    /// Whether the executor has `capability`, for branching on what an
+    /// executor can do rather than on which executor it is
+    pub fn supports(self, capability: BaseAgentCapability) -> bool {
+        self.capabilities().contains(&capability)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -181,7 +442,10 @@ impl CodingAgent {
 #[ts(export)]
 pub enum AvailabilityInfo {
     LoginDetected { last_auth_timestamp: i64 },
+    LoginExpired { expired_at: i64 },
     InstallationFound,
+    InstalledVersion { version: String },
+    VersionTooOld { found: String, required: String },
     NotFound,
 }
 
@@ -189,7 +453,9 @@ impl AvailabilityInfo {
     pub fn is_available(&self) -> bool {
         matches!(
             self,
-            AvailabilityInfo::LoginDetected { .. } | AvailabilityInfo::InstallationFound
+            AvailabilityInfo::LoginDetected { .. }
+                | AvailabilityInfo::InstallationFound
+                | AvailabilityInfo::InstalledVersion { .. }
         )
     }
 }
@@ -212,6 +478,43 @@ pub trait StandardCodingAgentExecutor {
         session_id: &str,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError>;
+    /// Start a new session that branches from `parent_session_id`, leaving
+    /// the parent session untouched
+    async fn spawn_fork(
+        &self,
+        _current_dir: &Path,
+        _prompt: &str,
+        _parent_session_id: &str,
+        _env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        Err(ExecutorError::ForkNotSupported)
+    }
+    /// [`Self::spawn`] with files and images attached to the prompt; by
+    /// default the prompt tells the agent where to find them
+    async fn spawn_with_attachments(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        attachments: &[Attachment],
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let prompt = mention_attachments(prompt, attachments, current_dir)?;
+        self.spawn(current_dir, &prompt, env).await
+    }
+    /// [`Self::spawn_follow_up`] with files and images attached to the prompt
+    async fn spawn_follow_up_with_attachments(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        session_id: &str,
+        attachments: &[Attachment],
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let prompt = mention_attachments(prompt, attachments, current_dir)?;
+        self.spawn_follow_up(current_dir, &prompt, session_id, env)
+            .await
+    }
+
     fn normalize_logs(&self, _raw_logs_event_store: Arc<MsgStore>, _worktree_path: &Path);
 
     // MCP configuration methods
@@ -233,15 +536,79 @@ pub trait StandardCodingAgentExecutor {
             AvailabilityInfo::NotFound
         }
     }
+
+    /// Like [`Self::get_availability_info`], but runs the agent's CLI where
+    /// supported to report the installed version
+    async fn probe(&self) -> AvailabilityInfo {
+        self.get_availability_info()
+    }
+
+    /// Oldest CLI version the executor works with, checked by [`Self::probe`]
+    fn min_supported_version(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Flag that makes the agent read its prompt from the file that follows
+    fn prompt_file_flag(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Prompt that makes the agent compact its session's context, sent as a
+    /// follow-up before retrying a run that overflowed it
+    fn compact_command(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Documented command that installs the agent's CLI
+    fn install_command(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Install the agent's CLI, streaming the installer output to `msg_store`
+    async fn install(&self, msg_store: Arc<MsgStore>) -> Result<(), ExecutorError> {
+        match self.install_command() {
+            Some(command) => crate::install::run_install_command(command, msg_store).await,
+            None => Err(ExecutorError::InstallNotSupported),
+        }
+    }
+
+    /// Command starting the agent's login flow without a terminal, one that
+    /// prints a URL to open and possibly a code to enter there
+    fn login_command(&self) -> Option<String> {
+        None
+    }
+
+    /// Log the agent's CLI in, streaming the login flow's output and the
+    /// URL and code it shows to `msg_store`
+    async fn authenticate(&self, msg_store: Arc<MsgStore>) -> Result<(), ExecutorError> {
+        match self.login_command() {
+            Some(command) => crate::login::run_login_command(&command, msg_store).await,
+            None => Err(ExecutorError::LoginNotSupported),
+        }
+    }
+
+    /// The command [`Self::spawn`] would start the agent with in
+    /// `current_dir`, and where its parts come from
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        _env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        Err(ExecutorError::DryRunNotSupported)
+    }
 }
 
 /// Result communicated through the exit signal
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ExecutorExitResult {
-    /// Process completed successfully (exit code 0)
-    Success,
-    /// Process should be marked as failed (non-zero exit)
-    Failure,
+    /// The run finished its task
+    Success(RunSummary),
+    /// The run failed; the process is marked as failed
+    Failed(FailureReason),
+    /// The run was interrupted before finishing
+    Interrupted,
+    /// Process exceeded one of its configured runtime limits
+    TimedOut(TimeoutKind),
 }
 
 /// Optional exit notification from an executor.
@@ -253,6 +620,11 @@ pub type ExecutorExitSignal = tokio::sync::oneshot::Receiver<ExecutorExitResult>
 /// When sent, the executor should attempt to interrupt gracefully before being killed.
 pub type InterruptSender = tokio::sync::oneshot::Sender<()>;
 
+/// Sender for user messages to a running session, available in interactive
+/// mode. Each message is a new user turn; sending fails once the session no
+/// longer reads input.
+pub type UserInputSender = tokio::sync::mpsc::UnboundedSender<String>;
+
 #[derive(Debug)]
 pub struct SpawnedChild {
     pub child: AsyncGroupChild,
@@ -260,6 +632,8 @@ pub struct SpawnedChild {
     pub exit_signal: Option<ExecutorExitSignal>,
     /// Container → Executor: signals when container wants to interrupt
     pub interrupt_sender: Option<InterruptSender>,
+    /// Container → Executor: user messages injected mid-run
+    pub input_sender: Option<UserInputSender>,
 }
 
 impl From<AsyncGroupChild> for SpawnedChild {
@@ -268,6 +642,7 @@ impl From<AsyncGroupChild> for SpawnedChild {
             child,
             exit_signal: None,
             interrupt_sender: None,
+            input_sender: None,
         }
     }
 }
@@ -288,9 +663,24 @@ impl AppendPrompt {
     }
 
     pub fn combine_prompt(&self, prompt: &str) -> String {
+        self.frame_prompt(None, prompt, &ExecutionEnv::default())
+    }
+
+    /// Frame `prompt` with the profile's template, then add this suffix.
+    /// Placeholders in either are filled from `env`.
+    pub fn frame_prompt(
+        &self,
+        template: Option<&PromptTemplate>,
+        prompt: &str,
+        env: &ExecutionEnv,
+    ) -> String {
+        let framed = match template {
+            Some(template) => template.frame(prompt, env),
+            None => prompt.to_string(),
+        };
         match self {
-            AppendPrompt(Some(value)) => format!("{prompt}{value}"),
-            AppendPrompt(None) => prompt.to_string(),
+            AppendPrompt(Some(value)) => format!("{framed}{}", prompt::render(value, env)),
+            AppendPrompt(None) => framed,
         }
     }
 }
@@ -326,6 +716,56 @@ mod tests {
         assert!(result.is_ok(), "CURSOR should deserialize via serde");
         assert_eq!(result.unwrap(), BaseCodingAgent::CursorAgent);
     }
+
+    #[tokio::test]
+    async fn test_spawn_fork_defaults_to_not_supported() {
+        let agent = CodingAgent::Copilot(serde_json::from_str("{}").unwrap());
+        let result = agent
+            .spawn_fork(
+                &std::env::temp_dir(),
+                "try another approach",
+                "session-1",
+                &ExecutionEnv::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(ExecutorError::ForkNotSupported)));
+        assert!(!agent.supports(BaseAgentCapability::SessionFork));
+        assert!(agent.supports(BaseAgentCapability::ResumeSession));
+    }
+
+    #[tokio::test]
+    async fn test_session_fork_is_advertised_only_by_agents_that_fork() {
+        use strum::VariantNames;
+
+        use crate::profile::{ExecutorConfigs, ExecutorProfileId};
+
+        // A missing directory and session make every real fork fail before
+        // anything is started, so only the default reports ForkNotSupported
+        let missing_dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let parent_session_id = uuid::Uuid::new_v4().to_string();
+        let configs = ExecutorConfigs::from_defaults();
+        for executor in CodingAgent::VARIANTS
+            .iter()
+            .filter_map(|name| BaseCodingAgent::from_str(name).ok())
+        {
+            let agent = configs
+                .get_coding_agent(&ExecutorProfileId::new(executor))
+                .unwrap();
+            let result = agent
+                .spawn_fork(
+                    &missing_dir,
+                    "try another approach",
+                    &parent_session_id,
+                    &ExecutionEnv::default(),
+                )
+                .await;
+            assert_eq!(
+                executor.supports(BaseAgentCapability::SessionFork),
+                !matches!(result, Err(ExecutorError::ForkNotSupported)),
+                "{executor} advertises SessionFork without overriding spawn_fork, or the reverse"
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -11,12 +11,15 @@ use workspace_utils::msg_store::MsgStore;
 
 use crate::{
     approvals::ExecutorApprovalService,
-    command::{CmdOverrides, CommandBuilder, apply_overrides},
+    approvals::policy::ApprovalPolicy,
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, apply_overrides},
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, ExecutorExitResult, SpawnedChild,
         StandardCodingAgentExecutor,
     },
+    outcome::FailureReason,
+    profile::RunOverrides,
     stdout_dup::create_stdout_pipe_writer,
 };
 
@@ -24,6 +27,7 @@ mod normalize_logs;
 mod sdk;
 mod types;
 
+pub(crate) use sdk::ensure_rustls_crypto_provider;
 use sdk::{LogWriter, RunConfig, run_session};
 
 #[derive(Derivative, Clone, Serialize, Deserialize, TS, JsonSchema)]
@@ -38,8 +42,16 @@ pub struct Opencode {
     /// Auto-approve agent actions
     #[serde(default = "default_to_true")]
     pub auto_approve: bool,
+    #[schemars(
+        title = "Approval Policy",
+        description = "Rules that allow, deny or ask about tool calls by tool name, file path globs and command regexes, checked in order before asking for approval"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalPolicy>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
     #[serde(skip)]
     #[ts(skip)]
     #[derivative(Debug = "ignore", PartialEq = "ignore")]
@@ -52,7 +64,7 @@ impl Opencode {
             // Pass hostname/port as separate args so OpenCode treats them as explicitly set
             // (it checks `process.argv.includes(\"--port\")` / `\"--hostname\"`).
             .extend_params(["serve", "--hostname", "127.0.0.1", "--port", "0"]);
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
     }
 
     async fn spawn_inner(
@@ -62,7 +74,13 @@ impl Opencode {
         resume_session: Option<&str>,
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        // The server is reached over a local port
+        if self.run.remote.is_some() {
+            return Err(ExecutorError::RemoteNotSupported);
+        }
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
 
         let command_parts = self.build_command_builder().build_initial()?;
         let (program_path, args) = command_parts.into_resolved().await?;
@@ -79,8 +97,8 @@ impl Opencode {
             .env("NO_COLOR", "1");
 
         env.clone()
-            .with_profile(&self.cmd)
-            .apply_to_command(&mut command);
+            .with_profile(&self.cmd, &self.run)
+            .apply_to_command(&mut command)?;
 
         let mut child = command.group_spawn()?;
         let server_stdout = child.inner().stdout.take().ok_or_else(|| {
@@ -117,12 +135,12 @@ impl Opencode {
         tokio::spawn(async move {
             let result = run_session(config, log_writer.clone(), interrupt_rx).await;
             let exit_result = match result {
-                Ok(()) => ExecutorExitResult::Success,
+                Ok(exit_result) => exit_result,
                 Err(err) => {
                     let _ = log_writer
                         .log_error(format!("OpenCode executor error: {err}"))
                         .await;
-                    ExecutorExitResult::Failure
+                    ExecutorExitResult::Failed(FailureReason::from(&err))
                 }
             };
             let _ = exit_signal_tx.send(exit_result);
@@ -132,6 +150,7 @@ impl Opencode {
             child,
             exit_signal: Some(exit_signal_rx),
             interrupt_sender: Some(interrupt_tx),
+            input_sender: None,
         })
     }
 }
@@ -247,6 +266,15 @@ impl StandardCodingAgentExecutor for Opencode {
             AvailabilityInfo::NotFound
         }
     }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        let env = setup_approvals_env(self.auto_approve, env);
+        Ok(self.build_command_builder().explain(&env, &self.cmd)?)
+    }
 }
 
 fn default_to_true() -> bool {
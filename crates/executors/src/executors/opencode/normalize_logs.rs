@@ -28,6 +28,8 @@ fn system_message(content: String) -> NormalizedEntry {
         entry_type: NormalizedEntryType::SystemMessage,
         content,
         metadata: None,
+        agent_id: None,
+        agent_role: None,
     }
 }
 
@@ -89,6 +91,8 @@ pub fn normalize_logs(msg_store: Arc<MsgStore>, worktree_path: &Path) {
                                 },
                                 content: message,
                                 metadata: None,
+                                agent_id: None,
+                                agent_role: None,
                             },
                         ),
                     );
@@ -216,6 +220,8 @@ impl LogState {
                         entry_type: NormalizedEntryType::ErrorMessage { error_type },
                         content: message,
                         metadata: None,
+                        agent_id: None,
+                        agent_role: None,
                     },
                 );
             }
@@ -276,6 +282,8 @@ impl LogState {
             },
             content: "TODO list updated".to_string(),
             metadata: None,
+            agent_id: None,
+            agent_role: None,
         };
 
         if let Some(index) = self.todo_update_entry {
@@ -416,6 +424,8 @@ impl LogState {
                         .trim()
                         .to_string(),
                     metadata: None,
+                    agent_id: None,
+                    agent_role: None,
                 },
             );
         }
@@ -554,6 +564,8 @@ fn update_streaming_text(
         entry_type,
         content: state.content.clone(),
         metadata: None,
+        agent_id: None,
+        agent_role: None,
     };
     upsert_normalized_entry(msg_store, state.index, entry, is_new);
 }
@@ -926,6 +938,8 @@ impl ToolCallState {
                 tool_call_id: self.call_id.clone(),
             })
             .ok(),
+            agent_id: None,
+            agent_role: None,
         }
     }
 
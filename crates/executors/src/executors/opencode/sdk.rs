@@ -20,10 +20,11 @@ use workspace_utils::approvals::ApprovalStatus;
 use super::types::OpencodeExecutorEvent;
 use crate::{
     approvals::{ExecutorApprovalError, ExecutorApprovalService},
-    executors::ExecutorError,
+    executors::{ExecutorError, ExecutorExitResult},
+    outcome::RunSummary,
 };
 
-fn ensure_rustls_crypto_provider() {
+pub(crate) fn ensure_rustls_crypto_provider() {
     static INIT: Once = Once::new();
     INIT.call_once(|| {
         if let Err(err) = rustls::crypto::aws_lc_rs::default_provider().install_default() {
@@ -125,7 +126,7 @@ pub async fn run_session(
     config: RunConfig,
     log_writer: LogWriter,
     interrupt_rx: oneshot::Receiver<()>,
-) -> Result<(), ExecutorError> {
+) -> Result<ExecutorExitResult, ExecutorError> {
     ensure_rustls_crypto_provider();
     let cancel = CancellationToken::new();
 
@@ -150,9 +151,9 @@ pub async fn run_session(
             }
             res = &mut session_fut => {
                 if interrupted {
-                    return Ok(());
+                    return Ok(ExecutorExitResult::Interrupted);
                 }
-                return res;
+                return res.map(|()| ExecutorExitResult::Success(RunSummary::default()));
             }
         }
     }
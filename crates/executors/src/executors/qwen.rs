@@ -9,14 +9,21 @@ use workspace_utils::msg_store::MsgStore;
 
 use crate::{
     approvals::ExecutorApprovalService,
-    command::{CmdOverrides, CommandBuilder, apply_overrides},
+    approvals::policy::ApprovalPolicy,
+    command::{CmdOverrides, CommandBuilder, CommandExplanation, apply_overrides},
     env::ExecutionEnv,
     executors::{
         AppendPrompt, AvailabilityInfo, ExecutorError, SpawnedChild, StandardCodingAgentExecutor,
         gemini::AcpAgentHarness,
     },
+    profile::RunOverrides,
+    secrets,
 };
 
+/// Key sent to endpoints without a key secret; Qwen Code only talks to an
+/// OpenAI-compatible endpoint it has a key for, and local servers take any
+const NO_API_KEY: &str = "none";
+
 #[derive(Derivative, Clone, Serialize, Deserialize, TS, JsonSchema)]
 #[derivative(Debug, PartialEq)]
 pub struct QwenCode {
@@ -24,8 +31,26 @@ pub struct QwenCode {
     pub append_prompt: AppendPrompt,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub yolo: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// OpenAI-compatible endpoint to run against, e.g. a self-hosted model
+    /// at `http://localhost:11434/v1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Secret holding the endpoint's API key; local servers that take any
+    /// key need none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_secret: Option<String>,
+    #[schemars(
+        title = "Approval Policy",
+        description = "Rules that allow, deny or ask about tool calls by tool name, file path globs and command regexes, checked in order before asking for approval"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalPolicy>,
     #[serde(flatten)]
     pub cmd: CmdOverrides,
+    #[serde(flatten)]
+    pub run: RunOverrides,
     #[serde(skip)]
     #[ts(skip)]
     #[derivative(Debug = "ignore", PartialEq = "ignore")]
@@ -39,8 +64,30 @@ impl QwenCode {
         if self.yolo.unwrap_or(false) {
             builder = builder.extend_params(["--yolo"]);
         }
+        if let Some(model) = &self.model {
+            builder = builder.extend_params(["--model", model.as_str()]);
+        }
         builder = builder.extend_params(["--experimental-acp"]);
-        apply_overrides(builder, &self.cmd)
+        apply_overrides(builder, &self.cmd, &self.run)
+    }
+
+    /// `env` pointing Qwen Code at the profile's endpoint and key, if it
+    /// sets them
+    fn endpoint_env(&self, env: &ExecutionEnv) -> Result<ExecutionEnv, ExecutorError> {
+        let mut env = env.clone();
+        if let Some(base_url) = &self.base_url {
+            env.insert("OPENAI_BASE_URL", base_url);
+        }
+        match &self.api_key_secret {
+            Some(name) => env.insert_secret(
+                "OPENAI_API_KEY",
+                secrets::resolve(name, env.tenant.as_ref())?,
+            ),
+            // Rather than whatever OpenAI key the server runs with
+            None if self.base_url.is_some() => env.insert("OPENAI_API_KEY", NO_API_KEY),
+            None => {}
+        }
+        Ok(env)
     }
 }
 
@@ -57,7 +104,9 @@ impl StandardCodingAgentExecutor for QwenCode {
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
         let qwen_command = self.build_command_builder().build_initial()?;
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
         let harness = AcpAgentHarness::with_session_namespace("qwen_sessions");
         let approvals = if self.yolo.unwrap_or(false) {
             None
@@ -69,8 +118,7 @@ impl StandardCodingAgentExecutor for QwenCode {
                 current_dir,
                 combined_prompt,
                 qwen_command,
-                env,
-                &self.cmd,
+                &self.endpoint_env(env)?.with_profile(&self.cmd, &self.run),
                 approvals,
             )
             .await
@@ -84,7 +132,9 @@ impl StandardCodingAgentExecutor for QwenCode {
         env: &ExecutionEnv,
     ) -> Result<SpawnedChild, ExecutorError> {
         let qwen_command = self.build_command_builder().build_follow_up(&[])?;
-        let combined_prompt = self.append_prompt.combine_prompt(prompt);
+        let combined_prompt =
+            self.append_prompt
+                .frame_prompt(self.run.prompt.as_ref(), prompt, env);
         let harness = AcpAgentHarness::with_session_namespace("qwen_sessions");
         let approvals = if self.yolo.unwrap_or(false) {
             None
@@ -97,8 +147,7 @@ impl StandardCodingAgentExecutor for QwenCode {
                 combined_prompt,
                 session_id,
                 qwen_command,
-                env,
-                &self.cmd,
+                &self.endpoint_env(env)?.with_profile(&self.cmd, &self.run),
                 approvals,
             )
             .await
@@ -114,6 +163,17 @@ impl StandardCodingAgentExecutor for QwenCode {
     }
 
     fn get_availability_info(&self) -> AvailabilityInfo {
+        // A profile's own endpoint needs its key rather than a Qwen login
+        if let Some(name) = &self.api_key_secret {
+            return match secrets::resolve(name, None) {
+                Ok(_) => AvailabilityInfo::InstallationFound,
+                Err(_) => AvailabilityInfo::NotFound,
+            };
+        }
+        if self.base_url.is_some() {
+            return AvailabilityInfo::InstallationFound;
+        }
+
         let mcp_config_found = self
             .default_mcp_config_path()
             .map(|p| p.exists())
@@ -129,4 +189,45 @@ impl StandardCodingAgentExecutor for QwenCode {
             AvailabilityInfo::NotFound
         }
     }
+
+    async fn explain(
+        &self,
+        _current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<CommandExplanation, ExecutorError> {
+        Ok(self
+            .build_command_builder()
+            .explain(&self.endpoint_env(env)?, &self.cmd)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_endpoint_settings_reach_the_environment() {
+        let local: QwenCode = serde_json::from_value(json!({
+            "model": "qwen2.5-coder:32b",
+            "base_url": "http://localhost:11434/v1",
+        }))
+        .unwrap();
+        let env = local.endpoint_env(&ExecutionEnv::new()).unwrap();
+        assert_eq!(env.vars["OPENAI_BASE_URL"], "http://localhost:11434/v1");
+        assert_eq!(env.vars["OPENAI_API_KEY"], NO_API_KEY);
+        assert!(local.get_availability_info().is_available());
+
+        let hosted: QwenCode = serde_json::from_value(json!({
+            "base_url": "https://models.example.com/v1",
+            "api_key_secret": "missing-in-tests",
+        }))
+        .unwrap();
+        assert!(hosted.endpoint_env(&ExecutionEnv::new()).is_err());
+        assert!(matches!(
+            hosted.get_availability_info(),
+            AvailabilityInfo::NotFound
+        ));
+    }
 }
@@ -0,0 +1,395 @@
+//! The same prompt run by several executors side by side.
+//!
+//! A [`FanOut`] starts one coding agent run per target, each under its own
+//! profile and in its own working directory, typically a worktree per target,
+//! so that agents can be compared on the same task. Every run streams into a
+//! [`MsgStore`] of its own, tagged with the target's label, and takes a slot
+//! from the [`SpawnGovernor`] like any other spawn, so a wide fan-out queues
+//! behind the configured limits instead of starting everything at once. Once
+//! all runs have ended, their outcomes are gathered into a [`FanOutReport`].
+
+use std::{
+    future::Future,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::{TryStreamExt, future::join_all, stream::select};
+use serde::Serialize;
+use tokio_util::io::ReaderStream;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::{
+    actions::{Executable, coding_agent_initial::CodingAgentInitialRequest},
+    approvals::ExecutorApprovalService,
+    cancellation::CancelScope,
+    env::ExecutionEnv,
+    executors::{BaseCodingAgent, ExecutorError, SpawnedChild},
+    governor::{SpawnLimits, SpawnPermit},
+    outcome::{ExecutionOutcome, OutcomeStats, OutcomeStatus},
+    profile::{ExecutorConfigs, ExecutorProfileId},
+    rate_limit::AgentProvider,
+    runtime::ExecutorsRuntime,
+};
+
+/// How long output still buffered in a run's pipes may take to reach its
+/// store once the process is gone
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One run of a fan-out, whose logs can be followed while it runs
+#[derive(Clone)]
+pub struct FanOutRun {
+    /// Label telling the run apart from the others, the profile's unless
+    /// given one
+    pub tag: String,
+    pub profile: ExecutorProfileId,
+    pub dir: PathBuf,
+    pub msg_store: Arc<MsgStore>,
+}
+
+/// How one run of a fan-out ended
+#[derive(Debug, Clone, Serialize)]
+pub struct FanOutResult {
+    pub tag: String,
+    pub profile: ExecutorProfileId,
+    pub outcome: ExecutionOutcome,
+}
+
+/// Outcomes of all runs of a fan-out, in the order the targets were added
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FanOutReport {
+    pub results: Vec<FanOutResult>,
+}
+
+impl FanOutReport {
+    pub fn succeeded(&self) -> impl Iterator<Item = &FanOutResult> {
+        self.results.iter().filter(|r| r.outcome.is_success())
+    }
+
+    /// Results best first: successful runs before failed ones, then the
+    /// cheaper, then the faster
+    pub fn ranked(&self) -> Vec<&FanOutResult> {
+        let mut ranked: Vec<_> = self.results.iter().collect();
+        ranked.sort_by(|a, b| {
+            let key = |r: &FanOutResult| {
+                (
+                    !r.outcome.is_success(),
+                    r.outcome.stats.total_cost_usd.unwrap_or(f64::INFINITY),
+                    r.outcome.stats.duration_ms.unwrap_or(u64::MAX),
+                )
+            };
+            let (a, b) = (key(a), key(b));
+            a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)).then(a.2.cmp(&b.2))
+        });
+        ranked
+    }
+}
+
+/// Builder for a prompt run across several profiles at once
+#[derive(Clone)]
+pub struct FanOut {
+    prompt: String,
+    runs: Vec<FanOutRun>,
+}
+
+impl FanOut {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Run the prompt with `profile` in `dir`, tagged with the profile
+    pub fn target(self, profile: ExecutorProfileId, dir: impl Into<PathBuf>) -> Self {
+        let tag = profile.to_string();
+        self.tagged_target(tag, profile, dir)
+    }
+
+    /// Run the prompt with `profile` in `dir`, tagged with `tag`, e.g. to
+    /// run one profile more than once
+    pub fn tagged_target(
+        mut self,
+        tag: impl Into<String>,
+        profile: ExecutorProfileId,
+        dir: impl Into<PathBuf>,
+    ) -> Self {
+        self.runs.push(FanOutRun {
+            tag: tag.into(),
+            profile,
+            dir: dir.into(),
+            msg_store: Arc::new(MsgStore::new()),
+        });
+        self
+    }
+
+    /// The runs, to follow their logs before and while [`Self::run`] runs
+    pub fn runs(&self) -> &[FanOutRun] {
+        &self.runs
+    }
+
+    /// Start every run, each once the governor has room for it, and wait
+    /// for all of them to end. Runs that fail to start end in an error
+    /// outcome; firing `cancel` stops the runs still going.
+    pub async fn run(
+        &self,
+        runtime: &ExecutorsRuntime,
+        approvals: Arc<dyn ExecutorApprovalService>,
+        env: &ExecutionEnv,
+        cancel: &CancelScope,
+    ) -> FanOutReport {
+        let configs = ExecutorConfigs::get_cached_for(env.tenant.as_ref());
+        let runs = self.runs.iter().map(|run| {
+            let request = CodingAgentInitialRequest {
+                prompt: self.prompt.clone(),
+                executor_profile_id: run.profile.clone(),
+                working_dir: None,
+                cwd_relative: None,
+                run_template: None,
+                attachments: Vec::new(),
+                review: None,
+            };
+            let approvals = approvals.clone();
+            let agent = configs.get_coding_agent(&run.profile);
+            let spawn = async move {
+                runtime
+                    .rate_limiter()
+                    .wait(AgentProvider::from(run.profile.executor), cancel)
+                    .await?;
+                request.spawn(&run.dir, approvals, env, cancel).await
+            };
+            run_target(
+                run,
+                runtime,
+                &configs.spawn_limits,
+                spawn,
+                |msg_store| {
+                    if let Some(agent) = agent {
+                        agent.normalize_logs_with_pipeline(msg_store, &run.dir, cancel);
+                    }
+                },
+                cancel,
+            )
+        });
+        FanOutReport {
+            results: join_all(runs).await,
+        }
+    }
+}
+
+/// Take a governor slot for `run`, start it with `spawn`, stream its output
+/// into its store through `normalize` and wait for it to end
+pub(crate) async fn run_target(
+    run: &FanOutRun,
+    runtime: &ExecutorsRuntime,
+    limits: &SpawnLimits,
+    spawn: impl Future<Output = Result<SpawnedChild, ExecutorError>>,
+    normalize: impl FnOnce(Arc<MsgStore>),
+    cancel: &CancelScope,
+) -> FanOutResult {
+    let outcome = match start(run.profile.executor, runtime, limits, spawn, cancel).await {
+        Ok((permit, spawned)) => {
+            let outcome = follow(run, spawned, normalize, cancel).await;
+            drop(permit);
+            outcome
+        }
+        Err(e) => {
+            tracing::warn!("Fan-out run {} did not start: {e}", run.tag);
+            run.msg_store.push_stderr(e.to_string());
+            run.msg_store.push_finished();
+            failed(e.to_string())
+        }
+    };
+    FanOutResult {
+        tag: run.tag.clone(),
+        profile: run.profile.clone(),
+        outcome,
+    }
+}
+
+async fn start(
+    executor: BaseCodingAgent,
+    runtime: &ExecutorsRuntime,
+    limits: &SpawnLimits,
+    spawn: impl Future<Output = Result<SpawnedChild, ExecutorError>>,
+    cancel: &CancelScope,
+) -> Result<(SpawnPermit, SpawnedChild), ExecutorError> {
+    let permit = runtime
+        .spawn_governor()
+        .acquire(executor, limits, cancel)
+        .await?;
+    Ok((permit, spawn.await?))
+}
+
+/// Stream a started run into its store and wait for the process to exit,
+/// or for the executor or `cancel` to end it
+async fn follow(
+    run: &FanOutRun,
+    mut spawned: SpawnedChild,
+    normalize: impl FnOnce(Arc<MsgStore>),
+    cancel: &CancelScope,
+) -> ExecutionOutcome {
+    let started = Instant::now();
+    let forwarder = match (
+        spawned.child.inner().stdout.take(),
+        spawned.child.inner().stderr.take(),
+    ) {
+        (Some(out), Some(err)) => {
+            let out = ReaderStream::new(out)
+                .map_ok(|chunk| LogMsg::Stdout(String::from_utf8_lossy(&chunk).into_owned()));
+            let err = ReaderStream::new(err)
+                .map_ok(|chunk| LogMsg::Stderr(String::from_utf8_lossy(&chunk).into_owned()));
+            Some(run.msg_store.clone().spawn_forwarder(select(out, err)))
+        }
+        _ => None,
+    };
+    normalize(run.msg_store.clone());
+
+    let executor_exit = async {
+        match spawned.exit_signal.take() {
+            Some(rx) => rx.await.ok(),
+            None => std::future::pending().await,
+        }
+    };
+    let outcome = tokio::select! {
+        status = spawned.child.wait() => match status {
+            Ok(status) if status.success() => succeeded(),
+            Ok(status) => failed(format!("Agent exited with {status}")),
+            Err(e) => failed(e.to_string()),
+        },
+        Some(result) = executor_exit => {
+            let _ = spawned.child.kill().await;
+            result.into()
+        }
+        _ = cancel.cancelled() => {
+            let _ = spawned.child.kill().await;
+            let reason = cancel.reason().map(|r| r.to_string()).unwrap_or_default();
+            failed(format!("Cancelled: {reason}"))
+        }
+    };
+
+    if let Some(forwarder) = forwarder
+        && tokio::time::timeout(DRAIN_TIMEOUT, forwarder)
+            .await
+            .is_err()
+    {
+        tracing::warn!("Output of fan-out run {} did not drain", run.tag);
+    }
+    run.msg_store.push_finished();
+
+    let mut outcome = outcome;
+    outcome
+        .stats
+        .duration_ms
+        .get_or_insert(started.elapsed().as_millis() as u64);
+    outcome
+}
+
+fn succeeded() -> ExecutionOutcome {
+    ExecutionOutcome {
+        status: OutcomeStatus::Success,
+        summary: None,
+        stats: OutcomeStats::default(),
+    }
+}
+
+fn failed(summary: String) -> ExecutionOutcome {
+    ExecutionOutcome {
+        status: OutcomeStatus::Error,
+        summary: Some(summary),
+        stats: OutcomeStats::default(),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{collections::HashMap, path::Path};
+
+    use super::*;
+    use crate::{executors::StandardCodingAgentExecutor, testing::MockExecutor};
+
+    fn run_with<'a>(
+        fan_out: &'a FanOut,
+        index: usize,
+        agent: &'a MockExecutor,
+        runtime: &'a ExecutorsRuntime,
+        limits: &'a SpawnLimits,
+        env: &'a ExecutionEnv,
+        cancel: &'a CancelScope,
+    ) -> impl Future<Output = FanOutResult> + 'a {
+        let run = &fan_out.runs()[index];
+        run_target(
+            run,
+            runtime,
+            limits,
+            agent.spawn(&run.dir, &fan_out.prompt, env),
+            |msg_store| agent.normalize_logs(msg_store, Path::new(".")),
+            cancel,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_runs_each_target_within_limits() {
+        let dir = std::env::temp_dir();
+        let fan_out = FanOut::new("Fix the flaky test")
+            .target(ExecutorProfileId::new(BaseCodingAgent::ClaudeCode), &dir)
+            .tagged_target(
+                "codex-a",
+                ExecutorProfileId::new(BaseCodingAgent::Codex),
+                &dir,
+            )
+            .tagged_target(
+                "codex-b",
+                ExecutorProfileId::new(BaseCodingAgent::Codex),
+                &dir,
+            );
+        let claude = MockExecutor::new().stdout("fixed by claude\n");
+        let codex = MockExecutor::new()
+            .after(Duration::from_millis(100))
+            .stdout("fixed by codex\n");
+        let broken = MockExecutor::new().stderr("no luck\n").exit_code(2);
+
+        // One Codex process at a time: the second Codex run waits for the first
+        let runtime = ExecutorsRuntime::new();
+        let limits = SpawnLimits {
+            max_concurrent: None,
+            per_executor: HashMap::from([(BaseCodingAgent::Codex, 1)]),
+        };
+        let env = ExecutionEnv::new();
+        let cancel = CancelScope::new();
+        let report = FanOutReport {
+            results: join_all([
+                run_with(&fan_out, 0, &claude, &runtime, &limits, &env, &cancel),
+                run_with(&fan_out, 1, &codex, &runtime, &limits, &env, &cancel),
+                run_with(&fan_out, 2, &broken, &runtime, &limits, &env, &cancel),
+            ])
+            .await,
+        };
+        assert_eq!(runtime.spawn_governor().queued(), 0);
+
+        let tags: Vec<_> = report.results.iter().map(|r| r.tag.as_str()).collect();
+        assert_eq!(tags, ["CLAUDE_CODE", "codex-a", "codex-b"]);
+        let succeeded: Vec<_> = report.succeeded().map(|r| r.tag.as_str()).collect();
+        assert_eq!(succeeded, ["CLAUDE_CODE", "codex-a"]);
+        assert!(
+            report.results[2]
+                .outcome
+                .summary
+                .as_deref()
+                .is_some_and(|s| s.contains("exit status: 2"))
+        );
+        assert_eq!(report.ranked().last().unwrap().tag, "codex-b");
+        assert!(report.results[1].outcome.stats.duration_ms.unwrap() >= 100);
+
+        // Each run streamed into a store of its own
+        for (run, expected) in fan_out.runs().iter().zip(["claude", "codex", "no luck"]) {
+            let history = run.msg_store.get_history();
+            assert!(matches!(history.last(), Some(LogMsg::Finished)));
+            assert!(history.iter().any(|msg| matches!(
+                msg,
+                LogMsg::Stdout(out) | LogMsg::Stderr(out) if out.contains(expected)
+            )));
+        }
+    }
+}
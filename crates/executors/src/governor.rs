@@ -0,0 +1,290 @@
+//! Limits on how many agent processes run at once.
+//!
+//! A swarm run can start dozens of executions together, each forking its own
+//! `npx` process. The [`SpawnGovernor`] caps the agent processes running per
+//! executor and in total, as set by the profiles' [`SpawnLimits`]. Spawns over
+//! a limit wait in a single queue and are let through oldest first as
+//! processes exit. A spawn held back only by its own executor's limit doesn't
+//! hold up spawns of other executors queued behind it.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use ts_rs::TS;
+
+use crate::{
+    cancellation::CancelScope,
+    executors::{BaseCodingAgent, ExecutorError},
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+pub struct SpawnLimits {
+    /// Agent processes that may run at once across all executors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+    /// Agent processes that may run at once per executor
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub per_executor: HashMap<BaseCodingAgent, usize>,
+}
+
+impl SpawnLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_concurrent.is_none() && self.per_executor.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    executor: BaseCodingAgent,
+    grant: oneshot::Sender<()>,
+}
+
+#[derive(Debug, Default)]
+struct GovernorState {
+    limits: SpawnLimits,
+    total: usize,
+    running: HashMap<BaseCodingAgent, usize>,
+    queue: VecDeque<Waiter>,
+    next_id: u64,
+}
+
+impl GovernorState {
+    fn has_room(&self, executor: BaseCodingAgent) -> bool {
+        self.limits
+            .max_concurrent
+            .is_none_or(|max| self.total < max)
+            && self
+                .limits
+                .per_executor
+                .get(&executor)
+                .is_none_or(|max| self.running.get(&executor).copied().unwrap_or(0) < *max)
+    }
+
+    fn take(&mut self, executor: BaseCodingAgent) {
+        self.total += 1;
+        *self.running.entry(executor).or_default() += 1;
+    }
+
+    fn release(&mut self, executor: BaseCodingAgent) {
+        self.total = self.total.saturating_sub(1);
+        if let Some(running) = self.running.get_mut(&executor) {
+            *running = running.saturating_sub(1);
+        }
+    }
+
+    /// Let queued spawns through, oldest first, while they fit
+    fn dispatch(&mut self) {
+        let mut i = 0;
+        while i < self.queue.len() {
+            if self
+                .limits
+                .max_concurrent
+                .is_some_and(|max| self.total >= max)
+            {
+                break;
+            }
+            if !self.has_room(self.queue[i].executor) {
+                i += 1;
+                continue;
+            }
+            let Some(waiter) = self.queue.remove(i) else {
+                break;
+            };
+            self.take(waiter.executor);
+            if waiter.grant.send(()).is_err() {
+                self.release(waiter.executor);
+            }
+        }
+    }
+}
+
+/// Running agent processes, counted against [`SpawnLimits`]
+#[derive(Clone, Default)]
+pub struct SpawnGovernor {
+    state: Arc<Mutex<GovernorState>>,
+}
+
+impl SpawnGovernor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait until an `executor` process fits within `limits`, or fail when
+    /// `cancel` fires first. The slot is taken until the permit is dropped.
+    pub async fn acquire(
+        &self,
+        executor: BaseCodingAgent,
+        limits: &SpawnLimits,
+        cancel: &CancelScope,
+    ) -> Result<SpawnPermit, ExecutorError> {
+        let mut ticket = {
+            let mut state = self.state.lock().unwrap();
+            if state.limits != *limits {
+                // Raised limits may let queued spawns through
+                state.limits = limits.clone();
+                state.dispatch();
+            }
+            if state.has_room(executor) {
+                state.take(executor);
+                return Ok(self.permit(executor));
+            }
+            let id = state.next_id;
+            state.next_id += 1;
+            let (grant, granted) = oneshot::channel();
+            state.queue.push_back(Waiter {
+                id,
+                executor,
+                grant,
+            });
+            tracing::debug!(
+                "Spawn of {executor} queued behind {} running processes",
+                state.total
+            );
+            Ticket {
+                state: self.state.clone(),
+                id,
+                executor,
+                granted,
+                done: false,
+            }
+        };
+
+        cancel
+            .run(async {
+                (&mut ticket.granted).await.map_err(|_| {
+                    ExecutorError::Io(std::io::Error::other("spawn queue was dropped"))
+                })
+            })
+            .await?;
+        ticket.done = true;
+        Ok(self.permit(executor))
+    }
+
+    /// Number of spawns waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.state.lock().unwrap().queue.len()
+    }
+
+    fn permit(&self, executor: BaseCodingAgent) -> SpawnPermit {
+        SpawnPermit {
+            state: self.state.clone(),
+            executor,
+        }
+    }
+}
+
+/// A queued spawn; leaving the queue without a permit gives back whatever
+/// it was granted in the meantime
+struct Ticket {
+    state: Arc<Mutex<GovernorState>>,
+    id: u64,
+    executor: BaseCodingAgent,
+    granted: oneshot::Receiver<()>,
+    done: bool,
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if self.granted.try_recv().is_ok() {
+            state.release(self.executor);
+            state.dispatch();
+        } else {
+            state.queue.retain(|waiter| waiter.id != self.id);
+        }
+    }
+}
+
+/// A running agent process's slot, freed when dropped
+#[derive(Debug)]
+pub struct SpawnPermit {
+    state: Arc<Mutex<GovernorState>>,
+    executor: BaseCodingAgent,
+}
+
+impl Drop for SpawnPermit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.release(self.executor);
+        state.dispatch();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cancellation::CancelReason;
+
+    #[tokio::test]
+    async fn test_queues_spawns_over_the_limit() {
+        let governor = SpawnGovernor::new();
+        let limits = SpawnLimits {
+            max_concurrent: Some(2),
+            per_executor: HashMap::from([(BaseCodingAgent::ClaudeCode, 1)]),
+        };
+        let cancel = CancelScope::new();
+
+        let first = governor
+            .acquire(BaseCodingAgent::ClaudeCode, &limits, &cancel)
+            .await
+            .unwrap();
+        let queued = {
+            let governor = governor.clone();
+            let limits = limits.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                governor
+                    .acquire(BaseCodingAgent::ClaudeCode, &limits, &cancel)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(governor.queued(), 1);
+
+        // Another executor isn't held up by Claude Code's limit
+        let gemini = governor
+            .acquire(BaseCodingAgent::Gemini, &limits, &cancel)
+            .await
+            .unwrap();
+        // ...but the total limit applies to everyone
+        let scope = CancelScope::new();
+        let over_total = {
+            let governor = governor.clone();
+            let limits = limits.clone();
+            let scope = scope.clone();
+            tokio::spawn(async move {
+                governor
+                    .acquire(BaseCodingAgent::Codex, &limits, &scope)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(governor.queued(), 2);
+        scope.cancel(CancelReason::UserRequested);
+        assert!(matches!(
+            over_total.await.unwrap(),
+            Err(ExecutorError::Cancelled(CancelReason::UserRequested))
+        ));
+        assert_eq!(governor.queued(), 1);
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(5), queued)
+            .await
+            .expect("queued spawn should be let through")
+            .unwrap()
+            .unwrap();
+        assert_eq!(governor.queued(), 0);
+        drop((second, gemini));
+        assert_eq!(governor.state.lock().unwrap().total, 0);
+    }
+}
@@ -0,0 +1,208 @@
+//! Handing a task from one executor to another.
+//!
+//! A task may be started with one agent and continued with another, e.g. a
+//! plan drafted by Claude Flow and implemented by Codex. Agents can't resume
+//! each other's sessions, so [`handoff`] turns the earlier run's normalized
+//! history into a digest that opens the new agent's prompt. Thinking is left
+//! out of the digest, being another model's reasoning. A handoff between
+//! profiles of the same executor, e.g. from its plan variant to its default
+//! one, stays a follow-up in the same session where the executor can resume
+//! sessions.
+
+use workspace_utils::log_msg::LogMsg;
+
+use crate::{
+    actions::{
+        ExecutorAction, ExecutorActionType, coding_agent_follow_up::CodingAgentFollowUpRequest,
+        coding_agent_initial::CodingAgentInitialRequest,
+    },
+    auto_commit::agent_name,
+    executors::{BaseAgentCapability, ExecutorError},
+    logs::{
+        NormalizedConversation,
+        transcript::{DigestOptions, TranscriptOptions},
+    },
+    profile::{ExecutorConfigs, ExecutorProfileId},
+};
+
+/// Budget of the digest the receiving agent starts from
+const HANDOFF_TOKENS: usize = 8_000;
+
+/// Action continuing the run of `action`, which logged `history`, with the
+/// profile `to` working on `prompt`
+pub fn handoff(
+    action: &ExecutorAction,
+    history: &[LogMsg],
+    to: ExecutorProfileId,
+    prompt: &str,
+    configs: &ExecutorConfigs,
+) -> Result<ExecutorAction, ExecutorError> {
+    let (from, task, working_dir, cwd_relative) = match action.typ() {
+        ExecutorActionType::CodingAgentInitialRequest(request) => (
+            &request.executor_profile_id,
+            &request.prompt,
+            &request.working_dir,
+            &request.cwd_relative,
+        ),
+        ExecutorActionType::CodingAgentFollowUpRequest(request) => (
+            &request.executor_profile_id,
+            &request.prompt,
+            &request.working_dir,
+            &request.cwd_relative,
+        ),
+        ExecutorActionType::ScriptRequest(_) => return Err(ExecutorError::HandoffNotSupported),
+    };
+    if configs.get_coding_agent(&to).is_none() {
+        return Err(ExecutorError::UnknownExecutorType(to.to_string()));
+    }
+
+    let session_id = history.iter().rev().find_map(|msg| match msg {
+        LogMsg::SessionId(id) => Some(id.clone()),
+        _ => None,
+    });
+    if let Some(session_id) = session_id
+        && to.executor == from.executor
+        && to.executor.supports(BaseAgentCapability::ResumeSession)
+    {
+        return Ok(ExecutorAction::new(
+            ExecutorActionType::CodingAgentFollowUpRequest(CodingAgentFollowUpRequest {
+                prompt: prompt.to_string(),
+                session_id,
+                executor_profile_id: to,
+                working_dir: working_dir.clone(),
+                cwd_relative: cwd_relative.clone(),
+                fork: false,
+                attachments: Vec::new(),
+            }),
+            None,
+        ));
+    }
+
+    let digest = NormalizedConversation::from_history(
+        history,
+        from.executor.to_string(),
+        Some(task.clone()),
+        &TranscriptOptions::default(),
+    )
+    .digest(&DigestOptions {
+        max_tokens: HANDOFF_TOKENS,
+        include_thinking: false,
+    });
+    let preamble = format!(
+        "This task was started by {} in an earlier session. This is a digest of what \
+         happened in it; continue from there.",
+        agent_name(from.executor)
+    );
+    Ok(ExecutorAction::new(
+        ExecutorActionType::CodingAgentInitialRequest(CodingAgentInitialRequest {
+            prompt: format!("{preamble}\n\n{}\n\n{prompt}", digest.text),
+            executor_profile_id: to,
+            working_dir: working_dir.clone(),
+            cwd_relative: cwd_relative.clone(),
+            run_template: None,
+            attachments: Vec::new(),
+            review: None,
+        }),
+        None,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        executors::BaseCodingAgent,
+        logs::{NormalizedEntry, NormalizedEntryType, utils::ConversationPatch},
+    };
+
+    fn entry(index: usize, entry_type: NormalizedEntryType, content: &str) -> LogMsg {
+        LogMsg::JsonPatch(ConversationPatch::add_normalized_entry(
+            index,
+            NormalizedEntry {
+                timestamp: None,
+                entry_type,
+                content: content.to_string(),
+                metadata: None,
+                agent_id: None,
+                agent_role: None,
+            },
+        ))
+    }
+
+    #[test]
+    fn test_handoff_digests_history_for_other_executors() {
+        let configs = ExecutorConfigs::from_defaults();
+        let history = [
+            LogMsg::SessionId("plan-session".to_string()),
+            entry(0, NormalizedEntryType::Thinking, "maybe a trait?"),
+            entry(
+                1,
+                NormalizedEntryType::AssistantMessage,
+                "Plan: move parsing into config/loader.rs",
+            ),
+        ];
+        let action = ExecutorAction::new(
+            ExecutorActionType::CodingAgentInitialRequest(CodingAgentInitialRequest {
+                prompt: "Plan the config refactor".to_string(),
+                executor_profile_id: ExecutorProfileId::new(BaseCodingAgent::ClaudeCode),
+                working_dir: Some("app".to_string()),
+                cwd_relative: None,
+                run_template: None,
+                attachments: Vec::new(),
+                review: None,
+            }),
+            None,
+        );
+
+        let handed = handoff(
+            &action,
+            &history,
+            ExecutorProfileId::new(BaseCodingAgent::Codex),
+            "Implement the plan",
+            &configs,
+        )
+        .unwrap();
+        let ExecutorActionType::CodingAgentInitialRequest(request) = handed.typ() else {
+            panic!("expected a fresh session");
+        };
+        assert_eq!(request.executor_profile_id.executor, BaseCodingAgent::Codex);
+        assert_eq!(request.working_dir.as_deref(), Some("app"));
+        assert!(
+            request
+                .prompt
+                .starts_with("This task was started by Claude Code")
+        );
+        assert!(request.prompt.contains("Plan the config refactor"));
+        assert!(
+            request
+                .prompt
+                .contains("move parsing into config/loader.rs")
+        );
+        assert!(!request.prompt.contains("maybe a trait?"));
+        assert!(request.prompt.ends_with("\n\nImplement the plan"));
+
+        // The same executor picks its own session back up
+        let handed = handoff(
+            &action,
+            &history,
+            ExecutorProfileId::new(BaseCodingAgent::ClaudeCode),
+            "Implement the plan",
+            &configs,
+        )
+        .unwrap();
+        assert!(matches!(
+            handed.typ(),
+            ExecutorActionType::CodingAgentFollowUpRequest(request)
+                if request.session_id == "plan-session" && request.prompt == "Implement the plan"
+        ));
+
+        let unknown = ExecutorProfileId {
+            executor: BaseCodingAgent::Codex,
+            variant: Some("NO_SUCH_VARIANT".to_string()),
+        };
+        assert!(matches!(
+            handoff(&action, &history, unknown, "Implement the plan", &configs),
+            Err(ExecutorError::UnknownExecutorType(_))
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+//! Heartbeats of long-running executions.
+//!
+//! An agent working through a large change can go minutes without output,
+//! which from the outside looks just like a hung one. While an execution runs,
+//! [`spawn_heartbeats`] pushes a [`Heartbeat`] to its [`MsgStore`] every
+//! [`HEARTBEAT_INTERVAL`]: how long it has run, how much output it produced
+//! and how long ago it last did. Once it stays quiet past its stall threshold
+//! the heartbeats are flagged as stalled, so UIs can tell "still working" from
+//! "hung" well before an idle timeout stops the execution.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle, time::Instant};
+use workspace_utils::{
+    log_msg::{Heartbeat, LogMsg},
+    msg_store::MsgStore,
+};
+
+use crate::{
+    actions::{ExecutorAction, ExecutorActionType},
+    profile::ExecutorConfigs,
+    runtime_limits::RuntimeLimits,
+};
+
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// Quiet period after which an execution without an idle timeout is stalled
+const DEFAULT_STALL_AFTER: Duration = Duration::from_secs(300);
+
+/// Quiet period after which `action`'s execution counts as stalled: half of
+/// its profile's idle timeout, if there is one
+pub fn stall_threshold(action: &ExecutorAction, configs: &ExecutorConfigs) -> Duration {
+    let profile = match action.typ() {
+        ExecutorActionType::CodingAgentInitialRequest(request) => &request.executor_profile_id,
+        ExecutorActionType::CodingAgentFollowUpRequest(request) => &request.executor_profile_id,
+        ExecutorActionType::ScriptRequest(_) => return DEFAULT_STALL_AFTER,
+    };
+    configs
+        .get_coding_agent(profile)
+        .and_then(|agent| RuntimeLimits::from(&agent.run_overrides().runtime).idle_timeout)
+        .map_or(DEFAULT_STALL_AFTER, |idle_timeout| idle_timeout / 2)
+}
+
+/// Push heartbeats to `msg_store` until its execution finishes
+pub fn spawn_heartbeats(msg_store: Arc<MsgStore>, stall_after: Duration) -> JoinHandle<()> {
+    spawn_heartbeats_every(msg_store, stall_after, HEARTBEAT_INTERVAL)
+}
+
+fn spawn_heartbeats_every(
+    msg_store: Arc<MsgStore>,
+    stall_after: Duration,
+    interval: Duration,
+) -> JoinHandle<()> {
+    let (history, mut rx) = (msg_store.get_history(), msg_store.get_receiver());
+    tokio::spawn(async move {
+        let mut output_bytes = 0;
+        for msg in &history {
+            match msg {
+                LogMsg::Stdout(s) | LogMsg::Stderr(s) => output_bytes += s.len() as u64,
+                LogMsg::Finished => return,
+                _ => {}
+            }
+        }
+        let started = Instant::now();
+        let mut last_output = started;
+        let mut stalled = false;
+        let mut ticks = tokio::time::interval_at(started + interval, interval);
+
+        loop {
+            tokio::select! {
+                msg = rx.recv() => match msg {
+                    Ok(LogMsg::Stdout(s) | LogMsg::Stderr(s)) => {
+                        output_bytes += s.len() as u64;
+                        last_output = Instant::now();
+                    }
+                    Ok(LogMsg::Finished) | Err(RecvError::Closed) => return,
+                    Ok(_) => {}
+                    // Whatever was missed was output too
+                    Err(RecvError::Lagged(_)) => last_output = Instant::now(),
+                },
+                _ = ticks.tick() => {
+                    let idle = last_output.elapsed();
+                    if idle >= stall_after && !stalled {
+                        tracing::warn!("Execution has produced no output for {}s", idle.as_secs());
+                    }
+                    stalled = idle >= stall_after;
+                    msg_store.push_heartbeat(Heartbeat {
+                        elapsed_secs: started.elapsed().as_secs(),
+                        output_bytes,
+                        idle_secs: idle.as_secs(),
+                        stalled,
+                    });
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeats_flag_stalls() {
+        let store = Arc::new(MsgStore::new());
+        store.push_stdout("starting\n");
+        let handle = spawn_heartbeats_every(
+            store.clone(),
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+        );
+        tokio::task::yield_now().await;
+        store.push_stderr("warn\n");
+
+        tokio::time::sleep(Duration::from_secs(95)).await;
+        store.push_finished();
+        handle.await.unwrap();
+
+        let heartbeats: Vec<_> = store
+            .get_history()
+            .into_iter()
+            .filter_map(|msg| match msg {
+                LogMsg::Heartbeat(heartbeat) => Some(heartbeat),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            heartbeats
+                .iter()
+                .map(|h| (h.elapsed_secs, h.idle_secs, h.stalled))
+                .collect::<Vec<_>>(),
+            [(30, 30, false), (60, 60, true), (90, 90, true)]
+        );
+        assert!(heartbeats.iter().all(|h| h.output_bytes == 14));
+    }
+}
@@ -0,0 +1,139 @@
+//! One-click installation of agent CLIs.
+//!
+//! Executors that know their documented install command (`npm i -g ...`,
+//! `pipx install ...`) run it through [`run_install_command`], which streams
+//! the installer's output into a [`MsgStore`]. Installs are tracked per
+//! executor so a page reload can reattach to a running install instead of
+//! starting a second one.
+
+use std::{
+    collections::HashMap,
+    process::Stdio,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    task::JoinHandle,
+};
+use workspace_utils::msg_store::MsgStore;
+
+use crate::{
+    command::CommandBuilder,
+    executors::{BaseCodingAgent, CodingAgent, ExecutorError, StandardCodingAgentExecutor},
+    probe,
+};
+
+/// Install of one executor: its output and the task running it
+type Install = (Arc<MsgStore>, JoinHandle<()>);
+
+static INSTALLS: LazyLock<RwLock<HashMap<BaseCodingAgent, Install>>> =
+    LazyLock::new(Default::default);
+
+/// Run `command`, pushing its output to `msg_store` line by line
+pub async fn run_install_command(
+    command: &str,
+    msg_store: Arc<MsgStore>,
+) -> Result<(), ExecutorError> {
+    let (program, args) = CommandBuilder::new(command)
+        .build_initial()?
+        .into_resolved()
+        .await?;
+    msg_store.push_stdout(format!("$ {command}\n"));
+
+    let mut child = Command::new(program)
+        .args(&args)
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ExecutorError::Io)?;
+
+    let stdout = child.stdout.take().map(|out| {
+        let store = msg_store.clone();
+        tokio::spawn(forward_lines(out, move |line| store.push_stdout(line)))
+    });
+    let stderr = child.stderr.take().map(|err| {
+        let store = msg_store.clone();
+        tokio::spawn(forward_lines(err, move |line| store.push_stderr(line)))
+    });
+    let status = child.wait().await.map_err(ExecutorError::Io)?;
+    for forwarder in [stdout, stderr].into_iter().flatten() {
+        let _ = forwarder.await;
+    }
+
+    // Availability checks must see the freshly installed CLI
+    probe::clear_probe_cache();
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ExecutorError::InstallFailed(format!(
+            "`{command}` exited with {status}"
+        )))
+    }
+}
+
+pub(crate) async fn forward_lines<R: AsyncRead + Unpin>(reader: R, push: impl Fn(String)) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        push(format!("{line}\n"));
+    }
+}
+
+/// Start installing `agent` in the background, or return the output of the
+/// install already running for `executor`
+pub fn start_install(
+    agent: CodingAgent,
+    executor: BaseCodingAgent,
+) -> Result<Arc<MsgStore>, ExecutorError> {
+    if agent.install_command().is_none() {
+        return Err(ExecutorError::InstallNotSupported);
+    }
+
+    let mut installs = INSTALLS.write().unwrap();
+    if let Some((msg_store, task)) = installs.get(&executor)
+        && !task.is_finished()
+    {
+        return Ok(msg_store.clone());
+    }
+    let msg_store = Arc::new(MsgStore::new());
+    let store = msg_store.clone();
+    let task = tokio::spawn(async move {
+        if let Err(e) = agent.install(store.clone()).await {
+            tracing::warn!("Installing {executor} failed: {e}");
+            store.push_stderr(format!("{e}\n"));
+        }
+        store.push_finished();
+    });
+    installs.insert(executor, (msg_store.clone(), task));
+    Ok(msg_store)
+}
+
+/// Output of the latest install of `executor`, running or finished
+pub fn install_progress(executor: BaseCodingAgent) -> Option<Arc<MsgStore>> {
+    INSTALLS
+        .read()
+        .unwrap()
+        .get(&executor)
+        .map(|(msg_store, _)| msg_store.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executors::copilot::Copilot;
+
+    #[tokio::test]
+    async fn test_install_requires_install_command() {
+        let copilot: CodingAgent = serde_json::from_value::<Copilot>(serde_json::json!({}))
+            .unwrap()
+            .into();
+        assert!(copilot.install_command().is_none());
+        assert!(matches!(
+            start_install(copilot, BaseCodingAgent::Copilot),
+            Err(ExecutorError::InstallNotSupported)
+        ));
+    }
+}
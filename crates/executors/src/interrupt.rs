@@ -0,0 +1,183 @@
+//! Graceful interruption of executor process groups.
+//!
+//! An interrupt first asks the executor to stop: through its own
+//! [`InterruptSender`] when it has one (e.g. a protocol-level interrupt), or by
+//! sending SIGINT to the process group otherwise. If the process has not exited
+//! once the grace period elapses, the whole group is killed.
+
+use std::{io, sync::Arc, time::Duration};
+
+use command_group::AsyncGroupChild;
+use tokio::sync::oneshot;
+use workspace_utils::msg_store::MsgStore;
+
+use crate::{
+    executors::{InterruptSender, SpawnedChild},
+    logs::{
+        NormalizedEntry, NormalizedEntryType,
+        utils::{EntryIndexProvider, patch::add_normalized_entry},
+    },
+};
+
+/// Grace period used when callers have no specific requirement
+pub const DEFAULT_INTERRUPT_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptOutcome {
+    /// The process exited within the grace period
+    Exited,
+    /// The process ignored the interrupt and was killed
+    Killed,
+}
+
+impl InterruptOutcome {
+    pub fn to_normalized_entry(self, grace: Duration) -> NormalizedEntry {
+        let content = match self {
+            InterruptOutcome::Exited => "Execution cancelled".to_string(),
+            InterruptOutcome::Killed => format!(
+                "Execution cancelled; the process did not stop within {}s and was killed",
+                grace.as_secs()
+            ),
+        };
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::SystemMessage,
+            content,
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+/// Send SIGINT to the process group led by `pid`.
+#[cfg(unix)]
+pub fn signal_process_group(pid: u32) -> io::Result<()> {
+    use nix::{
+        sys::signal::{Signal, killpg},
+        unistd::Pid,
+    };
+
+    killpg(Pid::from_raw(pid as i32), Signal::SIGINT).map_err(io::Error::from)
+}
+
+#[cfg(not(unix))]
+pub fn signal_process_group(_pid: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "SIGINT is not available on this platform",
+    ))
+}
+
+/// Interrupt sender for executors without a protocol-level interrupt: delivering
+/// on it sends SIGINT to the child's process group.
+pub fn sigint_interrupt_sender(child: &AsyncGroupChild) -> Option<InterruptSender> {
+    let pid = child.id()?;
+    let (interrupt_tx, interrupt_rx) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        if interrupt_rx.await.is_ok()
+            && let Err(e) = signal_process_group(pid)
+        {
+            tracing::debug!("Failed to send SIGINT to process group {pid}: {e}");
+        }
+    });
+    Some(interrupt_tx)
+}
+
+/// Interrupt `child`, escalating to a kill of the whole group after `grace`.
+pub async fn interrupt_child(
+    child: &mut AsyncGroupChild,
+    interrupt_sender: Option<InterruptSender>,
+    grace: Duration,
+) -> io::Result<InterruptOutcome> {
+    let delivered = interrupt_sender.is_some_and(|sender| sender.send(()).is_ok());
+    if !delivered
+        && let Some(pid) = child.id()
+        && let Err(e) = signal_process_group(pid)
+    {
+        tracing::debug!("Failed to send SIGINT to process group {pid}: {e}");
+    }
+
+    match tokio::time::timeout(grace, child.wait()).await {
+        Ok(status) => status.map(|_| InterruptOutcome::Exited),
+        Err(_) => {
+            child.kill().await?;
+            child.wait().await?;
+            Ok(InterruptOutcome::Killed)
+        }
+    }
+}
+
+/// Record that an execution was cancelled in its conversation log
+pub fn push_cancellation_entry(
+    msg_store: &Arc<MsgStore>,
+    outcome: InterruptOutcome,
+    grace: Duration,
+) {
+    let index_provider = EntryIndexProvider::start_from(msg_store);
+    add_normalized_entry(
+        msg_store,
+        &index_provider,
+        outcome.to_normalized_entry(grace),
+    );
+}
+
+impl SpawnedChild {
+    /// Interrupt the child and, if a store is given, log the cancellation to it
+    pub async fn interrupt(
+        &mut self,
+        grace: Duration,
+        msg_store: Option<&Arc<MsgStore>>,
+    ) -> io::Result<InterruptOutcome> {
+        let outcome = interrupt_child(&mut self.child, self.interrupt_sender.take(), grace).await?;
+        if let Some(msg_store) = msg_store {
+            push_cancellation_entry(msg_store, outcome, grace);
+        }
+        Ok(outcome)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use command_group::AsyncCommandGroup;
+    use tokio::{
+        io::{AsyncBufReadExt, BufReader},
+        process::Command,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_interrupt_exits_on_sigint() {
+        let child = Command::new("sleep").arg("30").group_spawn().unwrap();
+        let mut spawned = SpawnedChild::from(child);
+        let msg_store = Arc::new(MsgStore::new());
+
+        let outcome = spawned
+            .interrupt(Duration::from_secs(5), Some(&msg_store))
+            .await
+            .unwrap();
+        assert_eq!(outcome, InterruptOutcome::Exited);
+        assert_eq!(msg_store.get_history().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_escalates_to_kill() {
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' INT; echo ready; sleep 30"])
+            .stdout(std::process::Stdio::piped())
+            .group_spawn()
+            .unwrap();
+        // Wait until the trap is installed before interrupting
+        let stdout = child.inner().stdout.take().unwrap();
+        let mut lines = BufReader::new(stdout).lines();
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("ready"));
+        let mut spawned = SpawnedChild::from(child);
+
+        let outcome = spawned
+            .interrupt(Duration::from_millis(300), None)
+            .await
+            .unwrap();
+        assert_eq!(outcome, InterruptOutcome::Killed);
+    }
+}
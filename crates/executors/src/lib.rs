@@ -1,9 +1,58 @@
 pub mod actions;
 pub mod approvals;
+pub mod attachments;
+pub mod authorization;
+pub mod auto_commit;
+pub mod benchmark;
+pub mod budget;
+pub mod cancellation;
+pub mod checkpoint;
 pub mod command;
+pub mod context_overflow;
+pub mod credentials;
+pub mod describe;
+pub mod dry_run;
+pub mod encryption;
 pub mod env;
 pub mod executors;
+pub mod fan_out;
+pub mod governor;
+pub mod handoff;
+pub mod heartbeat;
+pub mod install;
+pub mod interrupt;
+pub mod log_tee;
+pub mod login;
 pub mod logs;
+pub mod loop_detection;
 pub mod mcp_config;
+pub mod npx_cache;
+pub mod orphans;
+pub mod outcome;
+pub mod output_decoder;
+pub mod permission_mode;
+pub mod probe;
 pub mod profile;
+pub mod profile_validation;
+pub mod prompt;
+pub mod rate_limit;
+pub mod remediation;
+pub mod remote;
+pub mod replay;
+pub mod resource_limits;
+pub mod retry;
+pub mod review;
+pub mod run_hooks;
+pub mod run_template;
+pub mod runtime;
+pub mod runtime_limits;
+pub mod secrets;
+pub mod sessions;
+pub mod standby;
 pub mod stdout_dup;
+pub mod supervisor;
+pub mod telemetry;
+pub mod tenant;
+pub mod testing;
+pub mod workspace_changes;
+pub mod worktree;
@@ -0,0 +1,194 @@
+//! Raw output of executions, kept on disk for post-mortems.
+//!
+//! When a stream parser trips over an agent's output, the conversation only
+//! shows what it made of it. With a profile's [`LogTee`] set, the raw stdout
+//! and stderr of each execution are also written, byte for byte as they are
+//! read from the agent and before binary output is replaced in the
+//! conversation, to `.vibe/logs/<execution id>.stdout.log` and `.stderr.log`
+//! in the worktree. A file that reaches `max_file_bytes` is
+//! rotated to `.log.1`, `.log.2` and so on, keeping `max_files` files per
+//! stream. The files are plain text, so nothing is written while encryption
+//! at rest is configured.
+
+use std::{
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::encryption::Encryptor;
+
+/// Directory in the worktree holding the logs, ignored by git
+pub const LOGS_DIR: &str = ".vibe/logs";
+const DEFAULT_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILES: u32 = 5;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct LogTee {
+    /// Size a log file may grow to before it is rotated; defaults to 10 MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_bytes: Option<u64>,
+    /// Files kept per stream, the one being written included; defaults to 5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<u32>,
+}
+
+/// Log files of one execution's stdout and stderr
+#[derive(Debug)]
+pub struct RawLogs {
+    pub stdout: RotatingFile,
+    pub stderr: RotatingFile,
+}
+
+impl LogTee {
+    /// Log files for the execution `execution_id` under `worktree`
+    pub fn open(&self, worktree: &Path, execution_id: &str) -> Option<RawLogs> {
+        if Encryptor::global().is_some() {
+            tracing::warn!(
+                "Not writing raw logs of execution {execution_id}: encryption at rest is configured"
+            );
+            return None;
+        }
+        let dir = worktree.join(LOGS_DIR);
+        if let Err(e) = create_logs_dir(&dir) {
+            tracing::warn!("Failed to create {}: {e}", dir.display());
+            return None;
+        }
+        let file = |stream: &str| {
+            RotatingFile::new(
+                dir.join(format!("{execution_id}.{stream}.log")),
+                self.max_file_bytes.unwrap_or(DEFAULT_MAX_FILE_BYTES),
+                self.max_files.unwrap_or(DEFAULT_MAX_FILES),
+            )
+        };
+        Some(RawLogs {
+            stdout: file("stdout"),
+            stderr: file("stderr"),
+        })
+    }
+}
+
+fn create_logs_dir(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let gitignore_path = dir.join(".gitignore");
+    if !gitignore_path.exists() {
+        std::fs::write(&gitignore_path, "*\n")?;
+    }
+    Ok(())
+}
+
+/// Log file that is moved aside to `<path>.1` once it would grow past
+/// `max_bytes`, older files moving up one number in turn
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    file: Option<File>,
+    written: u64,
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_bytes: u64, max_files: u32) -> Self {
+        Self {
+            path,
+            max_bytes,
+            max_files,
+            file: None,
+            written: 0,
+        }
+    }
+
+    /// Append `data`, logging a failure rather than interrupting the output
+    pub fn append(&mut self, data: &[u8]) {
+        if let Err(e) = self.write(data) {
+            tracing::warn!("Failed to write {}: {e}", self.path.display());
+        }
+    }
+
+    /// Append `data`, rotating first if it doesn't fit. A chunk larger than
+    /// a whole file is written in one piece rather than split.
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.written > 0 && self.written + data.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?;
+                self.written = file.metadata()?.len();
+                self.file.insert(file)
+            }
+        };
+        // Unbuffered, so the log is complete even if the server dies
+        file.write_all(data)?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        self.written = 0;
+        let kept = self.max_files.saturating_sub(1);
+        if kept == 0 {
+            return std::fs::remove_file(&self.path);
+        }
+        for n in (1..kept).rev() {
+            match std::fs::rename(self.archive(n), self.archive(n + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        std::fs::rename(&self.path, self.archive(1))
+    }
+
+    fn archive(&self, n: u32) -> PathBuf {
+        let mut path = OsString::from(&self.path);
+        path.push(format!(".{n}"));
+        PathBuf::from(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_output_written_and_rotated() {
+        let dir = std::env::temp_dir().join(format!("vk-log-tee-{}", uuid::Uuid::new_v4()));
+        let tee = LogTee {
+            max_file_bytes: Some(10),
+            max_files: Some(3),
+        };
+        let mut logs = tee.open(&dir, "exec").unwrap();
+        logs.stdout.append(b"0123456789");
+        logs.stderr.append(b"not json\n\xff\x00");
+        for chunk in ["{\"a\":1}\n", "{\"b\":", "2}\n", "{\"c\":3}\n"] {
+            logs.stdout.append(chunk.as_bytes());
+        }
+
+        let logs = dir.join(LOGS_DIR);
+        let read = |name: &str| std::fs::read_to_string(logs.join(name)).unwrap();
+        assert_eq!(read(".gitignore"), "*\n");
+        // Bytes that aren't text are kept as they were
+        assert_eq!(
+            std::fs::read(logs.join("exec.stderr.log")).unwrap(),
+            b"not json\n\xff\x00"
+        );
+        // The oldest file was dropped once three were kept
+        assert_eq!(read("exec.stdout.log"), "{\"c\":3}\n");
+        assert_eq!(read("exec.stdout.log.1"), "{\"b\":2}\n");
+        assert_eq!(read("exec.stdout.log.2"), "{\"a\":1}\n");
+        assert!(!logs.join("exec.stdout.log.3").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+//! Logging agent CLIs in from the UI.
+//!
+//! Once an agent's credentials expire, its runs fail with an auth error until
+//! the user logs in again. Executors whose CLI has a login flow that works
+//! without a terminal, one that prints a browser URL and possibly a device
+//! code to enter there, run it through [`run_login_command`]. Its output
+//! streams into a [`MsgStore`] like an install's, and the URL and code are
+//! picked out of it and published as a [`LoginPrompt`], so the UI can offer a
+//! link and the code rather than a log. Logins are tracked per executor so a
+//! page reload can reattach to the one waiting for the user.
+
+use std::{
+    collections::HashMap,
+    process::Stdio,
+    sync::{Arc, LazyLock, Mutex, RwLock},
+    time::Duration,
+};
+
+use json_patch::Patch;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncRead, process::Command, task::JoinHandle};
+use ts_rs::TS;
+use workspace_utils::msg_store::MsgStore;
+
+use crate::{
+    command::CommandBuilder,
+    executors::{BaseCodingAgent, CodingAgent, ExecutorError, StandardCodingAgentExecutor},
+    install::forward_lines,
+    probe,
+};
+
+/// How long the user has to finish logging in in the browser
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+static LOGIN_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"https?://[^\s"'<>`]+"#).unwrap());
+
+/// Device codes as CLIs show them, e.g. `WDJB-MJHT` or `7KQ2-X9PL`
+static DEVICE_CODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[A-Z0-9]{4,}(?:-[A-Z0-9]{4,})+\b").unwrap());
+
+/// Login of one executor: its output and the task running it
+type Login = (Arc<MsgStore>, JoinHandle<()>);
+
+static LOGINS: LazyLock<RwLock<HashMap<BaseCodingAgent, Login>>> = LazyLock::new(Default::default);
+
+/// What the user needs to finish a login in the browser, published at
+/// `/login_prompt` as the login flow shows it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct LoginPrompt {
+    /// Page to open to log in
+    pub url: Option<String>,
+    /// Code to enter on that page, for device code flows
+    pub code: Option<String>,
+}
+
+impl LoginPrompt {
+    /// Take the URL and code shown in `line` where still missing. Returns
+    /// whether either was found.
+    pub fn scan(&mut self, line: &str) -> bool {
+        let line = strip_ansi_escapes::strip_str(line);
+        let mut found = false;
+        if self.url.is_none()
+            && let Some(url) = LOGIN_URL.find(&line)
+        {
+            let url = url.as_str().trim_end_matches(['.', ',', ')', ']']);
+            self.url = Some(url.to_string());
+            found = true;
+        }
+        // Codes inside the URL are part of its path, not something to enter
+        let text = LOGIN_URL.replace_all(&line, "");
+        if self.code.is_none()
+            && let Some(code) = DEVICE_CODE.find(&text)
+        {
+            self.code = Some(code.as_str().to_string());
+            found = true;
+        }
+        found
+    }
+
+    pub fn to_patch(&self) -> Patch {
+        serde_json::from_value(serde_json::json!([{
+            "op": "add",
+            "path": "/login_prompt",
+            "value": self,
+        }]))
+        .unwrap()
+    }
+}
+
+/// Run the login flow `command`, pushing its output to `msg_store` line by
+/// line and its [`LoginPrompt`] as it appears
+pub async fn run_login_command(
+    command: &str,
+    msg_store: Arc<MsgStore>,
+) -> Result<(), ExecutorError> {
+    let (program, args) = CommandBuilder::new(command)
+        .build_initial()?
+        .into_resolved()
+        .await?;
+    msg_store.push_stdout(format!("$ {command}\n"));
+
+    let mut child = Command::new(program)
+        .args(&args)
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ExecutorError::Io)?;
+
+    let prompt = Arc::new(Mutex::new(LoginPrompt::default()));
+    let stdout = child
+        .stdout
+        .take()
+        .map(|out| forward(out, false, &msg_store, &prompt));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| forward(err, true, &msg_store, &prompt));
+    let status = match tokio::time::timeout(LOGIN_TIMEOUT, child.wait()).await {
+        Ok(status) => status.map_err(ExecutorError::Io)?,
+        Err(_) => {
+            let _ = child.kill().await;
+            return Err(ExecutorError::LoginFailed(format!(
+                "not completed within {} minutes",
+                LOGIN_TIMEOUT.as_secs() / 60
+            )));
+        }
+    };
+    for forwarder in [stdout, stderr].into_iter().flatten() {
+        let _ = forwarder.await;
+    }
+
+    // Availability checks must see the new credentials
+    probe::clear_probe_cache();
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ExecutorError::LoginFailed(format!(
+            "`{command}` exited with {status}"
+        )))
+    }
+}
+
+fn forward(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    stderr: bool,
+    msg_store: &Arc<MsgStore>,
+    prompt: &Arc<Mutex<LoginPrompt>>,
+) -> JoinHandle<()> {
+    let store = msg_store.clone();
+    let prompt = prompt.clone();
+    tokio::spawn(forward_lines(reader, move |line| {
+        let mut prompt = prompt.lock().unwrap();
+        if prompt.scan(&line) {
+            store.push_patch(prompt.to_patch());
+        }
+        if stderr {
+            store.push_stderr(line);
+        } else {
+            store.push_stdout(line);
+        }
+    }))
+}
+
+/// Start logging `agent` in in the background, or return the output of the
+/// login already running for `executor`
+pub fn start_login(
+    agent: CodingAgent,
+    executor: BaseCodingAgent,
+) -> Result<Arc<MsgStore>, ExecutorError> {
+    if agent.login_command().is_none() {
+        return Err(ExecutorError::LoginNotSupported);
+    }
+
+    let mut logins = LOGINS.write().unwrap();
+    if let Some((msg_store, task)) = logins.get(&executor)
+        && !task.is_finished()
+    {
+        return Ok(msg_store.clone());
+    }
+    let msg_store = Arc::new(MsgStore::new());
+    let store = msg_store.clone();
+    let task = tokio::spawn(async move {
+        if let Err(e) = agent.authenticate(store.clone()).await {
+            tracing::warn!("Logging in to {executor} failed: {e}");
+            store.push_stderr(format!("{e}\n"));
+        }
+        store.push_finished();
+    });
+    logins.insert(executor, (msg_store.clone(), task));
+    Ok(msg_store)
+}
+
+/// Output of the latest login of `executor`, running or finished
+pub fn login_progress(executor: BaseCodingAgent) -> Option<Arc<MsgStore>> {
+    LOGINS
+        .read()
+        .unwrap()
+        .get(&executor)
+        .map(|(msg_store, _)| msg_store.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executors::gemini::Gemini;
+
+    #[tokio::test]
+    async fn test_login_prompt_from_device_code_flow() {
+        let output = [
+            "Follow these steps to sign in with ChatGPT using device code authorization:",
+            "1. Open this link in your browser and sign in to your account",
+            "   \u{1b}[94mhttps://auth.openai.com/codex/device\u{1b}[0m",
+            "2. Enter this one-time code (expires in 15 minutes)",
+            "   \u{1b}[94mWDJB-MJHT\u{1b}[0m",
+            "Or visit https://auth.openai.com/ABCD-EFGH.",
+        ];
+        let mut prompt = LoginPrompt::default();
+        let found: Vec<_> = output.iter().map(|line| prompt.scan(line)).collect();
+        assert_eq!(found, [false, false, true, false, true, false]);
+        assert_eq!(
+            prompt,
+            LoginPrompt {
+                url: Some("https://auth.openai.com/codex/device".to_string()),
+                code: Some("WDJB-MJHT".to_string()),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(prompt.to_patch()).unwrap()[0]["path"],
+            "/login_prompt"
+        );
+
+        // A browser flow shows no code
+        let mut prompt = LoginPrompt::default();
+        prompt.scan("Open https://cursor.com/loginDeepControl?challenge=x1y2 to log in.");
+        assert_eq!(
+            prompt.url.as_deref(),
+            Some("https://cursor.com/loginDeepControl?challenge=x1y2")
+        );
+        assert_eq!(prompt.code, None);
+
+        // Gemini only logs in from an interactive session
+        let gemini: CodingAgent = serde_json::from_value::<Gemini>(serde_json::json!({}))
+            .unwrap()
+            .into();
+        assert!(matches!(
+            start_login(gemini, BaseCodingAgent::Gemini),
+            Err(ExecutorError::LoginNotSupported)
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+//! Export of a conversation as newline-delimited JSON, for archiving a run or
+//! feeding it to evaluation tooling.
+//!
+//! [`ExportFormat::Ndjson`] writes every normalized entry as is, one per line.
+//! [`ExportFormat::ClaudeTranscript`] writes the lines of a Claude Code
+//! session transcript instead, so tooling built for those reads runs of any
+//! executor: user and assistant messages, thinking, tool calls with their
+//! results, and errors as system lines.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use ts_rs::TS;
+
+use crate::logs::{
+    ActionType, CommandExitStatus, NormalizedConversation, NormalizedEntry, NormalizedEntryType,
+    ToolStatus,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Ndjson,
+    ClaudeTranscript,
+}
+
+impl ExportFormat {
+    pub fn file_extension(self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::ClaudeTranscript => "jsonl",
+        }
+    }
+}
+
+impl NormalizedConversation {
+    /// Write the conversation to `writer` in `format`, one JSON value per line
+    pub fn export(&self, format: ExportFormat, mut writer: impl Write) -> io::Result<()> {
+        let lines: Vec<Value> = match format {
+            ExportFormat::Ndjson => self
+                .entries
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<Result<_, _>>()?,
+            ExportFormat::ClaudeTranscript => self.claude_transcript_lines(),
+        };
+        for line in lines {
+            serde_json::to_writer(&mut writer, &line)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()
+    }
+
+    /// Export the conversation to a file at `path`, replacing it if present
+    pub fn export_to_file(&self, format: ExportFormat, path: &Path) -> io::Result<()> {
+        self.export(format, BufWriter::new(File::create(path)?))
+    }
+
+    fn claude_transcript_lines(&self) -> Vec<Value> {
+        let session_id = self.session_id.as_deref();
+        let line = |typ: &str, message: Value| {
+            json!({
+                "type": typ,
+                "sessionId": session_id,
+                "message": message,
+            })
+        };
+        let system = |content: &str, level: &str| {
+            json!({
+                "type": "system",
+                "sessionId": session_id,
+                "content": content,
+                "level": level,
+            })
+        };
+        let mut lines = Vec::new();
+        if let Some(prompt) = &self.prompt
+            && !self
+                .entries
+                .first()
+                .is_some_and(|entry| matches!(entry.entry_type, NormalizedEntryType::UserMessage))
+        {
+            lines.push(line("user", json!({ "role": "user", "content": prompt })));
+        }
+        for (index, entry) in self.entries.iter().enumerate() {
+            let assistant = |content: Value| {
+                line(
+                    "assistant",
+                    json!({ "role": "assistant", "content": [content] }),
+                )
+            };
+            match &entry.entry_type {
+                NormalizedEntryType::UserMessage | NormalizedEntryType::UserFeedback { .. } => {
+                    lines.push(line(
+                        "user",
+                        json!({ "role": "user", "content": entry.content }),
+                    ));
+                }
+                NormalizedEntryType::AssistantMessage => {
+                    lines.push(assistant(json!({ "type": "text", "text": entry.content })));
+                }
+                NormalizedEntryType::Thinking => {
+                    lines.push(assistant(
+                        json!({ "type": "thinking", "thinking": entry.content }),
+                    ));
+                }
+                NormalizedEntryType::ToolUse {
+                    tool_name,
+                    action_type,
+                    status,
+                } => {
+                    let id = format!("toolu_{index:05}");
+                    lines.push(assistant(json!({
+                        "type": "tool_use",
+                        "id": id,
+                        "name": tool_name,
+                        "input": action_type,
+                    })));
+                    if let Some((content, is_error)) = tool_result(entry, action_type, status) {
+                        lines.push(line(
+                            "user",
+                            json!({
+                                "role": "user",
+                                "content": [{
+                                    "type": "tool_result",
+                                    "tool_use_id": id,
+                                    "content": content,
+                                    "is_error": is_error,
+                                }],
+                            }),
+                        ));
+                    }
+                }
+                NormalizedEntryType::SystemMessage
+                | NormalizedEntryType::RawOutput
+                | NormalizedEntryType::RunSummary { .. }
+                | NormalizedEntryType::WorkspaceChanges { .. }
+                | NormalizedEntryType::TaskListUpdate { .. } => {
+                    lines.push(system(&entry.content, "info"));
+                }
+                NormalizedEntryType::ErrorMessage { .. }
+                | NormalizedEntryType::LoopDetected { .. } => {
+                    lines.push(system(&entry.content, "error"));
+                }
+                NormalizedEntryType::RunHook {
+                    stage,
+                    command,
+                    status,
+                } => {
+                    let level = match status {
+                        ToolStatus::Failed => "error",
+                        _ => "info",
+                    };
+                    let text = format!("{stage} hook `{command}`\n{}", entry.content);
+                    lines.push(system(&text, level));
+                }
+                NormalizedEntryType::ReviewFinding {
+                    severity,
+                    path,
+                    line,
+                } => {
+                    let location = match (path, line) {
+                        (Some(path), Some(line)) => format!(" {path}:{line}"),
+                        (Some(path), None) => format!(" {path}"),
+                        _ => String::new(),
+                    };
+                    let text = format!("[{severity}]{location} {}", entry.content);
+                    lines.push(system(&text, "warning"));
+                }
+                NormalizedEntryType::Loading | NormalizedEntryType::NextAction { .. } => {}
+            }
+        }
+        lines
+    }
+}
+
+/// Result of a finished tool call, and whether it failed
+fn tool_result(
+    entry: &NormalizedEntry,
+    action_type: &ActionType,
+    status: &ToolStatus,
+) -> Option<(String, bool)> {
+    let failed = matches!(
+        status,
+        ToolStatus::Failed | ToolStatus::Denied { .. } | ToolStatus::TimedOut
+    );
+    let content = match action_type {
+        ActionType::CommandRun {
+            result: Some(result),
+            ..
+        } => {
+            let failed = failed
+                || matches!(
+                    result.exit_status,
+                    Some(CommandExitStatus::ExitCode { code }) if code != 0
+                )
+                || matches!(
+                    result.exit_status,
+                    Some(CommandExitStatus::Success { success: false })
+                );
+            return Some((result.output.clone().unwrap_or_default(), failed));
+        }
+        ActionType::Tool {
+            result: Some(result),
+            ..
+        } => match &result.value {
+            Value::String(text) => text.clone(),
+            value => value.to_string(),
+        },
+        _ if failed => entry.content.clone(),
+        _ => return None,
+    };
+    Some((content, failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::CommandRunResult;
+
+    fn entry(entry_type: NormalizedEntryType, content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let conversation = NormalizedConversation {
+            entries: vec![
+                entry(NormalizedEntryType::UserMessage, "list files"),
+                entry(
+                    NormalizedEntryType::ToolUse {
+                        tool_name: "Bash".to_string(),
+                        action_type: ActionType::CommandRun {
+                            command: "ls".to_string(),
+                            result: Some(CommandRunResult {
+                                exit_status: Some(CommandExitStatus::ExitCode { code: 0 }),
+                                output: Some("README.md\n".to_string()),
+                            }),
+                        },
+                        status: ToolStatus::Success,
+                    },
+                    "ls",
+                ),
+                entry(NormalizedEntryType::AssistantMessage, "Just a README."),
+            ],
+            session_id: Some("session-1".to_string()),
+            executor_type: "CLAUDE_CODE".to_string(),
+            prompt: Some("list files".to_string()),
+            summary: None,
+        };
+
+        let mut ndjson = Vec::new();
+        conversation
+            .export(ExportFormat::Ndjson, &mut ndjson)
+            .unwrap();
+        let entries: Vec<NormalizedEntry> = String::from_utf8(ndjson)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 3);
+
+        let mut transcript = Vec::new();
+        conversation
+            .export(ExportFormat::ClaudeTranscript, &mut transcript)
+            .unwrap();
+        let lines: Vec<Value> = String::from_utf8(transcript)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let types: Vec<_> = lines
+            .iter()
+            .map(|line| line["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["user", "assistant", "user", "assistant"]);
+        let result = &lines[2]["message"]["content"][0];
+        assert_eq!(
+            result["tool_use_id"],
+            lines[1]["message"]["content"][0]["id"]
+        );
+        assert_eq!(result["content"], "README.md\n");
+        assert_eq!(result["is_error"], false);
+        assert!(lines.iter().all(|line| line["sessionId"] == "session-1"));
+    }
+}
@@ -0,0 +1,366 @@
+//! Executor-agnostic extraction of file edits from normalized logs.
+//!
+//! Most executors already normalize their edit tools into
+//! [`ActionType::FileEdit`], but tools they don't recognise (MCP editors,
+//! `str_replace`-style tools, `apply_patch`) end up as generic
+//! [`ActionType::Tool`] calls. This module detects edits in those calls from
+//! their arguments and combines them with the already normalized ones, so the
+//! changes made by an execution can be listed straight from its logs.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+use workspace_utils::{
+    diff::{create_unified_diff, normalize_unified_diff},
+    log_msg::LogMsg,
+};
+
+use crate::logs::{
+    ActionType, FileChange, NormalizedEntry, NormalizedEntryType, ToolStatus,
+    utils::patch::latest_normalized_entries,
+};
+
+const PATH_KEYS: &[&str] = &["path", "file_path", "filePath", "filename", "target_file"];
+const OLD_TEXT_KEYS: &[&str] = &["old_str", "old_string", "oldString", "old_text"];
+const NEW_TEXT_KEYS: &[&str] = &["new_str", "new_string", "newString", "new_text"];
+const CONTENT_KEYS: &[&str] = &["content", "contents", "file_text", "text"];
+const PATCH_KEYS: &[&str] = &["patch", "input", "diff"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct FileChangeEntry {
+    pub path: String,
+    pub change: FileChange,
+}
+
+fn str_arg<'a>(args: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|key| args.get(*key)?.as_str())
+}
+
+fn edit(path: &str, old: &str, new: &str) -> FileChange {
+    FileChange::Edit {
+        unified_diff: create_unified_diff(path, old, new),
+        has_line_numbers: false,
+    }
+}
+
+/// Detect file edits in a generic tool call from its arguments.
+///
+/// Recognises `str_replace`-style edits (single or multiple), whole-file
+/// writes, and patches in either `apply_patch` or unified diff format.
+pub fn tool_call_file_changes(tool_name: &str, args: &Value) -> Vec<FileChangeEntry> {
+    if let Some(patch) = str_arg(args, PATCH_KEYS) {
+        let changes = parse_patch(patch);
+        if !changes.is_empty() {
+            return changes;
+        }
+    }
+
+    let Some(path) = str_arg(args, PATH_KEYS) else {
+        return vec![];
+    };
+    let entry = |change| FileChangeEntry {
+        path: path.to_string(),
+        change,
+    };
+
+    if let (Some(old), Some(new)) = (str_arg(args, OLD_TEXT_KEYS), str_arg(args, NEW_TEXT_KEYS)) {
+        return vec![entry(edit(path, old, new))];
+    }
+    if let Some(edits) = args.get("edits").and_then(Value::as_array) {
+        return edits
+            .iter()
+            .filter_map(|e| {
+                Some(edit(
+                    path,
+                    str_arg(e, OLD_TEXT_KEYS)?,
+                    str_arg(e, NEW_TEXT_KEYS)?,
+                ))
+            })
+            .map(entry)
+            .collect();
+    }
+
+    let name = tool_name.to_lowercase();
+    let is_write = ["write", "create"].iter().any(|w| name.contains(w))
+        || args.get("command").and_then(Value::as_str) == Some("create");
+    match str_arg(args, CONTENT_KEYS) {
+        Some(content) if is_write => vec![entry(FileChange::Write {
+            content: content.to_string(),
+        })],
+        _ => vec![],
+    }
+}
+
+/// Split a patch into per-file changes. Supports the `*** Begin Patch`
+/// envelope used by `apply_patch` and plain unified diffs.
+pub fn parse_patch(patch: &str) -> Vec<FileChangeEntry> {
+    if patch.trim_start().starts_with("*** Begin Patch") {
+        parse_apply_patch(patch)
+    } else {
+        parse_unified_diff(patch)
+    }
+}
+
+fn parse_apply_patch(patch: &str) -> Vec<FileChangeEntry> {
+    enum Section {
+        Add(String),
+        Update(String),
+    }
+
+    let mut entries = Vec::new();
+    let mut current: Option<(Section, String)> = None;
+    let flush =
+        |current: Option<(Section, String)>, entries: &mut Vec<FileChangeEntry>| match current {
+            Some((Section::Add(path), body)) => entries.push(FileChangeEntry {
+                path,
+                change: FileChange::Write { content: body },
+            }),
+            Some((Section::Update(path), body)) if !body.is_empty() => {
+                let unified_diff = normalize_unified_diff(&path, &body);
+                entries.push(FileChangeEntry {
+                    path,
+                    change: FileChange::Edit {
+                        unified_diff,
+                        has_line_numbers: false,
+                    },
+                });
+            }
+            _ => {}
+        };
+
+    for line in patch.lines() {
+        if let Some(path) = line.strip_prefix("*** Add File: ") {
+            flush(current.take(), &mut entries);
+            current = Some((Section::Add(path.trim().to_string()), String::new()));
+        } else if let Some(path) = line.strip_prefix("*** Update File: ") {
+            flush(current.take(), &mut entries);
+            current = Some((Section::Update(path.trim().to_string()), String::new()));
+        } else if let Some(path) = line.strip_prefix("*** Delete File: ") {
+            flush(current.take(), &mut entries);
+            entries.push(FileChangeEntry {
+                path: path.trim().to_string(),
+                change: FileChange::Delete,
+            });
+        } else if let Some(new_path) = line.strip_prefix("*** Move to: ") {
+            if let Some((Section::Update(path), _)) = &current {
+                entries.push(FileChangeEntry {
+                    path: path.clone(),
+                    change: FileChange::Rename {
+                        new_path: new_path.trim().to_string(),
+                    },
+                });
+            }
+        } else if line.starts_with("*** ") {
+            // End of patch / end of file markers
+            flush(current.take(), &mut entries);
+        } else if let Some((section, body)) = &mut current {
+            match section {
+                Section::Add(_) => {
+                    body.push_str(line.strip_prefix('+').unwrap_or(line));
+                    body.push('\n');
+                }
+                // Hunk headers carry context rather than line numbers
+                Section::Update(_) if line.starts_with("@@") => body.push_str("@@\n"),
+                Section::Update(_) => {
+                    if !body.is_empty() || line.starts_with([' ', '+', '-']) {
+                        if body.is_empty() {
+                            body.push_str("@@\n");
+                        }
+                        body.push_str(line);
+                        body.push('\n');
+                    }
+                }
+            }
+        }
+    }
+    flush(current, &mut entries);
+    entries
+}
+
+fn parse_unified_diff(patch: &str) -> Vec<FileChangeEntry> {
+    let lines: Vec<&str> = patch.split_inclusive('\n').collect();
+    let starts: Vec<usize> = lines
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0].starts_with("--- ") && pair[1].starts_with("+++ "))
+        .map(|(i, _)| i)
+        .collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(lines.len());
+            let header_path = |line: &str, prefix: &str| {
+                let path = line[4..].trim_end().split('\t').next()?.trim();
+                (path != "/dev/null").then(|| path.strip_prefix(prefix).unwrap_or(path).to_string())
+            };
+            let old_path = header_path(lines[start], "a/");
+            let new_path = header_path(lines[start + 1], "b/");
+            let change = match (&old_path, &new_path) {
+                (Some(_), None) => FileChange::Delete,
+                (_, Some(path)) => FileChange::Edit {
+                    unified_diff: normalize_unified_diff(path, &lines[start..end].concat()),
+                    has_line_numbers: true,
+                },
+                (None, None) => return None,
+            };
+            Some(FileChangeEntry {
+                path: new_path.or(old_path)?,
+                change,
+            })
+        })
+        .collect()
+}
+
+/// File changes recorded by a single log entry. Tool calls that were denied
+/// or failed did not change anything and are ignored.
+pub fn entry_file_changes(entry: &NormalizedEntry) -> Vec<FileChangeEntry> {
+    let NormalizedEntryType::ToolUse {
+        tool_name,
+        action_type,
+        status,
+    } = &entry.entry_type
+    else {
+        return vec![];
+    };
+    if matches!(
+        status,
+        ToolStatus::Failed | ToolStatus::Denied { .. } | ToolStatus::TimedOut
+    ) {
+        return vec![];
+    }
+
+    match action_type {
+        ActionType::FileEdit { path, changes } => changes
+            .iter()
+            .map(|change| FileChangeEntry {
+                path: path.clone(),
+                change: change.clone(),
+            })
+            .collect(),
+        ActionType::Tool {
+            arguments: Some(args),
+            ..
+        } => tool_call_file_changes(tool_name, args),
+        _ => vec![],
+    }
+}
+
+/// All file changes in a sequence of entries, in the order they were made
+pub fn collect_file_changes<'a>(
+    entries: impl IntoIterator<Item = &'a NormalizedEntry>,
+) -> Vec<FileChangeEntry> {
+    entries.into_iter().flat_map(entry_file_changes).collect()
+}
+
+/// All file changes recorded in an execution's log history
+pub fn file_changes_from_history<'a>(
+    history: impl IntoIterator<Item = &'a LogMsg>,
+) -> Vec<FileChangeEntry> {
+    collect_file_changes(latest_normalized_entries(history).values())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn tool_entry(tool_name: &str, arguments: Value, status: ToolStatus) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::ToolUse {
+                tool_name: tool_name.to_string(),
+                action_type: ActionType::Tool {
+                    tool_name: tool_name.to_string(),
+                    arguments: Some(arguments),
+                    result: None,
+                },
+                status,
+            },
+            content: String::new(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_detects_edits_in_generic_tool_calls() {
+        let entries = vec![
+            tool_entry(
+                "mcp__editor__str_replace",
+                json!({"path": "src/lib.rs", "old_str": "fn a() {}\n", "new_str": "fn b() {}\n"}),
+                ToolStatus::Success,
+            ),
+            tool_entry(
+                "write_file",
+                json!({"file_path": "README.md", "content": "# Hi\n"}),
+                ToolStatus::Success,
+            ),
+            tool_entry(
+                "write_file",
+                json!({"file_path": "denied.md", "content": "no\n"}),
+                ToolStatus::Denied { reason: None },
+            ),
+            tool_entry(
+                "search",
+                json!({"path": "src", "text": "needle"}),
+                ToolStatus::Success,
+            ),
+        ];
+
+        let changes = collect_file_changes(&entries);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "src/lib.rs");
+        match &changes[0].change {
+            FileChange::Edit { unified_diff, .. } => {
+                assert!(unified_diff.contains("-fn a() {}"));
+                assert!(unified_diff.contains("+fn b() {}"));
+            }
+            other => panic!("expected edit, got {other:?}"),
+        }
+        assert!(matches!(
+            &changes[1].change,
+            FileChange::Write { content } if content == "# Hi\n"
+        ));
+    }
+
+    #[test]
+    fn test_parses_apply_patch_and_unified_diffs() {
+        let patch = "*** Begin Patch\n*** Add File: new.txt\n+hello\n*** Update File: src/main.rs\n*** Move to: src/app.rs\n@@ fn main\n-    old();\n+    new();\n*** Delete File: gone.txt\n*** End Patch\n";
+        let changes = parse_patch(patch);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                let kind = match &c.change {
+                    FileChange::Write { .. } => "write",
+                    FileChange::Delete => "delete",
+                    FileChange::Rename { .. } => "rename",
+                    FileChange::Edit { .. } => "edit",
+                };
+                (c.path.as_str(), kind)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("new.txt", "write"),
+                ("src/main.rs", "rename"),
+                ("src/main.rs", "edit"),
+                ("gone.txt", "delete"),
+            ]
+        );
+
+        let diff = "--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-x\n+y\n--- a/b.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-z\n";
+        let changes = parse_patch(diff);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(
+            &changes[0].change,
+            FileChange::Edit { unified_diff, has_line_numbers: true } if unified_diff.contains("+y")
+        ));
+        assert_eq!(changes[1].path, "b.txt");
+        assert!(matches!(changes[1].change, FileChange::Delete));
+    }
+}
@@ -3,8 +3,27 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use workspace_utils::approvals::ApprovalStatus;
 
+use crate::{
+    logs::{run_metrics::RunMetrics, task_list::TaskList},
+    loop_detection::LoopAction,
+    review::ReviewSeverity,
+    run_hooks::HookStage,
+    workspace_changes::WorkspaceChanges,
+};
+
+pub mod export;
+pub mod file_changes;
+pub mod pipeline;
 pub mod plain_text_processor;
+pub mod run_metrics;
+pub mod search;
 pub mod stderr_processor;
+pub mod stream;
+pub mod summary;
+pub mod task_list;
+pub mod tool_stats;
+pub mod transcript;
+pub mod truncation;
 pub mod utils;
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -95,6 +114,47 @@ pub enum NormalizedEntryType {
         execution_processes: usize,
         needs_setup: bool,
     },
+    RunSummary {
+        summary: RunSummary,
+    },
+    WorkspaceChanges {
+        changes: WorkspaceChanges,
+    },
+    RunHook {
+        stage: HookStage,
+        command: String,
+        status: ToolStatus,
+    },
+    ReviewFinding {
+        severity: ReviewSeverity,
+        path: Option<String>,
+        line: Option<u32>,
+    },
+    TaskListUpdate {
+        task_list: TaskList,
+    },
+    LoopDetected {
+        tool_name: Option<String>,
+        repeats: u32,
+        action: LoopAction,
+    },
+    /// Output the executor's parser couldn't read, kept as it was
+    RawOutput,
+}
+
+/// Digest of a finished multi-agent run, as reported by the agent itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RunSummary {
+    pub tasks_completed: Option<u32>,
+    pub tasks_total: Option<u32>,
+    /// Names or types of the agents that took part
+    pub agents: Vec<String>,
+    pub duration_ms: Option<u64>,
+    /// How responsive the run was, measured by the normalization pipeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub metrics: Option<RunMetrics>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -104,6 +164,14 @@ pub struct NormalizedEntry {
     pub content: String,
     #[ts(skip)]
     pub metadata: Option<serde_json::Value>,
+    /// Swarm agent the entry came from, for executors that run several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub agent_id: Option<String>,
+    /// Role of that agent within the swarm, e.g. `coder` or `tester`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub agent_role: Option<String>,
 }
 
 impl NormalizedEntry {
@@ -169,6 +237,17 @@ pub struct TodoItem {
     pub priority: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryOperation {
+    Store,
+    Retrieve,
+    Search,
+    List,
+    Delete,
+}
+
 /// Types of tool actions that can be performed
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
@@ -210,6 +289,14 @@ pub enum ActionType {
         todos: Vec<TodoItem>,
         operation: String,
     },
+    /// Access to claude-flow's shared memory
+    Memory {
+        operation: MemoryOperation,
+        #[serde(default)]
+        namespace: Option<String>,
+        #[serde(default)]
+        key: Option<String>,
+    },
     Other {
         description: String,
     },
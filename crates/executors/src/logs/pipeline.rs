@@ -0,0 +1,1235 @@
+//! Profile-configurable post-processing around an executor's log normalizer.
+//!
+//! When a profile sets `normalization`, the executor's normalizer runs against a
+//! staging store instead of the execution's own [`MsgStore`]. Raw output is
+//! copied into the staging store through the raw stages (ANSI stripping,
+//! redaction), and the entries the normalizer produces are copied back through
+//! the entry stages (redaction, classification) and the streaming stages, which
+//! decide how often in-place updates of a streaming entry reach the UI.
+//! Stages apply in the order they are listed. Without a `normalization` setting
+//! entries stream through as the executor's normalizer produces them.
+//!
+//! The profile's [`ThinkingVisibility`] is applied on the way back as well:
+//! collapsed thinking blocks are only forwarded once complete, and hidden ones
+//! are dropped, with later entries renumbered to keep the conversation dense.
+//! The `hide_entries` stage drops entries of other kinds the same way, and
+//! `truncate_outputs` cuts long entries short, keeping the full entry in a
+//! [`FullEntryStore`] for the UI to expand.
+//!
+//! Whatever the profile, the pipeline also follows the agent's todo list with
+//! a [`TaskListTracker`] and adds a `TaskListUpdate` entry right after each
+//! entry that changes it, renumbering later entries the same way. Profiles
+//! with [`LoopDetection`] settings also get a `LoopDetected` warning after
+//! the entry that completes a loop.
+//!
+//! Entries are stamped with the time the raw output before them was read,
+//! measured on a monotonic clock so that timestamps never go backwards. With
+//! the `merge_streams` stage, stderr is fed to the normalizer too, joined with
+//! stdout line by line in the order the lines arrive, so a warning shows up
+//! next to the step that caused it.
+//!
+//! The same clock times the run: once a live execution's output ends, its
+//! [`RunMetrics`] are added to the conversation in a `RunSummary` entry.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use json_patch::Patch;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast::error::RecvError, time::Instant};
+use ts_rs::TS;
+use uuid::Uuid;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::{
+    cancellation::CancelScope,
+    logs::{
+        NormalizedEntry, NormalizedEntryError, NormalizedEntryType,
+        run_metrics::{RunMeter, RunMetrics},
+        search::EntryKind,
+        task_list::TaskListTracker,
+        truncation::{FullEntryStore, truncate_entry},
+        utils::{ConversationPatch, patch::extract_normalized_entry_from_patch},
+    },
+    loop_detection::{DetectedLoop, LoopDetection, LoopDetector},
+};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Secrets that are redacted when `builtin_patterns` is enabled
+const BUILTIN_SECRET_PATTERNS: &[&str] = &[
+    r"sk-(?:ant-)?[A-Za-z0-9_-]{20,}",
+    r"gh[pousr]_[A-Za-z0-9]{36,}",
+    r"github_pat_[A-Za-z0-9_]{20,}",
+    r"AKIA[0-9A-Z]{16}",
+    r"xox[abposr]-[A-Za-z0-9-]{10,}",
+    r"(?i)bearer\s+[A-Za-z0-9._~+/-]{20,}=*",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassifiedAs {
+    SystemMessage,
+    Error,
+    SetupRequired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct ClassificationRule {
+    /// Regex matched against the entry content
+    pub pattern: String,
+    pub entry_type: ClassifiedAs,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum NormalizationStage {
+    // Remove ANSI escape sequences from raw output before it is parsed
+    StripAnsi,
+    // Replace secrets in raw output and entry contents with "[REDACTED]"
+    Redact {
+        #[serde(default)]
+        patterns: Vec<String>,
+        #[serde(default = "default_true")]
+        builtin_patterns: bool,
+    },
+    // Re-label system/error entries whose content matches a rule; first match wins
+    Classify {
+        rules: Vec<ClassificationRule>,
+    },
+    // Forward in-place updates of an entry at most once per window
+    Coalesce {
+        window_ms: u64,
+    },
+    // Stream in-place updates of entries as they arrive; without this stage an
+    // entry only reaches the UI once the next entry starts
+    DeltaStreaming,
+    // Feed stderr to the normalizer along with stdout, whole lines in the order
+    // they arrive, instead of normalizing it separately
+    MergeStreams,
+    // Leave entries of these kinds out of the conversation
+    HideEntries {
+        kinds: Vec<EntryKind>,
+    },
+    // Cut entry contents and tool outputs longer than this many KB, keeping
+    // the full entry to be expanded on request
+    TruncateOutputs {
+        max_kb: u64,
+    },
+}
+
+// How an executor's thinking/reasoning blocks reach the conversation
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ThinkingVisibility {
+    // Stream thinking blocks as they are produced
+    #[default]
+    Show,
+    // Deliver each thinking block in one piece once it is complete
+    Collapse,
+    // Leave thinking blocks out of the conversation
+    Hide,
+}
+
+#[derive(Debug, Clone)]
+struct Redactor(Vec<Regex>);
+
+impl Redactor {
+    fn new(patterns: &[String], builtin_patterns: bool) -> Self {
+        let builtin: &[&str] = if builtin_patterns {
+            BUILTIN_SECRET_PATTERNS
+        } else {
+            &[]
+        };
+        let regexes = builtin
+            .iter()
+            .copied()
+            .chain(patterns.iter().map(String::as_str))
+            .filter_map(|pattern| {
+                Regex::new(pattern)
+                    .inspect_err(|e| tracing::warn!("Ignoring redaction pattern {pattern:?}: {e}"))
+                    .ok()
+            })
+            .collect();
+        Self(regexes)
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.0.iter().fold(text.to_string(), |text, regex| {
+            regex.replace_all(&text, REDACTED).into_owned()
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RawStage {
+    StripAnsi,
+    Redact(Redactor),
+}
+
+#[derive(Debug, Clone)]
+enum EntryStage {
+    Redact(Redactor),
+    Classify(Vec<(Regex, ClassifiedAs)>),
+}
+
+/// Compiled form of a profile's normalization stages
+#[derive(Debug, Clone)]
+pub struct NormalizationPipeline {
+    raw: Vec<RawStage>,
+    entry: Vec<EntryStage>,
+    coalesce: Option<Duration>,
+    delta_streaming: bool,
+    merge_streams: bool,
+    hidden_kinds: Vec<EntryKind>,
+    truncate_bytes: Option<usize>,
+    full_entries: FullEntryStore,
+    thinking: ThinkingVisibility,
+    loop_detection: Option<LoopDetection>,
+    cancel: CancelScope,
+}
+
+impl NormalizationPipeline {
+    pub fn new(stages: &[NormalizationStage]) -> Self {
+        let mut pipeline = Self {
+            raw: vec![],
+            entry: vec![],
+            coalesce: None,
+            delta_streaming: false,
+            merge_streams: false,
+            hidden_kinds: vec![],
+            truncate_bytes: None,
+            full_entries: FullEntryStore::default(),
+            thinking: ThinkingVisibility::default(),
+            loop_detection: None,
+            cancel: CancelScope::default(),
+        };
+        for stage in stages {
+            match stage {
+                NormalizationStage::StripAnsi => pipeline.raw.push(RawStage::StripAnsi),
+                NormalizationStage::Redact {
+                    patterns,
+                    builtin_patterns,
+                } => {
+                    let redactor = Redactor::new(patterns, *builtin_patterns);
+                    pipeline.raw.push(RawStage::Redact(redactor.clone()));
+                    pipeline.entry.push(EntryStage::Redact(redactor));
+                }
+                NormalizationStage::Classify { rules } => {
+                    let rules = rules
+                        .iter()
+                        .filter_map(|rule| match Regex::new(&rule.pattern) {
+                            Ok(regex) => Some((regex, rule.entry_type)),
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Ignoring classification rule {:?}: {e}",
+                                    rule.pattern
+                                );
+                                None
+                            }
+                        })
+                        .collect();
+                    pipeline.entry.push(EntryStage::Classify(rules));
+                }
+                NormalizationStage::Coalesce { window_ms } => {
+                    pipeline.coalesce = Some(Duration::from_millis(*window_ms));
+                }
+                NormalizationStage::DeltaStreaming => pipeline.delta_streaming = true,
+                NormalizationStage::MergeStreams => pipeline.merge_streams = true,
+                NormalizationStage::HideEntries { kinds } => {
+                    pipeline.hidden_kinds.extend(kinds);
+                }
+                NormalizationStage::TruncateOutputs { max_kb } => {
+                    pipeline.truncate_bytes = Some((*max_kb as usize).saturating_mul(1024));
+                }
+            }
+        }
+        pipeline
+    }
+
+    pub fn with_thinking_visibility(mut self, thinking: ThinkingVisibility) -> Self {
+        self.thinking = thinking;
+        self
+    }
+
+    pub fn with_loop_detection(mut self, loop_detection: Option<LoopDetection>) -> Self {
+        self.loop_detection = loop_detection;
+        self
+    }
+
+    /// Stop forwarding raw output once `cancel` fires. Output received up to
+    /// then is still normalized.
+    pub fn with_cancel(mut self, cancel: CancelScope) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn process_raw(&self, text: &str) -> String {
+        self.raw
+            .iter()
+            .fold(text.to_string(), |text, stage| match stage {
+                RawStage::StripAnsi => strip_ansi_escapes::strip_str(&text),
+                RawStage::Redact(redactor) => redactor.apply(&text),
+            })
+    }
+
+    pub fn process_entry(&self, mut entry: NormalizedEntry) -> NormalizedEntry {
+        for stage in &self.entry {
+            match stage {
+                EntryStage::Redact(redactor) => entry.content = redactor.apply(&entry.content),
+                EntryStage::Classify(rules) => {
+                    if !matches!(
+                        entry.entry_type,
+                        NormalizedEntryType::SystemMessage
+                            | NormalizedEntryType::ErrorMessage { .. }
+                    ) {
+                        continue;
+                    }
+                    if let Some((_, classified)) = rules
+                        .iter()
+                        .find(|(regex, _)| regex.is_match(&entry.content))
+                    {
+                        entry.entry_type = match classified {
+                            ClassifiedAs::SystemMessage => NormalizedEntryType::SystemMessage,
+                            ClassifiedAs::Error => NormalizedEntryType::ErrorMessage {
+                                error_type: NormalizedEntryError::Other,
+                            },
+                            ClassifiedAs::SetupRequired => NormalizedEntryType::ErrorMessage {
+                                error_type: NormalizedEntryError::SetupRequired,
+                            },
+                        };
+                    }
+                }
+            }
+        }
+        entry
+    }
+
+    /// Whether `entry` is left out of the conversation
+    fn hides(&self, entry: &NormalizedEntry) -> bool {
+        if self.thinking == ThinkingVisibility::Hide
+            && matches!(entry.entry_type, NormalizedEntryType::Thinking)
+        {
+            return true;
+        }
+        EntryKind::of(&entry.entry_type).is_some_and(|kind| self.hidden_kinds.contains(&kind))
+    }
+
+    fn process_raw_msg(&self, msg: LogMsg) -> LogMsg {
+        match msg {
+            LogMsg::Stdout(text) => LogMsg::Stdout(self.process_raw(&text)),
+            LogMsg::Stderr(text) => LogMsg::Stderr(self.process_raw(&text)),
+            other => other,
+        }
+    }
+
+    /// Run `normalize` on a staging store fed from `msg_store` through this pipeline
+    pub fn run(self, msg_store: Arc<MsgStore>, normalize: impl FnOnce(Arc<MsgStore>)) {
+        let clock = ReadClock::new();
+        let mut stager = Stager {
+            merger: self.merge_streams.then(LineMerger::default),
+            pipeline: Arc::new(self),
+            staging: Arc::new(MsgStore::new()),
+            clock: clock.clone(),
+            live: false,
+        };
+        let pipeline = stager.pipeline.clone();
+        let staging = stager.staging.clone();
+
+        // Replay what the execution has produced so far, so entry indices
+        // continue after existing entries
+        let (history, mut raw_rx) = (msg_store.get_history(), msg_store.get_receiver());
+        let finished = history.iter().any(|msg| matches!(msg, LogMsg::Finished));
+        for msg in history {
+            stager.push(msg);
+        }
+        let mut staged_rx = staging.get_receiver();
+
+        if !finished {
+            stager.live = true;
+            tokio::spawn(async move {
+                loop {
+                    let msg = tokio::select! {
+                        biased;
+                        _ = stager.pipeline.cancel.cancelled() => break,
+                        msg = raw_rx.recv() => msg,
+                    };
+                    match msg {
+                        Ok(msg @ (LogMsg::Stdout(_) | LogMsg::Stderr(_))) => stager.push(msg),
+                        Ok(LogMsg::Finished) | Err(RecvError::Closed) => break,
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(n)) => {
+                            tracing::warn!("Normalization pipeline skipped {n} raw messages");
+                        }
+                    }
+                }
+                stager.push(LogMsg::Finished);
+            });
+        }
+
+        normalize(staging);
+
+        tokio::spawn(async move {
+            let mut emitter = Emitter {
+                msg_store,
+                pending: BTreeMap::new(),
+                held_thinking: BTreeMap::new(),
+                hidden: BTreeSet::new(),
+                full_entry_ids: BTreeMap::new(),
+                task_list: TaskListTracker::default(),
+                task_list_entries: BTreeSet::new(),
+                inserted: BTreeMap::new(),
+                loops: pipeline.loop_detection.clone().map(LoopDetector::new),
+                meter: (!finished).then(RunMeter::default),
+                last_index: None,
+                clock,
+                timestamps: BTreeMap::new(),
+            };
+            let mut flush_at: Option<Instant> = None;
+            loop {
+                let msg = tokio::select! {
+                    msg = staged_rx.recv() => msg,
+                    _ = tokio::time::sleep_until(flush_at.unwrap_or_else(Instant::now)), if flush_at.is_some() => {
+                        emitter.flush_pending();
+                        flush_at = None;
+                        continue;
+                    }
+                };
+                match msg {
+                    Ok(LogMsg::JsonPatch(patch)) => match entry_patch(&patch) {
+                        Some((EntryOp::Replace, index, mut entry)) => {
+                            let Some(output_index) = emitter.output_index(index) else {
+                                continue;
+                            };
+                            // Updates keep the time the entry was added at
+                            if entry.timestamp.is_none() {
+                                entry.timestamp = emitter.timestamps.get(&index).cloned();
+                            }
+                            let entry = pipeline.process_entry(entry);
+                            if let Some(meter) = &mut emitter.meter {
+                                meter.observe(index, &entry);
+                            }
+                            emitter.track_task_list(index, &entry);
+                            emitter.detect_loops(index, &entry, false);
+                            let entry = emitter.truncate(&pipeline, index, entry);
+                            let is_thinking =
+                                matches!(entry.entry_type, NormalizedEntryType::Thinking);
+                            let patch = ConversationPatch::replace(output_index, entry);
+                            if is_thinking && pipeline.thinking == ThinkingVisibility::Collapse {
+                                emitter.held_thinking.insert(index, patch);
+                                continue;
+                            }
+                            match (pipeline.coalesce, pipeline.delta_streaming) {
+                                (None, true) => emitter.msg_store.push_patch(patch),
+                                (coalesce, delta_streaming) => {
+                                    emitter.pending.insert(index, patch);
+                                    if delta_streaming && flush_at.is_none() {
+                                        flush_at = coalesce.map(|window| Instant::now() + window);
+                                    }
+                                }
+                            }
+                        }
+                        Some((EntryOp::Add, index, entry)) => {
+                            emitter.flush();
+                            // Classified first, so that hiding goes by the final kind
+                            let mut entry = pipeline.process_entry(entry);
+                            if let Some(meter) = &mut emitter.meter {
+                                meter.observe(index, &entry);
+                            }
+                            if pipeline.hides(&entry) {
+                                emitter.hidden.insert(index);
+                                continue;
+                            }
+                            let Some(output_index) = emitter.output_index(index) else {
+                                continue;
+                            };
+                            emitter.stamp(&mut entry);
+                            emitter
+                                .timestamps
+                                .insert(index, entry.timestamp.clone().unwrap_or_default());
+                            // The entry before is complete now
+                            if let Some(detected) =
+                                emitter.loops.as_mut().and_then(LoopDetector::finish)
+                            {
+                                emitter.report_loop(detected);
+                            }
+                            emitter.last_index = Some(index);
+                            let shown = emitter.truncate(&pipeline, index, entry.clone());
+                            emitter
+                                .msg_store
+                                .push_patch(ConversationPatch::add_normalized_entry(
+                                    output_index,
+                                    shown,
+                                ));
+                            emitter.track_task_list(index, &entry);
+                            emitter.detect_loops(index, &entry, true);
+                        }
+                        None => {
+                            emitter.flush();
+                            if let Some(patch) = emitter.renumber(patch) {
+                                emitter.msg_store.push_patch(patch);
+                            }
+                        }
+                    },
+                    Ok(LogMsg::SessionId(id)) => emitter.msg_store.push_session_id(id),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("Normalization pipeline skipped {n} normalized messages");
+                    }
+                    // The normalizer has finished and released the staging store
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            emitter.flush();
+            if let Some(detected) = emitter.loops.as_mut().and_then(LoopDetector::finish) {
+                emitter.report_loop(detected);
+            }
+            emitter.report_metrics();
+        });
+    }
+}
+
+struct Emitter {
+    msg_store: Arc<MsgStore>,
+    /// Held-back updates of streaming entries, latest per index
+    pending: BTreeMap<usize, Patch>,
+    /// Updates of collapsed thinking blocks, held until the next entry starts
+    held_thinking: BTreeMap<usize, Patch>,
+    /// Indices of the normalizer's entries that were hidden
+    hidden: BTreeSet<usize>,
+    /// Ids the full versions of truncated entries are stored under, by index
+    full_entry_ids: BTreeMap<usize, Uuid>,
+    task_list: TaskListTracker,
+    /// Indices of the normalizer's entries followed by a task list entry
+    task_list_entries: BTreeSet<usize>,
+    /// Number of entries the pipeline added after each of the normalizer's
+    /// entries; a task list entry always comes first
+    inserted: BTreeMap<usize, usize>,
+    loops: Option<LoopDetector>,
+    /// Measures the run, unless its output was all there at the start
+    meter: Option<RunMeter>,
+    /// Index of the latest entry the normalizer added and was forwarded
+    last_index: Option<usize>,
+    clock: ReadClock,
+    /// Timestamps of the normalizer's entries, by index
+    timestamps: BTreeMap<usize, String>,
+}
+
+impl Emitter {
+    /// Stamp `entry` with the time the latest output was read, unless the
+    /// executor gave it a time of its own
+    fn stamp(&self, entry: &mut NormalizedEntry) {
+        if entry.timestamp.is_none() {
+            entry.timestamp = Some(self.clock.last_read());
+        }
+    }
+
+    /// `entry`, the normalizer's entry at `index`, as the conversation shows
+    /// it: cut short if the pipeline truncates outputs and it is too long, in
+    /// which case the full entry is stored, replacing an earlier version
+    fn truncate(
+        &mut self,
+        pipeline: &NormalizationPipeline,
+        index: usize,
+        entry: NormalizedEntry,
+    ) -> NormalizedEntry {
+        let Some(max_bytes) = pipeline.truncate_bytes else {
+            return entry;
+        };
+        let id = *self
+            .full_entry_ids
+            .entry(index)
+            .or_insert_with(Uuid::new_v4);
+        let Some(truncated) = truncate_entry(&entry, max_bytes, id) else {
+            return entry;
+        };
+        if let Err(e) = pipeline.full_entries.save(id, &entry) {
+            tracing::warn!("Failed to store full entry {id}: {e}");
+        }
+        truncated
+    }
+
+    fn flush_pending(&mut self) {
+        for (_, patch) in std::mem::take(&mut self.pending) {
+            self.msg_store.push_patch(patch);
+        }
+    }
+
+    fn flush(&mut self) {
+        let held = std::mem::take(&mut self.held_thinking);
+        for (_, patch) in held.into_iter().chain(std::mem::take(&mut self.pending)) {
+            self.msg_store.push_patch(patch);
+        }
+    }
+
+    /// Index of a normalizer entry in the execution's conversation, or `None`
+    /// if the entry is hidden
+    fn output_index(&self, index: usize) -> Option<usize> {
+        if self.hidden.contains(&index) {
+            return None;
+        }
+        Some(
+            index - self.hidden.range(..index).count()
+                + self.inserted.range(..index).map(|(_, n)| n).sum::<usize>(),
+        )
+    }
+
+    /// Publish the todo list if `entry`, the normalizer's entry at `index`,
+    /// changed it: as an update of the task list entry following it, or as a
+    /// new one if it is the latest entry. A list changed further up the
+    /// conversation is published with the next change.
+    fn track_task_list(&mut self, index: usize, entry: &NormalizedEntry) {
+        let Some(mut task_list_entry) = self
+            .task_list
+            .update(entry)
+            .map(|list| list.to_normalized_entry())
+        else {
+            return;
+        };
+        let Some(output_index) = self.output_index(index) else {
+            return;
+        };
+        self.stamp(&mut task_list_entry);
+        if self.task_list_entries.contains(&index) {
+            self.msg_store.push_patch(ConversationPatch::replace(
+                output_index + 1,
+                task_list_entry,
+            ));
+        } else if self.last_index == Some(index) && !self.inserted.contains_key(&index) {
+            self.task_list_entries.insert(index);
+            self.insert_after(index, task_list_entry);
+        }
+    }
+
+    /// Add `entry` to the conversation after the normalizer's entry at
+    /// `index` and what the pipeline added after it before
+    fn insert_after(&mut self, index: usize, mut entry: NormalizedEntry) {
+        let Some(output_index) = self.output_index(index) else {
+            return;
+        };
+        self.stamp(&mut entry);
+        let inserted = self.inserted.entry(index).or_default();
+        *inserted += 1;
+        self.msg_store
+            .push_patch(ConversationPatch::add_normalized_entry(
+                output_index + *inserted,
+                entry,
+            ));
+    }
+
+    fn detect_loops(&mut self, index: usize, entry: &NormalizedEntry, added: bool) {
+        if let Some(detected) = self
+            .loops
+            .as_mut()
+            .and_then(|loops| loops.observe(index, entry, added))
+        {
+            self.report_loop(detected);
+        }
+    }
+
+    /// Warn about `detected` after the latest entry
+    fn report_loop(&mut self, detected: DetectedLoop) {
+        if let Some(index) = self.last_index {
+            self.insert_after(index, detected.to_normalized_entry());
+        }
+    }
+
+    /// Add the run's metrics to the agent's summary of it, or in a summary
+    /// of their own after the latest entry
+    fn report_metrics(&mut self) {
+        let Some(meter) = self.meter.take() else {
+            return;
+        };
+        let metrics = self.clock.metrics(meter.output_tokens());
+        match meter.summary_with(&metrics) {
+            Some((index, entry)) => {
+                if let Some(output_index) = self.output_index(index) {
+                    self.msg_store
+                        .push_patch(ConversationPatch::replace(output_index, entry));
+                }
+            }
+            None => {
+                if let Some(index) = self.last_index {
+                    self.insert_after(index, metrics.to_normalized_entry());
+                }
+            }
+        }
+    }
+
+    /// Rewrite `/entries/<index>` paths of a patch the pipeline doesn't
+    /// otherwise interpret, dropping operations on hidden entries
+    fn renumber(&self, patch: Patch) -> Option<Patch> {
+        if self.hidden.is_empty() && self.inserted.is_empty() {
+            return Some(patch);
+        }
+        let mut operations = Vec::with_capacity(patch.0.len());
+        for operation in patch.0 {
+            let mut value = serde_json::to_value(&operation).ok()?;
+            let path = value.get("path").and_then(|path| path.as_str());
+            let index = path
+                .and_then(|path| path.strip_prefix("/entries/"))
+                .and_then(|rest| rest.split('/').next())
+                .and_then(|segment| segment.parse::<usize>().ok());
+            let Some(index) = index else {
+                operations.push(operation);
+                continue;
+            };
+            let Some(output_index) = self.output_index(index) else {
+                continue;
+            };
+            let rest = &path.unwrap_or_default()["/entries/".len() + index.to_string().len()..];
+            let path = format!("/entries/{output_index}{rest}");
+            value["path"] = path.into();
+            operations.push(serde_json::from_value(value).ok()?);
+        }
+        (!operations.is_empty()).then_some(Patch(operations))
+    }
+}
+
+enum EntryOp {
+    Add,
+    Replace,
+}
+
+/// Wall-clock time of the latest raw output read, derived from a monotonic
+/// clock so it never goes backwards
+#[derive(Debug, Clone)]
+struct ReadClock {
+    started_at: DateTime<Utc>,
+    start: Instant,
+    /// Milliseconds from `start` to the latest read
+    last_read_ms: Arc<AtomicU64>,
+    /// Milliseconds from `start` to the first output read live
+    first_output_ms: Arc<OnceLock<u64>>,
+    /// Milliseconds from `start` to the end of the output
+    finished_ms: Arc<OnceLock<u64>>,
+}
+
+impl ReadClock {
+    fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            start: Instant::now(),
+            last_read_ms: Arc::new(AtomicU64::new(0)),
+            first_output_ms: Arc::new(OnceLock::new()),
+            finished_ms: Arc::new(OnceLock::new()),
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    fn record_read(&self) {
+        self.last_read_ms
+            .fetch_max(self.elapsed_ms(), Ordering::Relaxed);
+    }
+
+    fn metrics(&self, output_tokens: u64) -> RunMetrics {
+        RunMetrics::new(
+            self.first_output_ms.get().copied(),
+            self.finished_ms
+                .get()
+                .copied()
+                .unwrap_or_else(|| self.elapsed_ms()),
+            output_tokens,
+        )
+    }
+
+    fn last_read(&self) -> String {
+        let elapsed =
+            chrono::Duration::milliseconds(self.last_read_ms.load(Ordering::Relaxed) as i64);
+        (self.started_at + elapsed).to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+}
+
+/// Copies the execution's messages into the staging store through the raw
+/// stages, noting when output was read
+struct Stager {
+    pipeline: Arc<NormalizationPipeline>,
+    staging: Arc<MsgStore>,
+    merger: Option<LineMerger>,
+    clock: ReadClock,
+    /// Whether messages are read as the execution produces them, rather than
+    /// replayed from its history
+    live: bool,
+}
+
+impl Stager {
+    fn push(&mut self, msg: LogMsg) {
+        if self.live && matches!(msg, LogMsg::Finished) {
+            self.clock
+                .finished_ms
+                .get_or_init(|| self.clock.elapsed_ms());
+        }
+        let msg = match (msg, &mut self.merger) {
+            (msg @ (LogMsg::Stdout(_) | LogMsg::Stderr(_)), merger) => {
+                self.clock.record_read();
+                if self.live {
+                    self.clock
+                        .first_output_ms
+                        .get_or_init(|| self.clock.elapsed_ms());
+                }
+                match merger {
+                    Some(merger) => match merger.push(msg) {
+                        Some(lines) => LogMsg::Stdout(lines),
+                        None => return,
+                    },
+                    None => msg,
+                }
+            }
+            (LogMsg::Finished, Some(merger)) => {
+                if let Some(rest) = merger.finish() {
+                    self.staging
+                        .push(self.pipeline.process_raw_msg(LogMsg::Stdout(rest)));
+                }
+                LogMsg::Finished
+            }
+            (msg, _) => msg,
+        };
+        self.staging.push(self.pipeline.process_raw_msg(msg));
+    }
+}
+
+/// Joins stdout and stderr into one stream of whole lines, in the order the
+/// lines complete
+#[derive(Debug, Default)]
+struct LineMerger {
+    stdout: String,
+    stderr: String,
+}
+
+impl LineMerger {
+    /// Lines `msg` completes, if any; the rest of a line waits for its end
+    fn push(&mut self, msg: LogMsg) -> Option<String> {
+        let (buffer, text) = match msg {
+            LogMsg::Stdout(text) => (&mut self.stdout, text),
+            LogMsg::Stderr(text) => (&mut self.stderr, text),
+            _ => return None,
+        };
+        buffer.push_str(&text);
+        let end = buffer.rfind('\n')? + 1;
+        Some(buffer.drain(..end).collect())
+    }
+
+    /// Unfinished lines left once the output ends
+    fn finish(&mut self) -> Option<String> {
+        let mut rest = std::mem::take(&mut self.stdout);
+        if !rest.is_empty() && !self.stderr.is_empty() {
+            rest.push('\n');
+        }
+        rest.push_str(&std::mem::take(&mut self.stderr));
+        (!rest.is_empty()).then_some(rest)
+    }
+}
+
+fn entry_patch(patch: &Patch) -> Option<(EntryOp, usize, NormalizedEntry)> {
+    let [operation] = patch.0.as_slice() else {
+        return None;
+    };
+    let op = match operation {
+        json_patch::PatchOperation::Add(_) => EntryOp::Add,
+        json_patch::PatchOperation::Replace(_) => EntryOp::Replace,
+        _ => return None,
+    };
+    let (index, entry) = extract_normalized_entry_from_patch(patch)?;
+    Some((op, index, entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::utils::patch::latest_normalized_entries;
+
+    fn system_entry(content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::SystemMessage,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_stages_apply_in_order() {
+        let pipeline = NormalizationPipeline::new(&[
+            NormalizationStage::StripAnsi,
+            NormalizationStage::Redact {
+                patterns: vec!["hunter2".to_string()],
+                builtin_patterns: true,
+            },
+            NormalizationStage::Classify {
+                rules: vec![ClassificationRule {
+                    pattern: "(?i)not logged in".to_string(),
+                    entry_type: ClassifiedAs::SetupRequired,
+                }],
+            },
+        ]);
+
+        assert_eq!(
+            pipeline.process_raw("\x1b[31mkey sk-ant-REDACTED\x1b[0m hunter2"),
+            "key [REDACTED] [REDACTED]"
+        );
+
+        let entry = pipeline.process_entry(system_entry("Not logged in; run login"));
+        assert!(matches!(
+            entry.entry_type,
+            NormalizedEntryType::ErrorMessage {
+                error_type: NormalizedEntryError::SetupRequired
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_updates_held_until_next_entry_without_delta_streaming() {
+        let msg_store = Arc::new(MsgStore::new());
+        let pipeline = NormalizationPipeline::new(&[NormalizationStage::Redact {
+            patterns: vec!["secret".to_string()],
+            builtin_patterns: false,
+        }]);
+
+        pipeline.run(msg_store.clone(), |staging| {
+            tokio::spawn(async move {
+                staging.push_patch(ConversationPatch::add_normalized_entry(
+                    0,
+                    system_entry("a"),
+                ));
+                staging.push_patch(ConversationPatch::replace(0, system_entry("ab")));
+                staging.push_patch(ConversationPatch::replace(0, system_entry("ab secret")));
+                staging.push_patch(ConversationPatch::add_normalized_entry(
+                    1,
+                    system_entry("next"),
+                ));
+            });
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let history = msg_store.get_history();
+        // add, one coalesced replace, add
+        assert_eq!(history.len(), 3);
+        let entries = latest_normalized_entries(&history);
+        assert_eq!(entries[&0].content, "ab [REDACTED]");
+        assert_eq!(entries[&1].content, "next");
+    }
+
+    #[tokio::test]
+    async fn test_thinking_visibility() {
+        fn thinking(content: &str) -> NormalizedEntry {
+            NormalizedEntry {
+                entry_type: NormalizedEntryType::Thinking,
+                ..system_entry(content)
+            }
+        }
+
+        async fn run(visibility: ThinkingVisibility) -> Vec<LogMsg> {
+            let msg_store = Arc::new(MsgStore::new());
+            NormalizationPipeline::new(&[NormalizationStage::DeltaStreaming])
+                .with_thinking_visibility(visibility)
+                .run(msg_store.clone(), |staging| {
+                    tokio::spawn(async move {
+                        staging.push_patch(ConversationPatch::add_normalized_entry(
+                            0,
+                            system_entry("start"),
+                        ));
+                        staging
+                            .push_patch(ConversationPatch::add_normalized_entry(1, thinking("a")));
+                        staging.push_patch(ConversationPatch::replace(1, thinking("ab")));
+                        staging.push_patch(ConversationPatch::replace(1, thinking("abc")));
+                        staging.push_patch(ConversationPatch::add_normalized_entry(
+                            2,
+                            system_entry("answer"),
+                        ));
+                        staging.push_patch(ConversationPatch::replace(2, system_entry("answer!")));
+                    });
+                });
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            msg_store.get_history()
+        }
+
+        // add, add, replace with the complete block, add, replace
+        let history = run(ThinkingVisibility::Collapse).await;
+        assert_eq!(history.len(), 5);
+        assert_eq!(latest_normalized_entries(&history)[&1].content, "abc");
+
+        let history = run(ThinkingVisibility::Hide).await;
+        let entries = latest_normalized_entries(&history);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[&1].content, "answer!");
+    }
+
+    #[tokio::test]
+    async fn test_task_list_entries_follow_todo_updates() {
+        fn todo_write(todos: &[(&str, &str)]) -> NormalizedEntry {
+            let todos = todos
+                .iter()
+                .map(|(content, status)| crate::logs::TodoItem {
+                    content: content.to_string(),
+                    status: status.to_string(),
+                    priority: None,
+                })
+                .collect();
+            NormalizedEntry {
+                entry_type: NormalizedEntryType::ToolUse {
+                    tool_name: "TodoWrite".to_string(),
+                    action_type: crate::logs::ActionType::TodoManagement {
+                        todos,
+                        operation: "write".to_string(),
+                    },
+                    status: crate::logs::ToolStatus::Created,
+                },
+                ..system_entry("TODO list updated")
+            }
+        }
+
+        let msg_store = Arc::new(MsgStore::new());
+        NormalizationPipeline::new(&[NormalizationStage::DeltaStreaming])
+            .with_thinking_visibility(ThinkingVisibility::Hide)
+            .run(msg_store.clone(), |staging| {
+                tokio::spawn(async move {
+                    let thinking = NormalizedEntry {
+                        entry_type: NormalizedEntryType::Thinking,
+                        ..system_entry("plan")
+                    };
+                    let first = todo_write(&[("Read", "pending"), ("Build", "in_progress")]);
+                    let entries = [
+                        system_entry("start"),
+                        thinking,
+                        first.clone(),
+                        system_entry("working"),
+                    ];
+                    for (index, entry) in entries.into_iter().enumerate() {
+                        staging.push_patch(ConversationPatch::add_normalized_entry(index, entry));
+                    }
+                    // The call finishing doesn't change the list
+                    let finished = first.with_tool_status(crate::logs::ToolStatus::Success);
+                    staging.push_patch(ConversationPatch::replace(2, finished.unwrap()));
+                    staging.push_patch(ConversationPatch::add_normalized_entry(
+                        4,
+                        todo_write(&[("Read", "done"), ("Build", "In Progress")]),
+                    ));
+                    staging.push_patch(ConversationPatch::replace(
+                        4,
+                        todo_write(&[("Read", "completed"), ("Build", "completed")]),
+                    ));
+                    staging.push_patch(ConversationPatch::add_normalized_entry(
+                        5,
+                        system_entry("end"),
+                    ));
+                });
+            });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let entries = latest_normalized_entries(&msg_store.get_history());
+        let contents: Vec<_> = entries
+            .values()
+            .map(|entry| entry.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                "start",
+                "TODO list updated",
+                "0/2 tasks completed, working on: Build",
+                "working",
+                "TODO list updated",
+                "2/2 tasks completed",
+                "end",
+            ]
+        );
+        let NormalizedEntryType::TaskListUpdate { task_list } = &entries[&2].entry_type else {
+            panic!("expected a task list at 2, got {:?}", entries[&2]);
+        };
+        assert_eq!(
+            task_list.items[1].status,
+            crate::logs::task_list::TaskListStatus::InProgress
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merged_streams_keep_arrival_order_and_read_times() {
+        use futures::StreamExt;
+
+        let msg_store = Arc::new(MsgStore::new());
+        msg_store.push_stdout(r#"{"type":"#);
+        NormalizationPipeline::new(&[NormalizationStage::MergeStreams]).run(
+            msg_store.clone(),
+            |staging| {
+                tokio::spawn(async move {
+                    let mut lines = staging.stdout_lines_stream();
+                    let mut index = 0;
+                    while let Some(Ok(line)) = lines.next().await {
+                        staging.push_patch(ConversationPatch::add_normalized_entry(
+                            index,
+                            system_entry(&line),
+                        ));
+                        index += 1;
+                    }
+                });
+            },
+        );
+        // A warning in the middle of an event waits for the event's line to end
+        msg_store.push_stderr("npm warn deprecated\n");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        msg_store.push_stdout("\"result\"}\n");
+        msg_store.push_stderr("exiting");
+        msg_store.push_finished();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let entries = latest_normalized_entries(&msg_store.get_history());
+        let contents: Vec<_> = entries
+            .values()
+            .take(3)
+            .map(|entry| entry.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            ["npm warn deprecated", r#"{"type":"result"}"#, "exiting"]
+        );
+        // The run's metrics follow once the output ends
+        assert!(matches!(
+            entries[&3].entry_type,
+            NormalizedEntryType::RunSummary { .. }
+        ));
+        let times: Vec<_> = entries
+            .values()
+            .map(|entry| DateTime::parse_from_rfc3339(entry.timestamp.as_deref().unwrap()).unwrap())
+            .collect();
+        assert!(times.is_sorted());
+        assert!(times[1] > times[0]);
+    }
+
+    #[tokio::test]
+    async fn test_hidden_kinds_and_truncated_outputs() {
+        let msg_store = Arc::new(MsgStore::new());
+        let mut pipeline = NormalizationPipeline::new(&[
+            NormalizationStage::DeltaStreaming,
+            NormalizationStage::HideEntries {
+                kinds: vec![EntryKind::System],
+            },
+            NormalizationStage::TruncateOutputs { max_kb: 1 },
+        ]);
+        let dir = std::env::temp_dir().join(format!("vk-full-entries-{}", Uuid::new_v4()));
+        pipeline.full_entries = FullEntryStore::in_dir(dir.clone());
+        let message = |content: String| NormalizedEntry {
+            entry_type: NormalizedEntryType::AssistantMessage,
+            ..system_entry(&content)
+        };
+        let long = "x".repeat(3000);
+        pipeline.run(msg_store.clone(), {
+            let long = long.clone();
+            move |staging| {
+                tokio::spawn(async move {
+                    staging.push_patch(ConversationPatch::add_normalized_entry(
+                        0,
+                        system_entry("init"),
+                    ));
+                    staging.push_patch(ConversationPatch::add_normalized_entry(
+                        1,
+                        message("short".to_string()),
+                    ));
+                    staging.push_patch(ConversationPatch::replace(1, message(long)));
+                });
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let entries = latest_normalized_entries(&msg_store.get_history());
+        assert_eq!(entries.len(), 1);
+        let shown = &entries[&0];
+        assert_eq!(
+            shown.content,
+            format!("{}\n… [2 KB more, expand to see all]", "x".repeat(1024))
+        );
+        let id = shown.metadata.as_ref().unwrap()["full_entry_id"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let full = FullEntryStore::in_dir(dir.clone())
+            .load(id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(full.content, long);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_metrics_added_to_agent_summary() {
+        use futures::StreamExt;
+
+        use crate::logs::RunSummary;
+
+        let msg_store = Arc::new(MsgStore::new());
+        NormalizationPipeline::new(&[NormalizationStage::DeltaStreaming]).run(
+            msg_store.clone(),
+            |staging| {
+                tokio::spawn(async move {
+                    let mut lines = staging.stdout_lines_stream();
+                    let mut index = 0;
+                    while let Some(Ok(line)) = lines.next().await {
+                        let entry = match line.as_str() {
+                            "summary" => NormalizedEntry {
+                                entry_type: NormalizedEntryType::RunSummary {
+                                    summary: RunSummary {
+                                        tasks_completed: Some(2),
+                                        tasks_total: Some(2),
+                                        ..Default::default()
+                                    },
+                                },
+                                ..system_entry("2/2 tasks completed")
+                            },
+                            _ => NormalizedEntry {
+                                entry_type: NormalizedEntryType::AssistantMessage,
+                                ..system_entry(&"x".repeat(400))
+                            },
+                        };
+                        staging.push_patch(ConversationPatch::add_normalized_entry(index, entry));
+                        index += 1;
+                    }
+                });
+            },
+        );
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        msg_store.push_stdout("message\n");
+        tokio::time::sleep(Duration::from_millis(2000)).await;
+        msg_store.push_stdout("summary\n");
+        msg_store.push_finished();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let entries = latest_normalized_entries(&msg_store.get_history());
+        assert_eq!(entries.len(), 2);
+        let NormalizedEntryType::RunSummary { summary } = &entries[&1].entry_type else {
+            panic!("expected a run summary, got {:?}", entries[&1]);
+        };
+        assert_eq!(summary.tasks_completed, Some(2));
+        // 100 tokens over the two seconds from the first output to the end
+        assert_eq!(
+            summary.metrics,
+            Some(RunMetrics {
+                time_to_first_output_ms: Some(1500),
+                output_tokens: 100,
+                tokens_per_sec: Some(50.0),
+                wall_time_ms: 3500,
+            })
+        );
+        assert_eq!(
+            entries[&1].content,
+            "2/2 tasks completed\nFirst output after 1.5s, ~50.0 tokens/s, 3.5s in total"
+        );
+    }
+}
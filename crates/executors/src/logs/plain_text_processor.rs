@@ -407,6 +407,8 @@ mod tests {
                 entry_type: NormalizedEntryType::SystemMessage,
                 content: content.to_string(),
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             }
         };
 
@@ -436,6 +438,8 @@ mod tests {
                     },
                     content,
                     metadata: None,
+                    agent_id: None,
+                    agent_role: None,
                 }
             } else {
                 NormalizedEntry {
@@ -443,6 +447,8 @@ mod tests {
                     entry_type: NormalizedEntryType::SystemMessage,
                     content: content.to_string(),
                     metadata: None,
+                    agent_id: None,
+                    agent_role: None,
                 }
             }
         };
@@ -464,6 +470,8 @@ mod tests {
                 entry_type: NormalizedEntryType::SystemMessage,
                 content,
                 metadata: None,
+                agent_id: None,
+                agent_role: None,
             }
         };
 
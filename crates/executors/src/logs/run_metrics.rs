@@ -0,0 +1,194 @@
+//! Responsiveness of a run, measured by the normalization pipeline.
+//!
+//! While an execution runs, the pipeline notes when the agent's first output
+//! was read and when its output ended, and estimates the tokens of the
+//! messages and thinking blocks it produced. Once the run is over the
+//! [`RunMetrics`] are added to the conversation as part of a `RunSummary`
+//! entry: the agent's own summary if it reported one, otherwise a new entry
+//! at the end. Runs normalized from stored logs are not measured, as their
+//! output is read all at once.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::logs::{NormalizedEntry, NormalizedEntryType, RunSummary, transcript::estimate_tokens};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+pub struct RunMetrics {
+    /// From the start of the run to the agent's first output; `None` if it
+    /// produced none
+    pub time_to_first_output_ms: Option<u64>,
+    /// Estimated tokens of the agent's messages and thinking
+    pub output_tokens: u64,
+    /// Output tokens per second between the first output and the end
+    pub tokens_per_sec: Option<f64>,
+    pub wall_time_ms: u64,
+}
+
+impl RunMetrics {
+    pub fn new(first_output_ms: Option<u64>, wall_time_ms: u64, output_tokens: u64) -> Self {
+        let tokens_per_sec = first_output_ms
+            .map(|first| wall_time_ms.saturating_sub(first))
+            .filter(|&generating_ms| generating_ms > 0 && output_tokens > 0)
+            .map(|generating_ms| output_tokens as f64 * 1000.0 / generating_ms as f64);
+        Self {
+            time_to_first_output_ms: first_output_ms,
+            output_tokens,
+            tokens_per_sec,
+            wall_time_ms,
+        }
+    }
+
+    pub fn to_normalized_entry(&self) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::RunSummary {
+                summary: RunSummary {
+                    metrics: Some(self.clone()),
+                    ..Default::default()
+                },
+            },
+            content: self.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+impl fmt::Display for RunMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ms) = self.time_to_first_output_ms {
+            write!(f, "First output after {:.1}s, ", ms as f64 / 1000.0)?;
+        }
+        if let Some(rate) = self.tokens_per_sec {
+            write!(f, "~{rate:.1} tokens/s, ")?;
+        }
+        write!(f, "{:.1}s in total", self.wall_time_ms as f64 / 1000.0)
+    }
+}
+
+/// What the pipeline has seen of a run's entries so far
+#[derive(Debug, Default)]
+pub(crate) struct RunMeter {
+    /// Estimated tokens of the latest version of each message and thinking
+    /// block, by the normalizer's index
+    output_tokens: BTreeMap<usize, u64>,
+    /// The agent's own summary of the run, with its index
+    summary: Option<(usize, NormalizedEntry)>,
+}
+
+impl RunMeter {
+    /// Note the entry at `index`, added or updated
+    pub(crate) fn observe(&mut self, index: usize, entry: &NormalizedEntry) {
+        match entry.entry_type {
+            NormalizedEntryType::AssistantMessage | NormalizedEntryType::Thinking => {
+                self.output_tokens
+                    .insert(index, estimate_tokens(&entry.content) as u64);
+            }
+            NormalizedEntryType::RunSummary { .. } => {
+                self.summary = Some((index, entry.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn output_tokens(&self) -> u64 {
+        self.output_tokens.values().sum()
+    }
+
+    /// The agent's summary with `metrics` added, and its index, if it
+    /// reported one
+    pub(crate) fn summary_with(&self, metrics: &RunMetrics) -> Option<(usize, NormalizedEntry)> {
+        let (index, mut entry) = self.summary.clone()?;
+        if let NormalizedEntryType::RunSummary { summary } = &mut entry.entry_type {
+            summary.metrics = Some(metrics.clone());
+        }
+        entry.content = format!("{}\n{metrics}", entry.content);
+        Some((index, entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(entry_type: NormalizedEntryType, content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_rate_covers_the_time_after_the_first_output() {
+        let metrics = RunMetrics::new(Some(500), 2_500, 100);
+        assert_eq!(metrics.tokens_per_sec, Some(50.0));
+        assert_eq!(
+            metrics.to_string(),
+            "First output after 0.5s, ~50.0 tokens/s, 2.5s in total"
+        );
+
+        // No rate without output, or without time to produce it in
+        let silent = RunMetrics::new(None, 1_000, 0);
+        assert_eq!(silent.tokens_per_sec, None);
+        assert_eq!(silent.to_string(), "1.0s in total");
+        assert_eq!(RunMetrics::new(Some(1_000), 1_000, 10).tokens_per_sec, None);
+        assert_eq!(RunMetrics::new(Some(100), 1_000, 0).tokens_per_sec, None);
+
+        let NormalizedEntryType::RunSummary { summary } = metrics.to_normalized_entry().entry_type
+        else {
+            panic!("metrics are not a run summary");
+        };
+        assert_eq!(summary.metrics, Some(metrics));
+    }
+
+    #[test]
+    fn test_meter_counts_the_latest_version_of_each_entry() {
+        let mut meter = RunMeter::default();
+        meter.observe(0, &entry(NormalizedEntryType::AssistantMessage, "Look"));
+        // The message streams in; only its final text counts
+        meter.observe(
+            0,
+            &entry(NormalizedEntryType::AssistantMessage, "Looking at it"),
+        );
+        meter.observe(1, &entry(NormalizedEntryType::Thinking, "Hmm"));
+        meter.observe(
+            2,
+            &entry(NormalizedEntryType::UserMessage, "Fix the login page"),
+        );
+        assert_eq!(meter.output_tokens(), 4 + 1);
+
+        let metrics = RunMetrics::new(Some(100), 1_100, meter.output_tokens());
+        assert!(meter.summary_with(&metrics).is_none());
+        meter.observe(
+            3,
+            &entry(
+                NormalizedEntryType::RunSummary {
+                    summary: RunSummary {
+                        tasks_completed: Some(2),
+                        ..Default::default()
+                    },
+                },
+                "2 tasks completed",
+            ),
+        );
+        let (index, summary) = meter.summary_with(&metrics).unwrap();
+        assert_eq!(index, 3);
+        assert_eq!(
+            summary.content,
+            "2 tasks completed\nFirst output after 0.1s, ~5.0 tokens/s, 1.1s in total"
+        );
+        assert!(matches!(
+            summary.entry_type,
+            NormalizedEntryType::RunSummary { summary } if summary.tasks_completed == Some(2)
+                && summary.metrics == Some(metrics)
+        ));
+    }
+}
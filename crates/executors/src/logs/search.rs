@@ -0,0 +1,346 @@
+//! Searching the normalized entries of an execution.
+//!
+//! A [`LogSearchIndex`] keeps the latest version of every entry together with
+//! its lowercased text, and is updated message by message, so searching a
+//! long session doesn't re-parse its patches. Entries are matched by kind,
+//! time and text. Executors rarely timestamp entries, so entries without a
+//! timestamp use the time the index saw them; entries indexed from stored
+//! history have no time and never match a time range.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use ts_rs::TS;
+use uuid::Uuid;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::logs::{
+    NormalizedEntry, NormalizedEntryType, utils::patch::extract_normalized_entry_from_patch,
+};
+
+const DEFAULT_LIMIT: usize = 200;
+
+/// Indexes of live executions, kept up to date while they run and evicted
+/// once they finish
+static INDEXES: LazyLock<RwLock<HashMap<Uuid, Arc<RwLock<LogSearchIndex>>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Message,
+    ToolUse,
+    Error,
+    Thinking,
+    System,
+}
+
+impl EntryKind {
+    pub(crate) fn of(entry_type: &NormalizedEntryType) -> Option<Self> {
+        match entry_type {
+            NormalizedEntryType::UserMessage
+            | NormalizedEntryType::UserFeedback { .. }
+            | NormalizedEntryType::AssistantMessage => Some(Self::Message),
+            NormalizedEntryType::ToolUse { .. } | NormalizedEntryType::RunHook { .. } => {
+                Some(Self::ToolUse)
+            }
+            NormalizedEntryType::ErrorMessage { .. } => Some(Self::Error),
+            NormalizedEntryType::Thinking => Some(Self::Thinking),
+            NormalizedEntryType::SystemMessage
+            | NormalizedEntryType::RawOutput
+            | NormalizedEntryType::RunSummary { .. }
+            | NormalizedEntryType::WorkspaceChanges { .. }
+            | NormalizedEntryType::ReviewFinding { .. }
+            | NormalizedEntryType::TaskListUpdate { .. } => Some(Self::System),
+            NormalizedEntryType::LoopDetected { .. } => Some(Self::Error),
+            // UI placeholders rather than log content
+            NormalizedEntryType::Loading | NormalizedEntryType::NextAction { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+pub struct LogSearchQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<EntryKind>,
+    /// Case-insensitive text to find in the entry or its tool name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+    /// Index of the entry to continue after
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct LogSearchHit {
+    pub index: usize,
+    pub kind: EntryKind,
+    pub entry: NormalizedEntry,
+}
+
+#[derive(Debug, Clone)]
+struct IndexedEntry {
+    entry: NormalizedEntry,
+    kind: EntryKind,
+    time: Option<DateTime<Utc>>,
+    text: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogSearchIndex {
+    entries: BTreeMap<usize, IndexedEntry>,
+}
+
+impl LogSearchIndex {
+    /// Index of a log history whose entries have no time
+    pub fn from_history<'a>(history: impl IntoIterator<Item = &'a LogMsg>) -> Self {
+        let mut index = Self::default();
+        for msg in history {
+            index.apply(msg, None);
+        }
+        index
+    }
+
+    /// Index `msg`, attributing untimestamped entries to `seen_at`. Applying
+    /// a message twice has no further effect.
+    pub fn apply(&mut self, msg: &LogMsg, seen_at: Option<DateTime<Utc>>) {
+        let LogMsg::JsonPatch(patch) = msg else {
+            return;
+        };
+        let Some((index, entry)) = extract_normalized_entry_from_patch(patch) else {
+            return;
+        };
+        let Some(kind) = EntryKind::of(&entry.entry_type) else {
+            self.entries.remove(&index);
+            return;
+        };
+        let time = entry
+            .timestamp
+            .as_deref()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Utc))
+            // Replacements keep the time the entry first appeared
+            .or_else(|| self.entries.get(&index).and_then(|indexed| indexed.time))
+            .or(seen_at);
+        let mut text = entry.content.to_lowercase();
+        if let NormalizedEntryType::ToolUse { tool_name, .. } = &entry.entry_type {
+            text.push('\n');
+            text.push_str(&tool_name.to_lowercase());
+        }
+        self.entries.insert(
+            index,
+            IndexedEntry {
+                entry,
+                kind,
+                time,
+                text,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries matching `query`, in conversation order
+    pub fn search(&self, query: &LogSearchQuery) -> Vec<LogSearchHit> {
+        let text = query.text.as_deref().map(str::to_lowercase);
+        let start = query.after.map_or(0, |after| after + 1);
+        self.entries
+            .range(start..)
+            .filter(|(_, indexed)| {
+                query.kind.is_none_or(|kind| kind == indexed.kind)
+                    && text
+                        .as_deref()
+                        .is_none_or(|text| indexed.text.contains(text))
+                    && (query.since.is_none() && query.until.is_none()
+                        || indexed.time.is_some_and(|time| {
+                            query.since.is_none_or(|since| time >= since)
+                                && query.until.is_none_or(|until| time <= until)
+                        }))
+            })
+            .take(query.limit.unwrap_or(DEFAULT_LIMIT))
+            .map(|(index, indexed)| LogSearchHit {
+                index: *index,
+                kind: indexed.kind,
+                entry: indexed.entry.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Search index of a live execution's store, created on first use and kept
+/// up to date until the store finishes. Finished stores are indexed from
+/// their history on every call.
+pub fn live_search_index(execution_id: Uuid, msg_store: &MsgStore) -> Arc<RwLock<LogSearchIndex>> {
+    if let Some(index) = INDEXES.read().unwrap().get(&execution_id) {
+        return index.clone();
+    }
+    let mut indexes = INDEXES.write().unwrap();
+    if let Some(index) = indexes.get(&execution_id) {
+        return index.clone();
+    }
+
+    // Subscribe before reading the history so nothing falls in between;
+    // messages seen twice don't change the index
+    let mut rx = msg_store.get_receiver();
+    let history = msg_store.get_history();
+    let index = Arc::new(RwLock::new(LogSearchIndex::from_history(&history)));
+    if history.iter().any(|msg| matches!(msg, LogMsg::Finished)) {
+        return index;
+    }
+    let live = index.clone();
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(LogMsg::Finished) | Err(RecvError::Closed) => break,
+                Ok(msg) => live.write().unwrap().apply(&msg, Some(Utc::now())),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(
+                        "Search index of execution {execution_id} skipped {skipped} messages"
+                    );
+                }
+            }
+        }
+        INDEXES.write().unwrap().remove(&execution_id);
+    });
+    indexes.insert(execution_id, index.clone());
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::{ActionType, ToolStatus, utils::patch::ConversationPatch};
+
+    fn entry(entry_type: NormalizedEntryType, content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_search_by_kind_text_and_time() {
+        let bash = |status| NormalizedEntryType::ToolUse {
+            tool_name: "Bash".to_string(),
+            action_type: ActionType::CommandRun {
+                command: "cargo test".to_string(),
+                result: None,
+            },
+            status,
+        };
+        let mut index = LogSearchIndex::default();
+        let earlier = Utc::now() - chrono::Duration::minutes(5);
+        index.apply(
+            &LogMsg::JsonPatch(ConversationPatch::add_normalized_entry(
+                0,
+                entry(NormalizedEntryType::AssistantMessage, "Running the Tests"),
+            )),
+            Some(earlier),
+        );
+        index.apply(
+            &LogMsg::JsonPatch(ConversationPatch::add_normalized_entry(
+                1,
+                entry(bash(ToolStatus::Created), "cargo test"),
+            )),
+            Some(Utc::now()),
+        );
+        // The tool finishing replaces its entry
+        index.apply(
+            &LogMsg::JsonPatch(ConversationPatch::replace(
+                1,
+                entry(bash(ToolStatus::Success), "cargo test"),
+            )),
+            Some(Utc::now()),
+        );
+        assert_eq!(index.len(), 2);
+
+        let hits = index.search(&LogSearchQuery {
+            text: Some("TEST".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(hits.iter().map(|hit| hit.index).collect::<Vec<_>>(), [0, 1]);
+
+        let tools = index.search(&LogSearchQuery {
+            kind: Some(EntryKind::ToolUse),
+            text: Some("bash".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(tools.len(), 1);
+        assert!(matches!(
+            tools[0].entry.entry_type,
+            NormalizedEntryType::ToolUse {
+                status: ToolStatus::Success,
+                ..
+            }
+        ));
+
+        let recent = index.search(&LogSearchQuery {
+            since: Some(earlier + chrono::Duration::minutes(1)),
+            ..Default::default()
+        });
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].index, 1);
+        assert!(
+            index
+                .search(&LogSearchQuery {
+                    after: Some(1),
+                    ..Default::default()
+                })
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_live_index_evicted_when_finished() {
+        let execution_id = Uuid::new_v4();
+        let store = MsgStore::new();
+        let index = live_search_index(execution_id, &store);
+        assert!(INDEXES.read().unwrap().contains_key(&execution_id));
+
+        store.push_patch(ConversationPatch::add_normalized_entry(
+            0,
+            entry(NormalizedEntryType::AssistantMessage, "Done"),
+        ));
+        store.push_finished();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while INDEXES.read().unwrap().contains_key(&execution_id) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("index of a finished execution is evicted");
+        assert_eq!(index.read().unwrap().len(), 1);
+
+        // A finished store is indexed from its history without being kept
+        assert_eq!(
+            live_search_index(execution_id, &store)
+                .read()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(!INDEXES.read().unwrap().contains_key(&execution_id));
+    }
+}
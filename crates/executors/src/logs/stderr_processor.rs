@@ -8,10 +8,21 @@
 //! normalize_stderr_logs(msg_store.clone(), EntryIndexProvider::new());
 //! ```
 //!
-use std::{sync::Arc, time::Duration};
+//! Stderr is also matched against known failure patterns (auth failures, rate
+//! limits, context overflows, OOM, resource limits, network errors, missing binaries).
+//! Matching entries carry the [`ExecutorFailureReason`] in their metadata, and
+//! [`ExecutorFailureReason::from_history`] tells the orchestrator why a run
+//! failed once it exits, e.g. to retry rate limits but not auth errors.
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
 
 use futures::StreamExt;
-use workspace_utils::msg_store::MsgStore;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
 
 use super::{
     NormalizedEntry, NormalizedEntryError, NormalizedEntryType,
@@ -19,6 +30,111 @@ use super::{
 };
 use crate::logs::utils::EntryIndexProvider;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutorFailureReason {
+    AuthFailure,
+    RateLimited,
+    OutOfMemory,
+    /// A CPU time or process limit was hit
+    ResourceLimit,
+    Network,
+    MissingBinary,
+    /// The conversation outgrew the model's context window
+    ContextOverflow,
+}
+
+/// Stdout chunks at the end of a run searched for a context overflow or a
+/// rate limit
+const ERROR_STDOUT_TAIL: usize = 20;
+
+/// Rate limits as the API reports them in the JSON agents print to stdout;
+/// other mentions of rate limits there are as likely to be code
+static STDOUT_RATE_LIMIT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)rate_limit_error|overloaded_error|api error\W{0,3}(429|529)\b").unwrap()
+});
+
+/// Patterns per reason, most specific first: a chunk matching several
+/// patterns gets the first reason
+static FAILURE_PATTERNS: LazyLock<Vec<(ExecutorFailureReason, Regex)>> = LazyLock::new(|| {
+    [
+        (
+            ExecutorFailureReason::AuthFailure,
+            r"invalid (api|x-api)[ _-]?key|authentication (failed|error|required)|not logged in|please (log ?in|run .*login)|(oauth )?token (has )?expired|(status|code|error)\W{0,3}401\b",
+        ),
+        (
+            ExecutorFailureReason::OutOfMemory,
+            r"out of memory|heap out of memory|cannot allocate memory|std::bad_alloc|\boom[ -]?killed\b",
+        ),
+        (
+            ExecutorFailureReason::ResourceLimit,
+            r"cpu time limit exceeded|fork: retry|cannot fork|fork failed|spawn \S+ eagain|pthread_create.*resource temporarily unavailable",
+        ),
+        (
+            ExecutorFailureReason::MissingBinary,
+            r"command not found|is not recognized as an internal or external command|spawn \S+ enoent|not found in path",
+        ),
+        (
+            ExecutorFailureReason::ContextOverflow,
+            r"prompt is too long|context[ _-]length[ _-]exceeded|maximum context length|exceeds? the (model's )?context window|context window (is )?(full|exceeded)|input is too long",
+        ),
+        (
+            ExecutorFailureReason::RateLimited,
+            r"rate[ _-]?limit|too many requests|quota exceeded|usage limit|overloaded|(status|code|error)\W{0,3}(429|529)\b",
+        ),
+        (
+            ExecutorFailureReason::Network,
+            r"econnrefused|econnreset|etimedout|enotfound|eai_again|socket hang up|network error|fetch failed|connection (refused|reset|timed out)|getaddrinfo",
+        ),
+    ]
+    .into_iter()
+    .map(|(reason, pattern)| (reason, Regex::new(&format!("(?i){pattern}")).unwrap()))
+    .collect()
+});
+
+impl ExecutorFailureReason {
+    /// Failure reason a piece of stderr points to, if any
+    pub fn classify(stderr: &str) -> Option<Self> {
+        FAILURE_PATTERNS
+            .iter()
+            .find(|(_, pattern)| pattern.is_match(stderr))
+            .map(|(reason, _)| *reason)
+    }
+
+    /// Reason of the last classified stderr output in a log history, or a
+    /// context overflow or rate limit reported at the end of stdout, where
+    /// agents speaking JSON put their errors
+    pub fn from_history(history: &[LogMsg]) -> Option<Self> {
+        history
+            .iter()
+            .rev()
+            .find_map(|msg| match msg {
+                LogMsg::Stderr(stderr) => Self::classify(stderr),
+                _ => None,
+            })
+            .or_else(|| {
+                history
+                    .iter()
+                    .rev()
+                    .filter_map(|msg| match msg {
+                        LogMsg::Stdout(stdout) => Some(stdout),
+                        _ => None,
+                    })
+                    .take(ERROR_STDOUT_TAIL)
+                    .find_map(|stdout| match Self::classify(stdout) {
+                        Some(Self::ContextOverflow) => Some(Self::ContextOverflow),
+                        _ if STDOUT_RATE_LIMIT.is_match(stdout) => Some(Self::RateLimited),
+                        _ => None,
+                    })
+            })
+    }
+
+    /// Whether running again later may succeed
+    pub fn is_transient(self) -> bool {
+        matches!(self, Self::RateLimited | Self::Network)
+    }
+}
+
 /// Standard stderr log normalizer that uses PlainTextLogProcessor to stream error logs.
 ///
 /// Splits stderr output into discrete entries based on a latency threshold (2s) to group
@@ -48,7 +164,10 @@ pub fn normalize_stderr_logs(msg_store: Arc<MsgStore>, entry_index_provider: Ent
                     error_type: NormalizedEntryError::Other,
                 },
                 content: strip_ansi_escapes::strip_str(&content),
-                metadata: None,
+                metadata: ExecutorFailureReason::classify(&content)
+                    .map(|reason| serde_json::json!({ "failure_reason": reason })),
+                agent_id: None,
+                agent_role: None,
             }))
             .time_gap(Duration::from_secs(2)) // Break messages if they are 2 seconds apart
             .index_provider(entry_index_provider)
@@ -61,3 +180,81 @@ pub fn normalize_stderr_logs(msg_store: Arc<MsgStore>, entry_index_provider: Ent
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_failures() {
+        let cases = [
+            (
+                "Error: Invalid API key · Please run /login",
+                Some(ExecutorFailureReason::AuthFailure),
+            ),
+            (
+                "API Error: 429 {\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\"}}",
+                Some(ExecutorFailureReason::RateLimited),
+            ),
+            (
+                "FATAL ERROR: Reached heap limit Allocation failed - JavaScript heap out of memory",
+                Some(ExecutorFailureReason::OutOfMemory),
+            ),
+            (
+                "request to https://api.anthropic.com failed, reason: getaddrinfo ENOTFOUND",
+                Some(ExecutorFailureReason::Network),
+            ),
+            (
+                "bash: fork: retry: Resource temporarily unavailable",
+                Some(ExecutorFailureReason::ResourceLimit),
+            ),
+            (
+                "sh: 1: claude-flow: command not found",
+                Some(ExecutorFailureReason::MissingBinary),
+            ),
+            (
+                "Error: prompt is too long: 213462 tokens > 200000 maximum",
+                Some(ExecutorFailureReason::ContextOverflow),
+            ),
+            ("warning: unused variable `x`", None),
+        ];
+        for (stderr, reason) in cases {
+            assert_eq!(ExecutorFailureReason::classify(stderr), reason, "{stderr}");
+        }
+
+        let history = [
+            LogMsg::Stderr("connect ECONNRESET 1.2.3.4:443".to_string()),
+            LogMsg::Stdout("retrying".to_string()),
+            LogMsg::Stderr("Too Many Requests".to_string()),
+            LogMsg::Stderr("exiting".to_string()),
+        ];
+        assert_eq!(
+            ExecutorFailureReason::from_history(&history),
+            Some(ExecutorFailureReason::RateLimited)
+        );
+        let history = [
+            LogMsg::Stdout("{\"type\":\"assistant\"}\n".to_string()),
+            LogMsg::Stdout(
+                "{\"type\":\"result\",\"is_error\":true,\"result\":\"Prompt is too long\"}\n"
+                    .to_string(),
+            ),
+        ];
+        assert_eq!(
+            ExecutorFailureReason::from_history(&history),
+            Some(ExecutorFailureReason::ContextOverflow)
+        );
+        let history = [
+            LogMsg::Stdout(
+                "{\"type\":\"result\",\"is_error\":true,\"result\":\"API Error: 529 Overloaded\"}\n"
+                    .to_string(),
+            ),
+            LogMsg::Stdout("{\"type\":\"system\"}\n".to_string()),
+        ];
+        assert_eq!(
+            ExecutorFailureReason::from_history(&history),
+            Some(ExecutorFailureReason::RateLimited)
+        );
+        assert!(ExecutorFailureReason::RateLimited.is_transient());
+        assert!(!ExecutorFailureReason::AuthFailure.is_transient());
+    }
+}
@@ -0,0 +1,121 @@
+//! Streams of the normalized entries of an execution.
+//!
+//! Executors publish entries to their [`MsgStore`] as JSON patches, added once
+//! and replaced as they update. [`entry_stream`] turns that into a stream of
+//! [`StreamedEntry`] values, history first and then live, ending when the
+//! execution finishes. Every entry carries its index in the conversation, so
+//! a client that lost the stream can resume after the last index it saw
+//! instead of replaying everything, e.g. from an SSE `Last-Event-ID`.
+
+use axum::response::sse::Event;
+use futures::{StreamExt, future, stream::BoxStream};
+use serde::{Deserialize, Serialize};
+use tokio_stream::wrappers::BroadcastStream;
+use ts_rs::TS;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::logs::{
+    NormalizedEntry,
+    utils::patch::{extract_normalized_entry_from_patch, latest_normalized_entries},
+};
+
+pub const EV_ENTRY: &str = "entry";
+
+/// A normalized entry along with its index in the conversation
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct StreamedEntry {
+    pub index: usize,
+    pub entry: NormalizedEntry,
+}
+
+impl StreamedEntry {
+    /// SSE event whose id is the entry index, so reconnecting clients send it
+    /// back as `Last-Event-ID`
+    pub fn to_sse_event(&self) -> Event {
+        let data = serde_json::to_string(&self.entry).unwrap_or_else(|_| "{}".to_string());
+        Event::default()
+            .event(EV_ENTRY)
+            .id(self.index.to_string())
+            .data(data)
+    }
+}
+
+/// Entries of `msg_store`, resuming after entry index `after` when given.
+///
+/// Stored history only yields the latest version of each entry; live updates
+/// yield every version, so an entry may repeat with its index as it changes.
+/// Earlier entries updated while a client was away are not sent again.
+pub fn entry_stream(
+    msg_store: &MsgStore,
+    after: Option<usize>,
+) -> BoxStream<'static, StreamedEntry> {
+    let (history, rx) = (msg_store.get_history(), msg_store.get_receiver());
+    let is_new = move |index: usize| after.is_none_or(|after| index > after);
+
+    let finished = history.iter().any(|msg| matches!(msg, LogMsg::Finished));
+    let hist = futures::stream::iter(
+        latest_normalized_entries(&history)
+            .into_iter()
+            .filter(move |(index, _)| is_new(*index))
+            .map(|(index, entry)| StreamedEntry { index, entry }),
+    );
+    if finished {
+        return hist.boxed();
+    }
+
+    let live = BroadcastStream::new(rx)
+        .filter_map(|res| future::ready(res.ok()))
+        .take_while(|msg| future::ready(!matches!(msg, LogMsg::Finished)))
+        .filter_map(move |msg| {
+            future::ready(match msg {
+                LogMsg::JsonPatch(patch) => extract_normalized_entry_from_patch(&patch)
+                    .filter(|(index, _)| is_new(*index))
+                    .map(|(index, entry)| StreamedEntry { index, entry }),
+                _ => None,
+            })
+        });
+    hist.chain(live).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::{NormalizedEntryType, utils::ConversationPatch};
+
+    fn message(content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::AssistantMessage,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_entry_stream_resumes_after_cursor() {
+        let store = MsgStore::new();
+        store.push_patch(ConversationPatch::add_normalized_entry(0, message("zero")));
+        store.push_patch(ConversationPatch::add_normalized_entry(1, message("one")));
+        store.push_patch(ConversationPatch::replace(1, message("one, edited")));
+
+        let stream = entry_stream(&store, Some(0));
+        store.push_stdout("not an entry");
+        store.push_patch(ConversationPatch::add_normalized_entry(2, message("two")));
+        store.push_finished();
+
+        let entries: Vec<_> = stream
+            .map(|streamed| (streamed.index, streamed.entry.content))
+            .collect()
+            .await;
+        assert_eq!(
+            entries,
+            [(1, "one, edited".to_string()), (2, "two".to_string())]
+        );
+
+        // A finished execution only replays its history
+        let replayed: Vec<_> = entry_stream(&store, None).collect().await;
+        assert_eq!(replayed.len(), 3);
+    }
+}
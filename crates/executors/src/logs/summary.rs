@@ -0,0 +1,272 @@
+//! Post-run summary of an execution, built from its normalized log entries.
+//!
+//! Besides the final assistant message, the summary collects work the agent
+//! explicitly left unfinished: `TODO`/`FIXME` markers in assistant messages and
+//! in lines added by file edits, and "left as an exercise" style hand-offs.
+
+use std::sync::LazyLock;
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+use workspace_utils::msg_store::MsgStore;
+
+use crate::{
+    cancellation::CancelReason,
+    logs::{
+        ActionType, FileChange, NormalizedEntry, NormalizedEntryType,
+        utils::patch::latest_normalized_entries,
+    },
+};
+
+static MARKER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(TODO|FIXME)\b[\s:(\-]*(?:[\w.@-]+\)[\s:\-]*)?(.*)").expect("valid regex")
+});
+static EXERCISE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bleft as an? (?:exercise|follow[- ]up)\b").expect("valid regex")
+});
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum FollowUpKind {
+    Todo,
+    Fixme,
+    /// Work the agent explicitly handed back ("left as an exercise")
+    Deferred,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FollowUpSource {
+    AssistantMessage,
+    FileEdit { path: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS)]
+pub struct FollowUpItem {
+    pub kind: FollowUpKind,
+    pub text: String,
+    pub source: FollowUpSource,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[serde(transparent)]
+pub struct FollowUpItems(pub Vec<FollowUpItem>);
+
+impl FollowUpItems {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FollowUpItem> {
+        self.0.iter()
+    }
+
+    fn push_unique(&mut self, item: FollowUpItem) {
+        if !self.0.contains(&item) {
+            self.0.push(item);
+        }
+    }
+
+    /// Scan a block of text line by line for follow-up markers
+    pub fn scan_text(&mut self, text: &str, source: &FollowUpSource) {
+        for line in text.lines() {
+            if let Some(caps) = MARKER_RE.captures(line) {
+                let kind = match &caps[1] {
+                    "FIXME" => FollowUpKind::Fixme,
+                    _ => FollowUpKind::Todo,
+                };
+                let text = clean_marker_text(&caps[2]);
+                self.push_unique(FollowUpItem {
+                    kind,
+                    text: if text.is_empty() {
+                        line.trim().to_string()
+                    } else {
+                        text
+                    },
+                    source: source.clone(),
+                });
+            } else if EXERCISE_RE.is_match(line) {
+                self.push_unique(FollowUpItem {
+                    kind: FollowUpKind::Deferred,
+                    text: line.trim().to_string(),
+                    source: source.clone(),
+                });
+            }
+        }
+    }
+
+    /// Scan only the lines a file change adds
+    pub fn scan_file_change(&mut self, path: &str, change: &FileChange) {
+        let source = FollowUpSource::FileEdit {
+            path: path.to_string(),
+        };
+        match change {
+            FileChange::Write { content } => self.scan_text(content, &source),
+            FileChange::Edit { unified_diff, .. } => {
+                let added = unified_diff
+                    .lines()
+                    .filter(|line| line.starts_with('+') && !line.starts_with("+++"))
+                    .map(|line| &line[1..])
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.scan_text(&added, &source);
+            }
+            FileChange::Delete | FileChange::Rename { .. } => {}
+        }
+    }
+
+    /// Render the items as a prompt that asks an agent to finish them
+    pub fn to_follow_up_prompt(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut prompt = String::from(
+            "The previous run left the following items unfinished. Please address them:\n",
+        );
+        for item in self.iter() {
+            let location = match &item.source {
+                FollowUpSource::AssistantMessage => String::new(),
+                FollowUpSource::FileEdit { path } => format!(" ({path})"),
+            };
+            prompt.push_str(&format!("- {}{location}\n", item.text));
+        }
+        Some(prompt)
+    }
+}
+
+fn clean_marker_text(text: &str) -> String {
+    text.trim()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim()
+        .to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+pub struct ExecutionSummary {
+    pub last_assistant_message: Option<String>,
+    pub follow_up_items: FollowUpItems,
+    /// Why the execution was cancelled, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancel_reason: Option<CancelReason>,
+}
+
+impl ExecutionSummary {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a NormalizedEntry>) -> Self {
+        let mut summary = Self::default();
+        for entry in entries {
+            match &entry.entry_type {
+                NormalizedEntryType::AssistantMessage => {
+                    summary
+                        .follow_up_items
+                        .scan_text(&entry.content, &FollowUpSource::AssistantMessage);
+                    let content = entry.content.trim();
+                    if !content.is_empty() {
+                        summary.last_assistant_message = Some(content.to_string());
+                    }
+                }
+                NormalizedEntryType::ToolUse {
+                    action_type: ActionType::FileEdit { path, changes },
+                    ..
+                } => {
+                    for change in changes {
+                        summary.follow_up_items.scan_file_change(path, change);
+                    }
+                }
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    /// Build a summary from the normalized entries recorded in a `MsgStore`.
+    /// Entries that were replaced during streaming are only counted in their final form.
+    pub fn from_msg_store(msg_store: &MsgStore) -> Self {
+        Self::from_entries(latest_normalized_entries(&msg_store.get_history()).values())
+    }
+}
+
+/// Callback invoked when an execution finishes with follow-up items, e.g. to
+/// queue a follow-up prompt or create subtasks.
+#[async_trait]
+pub trait FollowUpHook: Send + Sync {
+    async fn on_follow_up_items(&self, execution_id: Uuid, items: &FollowUpItems);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(entry_type: NormalizedEntryType, content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_extracts_markers_from_messages_and_added_lines() {
+        let entries = vec![
+            entry(
+                NormalizedEntryType::AssistantMessage,
+                "Implemented parsing.\nError recovery is left as an exercise for later.",
+            ),
+            entry(
+                NormalizedEntryType::ToolUse {
+                    tool_name: "Edit".to_string(),
+                    action_type: ActionType::FileEdit {
+                        path: "src/parser.rs".to_string(),
+                        changes: vec![FileChange::Edit {
+                            unified_diff: "--- a/src/parser.rs\n+++ b/src/parser.rs\n@@ -1,2 +1,3 @@\n-// TODO(old): removed line\n+// TODO(alice): handle nested blocks\n+/* FIXME: quadratic */\n fn parse() {}\n".to_string(),
+                            has_line_numbers: true,
+                        }],
+                    },
+                    status: Default::default(),
+                },
+                "",
+            ),
+            entry(NormalizedEntryType::AssistantMessage, "Done."),
+        ];
+
+        let summary = ExecutionSummary::from_entries(&entries);
+        assert_eq!(summary.last_assistant_message.as_deref(), Some("Done."));
+
+        let items: Vec<_> = summary
+            .follow_up_items
+            .iter()
+            .map(|item| (item.kind, item.text.as_str()))
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                (
+                    FollowUpKind::Deferred,
+                    "Error recovery is left as an exercise for later."
+                ),
+                (FollowUpKind::Todo, "handle nested blocks"),
+                (FollowUpKind::Fixme, "quadratic"),
+            ]
+        );
+
+        let prompt = summary.follow_up_items.to_follow_up_prompt().unwrap();
+        assert!(prompt.contains("- handle nested blocks (src/parser.rs)"));
+    }
+
+    #[test]
+    fn test_no_follow_ups_without_markers() {
+        let entries = vec![entry(
+            NormalizedEntryType::AssistantMessage,
+            "Added a todo list component.",
+        )];
+        let summary = ExecutionSummary::from_entries(&entries);
+        assert!(summary.follow_up_items.is_empty());
+        assert_eq!(summary.follow_up_items.to_follow_up_prompt(), None);
+    }
+}
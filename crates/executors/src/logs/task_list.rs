@@ -0,0 +1,259 @@
+//! Agents' todo lists as one structured checklist.
+//!
+//! Agents keep track of their plan with a todo list: Claude Code and Cursor
+//! through `TodoWrite`, Codex, Gemini and Qwen through plan updates, opencode
+//! and Droid through todo tools of their own. Executors normalize all of
+//! these into [`ActionType::TodoManagement`] calls, but with each agent's own
+//! status strings, and the list is only visible as one tool call among
+//! others. [`TaskListTracker`] follows those calls, and markdown checklists in
+//! presented plans, through a run and keeps the current list with statuses
+//! the UI can rely on. The normalization pipeline adds it to the conversation
+//! as a [`NormalizedEntryType::TaskListUpdate`] entry whenever it changes.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::logs::{ActionType, NormalizedEntry, NormalizedEntryType, TodoItem};
+
+/// Markdown checklist items, e.g. `- [x] Add the cache`
+static CHECKLIST_ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:[-*+]|\d+\.)\s+\[([ xX])\]\s+(.+?)\s*$").unwrap());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskListStatus {
+    #[default]
+    Pending,
+    InProgress,
+    Completed,
+    Cancelled,
+}
+
+impl TaskListStatus {
+    /// Status from an agent's own name for it; unknown ones count as pending
+    pub fn parse(status: &str) -> Self {
+        let status = status.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        match status.as_str() {
+            "in_progress" | "inprogress" | "active" | "running" | "doing" => Self::InProgress,
+            "completed" | "complete" | "done" | "finished" => Self::Completed,
+            "cancelled" | "canceled" | "skipped" | "abandoned" => Self::Cancelled,
+            _ => Self::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct TaskListItem {
+    pub content: String,
+    pub status: TaskListStatus,
+    pub priority: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct TaskList {
+    pub items: Vec<TaskListItem>,
+}
+
+impl TaskList {
+    pub fn count(&self, status: TaskListStatus) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.status == status)
+            .count()
+    }
+
+    /// Progress line, e.g. `2/5 tasks completed, working on: Add the cache`
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{}/{} tasks completed",
+            self.count(TaskListStatus::Completed),
+            self.items.len()
+        );
+        if let Some(item) = self
+            .items
+            .iter()
+            .find(|item| item.status == TaskListStatus::InProgress)
+        {
+            summary.push_str(&format!(", working on: {}", item.content));
+        }
+        summary
+    }
+
+    pub fn to_normalized_entry(&self) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::TaskListUpdate {
+                task_list: self.clone(),
+            },
+            content: self.summary(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+impl From<&TodoItem> for TaskListItem {
+    fn from(todo: &TodoItem) -> Self {
+        Self {
+            content: todo.content.clone(),
+            status: TaskListStatus::parse(&todo.status),
+            priority: todo.priority.clone(),
+        }
+    }
+}
+
+/// Current todo list of a run, as its entries update it
+#[derive(Debug, Clone, Default)]
+pub struct TaskListTracker {
+    task_list: TaskList,
+}
+
+impl TaskListTracker {
+    pub fn task_list(&self) -> &TaskList {
+        &self.task_list
+    }
+
+    /// Take the todo list `entry` writes, if it writes one. Agents always
+    /// write their whole list, so it replaces the current one. Returns the
+    /// list if it changed.
+    pub fn update(&mut self, entry: &NormalizedEntry) -> Option<&TaskList> {
+        let items = task_list_items(entry)?;
+        if items == self.task_list.items {
+            return None;
+        }
+        self.task_list.items = items;
+        Some(&self.task_list)
+    }
+}
+
+fn task_list_items(entry: &NormalizedEntry) -> Option<Vec<TaskListItem>> {
+    let NormalizedEntryType::ToolUse { action_type, .. } = &entry.entry_type else {
+        return None;
+    };
+    let items: Vec<TaskListItem> = match action_type {
+        // Reads carry no list, or the one written before
+        ActionType::TodoManagement { operation, .. } if operation == "read" => return None,
+        ActionType::TodoManagement { todos, .. } => todos.iter().map(Into::into).collect(),
+        ActionType::PlanPresentation { plan } => plan
+            .lines()
+            .filter_map(|line| CHECKLIST_ITEM.captures(line))
+            .map(|item| TaskListItem {
+                content: item[2].to_string(),
+                status: match &item[1] {
+                    " " => TaskListStatus::Pending,
+                    _ => TaskListStatus::Completed,
+                },
+                priority: None,
+            })
+            .collect(),
+        _ => return None,
+    };
+    (!items.is_empty()).then_some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::ToolStatus;
+
+    fn tool_use(action_type: ActionType) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::ToolUse {
+                tool_name: "TodoWrite".to_string(),
+                action_type,
+                status: ToolStatus::Success,
+            },
+            content: String::new(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    fn todos(operation: &str, todos: &[(&str, &str)]) -> NormalizedEntry {
+        tool_use(ActionType::TodoManagement {
+            todos: todos
+                .iter()
+                .map(|(content, status)| TodoItem {
+                    content: content.to_string(),
+                    status: status.to_string(),
+                    priority: None,
+                })
+                .collect(),
+            operation: operation.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_agent_statuses_are_normalized() {
+        assert_eq!(
+            TaskListStatus::parse(" In-Progress"),
+            TaskListStatus::InProgress
+        );
+        assert_eq!(TaskListStatus::parse("done"), TaskListStatus::Completed);
+        assert_eq!(TaskListStatus::parse("canceled"), TaskListStatus::Cancelled);
+        assert_eq!(TaskListStatus::parse("blocked"), TaskListStatus::Pending);
+    }
+
+    #[test]
+    fn test_tracker_keeps_the_latest_written_list() {
+        let mut tracker = TaskListTracker::default();
+        let written = todos(
+            "write",
+            &[
+                ("Read the login page", "completed"),
+                ("Fix the redirect", "in_progress"),
+                ("Add a test", "pending"),
+            ],
+        );
+        let task_list = tracker.update(&written).unwrap();
+        assert_eq!(task_list.count(TaskListStatus::Completed), 1);
+        assert_eq!(
+            task_list.summary(),
+            "1/3 tasks completed, working on: Fix the redirect"
+        );
+        assert_eq!(
+            task_list.to_normalized_entry().content,
+            "1/3 tasks completed, working on: Fix the redirect"
+        );
+
+        // Unchanged lists, reads and other tools leave it as it was
+        assert!(tracker.update(&written).is_none());
+        assert!(tracker.update(&todos("read", &[])).is_none());
+        assert!(
+            tracker
+                .update(&tool_use(ActionType::Other {
+                    description: "ls".to_string(),
+                }))
+                .is_none()
+        );
+        assert_eq!(tracker.task_list().items.len(), 3);
+
+        let plan = tool_use(ActionType::PlanPresentation {
+            plan: "Plan:\n- [x] Read the login page\n1. [ ] Fix the redirect\nThen ship it"
+                .to_string(),
+        });
+        let task_list = tracker.update(&plan).unwrap();
+        assert_eq!(
+            task_list.items,
+            [
+                TaskListItem {
+                    content: "Read the login page".to_string(),
+                    status: TaskListStatus::Completed,
+                    priority: None,
+                },
+                TaskListItem {
+                    content: "Fix the redirect".to_string(),
+                    status: TaskListStatus::Pending,
+                    priority: None,
+                },
+            ]
+        );
+        assert_eq!(task_list.summary(), "1/2 tasks completed");
+    }
+}
@@ -0,0 +1,335 @@
+//! Latency and failure statistics of an execution's tool calls, and how
+//! much of its output the log parser couldn't read.
+//!
+//! A tool call shows up in the normalized logs as a `ToolUse` entry when the
+//! agent makes it, and is replaced in place once its result arrives. A
+//! [`ToolStatsCollector`] pairs the two and totals calls, failures and time
+//! to result per tool, so it is easy to see where an agent loses its time,
+//! such as a shell command it keeps retrying. Logs carry no times of their
+//! own, so calls are only timed while the execution runs, by a collector
+//! started with it through [`spawn_tool_stats`] and dropped once it
+//! finishes; statistics rebuilt from stored logs have counts and failures
+//! only. Output the parser skipped
+//! shows up as `RawOutput` entries, which are counted as parse errors.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use ts_rs::TS;
+use uuid::Uuid;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::logs::{
+    NormalizedEntryType, ToolStatus, utils::patch::extract_normalized_entry_from_patch,
+};
+
+/// Collectors of the running executions
+static COLLECTORS: LazyLock<RwLock<HashMap<Uuid, Arc<RwLock<ToolStatsCollector>>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ToolStats {
+    pub tool_name: String,
+    pub calls: u32,
+    /// Calls that failed, were denied or timed out
+    pub failures: u32,
+    pub error_rate: f64,
+    /// Average time from call to result, over the calls that were timed
+    pub avg_duration_ms: Option<u64>,
+    pub max_duration_ms: Option<u64>,
+    /// Time spent in the timed calls altogether
+    pub total_duration_ms: u64,
+}
+
+/// Statistics of a session's coding agent runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SessionStats {
+    pub tool_stats: Vec<ToolStats>,
+    /// Pieces of output the agent's log parser couldn't read and kept raw
+    pub parse_errors: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Totals {
+    calls: u32,
+    failures: u32,
+    timed_calls: u32,
+    total_ms: u64,
+    max_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Call {
+    tool_name: String,
+    started_at: Option<DateTime<Utc>>,
+    finished: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ToolStatsCollector {
+    /// Calls seen so far, by entry index
+    calls: HashMap<usize, Call>,
+    totals: BTreeMap<String, Totals>,
+    /// Raw output entries seen so far, by entry index
+    raw_outputs: HashSet<usize>,
+    parse_errors: u32,
+}
+
+impl ToolStatsCollector {
+    pub fn from_history(history: &[LogMsg]) -> Self {
+        let mut collector = Self::default();
+        for msg in history {
+            collector.apply(msg, None);
+        }
+        collector
+    }
+
+    /// Take the tool call `msg` adds or finishes, seen at `seen_at`
+    pub fn apply(&mut self, msg: &LogMsg, seen_at: Option<DateTime<Utc>>) {
+        let LogMsg::JsonPatch(patch) = msg else {
+            return;
+        };
+        let Some((index, entry)) = extract_normalized_entry_from_patch(patch) else {
+            return;
+        };
+        if matches!(entry.entry_type, NormalizedEntryType::RawOutput) {
+            if self.raw_outputs.insert(index) {
+                self.parse_errors += 1;
+            }
+            return;
+        }
+        let NormalizedEntryType::ToolUse {
+            tool_name, status, ..
+        } = &entry.entry_type
+        else {
+            return;
+        };
+        let finished = match status {
+            ToolStatus::Success => Some(false),
+            ToolStatus::Failed | ToolStatus::Denied { .. } | ToolStatus::TimedOut => Some(true),
+            ToolStatus::Created | ToolStatus::PendingApproval { .. } => None,
+        };
+
+        let call = self.calls.entry(index).or_insert_with(|| {
+            self.totals.entry(tool_name.clone()).or_default().calls += 1;
+            let started_at = entry
+                .timestamp
+                .as_deref()
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                .map(|ts| ts.with_timezone(&Utc))
+                .or(seen_at);
+            Call {
+                tool_name: tool_name.clone(),
+                // A call first seen with its result wasn't seen running
+                started_at: started_at.filter(|_| finished.is_none()),
+                finished: false,
+            }
+        });
+        let Some(failed) = finished else {
+            return;
+        };
+        if call.finished {
+            return;
+        }
+        call.finished = true;
+        let totals = self.totals.entry(call.tool_name.clone()).or_default();
+        if failed {
+            totals.failures += 1;
+        }
+        if let (Some(started_at), Some(finished_at)) = (call.started_at, seen_at) {
+            let ms = (finished_at - started_at).num_milliseconds().max(0) as u64;
+            totals.timed_calls += 1;
+            totals.total_ms += ms;
+            totals.max_ms = totals.max_ms.max(ms);
+        }
+    }
+
+    /// Add the totals of another execution, e.g. one of the same session
+    pub fn merge(&mut self, other: &Self) {
+        for (tool_name, other) in &other.totals {
+            let totals = self.totals.entry(tool_name.clone()).or_default();
+            totals.calls += other.calls;
+            totals.failures += other.failures;
+            totals.timed_calls += other.timed_calls;
+            totals.total_ms += other.total_ms;
+            totals.max_ms = totals.max_ms.max(other.max_ms);
+        }
+        self.parse_errors += other.parse_errors;
+    }
+
+    /// Statistics per tool, the ones taking the most time first
+    pub fn stats(&self) -> Vec<ToolStats> {
+        let mut stats: Vec<ToolStats> = self
+            .totals
+            .iter()
+            .map(|(tool_name, totals)| ToolStats {
+                tool_name: tool_name.clone(),
+                calls: totals.calls,
+                failures: totals.failures,
+                error_rate: totals.failures as f64 / totals.calls.max(1) as f64,
+                avg_duration_ms: (totals.timed_calls > 0)
+                    .then(|| totals.total_ms / totals.timed_calls as u64),
+                max_duration_ms: (totals.timed_calls > 0).then_some(totals.max_ms),
+                total_duration_ms: totals.total_ms,
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.total_duration_ms
+                .cmp(&a.total_duration_ms)
+                .then(b.calls.cmp(&a.calls))
+                .then_with(|| a.tool_name.cmp(&b.tool_name))
+        });
+        stats
+    }
+
+    pub fn session_stats(&self) -> SessionStats {
+        SessionStats {
+            tool_stats: self.stats(),
+            parse_errors: self.parse_errors,
+        }
+    }
+}
+
+/// Time the tool calls of the execution `execution_id` as its store receives
+/// them, until it finishes
+pub fn spawn_tool_stats(execution_id: Uuid, msg_store: &MsgStore) {
+    // Subscribe before reading the history so nothing falls in between;
+    // messages seen twice don't count twice
+    let mut rx = msg_store.get_receiver();
+    let history = msg_store.get_history();
+    if history.iter().any(|msg| matches!(msg, LogMsg::Finished)) {
+        return;
+    }
+    let collector = Arc::new(RwLock::new(ToolStatsCollector::from_history(&history)));
+    COLLECTORS
+        .write()
+        .unwrap()
+        .insert(execution_id, collector.clone());
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(LogMsg::Finished) | Err(RecvError::Closed) => break,
+                Ok(msg) => collector.write().unwrap().apply(&msg, Some(Utc::now())),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(
+                        "Tool statistics of execution {execution_id} skipped {skipped} messages"
+                    );
+                }
+            }
+        }
+        COLLECTORS.write().unwrap().remove(&execution_id);
+    });
+}
+
+/// Statistics of the execution `execution_id`, with its calls timed, while
+/// it runs
+pub fn live_tool_stats(execution_id: &Uuid) -> Option<ToolStatsCollector> {
+    let collectors = COLLECTORS.read().unwrap();
+    let collector = collectors.get(execution_id)?.read().unwrap().clone();
+    Some(collector)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::logs::{ActionType, NormalizedEntry, utils::patch::ConversationPatch};
+
+    fn bash(status: ToolStatus) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::ToolUse {
+                tool_name: "Bash".to_string(),
+                action_type: ActionType::CommandRun {
+                    command: "cargo test".to_string(),
+                    result: None,
+                },
+                status,
+            },
+            content: "cargo test".to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_tool_calls_paired_with_their_results() {
+        let start = Utc::now();
+        let at = |secs: i64| Some(start + Duration::seconds(secs));
+        let mut collector = ToolStatsCollector::default();
+        let calls = [
+            (
+                ConversationPatch::add_normalized_entry(0, bash(ToolStatus::Created)),
+                0,
+            ),
+            (ConversationPatch::replace(0, bash(ToolStatus::Failed)), 4),
+            // Seen again, e.g. when an update is forwarded twice
+            (ConversationPatch::replace(0, bash(ToolStatus::Failed)), 5),
+            (
+                ConversationPatch::add_normalized_entry(1, bash(ToolStatus::Created)),
+                6,
+            ),
+            (ConversationPatch::replace(1, bash(ToolStatus::Success)), 8),
+        ];
+        for (patch, secs) in calls {
+            collector.apply(&LogMsg::JsonPatch(patch), at(secs));
+        }
+
+        let [bash_stats] = collector.stats().try_into().unwrap();
+        assert_eq!(bash_stats.calls, 2);
+        assert_eq!(bash_stats.failures, 1);
+        assert_eq!(bash_stats.error_rate, 0.5);
+        assert_eq!(bash_stats.avg_duration_ms, Some(3000));
+        assert_eq!(bash_stats.max_duration_ms, Some(4000));
+
+        // Stored logs have no times to go by
+        let history = [LogMsg::JsonPatch(ConversationPatch::add_normalized_entry(
+            0,
+            bash(ToolStatus::Success),
+        ))];
+        let stored = ToolStatsCollector::from_history(&history);
+        collector.merge(&stored);
+        let [bash_stats] = collector.stats().try_into().unwrap();
+        assert_eq!(bash_stats.calls, 3);
+        assert_eq!(bash_stats.avg_duration_ms, Some(3000));
+        assert_eq!(stored.stats()[0].avg_duration_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_collector_removed_when_finished() {
+        let execution_id = Uuid::new_v4();
+        let store = MsgStore::new();
+        spawn_tool_stats(execution_id, &store);
+        store.push_patch(ConversationPatch::add_normalized_entry(
+            0,
+            bash(ToolStatus::Created),
+        ));
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while live_tool_stats(&execution_id).is_none_or(|stats| stats.stats().is_empty()) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("running execution has live statistics");
+
+        store.push_finished();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while live_tool_stats(&execution_id).is_some() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("collector of a finished execution is removed");
+
+        // Finished stores are never collected
+        spawn_tool_stats(execution_id, &store);
+        assert!(live_tool_stats(&execution_id).is_none());
+    }
+}
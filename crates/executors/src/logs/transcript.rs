@@ -0,0 +1,468 @@
+//! Export of an execution's conversation as a standalone transcript.
+//!
+//! Thinking blocks are left out unless explicitly requested: they often reason
+//! over file contents and environment details that the final answer doesn't
+//! reveal, so sharing a transcript should not share them by default.
+//!
+//! A transcript can also be condensed into a plain-text digest that fits a
+//! token budget, for feeding a run to another model (e.g. to judge or review
+//! it). The digest keeps the prompt, errors, failed tools, file diffs and the
+//! final answer ahead of command output, which in turn goes before
+//! intermediate chatter; whatever doesn't fit is replaced by omission markers.
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use workspace_utils::log_msg::LogMsg;
+
+use crate::logs::{
+    ActionType, CommandExitStatus, FileChange, NormalizedConversation, NormalizedEntry,
+    NormalizedEntryType, ToolStatus, summary::ExecutionSummary, task_list::TaskListStatus,
+    utils::patch::latest_normalized_entries,
+};
+
+/// Smallest per-entry allowance, so tight budgets still show something useful
+const MIN_SECTION_TOKENS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, TS)]
+pub struct TranscriptOptions {
+    #[serde(default)]
+    pub include_thinking: bool,
+}
+
+impl NormalizedConversation {
+    /// Build a transcript from the normalized history of an execution
+    pub fn from_history(
+        history: &[LogMsg],
+        executor_type: String,
+        prompt: Option<String>,
+        options: &TranscriptOptions,
+    ) -> Self {
+        let entries: Vec<_> = latest_normalized_entries(history)
+            .into_values()
+            .filter(|entry| {
+                options.include_thinking
+                    || !matches!(entry.entry_type, NormalizedEntryType::Thinking)
+            })
+            .collect();
+        let session_id = history.iter().rev().find_map(|msg| match msg {
+            LogMsg::SessionId(id) => Some(id.clone()),
+            _ => None,
+        });
+        let summary = ExecutionSummary::from_entries(&entries).last_assistant_message;
+        Self {
+            entries,
+            session_id,
+            executor_type,
+            prompt,
+            summary,
+        }
+    }
+}
+
+fn default_max_tokens() -> usize {
+    8_000
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TS)]
+pub struct DigestOptions {
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: usize,
+    #[serde(default)]
+    pub include_thinking: bool,
+}
+
+impl Default for DigestOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: default_max_tokens(),
+            include_thinking: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct TranscriptDigest {
+    pub text: String,
+    pub estimated_tokens: usize,
+    /// Entries left out entirely to stay within the budget
+    pub omitted_entries: usize,
+}
+
+/// Rough token count, at about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Shorten `text` to about `max_tokens` by cutting out its middle, keeping
+/// the start and the end, which usually carry the command and its outcome
+pub fn truncate_middle(text: &str, max_tokens: usize) -> String {
+    let total = estimate_tokens(text);
+    if total <= max_tokens {
+        return text.to_string();
+    }
+    let keep_chars = max_tokens * 4;
+    let head = keep_chars * 2 / 3;
+    let tail = keep_chars - head;
+    let chars = text.chars().count();
+    let head_end = text.char_indices().nth(head).map_or(text.len(), |(i, _)| i);
+    let tail_start = text
+        .char_indices()
+        .nth(chars - tail)
+        .map_or(text.len(), |(i, _)| i);
+    format!(
+        "{}\n[... {} tokens truncated ...]\n{}",
+        &text[..head_end],
+        total - max_tokens,
+        &text[tail_start..]
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Priority {
+    Chatter,
+    Context,
+    Essential,
+}
+
+fn render_file_change(change: &FileChange) -> String {
+    match change {
+        FileChange::Write { content } => format!("(new content)\n{content}"),
+        FileChange::Delete => "(deleted)".to_string(),
+        FileChange::Rename { new_path } => format!("(renamed to {new_path})"),
+        FileChange::Edit { unified_diff, .. } => unified_diff.clone(),
+    }
+}
+
+fn render_entry(entry: &NormalizedEntry) -> Option<(Priority, String)> {
+    let content = entry.content.trim();
+    let rendered = match &entry.entry_type {
+        NormalizedEntryType::UserMessage => (Priority::Essential, format!("### User\n{content}")),
+        NormalizedEntryType::UserFeedback { denied_tool } => (
+            Priority::Essential,
+            format!("### User denied {denied_tool}\n{content}"),
+        ),
+        NormalizedEntryType::AssistantMessage => {
+            (Priority::Chatter, format!("### Assistant\n{content}"))
+        }
+        NormalizedEntryType::ErrorMessage { .. } => {
+            (Priority::Essential, format!("### Error\n{content}"))
+        }
+        NormalizedEntryType::SystemMessage => (Priority::Chatter, format!("### System\n{content}")),
+        NormalizedEntryType::RawOutput => (Priority::Chatter, format!("### Raw output\n{content}")),
+        NormalizedEntryType::RunSummary { .. } => {
+            (Priority::Essential, format!("### Run summary\n{content}"))
+        }
+        NormalizedEntryType::WorkspaceChanges { .. } => (
+            Priority::Essential,
+            format!("### Workspace changes\n{content}"),
+        ),
+        NormalizedEntryType::RunHook {
+            stage,
+            command,
+            status,
+        } => {
+            // Output of hooks that passed is only context
+            let (priority, label) = match status {
+                ToolStatus::Failed => (Priority::Essential, " (failed)"),
+                _ => (Priority::Context, ""),
+            };
+            (
+                priority,
+                format!("### {stage} hook `{command}`{label}\n{}", content.trim()),
+            )
+        }
+        NormalizedEntryType::ReviewFinding {
+            severity,
+            path,
+            line,
+        } => {
+            let location = match (path, line) {
+                (Some(path), Some(line)) => format!(" in {path}:{line}"),
+                (Some(path), None) => format!(" in {path}"),
+                _ => String::new(),
+            };
+            (
+                Priority::Essential,
+                format!("### Review finding ({severity}){location}\n{content}"),
+            )
+        }
+        NormalizedEntryType::TaskListUpdate { task_list } => {
+            let mut text = format!("### Todo list\n{content}");
+            for item in &task_list.items {
+                let mark = match item.status {
+                    TaskListStatus::Completed => "x",
+                    TaskListStatus::InProgress => "~",
+                    TaskListStatus::Cancelled => "-",
+                    TaskListStatus::Pending => " ",
+                };
+                text.push_str(&format!("\n- [{mark}] {}", item.content));
+            }
+            (Priority::Context, text)
+        }
+        NormalizedEntryType::LoopDetected { .. } => {
+            (Priority::Essential, format!("### Loop detected\n{content}"))
+        }
+        NormalizedEntryType::Thinking => (Priority::Chatter, format!("### Thinking\n{content}")),
+        NormalizedEntryType::ToolUse {
+            tool_name,
+            action_type,
+            status,
+        } => {
+            let failed = match status {
+                ToolStatus::Failed => Some("failed"),
+                ToolStatus::Denied { .. } => Some("denied"),
+                ToolStatus::TimedOut => Some("timed out"),
+                _ => None,
+            };
+            let (priority, mut text) = match action_type {
+                ActionType::FileEdit { path, changes } => (
+                    Priority::Essential,
+                    format!(
+                        "### Edit {path}\n{}",
+                        changes
+                            .iter()
+                            .map(render_file_change)
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    ),
+                ),
+                ActionType::CommandRun { command, result } => {
+                    let exit = result.as_ref().and_then(|r| r.exit_status.as_ref());
+                    let (exit_label, ok) = match exit {
+                        Some(CommandExitStatus::ExitCode { code }) => {
+                            (format!(" (exit {code})"), *code == 0)
+                        }
+                        Some(CommandExitStatus::Success { success }) => (String::new(), *success),
+                        None => (String::new(), true),
+                    };
+                    let output = result
+                        .as_ref()
+                        .and_then(|r| r.output.as_deref())
+                        .unwrap_or_default()
+                        .trim();
+                    let priority = if ok {
+                        Priority::Context
+                    } else {
+                        Priority::Essential
+                    };
+                    (
+                        priority,
+                        format!("### Command `{command}`{exit_label}\n{output}"),
+                    )
+                }
+                ActionType::PlanPresentation { plan } => {
+                    (Priority::Context, format!("### Plan\n{plan}"))
+                }
+                ActionType::Tool {
+                    result: Some(result),
+                    ..
+                } => (
+                    Priority::Context,
+                    format!("### {tool_name}: {content}\n{}", result.value),
+                ),
+                _ => (Priority::Chatter, format!("### {tool_name}: {content}")),
+            };
+            if let Some(failed) = failed {
+                text.push_str(&format!("\n({failed})"));
+                (Priority::Essential, text)
+            } else {
+                (priority, text)
+            }
+        }
+        NormalizedEntryType::Loading | NormalizedEntryType::NextAction { .. } => return None,
+    };
+    Some(rendered)
+}
+
+fn omission_marker(count: usize) -> String {
+    format!("[... {count} entries omitted ...]")
+}
+
+impl NormalizedConversation {
+    /// Render the conversation as text within `options.max_tokens`
+    pub fn digest(&self, options: &DigestOptions) -> TranscriptDigest {
+        let section_cap = (options.max_tokens / 4).max(MIN_SECTION_TOKENS);
+        let last_assistant = self
+            .entries
+            .iter()
+            .rposition(|entry| matches!(entry.entry_type, NormalizedEntryType::AssistantMessage));
+
+        let header = self
+            .prompt
+            .as_deref()
+            .map(|prompt| truncate_middle(&format!("### Task\n{}", prompt.trim()), section_cap));
+        let mut sections: Vec<(Priority, String)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                options.include_thinking
+                    || !matches!(entry.entry_type, NormalizedEntryType::Thinking)
+            })
+            .filter_map(|(index, entry)| {
+                let (priority, text) = render_entry(entry)?;
+                let priority = if Some(index) == last_assistant {
+                    Priority::Essential
+                } else {
+                    priority
+                };
+                Some((priority, truncate_middle(&text, section_cap)))
+            })
+            .collect();
+        if sections.is_empty() && header.is_none() {
+            sections.push((Priority::Essential, "(empty conversation)".to_string()));
+        }
+
+        // Most important first; within a priority, later entries first
+        let mut order: Vec<usize> = (0..sections.len()).collect();
+        order.sort_by_key(|&i| (std::cmp::Reverse(sections[i].0), std::cmp::Reverse(i)));
+
+        let mut budget = options
+            .max_tokens
+            .saturating_sub(header.as_deref().map_or(0, estimate_tokens));
+        let mut selected = vec![false; sections.len()];
+        for &i in &order {
+            // Leave room for the marker of a gap this section may open
+            let cost = estimate_tokens(&sections[i].1) + estimate_tokens(&omission_marker(999));
+            if cost <= budget {
+                budget -= cost;
+                selected[i] = true;
+            }
+        }
+
+        let mut parts: Vec<String> = header.into_iter().collect();
+        let mut omitted = 0;
+        let mut gap = 0;
+        for (i, (_, text)) in sections.iter().enumerate() {
+            if selected[i] {
+                if gap > 0 {
+                    parts.push(omission_marker(gap));
+                    gap = 0;
+                }
+                parts.push(text.clone());
+            } else {
+                gap += 1;
+                omitted += 1;
+            }
+        }
+        if gap > 0 {
+            parts.push(omission_marker(gap));
+        }
+
+        let text = parts.join("\n\n");
+        TranscriptDigest {
+            estimated_tokens: estimate_tokens(&text),
+            text,
+            omitted_entries: omitted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::{NormalizedEntry, utils::ConversationPatch};
+
+    fn entry(entry_type: NormalizedEntryType, content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    #[test]
+    fn test_thinking_excluded_unless_requested() {
+        let history = vec![
+            LogMsg::SessionId("session-1".to_string()),
+            LogMsg::JsonPatch(ConversationPatch::add_normalized_entry(
+                0,
+                entry(NormalizedEntryType::Thinking, "The key in .env is..."),
+            )),
+            LogMsg::JsonPatch(ConversationPatch::add_normalized_entry(
+                1,
+                entry(NormalizedEntryType::AssistantMessage, "Done"),
+            )),
+        ];
+
+        let transcript = NormalizedConversation::from_history(
+            &history,
+            "CLAUDE_CODE".to_string(),
+            None,
+            &TranscriptOptions::default(),
+        );
+        assert_eq!(transcript.entries.len(), 1);
+        assert_eq!(transcript.session_id.as_deref(), Some("session-1"));
+        assert_eq!(transcript.summary.as_deref(), Some("Done"));
+
+        let transcript = NormalizedConversation::from_history(
+            &history,
+            "CLAUDE_CODE".to_string(),
+            None,
+            &TranscriptOptions {
+                include_thinking: true,
+            },
+        );
+        assert_eq!(transcript.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_digest_keeps_essentials_within_budget() {
+        let edit = entry(
+            NormalizedEntryType::ToolUse {
+                tool_name: "Edit".to_string(),
+                action_type: ActionType::FileEdit {
+                    path: "src/lib.rs".to_string(),
+                    changes: vec![FileChange::Edit {
+                        unified_diff: "@@ -1 +1 @@\n-old\n+new".to_string(),
+                        has_line_numbers: true,
+                    }],
+                },
+                status: ToolStatus::Success,
+            },
+            "",
+        );
+        let chatter = "Let me look around the codebase a bit more. ".repeat(20);
+        let mut entries: Vec<_> = (0..10)
+            .map(|_| entry(NormalizedEntryType::AssistantMessage, &chatter))
+            .collect();
+        entries.insert(3, edit);
+        entries.push(entry(
+            NormalizedEntryType::ErrorMessage {
+                error_type: crate::logs::NormalizedEntryError::Other,
+            },
+            "cargo test failed",
+        ));
+        entries.push(entry(NormalizedEntryType::AssistantMessage, "All done ✓"));
+        let conversation = NormalizedConversation {
+            entries,
+            session_id: None,
+            executor_type: "CLAUDE_CODE".to_string(),
+            prompt: Some("Rename the module".to_string()),
+            summary: None,
+        };
+
+        let digest = conversation.digest(&DigestOptions {
+            max_tokens: 400,
+            include_thinking: false,
+        });
+        assert!(digest.estimated_tokens <= 400);
+        assert!(digest.omitted_entries > 0);
+        for expected in [
+            "Rename the module",
+            "+new",
+            "cargo test failed",
+            "All done ✓",
+        ] {
+            assert!(digest.text.contains(expected), "missing {expected}");
+        }
+        assert!(digest.text.contains("entries omitted"));
+
+        let truncated = truncate_middle(&"é".repeat(1_000), 50);
+        assert!(truncated.contains("tokens truncated"));
+        assert!(estimate_tokens(&truncated) < 70);
+    }
+}
@@ -0,0 +1,204 @@
+//! Truncation of long entries, keeping the full entry aside.
+//!
+//! With the `truncate_outputs` normalization stage, an entry whose content or
+//! tool output is longer than the limit reaches the conversation cut short,
+//! ending in a marker the UI can expand. The full entry is written to a
+//! [`FullEntryStore`] under an id noted as `full_entry_id` in the entry's
+//! metadata, so a UI stream of a chatty session stays light while nothing is
+//! lost. Entries are sealed like other files at rest when encryption is
+//! configured.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+use workspace_utils::text::truncate_to_char_boundary;
+
+use crate::{
+    encryption,
+    logs::{ActionType, CommandRunResult, NormalizedEntry, NormalizedEntryType, ToolResult},
+};
+
+/// Directory under the asset dir holding full entries
+pub const FULL_ENTRIES_DIR: &str = "full_entries";
+/// Metadata key of the id a truncated entry's full version is stored under
+pub const FULL_ENTRY_ID_KEY: &str = "full_entry_id";
+
+#[derive(Debug, Clone)]
+pub struct FullEntryStore {
+    dir: PathBuf,
+}
+
+impl Default for FullEntryStore {
+    fn default() -> Self {
+        Self::in_dir(workspace_utils::assets::asset_dir().join(FULL_ENTRIES_DIR))
+    }
+}
+
+impl FullEntryStore {
+    pub fn in_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    /// Write `entry` under `id`, replacing an earlier version of it
+    pub fn save(&self, id: Uuid, entry: &NormalizedEntry) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let line = serde_json::to_string(entry)?;
+        std::fs::write(self.path(id), encryption::seal_line(&line)?.as_bytes())
+    }
+
+    /// The entry stored under `id`, if there is one
+    pub fn load(&self, id: Uuid) -> io::Result<Option<NormalizedEntry>> {
+        let line = match std::fs::read_to_string(self.path(id)) {
+            Ok(line) => line,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let line = encryption::open_line(line.trim_end())?;
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// `entry` with its content and tool output cut to `max_bytes` each, or
+/// `None` if nothing was longer. Entries with metadata other than an object
+/// are left whole, as the id of the full entry couldn't be noted.
+pub fn truncate_entry(
+    entry: &NormalizedEntry,
+    max_bytes: usize,
+    full_entry_id: Uuid,
+) -> Option<NormalizedEntry> {
+    if entry
+        .metadata
+        .as_ref()
+        .is_some_and(|metadata| !metadata.is_object())
+    {
+        return None;
+    }
+    let mut truncated = entry.clone();
+    let mut changed = truncate_text(&mut truncated.content, max_bytes);
+    if let NormalizedEntryType::ToolUse { action_type, .. } = &mut truncated.entry_type {
+        match action_type {
+            ActionType::CommandRun {
+                result:
+                    Some(CommandRunResult {
+                        output: Some(output),
+                        ..
+                    }),
+                ..
+            } => changed |= truncate_text(output, max_bytes),
+            ActionType::Tool {
+                result: Some(ToolResult { value, .. }),
+                ..
+            } => {
+                let mut output = match &*value {
+                    serde_json::Value::String(output) => output.clone(),
+                    value => value.to_string(),
+                };
+                if truncate_text(&mut output, max_bytes) {
+                    // A cut JSON value is no longer JSON, so it becomes text
+                    *value = serde_json::Value::String(output);
+                    changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    if !changed {
+        return None;
+    }
+    let metadata = truncated
+        .metadata
+        .get_or_insert_with(|| serde_json::Value::Object(Default::default()));
+    metadata[FULL_ENTRY_ID_KEY] = full_entry_id.to_string().into();
+    Some(truncated)
+}
+
+/// Cut `text` to `max_bytes` on a character boundary, followed by the marker
+fn truncate_text(text: &mut String, max_bytes: usize) -> bool {
+    if text.len() <= max_bytes {
+        return false;
+    }
+    let end = truncate_to_char_boundary(text, max_bytes).len();
+    let dropped_kb = (text.len() - end).div_ceil(1024);
+    text.truncate(end);
+    text.push_str(&format!("\n… [{dropped_kb} KB more, expand to see all]"));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::ToolResultValueType;
+
+    #[test]
+    fn test_long_output_truncated_and_full_entry_kept() {
+        let entry = NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::ToolUse {
+                tool_name: "mcp__search".to_string(),
+                action_type: ActionType::Tool {
+                    tool_name: "mcp__search".to_string(),
+                    arguments: None,
+                    result: Some(ToolResult {
+                        r#type: ToolResultValueType::Json,
+                        value: serde_json::json!({"hits": "é".repeat(2048)}),
+                    }),
+                },
+                status: crate::logs::ToolStatus::Success,
+            },
+            content: "search".to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        };
+        assert!(truncate_entry(&entry, 8192, Uuid::nil()).is_none());
+
+        let id = Uuid::new_v4();
+        let truncated = truncate_entry(&entry, 1000, id).unwrap();
+        let NormalizedEntryType::ToolUse {
+            action_type:
+                ActionType::Tool {
+                    result: Some(ToolResult { value, .. }),
+                    ..
+                },
+            ..
+        } = &truncated.entry_type
+        else {
+            panic!("not a tool use");
+        };
+        // Cut before the two-byte character that would cross the limit
+        assert_eq!(
+            value.as_str().unwrap(),
+            format!(
+                "{{\"hits\":\"{}\n… [4 KB more, expand to see all]",
+                "é".repeat(495)
+            )
+        );
+        assert_eq!(truncated.content, "search");
+        assert_eq!(
+            truncated.metadata.unwrap()[FULL_ENTRY_ID_KEY],
+            id.to_string()
+        );
+
+        let store = FullEntryStore::in_dir(
+            std::env::temp_dir().join(format!("vk-full-entries-{}", Uuid::new_v4())),
+        );
+        store.save(id, &entry).unwrap();
+        assert_eq!(
+            serde_json::to_value(store.load(id).unwrap().unwrap()).unwrap(),
+            serde_json::to_value(&entry).unwrap()
+        );
+        assert!(store.load(Uuid::new_v4()).unwrap().is_none());
+        std::fs::remove_dir_all(store.dir()).unwrap();
+    }
+}
@@ -32,15 +32,19 @@ impl EntryIndexProvider {
         self.0.store(0, Ordering::Relaxed);
     }
 
+    /// Step back `count` indices, once that many entries were removed from
+    /// the start of the conversation
+    pub fn rewind(&self, count: usize) {
+        self.0.fetch_sub(count, Ordering::Relaxed);
+    }
+
     /// Create a provider starting from the maximum existing normalized-entry index
     /// observed in prior JSON patches in `MsgStore`.
     pub fn start_from(msg_store: &MsgStore) -> Self {
         let provider = EntryIndexProvider::new();
 
         let max_index: Option<usize> = msg_store
-            .get_history()
-            .iter()
-            .filter_map(|msg| {
+            .filter_map_history(|msg| {
                 if let LogMsg::JsonPatch(patch) = msg {
                     patch.iter().find_map(|op| {
                         if let PatchOperation::Add(add) = op {
@@ -55,6 +59,7 @@ impl EntryIndexProvider {
                     None
                 }
             })
+            .into_iter()
             .max();
 
         let start_at = max_index.map_or(0, |n| n.saturating_add(1));
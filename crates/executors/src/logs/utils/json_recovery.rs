@@ -0,0 +1,73 @@
+//! Recovery of JSON objects from lines mixed with other output.
+//!
+//! Agents that stream JSON Lines share stdout with whatever they run through,
+//! so a line can carry an npm warning in front of an event, or several events
+//! run together. [`recover_json_line`] splits such a line into the objects
+//! that parse and the text around them, so the events aren't lost with the
+//! noise.
+
+use serde::de::DeserializeOwned;
+
+/// Part of a line that didn't parse as a whole
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recovered<'a, T> {
+    Json(T),
+    /// Text between the objects that parsed, trimmed
+    Raw(&'a str),
+}
+
+/// Every `T` in `line`, in order, with the text that isn't one in between.
+/// Scans for the next `{` each time an object fails to parse.
+pub fn recover_json_line<T: DeserializeOwned>(line: &str) -> Vec<Recovered<'_, T>> {
+    let mut parts = Vec::new();
+    // Start of the text not yet taken by an object
+    let mut raw_start = 0;
+    let mut search_from = 0;
+    while let Some(offset) = line[search_from..].find('{') {
+        let start = search_from + offset;
+        let mut objects = serde_json::Deserializer::from_str(&line[start..]).into_iter::<T>();
+        match objects.next() {
+            Some(Ok(value)) => {
+                push_raw(&mut parts, &line[raw_start..start]);
+                parts.push(Recovered::Json(value));
+                raw_start = start + objects.byte_offset();
+                search_from = raw_start;
+            }
+            _ => search_from = start + 1,
+        }
+    }
+    push_raw(&mut parts, &line[raw_start..]);
+    parts
+}
+
+fn push_raw<'a, T>(parts: &mut Vec<Recovered<'a, T>>, text: &'a str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        parts.push(Recovered::Raw(text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    #[test]
+    fn test_objects_recovered_from_noisy_line() {
+        let line = r#"npm warn config production Use `--omit=dev` {"type":"system"}{"type":"result","note":"{not json"} trailing {oops"#;
+        assert_eq!(
+            recover_json_line::<Value>(line),
+            vec![
+                Recovered::Raw("npm warn config production Use `--omit=dev`"),
+                Recovered::Json(json!({"type": "system"})),
+                Recovered::Json(json!({"type": "result", "note": "{not json"})),
+                Recovered::Raw("trailing {oops"),
+            ]
+        );
+        assert_eq!(
+            recover_json_line::<Value>("no json here"),
+            vec![Recovered::Raw("no json here")]
+        );
+    }
+}
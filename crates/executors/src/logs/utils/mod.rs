@@ -1,7 +1,17 @@
 //! Utility modules for executor framework
 
 pub mod entry_index;
+pub mod json_recovery;
 pub mod patch;
 
 pub use entry_index::EntryIndexProvider;
 pub use patch::ConversationPatch;
+use serde_json::Value;
+
+/// First value under `key` in a JSON event, or in the objects nested in it
+pub fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    let object = value.as_object()?;
+    object
+        .get(key)
+        .or_else(|| object.values().find_map(|value| find_key(value, key)))
+}
@@ -1,10 +1,10 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
-use json_patch::Patch;
+use json_patch::{Patch, PatchOperation as JsonPatchOperation};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, to_value};
 use ts_rs::TS;
-use workspace_utils::{diff::Diff, msg_store::MsgStore};
+use workspace_utils::{diff::Diff, log_msg::LogMsg, msg_store::MsgStore};
 
 use crate::logs::{NormalizedEntry, utils::EntryIndexProvider};
 
@@ -142,6 +142,41 @@ pub fn extract_normalized_entry_from_patch(patch: &Patch) -> Option<(usize, Norm
     })
 }
 
+/// Attribute the normalized entries that `patch` adds or replaces to a swarm
+/// agent
+pub fn attribute_entries(patch: &mut Patch, agent_id: &str, agent_role: Option<&str>) {
+    for op in patch.0.iter_mut() {
+        let value = match op {
+            JsonPatchOperation::Add(op) => &mut op.value,
+            JsonPatchOperation::Replace(op) => &mut op.value,
+            _ => continue,
+        };
+        if value.get("type").and_then(|t| t.as_str()) != Some("NORMALIZED_ENTRY") {
+            continue;
+        }
+        if let Some(entry) = value.get_mut("content").and_then(|c| c.as_object_mut()) {
+            entry.insert("agent_id".to_string(), json!(agent_id));
+            if let Some(role) = agent_role {
+                entry.insert("agent_role".to_string(), json!(role));
+            }
+        }
+    }
+}
+
+/// Final version of each normalized entry in a log history, keyed by entry index.
+/// Entries replaced during streaming are only returned in their latest form.
+pub fn latest_normalized_entries<'a>(
+    history: impl IntoIterator<Item = &'a LogMsg>,
+) -> BTreeMap<usize, NormalizedEntry> {
+    history
+        .into_iter()
+        .filter_map(|msg| match msg {
+            LogMsg::JsonPatch(patch) => extract_normalized_entry_from_patch(patch),
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn upsert_normalized_entry(
     msg_store: &Arc<MsgStore>,
     index: usize,
@@ -0,0 +1,357 @@
+//! Watchdog for agents stuck in a loop.
+//!
+//! An agent that keeps making the same tool call with the same input, or
+//! keeps writing the same message, is rarely going to get anywhere and burns
+//! time and tokens until a timeout stops it. A [`LoopDetector`] counts such
+//! repeats as the normalization pipeline forwards entries; once a profile's
+//! [`LoopDetection`] threshold is reached, the pipeline adds a
+//! [`NormalizedEntryType::LoopDetected`] warning to the conversation. Hosts
+//! follow those warnings with [`watch_loops`] and, depending on the profile's
+//! [`LoopAction`], interrupt the agent or send it a corrective message.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use ts_rs::TS;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::{
+    actions::{ExecutorAction, ExecutorActionType},
+    logs::{
+        NormalizedEntry, NormalizedEntryType, utils::patch::extract_normalized_entry_from_patch,
+    },
+    profile::ExecutorConfigs,
+};
+
+const DEFAULT_MAX_REPEATED_TOOL_CALLS: u32 = 5;
+const DEFAULT_MAX_REPEATED_MESSAGES: u32 = 3;
+const DEFAULT_CORRECTION: &str = "You appear to be repeating the same step without making progress. Stop, reconsider what is going wrong, and try a different approach.";
+
+/// What happens once an agent is found repeating itself
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopAction {
+    // Only add a warning to the conversation
+    #[default]
+    Warn,
+    // Stop the agent
+    Interrupt,
+    // Send the agent a message asking it to change its approach; agents that
+    // don't take input while running only get the warning
+    Correct,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct LoopDetection {
+    /// Identical tool calls in a row that count as a loop; defaults to 5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_repeated_tool_calls: Option<u32>,
+    /// Identical assistant messages in a row that count as a loop; defaults to 3
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_repeated_messages: Option<u32>,
+    #[serde(default)]
+    pub action: LoopAction,
+    /// Message sent to the agent by the `correct` action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correction: Option<String>,
+}
+
+impl LoopDetection {
+    pub fn correction(&self) -> &str {
+        self.correction.as_deref().unwrap_or(DEFAULT_CORRECTION)
+    }
+}
+
+/// A loop the detector found: a tool call, or the assistant message if
+/// `tool_name` is `None`, repeated `repeats` times in a row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedLoop {
+    pub tool_name: Option<String>,
+    pub repeats: u32,
+    pub action: LoopAction,
+}
+
+impl DetectedLoop {
+    pub fn to_normalized_entry(&self) -> NormalizedEntry {
+        let repeated = match &self.tool_name {
+            Some(tool_name) => format!(
+                "The agent called {tool_name} with the same input {} times in a row",
+                self.repeats
+            ),
+            None => format!(
+                "The agent wrote the same message {} times in a row",
+                self.repeats
+            ),
+        };
+        let content = match self.action {
+            LoopAction::Warn => format!("{repeated}; it may be stuck in a loop"),
+            LoopAction::Interrupt => format!("{repeated}; stopping it"),
+            LoopAction::Correct => format!("{repeated}; asking it to change its approach"),
+        };
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::LoopDetected {
+                tool_name: self.tool_name.clone(),
+                repeats: self.repeats,
+                action: self.action,
+            },
+            content,
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+/// Run of identical values, e.g. tool calls, seen one after another
+#[derive(Debug, Default)]
+struct Run {
+    last: Option<String>,
+    repeats: u32,
+}
+
+impl Run {
+    /// Count `value`; returns the length of the run if it reached `max`,
+    /// starting a new count
+    fn push(&mut self, value: String, max: u32) -> Option<u32> {
+        if self.last.as_ref() == Some(&value) {
+            self.repeats += 1;
+        } else {
+            self.last = Some(value);
+            self.repeats = 1;
+        }
+        if self.repeats < max.max(2) {
+            return None;
+        }
+        let repeats = self.repeats;
+        self.repeats = 0;
+        Some(repeats)
+    }
+}
+
+/// Repeats in the entries of one run, as the normalizer adds and updates them
+#[derive(Debug)]
+pub struct LoopDetector {
+    config: LoopDetection,
+    tool_calls: Run,
+    messages: Run,
+    /// Assistant message still streaming, by entry index; it is compared once
+    /// the next entry starts
+    open_message: Option<(usize, String)>,
+}
+
+impl LoopDetector {
+    pub fn new(config: LoopDetection) -> Self {
+        Self {
+            config,
+            tool_calls: Run::default(),
+            messages: Run::default(),
+            open_message: None,
+        }
+    }
+
+    /// Take the entry the normalizer added or updated at `index`. Returns
+    /// the loop a tool call completes; [`Self::finish`] is to be called
+    /// before each entry is added, for the message before it.
+    pub fn observe(
+        &mut self,
+        index: usize,
+        entry: &NormalizedEntry,
+        added: bool,
+    ) -> Option<DetectedLoop> {
+        match &entry.entry_type {
+            NormalizedEntryType::AssistantMessage
+                if added
+                    || self
+                        .open_message
+                        .as_ref()
+                        .is_some_and(|(open, _)| *open == index) =>
+            {
+                self.open_message = Some((index, entry.content.trim().to_string()));
+                None
+            }
+            // Calls are compared as first made, before their results arrive
+            NormalizedEntryType::ToolUse {
+                tool_name,
+                action_type,
+                ..
+            } if added => {
+                let call = format!(
+                    "{tool_name}\0{}",
+                    serde_json::to_string(action_type).unwrap_or_default()
+                );
+                let max = self
+                    .config
+                    .max_repeated_tool_calls
+                    .unwrap_or(DEFAULT_MAX_REPEATED_TOOL_CALLS);
+                let repeats = self.tool_calls.push(call, max)?;
+                Some(DetectedLoop {
+                    tool_name: Some(tool_name.clone()),
+                    repeats,
+                    action: self.config.action,
+                })
+            }
+            // A new prompt starts over
+            NormalizedEntryType::UserMessage if added => {
+                self.tool_calls = Run::default();
+                self.messages = Run::default();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Compare the assistant message still open, as it is complete once the
+    /// next entry starts or the run ends
+    pub fn finish(&mut self) -> Option<DetectedLoop> {
+        let (_, message) = self.open_message.take()?;
+        if message.is_empty() {
+            return None;
+        }
+        let max = self
+            .config
+            .max_repeated_messages
+            .unwrap_or(DEFAULT_MAX_REPEATED_MESSAGES);
+        let repeats = self.messages.push(message, max)?;
+        Some(DetectedLoop {
+            tool_name: None,
+            repeats,
+            action: self.config.action,
+        })
+    }
+}
+
+/// Loop detection settings of `action`'s profile, if it has any
+pub fn loop_detection(action: &ExecutorAction, configs: &ExecutorConfigs) -> Option<LoopDetection> {
+    let profile = match action.typ() {
+        ExecutorActionType::CodingAgentInitialRequest(request) => &request.executor_profile_id,
+        ExecutorActionType::CodingAgentFollowUpRequest(request) => &request.executor_profile_id,
+        ExecutorActionType::ScriptRequest(_) => return None,
+    };
+    configs
+        .get_coding_agent(profile)
+        .and_then(|agent| agent.run_overrides().runtime.loop_detection.clone())
+}
+
+/// Receive each loop reported in `msg_store`'s conversation until its
+/// execution finishes
+pub fn watch_loops(msg_store: &MsgStore) -> mpsc::UnboundedReceiver<DetectedLoop> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut log_rx = msg_store.get_receiver();
+    tokio::spawn(async move {
+        loop {
+            match log_rx.recv().await {
+                Ok(LogMsg::JsonPatch(patch)) => {
+                    let Some((
+                        _,
+                        NormalizedEntry {
+                            entry_type:
+                                NormalizedEntryType::LoopDetected {
+                                    tool_name,
+                                    repeats,
+                                    action,
+                                },
+                            ..
+                        },
+                    )) = extract_normalized_entry_from_patch(&patch)
+                    else {
+                        continue;
+                    };
+                    let detected = DetectedLoop {
+                        tool_name,
+                        repeats,
+                        action,
+                    };
+                    if tx.send(detected).is_err() {
+                        break;
+                    }
+                }
+                Ok(LogMsg::Finished) | Err(RecvError::Closed) => break,
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::{ActionType, ToolStatus};
+
+    fn entry(entry_type: NormalizedEntryType, content: &str) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type,
+            content: content.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+
+    fn read(path: &str) -> NormalizedEntry {
+        entry(
+            NormalizedEntryType::ToolUse {
+                tool_name: "Read".to_string(),
+                action_type: ActionType::FileRead {
+                    path: path.to_string(),
+                },
+                status: ToolStatus::Created,
+            },
+            path,
+        )
+    }
+
+    #[test]
+    fn test_repeated_tool_calls_and_messages_detected() {
+        let mut detector = LoopDetector::new(LoopDetection {
+            max_repeated_tool_calls: Some(3),
+            max_repeated_messages: Some(2),
+            action: LoopAction::Interrupt,
+            correction: None,
+        });
+        let mut index = 0;
+        let mut add = |detector: &mut LoopDetector, entry: NormalizedEntry| {
+            let finished = detector.finish();
+            let found = detector.observe(index, &entry, true);
+            index += 1;
+            finished.into_iter().chain(found).collect::<Vec<_>>()
+        };
+
+        // Reads of another file and thinking in between don't break the run
+        let mut detected = vec![];
+        detected.extend(add(&mut detector, read("a.rs")));
+        detected.extend(add(&mut detector, read("b.rs")));
+        detected.extend(add(&mut detector, read("b.rs")));
+        detected.extend(add(
+            &mut detector,
+            entry(NormalizedEntryType::Thinking, "hm"),
+        ));
+        detected.extend(add(&mut detector, read("b.rs")));
+        assert_eq!(
+            detected,
+            vec![DetectedLoop {
+                tool_name: Some("Read".to_string()),
+                repeats: 3,
+                action: LoopAction::Interrupt,
+            }]
+        );
+
+        // A message is only compared once complete
+        let message = |content| entry(NormalizedEntryType::AssistantMessage, content);
+        assert!(add(&mut detector, message("Let me")).is_empty());
+        assert_eq!(detector.observe(5, &message("Let me check"), false), None);
+        assert!(add(&mut detector, message("Let me check")).is_empty());
+        let found = add(&mut detector, read("c.rs"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].tool_name, None);
+        assert!(
+            found[0]
+                .to_normalized_entry()
+                .content
+                .ends_with("2 times in a row; stopping it")
+        );
+        assert_eq!(detector.finish(), None);
+    }
+}
@@ -2,10 +2,15 @@
 //!
 //! These helpers abstract over JSON vs TOML formats used by different agents.
 
-use std::{collections::HashMap, sync::LazyLock};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
 
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 use tokio::fs;
 use ts_rs::TS;
 
@@ -45,6 +50,17 @@ impl McpConfig {
     }
 }
 
+/// Which MCP config an agent that supports more than one should read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum McpConfigScope {
+    /// The config in the user's home directory
+    #[default]
+    User,
+    /// The worktree's own config, when it has one, so each project can bring
+    /// its own tool servers
+    Project,
+}
+
 /// Read an agent's external config file (JSON or TOML) and normalize it to serde_json::Value.
 pub async fn read_agent_config(
     config_path: &std::path::Path,
@@ -85,6 +101,209 @@ pub async fn write_agent_config(
     Ok(())
 }
 
+/// Outcome of [`merge_mcp_servers_into_config`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpMergeReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Servers changed outside vibe-kanban, or defined by the user under a
+    /// managed name; the file's version was kept
+    pub conflicts: Vec<String>,
+    /// Servers vibe-kanban never wrote, left as they were
+    pub kept: Vec<String>,
+    /// Copy of the file as it was before the write
+    pub backup_path: Option<String>,
+}
+
+impl McpMergeReport {
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Hashes of the servers vibe-kanban last wrote, per config file. Kept in
+/// the asset directory so the agents' own config directories only ever hold
+/// their config.
+fn managed_servers_path() -> PathBuf {
+    workspace_utils::assets::asset_dir().join("mcp_managed_servers.json")
+}
+
+type ManagedServers = HashMap<String, HashMap<String, String>>;
+
+async fn read_managed_servers() -> ManagedServers {
+    match fs::read_to_string(managed_servers_path()).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring unreadable managed MCP server record: {e}");
+            ManagedServers::new()
+        }),
+        Err(_) => ManagedServers::new(),
+    }
+}
+
+/// Hash of a server definition that ignores key order, which TOML configs
+/// don't keep
+fn server_hash(server: &Value) -> String {
+    fn canonical(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), canonical(v)))
+                    .collect::<BTreeMap<_, _>>()
+                    .into_iter()
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+            other => other.clone(),
+        }
+    }
+    Sha256::digest(canonical(server).to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Merge the servers vibe-kanban manages into those already in a config.
+///
+/// `last_written` holds the hashes of the servers written last time. A
+/// managed server replaces the existing one only if it is still exactly
+/// what was written; anything else in the file is the user's and is kept.
+/// With `force`, managed servers replace conflicting ones. Returns the
+/// merged servers, the hashes to remember and what changed.
+pub fn merge_mcp_servers(
+    existing: &ServerMap,
+    managed: &HashMap<String, Value>,
+    last_written: &HashMap<String, String>,
+    force: bool,
+) -> (ServerMap, HashMap<String, String>, McpMergeReport) {
+    let mut merged = existing.clone();
+    let mut written = HashMap::new();
+    let mut report = McpMergeReport::default();
+    let untouched = |name: &str, server: &Value| {
+        last_written.get(name).map(String::as_str) == Some(server_hash(server).as_str())
+    };
+
+    let mut names: Vec<_> = managed.keys().collect();
+    names.sort();
+    for name in names {
+        let server = &managed[name];
+        match existing.get(name) {
+            None => {
+                merged.insert(name.clone(), server.clone());
+                report.added.push(name.clone());
+            }
+            Some(current) if current == server => {
+                if !last_written.contains_key(name) {
+                    // Defined by the user exactly like this; leave it theirs
+                    continue;
+                }
+            }
+            Some(current) if force || untouched(name, current) => {
+                merged.insert(name.clone(), server.clone());
+                report.updated.push(name.clone());
+            }
+            Some(_) => {
+                report.conflicts.push(name.clone());
+                continue;
+            }
+        }
+        written.insert(name.clone(), server_hash(server));
+    }
+
+    for (name, current) in existing {
+        if managed.contains_key(name) {
+            continue;
+        }
+        if untouched(name, current) {
+            merged.shift_remove(name);
+            report.removed.push(name.clone());
+        } else {
+            report.kept.push(name.clone());
+        }
+    }
+
+    (merged, written, report)
+}
+
+/// Write `managed` into an agent's config without clobbering servers the
+/// user defined there; see [`merge_mcp_servers`]. The previous file is
+/// copied to `<file>.bak` before it is changed.
+pub async fn merge_mcp_servers_into_config(
+    config_path: &Path,
+    mcp_config: &McpConfig,
+    managed: &HashMap<String, Value>,
+    force: bool,
+) -> Result<McpMergeReport, ExecutorError> {
+    let mut config = read_agent_config(config_path, mcp_config).await?;
+    let existing = mcp_config
+        .servers_path
+        .iter()
+        .try_fold(&config, |value, key| value.get(key))
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+
+    let config_key = config_path.to_string_lossy().to_string();
+    let mut managed_servers = read_managed_servers().await;
+    let last_written = managed_servers.remove(&config_key).unwrap_or_default();
+    let (merged, written, mut report) = merge_mcp_servers(&existing, managed, &last_written, force);
+    if report.is_unchanged() && written == last_written {
+        return Ok(report);
+    }
+
+    if !report.is_unchanged() {
+        if fs::try_exists(config_path)
+            .await
+            .map_err(ExecutorError::Io)?
+        {
+            let mut backup = config_path.as_os_str().to_owned();
+            backup.push(".bak");
+            let backup = PathBuf::from(backup);
+            fs::copy(config_path, &backup)
+                .await
+                .map_err(ExecutorError::Io)?;
+            report.backup_path = Some(backup.to_string_lossy().to_string());
+        } else if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(ExecutorError::Io)?;
+        }
+        set_servers_at(&mut config, &mcp_config.servers_path, merged);
+        write_agent_config(config_path, mcp_config, &config).await?;
+    }
+
+    if !written.is_empty() {
+        managed_servers.insert(config_key, written);
+    }
+    let record_path = managed_servers_path();
+    if let Some(parent) = record_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(ExecutorError::Io)?;
+    }
+    fs::write(record_path, serde_json::to_string_pretty(&managed_servers)?)
+        .await
+        .map_err(ExecutorError::Io)?;
+
+    Ok(report)
+}
+
+/// Put `servers` at `path` in `config`, creating the objects along the way
+fn set_servers_at(config: &mut Value, path: &[String], servers: ServerMap) {
+    let mut current = config;
+    for key in path {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .expect("just made an object")
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    *current = Value::Object(servers);
+}
+
 type ServerMap = Map<String, Value>;
 
 fn is_http_server(s: &Map<String, Value>) -> bool {
@@ -299,9 +518,69 @@ impl CodingAgent {
             CodingAgent::Codex(_) => Codex,
             CodingAgent::Opencode(_) => Opencode,
             CodingAgent::Copilot(..) => Copilot,
+            // Runs without MCP servers
+            CodingAgent::ClaudeApi(_) => Passthrough,
         };
 
         let canonical = PRECONFIGURED_MCP_SERVERS.clone();
         apply_adapter(adapter, canonical)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn servers(value: Value) -> ServerMap {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_merge_keeps_user_servers_and_reports_conflicts() {
+        let vk_old = json!({ "command": "npx", "args": ["vibe-kanban@1", "--mcp"] });
+        let vk_new = json!({ "command": "npx", "args": ["vibe-kanban@2", "--mcp"] });
+        let stale = json!({ "command": "stale-server" });
+        let last_written = HashMap::from([
+            ("vibe_kanban".to_string(), server_hash(&vk_old)),
+            ("stale".to_string(), server_hash(&stale)),
+            (
+                "playwright".to_string(),
+                server_hash(&json!({ "command": "old" })),
+            ),
+        ]);
+        let existing = servers(json!({
+            "vibe_kanban": vk_old,
+            "stale": stale,
+            // Edited by hand since it was written
+            "playwright": { "command": "pw", "args": ["--headless"] },
+            "mine": { "command": "my-server" },
+        }));
+        let managed = HashMap::from([
+            ("vibe_kanban".to_string(), vk_new.clone()),
+            ("playwright".to_string(), json!({ "command": "pw" })),
+            (
+                "context7".to_string(),
+                json!({ "type": "http", "url": "https://x" }),
+            ),
+        ]);
+
+        let (merged, written, report) =
+            merge_mcp_servers(&existing, &managed, &last_written, false);
+        assert_eq!(report.added, vec!["context7"]);
+        assert_eq!(report.updated, vec!["vibe_kanban"]);
+        assert_eq!(report.removed, vec!["stale"]);
+        assert_eq!(report.conflicts, vec!["playwright"]);
+        assert_eq!(report.kept, vec!["mine"]);
+        assert_eq!(merged["vibe_kanban"], vk_new);
+        assert_eq!(merged["playwright"]["args"], json!(["--headless"]));
+        assert!(merged.contains_key("mine"));
+        assert!(!merged.contains_key("stale"));
+        assert!(!written.contains_key("playwright"));
+
+        let (merged, _, report) = merge_mcp_servers(&existing, &managed, &last_written, true);
+        assert_eq!(report.conflicts, Vec::<String>::new());
+        assert_eq!(merged["playwright"], json!({ "command": "pw" }));
+    }
+}
@@ -0,0 +1,215 @@
+//! Direct binaries for `npx -y <package>` commands.
+//!
+//! Going through `npx` costs seconds per spawn, even when the package is
+//! already in npm's cache, because npx re-resolves the package every time.
+//! Once npx has installed a package into `<npm cache>/_npx`, its binary can
+//! be run directly. Lookups are cached per package spec and revalidated
+//! against the installed `package.json`, so bumping the pinned version in a
+//! base command, or npx replacing the package, resolves afresh.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{LazyLock, RwLock},
+    time::SystemTime,
+};
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ResolvedBin {
+    bin: PathBuf,
+    manifest: PathBuf,
+    version: String,
+}
+
+static RESOLVED: LazyLock<RwLock<HashMap<String, ResolvedBin>>> = LazyLock::new(Default::default);
+
+/// The package spec and remaining arguments of an `npx` invocation that only
+/// uses `-y`/`--yes`, e.g. `["-y", "claude-flow", "--version"]`
+pub fn split_npx_args(args: &[String]) -> Option<(&str, &[String])> {
+    let start = args.iter().position(|arg| arg != "-y" && arg != "--yes")?;
+    let spec = args[start].as_str();
+    if spec.starts_with('-') {
+        // Options like `--package` or `-c` change what npx runs
+        return None;
+    }
+    Some((spec, &args[start + 1..]))
+}
+
+/// Installed binary of `spec` (`name`, `name@1.2.3`, `@scope/name@1.2.3`) in
+/// npx's cache, if npx has installed it before. Dist-tags like `latest`
+/// can't be checked offline and never resolve.
+pub fn cached_npx_bin(spec: &str) -> Option<PathBuf> {
+    if let Some(resolved) = RESOLVED.read().unwrap().get(spec)
+        && manifest_version(&resolved.manifest).as_deref() == Some(resolved.version.as_str())
+        && resolved.bin.is_file()
+    {
+        return Some(resolved.bin.clone());
+    }
+
+    let resolved = resolve_in(&npm_cache_dir()?, spec);
+    let mut cache = RESOLVED.write().unwrap();
+    match resolved {
+        Some(resolved) => {
+            let bin = resolved.bin.clone();
+            cache.insert(spec.to_string(), resolved);
+            Some(bin)
+        }
+        None => {
+            cache.remove(spec);
+            None
+        }
+    }
+}
+
+fn npm_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("npm_config_cache") {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        dirs::data_local_dir().map(|dir| dir.join("npm-cache"))
+    } else {
+        dirs::home_dir().map(|home| home.join(".npm"))
+    }
+}
+
+/// `name` and exact version of a package spec; the version is `None` when
+/// unpinned
+fn parse_spec(spec: &str) -> Option<(&str, Option<&str>)> {
+    // Skip the `@` of a scope when looking for the version separator
+    let (name, version) = match spec.get(1..)?.find('@') {
+        Some(at) => (&spec[..at + 1], Some(&spec[at + 2..])),
+        None => (spec, None),
+    };
+    match version {
+        Some(version) if !version.starts_with(|c: char| c.is_ascii_digit()) => None,
+        _ => Some((name, version)),
+    }
+}
+
+fn manifest_version(manifest: &Path) -> Option<String> {
+    let package: Value = serde_json::from_str(&fs::read_to_string(manifest).ok()?).ok()?;
+    package["version"].as_str().map(str::to_string)
+}
+
+fn resolve_in(cache_dir: &Path, spec: &str) -> Option<ResolvedBin> {
+    let (name, version) = parse_spec(spec)?;
+    let mut candidates = Vec::new();
+    for install in fs::read_dir(cache_dir.join("_npx")).ok()?.flatten() {
+        let manifest = install
+            .path()
+            .join("node_modules")
+            .join(name)
+            .join("package.json");
+        let Some(package) = fs::read_to_string(&manifest)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+        else {
+            continue;
+        };
+        let Some(installed) = package["version"].as_str() else {
+            continue;
+        };
+        if version.is_some_and(|version| version != installed) {
+            continue;
+        }
+        let Some(bin) = bin_name(&package, name)
+            .map(|bin| bin_path(&install.path().join("node_modules").join(".bin"), &bin))
+            .filter(|bin| bin.is_file())
+        else {
+            continue;
+        };
+        let modified = fs::metadata(&manifest)
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        candidates.push((
+            modified,
+            ResolvedBin {
+                bin,
+                version: installed.to_string(),
+                manifest,
+            },
+        ));
+    }
+    // Unpinned specs run whichever copy npx installed last
+    candidates
+        .into_iter()
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, resolved)| resolved)
+}
+
+/// The binary npx runs for a package: its only `bin`, or the one named
+/// after the package
+fn bin_name(package: &Value, name: &str) -> Option<String> {
+    let unscoped = name.rsplit('/').next().unwrap_or(name);
+    match &package["bin"] {
+        Value::String(_) => Some(unscoped.to_string()),
+        Value::Object(bins) if bins.len() == 1 => bins.keys().next().cloned(),
+        Value::Object(bins) => bins.contains_key(unscoped).then(|| unscoped.to_string()),
+        _ => None,
+    }
+}
+
+fn bin_path(bin_dir: &Path, bin: &str) -> PathBuf {
+    if cfg!(windows) {
+        bin_dir.join(format!("{bin}.cmd"))
+    } else {
+        bin_dir.join(bin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(cache_dir: &Path, hash: &str, name: &str, version: &str, bin: &str) {
+        let modules = cache_dir.join("_npx").join(hash).join("node_modules");
+        fs::create_dir_all(modules.join(name)).unwrap();
+        fs::create_dir_all(modules.join(".bin")).unwrap();
+        fs::write(
+            modules.join(name).join("package.json"),
+            serde_json::json!({ "name": name, "version": version, "bin": { bin: "cli.js" } })
+                .to_string(),
+        )
+        .unwrap();
+        fs::write(bin_path(&modules.join(".bin"), bin), "").unwrap();
+    }
+
+    #[test]
+    fn test_split_npx_args() {
+        let args: Vec<String> = ["-y", "@anthropic-ai/claude-code@2.0.76", "-p"]
+            .map(String::from)
+            .to_vec();
+        let (spec, rest) = split_npx_args(&args).unwrap();
+        assert_eq!(spec, "@anthropic-ai/claude-code@2.0.76");
+        assert_eq!(rest, ["-p".to_string()]);
+        assert!(split_npx_args(&["--package".to_string(), "x".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_resolves_installed_version() {
+        let cache_dir = std::env::temp_dir().join(format!("vk-npx-{}", uuid::Uuid::new_v4()));
+        install(
+            &cache_dir,
+            "a1",
+            "@anthropic-ai/claude-code",
+            "2.0.76",
+            "claude",
+        );
+        install(&cache_dir, "b2", "claude-flow", "2.7.0", "claude-flow");
+
+        let claude = resolve_in(&cache_dir, "@anthropic-ai/claude-code@2.0.76").unwrap();
+        assert!(claude.bin.starts_with(cache_dir.join("_npx").join("a1")));
+        assert_eq!(claude.version, "2.0.76");
+        // A different pinned version is a miss until npx installs it
+        assert!(resolve_in(&cache_dir, "@anthropic-ai/claude-code@2.0.77").is_none());
+        assert!(resolve_in(&cache_dir, "claude-flow@alpha").is_none());
+        assert_eq!(
+            resolve_in(&cache_dir, "claude-flow").unwrap().version,
+            "2.7.0"
+        );
+        fs::remove_dir_all(cache_dir).unwrap();
+    }
+}
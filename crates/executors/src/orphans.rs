@@ -0,0 +1,347 @@
+//! Process groups of running agents, tracked so none outlive the server.
+//!
+//! Agents run in process groups of their own, and a claude-flow swarm keeps
+//! spending API credits for as long as its group lives. The [`ChildRegistry`]
+//! records the group of every running agent, in memory and in a file per
+//! server under the vibe kanban temp directory. Dropping the [`OrphanGuard`]
+//! the server holds, including while unwinding from a panic, kills the groups
+//! still recorded. A server that crashed can't do that, so its successor calls
+//! [`ChildRegistry::kill_all_orphans`] on startup to kill the groups left in
+//! the files of servers that are gone.
+//!
+//! Process groups are a unix concept; elsewhere groups are recorded but not
+//! killed.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use command_group::AsyncGroupChild;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How long groups get to exit after SIGTERM before they're killed
+const TERM_GRACE: Duration = Duration::from_secs(2);
+
+static GLOBAL: LazyLock<ChildRegistry> = LazyLock::new(|| {
+    ChildRegistry::new(workspace_utils::path::get_vibe_kanban_temp_dir().join("process-groups"))
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct RecordedGroup {
+    pgid: u32,
+    /// Start time of the group leader, to tell it apart from a later
+    /// process given the same id; only known on Linux
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_time: Option<u64>,
+}
+
+/// Process groups of the agents this server is running
+pub struct ChildRegistry {
+    dir: PathBuf,
+    groups: Mutex<HashMap<Uuid, RecordedGroup>>,
+}
+
+impl ChildRegistry {
+    /// A registry keeping its files in `dir`
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn global() -> &'static ChildRegistry {
+        &GLOBAL
+    }
+
+    /// Record the process group of execution `id`'s agent
+    pub fn register(&self, id: Uuid, child: &AsyncGroupChild) {
+        // Spawned as the leader of its own group, so the pid is the pgid
+        let Some(pgid) = child.id() else {
+            return;
+        };
+        let group = RecordedGroup {
+            pgid,
+            start_time: process_start_time(pgid),
+        };
+        let mut groups = self.groups.lock().unwrap();
+        groups.insert(id, group);
+        self.persist(&groups);
+    }
+
+    /// Forget execution `id`'s group once its agent has exited
+    pub fn unregister(&self, id: &Uuid) {
+        let mut groups = self.groups.lock().unwrap();
+        if groups.remove(id).is_some() {
+            self.persist(&groups);
+        }
+    }
+
+    /// Kill the groups of every agent this server is running. Groups whose
+    /// leader is gone, or whose id now belongs to another process, are left
+    /// alone.
+    pub fn kill_all(&self) {
+        let mut groups = self.groups.lock().unwrap();
+        let mut recorded: Vec<_> = groups.drain().map(|(_, group)| group).collect();
+        recorded.retain(|group| group.is_alive());
+        if !recorded.is_empty() {
+            tracing::info!("Killing {} agent process groups", recorded.len());
+            terminate(&recorded);
+        }
+        self.persist(&groups);
+    }
+
+    /// Kill the groups left behind by servers that are no longer running,
+    /// returning how many were still alive
+    pub fn kill_all_orphans(&self) -> usize {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return 0,
+            Err(e) => {
+                tracing::warn!("Failed to read {}: {}", self.dir.display(), e);
+                return 0;
+            }
+        };
+        let mut orphans = Vec::new();
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let Some(server_pid) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            if server_pid == std::process::id() || is_running(server_pid) {
+                continue;
+            }
+            orphans.extend(read_groups(&path).into_values());
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+        orphans.retain(|group| group.is_alive());
+        if !orphans.is_empty() {
+            tracing::warn!(
+                "Killing {} agent process groups left behind by a previous server",
+                orphans.len()
+            );
+            terminate(&orphans);
+        }
+        orphans.len()
+    }
+
+    /// Guard that kills every recorded group when dropped
+    pub fn guard(&'static self) -> OrphanGuard {
+        OrphanGuard { registry: self }
+    }
+
+    fn file(&self) -> PathBuf {
+        self.dir.join(format!("{}.json", std::process::id()))
+    }
+
+    fn persist(&self, groups: &HashMap<Uuid, RecordedGroup>) {
+        let path = self.file();
+        let result = if groups.is_empty() {
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            }
+        } else {
+            fs::create_dir_all(&self.dir).and_then(|()| {
+                let json = serde_json::to_vec(groups).map_err(io::Error::other)?;
+                fs::write(&path, json)
+            })
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to record agent process groups: {}", e);
+        }
+    }
+}
+
+/// Kills the agents' process groups when the server goes away
+#[must_use]
+pub struct OrphanGuard {
+    registry: &'static ChildRegistry,
+}
+
+impl Drop for OrphanGuard {
+    fn drop(&mut self) {
+        self.registry.kill_all();
+    }
+}
+
+fn read_groups(path: &Path) -> HashMap<Uuid, RecordedGroup> {
+    fs::read(path)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+impl RecordedGroup {
+    /// Whether the group still exists and is the one recorded
+    #[cfg(unix)]
+    fn is_alive(&self) -> bool {
+        use nix::{sys::signal::killpg, unistd::Pid};
+
+        if killpg(Pid::from_raw(self.pgid as i32), None).is_err() {
+            return false;
+        }
+        // While a group exists its id isn't handed to new processes, so only
+        // a live leader can be a different process than the one recorded
+        match (self.start_time, process_start_time(self.pgid)) {
+            (Some(recorded), Some(current)) => recorded == current,
+            _ => true,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn is_alive(&self) -> bool {
+        false
+    }
+}
+
+/// SIGTERM `groups`, then SIGKILL the ones still alive after [`TERM_GRACE`]
+#[cfg(unix)]
+fn terminate(groups: &[RecordedGroup]) {
+    use nix::{
+        sys::signal::{Signal, killpg},
+        unistd::Pid,
+    };
+
+    let signal_all = |groups: &[RecordedGroup], signal| {
+        for group in groups {
+            if let Err(e) = killpg(Pid::from_raw(group.pgid as i32), signal) {
+                tracing::debug!("Failed to signal process group {}: {}", group.pgid, e);
+            }
+        }
+    };
+    signal_all(groups, Signal::SIGTERM);
+    let deadline = std::time::Instant::now() + TERM_GRACE;
+    let mut remaining = groups.to_vec();
+    while !remaining.is_empty() && std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+        remaining.retain(|group| group.is_alive());
+    }
+    signal_all(&remaining, Signal::SIGKILL);
+}
+
+#[cfg(not(unix))]
+fn terminate(_groups: &[RecordedGroup]) {}
+
+fn is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        use nix::{sys::signal::kill, unistd::Pid};
+
+        kill(Pid::from_raw(pid as i32), None).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        false
+    }
+}
+
+/// Start time of process `pid` in clock ticks since boot
+fn process_start_time(pid: u32) -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // The command name may contain spaces; fields after it are plain.
+        // `starttime` is the 22nd field, the 20th after the name.
+        let (_, rest) = stat.rsplit_once(')')?;
+        rest.split_whitespace().nth(19)?.parse().ok()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use command_group::AsyncCommandGroup;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_kills_groups_left_by_a_dead_server() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = std::env::temp_dir().join(format!("vk-process-groups-{}", Uuid::new_v4()));
+        let registry = ChildRegistry::new(dir.clone());
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "sleep 60 & sleep 60"])
+            .group_spawn()
+            .unwrap();
+        registry.register(Uuid::new_v4(), &child);
+        assert!(registry.file().exists());
+
+        // Nothing to do while the server that started the agent runs
+        assert_eq!(registry.kill_all_orphans(), 0);
+
+        // Leave the group behind as a server that's gone would
+        fs::rename(registry.file(), dir.join("999999999.json")).unwrap();
+        registry.groups.lock().unwrap().clear();
+        assert_eq!(registry.kill_all_orphans(), 1);
+
+        let status = tokio::time::timeout(Duration::from_secs(10), child.wait())
+            .await
+            .expect("orphaned group should be killed")
+            .unwrap();
+        assert_eq!(status.signal(), Some(nix::libc::SIGTERM));
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_kill_all_spares_reused_process_ids() {
+        let dir = std::env::temp_dir().join(format!("vk-process-groups-{}", Uuid::new_v4()));
+        let registry = ChildRegistry::new(dir.clone());
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("60")
+            .group_spawn()
+            .unwrap();
+        let id = Uuid::new_v4();
+        registry.register(id, &child);
+
+        // The recorded group as if its leader had exited and the id had been
+        // handed to another process since
+        if let Some(group) = registry.groups.lock().unwrap().get_mut(&id) {
+            *group.start_time.as_mut().unwrap() += 1;
+        }
+        registry.kill_all();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), child.wait())
+                .await
+                .is_err(),
+            "a process that isn't the recorded one must not be killed"
+        );
+        assert!(!registry.file().exists());
+
+        child.kill().await.unwrap();
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_unregistered_groups_are_forgotten() {
+        let dir = std::env::temp_dir().join(format!("vk-process-groups-{}", Uuid::new_v4()));
+        let registry = ChildRegistry::new(dir.clone());
+        let mut child = tokio::process::Command::new("true").group_spawn().unwrap();
+        let id = Uuid::new_v4();
+        registry.register(id, &child);
+        child.wait().await.unwrap();
+
+        registry.unregister(&id);
+        assert!(registry.groups.lock().unwrap().is_empty());
+        assert!(!registry.file().exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
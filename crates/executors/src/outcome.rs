@@ -0,0 +1,383 @@
+//! Structured results of runs.
+//!
+//! A run ends with an [`ExecutorExitResult`]: it succeeded with a
+//! [`RunSummary`], failed for a [`FailureReason`], was interrupted or timed
+//! out. Executors that know how their run went report it through the exit
+//! signal; otherwise it is put together from what is known once the process
+//! exits, see [`ExecutorExitResult::from_exit_status`].
+//!
+//! Agents with a stream-JSON protocol finish with a terminal `result` event
+//! that says whether the task succeeded, independently of how the process
+//! exits: claude-flow exits with 0 after a failed task, and may linger after
+//! reporting. [`report_outcome`] watches a copy of stdout for that event and
+//! ends the run through the exit signal with the [`ExecutionOutcome`] it
+//! reports.
+
+use std::{fmt, process::ExitStatus};
+
+use futures::{FutureExt, StreamExt, future::BoxFuture};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::{
+    executors::{ExecutorError, ExecutorExitResult, SpawnedChild},
+    logs::{
+        NormalizedEntry, NormalizedEntryError, NormalizedEntryType,
+        stderr_processor::ExecutorFailureReason,
+    },
+    stdout_dup::duplicate_stdout,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeStatus {
+    Success,
+    Error,
+    /// Stopped for going over the profile's cost or token budget
+    BudgetExceeded,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutcomeStats {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_turns: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_cost_usd: Option<f64>,
+}
+
+/// Outcome an agent reports in its terminal result event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionOutcome {
+    pub status: OutcomeStatus,
+    /// What the agent reported having done, or why it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub stats: OutcomeStats,
+}
+
+impl ExecutionOutcome {
+    pub fn is_success(&self) -> bool {
+        self.status == OutcomeStatus::Success
+    }
+}
+
+/// What a run reported about itself
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunSummary {
+    /// What the agent reported having done, or why it failed
+    pub summary: Option<String>,
+    pub stats: OutcomeStats,
+}
+
+/// Why a run failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureReason {
+    /// The agent reported failing its task
+    TaskFailed(RunSummary),
+    /// Stopped for going over the profile's cost or token budget
+    BudgetExceeded(RunSummary),
+    /// A known cause, found in stderr or in the executor's error
+    Classified(ExecutorFailureReason),
+    /// The executor ran into an error
+    Error(String),
+    /// The process exited unsuccessfully; without a code if a signal
+    /// killed it
+    Exited { code: Option<i32> },
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TaskFailed(RunSummary {
+                summary: Some(summary),
+                ..
+            })
+            | Self::BudgetExceeded(RunSummary {
+                summary: Some(summary),
+                ..
+            }) => f.write_str(summary),
+            Self::TaskFailed(_) => f.write_str("The agent reported a failure"),
+            Self::BudgetExceeded(_) => f.write_str("Budget exceeded"),
+            Self::Classified(reason) => f.write_str(match reason {
+                ExecutorFailureReason::AuthFailure => "The agent is not authenticated",
+                ExecutorFailureReason::RateLimited => "The agent was rate limited",
+                ExecutorFailureReason::OutOfMemory => "The agent ran out of memory",
+                ExecutorFailureReason::ResourceLimit => "The agent hit a resource limit",
+                ExecutorFailureReason::Network => "The agent lost its network connection",
+                ExecutorFailureReason::MissingBinary => "A command the agent needs is missing",
+                ExecutorFailureReason::ContextOverflow => {
+                    "The conversation outgrew the context window"
+                }
+            }),
+            Self::Error(message) => f.write_str(message),
+            Self::Exited { code: Some(code) } => write!(f, "The agent exited with code {code}"),
+            Self::Exited { code: None } => f.write_str("The agent was killed by a signal"),
+        }
+    }
+}
+
+impl From<&ExecutorError> for FailureReason {
+    fn from(error: &ExecutorError) -> Self {
+        match error {
+            ExecutorError::AuthRequired(_) => Self::Classified(ExecutorFailureReason::AuthFailure),
+            ExecutorError::ExecutableNotFound { .. } => {
+                Self::Classified(ExecutorFailureReason::MissingBinary)
+            }
+            error => Self::Error(error.to_string()),
+        }
+    }
+}
+
+impl From<ExecutionOutcome> for ExecutorExitResult {
+    fn from(outcome: ExecutionOutcome) -> Self {
+        let summary = RunSummary {
+            summary: outcome.summary,
+            stats: outcome.stats,
+        };
+        match outcome.status {
+            OutcomeStatus::Success => Self::Success(summary),
+            OutcomeStatus::Error => Self::Failed(FailureReason::TaskFailed(summary)),
+            OutcomeStatus::BudgetExceeded => Self::Failed(FailureReason::BudgetExceeded(summary)),
+        }
+    }
+}
+
+impl From<ExecutorExitResult> for ExecutionOutcome {
+    fn from(result: ExecutorExitResult) -> Self {
+        let (status, summary) = match result {
+            ExecutorExitResult::Success(summary) => (OutcomeStatus::Success, summary),
+            ExecutorExitResult::Failed(FailureReason::TaskFailed(summary)) => {
+                (OutcomeStatus::Error, summary)
+            }
+            ExecutorExitResult::Failed(FailureReason::BudgetExceeded(summary)) => {
+                (OutcomeStatus::BudgetExceeded, summary)
+            }
+            result => (
+                OutcomeStatus::Error,
+                RunSummary {
+                    summary: Some(result.to_string()),
+                    stats: OutcomeStats::default(),
+                },
+            ),
+        };
+        Self {
+            status,
+            summary: summary.summary,
+            stats: summary.stats,
+        }
+    }
+}
+
+impl fmt::Display for ExecutorExitResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Success(_) => f.write_str("Succeeded"),
+            Self::Failed(reason) => reason.fmt(f),
+            Self::Interrupted => f.write_str("Interrupted"),
+            Self::TimedOut(kind) => kind.fmt(f),
+        }
+    }
+}
+
+impl ExecutorExitResult {
+    /// Result of a run whose executor didn't report one, from how its
+    /// process exited and the failure its stderr points to, if any
+    pub fn from_exit_status(status: ExitStatus, stderr: Option<ExecutorFailureReason>) -> Self {
+        if status.success() {
+            return Self::Success(RunSummary::default());
+        }
+        Self::Failed(match stderr {
+            Some(reason) => FailureReason::Classified(reason),
+            None => FailureReason::Exited {
+                code: status.code(),
+            },
+        })
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    /// What the run reported about itself, if it reported anything
+    pub fn summary(&self) -> Option<&RunSummary> {
+        match self {
+            Self::Success(summary)
+            | Self::Failed(FailureReason::TaskFailed(summary))
+            | Self::Failed(FailureReason::BudgetExceeded(summary)) => Some(summary),
+            _ => None,
+        }
+    }
+
+    /// Known cause of a failed run
+    pub fn failure_reason(&self) -> Option<ExecutorFailureReason> {
+        match self {
+            Self::Failed(FailureReason::Classified(reason)) => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// Error entry saying why the run was stopped, if it was stopped rather
+    /// than ending by itself
+    pub fn stopped_entry(&self) -> Option<NormalizedEntry> {
+        match self {
+            Self::TimedOut(kind) => Some(kind.to_normalized_entry()),
+            Self::Failed(reason @ FailureReason::BudgetExceeded(_)) => Some(NormalizedEntry {
+                timestamp: None,
+                entry_type: NormalizedEntryType::ErrorMessage {
+                    error_type: NormalizedEntryError::Other,
+                },
+                content: reason.to_string(),
+                metadata: None,
+                agent_id: None,
+                agent_role: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Parses one stdout line into an outcome if it is the terminal result event
+pub type ResultParser = fn(&str) -> Option<ExecutionOutcome>;
+
+/// Finish `spawned` with the outcome of the first result event on its stdout
+/// recognized by `parse`. Without one, the
+/// executor's own exit signal or the process exit decides as before. Stdout
+/// is duplicated rather than consumed.
+pub fn report_outcome(
+    spawned: &mut SpawnedChild,
+    parse: ResultParser,
+) -> Result<(), ExecutorError> {
+    let mut stdout = duplicate_stdout(&mut spawned.child)?;
+    let (mut exit_tx, exit_rx) = oneshot::channel();
+    let mut executor_exit: BoxFuture<'static, _> = match spawned.exit_signal.replace(exit_rx) {
+        Some(rx) => rx.boxed(),
+        None => std::future::pending().boxed(),
+    };
+
+    tokio::spawn(async move {
+        let mut line = String::new();
+        let mut reading = true;
+        loop {
+            tokio::select! {
+                // Container stopped listening (process exited or was stopped)
+                _ = exit_tx.closed() => return,
+                result = &mut executor_exit => {
+                    if let Ok(result) = result {
+                        let _ = exit_tx.send(result);
+                    }
+                    return;
+                }
+                chunk = stdout.next(), if reading => {
+                    match chunk {
+                        Some(Ok(chunk)) => line.push_str(&chunk),
+                        // At EOF the last line may lack its newline
+                        _ => {
+                            reading = false;
+                            line.push('\n');
+                        }
+                    }
+                    while let Some(end) = line.find('\n') {
+                        if let Some(outcome) = parse(line[..end].trim()) {
+                            let _ = exit_tx.send(outcome.into());
+                            return;
+                        }
+                        line.drain(..=end);
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use command_group::AsyncCommandGroup;
+    use tokio::process::Command;
+
+    use super::*;
+
+    fn parse_done(line: &str) -> Option<ExecutionOutcome> {
+        let status = match line.strip_prefix("done: ")? {
+            "ok" => OutcomeStatus::Success,
+            _ => OutcomeStatus::Error,
+        };
+        Some(ExecutionOutcome {
+            status,
+            summary: None,
+            stats: OutcomeStats::default(),
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_result_from_status_stderr_and_result_event() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let exited = |code: i32| ExitStatus::from_raw(code << 8);
+        assert_eq!(
+            ExecutorExitResult::from_exit_status(exited(0), None),
+            ExecutorExitResult::Success(RunSummary::default())
+        );
+        let rate_limited = ExecutorExitResult::from_exit_status(
+            exited(1),
+            Some(ExecutorFailureReason::RateLimited),
+        );
+        assert_eq!(
+            rate_limited.failure_reason(),
+            Some(ExecutorFailureReason::RateLimited)
+        );
+        let killed = ExecutorExitResult::from_exit_status(ExitStatus::from_raw(9), None);
+        assert_eq!(
+            killed,
+            ExecutorExitResult::Failed(FailureReason::Exited { code: None })
+        );
+        assert_eq!(killed.to_string(), "The agent was killed by a signal");
+
+        // What the agent reported is kept for the session summary
+        let reported = ExecutorExitResult::from(ExecutionOutcome {
+            status: OutcomeStatus::BudgetExceeded,
+            summary: Some("Budget exceeded: the run cost $2.00".to_string()),
+            stats: OutcomeStats::default(),
+        });
+        assert!(!reported.is_success());
+        assert!(reported.stopped_entry().is_some());
+        assert_eq!(
+            reported.summary().and_then(|s| s.summary.as_deref()),
+            Some("Budget exceeded: the run cost $2.00")
+        );
+        assert_eq!(
+            ExecutionOutcome::from(reported).status,
+            OutcomeStatus::BudgetExceeded
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reports_result_before_process_exits() {
+        let child = Command::new("sh")
+            .args(["-c", "echo working; echo 'done: failed'; sleep 30"])
+            .stdout(std::process::Stdio::piped())
+            .group_spawn()
+            .unwrap();
+        let mut spawned = SpawnedChild::from(child);
+        report_outcome(&mut spawned, parse_done).unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), spawned.exit_signal.unwrap())
+            .await
+            .expect("outcome should be reported")
+            .unwrap();
+        assert_eq!(
+            result,
+            ExecutorExitResult::Failed(FailureReason::TaskFailed(RunSummary::default()))
+        );
+
+        spawned.child.kill().await.unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+//! Decoding of an agent's output pipes into text.
+//!
+//! Agents sometimes `cat` a binary file, and its bytes would reach the log
+//! parsers as replacement characters in the middle of their JSON. An
+//! [`OutputDecoder`] passes text through, holding back a character split
+//! across two reads, and replaces each run of chunks that aren't text (not
+//! UTF-8, or containing NUL bytes) with one placeholder line giving its size,
+//! which normalizers show like any other output. The raw bytes are kept in the
+//! profile's [`LogTee`](crate::log_tee::LogTee) files, which are written
+//! before decoding.
+
+use std::io;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt, future::ready, stream};
+
+/// Start of the line standing in for binary output
+pub const BINARY_PLACEHOLDER: &str = "[binary output omitted:";
+
+#[derive(Debug)]
+pub struct OutputDecoder {
+    /// Start of a character the last chunk ended in
+    partial: Vec<u8>,
+    /// Size of the binary output not yet reported
+    binary_bytes: usize,
+    at_line_start: bool,
+}
+
+impl Default for OutputDecoder {
+    fn default() -> Self {
+        Self {
+            partial: Vec::new(),
+            binary_bytes: 0,
+            at_line_start: true,
+        }
+    }
+}
+
+impl OutputDecoder {
+    /// Text to forward for `chunk`; empty while binary output continues
+    pub fn decode(&mut self, chunk: &[u8]) -> String {
+        let mut data = std::mem::take(&mut self.partial);
+        data.extend_from_slice(chunk);
+        let valid_up_to = match std::str::from_utf8(&data) {
+            Ok(_) => data.len(),
+            // Cut in the middle of a character, which the next chunk completes
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => {
+                self.binary_bytes += data.len();
+                return String::new();
+            }
+        };
+        if data[..valid_up_to].contains(&0) {
+            self.binary_bytes += data.len();
+            return String::new();
+        }
+        self.partial = data.split_off(valid_up_to);
+        let text = String::from_utf8(data).expect("checked to be UTF-8");
+        self.emit(&text)
+    }
+
+    /// What is left once the output ends
+    pub fn finish(&mut self) -> String {
+        self.binary_bytes += std::mem::take(&mut self.partial).len();
+        self.emit("")
+    }
+
+    /// `text`, after the placeholder for binary output before it, if any
+    fn emit(&mut self, text: &str) -> String {
+        let mut out = String::new();
+        if self.binary_bytes > 0 {
+            if !self.at_line_start {
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "{BINARY_PLACEHOLDER} {} bytes]\n",
+                std::mem::take(&mut self.binary_bytes)
+            ));
+        }
+        out.push_str(text);
+        if let Some(last) = out.chars().last() {
+            self.at_line_start = last == '\n';
+        }
+        out
+    }
+}
+
+/// The text of a pipe's `chunks`, with binary output replaced
+pub fn decode_output(
+    chunks: impl Stream<Item = io::Result<Bytes>>,
+) -> impl Stream<Item = io::Result<String>> {
+    let mut decoder = OutputDecoder::default();
+    chunks
+        .map(Some)
+        .chain(stream::once(ready(None)))
+        .filter_map(move |chunk| {
+            let text = match chunk {
+                Some(Ok(data)) => decoder.decode(&data),
+                Some(Err(e)) => return ready(Some(Err(e))),
+                None => decoder.finish(),
+            };
+            ready((!text.is_empty()).then_some(Ok(text)))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_binary_runs_replaced_and_split_characters_kept() {
+        let chunks: Vec<&[u8]> = vec![
+            b"{\"type\":\"caf\xc3",
+            b"\xa9\"}\nls",
+            b"\x7fELF\x02\x01\x01\x00\x00",
+            b"\xff\xfe\xfd",
+            b"{\"type\":\"done\"}\n",
+            b"\x89PNG",
+        ];
+        let decoded: Vec<String> =
+            decode_output(stream::iter(chunks).map(|chunk| Ok(Bytes::from_static(chunk))))
+                .map(Result::unwrap)
+                .collect()
+                .await;
+        assert_eq!(
+            decoded.concat(),
+            "{\"type\":\"café\"}\nls\n\
+             [binary output omitted: 12 bytes]\n\
+             {\"type\":\"done\"}\n\
+             [binary output omitted: 4 bytes]\n"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! What an agent may change, independent of the executor running it.
+//!
+//! A profile's [`AgentPermissionMode`] is mapped to each executor's own
+//! settings when it spawns: Claude Code's `--permission-mode`, the Codex
+//! sandbox, Droid's autonomy level and so on. Plan and read-only runs, e.g. a
+//! task attempt that only drafts a plan, are guaranteed not to modify files:
+//! an executor that can't enforce the mode refuses to start instead of
+//! running with more access than asked for. Modes an executor can only
+//! approximate more strictly, like accepting edits on agents that ask about
+//! everything or nothing, fall back to asking.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use ts_rs::TS;
+
+use crate::executors::{
+    BaseCodingAgent, CodingAgent, ExecutorError,
+    codex::{AskForApproval, SandboxMode},
+    droid::Autonomy,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AgentPermissionMode {
+    /// Explore and propose a plan without changing anything; implementing it
+    /// needs approval, where the agent asks for it
+    Plan,
+    /// Read and search only; tools that edit files or run commands are off
+    ReadOnly,
+    /// Edit files without asking, and ask before running commands
+    AcceptEdits,
+    /// Run without asking
+    Bypass,
+}
+
+impl AgentPermissionMode {
+    /// Whether `executor` can enforce the mode, rather than only approximate
+    /// it or not support it at all
+    pub fn is_enforced_by(self, executor: BaseCodingAgent) -> bool {
+        use AgentPermissionMode::*;
+
+        match executor {
+            BaseCodingAgent::ClaudeCode
+            | BaseCodingAgent::Codex
+            | BaseCodingAgent::Droid
+            | BaseCodingAgent::ClaudeApi => true,
+            BaseCodingAgent::Opencode => self != ReadOnly,
+            BaseCodingAgent::ClaudeFlow => self == Bypass,
+            BaseCodingAgent::Amp
+            | BaseCodingAgent::Gemini
+            | BaseCodingAgent::QwenCode
+            | BaseCodingAgent::CursorAgent
+            | BaseCodingAgent::Copilot => matches!(self, AcceptEdits | Bypass),
+        }
+    }
+
+    /// Adjust the agent's own permission settings to the mode, or fail when
+    /// its executor can't keep the agent from changing more than the mode
+    /// allows. Claude Code reads the mode from its overrides itself.
+    pub fn apply(self, agent: &mut CodingAgent) -> Result<(), ExecutorError> {
+        use AgentPermissionMode::*;
+
+        let executor = BaseCodingAgent::from(&*agent);
+        if !self.is_enforced_by(executor) {
+            return Err(ExecutorError::PermissionModeNotSupported {
+                executor,
+                mode: self,
+            });
+        }
+        match agent {
+            CodingAgent::ClaudeCode(claude) => {
+                claude.dangerously_skip_permissions = Some(self == Bypass);
+            }
+            CodingAgent::Codex(codex) => {
+                let (sandbox, ask_for_approval) = match self {
+                    Plan => (SandboxMode::ReadOnly, AskForApproval::OnRequest),
+                    ReadOnly => (SandboxMode::ReadOnly, AskForApproval::Never),
+                    AcceptEdits => (SandboxMode::WorkspaceWrite, AskForApproval::OnRequest),
+                    Bypass => (SandboxMode::DangerFullAccess, AskForApproval::Never),
+                };
+                codex.sandbox = Some(sandbox);
+                codex.ask_for_approval = Some(ask_for_approval);
+            }
+            CodingAgent::Droid(droid) => {
+                droid.autonomy = match self {
+                    Plan | ReadOnly => Autonomy::Normal,
+                    AcceptEdits => Autonomy::Low,
+                    Bypass => Autonomy::SkipPermissionsUnsafe,
+                };
+            }
+            CodingAgent::Opencode(opencode) => {
+                if self == Plan {
+                    opencode.mode = Some("plan".to_string());
+                }
+                opencode.auto_approve = self == Bypass;
+            }
+            CodingAgent::Amp(amp) => amp.dangerously_allow_all = Some(self == Bypass),
+            CodingAgent::Gemini(gemini) => gemini.yolo = Some(self == Bypass),
+            CodingAgent::QwenCode(qwen) => qwen.yolo = Some(self == Bypass),
+            CodingAgent::CursorAgent(cursor) => cursor.force = Some(self == Bypass),
+            CodingAgent::Copilot(copilot) => copilot.allow_all_tools = Some(self == Bypass),
+            // Asks before commands and edits alike when accepting edits
+            CodingAgent::ClaudeApi(claude_api) => {
+                claude_api.read_only = Some(matches!(self, Plan | ReadOnly));
+                claude_api.auto_approve = self == Bypass;
+            }
+            CodingAgent::ClaudeFlow(_) => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        executors::claude::types::PermissionMode,
+        profile::{ExecutorConfigs, ExecutorProfileId},
+    };
+
+    fn agent(executor: BaseCodingAgent) -> CodingAgent {
+        ExecutorConfigs::from_defaults()
+            .get_coding_agent(&ExecutorProfileId::new(executor))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_permission_modes_map_to_executor_settings() {
+        let mut codex = agent(BaseCodingAgent::Codex);
+        AgentPermissionMode::ReadOnly.apply(&mut codex).unwrap();
+        let CodingAgent::Codex(codex) = codex else {
+            unreachable!()
+        };
+        assert_eq!(codex.sandbox, Some(SandboxMode::ReadOnly));
+        assert_eq!(codex.ask_for_approval, Some(AskForApproval::Never));
+
+        // Claude Code stays in plan mode, with nothing approving its way out
+        let mut claude = agent(BaseCodingAgent::ClaudeCode);
+        claude.run_overrides_mut().permission_mode = Some(AgentPermissionMode::ReadOnly);
+        AgentPermissionMode::ReadOnly.apply(&mut claude).unwrap();
+        let CodingAgent::ClaudeCode(claude) = claude else {
+            unreachable!()
+        };
+        assert_eq!(claude.permission_mode(), PermissionMode::Plan);
+        assert_eq!(claude.dangerously_skip_permissions, Some(false));
+
+        let mut opencode = agent(BaseCodingAgent::Opencode);
+        AgentPermissionMode::Plan.apply(&mut opencode).unwrap();
+        assert!(matches!(
+            opencode,
+            CodingAgent::Opencode(opencode)
+                if opencode.mode.as_deref() == Some("plan") && !opencode.auto_approve
+        ));
+
+        // Agents that can't keep from editing refuse to run read only
+        let mut gemini = agent(BaseCodingAgent::Gemini);
+        assert!(matches!(
+            AgentPermissionMode::ReadOnly.apply(&mut gemini),
+            Err(ExecutorError::PermissionModeNotSupported {
+                executor: BaseCodingAgent::Gemini,
+                mode: AgentPermissionMode::ReadOnly,
+            })
+        ));
+        // ...but ask about edits they can't accept on their own
+        AgentPermissionMode::AcceptEdits.apply(&mut gemini).unwrap();
+        assert!(matches!(gemini, CodingAgent::Gemini(gemini) if gemini.yolo == Some(false)));
+    }
+}
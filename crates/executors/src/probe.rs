@@ -0,0 +1,156 @@
+//! Availability probing by running an agent's CLI.
+//!
+//! Config files only show that an agent was used at some point. Probing runs
+//! `<base command> --version` instead, with a timeout since `npx` may have to
+//! download the package first, and caches the answer per command so the
+//! settings page doesn't start a process on every render.
+
+use std::{
+    collections::HashMap,
+    process::Stdio,
+    sync::{LazyLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+use tokio::process::Command;
+
+use crate::{command::CommandBuilder, executors::AvailabilityInfo};
+
+/// Longest a `--version` run may take, including an `npx` download
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a probe result is reused
+pub const PROBE_TTL: Duration = Duration::from_secs(10 * 60);
+
+static VERSION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?").unwrap());
+
+/// When a command was probed and the version it reported
+type Probe = (Instant, Option<String>);
+
+static PROBES: LazyLock<RwLock<HashMap<String, Probe>>> = LazyLock::new(Default::default);
+
+/// First semver-looking version in a `--version` output
+pub fn parse_version(output: &str) -> Option<String> {
+    VERSION_RE
+        .find(output)
+        .map(|version| version.as_str().to_string())
+}
+
+/// Version reported by `<base_command> --version`, or `None` when the
+/// command can't be found, fails or doesn't answer in time
+pub async fn probe_version(base_command: &str) -> Option<String> {
+    if let Some((probed_at, version)) = PROBES.read().unwrap().get(base_command)
+        && probed_at.elapsed() < PROBE_TTL
+    {
+        return version.clone();
+    }
+
+    let version = run_version_command(base_command).await;
+    PROBES
+        .write()
+        .unwrap()
+        .insert(base_command.to_string(), (Instant::now(), version.clone()));
+    version
+}
+
+/// Availability from probing `base_command`: the installed version, or
+/// [`AvailabilityInfo::VersionTooOld`] when it is older than `min_version`.
+/// `None` when the command reports no version.
+pub async fn probe_availability(
+    base_command: &str,
+    min_version: Option<&str>,
+) -> Option<AvailabilityInfo> {
+    let version = probe_version(base_command).await?;
+    Some(match min_version {
+        Some(required) if is_older(&version, required) => AvailabilityInfo::VersionTooOld {
+            found: version,
+            required: required.to_string(),
+        },
+        _ => AvailabilityInfo::InstalledVersion { version },
+    })
+}
+
+/// Whether `version` comes before `required`. Only the numeric
+/// `major.minor.patch` part counts, so pre-releases of a version satisfy it.
+pub fn is_older(version: &str, required: &str) -> bool {
+    fn numeric(version: &str) -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    numeric(version) < numeric(required)
+}
+
+/// Forget cached probes, e.g. after installing or upgrading an agent
+pub fn clear_probe_cache() {
+    PROBES.write().unwrap().clear();
+}
+
+async fn run_version_command(base_command: &str) -> Option<String> {
+    let (program, args) = CommandBuilder::new(base_command)
+        .params(["--version"])
+        .build_initial()
+        .ok()?
+        .into_resolved()
+        .await
+        .ok()?;
+
+    let output = Command::new(program)
+        .args(&args)
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    match tokio::time::timeout(PROBE_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => {
+            parse_version(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(Ok(output)) => {
+            tracing::debug!("`{base_command} --version` exited with {}", output.status);
+            None
+        }
+        Ok(Err(e)) => {
+            tracing::debug!("Failed to run `{base_command} --version`: {e}");
+            None
+        }
+        Err(_) => {
+            tracing::warn!(
+                "`{base_command} --version` did not answer within {}s",
+                PROBE_TIMEOUT.as_secs()
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version("2.0.76 (Claude Code)\n"),
+            Some("2.0.76".to_string())
+        );
+        assert_eq!(
+            parse_version("claude-flow v2.7.0-alpha.10"),
+            Some("2.7.0-alpha.10".to_string())
+        );
+        assert_eq!(parse_version("command not found"), None);
+    }
+
+    #[test]
+    fn test_is_older() {
+        assert!(is_older("1.9.3", "2.0.0"));
+        assert!(is_older("2.0.9", "2.0.10"));
+        assert!(!is_older("2.0.0-alpha.10", "2.0.0"));
+        assert!(!is_older("2.1.0", "2.0.0"));
+    }
+}
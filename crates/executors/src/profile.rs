@@ -1,17 +1,37 @@
 use std::{
     collections::HashMap,
     fs,
+    path::Path,
     str::FromStr,
     sync::{LazyLock, RwLock},
+    time::{Duration, SystemTime},
 };
 
 use convert_case::{Case, Casing};
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, de::Error as DeError};
 use thiserror::Error;
+use tokio::{sync::watch, task::JoinHandle};
 use ts_rs::TS;
 
-use crate::executors::{
-    AvailabilityInfo, BaseCodingAgent, CodingAgent, StandardCodingAgentExecutor,
+use crate::{
+    authorization::RolePolicy,
+    budget::Budget,
+    command::without_description,
+    executors::{AvailabilityInfo, BaseCodingAgent, CodingAgent, StandardCodingAgentExecutor},
+    governor::SpawnLimits,
+    log_tee::LogTee,
+    logs::pipeline::{NormalizationStage, ThinkingVisibility},
+    loop_detection::LoopDetection,
+    permission_mode::AgentPermissionMode,
+    prompt::PromptTemplate,
+    remote::SshRemote,
+    resource_limits::ResourceLimits,
+    retry::RetryPolicy,
+    run_hooks::RunHook,
+    run_template::RunTemplate,
+    supervisor::Supervisor,
+    tenant::TenantId,
 };
 
 /// Return the canonical form for variant keys.
@@ -49,11 +69,26 @@ pub enum ProfileError {
 
     #[error("No available executor profile")]
     NoAvailableExecutorProfile,
+
+    #[error("Run template '{0}' not found")]
+    TemplateNotFound(String),
 }
 
 static EXECUTOR_PROFILES_CACHE: LazyLock<RwLock<ExecutorConfigs>> =
     LazyLock::new(|| RwLock::new(ExecutorConfigs::load()));
 
+/// Tenant profiles, layered over the server's and dropped whenever those
+/// are reloaded
+static TENANT_PROFILES_CACHE: LazyLock<RwLock<HashMap<TenantId, ExecutorConfigs>>> =
+    LazyLock::new(Default::default);
+
+/// Bumped every time the cached profiles are replaced
+static PROFILES_GENERATION: LazyLock<watch::Sender<u64>> = LazyLock::new(|| watch::Sender::new(0));
+
+/// How often profiles.json is checked for changes. Polling also catches
+/// editors that replace the file instead of writing to it.
+pub const PROFILES_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
 // New format default profiles (v3 - flattened)
 const DEFAULT_PROFILES_JSON: &str = include_str!("../default_profiles.json");
 
@@ -116,6 +151,137 @@ impl std::fmt::Display for ExecutorProfileId {
     }
 }
 
+/// How a profile's runs are set up, supervised and logged, whichever
+/// executor runs them
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema, Default)]
+#[schemars(transform = without_description)]
+pub struct RunOverrides {
+    #[schemars(
+        title = "Permission Mode",
+        description = "What the agent may change: plan, read_only, accept_edits or bypass, mapped to the executor's own permission settings. Plan and read_only runs never modify files; executors that can't enforce a mode refuse to start"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<AgentPermissionMode>,
+    #[schemars(
+        title = "Prompt Template",
+        description = "System prompt and text placed before and after the task prompt. {{task_title}}, {{branch}} and {{files_hint}} are filled in"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptTemplate>,
+    #[schemars(
+        title = "Remote Host",
+        description = "Run the agent on another machine over SSH. The host must see the worktrees at its worktree root, e.g. through a shared mount. Not supported by OpenCode, Copilot and Claude API"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<SshRemote>,
+    #[schemars(
+        title = "Dry Run",
+        description = "Log the command the agent would be started with, its parameters and environment, instead of starting it"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+    #[schemars(
+        title = "Working Subdirectory",
+        description = "Subdirectory of the worktree to run the agent in, e.g. one package of a monorepo. Paths in the logs stay relative to the worktree"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd_relative: Option<String>,
+    #[schemars(
+        title = "Auto Commit",
+        description = "Commit the agent's changes under a message taken from its result summary, or from the task when it reports none, with a Co-authored-by trailer crediting the agent"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_commit: Option<bool>,
+    #[schemars(
+        title = "Pre-run Hooks",
+        description = "Commands run in the agent's working directory before it starts, e.g. npm ci, with their output shown in the conversation"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_run: Option<Vec<RunHook>>,
+    #[schemars(
+        title = "Post-run Hooks",
+        description = "Commands run in the agent's working directory after a successful run and before its changes are committed, e.g. cargo test"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_run: Option<Vec<RunHook>>,
+    #[serde(flatten)]
+    pub runtime: RuntimeOverrides,
+    #[serde(flatten)]
+    pub log_pipeline: LogPipelineOverrides,
+}
+
+/// Limits on a run and how it recovers from failures
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema, Default)]
+#[schemars(transform = without_description)]
+pub struct RuntimeOverrides {
+    #[schemars(
+        title = "Timeout (seconds)",
+        description = "Kill the executor if it is still running after this many seconds"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[schemars(
+        title = "Idle Timeout (seconds)",
+        description = "Kill the executor if it produces no output for this many seconds"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
+    #[schemars(
+        title = "Spawn Retry Policy",
+        description = "Retry spawns that fail with transient errors, with exponential backoff"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    #[schemars(
+        title = "Resource Limits",
+        description = "CPU seconds, memory (MiB) and process limits on the agent and everything it runs. Limits cover the whole process tree when VK_CGROUP_ROOT points at a delegated cgroup v2 directory, and each process otherwise"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_limits: Option<ResourceLimits>,
+    #[schemars(
+        title = "Loop Detection",
+        description = "Watch for the agent repeating the same tool call or message, and warn, stop it or ask it to change its approach once it does"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_detection: Option<LoopDetection>,
+    #[schemars(
+        title = "Budget",
+        description = "Stop the run once it costs more than max_cost_usd US dollars or uses more than max_tokens tokens, as reported by the agent. Only enforced for agents that report their usage (Claude Code, Claude Flow, Codex)"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    #[schemars(
+        title = "Crash Supervisor",
+        description = "Resume the session when the agent exits unexpectedly before reporting its result, up to max_restarts times, keeping the restarts in one execution log. Only used by agents that can resume sessions"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supervisor: Option<Supervisor>,
+}
+
+/// How the agent's output is processed into the conversation and logs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema, Default)]
+#[schemars(transform = without_description)]
+pub struct LogPipelineOverrides {
+    #[schemars(
+        title = "Log Normalization Pipeline",
+        description = "Ordered stages applied around the executor's log normalization"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalization: Option<Vec<NormalizationStage>>,
+    #[schemars(
+        title = "Thinking Visibility",
+        description = "Show, collapse or hide the agent's thinking blocks in the conversation"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_visibility: Option<ThinkingVisibility>,
+    #[schemars(
+        title = "Raw Output Logs",
+        description = "Also write the agent's raw stdout and stderr to .vibe/logs/ in the worktree, rotating each file once it reaches max_file_bytes and keeping max_files per stream, for debugging output the logs failed to parse"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_tee: Option<LogTee>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
 pub struct ExecutorConfig {
     #[serde(flatten)]
@@ -173,11 +339,23 @@ impl ExecutorConfig {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
 pub struct ExecutorConfigs {
     pub executors: HashMap<BaseCodingAgent, ExecutorConfig>,
+    /// Named run configurations, keyed by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, RunTemplate>,
+    /// What each role may run, keyed by role name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub roles: HashMap<String, RolePolicy>,
+    /// How many agent processes may run at once
+    #[serde(default, skip_serializing_if = "SpawnLimits::is_unlimited")]
+    pub spawn_limits: SpawnLimits,
+    /// What a tenant's runs may use together; read from tenants' profiles
+    #[serde(default, skip_serializing_if = "Budget::is_unbounded")]
+    pub tenant_budget: Budget,
 }
 
 impl ExecutorConfigs {
     /// Normalise all variant keys in-place
-    fn canonicalise(&mut self) {
+    pub(crate) fn canonicalise(&mut self) {
         for profile in self.executors.values_mut() {
             let mut replacements = Vec::new();
             for key in profile.configurations.keys().cloned().collect::<Vec<_>>() {
@@ -202,12 +380,135 @@ impl ExecutorConfigs {
 
     /// Reload executor profiles cache
     pub fn reload() {
-        let mut cache = EXECUTOR_PROFILES_CACHE.write().unwrap();
-        *cache = Self::load();
+        Self::replace_cached(Self::load());
+    }
+
+    fn replace_cached(configs: Self) {
+        *EXECUTOR_PROFILES_CACHE.write().unwrap() = configs;
+        TENANT_PROFILES_CACHE.write().unwrap().clear();
+        PROFILES_GENERATION.send_modify(|generation| *generation += 1);
+    }
+
+    /// Cached profiles as seen by `tenant`, or the server's own for `None`
+    pub fn get_cached_for(tenant: Option<&TenantId>) -> ExecutorConfigs {
+        let Some(tenant) = tenant else {
+            return Self::get_cached();
+        };
+        if let Some(configs) = TENANT_PROFILES_CACHE.read().unwrap().get(tenant) {
+            return configs.clone();
+        }
+        let configs = Self::load_for_tenant(tenant);
+        TENANT_PROFILES_CACHE
+            .write()
+            .unwrap()
+            .insert(tenant.clone(), configs.clone());
+        configs
+    }
+
+    /// Reload one tenant's profiles after its overrides changed
+    pub fn reload_tenant(tenant: &TenantId) {
+        TENANT_PROFILES_CACHE.write().unwrap().remove(tenant);
+        PROFILES_GENERATION.send_modify(|generation| *generation += 1);
+    }
+
+    /// The server's profiles with `tenant`'s overrides applied. Overrides
+    /// that fail to parse are logged and ignored.
+    pub fn load_for_tenant(tenant: &TenantId) -> Self {
+        let base = Self::get_cached();
+        let content = match fs::read_to_string(tenant.profiles_path()) {
+            Ok(content) => content,
+            Err(_) => return base,
+        };
+        match serde_json::from_str::<Self>(&content) {
+            Ok(mut overrides) => {
+                overrides.canonicalise();
+                Self::merge_with_defaults(base, overrides)
+            }
+            Err(e) => {
+                tracing::error!("Failed to parse profiles.json of tenant {tenant}: {e}");
+                base
+            }
+        }
+    }
+
+    /// Save what differs from the server's profiles as `tenant`'s overrides
+    pub fn save_overrides_for(&self, tenant: &TenantId) -> Result<(), ProfileError> {
+        let base = Self::get_cached();
+        let mut current = self.clone();
+        current.canonicalise();
+
+        let mut overrides = Self::compute_overrides(&base, &current)?;
+        // Templates and roles the server already defines identically are not copied
+        overrides
+            .templates
+            .retain(|name, template| base.templates.get(name) != Some(template));
+        overrides
+            .roles
+            .retain(|name, policy| base.roles.get(name) != Some(policy));
+        Self::validate_merged(&Self::merge_with_defaults(base, overrides.clone()))?;
+
+        fs::create_dir_all(tenant.dir())?;
+        fs::write(
+            tenant.profiles_path(),
+            serde_json::to_string_pretty(&overrides)?,
+        )?;
+        Self::reload_tenant(tenant);
+        tracing::info!("Saved profile overrides for tenant {tenant}");
+        Ok(())
+    }
+
+    /// Subscribe to reloads of the cached profiles. The value is a counter
+    /// that changes on every reload; read the new profiles with
+    /// [`ExecutorConfigs::get_cached`].
+    pub fn subscribe() -> watch::Receiver<u64> {
+        PROFILES_GENERATION.subscribe()
+    }
+
+    /// Watch profiles.json and reload the cache when it changes. Edits that
+    /// fail to parse are ignored, keeping the profiles loaded before them.
+    pub fn spawn_watcher() -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let profiles_path = workspace_utils::assets::profiles_path();
+            let mut last_seen = file_fingerprint(&profiles_path);
+            let mut interval = tokio::time::interval(PROFILES_WATCH_INTERVAL);
+            loop {
+                interval.tick().await;
+                let fingerprint = file_fingerprint(&profiles_path);
+                if fingerprint == last_seen {
+                    continue;
+                }
+                last_seen = fingerprint;
+
+                match Self::try_load() {
+                    // Our own saves are followed by an explicit reload
+                    Ok(configs) if configs == Self::get_cached() => {}
+                    Ok(configs) => {
+                        tracing::info!("profiles.json changed, reloading executor profiles");
+                        Self::replace_cached(configs);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Ignoring invalid profiles.json: {e}");
+                    }
+                }
+            }
+        })
     }
 
     /// Load executor profiles from file or defaults
     pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|e| {
+            tracing::error!(
+                "Failed to parse user profiles.json: {}, using defaults only",
+                e
+            );
+            let mut defaults = Self::from_defaults();
+            defaults.canonicalise();
+            defaults
+        })
+    }
+
+    /// Load executor profiles, failing if profiles.json exists but is invalid
+    pub fn try_load() -> Result<Self, ProfileError> {
         let profiles_path = workspace_utils::assets::profiles_path();
 
         // Load defaults first
@@ -219,25 +520,19 @@ impl ExecutorConfigs {
             Ok(content) => content,
             Err(_) => {
                 tracing::info!("No user profiles.json found, using defaults only");
-                return defaults;
+                return Ok(defaults);
             }
         };
 
-        // Parse user overrides
-        match serde_json::from_str::<Self>(&content) {
-            Ok(mut user_overrides) => {
-                tracing::info!("Loaded user profile overrides from profiles.json");
-                user_overrides.canonicalise();
-                Self::merge_with_defaults(defaults, user_overrides)
-            }
-            Err(e) => {
-                tracing::error!(
-                    "Failed to parse user profiles.json: {}, using defaults only",
-                    e
-                );
-                defaults
-            }
+        for diagnostic in Self::validate_json(&content) {
+            tracing::warn!("profiles.json{}: {}", diagnostic.path, diagnostic.message);
         }
+
+        // Parse user overrides
+        let mut user_overrides = serde_json::from_str::<Self>(&content)?;
+        tracing::info!("Loaded user profile overrides from profiles.json");
+        user_overrides.canonicalise();
+        Ok(Self::merge_with_defaults(defaults, user_overrides))
     }
 
     /// Save user profile overrides to file (only saves what differs from defaults)
@@ -266,7 +561,15 @@ impl ExecutorConfigs {
     }
 
     /// Deep merge defaults with user overrides
-    fn merge_with_defaults(mut defaults: Self, overrides: Self) -> Self {
+    pub(crate) fn merge_with_defaults(mut defaults: Self, overrides: Self) -> Self {
+        defaults.templates.extend(overrides.templates);
+        defaults.roles.extend(overrides.roles);
+        if !overrides.spawn_limits.is_unlimited() {
+            defaults.spawn_limits = overrides.spawn_limits;
+        }
+        if !overrides.tenant_budget.is_unbounded() {
+            defaults.tenant_budget = overrides.tenant_budget;
+        }
         for (executor_key, override_profile) in overrides.executors {
             match defaults.executors.get_mut(&executor_key) {
                 Some(default_profile) => {
@@ -286,8 +589,14 @@ impl ExecutorConfigs {
 
     /// Compute what overrides are needed to transform defaults into current config
     fn compute_overrides(defaults: &Self, current: &Self) -> Result<Self, ProfileError> {
+        // Templates, roles, spawn limits and the tenant budget are
+        // user-defined only, so they are always kept
         let mut overrides = Self {
             executors: HashMap::new(),
+            templates: current.templates.clone(),
+            roles: current.roles.clone(),
+            spawn_limits: current.spawn_limits.clone(),
+            tenant_budget: current.tenant_budget.clone(),
         };
 
         // Fast scan for any illegal deletions BEFORE allocating/cloning
@@ -352,31 +661,10 @@ impl ExecutorConfigs {
 
     /// Validate that merged profiles are consistent and valid
     fn validate_merged(merged: &Self) -> Result<(), ProfileError> {
-        for (executor_key, profile) in &merged.executors {
-            // Ensure default configuration exists
-            let default_config = profile.configurations.get("DEFAULT").ok_or_else(|| {
-                ProfileError::Validation(format!(
-                    "Executor '{executor_key}' is missing required 'default' configuration"
-                ))
-            })?;
-
-            // Validate that the default agent type matches the executor key
-            if BaseCodingAgent::from(default_config) != *executor_key {
-                return Err(ProfileError::Validation(format!(
-                    "Executor key '{executor_key}' does not match the agent variant '{default_config}'"
-                )));
-            }
-
-            // Ensure configuration names don't conflict with reserved words
-            for config_name in profile.configurations.keys() {
-                if config_name.starts_with("__") {
-                    return Err(ProfileError::Validation(format!(
-                        "Configuration name '{config_name}' is reserved (starts with '__')"
-                    )));
-                }
-            }
+        match merged.validate_structure().into_iter().next() {
+            Some(diagnostic) => Err(ProfileError::Validation(diagnostic.message)),
+            None => Ok(()),
         }
-        Ok(())
     }
 
     /// Load from the new v3 defaults
@@ -401,6 +689,21 @@ impl ExecutorConfigs {
             .cloned()
     }
 
+    pub fn get_template(&self, name: &str) -> Option<&RunTemplate> {
+        self.templates.get(name)
+    }
+
+    /// Add or replace a run template
+    pub fn set_template(&mut self, name: String, template: RunTemplate) {
+        self.templates.insert(name, template);
+    }
+
+    pub fn remove_template(&mut self, name: &str) -> Result<RunTemplate, ProfileError> {
+        self.templates
+            .remove(name)
+            .ok_or_else(|| ProfileError::TemplateNotFound(name.to_string()))
+    }
+
     pub fn get_coding_agent_or_default(
         &self,
         executor_profile_id: &ExecutorProfileId,
@@ -476,6 +779,12 @@ impl ExecutorConfigs {
     }
 }
 
+/// Modification time and size of a file, or `None` if it doesn't exist
+fn file_fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
 pub fn to_default_variant(id: &ExecutorProfileId) -> ExecutorProfileId {
     ExecutorProfileId {
         executor: id.executor,
@@ -0,0 +1,633 @@
+//! Checking executor profiles before they are used.
+//!
+//! Profiles are plain JSON the user edits by hand. Serde ignores fields it
+//! doesn't know, so a misspelt option silently does nothing, and files or
+//! secrets a profile refers to are only read once an agent spawns with it.
+//! [`ExecutorConfigs::validate_json`] checks each executor configuration
+//! against the JSON Schema of its executor, and [`ExecutorConfigs::validate`]
+//! checks what the loaded profiles refer to, so both kinds of mistakes are
+//! reported up front as [`ProfileDiagnostic`]s pointing at where they are.
+
+use std::path::Path;
+
+use schemars::{JsonSchema, generate::SchemaSettings};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::{
+    executors::{
+        BaseCodingAgent, CodingAgent, StandardCodingAgentExecutor, amp::Amp, claude::ClaudeCode,
+        claude_api::ClaudeApi, claude_flow::ClaudeFlow, codex::Codex, copilot::Copilot,
+        cursor::CursorAgent, droid::Droid, gemini::Gemini, opencode::Opencode, qwen::QwenCode,
+    },
+    profile::ExecutorConfigs,
+    secrets,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    /// The profiles can't be loaded, or a run with them would fail
+    Error,
+    /// Something in the profiles has no effect
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ProfileDiagnostic {
+    /// JSON Pointer to the value the diagnostic is about, e.g.
+    /// `/executors/CLAUDE_CODE/PLAN/CLAUDE_CODE/model`
+    pub path: String,
+    pub message: String,
+    pub severity: DiagnosticSeverity,
+}
+
+impl ProfileDiagnostic {
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+            severity: DiagnosticSeverity::Error,
+        }
+    }
+
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+            severity: DiagnosticSeverity::Warning,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
+}
+
+impl ExecutorConfigs {
+    /// Check profiles.json content: its syntax, each executor configuration
+    /// against its executor's schema and, once it parses, what the profiles
+    /// refer to with the defaults merged in
+    pub fn validate_json(content: &str) -> Vec<ProfileDiagnostic> {
+        let value: Value = match serde_json::from_str(content) {
+            Ok(value) => value,
+            Err(e) => {
+                return vec![ProfileDiagnostic::error(
+                    "",
+                    format!(
+                        "Invalid JSON at line {}, column {}: {e}",
+                        e.line(),
+                        e.column()
+                    ),
+                )];
+            }
+        };
+        let mut diagnostics = Vec::new();
+        match value.get("executors").and_then(Value::as_object) {
+            Some(executors) => {
+                for (key, variants) in executors {
+                    check_executor(key, variants, &mut diagnostics);
+                }
+            }
+            None => diagnostics.push(ProfileDiagnostic::error(
+                "/executors",
+                "Missing the `executors` object",
+            )),
+        }
+
+        match serde_json::from_value::<Self>(value) {
+            Ok(mut overrides) => {
+                overrides.canonicalise();
+                let merged = Self::merge_with_defaults(Self::from_defaults(), overrides);
+                diagnostics.extend(merged.validate());
+            }
+            // Errors in the executor configurations are reported above
+            Err(e) if !diagnostics.iter().any(ProfileDiagnostic::is_error) => {
+                diagnostics.push(ProfileDiagnostic::error("", e.to_string()));
+            }
+            Err(_) => {}
+        }
+        diagnostics
+    }
+
+    /// Check that the profiles are consistent, and that the files and secrets
+    /// they refer to exist
+    pub fn validate(&self) -> Vec<ProfileDiagnostic> {
+        let mut diagnostics = self.validate_structure();
+        let mut executors: Vec<_> = self.executors.iter().collect();
+        executors.sort_by_key(|(executor, _)| executor.to_string());
+        for (executor, profile) in executors {
+            let mut variants: Vec<_> = profile.configurations.iter().collect();
+            variants.sort_by_key(|(name, _)| name.as_str());
+            for (variant, agent) in variants {
+                let path = format!("/executors/{executor}/{variant}/{executor}");
+                check_references(agent, &path, &mut diagnostics);
+            }
+            if let Some(agent) = profile.get_default() {
+                check_mcp_config(agent, &format!("/executors/{executor}"), &mut diagnostics);
+            }
+        }
+        diagnostics
+    }
+
+    /// Errors that keep the profiles from being saved
+    pub(crate) fn validate_structure(&self) -> Vec<ProfileDiagnostic> {
+        let mut diagnostics = Vec::new();
+        for (executor_key, profile) in &self.executors {
+            let path = format!("/executors/{executor_key}");
+            match profile.configurations.get("DEFAULT") {
+                None => diagnostics.push(ProfileDiagnostic::error(
+                    &path,
+                    format!("Executor '{executor_key}' is missing required 'default' configuration"),
+                )),
+                Some(default_config) if BaseCodingAgent::from(default_config) != *executor_key => {
+                    diagnostics.push(ProfileDiagnostic::error(
+                        format!("{path}/DEFAULT"),
+                        format!(
+                            "Executor key '{executor_key}' does not match the agent variant '{default_config}'"
+                        ),
+                    ))
+                }
+                Some(_) => {}
+            }
+            for config_name in profile.configurations.keys() {
+                if config_name.starts_with("__") {
+                    diagnostics.push(ProfileDiagnostic::error(
+                        format!("{path}/{}", escape_pointer(config_name)),
+                        format!(
+                            "Configuration name '{config_name}' is reserved (starts with '__')"
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for (name, template) in &self.templates {
+            if self
+                .get_coding_agent(&template.executor_profile_id)
+                .is_none()
+            {
+                diagnostics.push(ProfileDiagnostic::error(
+                    format!("/templates/{}/executor_profile_id", escape_pointer(name)),
+                    format!(
+                        "Run template '{name}' uses unknown profile '{}'",
+                        template.executor_profile_id
+                    ),
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+/// Check the variants of the executor `key` against its schema
+fn check_executor(key: &str, variants: &Value, diagnostics: &mut Vec<ProfileDiagnostic>) {
+    let path = format!("/executors/{}", escape_pointer(key));
+    let Some(executor) = parse_executor(key) else {
+        diagnostics.push(ProfileDiagnostic::error(
+            path,
+            format!("Unknown executor '{key}'"),
+        ));
+        return;
+    };
+    let Some(variants) = variants.as_object() else {
+        diagnostics.push(ProfileDiagnostic::error(
+            path,
+            "Expected an object of configurations",
+        ));
+        return;
+    };
+    let schema = executor_schema(executor);
+    for (variant, config) in variants {
+        let path = format!("{path}/{}", escape_pointer(variant));
+        let tagged = config.as_object().filter(|config| config.len() == 1);
+        let Some((tag, config)) = tagged.and_then(|config| config.iter().next()) else {
+            diagnostics.push(ProfileDiagnostic::error(
+                path,
+                format!("Expected an object with the single key '{executor}'"),
+            ));
+            continue;
+        };
+        if parse_executor(tag) != Some(executor) {
+            diagnostics.push(ProfileDiagnostic::error(
+                format!("{path}/{}", escape_pointer(tag)),
+                format!("Configuration of '{executor}' is tagged as '{tag}'"),
+            ));
+            continue;
+        }
+        let path = format!("{path}/{}", escape_pointer(tag));
+        check_value(config, &schema, &schema, &path, diagnostics);
+    }
+}
+
+fn parse_executor(key: &str) -> Option<BaseCodingAgent> {
+    serde_json::from_value(Value::String(key.to_string())).ok()
+}
+
+/// JSON Schema of `executor`'s configuration, as the settings UI uses it
+fn executor_schema(executor: BaseCodingAgent) -> Value {
+    fn schema_for<T: JsonSchema>() -> Value {
+        let schema = SchemaSettings::draft07()
+            .with(|settings| settings.inline_subschemas = true)
+            .into_generator()
+            .into_root_schema_for::<T>();
+        serde_json::to_value(schema).unwrap_or(Value::Bool(true))
+    }
+
+    match executor {
+        BaseCodingAgent::ClaudeCode => schema_for::<ClaudeCode>(),
+        BaseCodingAgent::ClaudeFlow => schema_for::<ClaudeFlow>(),
+        BaseCodingAgent::Amp => schema_for::<Amp>(),
+        BaseCodingAgent::Gemini => schema_for::<Gemini>(),
+        BaseCodingAgent::Codex => schema_for::<Codex>(),
+        BaseCodingAgent::Opencode => schema_for::<Opencode>(),
+        BaseCodingAgent::CursorAgent => schema_for::<CursorAgent>(),
+        BaseCodingAgent::QwenCode => schema_for::<QwenCode>(),
+        BaseCodingAgent::Copilot => schema_for::<Copilot>(),
+        BaseCodingAgent::Droid => schema_for::<Droid>(),
+        BaseCodingAgent::ClaudeApi => schema_for::<ClaudeApi>(),
+    }
+}
+
+/// Check `value` against `schema`, covering the parts of JSON Schema the
+/// derived executor schemas use
+fn check_value(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    diagnostics: &mut Vec<ProfileDiagnostic>,
+) {
+    let Some(schema) = schema.as_object() else {
+        if *schema == Value::Bool(false) {
+            diagnostics.push(ProfileDiagnostic::error(path, "No value is allowed here"));
+        }
+        return;
+    };
+    if let Some(target) = schema.get("$ref").and_then(Value::as_str) {
+        if let Some(resolved) = resolve_ref(root, target) {
+            check_value(value, resolved, root, path, diagnostics);
+        }
+        return;
+    }
+    if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+        for branch in all_of {
+            check_value(value, branch, root, path, diagnostics);
+        }
+    }
+    if let Some(branches) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        check_branches(value, branches, root, path, diagnostics);
+    }
+
+    if let Some(expected) = schema.get("type")
+        && !type_matches(value, expected)
+    {
+        diagnostics.push(ProfileDiagnostic::error(
+            path,
+            format!(
+                "Expected {}, found {}",
+                describe_type(expected),
+                json_type(value)
+            ),
+        ));
+        return;
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+        && !allowed.contains(value)
+    {
+        let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+        diagnostics.push(ProfileDiagnostic::error(
+            path,
+            format!("Expected one of {}, found {value}", allowed.join(", ")),
+        ));
+        return;
+    }
+    if let Some(expected) = schema.get("const")
+        && expected != value
+    {
+        diagnostics.push(ProfileDiagnostic::error(
+            path,
+            format!("Expected {expected}, found {value}"),
+        ));
+        return;
+    }
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64)
+            && number < minimum
+        {
+            diagnostics.push(ProfileDiagnostic::error(
+                path,
+                format!("Must be at least {minimum}, found {value}"),
+            ));
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64)
+            && number > maximum
+        {
+            diagnostics.push(ProfileDiagnostic::error(
+                path,
+                format!("Must be at most {maximum}, found {value}"),
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for required in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !object.contains_key(required) {
+                    diagnostics.push(ProfileDiagnostic::error(
+                        path,
+                        format!("Missing required field `{required}`"),
+                    ));
+                }
+            }
+            for (key, field) in object {
+                let field_path = format!("{path}/{}", escape_pointer(key));
+                match (
+                    properties.and_then(|properties| properties.get(key)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(field_schema), _) => {
+                        check_value(field, field_schema, root, &field_path, diagnostics)
+                    }
+                    (None, Some(Value::Bool(false))) => diagnostics.push(ProfileDiagnostic::error(
+                        field_path,
+                        format!("Unknown field `{key}`"),
+                    )),
+                    (None, Some(additional)) => {
+                        check_value(field, additional, root, &field_path, diagnostics)
+                    }
+                    // Serde skips fields it doesn't know
+                    (None, None) if properties.is_some() => {
+                        diagnostics.push(ProfileDiagnostic::warning(
+                            field_path,
+                            format!("Unknown field `{key}` is ignored"),
+                        ))
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_value(item, item_schema, root, &format!("{path}/{i}"), diagnostics);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Check `value` against the branch of an `anyOf` it matches best: the one
+/// with the fewest errors among those of its type
+fn check_branches(
+    value: &Value,
+    branches: &[Value],
+    root: &Value,
+    path: &str,
+    diagnostics: &mut Vec<ProfileDiagnostic>,
+) {
+    let mut best: Option<Vec<ProfileDiagnostic>> = None;
+    for branch in branches {
+        let mut found = Vec::new();
+        check_value(value, branch, root, path, &mut found);
+        let errors = found.iter().filter(|d| d.is_error()).count();
+        if errors == 0 {
+            diagnostics.extend(found);
+            return;
+        }
+        // A branch of another type only says what the value isn't
+        let wrong_type = found.iter().any(|d| d.path == path && d.is_error());
+        if !wrong_type
+            && best
+                .as_ref()
+                .is_none_or(|best| errors < best.iter().filter(|d| d.is_error()).count())
+        {
+            best = Some(found);
+        }
+    }
+    match best {
+        Some(found) => diagnostics.extend(found),
+        None => diagnostics.push(ProfileDiagnostic::error(
+            path,
+            format!("{value} is not one of the allowed values"),
+        )),
+    }
+}
+
+fn resolve_ref<'a>(root: &'a Value, target: &str) -> Option<&'a Value> {
+    root.pointer(target.strip_prefix('#')?)
+}
+
+fn type_matches(value: &Value, expected: &Value) -> bool {
+    let matches = |name: &str| match name {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        name => json_type(value) == name,
+    };
+    match expected {
+        Value::String(name) => matches(name),
+        Value::Array(names) => names.iter().filter_map(Value::as_str).any(matches),
+        _ => true,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" or "),
+        expected => expected.as_str().unwrap_or("a value").to_string(),
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Check the files and secrets the configuration at `path` refers to
+fn check_references(agent: &CodingAgent, path: &str, diagnostics: &mut Vec<ProfileDiagnostic>) {
+    let api_key_secret = match agent {
+        CodingAgent::ClaudeCode(claude) => claude.api_key_secret.as_deref(),
+        CodingAgent::ClaudeFlow(flow) => {
+            match (&flow.workflow, &flow.workflow_file) {
+                (Some(workflow), file) => {
+                    if let Err(e) = workflow.validate() {
+                        diagnostics.push(ProfileDiagnostic::error(
+                            format!("{path}/workflow"),
+                            format!("Invalid workflow: {e}"),
+                        ));
+                    }
+                    if file.is_some() {
+                        diagnostics.push(ProfileDiagnostic::warning(
+                            format!("{path}/workflow_file"),
+                            "The workflow file is ignored while an inline workflow is set",
+                        ));
+                    }
+                }
+                // Relative workflow files are looked up in each worktree
+                (None, Some(file))
+                    if Path::new(file).is_absolute() && !Path::new(file).is_file() =>
+                {
+                    diagnostics.push(ProfileDiagnostic::error(
+                        format!("{path}/workflow_file"),
+                        format!("Workflow file {file} does not exist"),
+                    ));
+                }
+                (None, _) => {}
+            }
+            flow.api_key_secret.as_deref()
+        }
+        _ => None,
+    };
+    if let Some(name) = api_key_secret
+        && let Err(e) = secrets::resolve(name, None)
+    {
+        diagnostics.push(ProfileDiagnostic::error(
+            format!("{path}/api_key_secret"),
+            e.to_string(),
+        ));
+    }
+}
+
+/// Check that the MCP config file of `agent`'s executor, if there is one,
+/// can be read
+fn check_mcp_config(agent: &CodingAgent, path: &str, diagnostics: &mut Vec<ProfileDiagnostic>) {
+    let Some(config_path) = agent.default_mcp_config_path() else {
+        return;
+    };
+    let Ok(content) = std::fs::read_to_string(&config_path) else {
+        return;
+    };
+    let parsed = if agent.get_mcp_config().is_toml_config {
+        toml::from_str::<toml::Value>(&content)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str::<Value>(&content)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    };
+    if let Err(e) = parsed {
+        diagnostics.push(ProfileDiagnostic::error(
+            path,
+            format!("MCP config {} can't be parsed: {e}", config_path.display()),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_report_where_they_are_wrong() {
+        let content = serde_json::json!({
+            "executors": {
+                "CLAUDE_CODE": {
+                    "PLAN": {
+                        "CLAUDE_CODE": {
+                            "plan": "yes",
+                            "modle": "opus",
+                            "timeout_secs": -5,
+                            "permission_mode": "read_only",
+                        }
+                    },
+                    "FAST": { "GEMINI": {} },
+                },
+                "CLAUDE_FLOW": {
+                    "DEFAULT": {
+                        "CLAUDE_FLOW": {
+                            "workflow_file": "/nonexistent/vk-workflow.json",
+                            "history": "last_turns",
+                        }
+                    }
+                },
+                "NOT_AN_AGENT": {},
+            }
+        })
+        .to_string();
+
+        let diagnostics = ExecutorConfigs::validate_json(&content);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.path.as_str(), d.severity))
+            .collect();
+        let plan = "/executors/CLAUDE_CODE/PLAN/CLAUDE_CODE";
+        for expected in [
+            (format!("{plan}/plan"), DiagnosticSeverity::Error),
+            (format!("{plan}/modle"), DiagnosticSeverity::Warning),
+            (format!("{plan}/timeout_secs"), DiagnosticSeverity::Error),
+            (
+                "/executors/CLAUDE_CODE/FAST/GEMINI".to_string(),
+                DiagnosticSeverity::Error,
+            ),
+            (
+                "/executors/NOT_AN_AGENT".to_string(),
+                DiagnosticSeverity::Error,
+            ),
+        ] {
+            assert!(
+                found.contains(&(expected.0.as_str(), expected.1)),
+                "{expected:?} not in {diagnostics:#?}"
+            );
+        }
+        assert_eq!(diagnostics.len(), 5, "{diagnostics:#?}");
+        assert_eq!(
+            diagnostics[0].message,
+            "Expected boolean or null, found string"
+        );
+
+        // Once the configurations are fixed, the files they refer to are checked
+        let content = content
+            .replace(r#""yes""#, "true")
+            .replace("-5", "5")
+            .replace(r#","FAST":{"GEMINI":{}}"#, "")
+            .replace(r#","NOT_AN_AGENT":{}"#, "");
+        let diagnostics = ExecutorConfigs::validate_json(&content);
+        assert!(
+            diagnostics.iter().any(|d| d.is_error()
+                && d.path == "/executors/CLAUDE_FLOW/DEFAULT/CLAUDE_FLOW/workflow_file"),
+            "{diagnostics:#?}"
+        );
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| !d.path.starts_with(plan) || !d.is_error())
+        );
+
+        assert_eq!(
+            ExecutorConfigs::validate_json("{\"executors\": {")[0].path,
+            ""
+        );
+
+        // The built-in profiles match their schemas
+        let defaults = ExecutorConfigs::validate_json(include_str!("../default_profiles.json"));
+        assert!(defaults.is_empty(), "{defaults:#?}");
+    }
+}
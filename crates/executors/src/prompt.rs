@@ -0,0 +1,220 @@
+//! Framing task prompts with profile-defined text.
+//!
+//! A [`PromptTemplate`] surrounds the task prompt with a system prompt, text to
+//! prepend and text to append. All three may use placeholders that are filled
+//! from the execution's `VK_*` environment:
+//!
+//! - `{{task_title}}`: title of the task being worked on
+//! - `{{branch}}`: branch of the workspace
+//! - `{{base_branch}}`: branch the workspace's branch was created from
+//! - `{{worktree}}`: path of the worktree the agent runs in
+//! - `{{files_hint}}`: where in the workspace the agent should look
+//!
+//! Unknown placeholders are left untouched, so text meant for the agent that
+//! happens to use braces survives.
+//!
+//! Multi-megabyte prompts can choke agents that read them from stdin or argv.
+//! Agents that accept a prompt file get such prompts through a file in the
+//! worktree instead, as selected by the profile's [`PromptDelivery`].
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::{Duration, SystemTime},
+};
+
+use regex::{Captures, Regex};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use uuid::Uuid;
+
+use crate::env::ExecutionEnv;
+
+/// Placeholders and the environment variables they are filled from
+pub const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("task_title", "VK_TASK_TITLE"),
+    ("branch", "VK_WORKSPACE_BRANCH"),
+    ("base_branch", "VK_BASE_BRANCH"),
+    ("worktree", "VK_WORKTREE_PATH"),
+    ("files_hint", "VK_FILES_HINT"),
+];
+
+/// Size from which [`PromptDelivery::Auto`] switches to a prompt file
+pub const PROMPT_FILE_THRESHOLD: usize = 256 * 1024;
+/// Worktree directory prompt files are written to
+const PROMPT_DIR: &str = ".vibe-kanban/prompts";
+/// Scratch files older than this are removed when the next one is written
+const SCRATCH_FILE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+static PLACEHOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([a-z_]+)\s*\}\}").unwrap());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
+pub struct PromptTemplate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "System Prompt",
+        description = "Standing instructions placed ahead of everything else",
+        extend("format" = "textarea")
+    )]
+    pub system_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Prepend",
+        description = "Text placed before the task prompt",
+        extend("format" = "textarea")
+    )]
+    pub prepend: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Append",
+        description = "Text placed after the task prompt",
+        extend("format" = "textarea")
+    )]
+    pub append: Option<String>,
+}
+
+impl PromptTemplate {
+    /// `prompt` between the rendered parts of the template, each separated
+    /// by a blank line
+    pub fn frame(&self, prompt: &str, env: &ExecutionEnv) -> String {
+        let before = [&self.system_prompt, &self.prepend]
+            .into_iter()
+            .flatten()
+            .map(|text| render(text, env));
+        let after = self.append.iter().map(|text| render(text, env));
+        before
+            .chain(std::iter::once(prompt.to_string()))
+            .chain(after)
+            .filter(|part| !part.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptDelivery {
+    // A prompt file for large prompts, if the agent takes one
+    #[default]
+    Auto,
+    // Always the agent's usual channel, stdin or argv
+    Inline,
+    // A prompt file whenever the agent takes one
+    File,
+}
+
+impl PromptDelivery {
+    /// Whether `prompt` goes through a file for an agent that takes one
+    pub fn uses_file(self, prompt: &str) -> bool {
+        match self {
+            Self::Auto => prompt.len() >= PROMPT_FILE_THRESHOLD,
+            Self::Inline => false,
+            Self::File => true,
+        }
+    }
+}
+
+/// Write `prompt` to a new file under `current_dir` that git ignores, and
+/// return its absolute path
+pub fn write_prompt_file(current_dir: &Path, prompt: &str) -> io::Result<PathBuf> {
+    let path = scratch_dir(current_dir, PROMPT_DIR)?.join(format!("{}.md", Uuid::new_v4()));
+    fs::write(&path, prompt)?;
+    Ok(path)
+}
+
+/// `rel` under `current_dir`, created if needed and ignored by git, with
+/// files older than a day removed
+pub(crate) fn scratch_dir(current_dir: &Path, rel: &str) -> io::Result<PathBuf> {
+    let dir = current_dir.join(rel);
+    fs::create_dir_all(&dir)?;
+    // Ignores the directory's contents, including itself
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, "*\n")?;
+    }
+    remove_stale_files(&dir);
+    Ok(dir)
+}
+
+fn remove_stale_files(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let stale = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > SCRATCH_FILE_TTL);
+        if stale && path.file_name().is_some_and(|name| name != ".gitignore") {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// `text` with known placeholders replaced by their values from `env`; a
+/// placeholder whose variable isn't set becomes empty
+pub fn render(text: &str, env: &ExecutionEnv) -> String {
+    PLACEHOLDER_RE
+        .replace_all(text, |caps: &Captures| {
+            match PLACEHOLDERS.iter().find(|(name, _)| *name == &caps[1]) {
+                Some((_, var)) => env.vars.get(*var).cloned().unwrap_or_default(),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_fills_placeholders() {
+        let mut env = ExecutionEnv::default();
+        env.insert("VK_TASK_TITLE", "Fix login");
+        env.insert("VK_WORKSPACE_BRANCH", "vk/1a2b-fix-login");
+
+        let template = PromptTemplate {
+            system_prompt: Some("You are working on {{ task_title }}.".to_string()),
+            prepend: None,
+            append: Some(
+                "Commit to {{branch}}. Focus on {{files_hint}}, not {{task}}.".to_string(),
+            ),
+        };
+        assert_eq!(
+            template.frame("Users can't log in", &env),
+            "You are working on Fix login.\n\nUsers can't log in\n\n\
+             Commit to vk/1a2b-fix-login. Focus on , not {{task}}."
+        );
+        assert_eq!(
+            PromptTemplate::default().frame("Users can't log in", &env),
+            "Users can't log in"
+        );
+    }
+
+    #[test]
+    fn test_prompt_file_delivery() {
+        let large = "x".repeat(PROMPT_FILE_THRESHOLD);
+        assert!(PromptDelivery::Auto.uses_file(&large));
+        assert!(!PromptDelivery::Auto.uses_file("Fix the bug"));
+        assert!(PromptDelivery::File.uses_file("Fix the bug"));
+        assert!(!PromptDelivery::Inline.uses_file(&large));
+
+        let dir = std::env::temp_dir().join(format!("vk-prompt-{}", Uuid::new_v4()));
+        let path = write_prompt_file(&dir, "Fix the bug").unwrap();
+        assert!(path.starts_with(dir.join(PROMPT_DIR)));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Fix the bug");
+        assert_eq!(
+            fs::read_to_string(dir.join(PROMPT_DIR).join(".gitignore")).unwrap(),
+            "*\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+//! Cool-downs for providers that rate limited an agent.
+//!
+//! A run that fails because its provider rate limited it (HTTP 429, or an
+//! overloaded API) is most likely followed by more of the same if the next
+//! agent on that provider is spawned right away. The [`RateLimiter`] records
+//! such failures per [`AgentProvider`] and holds back spawns for it until its
+//! cool-down has passed. The cool-down is the wait the provider asked for
+//! when its error says so, and otherwise grows exponentially with the rate
+//! limits in a row, jittered so spawns held back together don't all hit the
+//! provider again at once. A run that succeeds clears its provider's
+//! cool-down.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use tokio::time::Instant;
+use ts_rs::TS;
+use uuid::Uuid;
+use workspace_utils::log_msg::LogMsg;
+
+use crate::{
+    cancellation::CancelScope,
+    executors::{BaseCodingAgent, ExecutorError},
+};
+
+/// Cool-down after the first rate limit in a row; doubled after each further
+const BASE_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// Upper bound for a cool-down, whatever the provider asks for
+const MAX_COOLDOWN: Duration = Duration::from_secs(600);
+
+/// Log messages at the end of a run searched for a retry hint
+const RETRY_HINT_TAIL: usize = 20;
+
+static RETRY_AFTER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:retry[ _-]after|try again in|retry in)\W{0,3}(\d+(?:\.\d+)?)\s*(ms|milliseconds?|s|secs?|seconds?|m|mins?|minutes?)?\b",
+    )
+    .unwrap()
+});
+
+/// Service an agent's requests are rate limited by. Agents whose own service
+/// picks the model count as a provider of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AgentProvider {
+    Anthropic,
+    #[serde(rename = "openai")]
+    #[strum(serialize = "openai")]
+    OpenAi,
+    Google,
+    Qwen,
+    Amp,
+    Cursor,
+    Copilot,
+    Droid,
+    Opencode,
+}
+
+impl From<BaseCodingAgent> for AgentProvider {
+    fn from(executor: BaseCodingAgent) -> Self {
+        match executor {
+            BaseCodingAgent::ClaudeCode
+            | BaseCodingAgent::ClaudeFlow
+            | BaseCodingAgent::ClaudeApi => AgentProvider::Anthropic,
+            BaseCodingAgent::Codex => AgentProvider::OpenAi,
+            BaseCodingAgent::Gemini => AgentProvider::Google,
+            BaseCodingAgent::QwenCode => AgentProvider::Qwen,
+            BaseCodingAgent::Amp => AgentProvider::Amp,
+            BaseCodingAgent::CursorAgent => AgentProvider::Cursor,
+            BaseCodingAgent::Copilot => AgentProvider::Copilot,
+            BaseCodingAgent::Droid => AgentProvider::Droid,
+            BaseCodingAgent::Opencode => AgentProvider::Opencode,
+        }
+    }
+}
+
+/// Wait a rate limit error at the end of `history` asks for, e.g.
+/// `Retry-After: 30` or `try again in 2 minutes`
+pub fn retry_after(history: &[LogMsg]) -> Option<Duration> {
+    history
+        .iter()
+        .rev()
+        .filter_map(|msg| match msg {
+            LogMsg::Stderr(text) | LogMsg::Stdout(text) => Some(text),
+            _ => None,
+        })
+        .take(RETRY_HINT_TAIL)
+        .find_map(|text| parse_retry_after(text))
+}
+
+fn parse_retry_after(text: &str) -> Option<Duration> {
+    let captures = RETRY_AFTER_RE.captures(text)?;
+    let value: f64 = captures[1].parse().ok()?;
+    let seconds = match captures.get(2).map(|unit| unit.as_str().to_lowercase()) {
+        Some(unit) if unit.starts_with("ms") || unit.starts_with("milli") => value / 1000.0,
+        Some(unit) if unit.starts_with('m') => value * 60.0,
+        _ => value,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// A provider's cool-down, as shown to users
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS)]
+pub struct ProviderCooldown {
+    pub provider: AgentProvider,
+    /// When spawns for the provider are let through again
+    pub until: DateTime<Utc>,
+    /// Rate limits in a row since the provider last served a run
+    pub strikes: u32,
+}
+
+#[derive(Debug)]
+struct Cooldown {
+    until: Instant,
+    resumes_at: DateTime<Utc>,
+    strikes: u32,
+}
+
+/// Cool-downs of the providers that rate limited a run, shared by all spawns
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    cooldowns: Arc<Mutex<HashMap<AgentProvider, Cooldown>>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold back spawns for `provider`, which rate limited a run asking to
+    /// wait `retry_after` if it said. Returns the cool-down.
+    pub fn record_rate_limit(
+        &self,
+        provider: AgentProvider,
+        retry_after: Option<Duration>,
+    ) -> Duration {
+        let mut cooldowns = self.cooldowns.lock().unwrap();
+        let strikes = cooldowns
+            .get(&provider)
+            .map_or(0, |cooldown| cooldown.strikes)
+            + 1;
+        let delay = match retry_after {
+            Some(delay) => delay.min(MAX_COOLDOWN),
+            None => jittered(backoff(strikes)),
+        };
+        tracing::warn!(
+            "{provider} rate limited a run ({strikes} in a row); holding back its spawns for {}s",
+            delay.as_secs()
+        );
+        cooldowns.insert(
+            provider,
+            Cooldown {
+                until: Instant::now() + delay,
+                resumes_at: Utc::now() + delay,
+                strikes,
+            },
+        );
+        delay
+    }
+
+    /// Clear `provider`'s cool-down after it served a run
+    pub fn record_success(&self, provider: AgentProvider) {
+        self.cooldowns.lock().unwrap().remove(&provider);
+    }
+
+    /// Time left before spawns for `provider` are let through
+    pub fn remaining(&self, provider: AgentProvider) -> Option<Duration> {
+        self.cooldowns
+            .lock()
+            .unwrap()
+            .get(&provider)
+            .map(|cooldown| cooldown.until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Wait out `provider`'s cool-down, or fail when `cancel` fires first.
+    /// Rate limits recorded in the meantime extend the wait.
+    pub async fn wait(
+        &self,
+        provider: AgentProvider,
+        cancel: &CancelScope,
+    ) -> Result<(), ExecutorError> {
+        while let Some(remaining) = self.remaining(provider) {
+            tracing::info!(
+                "Spawn for {provider} waits {}s for its rate limit to cool down",
+                remaining.as_secs()
+            );
+            cancel
+                .run(async {
+                    tokio::time::sleep(remaining).await;
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Providers whose spawns are held back, soonest to resume first
+    pub fn status(&self) -> Vec<ProviderCooldown> {
+        let now = Instant::now();
+        let mut status: Vec<_> = self
+            .cooldowns
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, cooldown)| cooldown.until > now)
+            .map(|(provider, cooldown)| ProviderCooldown {
+                provider: *provider,
+                until: cooldown.resumes_at,
+                strikes: cooldown.strikes,
+            })
+            .collect();
+        status.sort_by_key(|cooldown| cooldown.until);
+        status
+    }
+}
+
+/// Cool-down after the given number of rate limits in a row
+fn backoff(strikes: u32) -> Duration {
+    BASE_COOLDOWN
+        .saturating_mul(1 << strikes.saturating_sub(1).min(16))
+        .min(MAX_COOLDOWN)
+}
+
+/// Somewhere between half of `delay` and all of it
+fn jittered(delay: Duration) -> Duration {
+    let half = delay / 2;
+    let fraction = (Uuid::new_v4().as_u64_pair().0 % 1_000) as u32;
+    half + half * fraction / 1_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancellation::CancelReason;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_holds_back_provider() {
+        let limiter = RateLimiter::new();
+        let cancel = CancelScope::new();
+
+        let history = [
+            LogMsg::Stderr("429 Too Many Requests. Please retry after 30 seconds".to_string()),
+            LogMsg::Stdout("{}".to_string()),
+        ];
+        let cooldown =
+            limiter.record_rate_limit(BaseCodingAgent::ClaudeCode.into(), retry_after(&history));
+        assert_eq!(cooldown, Duration::from_secs(30));
+        // Claude Flow runs on the same provider, Codex doesn't
+        assert!(
+            limiter
+                .remaining(BaseCodingAgent::ClaudeFlow.into())
+                .is_some()
+        );
+        assert!(limiter.remaining(BaseCodingAgent::Codex.into()).is_none());
+        assert_eq!(limiter.status()[0].provider, AgentProvider::Anthropic);
+
+        let start = Instant::now();
+        limiter
+            .wait(AgentProvider::Anthropic, &cancel)
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= cooldown);
+        assert!(limiter.status().is_empty());
+
+        // Without a hint the cool-down backs off, and success clears it
+        let second = limiter.record_rate_limit(AgentProvider::Anthropic, None);
+        assert!((BASE_COOLDOWN..=BASE_COOLDOWN * 2).contains(&second));
+        limiter.record_success(AgentProvider::Anthropic);
+        assert!(limiter.remaining(AgentProvider::Anthropic).is_none());
+
+        limiter.record_rate_limit(AgentProvider::OpenAi, Some(Duration::from_secs(60)));
+        cancel.cancel(CancelReason::UserRequested);
+        assert!(matches!(
+            limiter.wait(AgentProvider::OpenAi, &cancel).await,
+            Err(ExecutorError::Cancelled(CancelReason::UserRequested))
+        ));
+    }
+}
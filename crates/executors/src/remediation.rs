@@ -0,0 +1,243 @@
+//! What went wrong with an executor, and what the user can do about it.
+//!
+//! [`ExecutorError`] has grown a variant per failure site, and its messages
+//! often end in a raw `io::Error`. UIs shouldn't have to match on those, so
+//! every error falls into one of a few [`ExecutorErrorKind`]s, and each comes
+//! with a [`Remediation`]: a machine-readable hint such as "install the
+//! agent" or "log in again" a UI can turn into an action, with a sentence to
+//! show next to the error. Failures classified from a run's stderr map onto
+//! the same kinds.
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cancellation::CancelReason, executors::ExecutorError,
+    logs::stderr_processor::ExecutorFailureReason,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutorErrorKind {
+    /// The agent's executable is missing, or too old to run
+    NotInstalled,
+    /// The agent isn't logged in, or its login has expired
+    AuthExpired,
+    RateLimited,
+    /// The agent process could not be started or kept running
+    SpawnFailed,
+    /// Output or configuration that could not be parsed
+    StreamParse,
+    /// The run was cancelled before it finished
+    Interrupted,
+    /// The run took longer than it was allowed to
+    Timeout,
+    /// The executor or profile can't do what was asked
+    Unsupported,
+    /// The request refers to something that doesn't exist or isn't allowed
+    /// where it points
+    InvalidRequest,
+    /// A policy kept the run from starting
+    Forbidden,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Remediation {
+    InstallAgent,
+    UpgradeAgent,
+    LogIn,
+    /// Wait for the provider's rate limit to reset, then retry
+    WaitAndRetry,
+    Retry,
+    /// Allow the run more time in the profile's runtime limits
+    RaiseTimeout,
+    /// Fix the profile or configuration file the error points to
+    FixConfig,
+    /// Change the request, e.g. pick another executor or directory
+    ChangeRequest,
+    /// Ask an administrator to allow the run
+    AskAdmin,
+    ReportBug,
+}
+
+impl Remediation {
+    /// Sentence shown next to the error
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::InstallAgent => "Install the coding agent, or check that it is on your PATH.",
+            Self::UpgradeAgent => "Update the coding agent to a newer version.",
+            Self::LogIn => "Log in to the coding agent again.",
+            Self::WaitAndRetry => "The provider is rate limiting requests; try again later.",
+            Self::Retry => "Try again.",
+            Self::RaiseTimeout => "Raise the profile's runtime limit, or split the task up.",
+            Self::FixConfig => "Check the executor profile and its configuration.",
+            Self::ChangeRequest => "Pick a different executor, session or directory.",
+            Self::AskAdmin => "Ask an administrator to allow this run.",
+            Self::ReportBug => "This looks like a bug; please report it.",
+        }
+    }
+}
+
+/// An executor error as UIs receive it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ExecutorErrorInfo {
+    pub kind: ExecutorErrorKind,
+    pub remediation: Remediation,
+    pub message: String,
+    pub hint: String,
+}
+
+impl ExecutorErrorKind {
+    /// What the user can usually do about errors of this kind
+    pub fn remediation(self) -> Remediation {
+        match self {
+            Self::NotInstalled => Remediation::InstallAgent,
+            Self::AuthExpired => Remediation::LogIn,
+            Self::RateLimited => Remediation::WaitAndRetry,
+            Self::SpawnFailed | Self::Interrupted | Self::Other => Remediation::Retry,
+            Self::StreamParse => Remediation::ReportBug,
+            Self::Timeout => Remediation::RaiseTimeout,
+            Self::Unsupported | Self::InvalidRequest => Remediation::ChangeRequest,
+            Self::Forbidden => Remediation::AskAdmin,
+        }
+    }
+}
+
+impl From<ExecutorFailureReason> for ExecutorErrorKind {
+    fn from(reason: ExecutorFailureReason) -> Self {
+        match reason {
+            ExecutorFailureReason::AuthFailure => Self::AuthExpired,
+            ExecutorFailureReason::RateLimited => Self::RateLimited,
+            ExecutorFailureReason::MissingBinary => Self::NotInstalled,
+            ExecutorFailureReason::OutOfMemory | ExecutorFailureReason::ResourceLimit => {
+                Self::SpawnFailed
+            }
+            ExecutorFailureReason::Network | ExecutorFailureReason::ContextOverflow => Self::Other,
+        }
+    }
+}
+
+impl ExecutorError {
+    pub fn kind(&self) -> ExecutorErrorKind {
+        match self {
+            ExecutorError::ExecutableNotFound { .. }
+            | ExecutorError::VersionTooOld { .. }
+            | ExecutorError::InstallFailed(_) => ExecutorErrorKind::NotInstalled,
+            ExecutorError::SpawnError(e) | ExecutorError::Io(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => ExecutorErrorKind::NotInstalled,
+                std::io::ErrorKind::TimedOut => ExecutorErrorKind::Timeout,
+                _ => ExecutorErrorKind::SpawnFailed,
+            },
+            ExecutorError::AuthRequired(_) | ExecutorError::LoginFailed(_) => {
+                ExecutorErrorKind::AuthExpired
+            }
+            ExecutorError::Json(_)
+            | ExecutorError::TomlSerialize(_)
+            | ExecutorError::TomlDeserialize(_) => ExecutorErrorKind::StreamParse,
+            ExecutorError::Cancelled(CancelReason::Timeout) | ExecutorError::SpawnTimeout(_) => {
+                ExecutorErrorKind::Timeout
+            }
+            ExecutorError::Cancelled(_) => ExecutorErrorKind::Interrupted,
+            ExecutorError::FollowUpNotSupported(_)
+            | ExecutorError::UnknownExecutorType(_)
+            | ExecutorError::SetupHelperNotSupported
+            | ExecutorError::ForkNotSupported
+            | ExecutorError::InstallNotSupported
+            | ExecutorError::RemoteNotSupported
+            | ExecutorError::DryRunNotSupported
+            | ExecutorError::PermissionModeNotSupported { .. }
+            | ExecutorError::HandoffNotSupported
+            | ExecutorError::LoginNotSupported => ExecutorErrorKind::Unsupported,
+            ExecutorError::SessionNotFound(_)
+            | ExecutorError::InvalidAttachment(_)
+            | ExecutorError::InvalidWorkingDir(_)
+            | ExecutorError::NothingToReview(_)
+            | ExecutorError::Secret(_)
+            | ExecutorError::InvalidWorkflow(_) => ExecutorErrorKind::InvalidRequest,
+            ExecutorError::Unauthorized(_) => ExecutorErrorKind::Forbidden,
+            ExecutorError::ExecutorApprovalError(_)
+            | ExecutorError::CommandBuild(_)
+            | ExecutorError::HookFailed { .. }
+            | ExecutorError::ReviewDiff(_) => ExecutorErrorKind::Other,
+        }
+    }
+
+    /// What the user can do about this error, where it differs from what
+    /// its kind usually calls for
+    pub fn remediation(&self) -> Remediation {
+        match self {
+            ExecutorError::VersionTooOld { .. } => Remediation::UpgradeAgent,
+            ExecutorError::TomlDeserialize(_)
+            | ExecutorError::CommandBuild(_)
+            | ExecutorError::HookFailed { .. }
+            | ExecutorError::UnknownExecutorType(_)
+            | ExecutorError::PermissionModeNotSupported { .. }
+            | ExecutorError::Secret(_)
+            | ExecutorError::InvalidWorkflow(_) => Remediation::FixConfig,
+            error => error.kind().remediation(),
+        }
+    }
+
+    pub fn info(&self) -> ExecutorErrorInfo {
+        let remediation = self.remediation();
+        ExecutorErrorInfo {
+            kind: self.kind(),
+            remediation,
+            message: self.to_string(),
+            hint: remediation.hint().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_carry_kind_and_remediation() {
+        let missing = ExecutorError::ExecutableNotFound {
+            program: "codex".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(missing.info()).unwrap(),
+            serde_json::json!({
+                "kind": "not_installed",
+                "remediation": "install_agent",
+                "message": "Executable `codex` not found in PATH",
+                "hint": "Install the coding agent, or check that it is on your PATH.",
+            })
+        );
+
+        let old = ExecutorError::VersionTooOld {
+            found: "0.1.0".to_string(),
+            required: "0.2.0".to_string(),
+        };
+        assert_eq!(old.kind(), ExecutorErrorKind::NotInstalled);
+        assert_eq!(old.remediation(), Remediation::UpgradeAgent);
+
+        let io = |kind| ExecutorError::Io(std::io::Error::from(kind));
+        assert_eq!(
+            io(std::io::ErrorKind::NotFound).kind(),
+            ExecutorErrorKind::NotInstalled
+        );
+        assert_eq!(
+            io(std::io::ErrorKind::PermissionDenied).kind(),
+            ExecutorErrorKind::SpawnFailed
+        );
+
+        assert_eq!(
+            ExecutorError::Cancelled(CancelReason::Timeout).remediation(),
+            Remediation::RaiseTimeout
+        );
+        assert_eq!(
+            ExecutorError::Cancelled(CancelReason::UserRequested).kind(),
+            ExecutorErrorKind::Interrupted
+        );
+        assert_eq!(
+            ExecutorErrorKind::from(ExecutorFailureReason::RateLimited).remediation(),
+            Remediation::WaitAndRetry
+        );
+    }
+}
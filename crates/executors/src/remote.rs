@@ -0,0 +1,235 @@
+//! Running executors on another machine over SSH.
+//!
+//! Heavy agents can run on a build box instead of the machine serving vibe
+//! kanban. A profile with an [`SshRemote`] spawns its agent through `ssh`: the
+//! command the executor builds is run on the host, in the host's copy of the
+//! worktree, with the variables the executor set. Stdin, stdout and stderr go
+//! over the connection, so prompts, follow-ups and log streaming work as they
+//! do locally. The variables are sent over stdin too, through a local `sh`,
+//! and the host's login shell must be POSIX compatible to read them.
+//!
+//! The host must see the worktrees, e.g. through a shared mount, at
+//! `worktree_root`. Paths under the local worktree directory are mapped below
+//! it; other paths are used unchanged. The agent stops once the connection
+//! drops and it reads its input or writes output.
+
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use ts_rs::TS;
+
+use crate::command::CommandBuildError;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct SshRemote {
+    /// Host to connect to, as accepted by `ssh`
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Private key to authenticate with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    /// Where the host sees the worktrees
+    pub worktree_root: String,
+    /// Extra `-o` options for `ssh`, e.g. `StrictHostKeyChecking=accept-new`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_options: Option<Vec<String>>,
+}
+
+impl SshRemote {
+    /// `local` as the host sees it
+    pub fn remote_path(&self, local: &Path) -> PathBuf {
+        match local.strip_prefix(local_worktree_root()) {
+            Ok(rel) => Path::new(&self.worktree_root).join(rel),
+            Err(_) => local.to_path_buf(),
+        }
+    }
+
+    /// An `ssh` command that runs `command` on the host, with its working
+    /// directory and the variables set on it. Stdio is piped.
+    ///
+    /// Variables may hold secrets, so they never appear on a command line: a
+    /// local `sh` sends them ahead of the command's own stdin, and the remote
+    /// shell reads them up to a one-off end marker before it execs the
+    /// command.
+    pub fn wrap(&self, command: &Command) -> Result<Command, CommandBuildError> {
+        let command = command.as_std();
+        let end_marker = format!("vk-env-{}", uuid::Uuid::new_v4().simple());
+        let mut preamble = String::new();
+        for (key, value) in command.get_envs() {
+            // Removed variables aren't set on the host to begin with
+            let Some(value) = value else { continue };
+            let assignment = format!("{}={}", key.to_string_lossy(), value.to_string_lossy());
+            preamble.push_str(&format!("export {}\n", shlex::try_quote(&assignment)?));
+        }
+        preamble.push_str(&end_marker);
+
+        let mut script = format!(
+            "vk_env=; while IFS= read -r vk_line && [ \"$vk_line\" != {end_marker} ]; \
+             do vk_env=\"$vk_env$vk_line\n\"; done; eval \"$vk_env\"; "
+        );
+        if let Some(dir) = command.get_current_dir() {
+            let dir = self.remote_path(dir);
+            script.push_str(&format!(
+                "cd {} && ",
+                shlex::try_quote(&dir.to_string_lossy())?
+            ));
+        }
+        script.push_str("exec");
+        let argv = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned());
+        for word in argv {
+            script.push(' ');
+            script.push_str(&shlex::try_quote(&word)?);
+        }
+
+        let mut ssh = Command::new("sh");
+        ssh.kill_on_drop(true)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .env(REMOTE_ENV_VAR, preamble)
+            .arg("-c")
+            .arg(RELAY_SCRIPT)
+            .arg("vk-ssh")
+            .args(["ssh", "-T", "-o", "BatchMode=yes"]);
+        if let Some(port) = self.port {
+            ssh.arg("-p").arg(port.to_string());
+        }
+        if let Some(identity_file) = &self.identity_file {
+            ssh.arg("-i").arg(identity_file);
+        }
+        for option in self.ssh_options.iter().flatten() {
+            ssh.arg("-o").arg(option);
+        }
+        // A host starting with `-` must not be taken for an option
+        ssh.arg("--");
+        match &self.user {
+            Some(user) => ssh.arg(format!("{user}@{}", self.host)),
+            None => ssh.arg(&self.host),
+        };
+        ssh.arg(script);
+        Ok(ssh)
+    }
+}
+
+/// Local variable carrying the remote variables to [`RELAY_SCRIPT`]. Unlike
+/// arguments, a process's environment is only readable by its owner.
+const REMOTE_ENV_VAR: &str = "VK_REMOTE_ENV";
+
+/// Runs `ssh` (its arguments) with the remote variables written to its stdin
+/// ahead of the agent's own input
+const RELAY_SCRIPT: &str = "vk_env=$VK_REMOTE_ENV; unset VK_REMOTE_ENV; \
+     { printf '%s\\n' \"$vk_env\"; exec cat; } | exec \"$@\"";
+
+/// Local directory worktrees are created in, as in the server's
+/// `WorktreeManager`
+fn local_worktree_root() -> PathBuf {
+    workspace_utils::path::get_vibe_kanban_temp_dir().join("worktrees")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wraps_command_in_ssh() {
+        let remote = SshRemote {
+            host: "build-box".to_string(),
+            user: Some("ci".to_string()),
+            port: Some(2222),
+            identity_file: None,
+            worktree_root: "/srv/worktrees".to_string(),
+            ssh_options: Some(vec!["StrictHostKeyChecking=accept-new".to_string()]),
+        };
+        let mut command = Command::new("npx");
+        command
+            .current_dir(local_worktree_root().join("1a2b-fix-login/web"))
+            .args(["-y", "@anthropic-ai/claude-code", "-p"])
+            .arg("Fix the user's login")
+            .env("VK_TASK_TITLE", "Fix login");
+
+        let ssh = remote.wrap(&command).unwrap();
+        let ssh = ssh.as_std();
+        assert_eq!(ssh.get_program(), "sh");
+        let args: Vec<_> = ssh.get_args().map(|arg| arg.to_str().unwrap()).collect();
+        assert_eq!(
+            args[..12],
+            [
+                "-c",
+                RELAY_SCRIPT,
+                "vk-ssh",
+                "ssh",
+                "-T",
+                "-o",
+                "BatchMode=yes",
+                "-p",
+                "2222",
+                "-o",
+                "StrictHostKeyChecking=accept-new",
+                "--",
+            ]
+        );
+        assert_eq!(args[12], "ci@build-box");
+        assert!(args[13].ends_with(
+            "cd /srv/worktrees/1a2b-fix-login/web && \
+             exec npx -y @anthropic-ai/claude-code -p \"Fix the user's login\""
+        ));
+        // Values stay off the command line
+        assert!(args.iter().all(|arg| !arg.contains("Fix login")));
+        assert_eq!(
+            remote.remote_path(Path::new("/home/me/project")),
+            Path::new("/home/me/project")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sends_variables_ahead_of_stdin() {
+        use tokio::io::AsyncWriteExt;
+
+        let remote = SshRemote {
+            host: "build-box".to_string(),
+            user: None,
+            port: None,
+            identity_file: None,
+            worktree_root: "/srv/worktrees".to_string(),
+            ssh_options: None,
+        };
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", "printf '%s|' \"$VK_TASK_TITLE\"; cat"])
+            .env("VK_TASK_TITLE", "it's\nmulti-line");
+        let wrapped = remote.wrap(&command).unwrap();
+        let wrapped = wrapped.as_std();
+        let args: Vec<_> = wrapped.get_args().collect();
+        let script = args.last().unwrap();
+        let (_, preamble) = wrapped
+            .get_envs()
+            .find(|(key, _)| *key == REMOTE_ENV_VAR)
+            .unwrap();
+
+        // Run the remote side with `sh -c` in place of `ssh host`
+        let mut relay = Command::new("sh");
+        relay
+            .args(["-c", RELAY_SCRIPT, "vk-ssh", "sh", "-c"])
+            .arg(script)
+            .env(REMOTE_ENV_VAR, preamble.unwrap())
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped());
+        let mut child = relay.spawn().unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"the prompt").await.unwrap();
+        drop(stdin);
+        let output = child.wait_with_output().await.unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "it's\nmulti-line|the prompt"
+        );
+    }
+}
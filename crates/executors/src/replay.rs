@@ -0,0 +1,269 @@
+//! Recording and replaying raw executor output.
+//!
+//! When `EXECUTOR_RECORD_DIR` is set, the raw stdout/stderr of every coding
+//! agent run is written to a JSONL fixture in that directory. A [`FakeChild`]
+//! later streams such a fixture through an executor's `normalize_logs`, which
+//! lets tests exercise log normalization against real output without
+//! installing or running the agent. Recordings are sealed like other
+//! transcripts when encryption at rest is configured.
+
+use std::{
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::Instant};
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::{encryption, executors::StandardCodingAgentExecutor};
+
+/// Directory that recordings of live runs are written to, if set
+pub const RECORD_DIR_ENV: &str = "EXECUTOR_RECORD_DIR";
+
+/// How long a replayed store must stay unchanged before normalization is
+/// considered complete
+const SETTLE_INTERVAL: Duration = Duration::from_millis(50);
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn record_dir() -> Option<PathBuf> {
+    std::env::var_os(RECORD_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedChunk {
+    /// Milliseconds since the recording started
+    pub at_ms: u64,
+    pub stream: OutputStream,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub chunks: Vec<RecordedChunk>,
+}
+
+impl Recording {
+    /// Record the raw output pushed to `msg_store` until it finishes
+    pub async fn capture(msg_store: Arc<MsgStore>) -> Self {
+        let start = Instant::now();
+        let mut chunks = Vec::new();
+        let mut stream = msg_store.history_plus_stream();
+        while let Some(Ok(msg)) = stream.next().await {
+            let (stream, data) = match msg {
+                LogMsg::Stdout(data) => (OutputStream::Stdout, data),
+                LogMsg::Stderr(data) => (OutputStream::Stderr, data),
+                LogMsg::Finished => break,
+                _ => continue,
+            };
+            chunks.push(RecordedChunk {
+                at_ms: start.elapsed().as_millis() as u64,
+                stream,
+                data,
+            });
+        }
+        Self { chunks }
+    }
+
+    /// Capture `msg_store` in the background and save it to `path` once the
+    /// run finishes
+    pub fn spawn_recorder(msg_store: Arc<MsgStore>, path: PathBuf) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let recording = Self::capture(msg_store).await;
+            if let Err(e) = recording.save(&path) {
+                tracing::warn!("Failed to save recording to {}: {e}", path.display());
+            }
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        let mut chunks = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            chunks.push(serde_json::from_str(&encryption::open_line(&line)?)?);
+        }
+        Ok(Self { chunks })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        for chunk in &self.chunks {
+            let line = serde_json::to_string(chunk)?;
+            writer.write_all(encryption::seal_line(&line)?.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()
+    }
+}
+
+/// Stands in for a spawned executor by replaying a [`Recording`]
+#[derive(Debug, Clone)]
+pub struct FakeChild {
+    recording: Recording,
+    /// Reproduce the recorded delays between chunks instead of replaying
+    /// everything at once
+    realtime: bool,
+}
+
+impl From<Recording> for FakeChild {
+    fn from(recording: Recording) -> Self {
+        Self {
+            recording,
+            realtime: false,
+        }
+    }
+}
+
+impl FakeChild {
+    pub fn from_fixture(path: &Path) -> io::Result<Self> {
+        Recording::load(path).map(Self::from)
+    }
+
+    pub fn realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// Push the recorded output into `msg_store`, followed by `Finished`
+    pub async fn stream_into(&self, msg_store: &MsgStore) {
+        let start = Instant::now();
+        for chunk in &self.recording.chunks {
+            if self.realtime {
+                tokio::time::sleep_until(start + Duration::from_millis(chunk.at_ms)).await;
+            }
+            match chunk.stream {
+                OutputStream::Stdout => msg_store.push_stdout(chunk.data.clone()),
+                OutputStream::Stderr => msg_store.push_stderr(chunk.data.clone()),
+            }
+        }
+        msg_store.push_finished();
+    }
+
+    /// Normalize the recording with `executor`, returning the store once
+    /// normalization has stopped producing output
+    pub async fn replay<E>(&self, executor: &E, worktree_path: &Path) -> Arc<MsgStore>
+    where
+        E: StandardCodingAgentExecutor + ?Sized,
+    {
+        let msg_store = Arc::new(MsgStore::new());
+        executor.normalize_logs(msg_store.clone(), worktree_path);
+        self.stream_into(&msg_store).await;
+        wait_until_settled(&msg_store).await;
+        msg_store
+    }
+}
+
+async fn wait_until_settled(msg_store: &MsgStore) {
+    let deadline = Instant::now() + SETTLE_TIMEOUT;
+    let mut len = msg_store.get_history().len();
+    while Instant::now() < deadline {
+        tokio::time::sleep(SETTLE_INTERVAL).await;
+        let next = msg_store.get_history().len();
+        if next == len {
+            return;
+        }
+        len = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        command::CmdOverrides,
+        executors::{AppendPrompt, claude_flow::ClaudeFlow},
+        logs::{NormalizedEntryType, utils::patch::latest_normalized_entries},
+        profile::RunOverrides,
+    };
+
+    fn fixture_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("replay-{}-{name}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_capture_and_round_trip() {
+        let msg_store = Arc::new(MsgStore::new());
+        msg_store.push_stdout("line one\n");
+        msg_store.push_session_id("session".to_string());
+        msg_store.push_stderr("warning\n");
+        msg_store.push_finished();
+
+        let recording = Recording::capture(msg_store).await;
+        let streams: Vec<_> = recording.chunks.iter().map(|c| c.stream).collect();
+        assert_eq!(streams, vec![OutputStream::Stdout, OutputStream::Stderr]);
+
+        let path = fixture_path("round-trip");
+        recording.save(&path).unwrap();
+        assert_eq!(Recording::load(&path).unwrap(), recording);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_normalizes_fixture() {
+        let recording = Recording {
+            chunks: [
+                r#"{"type":"system","subtype":"init","session_id":"abc"}"#,
+                r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Hello from the fixture"}]}}"#,
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| RecordedChunk {
+                at_ms: i as u64 * 10,
+                stream: OutputStream::Stdout,
+                data: format!("{line}\n"),
+            })
+            .collect(),
+        };
+        let flow = ClaudeFlow {
+            append_prompt: AppendPrompt(None),
+            non_interactive: Some(true),
+            enable_chaining: None,
+            agent_id: None,
+            workflow_file: None,
+            workflow: None,
+            task_description: None,
+            memory_namespace: None,
+            persist_memory: None,
+            history: None,
+            history_turns: None,
+            model: None,
+            fallback_model: None,
+            max_thinking_tokens: None,
+            reasoning_effort: None,
+            api_key_secret: None,
+            prompt_delivery: None,
+            interactive: None,
+            cmd: CmdOverrides::default(),
+            run: RunOverrides::default(),
+        };
+
+        let msg_store = FakeChild::from(recording)
+            .realtime(true)
+            .replay(&flow, Path::new("/tmp/replay"))
+            .await;
+        let entries = latest_normalized_entries(&msg_store.get_history());
+        assert!(entries.values().any(|entry| {
+            matches!(entry.entry_type, NormalizedEntryType::AssistantMessage)
+                && entry.content == "Hello from the fixture"
+        }));
+    }
+}
@@ -0,0 +1,342 @@
+//! CPU, memory and process limits on spawned agents.
+//!
+//! A runaway build step started by an agent can exhaust the host. Profiles set
+//! [`ResourceLimits`] through [`RuntimeOverrides`], and [`ExecutionEnv`] applies
+//! them to the agent before it starts, so everything it spawns inherits them.
+//!
+//! Without further setup the limits are rlimits, which apply to each process
+//! on its own: CPU seconds, address space, and the number of processes of the
+//! user running the server. When `VK_CGROUP_ROOT` points at a cgroup v2
+//! directory delegated to the server, with the `memory`, `pids` and `cpu`
+//! controllers enabled, each execution instead gets a cgroup below it, and
+//! memory, process and CPU share limits cover the agent's whole process tree.
+//!
+//! Once a limited run fails, [`ResourceViolation::detect`] tells from its exit
+//! signal and cgroup events whether a limit was hit.
+//!
+//! [`RuntimeOverrides`]: crate::profile::RuntimeOverrides
+//! [`ExecutionEnv`]: crate::env::ExecutionEnv
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::logs::{
+    NormalizedEntry, NormalizedEntryError, NormalizedEntryType,
+    stderr_processor::ExecutorFailureReason,
+};
+
+/// Delegated cgroup v2 directory executions get their cgroups under
+pub const CGROUP_ROOT_ENV: &str = "VK_CGROUP_ROOT";
+/// Period `cpu_percent` is a share of, in microseconds
+const CPU_PERIOD_US: u64 = 100_000;
+/// Seconds between the CPU rlimit's warning signal and the kill
+const CPU_GRACE_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct ResourceLimits {
+    /// CPU time each process may use, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_secs: Option<u64>,
+    /// Memory of the execution's cgroup, or address space of each process
+    /// without one, in MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    /// Processes in the execution's cgroup, or of the server's user without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_processes: Option<u64>,
+    /// Share of one CPU the execution may use; needs a cgroup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_percent: Option<u32>,
+}
+
+impl ResourceLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.cpu_secs.is_none()
+            && self.memory_mb.is_none()
+            && self.max_processes.is_none()
+            && self.cpu_percent.is_none()
+    }
+
+    /// These limits, with the ones left unset taken from `base`
+    pub fn or(&self, base: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            cpu_secs: self.cpu_secs.or(base.cpu_secs),
+            memory_mb: self.memory_mb.or(base.memory_mb),
+            max_processes: self.max_processes.or(base.max_processes),
+            cpu_percent: self.cpu_percent.or(base.cpu_percent),
+        }
+    }
+
+    /// Cgroup of an execution, if the server has a cgroup root delegated to it
+    pub fn cgroup_dir(execution_id: Uuid) -> Option<PathBuf> {
+        let root = std::env::var_os(CGROUP_ROOT_ENV)?;
+        Some(PathBuf::from(root).join(format!("vk-{execution_id}")))
+    }
+
+    /// Remove an execution's cgroup once its processes are gone
+    pub fn remove_cgroup(dir: &Path) {
+        if let Err(e) = fs::remove_dir(dir)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            tracing::warn!("Failed to remove cgroup {}: {}", dir.display(), e);
+        }
+    }
+
+    /// Limit the process `command` starts, in the cgroup at `cgroup_dir` if
+    /// one is given and can be set up
+    #[cfg(unix)]
+    pub(crate) fn apply(&self, command: &mut tokio::process::Command, cgroup_dir: Option<&Path>) {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+        use nix::sys::resource::{Resource, setrlimit};
+
+        let cgroup_procs = cgroup_dir.and_then(|dir| match self.create_cgroup(dir) {
+            Ok(()) => CString::new(dir.join("cgroup.procs").as_os_str().as_bytes()).ok(),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to set up cgroup {}, limiting with rlimits: {}",
+                    dir.display(),
+                    e
+                );
+                None
+            }
+        });
+        let in_cgroup = cgroup_procs.is_some();
+
+        let mut rlimits = Vec::new();
+        if let Some(secs) = self.cpu_secs {
+            // SIGXCPU at the soft limit, SIGKILL at the hard one
+            rlimits.push((Resource::RLIMIT_CPU, secs, secs + CPU_GRACE_SECS));
+        }
+        if !in_cgroup {
+            if let Some(mb) = self.memory_mb {
+                let bytes = mb.saturating_mul(1024 * 1024);
+                rlimits.push((Resource::RLIMIT_AS, bytes, bytes));
+            }
+            if let Some(max) = self.max_processes {
+                rlimits.push((Resource::RLIMIT_NPROC, max, max));
+            }
+        }
+        if rlimits.is_empty() && !in_cgroup {
+            return;
+        }
+
+        // Only async-signal-safe calls between fork and exec
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(procs) = &cgroup_procs {
+                    join_cgroup(procs)?;
+                }
+                for (resource, soft, hard) in &rlimits {
+                    setrlimit(*resource, *soft, *hard)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(unix)]
+    fn create_cgroup(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        if let Some(mb) = self.memory_mb {
+            fs::write(
+                dir.join("memory.max"),
+                mb.saturating_mul(1024 * 1024).to_string(),
+            )?;
+            // Without swap the limit can't be sidestepped
+            let _ = fs::write(dir.join("memory.swap.max"), "0");
+        }
+        if let Some(max) = self.max_processes {
+            fs::write(dir.join("pids.max"), max.to_string())?;
+        }
+        if let Some(percent) = self.cpu_percent {
+            let quota = CPU_PERIOD_US * u64::from(percent.max(1)) / 100;
+            fs::write(dir.join("cpu.max"), format!("{quota} {CPU_PERIOD_US}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Move the calling process into the cgroup whose `cgroup.procs` is `procs`
+#[cfg(unix)]
+fn join_cgroup(procs: &std::ffi::CStr) -> io::Result<()> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    use nix::{
+        fcntl::{OFlag, open},
+        sys::stat::Mode,
+        unistd::write,
+    };
+
+    let fd = open(procs, OFlag::O_WRONLY | OFlag::O_CLOEXEC, Mode::empty())?;
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    // "0" stands for the writing process
+    write(&fd, b"0")?;
+    Ok(())
+}
+
+/// Which limit a failed run hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceViolation {
+    Cpu,
+    Memory,
+    Processes,
+}
+
+impl fmt::Display for ResourceViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cpu => write!(f, "Execution was killed after exceeding its CPU time limit"),
+            Self::Memory => write!(f, "Execution was killed after exceeding its memory limit"),
+            Self::Processes => write!(f, "Execution hit its process limit"),
+        }
+    }
+}
+
+impl ResourceViolation {
+    /// Limit hit by a run that exited with `status`, as told by its exit
+    /// signal and the events of its cgroup at `cgroup_dir`
+    pub fn detect(status: Option<&ExitStatus>, cgroup_dir: Option<&Path>) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            if status.and_then(|status| status.signal()) == Some(nix::libc::SIGXCPU) {
+                return Some(Self::Cpu);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = status;
+
+        let dir = cgroup_dir?;
+        if event_count(&dir.join("memory.events"), "oom_kill") > 0 {
+            return Some(Self::Memory);
+        }
+        if event_count(&dir.join("pids.events"), "max") > 0 {
+            return Some(Self::Processes);
+        }
+        None
+    }
+
+    pub fn failure_reason(self) -> ExecutorFailureReason {
+        match self {
+            Self::Memory => ExecutorFailureReason::OutOfMemory,
+            Self::Cpu | Self::Processes => ExecutorFailureReason::ResourceLimit,
+        }
+    }
+
+    /// Normalized error entry describing the violation, for display in the
+    /// conversation
+    pub fn to_normalized_entry(&self) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::ErrorMessage {
+                error_type: NormalizedEntryError::Other,
+            },
+            content: self.to_string(),
+            metadata: Some(serde_json::json!({ "failure_reason": self.failure_reason() })),
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+/// Count of `key` in a cgroup events file, 0 if it can't be read
+fn event_count(path: &Path, key: &str) -> u64 {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|events| {
+            events.lines().find_map(|line| {
+                line.strip_prefix(key)?
+                    .strip_prefix(' ')?
+                    .trim()
+                    .parse()
+                    .ok()
+            })
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_violations_from_cgroup_events() {
+        let dir = std::env::temp_dir().join(format!("vk-cgroup-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(ResourceViolation::detect(None, Some(&dir)), None);
+
+        fs::write(dir.join("pids.events"), "max 3\n").unwrap();
+        fs::write(
+            dir.join("memory.events"),
+            "low 0\nhigh 0\nmax 12\noom 1\noom_kill 0\n",
+        )
+        .unwrap();
+        assert_eq!(
+            ResourceViolation::detect(None, Some(&dir)),
+            Some(ResourceViolation::Processes)
+        );
+        fs::write(
+            dir.join("memory.events"),
+            "low 0\nhigh 0\nmax 12\noom 1\noom_kill 1\n",
+        )
+        .unwrap();
+        let violation = ResourceViolation::detect(None, Some(&dir)).unwrap();
+        assert_eq!(violation, ResourceViolation::Memory);
+        assert_eq!(
+            violation.failure_reason(),
+            ExecutorFailureReason::OutOfMemory
+        );
+        fs::remove_dir_all(dir).unwrap();
+
+        let profile = ResourceLimits {
+            memory_mb: Some(2048),
+            ..Default::default()
+        };
+        let base = ResourceLimits {
+            memory_mb: Some(512),
+            max_processes: Some(256),
+            ..Default::default()
+        };
+        assert_eq!(
+            profile.or(&base),
+            ResourceLimits {
+                memory_mb: Some(2048),
+                max_processes: Some(256),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cpu_limit_kills_with_sigxcpu() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut command = tokio::process::Command::new("sh");
+        command.args(["-c", "while :; do :; done"]);
+        ResourceLimits {
+            cpu_secs: Some(1),
+            ..Default::default()
+        }
+        .apply(&mut command, None);
+        let status = tokio::time::timeout(std::time::Duration::from_secs(30), command.status())
+            .await
+            .expect("CPU limit should stop the loop")
+            .unwrap();
+        assert_eq!(status.signal(), Some(nix::libc::SIGXCPU));
+        assert_eq!(
+            ResourceViolation::detect(Some(&status), None),
+            Some(ResourceViolation::Cpu)
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Retrying executor spawns that fail for transient reasons.
+//!
+//! Spawning through `npx` occasionally fails on the first try: the package
+//! cache is being populated by a concurrent run, the binary is briefly busy, or
+//! a resolution hits a network hiccup. A per-profile [`RetryPolicy`] retries
+//! such failures with exponential backoff; errors outside the configured
+//! classes (e.g. missing authentication) are returned immediately.
+//!
+//! The same policy decides whether a run that failed later, for a reason
+//! classified from its stderr, is started again: rate limits are retried by
+//! default, auth failures never are. Runs that overflowed the agent's context
+//! window are retried once it is compacted, see [`crate::context_overflow`].
+
+use std::{future::Future, time::Duration};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cancellation::CancelScope, executors::ExecutorError,
+    logs::stderr_processor::ExecutorFailureReason, profile::RuntimeOverrides,
+};
+
+/// Kind of failure a retry policy may retry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryableErrorClass {
+    /// The OS failed to start the process or set up its pipes
+    Io,
+    /// The executable could not be resolved on PATH
+    ExecutableNotFound,
+    /// The agent exited after hitting a rate limit
+    RateLimited,
+    /// The agent exited after a network error
+    Network,
+    /// The agent exited after its conversation outgrew the context window;
+    /// retried after compacting it
+    ContextOverflow,
+}
+
+impl RetryableErrorClass {
+    pub fn of(error: &ExecutorError) -> Option<Self> {
+        match error {
+            ExecutorError::SpawnError(_) | ExecutorError::Io(_) => Some(Self::Io),
+            ExecutorError::ExecutableNotFound { .. } => Some(Self::ExecutableNotFound),
+            _ => None,
+        }
+    }
+
+    /// Class of a run that failed after spawning; only transient reasons
+    /// have one
+    pub fn of_failure(reason: ExecutorFailureReason) -> Option<Self> {
+        match reason {
+            ExecutorFailureReason::RateLimited => Some(Self::RateLimited),
+            ExecutorFailureReason::Network => Some(Self::Network),
+            ExecutorFailureReason::ContextOverflow => Some(Self::ContextOverflow),
+            ExecutorFailureReason::AuthFailure
+            | ExecutorFailureReason::OutOfMemory
+            | ExecutorFailureReason::ResourceLimit
+            | ExecutorFailureReason::MissingBinary => None,
+        }
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_backoff_ms() -> u64 {
+    10_000
+}
+
+fn default_retry_on() -> Vec<RetryableErrorClass> {
+    vec![
+        RetryableErrorClass::Io,
+        RetryableErrorClass::ExecutableNotFound,
+        RetryableErrorClass::RateLimited,
+        RetryableErrorClass::ContextOverflow,
+    ]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after each further failure
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the delay between attempts
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// Error classes that are worth retrying
+    #[serde(default = "default_retry_on")]
+    pub retry_on: Vec<RetryableErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            retry_on: default_retry_on(),
+        }
+    }
+}
+
+impl From<&RuntimeOverrides> for RetryPolicy {
+    /// Profiles without a retry policy spawn exactly once
+    fn from(cmd: &RuntimeOverrides) -> Self {
+        cmd.retry.clone().unwrap_or_else(Self::no_retry)
+    }
+}
+
+impl RetryPolicy {
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_retryable(&self, error: &ExecutorError) -> bool {
+        RetryableErrorClass::of(error).is_some_and(|class| self.retry_on.contains(&class))
+    }
+
+    /// Whether a run that failed for `reason` should be started again
+    pub fn retries_failure(&self, reason: ExecutorFailureReason) -> bool {
+        RetryableErrorClass::of_failure(reason).is_some_and(|class| self.retry_on.contains(&class))
+    }
+
+    /// Delay after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(32);
+        let delay = self.initial_backoff_ms.saturating_mul(factor);
+        Duration::from_millis(delay.min(self.max_backoff_ms))
+    }
+
+    /// Run `spawn` until it succeeds, fails with a non-retryable error, the
+    /// attempts are exhausted, or `cancel` is cancelled. The last error is
+    /// returned in the latter cases. Each attempt, but not the backoff
+    /// between them, is limited to `attempt_timeout`.
+    pub async fn run<T, F, Fut>(
+        &self,
+        cancel: &CancelScope,
+        attempt_timeout: Option<Duration>,
+        mut spawn: F,
+    ) -> Result<T, ExecutorError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ExecutorError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let started = async {
+                match attempt_timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, spawn())
+                        .await
+                        .unwrap_or(Err(ExecutorError::SpawnTimeout(timeout))),
+                    None => spawn().await,
+                }
+            };
+            match cancel.run(started).await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts && self.is_retryable(&e) => {
+                    let delay = self.backoff(attempt);
+                    tracing::warn!(
+                        "Spawn attempt {attempt}/{} failed: {e}; retrying in {}ms",
+                        self.max_attempts,
+                        delay.as_millis()
+                    );
+                    cancel
+                        .run(async {
+                            tokio::time::sleep(delay).await;
+                            Ok(())
+                        })
+                        .await?;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::cancellation::CancelReason;
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff_ms: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn not_found() -> ExecutorError {
+        ExecutorError::ExecutableNotFound {
+            program: "npx".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_until_success() {
+        let calls = AtomicU32::new(0);
+        let result = fast_policy(3)
+            .run(&CancelScope::new(), None, || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(not_found()),
+                    n => Ok(n),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        calls.store(0, Ordering::SeqCst);
+        let result: Result<(), _> = fast_policy(2)
+            .run(&CancelScope::new(), None, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(not_found())
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(ExecutorError::ExecutableNotFound { .. })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_errors_fail_fast() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = fast_policy(5)
+            .run(&CancelScope::new(), None, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(ExecutorError::AuthRequired("log in first".to_string()))
+            })
+            .await;
+        assert!(matches!(result, Err(ExecutorError::AuthRequired(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let cancel = CancelScope::new();
+        cancel.cancel(CancelReason::UserRequested);
+        let result: Result<(), _> = fast_policy(5)
+            .run(&cancel, None, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(not_found())
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(ExecutorError::Cancelled(CancelReason::UserRequested))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let policy = RetryPolicy {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 1_500,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(1_000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(1_500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_attempt_timeout_leaves_out_the_backoff() {
+        let calls = AtomicU32::new(0);
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            initial_backoff_ms: 5_000,
+            ..RetryPolicy::default()
+        };
+        let result = policy
+            .run(
+                &CancelScope::new(),
+                Some(Duration::from_secs(1)),
+                || async {
+                    match calls.fetch_add(1, Ordering::SeqCst) {
+                        0 => Err(not_found()),
+                        n => Ok(n),
+                    }
+                },
+            )
+            .await;
+        assert_eq!(result.unwrap(), 1);
+
+        let result: Result<(), _> = policy
+            .run(&CancelScope::new(), Some(Duration::from_secs(1)), || {
+                std::future::pending()
+            })
+            .await;
+        assert!(
+            matches!(result, Err(ExecutorError::SpawnTimeout(timeout)) if timeout.as_secs() == 1)
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Reviewer runs over the changes an earlier execution made.
+//!
+//! A coding agent request with a [`ReviewTarget`] spawns its agent to review
+//! rather than to implement: the crate gathers the workspace's diff against
+//! the target's base and wraps the request's prompt, as the reviewer's
+//! instructions, into a prompt carrying the diff that asks for one finding per
+//! line. Once the run exits, [`findings_from_history`] picks those lines out
+//! of the agent's messages, and each is added to the conversation as a
+//! [`NormalizedEntryType::ReviewFinding`] entry with its severity.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use ts_rs::TS;
+use workspace_utils::log_msg::LogMsg;
+
+use crate::{
+    executors::ExecutorError,
+    logs::{NormalizedEntry, NormalizedEntryType, utils::patch::latest_normalized_entries},
+    worktree::git,
+};
+
+/// Diffs longer than this are cut before they go into the prompt
+const MAX_REVIEW_DIFF: usize = 256 * 1024;
+
+/// Instructions for requests that don't give any
+const DEFAULT_INSTRUCTIONS: &str =
+    "Review these changes for bugs, regressions, security problems and missing tests.";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+pub struct ReviewTarget {
+    /// Branch or commit the reviewed changes started from. The diff runs from
+    /// its merge base with `HEAD` to the working tree, so uncommitted changes
+    /// are reviewed too.
+    pub base: String,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    TS,
+    Display,
+    EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum ReviewSeverity {
+    Critical,
+    Major,
+    Minor,
+    Nit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewFinding {
+    pub severity: ReviewSeverity,
+    pub path: Option<String>,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl ReviewTarget {
+    /// Changes under review in the repository at `dir`
+    pub async fn diff(&self, dir: &Path) -> Result<String, ExecutorError> {
+        let merge_base = git(dir, ["merge-base", self.base.as_str(), "HEAD"])
+            .await
+            .map_err(ExecutorError::ReviewDiff)?;
+        let diff = git(dir, ["diff", merge_base.as_str()])
+            .await
+            .map_err(ExecutorError::ReviewDiff)?;
+        if diff.is_empty() {
+            return Err(ExecutorError::NothingToReview(self.base.clone()));
+        }
+        Ok(diff)
+    }
+
+    /// Prompt asking the reviewer to follow `instructions` on the changes in
+    /// the repository at `dir`
+    pub async fn prompt(&self, instructions: &str, dir: &Path) -> Result<String, ExecutorError> {
+        let mut diff = self.diff(dir).await?;
+        if diff.len() > MAX_REVIEW_DIFF {
+            let cut = (0..=MAX_REVIEW_DIFF)
+                .rev()
+                .find(|&i| diff.is_char_boundary(i))
+                .unwrap_or(0);
+            diff.truncate(cut);
+            diff.push_str("\n[diff truncated]");
+        }
+        let instructions = match instructions.trim() {
+            "" => DEFAULT_INSTRUCTIONS,
+            instructions => instructions,
+        };
+        Ok(format!(
+            "{instructions}\n\n\
+             The changes are below; don't modify any files. Report each problem on \
+             its own line, as\n\n\
+             [severity] path:line - description\n\n\
+             where severity is critical, major, minor or nit. Leave out `:line` for \
+             problems that aren't tied to a line, and the location for problems \
+             that aren't tied to a file. Say so if there is nothing to report.\n\n\
+             ```diff\n{diff}\n```"
+        ))
+    }
+}
+
+impl ReviewFinding {
+    /// Finding in a line of the form `[severity] path:line - message`, where
+    /// the location is optional
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim().trim_start_matches(['-', '*', ' ']);
+        let (severity, rest) = line.strip_prefix('[')?.split_once(']')?;
+        let severity = severity.trim().parse().ok()?;
+        let rest = rest.trim_start_matches(['*', ':', ' ']).trim_end();
+
+        let separated = rest.split_once(" - ").or_else(|| rest.split_once(" — "));
+        let (path, line, message) = match separated {
+            Some((location, message)) if !location.contains(' ') => {
+                let location = location.trim_matches('`');
+                match location.rsplit_once(':') {
+                    Some((path, line)) if line.parse::<u32>().is_ok() => {
+                        (Some(path), line.parse().ok(), message)
+                    }
+                    _ => (Some(location), None, message),
+                }
+            }
+            _ => (None, None, rest),
+        };
+        let message = message.trim();
+        if message.is_empty() {
+            return None;
+        }
+        Some(Self {
+            severity,
+            path: path.filter(|p| !p.is_empty()).map(str::to_string),
+            line,
+            message: message.to_string(),
+        })
+    }
+
+    pub fn to_normalized_entry(&self) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::ReviewFinding {
+                severity: self.severity,
+                path: self.path.clone(),
+                line: self.line,
+            },
+            content: self.message.clone(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+/// Findings the reviewer reported in its messages, each once
+pub fn findings_from_history(history: &[LogMsg]) -> Vec<ReviewFinding> {
+    let mut findings = Vec::new();
+    for entry in latest_normalized_entries(history).into_values() {
+        if !matches!(entry.entry_type, NormalizedEntryType::AssistantMessage) {
+            continue;
+        }
+        for finding in entry.content.lines().filter_map(ReviewFinding::parse) {
+            if !findings.contains(&finding) {
+                findings.push(finding);
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn test_review_prompt_and_findings() {
+        let repo = std::env::temp_dir().join(format!("vk-review-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&repo).unwrap();
+        run_git(&repo, &["init", "-q", "-b", "main"]);
+        std::fs::write(repo.join("lib.rs"), "fn a() {}\n").unwrap();
+        run_git(&repo, &["add", "."]);
+        run_git(&repo, &["commit", "-q", "-m", "init"]);
+
+        let target = ReviewTarget {
+            base: "main".to_string(),
+        };
+        assert!(matches!(
+            target.prompt("", &repo).await,
+            Err(ExecutorError::NothingToReview(_))
+        ));
+
+        // Committed and uncommitted work on the attempt branch
+        run_git(&repo, &["checkout", "-q", "-b", "attempt"]);
+        std::fs::write(repo.join("lib.rs"), "fn a() { b() }\n").unwrap();
+        run_git(&repo, &["commit", "-q", "-am", "call b"]);
+        std::fs::write(repo.join("lib.rs"), "fn a() { b().unwrap() }\n").unwrap();
+        let prompt = target.prompt("Focus on panics.", &repo).await.unwrap();
+        assert!(prompt.starts_with("Focus on panics.\n\n"));
+        assert!(prompt.contains("-fn a() {}\n+fn a() { b().unwrap() }"));
+        std::fs::remove_dir_all(repo).unwrap();
+
+        assert_eq!(
+            ReviewFinding::parse("- **[Major]** `src/lib.rs:12` - unwrap on user input"),
+            Some(ReviewFinding {
+                severity: ReviewSeverity::Major,
+                path: Some("src/lib.rs".to_string()),
+                line: Some(12),
+                message: "unwrap on user input".to_string(),
+            })
+        );
+        assert_eq!(
+            ReviewFinding::parse("[nit] No changelog entry"),
+            Some(ReviewFinding {
+                severity: ReviewSeverity::Nit,
+                path: None,
+                line: None,
+                message: "No changelog entry".to_string(),
+            })
+        );
+        assert_eq!(ReviewFinding::parse("[note] just a thought"), None);
+        assert_eq!(ReviewFinding::parse("Looks good overall"), None);
+    }
+}
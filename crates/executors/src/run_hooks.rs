@@ -0,0 +1,325 @@
+//! Commands run in the worktree before and after an agent.
+//!
+//! A profile's `pre_run` hooks prepare the worktree before the agent starts,
+//! e.g. `npm ci`, and its `post_run` hooks check what a successful run left
+//! behind, e.g. `cargo test`. Each hook runs in the profile's shell with the
+//! agent's environment, and its output is streamed into the execution's
+//! [`MsgStore`] as a [`NormalizedEntryType::RunHook`] entry of its own. A
+//! failing hook either aborts, which keeps the agent from starting or fails
+//! the run, or lets the remaining hooks and the run continue.
+
+use std::{path::PathBuf, process::Stdio, time::Duration};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+};
+use tokio_stream::{StreamExt, wrappers::LinesStream};
+use ts_rs::TS;
+use workspace_utils::msg_store::MsgStore;
+
+use crate::{
+    cancellation::CancelScope,
+    command::{CmdOverrides, ScriptShell},
+    env::ExecutionEnv,
+    executors::ExecutorError,
+    logs::{
+        NormalizedEntry, NormalizedEntryType, ToolStatus,
+        utils::{ConversationPatch, EntryIndexProvider},
+    },
+    profile::RunOverrides,
+};
+
+/// Output of a hook beyond this many bytes is dropped from the front
+const MAX_HOOK_OUTPUT: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS, JsonSchema)]
+pub struct RunHook {
+    #[schemars(
+        title = "Command",
+        description = "Shell command run in the agent's working directory"
+    )]
+    pub command: String,
+    #[serde(default)]
+    #[schemars(
+        title = "On Failure",
+        description = "abort stops the run when the command fails; continue carries on"
+    )]
+    pub on_failure: HookFailureMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        title = "Timeout (seconds)",
+        description = "Fail the command if it is still running after this many seconds"
+    )]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookFailureMode {
+    // Keep the agent from starting, or fail its run
+    #[default]
+    Abort,
+    // Log the failure and carry on
+    Continue,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum HookStage {
+    PreRun,
+    PostRun,
+}
+
+/// The hooks of one stage, with the directory and environment they run in
+#[derive(Debug, Clone)]
+pub struct HookRunner {
+    stage: HookStage,
+    hooks: Vec<RunHook>,
+    shell: ScriptShell,
+    dir: PathBuf,
+    env: ExecutionEnv,
+}
+
+impl HookRunner {
+    /// Runner for the `stage` hooks of the profile's `run` overrides, if it
+    /// has any. They run in the shell and environment of its command `cmd`.
+    pub fn for_profile(
+        stage: HookStage,
+        cmd: &CmdOverrides,
+        run: &RunOverrides,
+        dir: PathBuf,
+        env: &ExecutionEnv,
+    ) -> Option<Self> {
+        let hooks = match stage {
+            HookStage::PreRun => run.pre_run.clone(),
+            HookStage::PostRun => run.post_run.clone(),
+        }
+        .filter(|hooks| !hooks.is_empty())?;
+        Some(Self {
+            stage,
+            hooks,
+            shell: cmd.shell.unwrap_or_else(ScriptShell::os_default),
+            dir,
+            env: env.clone().with_profile(cmd, run),
+        })
+    }
+
+    /// Run the hooks in order, streaming each into `msg_store`. Fails with
+    /// the first aborting hook that fails.
+    pub async fn run(
+        &self,
+        msg_store: &MsgStore,
+        cancel: &CancelScope,
+    ) -> Result<(), ExecutorError> {
+        let index_provider = EntryIndexProvider::start_from(msg_store);
+        for hook in &self.hooks {
+            cancel.check()?;
+            let index = index_provider.next();
+            let mut output = String::new();
+            msg_store.push_patch(ConversationPatch::add_normalized_entry(
+                index,
+                self.entry(hook, &output, ToolStatus::Created),
+            ));
+
+            let result = cancel
+                .run(self.run_hook(hook, |line| {
+                    output.push_str(line);
+                    output.push('\n');
+                    if output.len() > MAX_HOOK_OUTPUT {
+                        let cut = output.len() - MAX_HOOK_OUTPUT;
+                        let cut = (cut..output.len())
+                            .find(|&i| output.is_char_boundary(i))
+                            .unwrap_or(output.len());
+                        output.drain(..cut);
+                    }
+                    msg_store.push_patch(ConversationPatch::replace(
+                        index,
+                        self.entry(hook, &output, ToolStatus::Created),
+                    ));
+                }))
+                .await;
+            let failure = match result {
+                Ok(None) => None,
+                Ok(Some(failure)) => Some(failure),
+                Err(e) => {
+                    msg_store.push_patch(ConversationPatch::replace(
+                        index,
+                        self.entry(hook, &output, ToolStatus::Failed),
+                    ));
+                    return Err(e);
+                }
+            };
+
+            let status = match &failure {
+                None => ToolStatus::Success,
+                Some(failure) => {
+                    output.push_str(failure);
+                    ToolStatus::Failed
+                }
+            };
+            msg_store.push_patch(ConversationPatch::replace(
+                index,
+                self.entry(hook, &output, status),
+            ));
+            if let Some(failure) = failure {
+                tracing::warn!("{} hook `{}` {}", self.stage, hook.command, failure);
+                if hook.on_failure == HookFailureMode::Abort {
+                    return Err(ExecutorError::HookFailed {
+                        stage: self.stage,
+                        command: hook.command.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run `hook`, passing each line of its output to `on_line`. Returns
+    /// how it failed, if it did.
+    async fn run_hook(
+        &self,
+        hook: &RunHook,
+        mut on_line: impl FnMut(&str),
+    ) -> Result<Option<String>, ExecutorError> {
+        let (program, args) = self.shell.command(hook.command.clone());
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .current_dir(&self.dir)
+            .kill_on_drop(true)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        self.env.apply_to_command(&mut command)?;
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => return Ok(Some(format!("could not be started: {e}"))),
+        };
+
+        let stdout = LinesStream::new(BufReader::new(child.stdout.take().unwrap()).lines());
+        let stderr = LinesStream::new(BufReader::new(child.stderr.take().unwrap()).lines());
+        let mut lines = stdout.merge(stderr);
+        let timeout = hook
+            .timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::MAX);
+        let finished = tokio::time::timeout(timeout, async {
+            while let Some(Ok(line)) = lines.next().await {
+                on_line(&line);
+            }
+            child.wait().await
+        })
+        .await;
+        Ok(match finished {
+            Ok(Ok(status)) if status.success() => None,
+            Ok(Ok(status)) => Some(match status.code() {
+                Some(code) => format!("exited with code {code}"),
+                None => "was killed".to_string(),
+            }),
+            Ok(Err(e)) => Some(format!("failed: {e}")),
+            Err(_) => Some(format!("timed out after {}s", timeout.as_secs())),
+        })
+    }
+
+    fn entry(&self, hook: &RunHook, output: &str, status: ToolStatus) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::RunHook {
+                stage: self.stage,
+                command: hook.command.clone(),
+                status,
+            },
+            content: output.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use workspace_utils::log_msg::LogMsg;
+
+    use super::*;
+    use crate::logs::utils::patch::latest_normalized_entries;
+
+    fn hook(command: &str, on_failure: HookFailureMode) -> RunHook {
+        RunHook {
+            command: command.to_string(),
+            on_failure,
+            timeout_secs: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_stream_output_and_handle_failures() {
+        let cmd = CmdOverrides {
+            env: Some([("HOOK_VAR".to_string(), "from profile".to_string())].into()),
+            shell: Some(ScriptShell::Bash),
+            ..Default::default()
+        };
+        let run = RunOverrides {
+            pre_run: Some(vec![
+                hook(
+                    "echo installing; echo $HOOK_VAR >&2",
+                    HookFailureMode::Abort,
+                ),
+                hook("exit 3", HookFailureMode::Continue),
+                hook("false", HookFailureMode::Abort),
+                hook("echo never", HookFailureMode::Abort),
+            ]),
+            ..Default::default()
+        };
+        assert!(
+            HookRunner::for_profile(
+                HookStage::PostRun,
+                &cmd,
+                &run,
+                std::env::temp_dir(),
+                &ExecutionEnv::new()
+            )
+            .is_none()
+        );
+        let runner = HookRunner::for_profile(
+            HookStage::PreRun,
+            &cmd,
+            &run,
+            std::env::temp_dir(),
+            &ExecutionEnv::new(),
+        )
+        .unwrap();
+
+        let msg_store = MsgStore::new();
+        let result = runner.run(&msg_store, &CancelScope::new()).await;
+        assert!(matches!(
+            result,
+            Err(ExecutorError::HookFailed { stage: HookStage::PreRun, ref command })
+                if command == "false"
+        ));
+
+        let history: Vec<LogMsg> = msg_store.get_history();
+        let entries: Vec<_> = latest_normalized_entries(&history).into_values().collect();
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].content.contains("installing\n"));
+        assert!(entries[0].content.contains("from profile\n"));
+        assert!(matches!(
+            entries[0].entry_type,
+            NormalizedEntryType::RunHook {
+                status: ToolStatus::Success,
+                ..
+            }
+        ));
+        assert!(entries[1].content.ends_with("exited with code 3"));
+        assert!(matches!(
+            &entries[2].entry_type,
+            NormalizedEntryType::RunHook { command, status: ToolStatus::Failed, .. }
+                if command == "false"
+        ));
+    }
+}
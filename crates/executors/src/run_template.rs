@@ -0,0 +1,270 @@
+//! Named run configurations.
+//!
+//! A [`RunTemplate`] bundles an executor profile with a permission level, a
+//! prompt template, extra environment variables and scripts to run once the
+//! agent finishes, so a recurring workflow ("quick fix", "security review")
+//! can be launched in one step. Templates are stored alongside the executor
+//! profiles in `profiles.json`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    actions::{
+        ExecutorAction, ExecutorActionType,
+        script::{ScriptContext, ScriptRequest, ScriptRequestLanguage},
+    },
+    executors::{
+        CodingAgent,
+        claude::types::PermissionMode,
+        codex::{AskForApproval, SandboxMode},
+        droid::Autonomy,
+    },
+    profile::ExecutorProfileId,
+};
+
+/// Placeholder in a prompt template that is replaced by the task prompt
+pub const TASK_PLACEHOLDER: &str = "{{task}}";
+
+// How much the agent may do without asking, from least to most permissive
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, TS,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PermissionLevel {
+    // Keep whatever the profile configures
+    #[default]
+    Profile,
+    // Plan or read only, where the agent supports it
+    ReadOnly,
+    // Ask before edits and commands
+    Supervised,
+    // Run without asking
+    Autonomous,
+}
+
+impl PermissionLevel {
+    /// The level an agent's own settings amount to. Never returns
+    /// [`PermissionLevel::Profile`].
+    pub fn of(agent: &CodingAgent) -> Self {
+        use PermissionLevel::*;
+
+        let autonomous_if = |flag: Option<bool>| {
+            if flag.unwrap_or(false) {
+                Autonomous
+            } else {
+                Supervised
+            }
+        };
+        match agent {
+            CodingAgent::ClaudeCode(claude) => match claude.permission_mode() {
+                PermissionMode::Plan => ReadOnly,
+                PermissionMode::BypassPermissions => Autonomous,
+                _ => Supervised,
+            },
+            CodingAgent::Codex(codex) => match (&codex.sandbox, &codex.ask_for_approval) {
+                (Some(SandboxMode::ReadOnly), _) => ReadOnly,
+                (Some(SandboxMode::DangerFullAccess), _) | (_, Some(AskForApproval::Never)) => {
+                    Autonomous
+                }
+                _ => Supervised,
+            },
+            CodingAgent::Droid(droid) => match droid.autonomy {
+                Autonomy::Normal => ReadOnly,
+                Autonomy::Low | Autonomy::Medium => Supervised,
+                Autonomy::High | Autonomy::SkipPermissionsUnsafe => Autonomous,
+            },
+            CodingAgent::Amp(amp) => autonomous_if(amp.dangerously_allow_all),
+            CodingAgent::Gemini(gemini) => autonomous_if(gemini.yolo),
+            CodingAgent::QwenCode(qwen) => autonomous_if(qwen.yolo),
+            CodingAgent::Opencode(opencode) => autonomous_if(Some(opencode.auto_approve)),
+            CodingAgent::CursorAgent(cursor) => autonomous_if(cursor.force),
+            CodingAgent::Copilot(copilot) => autonomous_if(copilot.allow_all_tools),
+            CodingAgent::ClaudeApi(claude_api) if claude_api.read_only.unwrap_or(false) => ReadOnly,
+            CodingAgent::ClaudeApi(claude_api) => autonomous_if(Some(claude_api.auto_approve)),
+            // Claude Flow swarms run without approvals
+            CodingAgent::ClaudeFlow(_) => Autonomous,
+        }
+    }
+
+    /// Adjust the agent's own permission settings to this level. Agents
+    /// without a matching setting are left unchanged.
+    pub fn apply(self, agent: &mut CodingAgent) {
+        use PermissionLevel::*;
+
+        match (self, agent) {
+            (Profile, _) => {}
+            (level, CodingAgent::ClaudeCode(claude)) => {
+                claude.plan = Some(level == ReadOnly);
+                claude.approvals = Some(level == Supervised);
+                claude.dangerously_skip_permissions = Some(level == Autonomous);
+            }
+            (level, CodingAgent::Codex(codex)) => {
+                codex.sandbox = Some(match level {
+                    ReadOnly => SandboxMode::ReadOnly,
+                    Autonomous => SandboxMode::DangerFullAccess,
+                    _ => SandboxMode::WorkspaceWrite,
+                });
+                if level != ReadOnly {
+                    codex.ask_for_approval = Some(match level {
+                        Autonomous => AskForApproval::Never,
+                        _ => AskForApproval::OnRequest,
+                    });
+                }
+            }
+            (level, CodingAgent::Droid(droid)) => {
+                droid.autonomy = match level {
+                    ReadOnly => Autonomy::Normal,
+                    Autonomous => Autonomy::SkipPermissionsUnsafe,
+                    _ => Autonomy::Low,
+                };
+            }
+            (level, CodingAgent::ClaudeApi(claude_api)) => {
+                claude_api.read_only = Some(level == ReadOnly);
+                claude_api.auto_approve = level == Autonomous;
+            }
+            (ReadOnly, _) => {}
+            (level, CodingAgent::Amp(amp)) => {
+                amp.dangerously_allow_all = Some(level == Autonomous);
+            }
+            (level, CodingAgent::Gemini(gemini)) => gemini.yolo = Some(level == Autonomous),
+            (level, CodingAgent::QwenCode(qwen)) => qwen.yolo = Some(level == Autonomous),
+            (level, CodingAgent::Opencode(opencode)) => {
+                opencode.auto_approve = level == Autonomous;
+            }
+            (level, CodingAgent::CursorAgent(cursor)) => cursor.force = Some(level == Autonomous),
+            (level, CodingAgent::Copilot(copilot)) => {
+                copilot.allow_all_tools = Some(level == Autonomous);
+            }
+            (_, CodingAgent::ClaudeFlow(_)) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+pub struct RunTemplate {
+    pub executor_profile_id: ExecutorProfileId,
+    #[serde(default)]
+    pub permission_level: PermissionLevel,
+    /// Prompt sent to the agent; `{{task}}` is replaced by the task prompt,
+    /// which is appended when the placeholder is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_template: Option<String>,
+    /// Environment variables added to the profile's
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Scripts run in order after the agent finishes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_steps: Vec<String>,
+}
+
+impl RunTemplate {
+    pub fn render_prompt(&self, task_prompt: &str) -> String {
+        match &self.prompt_template {
+            Some(template) if template.contains(TASK_PLACEHOLDER) => {
+                template.replace(TASK_PLACEHOLDER, task_prompt)
+            }
+            Some(template) => format!("{template}\n\n{task_prompt}"),
+            None => task_prompt.to_string(),
+        }
+    }
+
+    /// Apply the template's permission level and environment to the agent
+    /// resolved from its profile
+    pub fn apply(&self, agent: &mut CodingAgent) {
+        self.permission_level.apply(agent);
+        if !self.env.is_empty() {
+            agent
+                .cmd_overrides_mut()
+                .env
+                .get_or_insert_default()
+                .extend(self.env.clone());
+        }
+    }
+
+    /// Chain the post-steps into a sequence of script actions, followed by
+    /// `then`
+    pub fn post_step_actions(
+        &self,
+        working_dir: Option<String>,
+        then: Option<ExecutorAction>,
+    ) -> Option<ExecutorAction> {
+        self.post_steps.iter().rev().fold(then, |next, script| {
+            Some(ExecutorAction::new(
+                ExecutorActionType::ScriptRequest(ScriptRequest {
+                    script: script.clone(),
+                    language: ScriptRequestLanguage::Bash,
+                    context: ScriptContext::CleanupScript,
+                    working_dir: working_dir.clone(),
+                    timeout_secs: None,
+                }),
+                next.map(Box::new),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executors::BaseCodingAgent;
+
+    fn template() -> RunTemplate {
+        serde_json::from_value(serde_json::json!({
+            "executor_profile_id": { "executor": "CLAUDE_CODE" },
+            "permission_level": "READ_ONLY",
+            "prompt_template": "Review for security issues:\n{{task}}",
+            "env": { "REVIEW": "1" },
+            "post_steps": ["cargo fmt", "cargo test"],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_and_apply() {
+        let template = template();
+        assert_eq!(
+            template.render_prompt("Add login"),
+            "Review for security issues:\nAdd login"
+        );
+
+        let mut agent = crate::profile::ExecutorConfigs::from_defaults()
+            .get_coding_agent(&ExecutorProfileId::new(BaseCodingAgent::ClaudeCode))
+            .unwrap();
+        template.apply(&mut agent);
+        let CodingAgent::ClaudeCode(claude) = &agent else {
+            panic!("expected Claude Code");
+        };
+        assert_eq!(claude.plan, Some(true));
+        assert_eq!(claude.dangerously_skip_permissions, Some(false));
+        assert_eq!(
+            agent.cmd_overrides().env.as_ref().unwrap()["REVIEW"],
+            "1".to_string()
+        );
+    }
+
+    #[test]
+    fn test_post_steps_chain_before_cleanup() {
+        let cleanup = ExecutorAction::new(
+            ExecutorActionType::ScriptRequest(ScriptRequest {
+                script: "cleanup".to_string(),
+                language: ScriptRequestLanguage::Bash,
+                context: ScriptContext::CleanupScript,
+                working_dir: None,
+                timeout_secs: None,
+            }),
+            None,
+        );
+        let mut action = template().post_step_actions(None, Some(cleanup));
+        let mut scripts = Vec::new();
+        while let Some(current) = action {
+            if let ExecutorActionType::ScriptRequest(script) = current.typ() {
+                scripts.push(script.script.clone());
+            }
+            action = current.next_action().cloned();
+        }
+        assert_eq!(scripts, ["cargo fmt", "cargo test", "cleanup"]);
+    }
+}
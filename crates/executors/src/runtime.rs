@@ -0,0 +1,218 @@
+//! Lifecycle of the executors runtime as a whole.
+//!
+//! [`ExecutorsRuntime`] owns the cancel scopes of running executions, the
+//! limits on concurrent agent processes, the cool-downs of rate limited
+//! providers and the background writes that must land before the host exits. On shutdown it
+//! stops accepting new executions, cancels the running ones so the host
+//! interrupts them, waits for pending writes and registered queues to flush,
+//! and reports whatever did not finish before the deadline.
+
+use std::{
+    future::Future,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::{task::JoinHandle, time::Instant};
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+
+use crate::{
+    cancellation::{CancelReason, CancelScope, CancelTree},
+    executors::ExecutorError,
+    governor::SpawnGovernor,
+    rate_limit::RateLimiter,
+    standby::StandbyPool,
+};
+
+/// How often shutdown checks whether running executions have finished
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A queue of work (notifications, audit records, ...) that is flushed on
+/// shutdown
+#[async_trait]
+pub trait FlushQueue: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn flush(&self) -> Result<(), String>;
+}
+
+/// What was left undone when shutdown reached its deadline
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Executions still running at the deadline
+    pub unfinished_executions: Vec<Uuid>,
+    /// Background writes still pending at the deadline
+    pub pending_writes: usize,
+    /// Queues that failed or timed out, with the reason
+    pub failed_queues: Vec<(String, String)>,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.unfinished_executions.is_empty()
+            && self.pending_writes == 0
+            && self.failed_queues.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct ExecutorsRuntime {
+    cancel_tree: CancelTree,
+    spawn_governor: SpawnGovernor,
+    rate_limiter: RateLimiter,
+    shutting_down: AtomicBool,
+    writes: TaskTracker,
+    queues: Mutex<Vec<Arc<dyn FlushQueue>>>,
+}
+
+impl ExecutorsRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel_tree(&self) -> &CancelTree {
+        &self.cancel_tree
+    }
+
+    pub fn spawn_governor(&self) -> &SpawnGovernor {
+        &self.spawn_governor
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn is_accepting(&self) -> bool {
+        !self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Fail with [`CancelReason::Shutdown`] once shutdown has begun
+    pub fn check_accepting(&self) -> Result<(), ExecutorError> {
+        if self.is_accepting() {
+            Ok(())
+        } else {
+            Err(ExecutorError::Cancelled(CancelReason::Shutdown))
+        }
+    }
+
+    /// Register an execution's cancel scope under `root_id`
+    pub fn start_execution(
+        &self,
+        root_id: Uuid,
+        execution_id: Uuid,
+    ) -> Result<CancelScope, ExecutorError> {
+        self.check_accepting()?;
+        Ok(self.cancel_tree.start_execution(root_id, execution_id))
+    }
+
+    /// Spawn a background write that shutdown waits for
+    pub fn spawn_write<F>(&self, write: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.writes.spawn(write)
+    }
+
+    pub fn register_queue(&self, queue: Arc<dyn FlushQueue>) {
+        self.queues.lock().unwrap().push(queue);
+    }
+
+    /// Stop accepting executions, cancel the running ones and flush pending
+    /// work, giving up after `deadline`
+    pub async fn shutdown(&self, deadline: Duration) -> ShutdownReport {
+        let deadline = Instant::now() + deadline;
+        self.shutting_down.store(true, Ordering::SeqCst);
+        StandbyPool::global().clear();
+        self.cancel_tree.cancel_all(CancelReason::Shutdown);
+
+        let mut report = ShutdownReport::default();
+        loop {
+            let running = self.cancel_tree.running();
+            if running.is_empty() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                report.unfinished_executions = running;
+                break;
+            }
+            tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+        }
+
+        self.writes.close();
+        if tokio::time::timeout_at(deadline, self.writes.wait())
+            .await
+            .is_err()
+        {
+            report.pending_writes = self.writes.len();
+        }
+
+        let queues = self.queues.lock().unwrap().clone();
+        for queue in queues {
+            let failure = match tokio::time::timeout_at(deadline, queue.flush()).await {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => e,
+                Err(_) => "timed out".to_string(),
+            };
+            report
+                .failed_queues
+                .push((queue.name().to_string(), failure));
+        }
+
+        if !report.is_clean() {
+            tracing::warn!("Executors runtime shut down with unfinished work: {report:?}");
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StuckQueue;
+
+    #[async_trait]
+    impl FlushQueue for StuckQueue {
+        fn name(&self) -> &str {
+            "stuck"
+        }
+
+        async fn flush(&self) -> Result<(), String> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_reports_unfinished_work() {
+        let runtime = ExecutorsRuntime::new();
+        let (workspace, finishing, stuck) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        // A host that finishes its execution once it is cancelled
+        let scope = runtime.start_execution(workspace, finishing).unwrap();
+        let tree = runtime.cancel_tree().clone();
+        tokio::spawn(async move {
+            scope.cancelled().await;
+            tree.finish_execution(&finishing);
+        });
+        runtime.start_execution(workspace, stuck).unwrap();
+        runtime.spawn_write(async {});
+        runtime.register_queue(Arc::new(StuckQueue));
+
+        let report = runtime.shutdown(Duration::from_millis(200)).await;
+        assert_eq!(report.unfinished_executions, vec![stuck]);
+        assert_eq!(report.pending_writes, 0);
+        assert_eq!(
+            report.failed_queues,
+            vec![("stuck".to_string(), "timed out".to_string())]
+        );
+        assert!(matches!(
+            runtime.start_execution(workspace, Uuid::new_v4()),
+            Err(ExecutorError::Cancelled(CancelReason::Shutdown))
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+//! Wall-clock and idle timeouts for spawned executors.
+//!
+//! Limits are configured per profile through [`RuntimeOverrides`] and enforced by a
+//! watchdog that sits between the executor and the container: it forwards the
+//! executor's own exit signal untouched, and reports
+//! [`ExecutorExitResult::TimedOut`] if a limit expires first. The container then
+//! kills the process group just like any other executor-initiated exit.
+
+use std::{fmt, time::Duration};
+
+use futures::{FutureExt, StreamExt, future::BoxFuture};
+use tokio::{sync::oneshot, time::Instant};
+
+use crate::{
+    executors::{ExecutorError, ExecutorExitResult, SpawnedChild},
+    logs::{NormalizedEntry, NormalizedEntryError, NormalizedEntryType},
+    profile::RuntimeOverrides,
+    stdout_dup::duplicate_stdout,
+};
+
+/// Which limit caused an execution to be stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// The process ran longer than the total allowed runtime
+    Total(Duration),
+    /// The process produced no stdout output for the allowed idle period
+    Idle(Duration),
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutKind::Total(limit) => {
+                write!(f, "Execution timed out after {}s", limit.as_secs())
+            }
+            TimeoutKind::Idle(limit) => write!(
+                f,
+                "Execution timed out after {}s without output",
+                limit.as_secs()
+            ),
+        }
+    }
+}
+
+impl TimeoutKind {
+    /// Normalized error entry describing the timeout, for display in the conversation
+    pub fn to_normalized_entry(&self) -> NormalizedEntry {
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::ErrorMessage {
+                error_type: NormalizedEntryError::Other,
+            },
+            content: self.to_string(),
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuntimeLimits {
+    pub timeout: Option<Duration>,
+    pub idle_timeout: Option<Duration>,
+}
+
+impl From<&RuntimeOverrides> for RuntimeLimits {
+    fn from(cmd: &RuntimeOverrides) -> Self {
+        Self {
+            timeout: cmd.timeout_secs.map(Duration::from_secs),
+            idle_timeout: cmd.idle_timeout_secs.map(Duration::from_secs),
+        }
+    }
+}
+
+impl RuntimeLimits {
+    pub fn is_unbounded(&self) -> bool {
+        self.timeout.is_none() && self.idle_timeout.is_none()
+    }
+
+    /// Earliest instant at which one of the limits expires, and which one it is.
+    /// `last_output` is `None` once stdout has closed, which disables the idle limit.
+    fn next_deadline(
+        &self,
+        started: Instant,
+        last_output: Option<Instant>,
+    ) -> Option<(Instant, TimeoutKind)> {
+        let total = self
+            .timeout
+            .map(|limit| (started + limit, TimeoutKind::Total(limit)));
+        let idle = self
+            .idle_timeout
+            .zip(last_output)
+            .map(|(limit, last)| (last + limit, TimeoutKind::Idle(limit)));
+
+        match (total, idle) {
+            (Some(total), Some(idle)) => Some(if idle.0 < total.0 { idle } else { total }),
+            (total, idle) => total.or(idle),
+        }
+    }
+
+    /// Start enforcing the limits on a freshly spawned child.
+    ///
+    /// The child's exit signal is replaced by one that resolves either with the
+    /// executor's own result or with [`ExecutorExitResult::TimedOut`], and
+    /// otherwise stays pending until the container drops it. Output activity
+    /// is observed on stdout, which is duplicated rather than consumed.
+    pub fn enforce(&self, spawned: &mut SpawnedChild) -> Result<(), ExecutorError> {
+        if self.is_unbounded() {
+            return Ok(());
+        }
+
+        let mut activity = duplicate_stdout(&mut spawned.child)?;
+        let executor_exit = spawned.exit_signal.take();
+        let (mut exit_tx, exit_rx) = oneshot::channel();
+        spawned.exit_signal = Some(exit_rx);
+
+        let limits = *self;
+        tokio::spawn(async move {
+            let mut executor_exit: BoxFuture<'static, _> = match executor_exit {
+                Some(rx) => rx.boxed(),
+                None => std::future::pending().boxed(),
+            };
+
+            let started = Instant::now();
+            let mut last_output = Some(started);
+
+            loop {
+                let Some((deadline, kind)) = limits.next_deadline(started, last_output) else {
+                    // No limit left to enforce; keep relaying the executor's own signal
+                    tokio::select! {
+                        _ = exit_tx.closed() => {}
+                        result = executor_exit => {
+                            if let Ok(result) = result {
+                                let _ = exit_tx.send(result);
+                            }
+                        }
+                    }
+                    return;
+                };
+
+                tokio::select! {
+                    // Container stopped listening (process exited or was stopped)
+                    _ = exit_tx.closed() => return,
+                    result = &mut executor_exit => {
+                        if let Ok(result) = result {
+                            let _ = exit_tx.send(result);
+                        }
+                        return;
+                    }
+                    // A closed stdout only ends the idle limit: the process
+                    // may keep running, and its exit is the container's to
+                    // report, so the signal stays open until it stops listening
+                    chunk = activity.next(), if last_output.is_some() => {
+                        last_output = chunk.map(|_| Instant::now());
+                    }
+                    _ = tokio::time::sleep_until(deadline) => {
+                        tracing::warn!("{kind}; stopping executor");
+                        let _ = exit_tx.send(ExecutorExitResult::TimedOut(kind));
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_group::AsyncCommandGroup;
+    use tokio::process::Command;
+
+    use super::*;
+
+    fn limits(timeout: Option<u64>, idle_timeout: Option<u64>) -> RuntimeLimits {
+        RuntimeLimits::from(&RuntimeOverrides {
+            timeout_secs: timeout,
+            idle_timeout_secs: idle_timeout,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_next_deadline_picks_earliest_limit() {
+        let start = Instant::now();
+
+        assert_eq!(limits(None, None).next_deadline(start, Some(start)), None);
+        assert_eq!(
+            limits(Some(10), Some(3)).next_deadline(start, Some(start)),
+            Some((
+                start + Duration::from_secs(3),
+                TimeoutKind::Idle(Duration::from_secs(3))
+            ))
+        );
+
+        let recent_output = start + Duration::from_secs(8);
+        assert_eq!(
+            limits(Some(10), Some(3)).next_deadline(start, Some(recent_output)),
+            Some((
+                start + Duration::from_secs(10),
+                TimeoutKind::Total(Duration::from_secs(10))
+            ))
+        );
+
+        // Idle limit no longer applies once stdout has closed
+        assert_eq!(limits(None, Some(3)).next_deadline(start, None), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_idle_timeout_fires_when_child_is_silent() {
+        let child = Command::new("sh")
+            .args(["-c", "echo started; sleep 30"])
+            .stdout(std::process::Stdio::piped())
+            .group_spawn()
+            .unwrap();
+        let mut spawned = SpawnedChild::from(child);
+
+        let limits = RuntimeLimits {
+            timeout: None,
+            idle_timeout: Some(Duration::from_millis(200)),
+        };
+        limits.enforce(&mut spawned).unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), spawned.exit_signal.unwrap())
+            .await
+            .expect("watchdog should fire")
+            .unwrap();
+        assert!(matches!(
+            result,
+            ExecutorExitResult::TimedOut(TimeoutKind::Idle(_))
+        ));
+
+        spawned.child.kill().await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_closed_stdout_leaves_exit_to_the_process() {
+        let child = Command::new("sh")
+            .args(["-c", "echo started; exec >&-; sleep 0.3; exit 3"])
+            .stdout(std::process::Stdio::piped())
+            .group_spawn()
+            .unwrap();
+        let mut spawned = SpawnedChild::from(child);
+
+        let limits = RuntimeLimits {
+            timeout: None,
+            idle_timeout: Some(Duration::from_millis(100)),
+        };
+        limits.enforce(&mut spawned).unwrap();
+        let mut exit_signal = spawned.exit_signal.take().unwrap();
+
+        let status = tokio::select! {
+            result = &mut exit_signal => panic!("exit signal resolved with {result:?}"),
+            status = spawned.child.wait() => status.unwrap(),
+        };
+        assert_eq!(status.code(), Some(3));
+        // Still open, so the container goes by the exit status
+        assert!(matches!(
+            exit_signal.try_recv(),
+            Err(oneshot::error::TryRecvError::Empty)
+        ));
+    }
+}
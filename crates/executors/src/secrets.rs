@@ -0,0 +1,157 @@
+//! Secrets agents run with, from a managed secret store.
+//!
+//! CI runners and server deployments usually have no agent logged in and no
+//! credentials in the home directory. Their profiles name a secret instead,
+//! such as the API key to run with, which is looked up in the registered
+//! [`SecretStore`] when the agent spawns and handed to it through
+//! [`ExecutionEnv::insert_secret`], so it never shows in debug output or
+//! command explanations.
+//!
+//! Without a registered store, a secret is read from the file of that name
+//! in `VK_SECRETS_DIR`, as Docker and Kubernetes mount them, or else from the
+//! `VK_SECRET_<NAME>` variable, e.g. `VK_SECRET_ANTHROPIC_API_KEY` for
+//! `anthropic-api-key`.
+//!
+//! Executions of a [tenant](crate::tenant) only see the tenant's own secrets:
+//! the files in its `secrets` directory, never the server's.
+//!
+//! [`ExecutionEnv::insert_secret`]: crate::env::ExecutionEnv::insert_secret
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use thiserror::Error;
+
+use crate::tenant::TenantId;
+
+pub const SECRETS_DIR_ENV: &str = "VK_SECRETS_DIR";
+const SECRET_VAR_PREFIX: &str = "VK_SECRET_";
+
+static SECRET_STORE: LazyLock<RwLock<Arc<dyn SecretStore>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(EnvSecretStore::from_env())));
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("Invalid secret name '{0}'")]
+    InvalidName(String),
+    #[error("Secret '{0}' not found in the secret store")]
+    NotFound(String),
+}
+
+/// Source of the secrets profiles refer to by name
+pub trait SecretStore: Send + Sync {
+    /// The secret `name` of `tenant`, or of the server itself for `None`
+    fn secret(&self, tenant: Option<&TenantId>, name: &str) -> Option<String>;
+}
+
+/// Secrets from files in a directory, falling back to `VK_SECRET_<NAME>`
+/// variables
+#[derive(Debug, Clone, Default)]
+pub struct EnvSecretStore {
+    dir: Option<PathBuf>,
+}
+
+impl EnvSecretStore {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+
+    /// Store reading the directory in `VK_SECRETS_DIR`, if set
+    pub fn from_env() -> Self {
+        Self::new(std::env::var_os(SECRETS_DIR_ENV).map(PathBuf::from))
+    }
+}
+
+impl SecretStore for EnvSecretStore {
+    fn secret(&self, tenant: Option<&TenantId>, name: &str) -> Option<String> {
+        let secret = match tenant {
+            Some(tenant) => std::fs::read_to_string(tenant.secrets_dir().join(name)).ok(),
+            None => self
+                .dir
+                .as_ref()
+                .and_then(|dir| std::fs::read_to_string(dir.join(name)).ok())
+                .or_else(|| std::env::var(secret_var(name)).ok()),
+        };
+        secret
+            .map(|secret| secret.trim_end_matches(['\r', '\n']).to_string())
+            .filter(|secret| !secret.is_empty())
+    }
+}
+
+/// Variable the secret `name` is read from without a secrets directory
+fn secret_var(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '-' | '.' => '_',
+            c => c.to_ascii_uppercase(),
+        })
+        .collect();
+    format!("{SECRET_VAR_PREFIX}{name}")
+}
+
+/// Replace the store secrets are looked up in; `None` goes back to files and
+/// variables
+pub fn set_secret_store(store: Option<Arc<dyn SecretStore>>) {
+    *SECRET_STORE.write().unwrap() = store.unwrap_or_else(|| Arc::new(EnvSecretStore::from_env()));
+}
+
+/// Look the secret `name` of `tenant`, or of the server for `None`, up in the
+/// configured store
+pub fn resolve(name: &str, tenant: Option<&TenantId>) -> Result<String, SecretError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(SecretError::InvalidName(name.to_string()));
+    }
+    let store = SECRET_STORE.read().unwrap().clone();
+    store
+        .secret(tenant, name)
+        .ok_or_else(|| SecretError::NotFound(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::ExecutionEnv;
+
+    #[test]
+    fn test_secrets_resolve_from_files_then_variables() {
+        let dir = std::env::temp_dir().join(format!("vk-secrets-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("anthropic-api-key"), "sk-ant-file\n").unwrap();
+        let store = EnvSecretStore::new(Some(dir.clone()));
+        assert_eq!(
+            store.secret(None, "anthropic-api-key").as_deref(),
+            Some("sk-ant-file")
+        );
+        assert_eq!(
+            secret_var("ci.anthropic-api-key"),
+            "VK_SECRET_CI_ANTHROPIC_API_KEY"
+        );
+        assert_eq!(store.secret(None, "missing-in-tests"), None);
+        // Tenants never fall back to the server's secrets
+        let tenant = TenantId::new("acme").unwrap();
+        assert_eq!(store.secret(Some(&tenant), "anthropic-api-key"), None);
+
+        assert!(matches!(
+            resolve("../etc/passwd", None),
+            Err(SecretError::InvalidName(_))
+        ));
+        assert!(matches!(
+            resolve("missing-in-tests", None),
+            Err(SecretError::NotFound(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let mut env = ExecutionEnv::new();
+        env.insert_secret("ANTHROPIC_API_KEY", "sk-ant-file");
+        assert!(!format!("{env:?}").contains("sk-ant-file"));
+        assert_eq!(env.vars["ANTHROPIC_API_KEY"], "sk-ant-file");
+    }
+}
@@ -0,0 +1,267 @@
+//! Manifest of the agent sessions started in a worktree.
+//!
+//! Every coding agent run appends a [`SessionRecord`] to
+//! `.vibe-sessions/sessions.jsonl` in its working directory once the agent
+//! reports its session id. Follow-ups check the manifest before passing a
+//! session id back to the agent, so a stale or foreign id fails early with
+//! [`ExecutorError::SessionNotFound`] instead of inside the agent. Worktrees
+//! without a manifest predate it and are not checked.
+
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use workspace_utils::{log_msg::LogMsg, msg_store::MsgStore};
+
+use crate::{
+    encryption,
+    executors::{BaseCodingAgent, ExecutorError},
+};
+
+/// Directory in the worktree holding the manifest, ignored by git
+pub const SESSIONS_DIR: &str = ".vibe-sessions";
+const MANIFEST_FILE: &str = "sessions.jsonl";
+/// Longest prompt excerpt kept per record
+const MAX_PROMPT_CHARS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub executor: BaseCodingAgent,
+    pub session_id: String,
+    pub worktree: PathBuf,
+    pub started_at: DateTime<Utc>,
+    /// Start of the prompt that last ran in the session
+    pub last_prompt: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionManifest {
+    path: PathBuf,
+}
+
+impl SessionManifest {
+    pub fn for_worktree(worktree: &Path) -> Self {
+        Self {
+            path: worktree.join(SESSIONS_DIR).join(MANIFEST_FILE),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub fn append(&self, record: &SessionRecord) -> io::Result<()> {
+        let dir = self.path.parent().expect("manifest path has a parent");
+        std::fs::create_dir_all(dir)?;
+        let gitignore_path = dir.join(".gitignore");
+        if !gitignore_path.exists() {
+            std::fs::write(&gitignore_path, "*\n")?;
+        }
+
+        let line = serde_json::to_string(record)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", encryption::seal_line(&line)?)
+    }
+
+    /// Every record in the order they were written. Unreadable lines are
+    /// skipped.
+    pub fn records(&self) -> io::Result<Vec<SessionRecord>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match encryption::open_line(&line)
+                .map_err(io::Error::from)
+                .and_then(|line| serde_json::from_str(&line).map_err(io::Error::from))
+            {
+                Ok(record) => records.push(record),
+                Err(e) => tracing::warn!("Skipping session record in {}: {e}", self.path.display()),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Sessions that can be resumed, optionally only those of `executor`,
+    /// most recently used first. A session resumed several times is listed
+    /// once, with its first start time and latest prompt.
+    pub fn resumable(&self, executor: Option<BaseCodingAgent>) -> io::Result<Vec<SessionRecord>> {
+        // Keyed by executor and session id, with the position of the latest use
+        let mut sessions: HashMap<(BaseCodingAgent, String), (usize, SessionRecord)> =
+            HashMap::new();
+        for (position, record) in self.records()?.into_iter().enumerate() {
+            if executor.is_some_and(|executor| executor != record.executor) {
+                continue;
+            }
+            let key = (record.executor, record.session_id.clone());
+            match sessions.get_mut(&key) {
+                Some((last_used, existing)) => {
+                    *last_used = position;
+                    existing.last_prompt = record.last_prompt;
+                }
+                None => {
+                    sessions.insert(key, (position, record));
+                }
+            }
+        }
+        let mut sessions: Vec<_> = sessions.into_values().collect();
+        sessions.sort_by_key(|(last_used, _)| std::cmp::Reverse(*last_used));
+        Ok(sessions.into_iter().map(|(_, record)| record).collect())
+    }
+
+    pub fn find(
+        &self,
+        executor: BaseCodingAgent,
+        session_id: &str,
+    ) -> io::Result<Option<SessionRecord>> {
+        Ok(self
+            .resumable(Some(executor))?
+            .into_iter()
+            .find(|record| record.session_id == session_id))
+    }
+
+    /// Check that `session_id` was started by `executor` in this worktree.
+    /// Passes when there is no manifest to check against.
+    pub fn validate(
+        &self,
+        executor: BaseCodingAgent,
+        session_id: &str,
+    ) -> Result<(), ExecutorError> {
+        if !self.exists() {
+            return Ok(());
+        }
+        match self.find(executor, session_id) {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(ExecutorError::SessionNotFound(session_id.to_string())),
+            Err(e) => {
+                tracing::warn!("Failed to read {}: {e}", self.path.display());
+                Ok(())
+            }
+        }
+    }
+
+    /// Append a record for the session `msg_store` reports, once it does
+    pub fn spawn_recorder(
+        self,
+        msg_store: Arc<MsgStore>,
+        executor: BaseCodingAgent,
+        worktree: PathBuf,
+        prompt: &str,
+    ) -> JoinHandle<()> {
+        let last_prompt: String = prompt.chars().take(MAX_PROMPT_CHARS).collect();
+        let started_at = Utc::now();
+        tokio::spawn(async move {
+            let mut stream = msg_store.history_plus_stream();
+            while let Some(Ok(msg)) = stream.next().await {
+                let session_id = match msg {
+                    LogMsg::SessionId(session_id) => session_id,
+                    LogMsg::Finished => return,
+                    _ => continue,
+                };
+                let record = SessionRecord {
+                    executor,
+                    session_id,
+                    worktree,
+                    started_at,
+                    last_prompt,
+                };
+                if let Err(e) = self.append(&record) {
+                    tracing::warn!("Failed to record session in {}: {e}", self.path.display());
+                }
+                return;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(executor: BaseCodingAgent, session_id: &str, prompt: &str) -> SessionRecord {
+        SessionRecord {
+            executor,
+            session_id: session_id.to_string(),
+            worktree: PathBuf::from("/tmp/worktree"),
+            started_at: Utc::now(),
+            last_prompt: prompt.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_and_validates_sessions() {
+        let dir = std::env::temp_dir().join(format!("vk-sessions-{}", uuid::Uuid::new_v4()));
+        let manifest = SessionManifest::for_worktree(&dir);
+        assert!(
+            manifest
+                .validate(BaseCodingAgent::ClaudeCode, "anything")
+                .is_ok()
+        );
+
+        let msg_store = Arc::new(MsgStore::new());
+        let recorder = manifest.clone().spawn_recorder(
+            msg_store.clone(),
+            BaseCodingAgent::ClaudeCode,
+            dir.clone(),
+            "Fix the login bug",
+        );
+        msg_store.push_session_id("abc".to_string());
+        msg_store.push_finished();
+        recorder.await.unwrap();
+
+        manifest
+            .append(&record(BaseCodingAgent::Amp, "t-1", "Write docs"))
+            .unwrap();
+        manifest
+            .append(&record(BaseCodingAgent::ClaudeCode, "abc", "Add a test"))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join(SESSIONS_DIR).join(".gitignore")).unwrap(),
+            "*\n"
+        );
+
+        let all = manifest.resumable(None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].session_id, "abc");
+        assert_eq!(all[0].last_prompt, "Add a test");
+        assert_eq!(
+            manifest
+                .resumable(Some(BaseCodingAgent::Amp))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(
+            manifest
+                .validate(BaseCodingAgent::ClaudeCode, "abc")
+                .is_ok()
+        );
+        assert!(matches!(
+            manifest.validate(BaseCodingAgent::ClaudeCode, "t-1"),
+            Err(ExecutorError::SessionNotFound(id)) if id == "t-1"
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,353 @@
+//! Warm standby sessions for low-latency task starts.
+//!
+//! Starting an agent through `npx` and waiting for its control protocol to
+//! initialize takes several seconds before the prompt is even sent. For
+//! profiles with `warm_standby` set, an idle session is started in the
+//! workspace a task just ran in; the next task started there with the same
+//! profile claims it and sends its prompt straight away. Idle sessions are
+//! health-checked periodically and recycled once their TTL expires.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, LazyLock, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use ts_rs::TS;
+
+use crate::{
+    env::ExecutionEnv,
+    executors::{AppendPrompt, CodingAgent, ExecutorError, SpawnedChild},
+    profile::ExecutorProfileId,
+    prompt::PromptTemplate,
+};
+
+/// How often idle sessions are checked for liveness and expiry
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+fn default_ttl_secs() -> u64 {
+    600
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct StandbyConfig {
+    /// Seconds an unclaimed session is kept before it is recycled
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for StandbyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_ttl_secs(),
+        }
+    }
+}
+
+impl StandbyConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs)
+    }
+}
+
+/// A spawned and initialized agent process waiting for its prompt.
+///
+/// Dropping an unclaimed session kills its process group.
+pub struct WarmSession {
+    spawned: Option<SpawnedChild>,
+    prompt_tx: Option<oneshot::Sender<String>>,
+    append_prompt: AppendPrompt,
+    template: Option<PromptTemplate>,
+    created_at: Instant,
+}
+
+impl WarmSession {
+    pub fn new(
+        spawned: SpawnedChild,
+        prompt_tx: oneshot::Sender<String>,
+        append_prompt: AppendPrompt,
+    ) -> Self {
+        Self {
+            spawned: Some(spawned),
+            prompt_tx: Some(prompt_tx),
+            append_prompt,
+            template: None,
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Frame the claimed prompt with the profile's prompt template
+    pub fn with_template(mut self, template: Option<PromptTemplate>) -> Self {
+        self.template = template;
+        self
+    }
+
+    pub fn age(&self) -> Duration {
+        self.created_at.elapsed()
+    }
+
+    /// Whether the process is still running and waiting for a prompt
+    pub fn is_healthy(&mut self) -> bool {
+        let waiting = self.prompt_tx.as_ref().is_some_and(|tx| !tx.is_closed());
+        waiting
+            && self
+                .spawned
+                .as_mut()
+                .is_some_and(|spawned| matches!(spawned.child.try_wait(), Ok(None)))
+    }
+
+    /// Send the task's prompt and hand over the process. Placeholders are
+    /// filled from the claiming execution's `env`.
+    pub fn claim(
+        mut self,
+        prompt: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let prompt = self
+            .append_prompt
+            .frame_prompt(self.template.as_ref(), prompt, env);
+        let sent = self
+            .prompt_tx
+            .take()
+            .is_some_and(|tx| tx.send(prompt).is_ok());
+        match self.spawned.take() {
+            Some(spawned) if sent => Ok(spawned),
+            spawned => {
+                self.spawned = spawned;
+                Err(ExecutorError::Io(std::io::Error::other(
+                    "warm session exited before it was claimed",
+                )))
+            }
+        }
+    }
+}
+
+impl Drop for WarmSession {
+    fn drop(&mut self) {
+        if let Some(spawned) = self.spawned.as_mut() {
+            let _ = spawned.child.start_kill();
+        }
+    }
+}
+
+/// Identifies where a warm session can be used: the same profile, directory
+/// and environment as the task that claims it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StandbyKey {
+    pub profile: ExecutorProfileId,
+    pub current_dir: PathBuf,
+    env: Vec<(String, String)>,
+}
+
+impl StandbyKey {
+    pub fn new(profile: ExecutorProfileId, current_dir: &Path, env: &ExecutionEnv) -> Self {
+        let mut env: Vec<_> = env
+            .vars
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        env.sort();
+        Self {
+            profile,
+            current_dir: current_dir.to_path_buf(),
+            env,
+        }
+    }
+}
+
+struct Standby {
+    /// Configuration the session was started with
+    agent: CodingAgent,
+    ttl: Duration,
+    session: WarmSession,
+}
+
+impl Standby {
+    fn is_usable(&mut self) -> bool {
+        self.session.age() < self.ttl && self.session.is_healthy()
+    }
+}
+
+#[derive(Default)]
+pub struct StandbyPool {
+    sessions: Mutex<HashMap<StandbyKey, Standby>>,
+    health_checks_started: AtomicBool,
+}
+
+static GLOBAL_POOL: LazyLock<Arc<StandbyPool>> = LazyLock::new(Arc::default);
+
+impl StandbyPool {
+    pub fn global() -> &'static Arc<StandbyPool> {
+        &GLOBAL_POOL
+    }
+
+    /// Claim the idle session for `key`, if it was started with the same
+    /// configuration as `agent` and is still usable
+    pub fn take(&self, key: &StandbyKey, agent: &CodingAgent) -> Option<WarmSession> {
+        let mut standby = self.sessions.lock().unwrap().remove(key)?;
+        if &standby.agent != agent {
+            tracing::debug!(
+                "Discarding warm session for {}: profile changed",
+                key.profile
+            );
+            return None;
+        }
+        standby.is_usable().then_some(standby.session)
+    }
+
+    /// Park a session for `key`, replacing any previous one
+    pub fn insert(self: &Arc<Self>, key: StandbyKey, agent: CodingAgent, session: WarmSession) {
+        let Some(config) = agent.warm_standby().cloned() else {
+            return;
+        };
+        let standby = Standby {
+            agent,
+            ttl: config.ttl(),
+            session,
+        };
+        self.sessions.lock().unwrap().insert(key, standby);
+        self.spawn_health_checks();
+    }
+
+    /// Start a session for `key` in the background, if the profile asks for
+    /// one and the executor supports it
+    pub fn replenish(self: &Arc<Self>, key: StandbyKey, agent: CodingAgent, env: ExecutionEnv) {
+        if agent.warm_standby().is_none() {
+            return;
+        }
+        let pool = self.clone();
+        tokio::spawn(async move {
+            match agent.spawn_warm(&key.current_dir, &env).await {
+                Ok(Some(session)) => pool.insert(key, agent, session),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to start warm session for {}: {e}", key.profile)
+                }
+            }
+        });
+    }
+
+    /// Drop sessions that exited or outlived their TTL, returning how many
+    /// were removed
+    pub fn prune(&self) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, standby| standby.is_usable());
+        before - sessions.len()
+    }
+
+    /// Kill all idle sessions
+    pub fn clear(&self) {
+        self.sessions.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn spawn_health_checks(self: &Arc<Self>) {
+        if self.health_checks_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let pool: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(pool) = pool.upgrade() else {
+                    break;
+                };
+                let pruned = pool.prune();
+                if pruned > 0 {
+                    tracing::debug!("Recycled {pruned} warm standby session(s)");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        executors::{BaseCodingAgent, StandardCodingAgentExecutor, claude::ClaudeCode},
+        testing::MockExecutor,
+    };
+
+    async fn idle_session() -> (WarmSession, oneshot::Receiver<String>) {
+        let spawned = MockExecutor::new()
+            .hang()
+            .spawn(&std::env::temp_dir(), "", &ExecutionEnv::default())
+            .await
+            .unwrap();
+        let (prompt_tx, prompt_rx) = oneshot::channel();
+        let append_prompt = AppendPrompt(Some("Be brief.".to_string()));
+        (
+            WarmSession::new(spawned, prompt_tx, append_prompt),
+            prompt_rx,
+        )
+    }
+
+    fn claude(ttl_secs: u64) -> CodingAgent {
+        let mut agent: ClaudeCode = serde_json::from_str("{}").unwrap();
+        agent.warm_standby = Some(StandbyConfig { ttl_secs });
+        CodingAgent::ClaudeCode(agent)
+    }
+
+    fn key() -> StandbyKey {
+        let mut env = ExecutionEnv::default();
+        env.insert("VK_TASK_ID", "task");
+        StandbyKey::new(
+            ExecutorProfileId::new(BaseCodingAgent::ClaudeCode),
+            &std::env::temp_dir(),
+            &env,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_claim_sends_prompt() {
+        let (session, prompt_rx) = idle_session().await;
+        let pool = Arc::new(StandbyPool::default());
+        pool.insert(key(), claude(600), session);
+
+        // A changed profile discards the session
+        assert!(pool.take(&key(), &claude(60)).is_none());
+        assert!(pool.is_empty());
+        assert!(prompt_rx.await.is_err());
+
+        let (session, prompt_rx_2) = idle_session().await;
+        pool.insert(key(), claude(600), session);
+        let mut session = pool.take(&key(), &claude(600)).unwrap();
+        assert!(session.is_healthy());
+        let mut spawned = session
+            .claim("Fix the bug", &ExecutionEnv::default())
+            .unwrap();
+        assert_eq!(prompt_rx_2.await.unwrap(), "Fix the bugBe brief.");
+        spawned.child.kill().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prune_recycles_expired_and_dead_sessions() {
+        let pool = Arc::new(StandbyPool::default());
+        let (session, _prompt_rx) = idle_session().await;
+        pool.insert(key(), claude(0), session);
+        assert_eq!(pool.prune(), 1);
+
+        let (mut session, prompt_rx) = idle_session().await;
+        drop(prompt_rx);
+        assert!(!session.is_healthy());
+        pool.insert(key(), claude(600), session);
+        assert!(pool.take(&key(), &claude(600)).is_none());
+    }
+}
@@ -7,12 +7,17 @@
 use std::os::unix::io::{FromRawFd, IntoRawFd, OwnedFd};
 #[cfg(windows)]
 use std::os::windows::io::{FromRawHandle, IntoRawHandle, OwnedHandle};
+use std::{path::Path, process::Stdio};
 
-use command_group::AsyncGroupChild;
+use command_group::{AsyncCommandGroup, AsyncGroupChild};
 use futures::{StreamExt, stream::BoxStream};
-use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    process::Command,
+};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_util::io::ReaderStream;
+use workspace_utils::shell::get_shell_command;
 
 use crate::executors::ExecutorError;
 
@@ -183,6 +188,40 @@ pub fn create_stdout_pipe_writer<'b>(
     wrap_fd_as_tokio_writer(pipe_writer)
 }
 
+/// Create a fresh stderr pipe for the child process and return an async writer
+/// that writes directly to the child's new stderr, like
+/// [`create_stdout_pipe_writer`] does for stdout.
+pub fn create_stderr_pipe_writer<'b>(
+    child: &mut AsyncGroupChild,
+) -> Result<impl AsyncWrite + 'b, ExecutorError> {
+    let (pipe_reader, pipe_writer) = os_pipe::pipe().map_err(|e| {
+        ExecutorError::Io(std::io::Error::other(format!("Failed to create pipe: {e}")))
+    })?;
+    child.inner().stderr = Some(wrap_fd_as_child_stderr(pipe_reader)?);
+
+    wrap_fd_as_tokio_writer(pipe_writer)
+}
+
+/// Spawn a process that stands in for an executor running elsewhere, e.g. in
+/// process, so the container has a child to track.
+///
+/// The stand-in reads `stdin` until it closes and exits then. Its stdout is
+/// discarded and its stderr piped; callers give it pipes of their own with
+/// [`create_stdout_pipe_writer`] and [`create_stderr_pipe_writer`].
+pub fn spawn_stand_in(current_dir: &Path, stdin: Stdio) -> Result<AsyncGroupChild, ExecutorError> {
+    let (shell, shell_arg) = get_shell_command();
+    let mut command = Command::new(shell);
+    command
+        .kill_on_drop(true)
+        .stdin(stdin)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .current_dir(current_dir)
+        .arg(shell_arg)
+        .arg(if cfg!(windows) { "more" } else { "cat" });
+    Ok(command.group_spawn()?)
+}
+
 // =========================================
 // OS file descriptor helper functions
 // =========================================
@@ -210,6 +249,27 @@ fn wrap_fd_as_child_stdout(
     }
 }
 
+/// Convert os_pipe::PipeReader to tokio::process::ChildStderr
+fn wrap_fd_as_child_stderr(
+    pipe_reader: os_pipe::PipeReader,
+) -> Result<tokio::process::ChildStderr, ExecutorError> {
+    #[cfg(unix)]
+    {
+        let raw_fd = pipe_reader.into_raw_fd();
+        let owned_fd = unsafe { OwnedFd::from_raw_fd(raw_fd) };
+        let std_stderr = std::process::ChildStderr::from(owned_fd);
+        tokio::process::ChildStderr::from_std(std_stderr).map_err(ExecutorError::Io)
+    }
+
+    #[cfg(windows)]
+    {
+        let raw_handle = pipe_reader.into_raw_handle();
+        let owned_handle = unsafe { OwnedHandle::from_raw_handle(raw_handle) };
+        let std_stderr = std::process::ChildStderr::from(owned_handle);
+        tokio::process::ChildStderr::from_std(std_stderr).map_err(ExecutorError::Io)
+    }
+}
+
 /// Convert os_pipe::PipeWriter to a tokio file for async writing
 fn wrap_fd_as_tokio_writer(
     pipe_writer: os_pipe::PipeWriter,
@@ -0,0 +1,426 @@
+//! Resuming agents that crash partway through a run.
+//!
+//! Now and then an agent process dies mid-session: it is killed by a signal,
+//! or loses its connection and exits non-zero, leaving a session that could
+//! have carried on. With a profile's [`Supervisor`] set, the agent runs under
+//! a supervisor that watches its stdout for the session id and for the
+//! `result` event that ends a run. If the process exits unsuccessfully before
+//! reporting a result, the supervisor resumes the session with a follow-up,
+//! as `--resume <session_id>` would, up to `max_restarts` times.
+//!
+//! The container sees a single execution throughout. It holds a stand-in
+//! process whose stdout and stderr carry the output of each agent process in
+//! turn, so the restarts read as one conversation; interrupts and user input
+//! are passed on to whichever process is running.
+
+use std::{future::pending, path::Path, pin::Pin, time::Duration};
+
+use futures::{FutureExt, StreamExt, future::BoxFuture};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    sync::{mpsc, oneshot},
+};
+use tokio_util::io::ReaderStream;
+use ts_rs::TS;
+
+use crate::{
+    env::ExecutionEnv,
+    executors::{
+        CodingAgent, ExecutorError, ExecutorExitResult, SpawnedChild, StandardCodingAgentExecutor,
+        claude_flow::parse_result_event,
+    },
+    interrupt::{DEFAULT_INTERRUPT_GRACE, signal_process_group},
+    logs::{stderr_processor::ExecutorFailureReason, utils::find_key},
+    outcome::{ExecutionOutcome, FailureReason},
+    stdout_dup::{create_stderr_pipe_writer, create_stdout_pipe_writer, spawn_stand_in},
+};
+
+const DEFAULT_MAX_RESTARTS: u32 = 2;
+const DEFAULT_RESTART_DELAY_MS: u64 = 1_000;
+const RESUME_PROMPT: &str =
+    "Your previous run stopped unexpectedly. Continue the task from where you left off.";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TS, JsonSchema)]
+pub struct Supervisor {
+    /// Times a crashed agent is resumed within one execution; defaults to 2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_restarts: Option<u32>,
+    /// Milliseconds to wait before resuming it; defaults to 1000
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_delay_ms: Option<u64>,
+}
+
+/// Spawns the agent again, resuming the session with the given id
+type Respawn =
+    Box<dyn FnMut(String) -> BoxFuture<'static, Result<SpawnedChild, ExecutorError>> + Send>;
+
+type Output = Pin<Box<dyn AsyncWrite + Send>>;
+
+impl Supervisor {
+    /// Run a freshly spawned `agent` under supervision. The returned child
+    /// is the stand-in the container tracks in its place.
+    pub fn supervise(
+        &self,
+        spawned: SpawnedChild,
+        agent: &CodingAgent,
+        current_dir: &Path,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let agent = agent.clone();
+        let dir = current_dir.to_path_buf();
+        let env = env.clone();
+        let respawn: Respawn = Box::new(move |session_id| {
+            let (agent, dir, env) = (agent.clone(), dir.clone(), env.clone());
+            async move {
+                agent
+                    .spawn_follow_up(&dir, RESUME_PROMPT, &session_id, &env)
+                    .await
+            }
+            .boxed()
+        });
+        self.supervise_with(spawned, current_dir, respawn)
+    }
+
+    fn supervise_with(
+        &self,
+        mut spawned: SpawnedChild,
+        current_dir: &Path,
+        mut respawn: Respawn,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        // The stand-in exits once its stdin closes, which the supervisor
+        // holds open until the execution is over
+        let (stdin_reader, stdin_writer) = os_pipe::pipe().map_err(|e| {
+            ExecutorError::Io(std::io::Error::other(format!("Failed to create pipe: {e}")))
+        })?;
+        let mut stand_in = spawn_stand_in(current_dir, stdin_reader.into())?;
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        let input_sender = spawned.input_sender.is_some().then_some(input_tx);
+        let (interrupt_tx, interrupt_rx) = oneshot::channel();
+        let (mut exit_tx, exit_rx) = oneshot::channel();
+        let mut run = SupervisedRun {
+            stdout: Box::pin(create_stdout_pipe_writer(&mut stand_in)?),
+            stderr: Box::pin(create_stderr_pipe_writer(&mut stand_in)?),
+            interrupt_rx: Some(interrupt_rx),
+            input_rx,
+            interrupted: false,
+            session_id: None,
+        };
+
+        let max_restarts = self.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS);
+        let restart_delay =
+            Duration::from_millis(self.restart_delay_ms.unwrap_or(DEFAULT_RESTART_DELAY_MS));
+        tokio::spawn(async move {
+            let mut restarts = 0;
+            let result = loop {
+                let failure = match run.watch(&mut spawned, &mut exit_tx).await {
+                    ChildExit::Abandoned => return,
+                    ChildExit::Reported(result) => break result,
+                    ChildExit::Exited { .. } if run.interrupted => {
+                        break ExecutorExitResult::Interrupted;
+                    }
+                    ChildExit::Exited { result, finished } if finished || result.is_success() => {
+                        break result;
+                    }
+                    ChildExit::Exited { result, .. } => result,
+                };
+                // Without a session there is nothing to resume
+                let Some(session_id) = run.session_id.clone() else {
+                    break failure;
+                };
+                if restarts == max_restarts {
+                    run.note(&format!(
+                        "The agent exited before finishing and was restarted {max_restarts} times already; giving up"
+                    ))
+                    .await;
+                    break failure;
+                }
+                restarts += 1;
+                run.note(&format!(
+                    "The agent exited before finishing; resuming session {session_id} (restart {restarts} of {max_restarts})"
+                ))
+                .await;
+
+                tokio::select! {
+                    _ = exit_tx.closed() => return,
+                    requested = interrupt(&mut run.interrupt_rx) => {
+                        if requested {
+                            run.interrupted = true;
+                            break ExecutorExitResult::Interrupted;
+                        }
+                    }
+                    _ = tokio::time::sleep(restart_delay) => {}
+                }
+                match respawn(session_id).await {
+                    Ok(next) => spawned = next,
+                    Err(err) => {
+                        run.note(&format!("Failed to resume the agent: {err}"))
+                            .await;
+                        break ExecutorExitResult::Failed(FailureReason::from(&err));
+                    }
+                }
+            };
+
+            let _ = exit_tx.send(result);
+            let interrupted = run.interrupted;
+            drop(run);
+            // An interrupted stand-in is waited on to exit. Otherwise the
+            // container stops it on the exit signal, which it should act on
+            // before seeing the stand-in exit by itself.
+            if !interrupted {
+                tokio::time::sleep(DEFAULT_INTERRUPT_GRACE).await;
+            }
+            drop(stdin_writer);
+        });
+
+        Ok(SpawnedChild {
+            child: stand_in,
+            exit_signal: Some(exit_rx),
+            interrupt_sender: Some(interrupt_tx),
+            input_sender,
+        })
+    }
+}
+
+/// How one agent process ended
+enum ChildExit {
+    /// The executor reported its own result
+    Reported(ExecutorExitResult),
+    /// The process exited, with the result its exit status, stderr and
+    /// result event add up to; `finished` if it reported a result event
+    Exited {
+        result: ExecutorExitResult,
+        finished: bool,
+    },
+    /// The container stopped listening, as the execution is over
+    Abandoned,
+}
+
+/// State carried across the agent processes of one execution
+struct SupervisedRun {
+    /// The stand-in's stdout and stderr
+    stdout: Output,
+    stderr: Output,
+    interrupt_rx: Option<oneshot::Receiver<()>>,
+    input_rx: mpsc::UnboundedReceiver<String>,
+    interrupted: bool,
+    /// Latest session id the agent reported
+    session_id: Option<String>,
+}
+
+impl SupervisedRun {
+    /// Forward the output of `spawned` to the stand-in until it exits and its
+    /// output is drained
+    async fn watch(
+        &mut self,
+        spawned: &mut SpawnedChild,
+        exit_tx: &mut oneshot::Sender<ExecutorExitResult>,
+    ) -> ChildExit {
+        let mut stdout = spawned.child.inner().stdout.take().map(ReaderStream::new);
+        let mut stderr = spawned.child.inner().stderr.take().map(ReaderStream::new);
+        let mut executor_exit: BoxFuture<'static, _> = match spawned.exit_signal.take() {
+            Some(rx) => rx.boxed(),
+            None => pending().boxed(),
+        };
+        let mut reported = None;
+        let mut exited = None;
+        let mut outcome = None;
+        let mut stderr_reason = None;
+        let mut line = String::new();
+
+        while exited.is_none() || stdout.is_some() || stderr.is_some() {
+            tokio::select! {
+                _ = exit_tx.closed() => {
+                    let _ = spawned.child.kill().await;
+                    return ChildExit::Abandoned;
+                }
+                result = &mut executor_exit, if reported.is_none() => match result {
+                    Ok(result) => {
+                        reported = Some(result);
+                        let _ = spawned.child.kill().await;
+                    }
+                    // The executor won't report anything
+                    Err(_) => executor_exit = pending().boxed(),
+                },
+                status = spawned.child.inner().wait(), if exited.is_none() => {
+                    exited = Some(status);
+                    // Anything the agent left running would hold its pipes open
+                    let _ = spawned.child.kill().await;
+                }
+                chunk = next_chunk(&mut stdout) => match chunk {
+                    Some(Ok(data)) => {
+                        let _ = self.stdout.write_all(&data).await;
+                        line.push_str(&String::from_utf8_lossy(&data));
+                        while let Some(end) = line.find('\n') {
+                            if let Some(reported) = scan_line(line[..end].trim(), &mut self.session_id) {
+                                outcome = Some(reported);
+                            }
+                            line.drain(..=end);
+                        }
+                    }
+                    _ => stdout = None,
+                },
+                chunk = next_chunk(&mut stderr) => match chunk {
+                    Some(Ok(data)) => {
+                        let _ = self.stderr.write_all(&data).await;
+                        if let Some(reason) = ExecutorFailureReason::classify(&String::from_utf8_lossy(&data)) {
+                            stderr_reason = Some(reason);
+                        }
+                    }
+                    _ => stderr = None,
+                },
+                requested = interrupt(&mut self.interrupt_rx) => {
+                    if requested {
+                        self.interrupted = true;
+                        match spawned.interrupt_sender.take() {
+                            Some(sender) => {
+                                let _ = sender.send(());
+                            }
+                            None => {
+                                if let Some(pid) = spawned.child.id() {
+                                    let _ = signal_process_group(pid);
+                                }
+                            }
+                        }
+                    }
+                }
+                Some(message) = self.input_rx.recv() => {
+                    if let Some(sender) = &spawned.input_sender {
+                        let _ = sender.send(message);
+                    }
+                }
+            }
+        }
+        if let Some(reported) = scan_line(line.trim(), &mut self.session_id) {
+            outcome = Some(reported);
+        }
+
+        if let Some(result) = reported {
+            return ChildExit::Reported(result);
+        }
+        let finished = outcome.is_some();
+        let result = match (outcome, exited) {
+            (Some(outcome), _) => outcome.into(),
+            (None, Some(Ok(status))) => ExecutorExitResult::from_exit_status(status, stderr_reason),
+            (None, _) => ExecutorExitResult::Failed(FailureReason::Exited { code: None }),
+        };
+        ChildExit::Exited { result, finished }
+    }
+
+    /// Tell the user what the supervisor is doing, on the stand-in's stderr
+    async fn note(&mut self, message: &str) {
+        let _ = self
+            .stderr
+            .write_all(format!("{message}\n").as_bytes())
+            .await;
+    }
+}
+
+/// Next chunk of a pipe that is still open
+async fn next_chunk<R: AsyncRead + Unpin>(
+    stream: &mut Option<ReaderStream<R>>,
+) -> Option<std::io::Result<bytes::Bytes>> {
+    match stream {
+        Some(stream) => stream.next().await,
+        None => pending().await,
+    }
+}
+
+/// Whether an interrupt was requested; waits forever once the container has
+/// either requested one or dropped its sender
+async fn interrupt(rx: &mut Option<oneshot::Receiver<()>>) -> bool {
+    match rx {
+        Some(receiver) => {
+            let requested = receiver.await.is_ok();
+            *rx = None;
+            requested
+        }
+        None => pending().await,
+    }
+}
+
+/// Take the session id a line of stdout reports; returns the outcome if it
+/// is the result event that ends a run
+fn scan_line(line: &str, session_id: &mut Option<String>) -> Option<ExecutionOutcome> {
+    let Ok(event) = serde_json::from_str::<Value>(line) else {
+        return None;
+    };
+    if let Some(id) = ["session_id", "sessionId"]
+        .iter()
+        .find_map(|key| find_key(&event, key).and_then(Value::as_str))
+        && !id.is_empty()
+    {
+        *session_id = Some(id.to_string());
+    }
+    parse_result_event(line)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        process::Stdio,
+        sync::{Arc, Mutex},
+    };
+
+    use command_group::AsyncCommandGroup;
+    use tokio::{io::AsyncReadExt, process::Command};
+
+    use super::*;
+    use crate::outcome::RunSummary;
+
+    fn spawn_script(script: &str) -> Result<SpawnedChild, ExecutorError> {
+        let child = Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .group_spawn()?;
+        Ok(SpawnedChild::from(child))
+    }
+
+    #[tokio::test]
+    async fn test_crashed_agent_resumed_with_its_session() {
+        let resumed = Arc::new(Mutex::new(vec![]));
+        let respawn: Respawn = Box::new({
+            let resumed = resumed.clone();
+            move |session_id| {
+                resumed.lock().unwrap().push(session_id);
+                async {
+                    spawn_script(r#"echo '{"type":"assistant"}'; echo '{"type":"result","session_id":"s2"}'"#)
+                }
+                .boxed()
+            }
+        });
+        let supervisor = Supervisor {
+            max_restarts: Some(1),
+            restart_delay_ms: Some(0),
+        };
+        let crashing =
+            spawn_script(r#"echo '{"type":"system","session_id":"s1"}'; kill -9 $$"#).unwrap();
+        let mut spawned = supervisor
+            .supervise_with(crashing, Path::new("."), respawn)
+            .unwrap();
+
+        let result = spawned.exit_signal.take().unwrap().await.unwrap();
+        assert_eq!(result, ExecutorExitResult::Success(RunSummary::default()));
+        assert_eq!(*resumed.lock().unwrap(), vec!["s1".to_string()]);
+
+        let mut stdout = spawned.child.inner().stdout.take().unwrap();
+        let mut stderr = spawned.child.inner().stderr.take().unwrap();
+        spawned.child.kill().await.unwrap();
+        let mut stitched = String::new();
+        stdout.read_to_string(&mut stitched).await.unwrap();
+        assert_eq!(
+            stitched.lines().collect::<Vec<_>>(),
+            vec![
+                r#"{"type":"system","session_id":"s1"}"#,
+                r#"{"type":"assistant"}"#,
+                r#"{"type":"result","session_id":"s2"}"#,
+            ]
+        );
+        let mut notes = String::new();
+        stderr.read_to_string(&mut notes).await.unwrap();
+        assert!(notes.contains("resuming session s1 (restart 1 of 1)"));
+    }
+}
@@ -0,0 +1,331 @@
+//! Opt-in, anonymized usage telemetry for executors.
+//!
+//! When the user opts in, finished runs are aggregated locally into counts
+//! per executor: how often it ran, how runs ended, which failure classes
+//! occurred and coarse duration buckets. Prompts, output, paths and ids are
+//! never recorded. The aggregate is kept in `telemetry.json` in the asset
+//! directory and can be exported as a [`TelemetryReport`] for the user to
+//! inspect before choosing to send it. Opting out deletes it.
+//!
+//! Each [tenant](crate::tenant)'s runs are counted apart from the server's
+//! own and exported separately, so no report mixes tenants or names one.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cancellation::CancelReason,
+    executors::{BaseCodingAgent, ExecutorError},
+    logs::stderr_processor::ExecutorFailureReason,
+    tenant::TenantId,
+};
+
+static TELEMETRY: LazyLock<Telemetry> = LazyLock::new(|| Telemetry {
+    path: Some(workspace_utils::assets::asset_dir().join("telemetry.json")),
+    ..Telemetry::default()
+});
+
+/// Why a run failed, without any of its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    ExecutableNotFound,
+    SpawnError,
+    AuthRequired,
+    Unauthorized,
+    RateLimited,
+    OutOfMemory,
+    /// The agent hit a CPU time or process limit
+    ResourceLimit,
+    Network,
+    /// The conversation outgrew the model's context window
+    ContextOverflow,
+    /// The agent exited with a non-zero code
+    NonZeroExit,
+    /// The agent was killed without being cancelled
+    Killed,
+    Other,
+}
+
+impl FailureClass {
+    pub fn of(error: &ExecutorError) -> Self {
+        match error {
+            ExecutorError::ExecutableNotFound { .. } => Self::ExecutableNotFound,
+            ExecutorError::SpawnError(_) | ExecutorError::Io(_) => Self::SpawnError,
+            ExecutorError::AuthRequired(_) => Self::AuthRequired,
+            ExecutorError::Unauthorized(_) => Self::Unauthorized,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl From<ExecutorFailureReason> for FailureClass {
+    fn from(reason: ExecutorFailureReason) -> Self {
+        match reason {
+            ExecutorFailureReason::AuthFailure => Self::AuthRequired,
+            ExecutorFailureReason::RateLimited => Self::RateLimited,
+            ExecutorFailureReason::OutOfMemory => Self::OutOfMemory,
+            ExecutorFailureReason::ResourceLimit => Self::ResourceLimit,
+            ExecutorFailureReason::Network => Self::Network,
+            ExecutorFailureReason::MissingBinary => Self::ExecutableNotFound,
+            ExecutorFailureReason::ContextOverflow => Self::ContextOverflow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Succeeded,
+    Failed(FailureClass),
+    Cancelled(CancelReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationBucket {
+    UnderOneMinute,
+    UnderTenMinutes,
+    UnderOneHour,
+    OneHourOrMore,
+}
+
+impl DurationBucket {
+    pub fn of(duration: Duration) -> Self {
+        match duration.as_secs() {
+            0..60 => Self::UnderOneMinute,
+            60..600 => Self::UnderTenMinutes,
+            600..3600 => Self::UnderOneHour,
+            _ => Self::OneHourOrMore,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ExecutorUsage {
+    pub runs: u32,
+    pub succeeded: u32,
+    pub failed: u32,
+    pub cancelled: u32,
+    pub failure_classes: HashMap<FailureClass, u32>,
+    pub durations: HashMap<DurationBucket, u32>,
+}
+
+/// Everything telemetry has collected, exactly as it would be sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct TelemetryReport {
+    /// When collection started
+    pub since: DateTime<Utc>,
+    pub executors: HashMap<BaseCodingAgent, ExecutorUsage>,
+}
+
+impl Default for TelemetryReport {
+    fn default() -> Self {
+        Self {
+            since: Utc::now(),
+            executors: HashMap::new(),
+        }
+    }
+}
+
+/// The server's own aggregate, as older versions stored it, and each
+/// tenant's
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Collected {
+    #[serde(flatten)]
+    server: TelemetryReport,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    tenants: HashMap<TenantId, TelemetryReport>,
+}
+
+#[derive(Default)]
+pub struct Telemetry {
+    /// Where the aggregate is persisted; kept in memory only if unset
+    path: Option<PathBuf>,
+    enabled: AtomicBool,
+    collected: Mutex<Option<Collected>>,
+}
+
+impl Telemetry {
+    pub fn global() -> &'static Telemetry {
+        &TELEMETRY
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Opt in or out. Opting out discards everything collected so far.
+    pub fn set_enabled(&self, enabled: bool) {
+        if self.enabled.swap(enabled, Ordering::SeqCst) == enabled {
+            return;
+        }
+        let mut collected = self.collected.lock().unwrap();
+        let Some(path) = &self.path else {
+            *collected = None;
+            return;
+        };
+        if enabled {
+            *collected = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|raw| serde_json::from_str(&raw).ok());
+        } else {
+            *collected = None;
+            if let Err(e) = std::fs::remove_file(path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                tracing::warn!("Failed to delete telemetry data: {e}");
+            }
+        }
+    }
+
+    /// Count a finished run of `tenant`, or of the server for `None`, if the
+    /// user opted in
+    pub fn record_run(
+        &self,
+        executor: BaseCodingAgent,
+        tenant: Option<&TenantId>,
+        duration: Duration,
+        outcome: RunOutcome,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut collected = self.collected.lock().unwrap();
+        let collected = collected.get_or_insert_with(Collected::default);
+        let report = match tenant {
+            Some(tenant) => collected.tenants.entry(tenant.clone()).or_default(),
+            None => &mut collected.server,
+        };
+        let usage = report.executors.entry(executor).or_default();
+        usage.runs = usage.runs.saturating_add(1);
+        match outcome {
+            RunOutcome::Succeeded => usage.succeeded = usage.succeeded.saturating_add(1),
+            RunOutcome::Failed(class) => {
+                usage.failed = usage.failed.saturating_add(1);
+                let count = usage.failure_classes.entry(class).or_default();
+                *count = count.saturating_add(1);
+            }
+            RunOutcome::Cancelled(_) => usage.cancelled = usage.cancelled.saturating_add(1),
+        }
+        let count = usage
+            .durations
+            .entry(DurationBucket::of(duration))
+            .or_default();
+        *count = count.saturating_add(1);
+
+        if let Some(path) = &self.path
+            && let Err(e) = serde_json::to_string(collected)
+                .map_err(std::io::Error::from)
+                .and_then(|raw| std::fs::write(path, raw))
+        {
+            tracing::warn!("Failed to save telemetry data: {e}");
+        }
+    }
+
+    /// Count a run that failed to spawn
+    pub fn record_spawn_failure(
+        &self,
+        executor: BaseCodingAgent,
+        tenant: Option<&TenantId>,
+        error: &ExecutorError,
+    ) {
+        let outcome = match error {
+            ExecutorError::Cancelled(reason) => RunOutcome::Cancelled(*reason),
+            error => RunOutcome::Failed(FailureClass::of(error)),
+        };
+        self.record_run(executor, tenant, Duration::ZERO, outcome);
+    }
+
+    /// The aggregate collected so far for `tenant`, or for the server's own
+    /// runs, for the user to inspect
+    pub fn export(&self, tenant: Option<&TenantId>) -> Option<TelemetryReport> {
+        let collected = self.collected.lock().unwrap();
+        let collected = collected.as_ref()?;
+        match tenant {
+            Some(tenant) => collected.tenants.get(tenant).cloned(),
+            None => Some(collected.server.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregates_only_when_enabled() {
+        let telemetry = Telemetry::default();
+        telemetry.record_run(
+            BaseCodingAgent::Amp,
+            None,
+            Duration::from_secs(5),
+            RunOutcome::Succeeded,
+        );
+        assert!(telemetry.export(None).is_none());
+
+        telemetry.set_enabled(true);
+        telemetry.record_spawn_failure(
+            BaseCodingAgent::Amp,
+            None,
+            &ExecutorError::ExecutableNotFound {
+                program: "amp".to_string(),
+            },
+        );
+        telemetry.record_run(
+            BaseCodingAgent::Amp,
+            None,
+            Duration::from_secs(700),
+            RunOutcome::Cancelled(CancelReason::UserRequested),
+        );
+
+        let usage = &telemetry.export(None).unwrap().executors[&BaseCodingAgent::Amp];
+        assert_eq!((usage.runs, usage.failed, usage.cancelled), (2, 1, 1));
+        assert_eq!(usage.failure_classes[&FailureClass::ExecutableNotFound], 1);
+        assert_eq!(usage.durations[&DurationBucket::UnderOneMinute], 1);
+        assert_eq!(usage.durations[&DurationBucket::UnderOneHour], 1);
+
+        telemetry.set_enabled(false);
+        assert!(telemetry.export(None).is_none());
+    }
+
+    #[test]
+    fn test_tenants_are_counted_apart() {
+        let telemetry = Telemetry::default();
+        telemetry.set_enabled(true);
+        let acme = TenantId::new("acme").unwrap();
+        telemetry.record_run(
+            BaseCodingAgent::Amp,
+            Some(&acme),
+            Duration::from_secs(5),
+            RunOutcome::Succeeded,
+        );
+
+        let report = telemetry.export(Some(&acme)).unwrap();
+        assert_eq!(report.executors[&BaseCodingAgent::Amp].runs, 1);
+        assert!(telemetry.export(None).unwrap().executors.is_empty());
+        assert!(
+            telemetry
+                .export(Some(&TenantId::new("globex").unwrap()))
+                .is_none()
+        );
+
+        // Aggregates stored before tenants existed still load
+        let stored: Collected = serde_json::from_value(serde_json::json!({
+            "since": "2025-01-01T00:00:00Z",
+            "executors": {},
+        }))
+        .unwrap();
+        assert!(stored.tenants.is_empty());
+    }
+}
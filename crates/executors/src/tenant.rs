@@ -0,0 +1,103 @@
+//! Tenants sharing one server.
+//!
+//! Each tenant layers its own profile overrides on top of the server's
+//! profiles, stored under `tenants/<id>/profiles.json` in the asset
+//! directory. API keys live in the `env` of those profiles or in the
+//! tenant's own secrets, so tenants never see each other's credentials, and
+//! usage and budgets are counted per tenant.
+//!
+//! An execution's tenant is stored with its
+//! [`ExecutorAction`](crate::actions::ExecutorAction): a new session runs as
+//! the tenant set in the server's config, and its later executions keep the
+//! tenant it started with. Spawns carry it in
+//! [`ExecutionEnv`](crate::env::ExecutionEnv), which also exposes it to the
+//! agent as `VK_TENANT_ID`.
+
+use std::{fmt, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use ts_rs::TS;
+
+const MAX_TENANT_ID_LEN: usize = 64;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TenantError {
+    #[error("Invalid tenant id '{0}': use 1-64 ASCII letters, digits, '-' or '_'")]
+    InvalidId(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[serde(try_from = "String", into = "String")]
+#[ts(type = "string")]
+pub struct TenantId(String);
+
+impl TenantId {
+    pub fn new(id: impl Into<String>) -> Result<Self, TenantError> {
+        let id = id.into();
+        let valid = !id.is_empty()
+            && id.len() <= MAX_TENANT_ID_LEN
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Ok(Self(id))
+        } else {
+            Err(TenantError::InvalidId(id))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Directory holding the tenant's own files
+    pub fn dir(&self) -> PathBuf {
+        workspace_utils::assets::asset_dir()
+            .join("tenants")
+            .join(&self.0)
+    }
+
+    pub fn profiles_path(&self) -> PathBuf {
+        self.dir().join("profiles.json")
+    }
+
+    /// Directory holding the tenant's secrets, one file each
+    pub fn secrets_dir(&self) -> PathBuf {
+        self.dir().join("secrets")
+    }
+}
+
+impl fmt::Display for TenantId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for TenantId {
+    type Error = TenantError;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Self::new(id)
+    }
+}
+
+impl From<TenantId> for String {
+    fn from(id: TenantId) -> Self {
+        id.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_ids_are_path_safe() {
+        assert_eq!(TenantId::new("team-a_1").unwrap().as_str(), "team-a_1");
+        for invalid in ["", "../etc", "a/b", "with space", &"x".repeat(65)] {
+            assert!(TenantId::new(invalid).is_err(), "{invalid:?} was accepted");
+        }
+        assert!(serde_json::from_str::<TenantId>("\"..\"").is_err());
+    }
+}
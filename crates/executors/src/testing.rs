@@ -0,0 +1,373 @@
+//! Scriptable stand-in for a coding agent, for testing orchestration without
+//! installing or running a real agent.
+//!
+//! A [`MockExecutor`] plays a timeline of stdout/stderr writes from a real
+//! `sh` process group, so containers, runtime limits and interrupts see the
+//! same process behaviour as with an agent. Its normalizer turns stdout lines
+//! into assistant messages and stderr into error entries.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use executors::testing::{MockExecutor, MockInterrupt};
+//! let agent = MockExecutor::new()
+//!     .stdout("Reading the code\n")
+//!     .after(Duration::from_millis(200))
+//!     .stderr("warning: deprecated flag\n")
+//!     .exit_code(1)
+//!     .on_interrupt(MockInterrupt::Ignore);
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use futures::StreamExt;
+use tokio::process::Command;
+use workspace_utils::msg_store::MsgStore;
+
+use crate::{
+    env::ExecutionEnv,
+    executors::{ExecutorError, SpawnedChild, StandardCodingAgentExecutor},
+    interrupt::sigint_interrupt_sender,
+    logs::{
+        NormalizedEntry, NormalizedEntryType,
+        stderr_processor::normalize_stderr_logs,
+        utils::{EntryIndexProvider, patch::add_normalized_entry},
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockOutput {
+    Stdout(String),
+    Stderr(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockStep {
+    /// Delay before the output is written
+    pub delay: Duration,
+    pub output: MockOutput,
+}
+
+/// How the mock process reacts to SIGINT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MockInterrupt {
+    /// Exit immediately with code 130
+    #[default]
+    Exit,
+    /// Keep running, so the container has to escalate to a kill
+    Ignore,
+}
+
+/// A call the executor received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    pub prompt: String,
+    /// Set for follow-ups
+    pub session_id: Option<String>,
+    pub current_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockExecutor {
+    steps: Vec<MockStep>,
+    next_delay: Duration,
+    exit_code: i32,
+    /// Keep running after the timeline until interrupted or killed
+    hang: bool,
+    interrupt: MockInterrupt,
+    session_id: Option<String>,
+    /// Number of spawns that fail with `ExecutableNotFound` before one succeeds
+    spawn_failures: Arc<AtomicU32>,
+    calls: Arc<Mutex<Vec<MockCall>>>,
+}
+
+impl MockExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait `delay` before the next output
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.next_delay += delay;
+        self
+    }
+
+    pub fn stdout(self, text: impl Into<String>) -> Self {
+        self.push(MockOutput::Stdout(text.into()))
+    }
+
+    pub fn stderr(self, text: impl Into<String>) -> Self {
+        self.push(MockOutput::Stderr(text.into()))
+    }
+
+    fn push(mut self, output: MockOutput) -> Self {
+        self.steps.push(MockStep {
+            delay: std::mem::take(&mut self.next_delay),
+            output,
+        });
+        self
+    }
+
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    pub fn hang(mut self) -> Self {
+        self.hang = true;
+        self
+    }
+
+    pub fn on_interrupt(mut self, interrupt: MockInterrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Session id reported by the normalizer
+    pub fn session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn fail_spawns(self, count: u32) -> Self {
+        self.spawn_failures.store(count, Ordering::SeqCst);
+        self
+    }
+
+    pub fn steps(&self) -> &[MockStep] {
+        &self.steps
+    }
+
+    /// Calls received so far, shared between clones of the executor
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Shell script that plays the timeline
+    pub fn script(&self) -> String {
+        let mut script = match self.interrupt {
+            MockInterrupt::Exit => "trap 'exit 130' INT\n".to_string(),
+            MockInterrupt::Ignore => "trap '' INT\n".to_string(),
+        };
+        for step in &self.steps {
+            if !step.delay.is_zero() {
+                script.push_str(&format!("sleep {:.3}\n", step.delay.as_secs_f64()));
+            }
+            let (text, redirect) = match &step.output {
+                MockOutput::Stdout(text) => (text, ""),
+                MockOutput::Stderr(text) => (text, " >&2"),
+            };
+            script.push_str(&format!("printf '%s' {}{redirect}\n", quote(text)));
+        }
+        if self.hang {
+            // Sleep in the background so the trap runs as soon as SIGINT arrives
+            script.push_str("while :; do sleep 1 & wait $!; done\n");
+        }
+        script.push_str(&format!("exit {}\n", self.exit_code));
+        script
+    }
+
+    async fn spawn_mock(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        session_id: Option<&str>,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        self.calls.lock().unwrap().push(MockCall {
+            prompt: prompt.to_string(),
+            session_id: session_id.map(str::to_string),
+            current_dir: current_dir.to_path_buf(),
+        });
+        let failing = self
+            .spawn_failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            return Err(ExecutorError::ExecutableNotFound {
+                program: "mock-agent".to_string(),
+            });
+        }
+
+        let mut command = Command::new("sh");
+        command
+            .kill_on_drop(true)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .current_dir(current_dir)
+            .arg("-c")
+            .arg(self.script());
+        env.apply_to_command(&mut command)?;
+
+        let child = command.group_spawn()?;
+        let interrupt_sender = sigint_interrupt_sender(&child);
+        Ok(SpawnedChild {
+            child,
+            exit_signal: None,
+            interrupt_sender,
+            input_sender: None,
+        })
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[async_trait]
+impl StandardCodingAgentExecutor for MockExecutor {
+    async fn spawn(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        self.spawn_mock(current_dir, prompt, None, env).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        session_id: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        self.spawn_mock(current_dir, prompt, Some(session_id), env)
+            .await
+    }
+
+    fn normalize_logs(&self, msg_store: Arc<MsgStore>, _worktree_path: &Path) {
+        let entry_index_provider = EntryIndexProvider::start_from(&msg_store);
+        if let Some(session_id) = &self.session_id {
+            msg_store.push_session_id(session_id.clone());
+        }
+
+        let stdout_store = msg_store.clone();
+        let index_provider = entry_index_provider.clone();
+        tokio::spawn(async move {
+            let mut lines = stdout_store.stdout_lines_stream();
+            while let Some(Ok(line)) = lines.next().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                add_normalized_entry(
+                    &stdout_store,
+                    &index_provider,
+                    NormalizedEntry {
+                        timestamp: None,
+                        entry_type: NormalizedEntryType::AssistantMessage,
+                        content: line,
+                        metadata: None,
+                        agent_id: None,
+                        agent_role: None,
+                    },
+                );
+            }
+        });
+
+        normalize_stderr_logs(msg_store, entry_index_provider);
+    }
+
+    fn default_mcp_config_path(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::{
+        cancellation::CancelScope,
+        interrupt::InterruptOutcome,
+        retry::{RetryPolicy, RetryableErrorClass},
+    };
+
+    #[tokio::test]
+    async fn test_plays_timeline_and_exit_code() {
+        let agent = MockExecutor::new()
+            .stdout("it's done\n")
+            .after(Duration::from_millis(50))
+            .stderr("warning\n")
+            .exit_code(3)
+            .fail_spawns(1);
+        let dir = std::env::temp_dir();
+        let env = ExecutionEnv::default();
+
+        let policy = RetryPolicy {
+            initial_backoff_ms: 1,
+            retry_on: vec![RetryableErrorClass::ExecutableNotFound],
+            ..RetryPolicy::default()
+        };
+        let mut spawned = policy
+            .run(&CancelScope::new(), None, || {
+                agent.spawn(&dir, "fix it", &env)
+            })
+            .await
+            .unwrap();
+        assert_eq!(agent.calls().len(), 2);
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let child = spawned.child.inner();
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut stdout)
+            .await
+            .unwrap();
+        child
+            .stderr
+            .take()
+            .unwrap()
+            .read_to_string(&mut stderr)
+            .await
+            .unwrap();
+        assert_eq!(stdout, "it's done\n");
+        assert_eq!(stderr, "warning\n");
+        assert_eq!(spawned.child.wait().await.unwrap().code(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_behaviour() {
+        let dir = std::env::temp_dir();
+        let env = ExecutionEnv::default();
+
+        let mut spawned = MockExecutor::new()
+            .hang()
+            .spawn(&dir, "run", &env)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let outcome = spawned
+            .interrupt(Duration::from_secs(5), None)
+            .await
+            .unwrap();
+        assert_eq!(outcome, InterruptOutcome::Exited);
+
+        let mut spawned = MockExecutor::new()
+            .hang()
+            .on_interrupt(MockInterrupt::Ignore)
+            .spawn(&dir, "run", &env)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let outcome = spawned
+            .interrupt(Duration::from_millis(300), None)
+            .await
+            .unwrap();
+        assert_eq!(outcome, InterruptOutcome::Killed);
+    }
+}
@@ -0,0 +1,313 @@
+//! What an execution left changed in its workspace.
+//!
+//! Agents don't reliably mention every file they touch, and some edit files
+//! through shell commands the logs only show as commands. Once a run exits,
+//! [`WorkspaceChanges::collect`] asks git what is uncommitted in each of the
+//! workspace's repositories, with line counts per file, and
+//! [`WorkspaceChanges::to_normalized_entry`] turns that into a conversation
+//! entry, so consumers of the logs see the changes either way.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    logs::{NormalizedEntry, NormalizedEntryType},
+    worktree::{WorktreeError, git},
+};
+
+/// Untracked files larger than this aren't read to count their lines
+const MAX_COUNTED_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+impl FileChangeStatus {
+    fn marker(self) -> char {
+        match self {
+            Self::Added => 'A',
+            Self::Modified => 'M',
+            Self::Deleted => 'D',
+            Self::Renamed => 'R',
+            Self::Untracked => '?',
+            Self::Conflicted => 'U',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ChangedFile {
+    /// Path relative to the workspace
+    pub path: String,
+    /// Path the file was renamed from
+    pub old_path: Option<String>,
+    pub status: FileChangeStatus,
+    /// Lines added and removed; unknown for binary and very large files
+    pub additions: Option<u32>,
+    pub deletions: Option<u32>,
+}
+
+/// Uncommitted changes in a workspace once a run exited
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct WorkspaceChanges {
+    pub files: Vec<ChangedFile>,
+    pub additions: u32,
+    pub deletions: u32,
+}
+
+impl WorkspaceChanges {
+    /// Changes in the repository `worktrees` of the workspace at `root`,
+    /// with paths relative to `root`
+    pub async fn collect(root: &Path, worktrees: &[PathBuf]) -> Result<Self, WorktreeError> {
+        let mut changes = Self::default();
+        for worktree in worktrees {
+            let prefix = worktree.strip_prefix(root).unwrap_or(worktree);
+            for mut file in changed_files(worktree).await? {
+                file.path = join(prefix, &file.path);
+                file.old_path = file.old_path.map(|old| join(prefix, &old));
+                changes.additions += file.additions.unwrap_or(0);
+                changes.deletions += file.deletions.unwrap_or(0);
+                changes.files.push(file);
+            }
+        }
+        Ok(changes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Summary line in the style of `git diff --stat`
+    pub fn summary(&self) -> String {
+        let files = self.files.len();
+        format!(
+            "{files} file{} changed, {} insertion{}(+), {} deletion{}(-)",
+            if files == 1 { "" } else { "s" },
+            self.additions,
+            if self.additions == 1 { "" } else { "s" },
+            self.deletions,
+            if self.deletions == 1 { "" } else { "s" },
+        )
+    }
+
+    /// Entry listing the changes below their summary, for the conversation
+    pub fn to_normalized_entry(&self) -> NormalizedEntry {
+        let mut content = self.summary();
+        for file in &self.files {
+            content.push_str(&format!("\n{} {}", file.status.marker(), file.path));
+            if let (Some(additions), Some(deletions)) = (file.additions, file.deletions) {
+                content.push_str(&format!(" (+{additions} -{deletions})"));
+            }
+        }
+        NormalizedEntry {
+            timestamp: None,
+            entry_type: NormalizedEntryType::WorkspaceChanges {
+                changes: self.clone(),
+            },
+            content,
+            metadata: None,
+            agent_id: None,
+            agent_role: None,
+        }
+    }
+}
+
+fn join(prefix: &Path, path: &str) -> String {
+    if prefix.as_os_str().is_empty() {
+        path.to_string()
+    } else {
+        prefix.join(path).to_string_lossy().replace('\\', "/")
+    }
+}
+
+/// Uncommitted files in `worktree`, with line counts against `HEAD`
+async fn changed_files(worktree: &Path) -> Result<Vec<ChangedFile>, WorktreeError> {
+    let status = git(
+        worktree,
+        ["status", "--porcelain=v2", "-z", "--untracked-files=all"],
+    )
+    .await?;
+    let mut files = parse_status(&status);
+
+    // Fails before the first commit, which leaves counts to untracked files
+    let numstat = git(worktree, ["diff", "HEAD", "--numstat", "-z"])
+        .await
+        .unwrap_or_default();
+    for (path, additions, deletions) in parse_numstat(&numstat) {
+        if let Some(file) = files.iter_mut().find(|file| file.path == path) {
+            file.additions = additions;
+            file.deletions = deletions;
+        }
+    }
+    for file in &mut files {
+        if file.status == FileChangeStatus::Untracked {
+            file.additions = count_lines(&worktree.join(&file.path)).await;
+            file.deletions = file.additions.map(|_| 0);
+        }
+    }
+    Ok(files)
+}
+
+/// Files in `git status --porcelain=v2 -z` output
+fn parse_status(output: &str) -> Vec<ChangedFile> {
+    let mut records = output.split('\0').filter(|record| !record.is_empty());
+    let mut files = Vec::new();
+    while let Some(record) = records.next() {
+        let (status, path, old_path) = match record.as_bytes()[0] {
+            b'1' => match record.splitn(9, ' ').collect::<Vec<_>>()[..] {
+                [_, xy, .., path] => (changed_status(xy), path, None),
+                _ => continue,
+            },
+            // The original path follows renames and copies as its own record
+            b'2' => match record.splitn(10, ' ').collect::<Vec<_>>()[..] {
+                [.., path] => (FileChangeStatus::Renamed, path, records.next()),
+                _ => continue,
+            },
+            b'u' => match record.splitn(11, ' ').collect::<Vec<_>>()[..] {
+                [.., path] => (FileChangeStatus::Conflicted, path, None),
+                _ => continue,
+            },
+            b'?' => (FileChangeStatus::Untracked, &record[2..], None),
+            _ => continue,
+        };
+        files.push(ChangedFile {
+            path: path.to_string(),
+            old_path: old_path.map(str::to_string),
+            status,
+            additions: None,
+            deletions: None,
+        });
+    }
+    files
+}
+
+/// Status of an ordinary change from its staged and unstaged codes
+fn changed_status(xy: &str) -> FileChangeStatus {
+    if xy.contains('A') {
+        FileChangeStatus::Added
+    } else if xy.contains('D') {
+        FileChangeStatus::Deleted
+    } else {
+        FileChangeStatus::Modified
+    }
+}
+
+/// Path and line counts of each file in `git diff --numstat -z` output;
+/// counts are `None` for binary files
+fn parse_numstat(output: &str) -> Vec<(String, Option<u32>, Option<u32>)> {
+    let mut records = output.split('\0');
+    let mut counts = Vec::new();
+    while let Some(record) = records.next() {
+        let mut fields = record.splitn(3, '\t');
+        let (Some(additions), Some(deletions), Some(path)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        // Renames leave the path empty and list the old, then the new path
+        let path = if path.is_empty() {
+            records.next();
+            records.next().unwrap_or_default()
+        } else {
+            path
+        };
+        counts.push((
+            path.to_string(),
+            additions.parse().ok(),
+            deletions.parse().ok(),
+        ));
+    }
+    counts
+}
+
+async fn count_lines(path: &Path) -> Option<u32> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    if metadata.len() > MAX_COUNTED_FILE_SIZE {
+        return None;
+    }
+    let bytes = tokio::fs::read(path).await.ok()?;
+    // Git counts no lines for binary files either
+    let text = std::str::from_utf8(&bytes).ok()?;
+    Some(text.lines().count() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn test_collect_workspace_changes() {
+        let root = std::env::temp_dir().join(format!("vk-changes-{}", uuid::Uuid::new_v4()));
+        let repo = root.join("app");
+        std::fs::create_dir_all(&repo).unwrap();
+        run_git(&repo, &["init", "-q"]);
+        std::fs::write(repo.join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(repo.join("old name.txt"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(repo.join("gone.md"), "bye\n").unwrap();
+        run_git(&repo, &["add", "."]);
+        run_git(&repo, &["commit", "-q", "-m", "init"]);
+
+        std::fs::write(repo.join("main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        run_git(&repo, &["mv", "old name.txt", "new name.txt"]);
+        std::fs::remove_file(repo.join("gone.md")).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::write(repo.join("src/lib.rs"), "pub fn run() {}\n").unwrap();
+
+        let changes = WorkspaceChanges::collect(&root, std::slice::from_ref(&repo))
+            .await
+            .unwrap();
+        let file = |path: &str| {
+            changes
+                .files
+                .iter()
+                .find(|file| file.path == path)
+                .unwrap_or_else(|| panic!("{path} not in {changes:?}"))
+        };
+        assert_eq!(changes.files.len(), 4);
+        assert_eq!(file("app/main.rs").status, FileChangeStatus::Modified);
+        assert_eq!(file("app/main.rs").additions, Some(3));
+        assert_eq!(file("app/main.rs").deletions, Some(1));
+        let renamed = file("app/new name.txt");
+        assert_eq!(renamed.status, FileChangeStatus::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("app/old name.txt"));
+        assert_eq!(renamed.additions, Some(0));
+        assert_eq!(file("app/gone.md").status, FileChangeStatus::Deleted);
+        assert_eq!(file("app/src/lib.rs").status, FileChangeStatus::Untracked);
+        assert_eq!((changes.additions, changes.deletions), (4, 2));
+
+        let entry = changes.to_normalized_entry();
+        assert!(matches!(
+            entry.entry_type,
+            NormalizedEntryType::WorkspaceChanges { .. }
+        ));
+        assert!(
+            entry
+                .content
+                .starts_with("4 files changed, 4 insertions(+), 2 deletions(-)")
+        );
+        assert!(entry.content.contains("\nM app/main.rs (+3 -1)"));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
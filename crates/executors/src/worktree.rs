@@ -0,0 +1,220 @@
+//! Isolated git worktrees for executions.
+//!
+//! An execution that shouldn't share a checkout with anything else gets an
+//! [`AttemptWorktree`]: a git worktree of the repository on a branch of its
+//! own, created from a base branch. [`ExecutionEnv::with_worktree`] exposes
+//! the branch and worktree to the agent as `VK_*` variables, which prompt
+//! templates fill their `{{branch}}`, `{{base_branch}}` and `{{worktree}}`
+//! placeholders from. Cleaning up removes the worktree and, unless its work
+//! is kept, the branch.
+//!
+//! [`ExecutionEnv::with_worktree`]: crate::env::ExecutionEnv::with_worktree
+
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use thiserror::Error;
+use tokio::process::Command;
+use uuid::Uuid;
+use workspace_utils::{
+    shell::resolve_executable_path,
+    text::{git_branch_id, short_uuid},
+};
+
+#[derive(Debug, Error)]
+pub enum WorktreeError {
+    #[error("git executable not found")]
+    GitNotAvailable,
+    #[error("git {command} failed: {stderr}")]
+    Git { command: String, stderr: String },
+    #[error("Worktree path {0} already exists")]
+    PathExists(PathBuf),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// A worktree and branch created for one execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttemptWorktree {
+    /// Repository the worktree belongs to
+    pub repo_path: PathBuf,
+    pub path: PathBuf,
+    pub branch: String,
+    /// Branch the worktree's branch was created from
+    pub base_branch: String,
+}
+
+impl AttemptWorktree {
+    /// Branch name for an execution `id` working on `title`, e.g.
+    /// `vk/1a2b-fix-login-redire`; an empty `prefix` leaves the namespace out
+    pub fn branch_name(prefix: &str, id: &Uuid, title: &str) -> String {
+        let name = format!("{}-{}", short_uuid(id), git_branch_id(title));
+        if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        }
+    }
+
+    /// Check out a new `branch` off `base_branch` of the repository at
+    /// `repo_path` into a worktree at `path`, which must not exist yet
+    pub async fn create(
+        repo_path: &Path,
+        path: &Path,
+        branch: &str,
+        base_branch: &str,
+    ) -> Result<Self, WorktreeError> {
+        if path.exists() {
+            return Err(WorktreeError::PathExists(path.to_path_buf()));
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        git(
+            repo_path,
+            [
+                OsStr::new("worktree"),
+                OsStr::new("add"),
+                OsStr::new("-b"),
+                OsStr::new(branch),
+                path.as_os_str(),
+                OsStr::new(base_branch),
+            ],
+        )
+        .await?;
+        Ok(Self {
+            repo_path: repo_path.to_path_buf(),
+            path: path.to_path_buf(),
+            branch: branch.to_string(),
+            base_branch: base_branch.to_string(),
+        })
+    }
+
+    /// Remove the worktree, discarding uncommitted changes, and delete its
+    /// branch unless `keep_branch`. A worktree removed by other means is
+    /// only pruned from the repository's records.
+    pub async fn cleanup(&self, keep_branch: bool) -> Result<(), WorktreeError> {
+        if self.path.exists() {
+            git(
+                &self.repo_path,
+                [
+                    OsStr::new("worktree"),
+                    OsStr::new("remove"),
+                    OsStr::new("--force"),
+                    self.path.as_os_str(),
+                ],
+            )
+            .await?;
+        }
+        git(&self.repo_path, ["worktree", "prune"]).await?;
+        if !keep_branch {
+            git(&self.repo_path, ["branch", "-D", self.branch.as_str()]).await?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) async fn git<I, S>(repo_path: &Path, args: I) -> Result<String, WorktreeError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let git = resolve_executable_path("git")
+        .await
+        .ok_or(WorktreeError::GitNotAvailable)?;
+    let args: Vec<_> = args
+        .into_iter()
+        .map(|a| a.as_ref().to_os_string())
+        .collect();
+    let output = Command::new(git)
+        .arg("-C")
+        .arg(repo_path)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(WorktreeError::Git {
+            command: args
+                .iter()
+                .take(2)
+                .map(|a| a.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::ExecutionEnv, prompt::PromptTemplate};
+
+    fn run_git(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_worktree_lifecycle() {
+        let root = std::env::temp_dir().join(format!("vk-worktree-{}", Uuid::new_v4()));
+        let repo = root.join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        run_git(&repo, &["init", "-q", "-b", "main"]);
+        std::fs::write(repo.join("README.md"), "hello\n").unwrap();
+        run_git(&repo, &["add", "."]);
+        run_git(&repo, &["commit", "-q", "-m", "init"]);
+
+        let id = Uuid::parse_str("1a2b3c4d-0000-0000-0000-000000000000").unwrap();
+        let branch = AttemptWorktree::branch_name("vk", &id, "Fix the login redirect!");
+        assert_eq!(branch, "vk/1a2b-fix-the-login-re");
+
+        let path = root.join("worktrees").join("attempt");
+        let worktree = AttemptWorktree::create(&repo, &path, &branch, "main")
+            .await
+            .unwrap();
+        assert!(path.join("README.md").exists());
+        assert_eq!(run_git(&path, &["branch", "--show-current"]), branch);
+        assert!(matches!(
+            AttemptWorktree::create(&repo, &path, "other", "main").await,
+            Err(WorktreeError::PathExists(_))
+        ));
+
+        let env = ExecutionEnv::new().with_worktree(&worktree);
+        let template = PromptTemplate {
+            prepend: Some("Work on {{branch}} (from {{base_branch}}) in {{worktree}}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            template.frame("go", &env),
+            format!(
+                "Work on {branch} (from main) in {}\n\ngo",
+                path.to_string_lossy()
+            )
+        );
+
+        // Uncommitted work goes with the worktree; the branch may stay
+        std::fs::write(path.join("scratch.txt"), "wip").unwrap();
+        worktree.cleanup(true).await.unwrap();
+        assert!(!path.exists());
+        assert_eq!(run_git(&repo, &["branch", "--list", &branch]), branch);
+        worktree.cleanup(false).await.unwrap();
+        assert!(run_git(&repo, &["branch", "--list", &branch]).is_empty());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
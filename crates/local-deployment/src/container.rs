@@ -31,13 +31,47 @@ use executors::{
     actions::{
         Executable, ExecutorAction, ExecutorActionType,
         coding_agent_follow_up::CodingAgentFollowUpRequest,
-        coding_agent_initial::CodingAgentInitialRequest,
+        coding_agent_initial::CodingAgentInitialRequest, script::ScriptContext,
     },
-    approvals::{ExecutorApprovalService, NoopExecutorApprovalService},
+    approvals::{
+        ExecutorApprovalService, NoopExecutorApprovalService,
+        audit::{ApprovalAuditLog, ApprovalDecider, AuditedApprovalService},
+    },
+    auto_commit,
+    cancellation::{CancelReason, CancelScope},
+    checkpoint::{CheckpointingApprovalService, GitCheckpoints},
+    encryption,
     env::ExecutionEnv,
-    executors::{BaseCodingAgent, ExecutorExitResult, ExecutorExitSignal, InterruptSender},
-    logs::{NormalizedEntryType, utils::patch::extract_normalized_entry_from_patch},
-    profile::ExecutorProfileId,
+    executors::{
+        BaseAgentCapability, BaseCodingAgent, ExecutorExitResult, ExecutorExitSignal,
+        InterruptSender, UserInputSender,
+    },
+    governor::SpawnPermit,
+    heartbeat::{spawn_heartbeats, stall_threshold},
+    interrupt::{DEFAULT_INTERRUPT_GRACE, interrupt_child, push_cancellation_entry},
+    log_tee::RawLogs,
+    logs::{
+        NormalizedEntryType,
+        stderr_processor::ExecutorFailureReason,
+        summary::{ExecutionSummary, FollowUpHook},
+        utils::{
+            ConversationPatch, EntryIndexProvider, patch::extract_normalized_entry_from_patch,
+        },
+    },
+    loop_detection::{LoopAction, LoopDetection, loop_detection, watch_loops},
+    mcp_config::McpConfigScope,
+    orphans::ChildRegistry,
+    outcome::RunSummary,
+    output_decoder::decode_output,
+    profile::{ExecutorConfigs, ExecutorProfileId},
+    rate_limit::{self, AgentProvider},
+    resource_limits::{ResourceLimits, ResourceViolation},
+    review,
+    run_hooks::{HookRunner, HookStage},
+    runtime::ExecutorsRuntime,
+    telemetry::{FailureClass, RunOutcome, Telemetry},
+    tenant::TenantId,
+    workspace_changes::WorkspaceChanges,
 };
 use futures::{FutureExt, TryStreamExt, stream::select};
 use serde_json::json;
@@ -45,7 +79,10 @@ use services::services::{
     analytics::AnalyticsContext,
     approvals::{Approvals, executor_approvals::ExecutorApprovalBridge},
     config::Config,
-    container::{ContainerError, ContainerRef, ContainerService},
+    container::{
+        ContainerError, ContainerRef, ContainerService, persisted_msg_store_dir,
+        prune_persisted_msg_stores,
+    },
     diff_stream::{self, DiffStreamHandle},
     git::{GitCli, GitService},
     image::ImageService,
@@ -58,7 +95,7 @@ use tokio::{sync::RwLock, task::JoinHandle};
 use tokio_util::io::ReaderStream;
 use utils::{
     log_msg::LogMsg,
-    msg_store::MsgStore,
+    msg_store::{ForwardBuffer, MsgStore},
     text::{git_branch_id, short_uuid, truncate_to_char_boundary},
 };
 use uuid::Uuid;
@@ -70,6 +107,9 @@ pub struct LocalContainerService {
     db: DBService,
     child_store: Arc<RwLock<HashMap<Uuid, Arc<RwLock<AsyncGroupChild>>>>>,
     interrupt_senders: Arc<RwLock<HashMap<Uuid, InterruptSender>>>,
+    input_senders: Arc<RwLock<HashMap<Uuid, UserInputSender>>>,
+    spawn_permits: Arc<RwLock<HashMap<Uuid, SpawnPermit>>>,
+    post_run_hooks: Arc<RwLock<HashMap<Uuid, HookRunner>>>,
     msg_stores: Arc<RwLock<HashMap<Uuid, Arc<MsgStore>>>>,
     config: Arc<RwLock<Config>>,
     git: GitService,
@@ -79,6 +119,8 @@ pub struct LocalContainerService {
     queued_message_service: QueuedMessageService,
     publisher: Result<SharePublisher, RemoteClientNotConfigured>,
     notification_service: NotificationService,
+    follow_up_hook: Option<Arc<dyn FollowUpHook>>,
+    runtime: Arc<ExecutorsRuntime>,
 }
 
 impl LocalContainerService {
@@ -102,6 +144,9 @@ impl LocalContainerService {
             db,
             child_store,
             interrupt_senders,
+            input_senders: Arc::new(RwLock::new(HashMap::new())),
+            spawn_permits: Arc::new(RwLock::new(HashMap::new())),
+            post_run_hooks: Arc::new(RwLock::new(HashMap::new())),
             msg_stores,
             config,
             git,
@@ -111,6 +156,8 @@ impl LocalContainerService {
             queued_message_service,
             publisher,
             notification_service,
+            follow_up_hook: None,
+            runtime: Arc::new(ExecutorsRuntime::new()),
         };
 
         container.spawn_workspace_cleanup();
@@ -118,12 +165,19 @@ impl LocalContainerService {
         container
     }
 
+    /// Register a hook that receives TODO/FIXME follow-ups left behind by coding agent runs
+    pub fn with_follow_up_hook(mut self, hook: Arc<dyn FollowUpHook>) -> Self {
+        self.follow_up_hook = Some(hook);
+        self
+    }
+
     pub async fn get_child_from_store(&self, id: &Uuid) -> Option<Arc<RwLock<AsyncGroupChild>>> {
         let map = self.child_store.read().await;
         map.get(id).cloned()
     }
 
     pub async fn add_child_to_store(&self, id: Uuid, exec: AsyncGroupChild) {
+        ChildRegistry::global().register(id, &exec);
         let mut map = self.child_store.write().await;
         map.insert(id, Arc::new(RwLock::new(exec)));
     }
@@ -131,6 +185,7 @@ impl LocalContainerService {
     pub async fn remove_child_from_store(&self, id: &Uuid) {
         let mut map = self.child_store.write().await;
         map.remove(id);
+        ChildRegistry::global().unregister(id);
     }
 
     async fn add_interrupt_sender(&self, id: Uuid, sender: InterruptSender) {
@@ -143,6 +198,16 @@ impl LocalContainerService {
         map.remove(id)
     }
 
+    async fn add_input_sender(&self, id: Uuid, sender: UserInputSender) {
+        let mut map = self.input_senders.write().await;
+        map.insert(id, sender);
+    }
+
+    async fn remove_input_sender(&self, id: &Uuid) {
+        let mut map = self.input_senders.write().await;
+        map.remove(id);
+    }
+
     pub async fn cleanup_workspace(db: &DBService, workspace: &Workspace) {
         let Some(container_ref) = &workspace.container_ref else {
             return;
@@ -209,6 +274,12 @@ impl LocalContainerService {
                 cleanup_expired(&db).await.unwrap_or_else(|e| {
                     tracing::error!("Failed to clean up expired workspaces: {}", e)
                 });
+                match tokio::task::spawn_blocking(prune_persisted_msg_stores).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(pruned)) => tracing::info!("Pruned {} persisted log stores", pruned),
+                    Ok(Err(e)) => tracing::error!("Failed to prune persisted log stores: {}", e),
+                    Err(e) => tracing::error!("Failed to prune persisted log stores: {}", e),
+                }
             }
         });
     }
@@ -238,14 +309,35 @@ impl LocalContainerService {
     async fn get_commit_message(&self, ctx: &ExecutionContext) -> String {
         match ctx.execution_process.run_reason {
             ExecutionProcessRunReason::CodingAgent => {
-                // Try to retrieve the task summary from the coding agent turn
-                // otherwise fallback to default message
-                match CodingAgentTurn::find_by_execution_process_id(
+                let turn = CodingAgentTurn::find_by_execution_process_id(
                     &self.db().pool,
                     ctx.execution_process.id,
                 )
-                .await
+                .await;
+
+                // Profiles with auto commit word the message from the agent's
+                // report, or the task, and credit the agent
+                if let Ok(action) = ctx.execution_process.executor_action()
+                    && let Some(profile_id) = action.executor_profile_id()
+                    && ExecutorConfigs::get_cached_for(action.tenant.as_ref())
+                        .get_coding_agent(profile_id)
+                        .is_some_and(|agent| agent.run_overrides().auto_commit == Some(true))
                 {
+                    let summary = turn
+                        .as_ref()
+                        .ok()
+                        .and_then(|turn| turn.as_ref()?.summary.clone());
+                    return auto_commit::commit_message(
+                        summary.as_deref(),
+                        &ctx.task.title,
+                        ctx.task.description.as_deref(),
+                        profile_id.executor,
+                    );
+                }
+
+                // Try to retrieve the task summary from the coding agent turn
+                // otherwise fallback to default message
+                match turn {
                     Ok(Some(turn)) if turn.summary.is_some() => turn.summary.unwrap(),
                     Ok(_) => {
                         tracing::debug!(
@@ -340,6 +432,54 @@ impl LocalContainerService {
         any_committed
     }
 
+    /// Act on the loops the normalization pipeline reports for an execution,
+    /// per its profile's loop detection settings
+    fn spawn_loop_watchdog(
+        &self,
+        exec_id: Uuid,
+        msg_store: &MsgStore,
+        loop_detection: LoopDetection,
+    ) -> JoinHandle<()> {
+        let container = self.clone();
+        let mut loops = watch_loops(msg_store);
+        tokio::spawn(async move {
+            while let Some(detected) = loops.recv().await {
+                tracing::info!(
+                    "Execution {} repeated {} {} times",
+                    exec_id,
+                    detected.tool_name.as_deref().unwrap_or("a message"),
+                    detected.repeats
+                );
+                match loop_detection.action {
+                    LoopAction::Warn => {}
+                    LoopAction::Interrupt => {
+                        // The exit monitor stops the process
+                        container
+                            .runtime
+                            .cancel_tree()
+                            .cancel_execution(&exec_id, CancelReason::LoopDetected);
+                        break;
+                    }
+                    LoopAction::Correct => {
+                        let correction = loop_detection.correction().to_string();
+                        let sent = container
+                            .input_senders
+                            .read()
+                            .await
+                            .get(&exec_id)
+                            .is_some_and(|sender| sender.send(correction).is_ok());
+                        if !sent {
+                            tracing::info!(
+                                "Execution {} takes no input, so its loop was only reported",
+                                exec_id
+                            );
+                        }
+                    }
+                }
+            }
+        })
+    }
+
     /// Spawn a background task that polls the child process for completion and
     /// cleans up the execution entry when it exits.
     pub fn spawn_exit_monitor(
@@ -357,39 +497,135 @@ impl LocalContainerService {
         let publisher = self.publisher.clone();
 
         let mut process_exit_rx = self.spawn_os_exit_watcher(exec_id);
+        let cancel = self
+            .runtime
+            .cancel_tree()
+            .execution(&exec_id)
+            .unwrap_or_default();
 
         tokio::spawn(async move {
             let mut exit_signal_future = exit_signal
                 .map(|rx| rx.boxed()) // wait for result
                 .unwrap_or_else(|| std::future::pending().boxed()); // no signal, stall forever
 
-            let status_result: std::io::Result<std::process::ExitStatus>;
+            let mut status_result: std::io::Result<std::process::ExitStatus>;
+            let mut reported_result: Option<ExecutorExitResult> = None;
+            let mut cancel_handled = false;
 
             // Wait for process to exit, or exit signal from executor
-            tokio::select! {
-                // Exit signal with result.
-                // Some coding agent processes do not automatically exit after processing the user request; instead the executor
-                // signals when processing has finished to gracefully kill the process.
-                exit_result = &mut exit_signal_future => {
-                    // Executor signaled completion: kill group and use the provided result
-                    if let Some(child_lock) = child_store.read().await.get(&exec_id).cloned() {
-                        let mut child = child_lock.write().await ;
-                        if let Err(err) = command::kill_process_group(&mut child).await {
-                            tracing::error!("Failed to kill process group after exit signal: {} {}", exec_id, err);
+            loop {
+                tokio::select! {
+                    // Exit signal with result.
+                    // Some coding agent processes do not automatically exit after processing the user request; instead the executor
+                    // signals when processing has finished to gracefully kill the process.
+                    exit_result = &mut exit_signal_future => {
+                        // A dropped signal reports nothing; the process's own
+                        // exit decides how the run ended
+                        let Ok(exit_result) = exit_result else {
+                            exit_signal_future = std::future::pending().boxed();
+                            continue;
+                        };
+                        // Executor signaled completion: kill group and use the provided result
+                        if let Some(child_lock) = child_store.read().await.get(&exec_id).cloned() {
+                            let mut child = child_lock.write().await ;
+                            if let Err(err) = command::kill_process_group(&mut child).await {
+                                tracing::error!("Failed to kill process group after exit signal: {} {}", exec_id, err);
+                            }
                         }
-                    }
 
-                    // Map the exit result to appropriate exit status
-                    status_result = match exit_result {
-                        Ok(ExecutorExitResult::Success) => Ok(success_exit_status()),
-                        Ok(ExecutorExitResult::Failure) => Ok(failure_exit_status()),
-                        Err(_) => Ok(success_exit_status()), // Channel closed, assume success
-                    };
+                        tracing::debug!("Execution {} reported {:?}", exec_id, exit_result);
+                        if matches!(exit_result, ExecutorExitResult::TimedOut(_)) {
+                            container.runtime.cancel_tree().cancel_execution(&exec_id, CancelReason::Timeout);
+                        }
+                        if let Some(entry) = exit_result.stopped_entry()
+                            && let Some(msg_store) = msg_stores.read().await.get(&exec_id)
+                        {
+                            let index_provider = EntryIndexProvider::start_from(msg_store);
+                            msg_store.push_patch(ConversationPatch::add_normalized_entry(
+                                index_provider.next(),
+                                entry,
+                            ));
+                        }
+                        status_result = Ok(if exit_result.is_success() {
+                            success_exit_status()
+                        } else {
+                            failure_exit_status()
+                        });
+                        reported_result = Some(exit_result);
+                        break;
+                    }
+                    // Process exit
+                    exit_status_result = &mut process_exit_rx => {
+                        status_result = exit_status_result.unwrap_or_else(|e| Err(std::io::Error::other(e)));
+                        // The exit status and stderr give the result of runs
+                        // whose executor didn't report one
+                        if let Ok(exit_status) = &status_result {
+                            let stderr_reason = match msg_stores.read().await.get(&exec_id) {
+                                Some(msg_store) if !exit_status.success() => {
+                                    ExecutorFailureReason::from_history(&msg_store.get_history())
+                                }
+                                _ => None,
+                            };
+                            reported_result =
+                                Some(ExecutorExitResult::from_exit_status(*exit_status, stderr_reason));
+                        }
+                        break;
+                    }
+                    // Cancelled with its workspace or on shutdown. Stops requested
+                    // through stop_execution have already interrupted the process.
+                    _ = cancel.cancelled(), if !cancel_handled => {
+                        cancel_handled = true;
+                        if !ExecutionProcess::was_stopped(&db.pool, exec_id).await
+                            && let Ok(Some(process)) = ExecutionProcess::find_by_id(&db.pool, exec_id).await
+                            && let Err(e) = container.stop_execution(&process, ExecutionProcessStatus::Killed).await
+                        {
+                            tracing::error!("Failed to stop cancelled execution {}: {}", exec_id, e);
+                        }
+                    }
                 }
-                // Process exit
-                exit_status_result = &mut process_exit_rx => {
-                    status_result = exit_status_result.unwrap_or_else(|e| Err(std::io::Error::other(e)));
+            }
+
+            // Lets a runtime limits watchdog still holding the signal stop
+            drop(exit_signal_future);
+
+            // The session no longer reads input, and its slot is free
+            container.remove_input_sender(&exec_id).await;
+            container.spawn_permits.write().await.remove(&exec_id);
+
+            // Post-run hooks check what a successful run left behind; one that
+            // aborts fails the run
+            let post_run = container.post_run_hooks.write().await.remove(&exec_id);
+            let msg_store = msg_stores.read().await.get(&exec_id).cloned();
+            if let Some(post_run) = post_run
+                && let Some(msg_store) = msg_store
+                && matches!(&status_result, Ok(exit_status) if exit_status.success())
+                && !cancel.is_cancelled()
+                && let Err(e) = post_run.run(&msg_store, &cancel).await
+            {
+                tracing::info!("Execution {}: {}", exec_id, e);
+                status_result = Ok(failure_exit_status());
+            }
+
+            // A failed run may have been stopped by its resource limits
+            let cgroup_dir = ResourceLimits::cgroup_dir(exec_id);
+            let violation = match &status_result {
+                Ok(exit_status) if !exit_status.success() => {
+                    ResourceViolation::detect(Some(exit_status), cgroup_dir.as_deref())
                 }
+                _ => None,
+            };
+            if let Some(dir) = &cgroup_dir {
+                ResourceLimits::remove_cgroup(dir);
+            }
+            if let Some(violation) = violation
+                && let Some(msg_store) = msg_stores.read().await.get(&exec_id)
+            {
+                tracing::info!("Execution {}: {}", exec_id, violation);
+                let index_provider = EntryIndexProvider::start_from(msg_store);
+                msg_store.push_patch(ConversationPatch::add_normalized_entry(
+                    index_provider.next(),
+                    violation.to_normalized_entry(),
+                ));
             }
 
             let (exit_code, status) = match status_result {
@@ -412,17 +648,119 @@ impl LocalContainerService {
                 tracing::error!("Failed to update execution process completion: {}", e);
             }
 
+            let cancel_reason = container
+                .runtime
+                .cancel_tree()
+                .execution(&exec_id)
+                .and_then(|scope| scope.reason());
+
             if let Ok(ctx) = ExecutionProcess::load_context(&db.pool, exec_id).await {
                 // Update executor session summary if available
-                if let Err(e) = container.update_executor_session_summary(&exec_id).await {
+                if let Err(e) = container
+                    .update_executor_session_summary(
+                        &exec_id,
+                        reported_result
+                            .as_ref()
+                            .and_then(ExecutorExitResult::summary),
+                    )
+                    .await
+                {
                     tracing::warn!("Failed to update executor session summary: {}", e);
                 }
 
+                // Record what the agent left changed before it gets committed,
+                // whether or not its logs mention the edits
+                if matches!(
+                    ctx.execution_process.run_reason,
+                    ExecutionProcessRunReason::CodingAgent
+                ) && let Some(container_ref) = &ctx.workspace.container_ref
+                {
+                    let root = PathBuf::from(container_ref);
+                    let worktrees: Vec<_> =
+                        ctx.repos.iter().map(|repo| root.join(&repo.name)).collect();
+                    match WorkspaceChanges::collect(&root, &worktrees).await {
+                        Ok(changes) if !changes.is_empty() => {
+                            if let Some(msg_store) = msg_stores.read().await.get(&exec_id) {
+                                let index_provider = EntryIndexProvider::start_from(msg_store);
+                                msg_store.push_patch(ConversationPatch::add_normalized_entry(
+                                    index_provider.next(),
+                                    changes.to_normalized_entry(),
+                                ));
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            tracing::warn!("Failed to collect workspace changes: {}", e);
+                        }
+                    }
+                }
+
+                // A reviewer's findings become entries of their own
+                if let Ok(action) = ctx.execution_process.executor_action()
+                    && let ExecutorActionType::CodingAgentInitialRequest(request) = action.typ()
+                    && request.review.is_some()
+                    && let Some(msg_store) = msg_stores.read().await.get(&exec_id)
+                {
+                    let findings = review::findings_from_history(&msg_store.get_history());
+                    let index_provider = EntryIndexProvider::start_from(msg_store);
+                    for finding in findings {
+                        msg_store.push_patch(ConversationPatch::add_normalized_entry(
+                            index_provider.next(),
+                            finding.to_normalized_entry(),
+                        ));
+                    }
+                }
+
                 let success = matches!(
                     ctx.execution_process.status,
                     ExecutionProcessStatus::Completed
                 ) && exit_code == Some(0);
 
+                // Why a coding agent failed, as far as its resource limits,
+                // its own report or its stderr tell
+                let failure_reason = if !success
+                    && cancel_reason.is_none()
+                    && matches!(
+                        ctx.execution_process.run_reason,
+                        ExecutionProcessRunReason::CodingAgent
+                    )
+                    && matches!(ctx.execution_process.status, ExecutionProcessStatus::Failed)
+                {
+                    match (violation, msg_stores.read().await.get(&exec_id)) {
+                        (Some(violation), _) => Some(violation.failure_reason()),
+                        (None, msg_store) => reported_result
+                            .as_ref()
+                            .and_then(ExecutorExitResult::failure_reason)
+                            .or_else(|| {
+                                msg_store.and_then(|msg_store| {
+                                    ExecutorFailureReason::from_history(&msg_store.get_history())
+                                })
+                            }),
+                    }
+                } else {
+                    None
+                };
+
+                // Spawns on the same provider wait out a rate limit, until a
+                // run gets through again
+                if let Some(executor) = ctx
+                    .execution_process
+                    .executor_action()
+                    .ok()
+                    .and_then(|action| action.base_executor())
+                {
+                    let rate_limiter = container.runtime.rate_limiter();
+                    if failure_reason == Some(ExecutorFailureReason::RateLimited) {
+                        let retry_after = match msg_stores.read().await.get(&exec_id) {
+                            Some(msg_store) => rate_limit::retry_after(&msg_store.get_history()),
+                            None => None,
+                        };
+                        rate_limiter.record_rate_limit(executor.into(), retry_after);
+                    } else if success {
+                        rate_limiter.record_success(executor.into());
+                    }
+                }
+
                 let cleanup_done = matches!(
                     ctx.execution_process.run_reason,
                     ExecutionProcessRunReason::CleanupScript
@@ -446,12 +784,38 @@ impl LocalContainerService {
                         ctx.execution_process.run_reason,
                         ExecutionProcessRunReason::CodingAgent
                     ) {
+                        // Cleanup is pointless without changes, but other
+                        // actions chained after this one, e.g. the prompt
+                        // retried after compacting the session or a test
+                        // script, still have to start
                         changes_committed
+                            || ctx
+                                .execution_process
+                                .executor_action()
+                                .ok()
+                                .and_then(|action| action.next_action_after(true))
+                                .is_some_and(|next| {
+                                    !matches!(
+                                        next.typ(),
+                                        ExecutorActionType::ScriptRequest(script)
+                                            if script.context == ScriptContext::CleanupScript
+                                    )
+                                })
                     } else {
                         true
                     };
 
-                    if should_start_next {
+                    if let Some(reason) = cancel_reason {
+                        // Nothing chained from a cancelled execution is started
+                        tracing::info!(
+                            "Not starting next action for workspace {}: {}",
+                            ctx.workspace.id,
+                            reason
+                        );
+                        if !container.should_finalize(&ctx) {
+                            container.finalize_task(publisher.as_ref().ok(), &ctx).await;
+                        }
+                    } else if should_start_next {
                         // If the process exited successfully, start the next action
                         if let Err(e) = container.try_start_next_action(&ctx).await {
                             tracing::error!("Failed to start next action after completion: {}", e);
@@ -467,13 +831,38 @@ impl LocalContainerService {
                     }
                 }
 
-                if container.should_finalize(&ctx) {
+                // A retried run finalizes the task once it finishes itself
+                let retried = match failure_reason {
+                    Some(reason) => container.retry_failed_execution(&ctx, reason).await,
+                    None => false,
+                };
+
+                // Pipelines may chain actions that only run after a failure,
+                // e.g. an agent fixing failing tests. Chains built for every
+                // run have none: their steps follow a success, and cleanup
+                // scripts are never started from a failed run
+                if !success
+                    && !cleanup_done
+                    && !retried
+                    && cancel_reason.is_none()
+                    && matches!(ctx.execution_process.status, ExecutionProcessStatus::Failed)
+                    && ctx
+                        .execution_process
+                        .executor_action()
+                        .is_ok_and(|action| action.next_action_on_exit(false).is_some())
+                    && let Err(e) = container.try_start_next_action(&ctx).await
+                {
+                    tracing::error!("Failed to start next action after failure: {}", e);
+                }
+
+                if !retried && container.should_finalize(&ctx) {
                     // Only execute queued messages if the execution succeeded
-                    // If it failed or was killed, just clear the queue and finalize
-                    let should_execute_queued = !matches!(
-                        ctx.execution_process.status,
-                        ExecutionProcessStatus::Failed | ExecutionProcessStatus::Killed
-                    );
+                    // If it failed, was killed or cancelled, just clear the queue and finalize
+                    let should_execute_queued = cancel_reason.is_none()
+                        && !matches!(
+                            ctx.execution_process.status,
+                            ExecutionProcessStatus::Failed | ExecutionProcessStatus::Killed
+                        );
 
                     if let Some(queued_msg) =
                         container.queued_message_service.take_queued(ctx.session.id)
@@ -508,9 +897,14 @@ impl LocalContainerService {
                                 container.finalize_task(publisher.as_ref().ok(), &ctx).await;
                             }
                         } else {
-                            // Execution failed or was killed - discard the queued message and finalize
+                            // Execution failed or was killed - discard the queued messages and finalize
+                            let discarded = 1 + container
+                                .queued_message_service
+                                .cancel_queued(ctx.session.id)
+                                .len();
                             tracing::info!(
-                                "Discarding queued message for session {} due to execution status {:?}",
+                                "Discarding {} queued messages for session {} due to execution status {:?}",
+                                discarded,
                                 ctx.session.id,
                                 ctx.execution_process.status
                             );
@@ -521,6 +915,39 @@ impl LocalContainerService {
                     }
                 }
 
+                if matches!(
+                    &ctx.execution_process.run_reason,
+                    ExecutionProcessRunReason::CodingAgent
+                ) && let Ok(action) = ctx.execution_process.executor_action()
+                    && let Some(executor) = action.base_executor()
+                {
+                    let outcome = match (cancel_reason, &ctx.execution_process.status) {
+                        (Some(reason), _) => RunOutcome::Cancelled(reason),
+                        _ if success => RunOutcome::Succeeded,
+                        (None, ExecutionProcessStatus::Killed) => {
+                            RunOutcome::Failed(FailureClass::Killed)
+                        }
+                        (None, _) => RunOutcome::Failed(
+                            failure_reason
+                                .map(FailureClass::from)
+                                .unwrap_or(FailureClass::NonZeroExit),
+                        ),
+                    };
+                    let duration = ctx
+                        .execution_process
+                        .completed_at
+                        .and_then(|completed| {
+                            (completed - ctx.execution_process.started_at).to_std().ok()
+                        })
+                        .unwrap_or_default();
+                    Telemetry::global().record_run(
+                        executor,
+                        action.tenant.as_ref(),
+                        duration,
+                        outcome,
+                    );
+                }
+
                 // Fire analytics event when CodingAgent execution has finished
                 if config.read().await.analytics_enabled
                     && matches!(
@@ -558,8 +985,9 @@ impl LocalContainerService {
                 }
             }
 
-            // Cleanup child handle
-            child_store.write().await.remove(&exec_id);
+            // Cleanup child handle; its group is no one's to kill any more
+            container.remove_child_from_store(&exec_id).await;
+            container.runtime.cancel_tree().finish_execution(&exec_id);
         })
     }
 
@@ -605,28 +1033,63 @@ impl LocalContainerService {
         format!("{}-{}", short_uuid(workspace_id), task_title_id)
     }
 
-    async fn track_child_msgs_in_store(&self, id: Uuid, child: &mut AsyncGroupChild) {
-        let store = Arc::new(MsgStore::new());
+    async fn track_child_msgs_in_store(
+        &self,
+        id: Uuid,
+        child: &mut AsyncGroupChild,
+        raw_logs: Option<RawLogs>,
+    ) {
+        let store = self.msg_store_for(id).await;
 
         let out = child.inner().stdout.take().expect("no stdout");
         let err = child.inner().stderr.take().expect("no stderr");
 
+        // The profile's raw logs get the bytes before binary output is replaced
+        let (mut out_log, mut err_log) = match raw_logs {
+            Some(RawLogs { stdout, stderr }) => (Some(stdout), Some(stderr)),
+            None => (None, None),
+        };
+
         // Map stdout bytes -> LogMsg::Stdout
-        let out = ReaderStream::new(out)
-            .map_ok(|chunk| LogMsg::Stdout(String::from_utf8_lossy(&chunk).into_owned()));
+        let out = decode_output(ReaderStream::new(out).inspect_ok(move |chunk| {
+            if let Some(log) = &mut out_log {
+                log.append(chunk);
+            }
+        }))
+        .map_ok(LogMsg::Stdout);
 
         // Map stderr bytes -> LogMsg::Stderr
-        let err = ReaderStream::new(err)
-            .map_ok(|chunk| LogMsg::Stderr(String::from_utf8_lossy(&chunk).into_owned()));
+        let err = decode_output(ReaderStream::new(err).inspect_ok(move |chunk| {
+            if let Some(log) = &mut err_log {
+                log.append(chunk);
+            }
+        }))
+        .map_ok(LogMsg::Stderr);
 
         // If you have a JSON Patch source, map it to LogMsg::JsonPatch too, then select all three.
 
-        // Merge and forward into the store
+        // Merge and forward into the store, reading ahead of it only so far
         let merged = select(out, err); // Stream<Item = Result<LogMsg, io::Error>>
-        store.clone().spawn_forwarder(merged);
+        store.spawn_buffered_forwarder(merged, ForwardBuffer::default());
+    }
 
-        let mut map = self.msg_stores().write().await;
-        map.insert(id, store);
+    /// Store of the execution `id`'s logs, created on first use. Pre-run
+    /// hooks log into it before the agent's output is tracked.
+    async fn msg_store_for(&self, id: Uuid) -> Arc<MsgStore> {
+        self.msg_stores()
+            .write()
+            .await
+            .entry(id)
+            .or_insert_with(|| {
+                Arc::new(
+                    MsgStore::persistent(persisted_msg_store_dir(&id), encryption::LINE_CODEC)
+                        .unwrap_or_else(|e| {
+                            tracing::warn!("Not persisting logs of execution {id}: {e}");
+                            MsgStore::new()
+                        }),
+                )
+            })
+            .clone()
     }
 
     /// Create a live diff log stream for ongoing attempts for WebSocket
@@ -672,19 +1135,51 @@ impl LocalContainerService {
     }
 
     /// Update the coding agent turn summary with the final assistant message
-    async fn update_executor_session_summary(&self, exec_id: &Uuid) -> Result<(), anyhow::Error> {
+    async fn update_executor_session_summary(
+        &self,
+        exec_id: &Uuid,
+        reported: Option<&RunSummary>,
+    ) -> Result<(), anyhow::Error> {
         // Check if there's a coding agent turn for this execution process
         let turn = CodingAgentTurn::find_by_execution_process_id(&self.db.pool, *exec_id).await?;
 
         if let Some(turn) = turn {
-            // Only update if summary is not already set
+            // Only update if summary is not already set; a summary the agent
+            // reported itself beats its last message
             if turn.summary.is_none() {
-                if let Some(summary) = self.extract_last_assistant_message(exec_id) {
+                if let Some(summary) = reported
+                    .and_then(|reported| reported.summary.clone())
+                    .or_else(|| self.extract_last_assistant_message(exec_id))
+                {
                     CodingAgentTurn::update_summary(&self.db.pool, *exec_id, &summary).await?;
                 } else {
                     tracing::debug!("No assistant message found for execution {}", exec_id);
                 }
             }
+
+            if let Some(hook) = &self.follow_up_hook {
+                let msg_store = self.msg_stores.read().await.get(exec_id).cloned();
+                if let Some(msg_store) = msg_store {
+                    let summary = ExecutionSummary {
+                        cancel_reason: self
+                            .runtime
+                            .cancel_tree()
+                            .execution(exec_id)
+                            .and_then(|scope| scope.reason()),
+                        ..ExecutionSummary::from_msg_store(&msg_store)
+                    };
+                    if let Some(reason) = summary.cancel_reason {
+                        tracing::debug!(
+                            "Not reporting follow-up items for execution {}: {}",
+                            exec_id,
+                            reason
+                        );
+                    } else if !summary.follow_up_items.is_empty() {
+                        hook.on_follow_up_items(*exec_id, &summary.follow_up_items)
+                            .await;
+                    }
+                }
+            }
         }
 
         Ok(())
@@ -845,12 +1340,19 @@ impl LocalContainerService {
                 session_id: agent_session_id,
                 executor_profile_id: executor_profile_id.clone(),
                 working_dir: working_dir.clone(),
+                cwd_relative: None,
+                fork: false,
+                attachments: Vec::new(),
             })
         } else {
             ExecutorActionType::CodingAgentInitialRequest(CodingAgentInitialRequest {
                 prompt: queued_data.message.clone(),
                 executor_profile_id: executor_profile_id.clone(),
                 working_dir,
+                cwd_relative: None,
+                run_template: None,
+                attachments: Vec::new(),
+                review: None,
             })
         };
 
@@ -901,6 +1403,10 @@ impl ContainerService for LocalContainerService {
         &self.notification_service
     }
 
+    fn runtime(&self) -> &ExecutorsRuntime {
+        &self.runtime
+    }
+
     async fn git_branch_prefix(&self) -> String {
         self.config.read().await.git_branch_prefix.clone()
     }
@@ -909,6 +1415,10 @@ impl ContainerService for LocalContainerService {
         PathBuf::from(workspace.container_ref.clone().unwrap_or_default())
     }
 
+    async fn default_tenant(&self) -> Option<TenantId> {
+        self.config.read().await.tenant.clone()
+    }
+
     async fn create(&self, workspace: &Workspace) -> Result<ContainerRef, ContainerError> {
         let task = workspace
             .parent_task(&self.db.pool)
@@ -1051,6 +1561,7 @@ impl ContainerService for LocalContainerService {
         workspace: &Workspace,
         execution_process: &ExecutionProcess,
         executor_action: &ExecutorAction,
+        cancel: &CancelScope,
     ) -> Result<(), ContainerError> {
         // Get the worktree path
         let container_ref = workspace
@@ -1061,25 +1572,69 @@ impl ContainerService for LocalContainerService {
             )))?;
         let current_dir = PathBuf::from(container_ref);
 
-        let approvals_service: Arc<dyn ExecutorApprovalService> =
+        let (approvals_service, decider): (Arc<dyn ExecutorApprovalService>, _) =
             match executor_action.base_executor() {
-                Some(
-                    BaseCodingAgent::Codex
-                    | BaseCodingAgent::ClaudeCode
-                    | BaseCodingAgent::Gemini
-                    | BaseCodingAgent::QwenCode
-                    | BaseCodingAgent::Opencode,
-                ) => ExecutorApprovalBridge::new(
-                    self.approvals.clone(),
-                    self.db.clone(),
-                    self.notification_service.clone(),
-                    execution_process.id,
+                Some(executor) if executor.supports(BaseAgentCapability::ApprovalsHook) => (
+                    ExecutorApprovalBridge::new(
+                        self.approvals.clone(),
+                        self.db.clone(),
+                        self.notification_service.clone(),
+                        execution_process.id,
+                    ),
+                    ApprovalDecider::User,
+                ),
+                _ => (
+                    Arc::new(NoopExecutorApprovalService {}),
+                    ApprovalDecider::Auto,
                 ),
-                _ => Arc::new(NoopExecutorApprovalService {}),
             };
+        let (checkpoints_enabled, principal) = {
+            let config = self.config.read().await;
+            (config.checkpoints_enabled, config.principal.clone())
+        };
+        // Set when the execution was created, as its session's tenant
+        let tenant = executor_action.tenant.clone();
+        let approval_audit = ApprovalAuditLog::new(execution_process.session_id)
+            .for_execution_process(execution_process.id)
+            .with_tenant(tenant.clone());
+        let mut approvals_service: Arc<dyn ExecutorApprovalService> =
+            AuditedApprovalService::new(approvals_service, approval_audit.clone(), decider);
+
+        if checkpoints_enabled {
+            let repositories =
+                WorkspaceRepo::find_repos_for_workspace(&self.db.pool, workspace.id).await?;
+            let worktrees = repositories
+                .iter()
+                .map(|repo| current_dir.join(&repo.name))
+                .collect();
+            approvals_service = CheckpointingApprovalService::new(
+                approvals_service,
+                Arc::new(GitCheckpoints::new(worktrees)),
+            );
+        }
 
         // Build ExecutionEnv with VK_* variables
-        let mut env = ExecutionEnv::new();
+        // Agents that support it prefer an MCP config checked into the worktree
+        // Each spawn attempt may take 30 seconds; retry backoff and version
+        // probes come on top
+        let mut env = ExecutionEnv::new()
+            .with_approval_audit(approval_audit)
+            .with_mcp_scope(McpConfigScope::Project)
+            .with_spawn_timeout(Duration::from_secs(30));
+        // Role policies are checked for the configured principal at spawn
+        if let Some(principal) = principal {
+            env = env.with_principal(principal);
+        }
+        // The tenant's profiles, credentials included, replace the server's
+        if let Some(tenant) = tenant.clone() {
+            env = env.with_tenant(tenant);
+        }
+        let configs = ExecutorConfigs::get_cached_for(tenant.as_ref());
+        // Profiles' resource limits cover the whole process tree when a
+        // cgroup root is delegated to the server
+        if let Some(dir) = ResourceLimits::cgroup_dir(execution_process.id) {
+            env = env.with_cgroup_dir(dir);
+        }
 
         // Load task and project context for environment variables
         let task = workspace
@@ -1098,21 +1653,91 @@ impl ContainerService for LocalContainerService {
         env.insert("VK_TASK_ID", task.id.to_string());
         env.insert("VK_WORKSPACE_ID", workspace.id.to_string());
         env.insert("VK_WORKSPACE_BRANCH", &workspace.branch);
+        env.insert("VK_WORKTREE_PATH", current_dir.to_string_lossy());
+        env.insert("VK_TASK_TITLE", &task.title);
+        // Prompt templates can point the agent at the workspace's repositories
+        let repos = WorkspaceRepo::find_repos_for_workspace(&self.db.pool, workspace.id).await?;
+        env.insert(
+            "VK_FILES_HINT",
+            repos
+                .iter()
+                .map(|repo| repo.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
 
-        // Create the child and stream, add to execution tracker with timeout
-        let mut spawned = tokio::time::timeout(
-            Duration::from_secs(30),
-            executor_action.spawn(&current_dir, approvals_service, &env),
-        )
-        .await
-        .map_err(|_| {
-            ContainerError::Other(anyhow!(
-                "Timeout: process took more than 30 seconds to start"
-            ))
-        })??;
+        // Resolve the agent as its spawn will, so an unauthorized run fails
+        // before anything of the profile runs
+        let agent = executor_action.coding_agent(&env)?;
+
+        // Wait out the provider's rate limit, then for a free slot if agent
+        // processes are limited; the slot is held until the process exits
+        let spawn_permit = match executor_action.base_executor() {
+            Some(executor) => {
+                self.runtime
+                    .rate_limiter()
+                    .wait(AgentProvider::from(executor), cancel)
+                    .await?;
+                Some(
+                    self.runtime
+                        .spawn_governor()
+                        .acquire(executor, &configs.spawn_limits, cancel)
+                        .await?,
+                )
+            }
+            None => None,
+        };
+
+        // Profile hooks run where the agent does, with its environment and the
+        // run template and permission mode applied
+        let mut post_run = None;
+        let mut raw_logs = None;
+        if let Some(agent) = &agent {
+            let (cmd, run) = (agent.cmd_overrides(), agent.run_overrides());
+            let agent_dir = match executor_action.typ() {
+                ExecutorActionType::CodingAgentInitialRequest(request) => {
+                    request.effective_dir(&current_dir)
+                }
+                ExecutorActionType::CodingAgentFollowUpRequest(request) => {
+                    request.effective_dir(&current_dir)
+                }
+                ExecutorActionType::ScriptRequest(_) => current_dir.clone(),
+            };
+            if let Some(pre_run) =
+                HookRunner::for_profile(HookStage::PreRun, cmd, run, agent_dir.clone(), &env)
+            {
+                let msg_store = self.msg_store_for(execution_process.id).await;
+                if let Err(e) = pre_run.run(&msg_store, cancel).await {
+                    // The hooks' output stays with the execution's logs
+                    self.spawn_stream_raw_logs_to_db(&execution_process.id);
+                    if let Some(msg_store) =
+                        self.msg_stores.write().await.remove(&execution_process.id)
+                    {
+                        msg_store.push_finished();
+                    }
+                    return Err(e.into());
+                }
+            }
+            raw_logs =
+                run.log_pipeline.log_tee.as_ref().and_then(|log_tee| {
+                    log_tee.open(&agent_dir, &execution_process.id.to_string())
+                });
+            post_run = HookRunner::for_profile(HookStage::PostRun, cmd, run, agent_dir, &env);
+        }
+
+        // Create the child and stream, add to execution tracker
+        let mut spawned = executor_action
+            .spawn(&current_dir, approvals_service, &env, cancel)
+            .await?;
 
-        self.track_child_msgs_in_store(execution_process.id, &mut spawned.child)
+        self.track_child_msgs_in_store(execution_process.id, &mut spawned.child, raw_logs)
             .await;
+        if let Some(msg_store) = self.get_msg_store_by_id(&execution_process.id).await {
+            if let Some(loop_detection) = loop_detection(executor_action, &configs) {
+                self.spawn_loop_watchdog(execution_process.id, &msg_store, loop_detection);
+            }
+            spawn_heartbeats(msg_store, stall_threshold(executor_action, &configs));
+        }
 
         self.add_child_to_store(execution_process.id, spawned.child)
             .await;
@@ -1123,12 +1748,42 @@ impl ContainerService for LocalContainerService {
                 .await;
         }
 
+        // Store input sender for messages sent while the agent runs
+        if let Some(input_sender) = spawned.input_sender {
+            self.add_input_sender(execution_process.id, input_sender)
+                .await;
+        }
+
+        if let Some(spawn_permit) = spawn_permit {
+            self.spawn_permits
+                .write()
+                .await
+                .insert(execution_process.id, spawn_permit);
+        }
+        if let Some(post_run) = post_run {
+            self.post_run_hooks
+                .write()
+                .await
+                .insert(execution_process.id, post_run);
+        }
+
         // Spawn unified exit monitor: watches OS exit and optional executor signal
         let _hn = self.spawn_exit_monitor(&execution_process.id, spawned.exit_signal);
 
         Ok(())
     }
 
+    async fn send_user_input(
+        &self,
+        execution_process: &ExecutionProcess,
+        message: String,
+    ) -> Result<(), ContainerError> {
+        let map = self.input_senders.read().await;
+        map.get(&execution_process.id)
+            .and_then(|sender| sender.send(message).ok())
+            .ok_or(ContainerError::InputNotAccepted(execution_process.id))
+    }
+
     async fn stop_execution(
         &self,
         execution_process: &ExecutionProcess,
@@ -1148,35 +1803,30 @@ impl ContainerService for LocalContainerService {
 
         ExecutionProcess::update_completion(&self.db.pool, execution_process.id, status, exit_code)
             .await?;
+        self.runtime
+            .cancel_tree()
+            .cancel_execution(&execution_process.id, CancelReason::UserRequested);
 
-        // Try graceful interrupt first, then force kill
-        if let Some(interrupt_sender) = self.take_interrupt_sender(&execution_process.id).await {
-            // Send interrupt signal (ignore error if receiver dropped)
-            let _ = interrupt_sender.send(());
-
-            // Wait for graceful exit with timeout
-            let graceful_exit = {
-                let mut child_guard = child.write().await;
-                tokio::time::timeout(Duration::from_secs(5), child_guard.wait()).await
-            };
-
-            match graceful_exit {
-                Ok(Ok(_)) => {
-                    tracing::debug!(
-                        "Process {} exited gracefully after interrupt",
-                        execution_process.id
-                    );
-                }
-                Ok(Err(e)) => {
-                    tracing::info!("Error waiting for process {}: {}", execution_process.id, e);
-                }
-                Err(_) => {
-                    tracing::debug!(
-                        "Graceful shutdown timed out for process {}, force killing",
-                        execution_process.id
-                    );
+        // Try graceful interrupt first (executor interrupt or SIGINT), then force kill
+        let interrupt_sender = self.take_interrupt_sender(&execution_process.id).await;
+        let outcome = {
+            let mut child_guard = child.write().await;
+            interrupt_child(&mut child_guard, interrupt_sender, DEFAULT_INTERRUPT_GRACE).await
+        };
+        match outcome {
+            Ok(outcome) => {
+                tracing::debug!(
+                    "Process {} interrupted: {:?}",
+                    execution_process.id,
+                    outcome
+                );
+                if let Some(msg_store) = self.get_msg_store_by_id(&execution_process.id).await {
+                    push_cancellation_entry(&msg_store, outcome, DEFAULT_INTERRUPT_GRACE);
                 }
             }
+            Err(e) => {
+                tracing::info!("Error interrupting process {}: {}", execution_process.id, e);
+            }
         }
 
         // Kill the child process and remove from the store
@@ -63,6 +63,12 @@ fn generate_types_content() -> String {
         utils::approvals::ApprovalResponse::decl(),
         utils::diff::Diff::decl(),
         utils::diff::DiffChangeKind::decl(),
+        utils::diff::DiffHunk::decl(),
+        utils::semantic_diff::SemanticSummary::decl(),
+        utils::semantic_diff::SymbolChange::decl(),
+        utils::semantic_diff::SymbolKind::decl(),
+        utils::semantic_diff::SymbolChangeKind::decl(),
+        utils::log_msg::Heartbeat::decl(),
         utils::response::ApiResponse::<()>::decl(),
         utils::api::oauth::LoginStatus::decl(),
         utils::api::oauth::ProfileResponse::decl(),
@@ -106,6 +112,9 @@ fn generate_types_content() -> String {
         server::routes::config::CheckEditorAvailabilityQuery::decl(),
         server::routes::config::CheckEditorAvailabilityResponse::decl(),
         server::routes::config::CheckAgentAvailabilityQuery::decl(),
+        server::routes::config::AgentInstallQuery::decl(),
+        server::routes::config::AgentLoginQuery::decl(),
+        server::routes::config::TelemetryQuery::decl(),
         server::routes::oauth::CurrentUserResponse::decl(),
         server::routes::sessions::CreateFollowUpAttempt::decl(),
         server::routes::task_attempts::ChangeTargetBranchRequest::decl(),
@@ -147,6 +156,7 @@ fn generate_types_content() -> String {
         server::routes::task_attempts::workspace_summary::WorkspaceSummaryRequest::decl(),
         server::routes::task_attempts::workspace_summary::WorkspaceSummary::decl(),
         server::routes::task_attempts::workspace_summary::WorkspaceSummaryResponse::decl(),
+        server::routes::task_attempts::checkpoints::RollbackCheckpointRequest::decl(),
         services::services::filesystem::DirectoryEntry::decl(),
         services::services::filesystem::DirectoryListResponse::decl(),
         services::services::config::Config::decl(),
@@ -162,11 +172,19 @@ fn generate_types_content() -> String {
         services::services::git::GitBranch::decl(),
         services::services::share::SharedTaskDetails::decl(),
         services::services::queued_message::QueuedMessage::decl(),
+        services::services::queued_message::QueueMode::decl(),
         services::services::queued_message::QueueStatus::decl(),
         services::services::git::ConflictOp::decl(),
+        services::services::git::RollbackStrategy::decl(),
+        services::services::container::RepoRollback::decl(),
+        server::routes::execution_processes::SendUserInputRequest::decl(),
+        server::routes::execution_processes::RollbackExecutionRequest::decl(),
+        services::services::git::FileSelection::decl(),
+        server::routes::execution_processes::ApplyExecutionChangesRequest::decl(),
         executors::actions::ExecutorAction::decl(),
         executors::mcp_config::McpConfig::decl(),
         executors::actions::ExecutorActionType::decl(),
+        executors::actions::pipeline::RunCondition::decl(),
         executors::actions::script::ScriptContext::decl(),
         executors::actions::script::ScriptRequest::decl(),
         executors::actions::script::ScriptRequestLanguage::decl(),
@@ -174,11 +192,64 @@ fn generate_types_content() -> String {
         executors::executors::CodingAgent::decl(),
         executors::executors::AvailabilityInfo::decl(),
         executors::command::CommandBuilder::decl(),
+        executors::logs::pipeline::NormalizationStage::decl(),
+        executors::logs::pipeline::ClassificationRule::decl(),
+        executors::logs::pipeline::ClassifiedAs::decl(),
+        executors::logs::pipeline::ThinkingVisibility::decl(),
+        executors::retry::RetryPolicy::decl(),
+        executors::retry::RetryableErrorClass::decl(),
+        executors::loop_detection::LoopAction::decl(),
+        executors::loop_detection::LoopDetection::decl(),
+        executors::budget::Budget::decl(),
+        executors::supervisor::Supervisor::decl(),
+        executors::log_tee::LogTee::decl(),
+        executors::cancellation::CancelReason::decl(),
+        executors::telemetry::FailureClass::decl(),
+        executors::telemetry::DurationBucket::decl(),
+        executors::telemetry::ExecutorUsage::decl(),
+        executors::telemetry::TelemetryReport::decl(),
+        executors::remediation::ExecutorErrorKind::decl(),
+        executors::remediation::Remediation::decl(),
+        executors::remediation::ExecutorErrorInfo::decl(),
+        executors::login::LoginPrompt::decl(),
+        executors::profile_validation::DiagnosticSeverity::decl(),
+        executors::profile_validation::ProfileDiagnostic::decl(),
+        executors::executors::claude_flow::workflow::WorkflowTaskStatus::decl(),
+        executors::executors::claude_flow::workflow::WorkflowTaskProgress::decl(),
+        executors::executors::claude_flow::workflow::WorkflowProgress::decl(),
+        executors::describe::CrateDescription::decl(),
+        executors::describe::ExecutorDescription::decl(),
+        executors::describe::FeatureFlags::decl(),
+        executors::checkpoint::Checkpoint::decl(),
+        executors::standby::StandbyConfig::decl(),
+        executors::approvals::policy::PolicyDecision::decl(),
+        executors::approvals::policy::ApprovalRule::decl(),
+        executors::approvals::policy::ApprovalPolicy::decl(),
+        executors::permission_mode::AgentPermissionMode::decl(),
+        executors::approvals::audit::ApprovalDecider::decl(),
+        executors::approvals::audit::AuditDecision::decl(),
+        executors::approvals::audit::ApprovalAuditEntry::decl(),
+        executors::approvals::audit::ApprovalAuditQuery::decl(),
+        executors::prompt::PromptTemplate::decl(),
+        executors::prompt::PromptDelivery::decl(),
+        executors::remote::SshRemote::decl(),
+        executors::resource_limits::ResourceLimits::decl(),
+        executors::command::ScriptShell::decl(),
+        executors::attachments::Attachment::decl(),
         executors::profile::ExecutorProfileId::decl(),
         executors::profile::ExecutorConfig::decl(),
         executors::profile::ExecutorConfigs::decl(),
+        executors::governor::SpawnLimits::decl(),
+        executors::rate_limit::AgentProvider::decl(),
+        executors::rate_limit::ProviderCooldown::decl(),
+        executors::run_template::RunTemplate::decl(),
+        executors::run_template::PermissionLevel::decl(),
+        executors::authorization::RolePolicy::decl(),
+        executors::authorization::Principal::decl(),
         executors::executors::BaseAgentCapability::decl(),
         executors::executors::claude::ClaudeCode::decl(),
+        executors::executors::claude::ClaudeModel::decl(),
+        executors::executors::claude::ClaudeReasoningEffort::decl(),
         executors::executors::gemini::Gemini::decl(),
         executors::executors::amp::Amp::decl(),
         executors::executors::codex::Codex::decl(),
@@ -194,6 +265,7 @@ fn generate_types_content() -> String {
         executors::executors::droid::Droid::decl(),
         executors::executors::droid::Autonomy::decl(),
         executors::executors::droid::ReasoningEffortLevel::decl(),
+        executors::executors::claude_api::ClaudeApi::decl(),
         executors::executors::AppendPrompt::decl(),
         executors::actions::coding_agent_initial::CodingAgentInitialRequest::decl(),
         executors::actions::coding_agent_follow_up::CodingAgentFollowUpRequest::decl(),
@@ -201,9 +273,35 @@ fn generate_types_content() -> String {
         executors::logs::CommandRunResult::decl(),
         executors::logs::NormalizedEntry::decl(),
         executors::logs::NormalizedEntryType::decl(),
+        executors::logs::RunSummary::decl(),
+        executors::logs::run_metrics::RunMetrics::decl(),
+        executors::workspace_changes::FileChangeStatus::decl(),
+        executors::workspace_changes::ChangedFile::decl(),
+        executors::workspace_changes::WorkspaceChanges::decl(),
+        executors::logs::task_list::TaskListStatus::decl(),
+        executors::logs::task_list::TaskListItem::decl(),
+        executors::logs::task_list::TaskList::decl(),
+        executors::run_hooks::RunHook::decl(),
+        executors::run_hooks::HookFailureMode::decl(),
+        executors::run_hooks::HookStage::decl(),
+        executors::review::ReviewTarget::decl(),
+        executors::review::ReviewSeverity::decl(),
         executors::logs::FileChange::decl(),
+        executors::logs::file_changes::FileChangeEntry::decl(),
+        executors::logs::NormalizedConversation::decl(),
+        executors::logs::transcript::TranscriptOptions::decl(),
+        executors::logs::transcript::DigestOptions::decl(),
+        executors::logs::transcript::TranscriptDigest::decl(),
+        executors::logs::export::ExportFormat::decl(),
+        executors::logs::search::EntryKind::decl(),
+        executors::logs::search::LogSearchQuery::decl(),
+        executors::logs::search::LogSearchHit::decl(),
+        executors::logs::tool_stats::ToolStats::decl(),
+        executors::logs::tool_stats::SessionStats::decl(),
+        executors::logs::stream::StreamedEntry::decl(),
         executors::logs::ActionType::decl(),
         executors::logs::TodoItem::decl(),
+        executors::logs::MemoryOperation::decl(),
         executors::logs::NormalizedEntryError::decl(),
         executors::logs::ToolResult::decl(),
         executors::logs::ToolResultValueType::decl(),
@@ -295,6 +393,10 @@ fn generate_schemas() -> Result<HashMap<&'static str, String>, serde_json::Error
             "droid",
             generate_json_schema::<executors::executors::droid::Droid>()?,
         ),
+        (
+            "claude_api",
+            generate_json_schema::<executors::executors::claude_api::ClaudeApi>()?,
+        ),
     ]);
     println!(
         "✅ JSON schemas generated. {} schemas created.",
@@ -10,7 +10,9 @@ use db::models::{
     workspace::WorkspaceError,
 };
 use deployment::{DeploymentError, RemoteClientNotConfigured};
-use executors::executors::ExecutorError;
+use executors::{
+    checkpoint::CheckpointError, executors::ExecutorError, remediation::ExecutorErrorInfo,
+};
 use git2::Error as Git2Error;
 use services::services::{
     config::{ConfigError, EditorOpenError},
@@ -53,6 +55,8 @@ pub enum ApiError {
     #[error(transparent)]
     Executor(#[from] ExecutorError),
     #[error(transparent)]
+    Checkpoint(#[from] CheckpointError),
+    #[error(transparent)]
     Database(#[from] sqlx::Error),
     #[error(transparent)]
     Worktree(#[from] WorktreeError),
@@ -118,12 +122,41 @@ impl IntoResponse for ApiError {
                 services::services::git::GitServiceError::RebaseInProgress => {
                     (StatusCode::CONFLICT, "GitServiceError")
                 }
+                services::services::git::GitServiceError::RollbackBlocked(_) => {
+                    (StatusCode::CONFLICT, "GitServiceError")
+                }
                 _ => (StatusCode::INTERNAL_SERVER_ERROR, "GitServiceError"),
             },
             ApiError::GitHost(_) => (StatusCode::INTERNAL_SERVER_ERROR, "GitHostError"),
             ApiError::Deployment(_) => (StatusCode::INTERNAL_SERVER_ERROR, "DeploymentError"),
+            ApiError::Container(ContainerError::ExecutorError(ExecutorError::Unauthorized(_)))
+            | ApiError::Executor(ExecutorError::Unauthorized(_)) => {
+                (StatusCode::FORBIDDEN, "ForbiddenError")
+            }
+            ApiError::Container(ContainerError::ExecutorError(ExecutorError::SessionNotFound(
+                _,
+            )))
+            | ApiError::Executor(ExecutorError::SessionNotFound(_)) => {
+                (StatusCode::NOT_FOUND, "ExecutorError")
+            }
+            ApiError::Container(ContainerError::ExecutorError(ExecutorError::VersionTooOld {
+                ..
+            }))
+            | ApiError::Executor(ExecutorError::VersionTooOld { .. }) => {
+                (StatusCode::PRECONDITION_FAILED, "ExecutorError")
+            }
+            ApiError::Executor(ExecutorError::InstallNotSupported) => {
+                (StatusCode::BAD_REQUEST, "ExecutorError")
+            }
+            ApiError::Container(ContainerError::InputNotAccepted(_)) => {
+                (StatusCode::CONFLICT, "ContainerError")
+            }
             ApiError::Container(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ContainerError"),
             ApiError::Executor(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ExecutorError"),
+            ApiError::Checkpoint(CheckpointError::NotFound(_)) => {
+                (StatusCode::NOT_FOUND, "CheckpointError")
+            }
+            ApiError::Checkpoint(_) => (StatusCode::INTERNAL_SERVER_ERROR, "CheckpointError"),
             ApiError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "DatabaseError"),
             ApiError::Worktree(_) => (StatusCode::INTERNAL_SERVER_ERROR, "WorktreeError"),
             ApiError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ConfigError"),
@@ -194,6 +227,7 @@ impl IntoResponse for ApiError {
             },
             ApiError::GitService(git_err) => match git_err {
                 services::services::git::GitServiceError::MergeConflicts(msg) => msg.clone(),
+                services::services::git::GitServiceError::RollbackBlocked(_) => self.to_string(),
                 services::services::git::GitServiceError::RebaseInProgress => {
                     "A rebase is already in progress. Resolve conflicts or abort the rebase, then retry.".to_string()
                 }
@@ -255,6 +289,16 @@ impl IntoResponse for ApiError {
             ApiError::Forbidden(msg) => msg.clone(),
             _ => format!("{}: {}", error_type, self),
         };
+        // Executor errors tell the UI what kind of problem it is and how to fix it
+        if let ApiError::Executor(error)
+        | ApiError::Container(ContainerError::ExecutorError(error)) = &self
+        {
+            let response = ApiResponse::<(), ExecutorErrorInfo>::error_with_message_and_data(
+                &error_message,
+                error.info(),
+            );
+            return (status_code, Json(response)).into_response();
+        }
         let response = ApiResponse::<()>::error(&error_message);
         (status_code, Json(response)).into_response()
     }
@@ -1,5 +1,8 @@
+use std::time::Duration;
+
 use anyhow::{self, Error as AnyhowError};
 use deployment::{Deployment, DeploymentError};
+use executors::{orphans::ChildRegistry, profile::ExecutorConfigs};
 use server::{DeploymentImpl, routes};
 use services::services::container::ContainerService;
 use sqlx::Error as SqlxError;
@@ -25,6 +28,9 @@ pub enum VibeKanbanError {
     Other(#[from] AnyhowError),
 }
 
+/// How long running executions get to stop and queues to flush on shutdown
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(10);
+
 #[tokio::main]
 async fn main() -> Result<(), VibeKanbanError> {
     // Install rustls crypto provider before any TLS operations
@@ -50,6 +56,10 @@ async fn main() -> Result<(), VibeKanbanError> {
         std::fs::create_dir_all(asset_dir())?;
     }
 
+    // Agents still running when the server goes away are killed with it
+    let _orphan_guard = ChildRegistry::global().guard();
+    ChildRegistry::global().kill_all_orphans();
+
     let deployment = DeploymentImpl::new().await?;
     deployment.update_sentry_scope().await?;
     deployment
@@ -68,6 +78,7 @@ async fn main() -> Result<(), VibeKanbanError> {
         .await
         .map_err(DeploymentError::from)?;
     deployment.spawn_pr_monitor_service().await;
+    ExecutorConfigs::spawn_watcher();
     deployment
         .track_if_analytics_allowed("session_start", serde_json::json!({}))
         .await;
@@ -179,6 +190,18 @@ pub async fn shutdown_signal() {
 }
 
 pub async fn perform_cleanup_actions(deployment: &DeploymentImpl) {
+    let report = deployment
+        .container()
+        .runtime()
+        .shutdown(SHUTDOWN_DEADLINE)
+        .await;
+    if !report.unfinished_executions.is_empty() {
+        tracing::warn!(
+            "{} execution(s) did not stop within {:?}, killing them",
+            report.unfinished_executions.len(),
+            SHUTDOWN_DEADLINE
+        );
+    }
     deployment
         .container()
         .kill_all_running_processes()
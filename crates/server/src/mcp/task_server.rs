@@ -701,6 +701,7 @@ impl TaskServer {
             task_id,
             executor_profile_id,
             repos: workspace_repos,
+            run_template: None,
         };
 
         let url = self.url("/api/task-attempts");
@@ -1,29 +1,43 @@
 use std::collections::HashMap;
 
 use axum::{
-    Json, Router,
+    BoxError, Json, Router,
     body::Body,
     extract::{Path, Query, State},
     http,
-    response::{Json as ResponseJson, Response},
-    routing::{get, put},
+    response::{
+        Json as ResponseJson, Response, Sse,
+        sse::{Event, KeepAlive},
+    },
+    routing::{get, post, put},
 };
 use deployment::{Deployment, DeploymentError};
 use executors::{
+    describe::{CrateDescription, describe},
     executors::{
         AvailabilityInfo, BaseAgentCapability, BaseCodingAgent, StandardCodingAgentExecutor,
     },
-    mcp_config::{McpConfig, read_agent_config, write_agent_config},
+    install::{install_progress, start_install},
+    login::{login_progress, start_login},
+    mcp_config::{McpConfig, merge_mcp_servers_into_config, read_agent_config},
     profile::{ExecutorConfigs, ExecutorProfileId},
+    profile_validation::ProfileDiagnostic,
+    rate_limit::ProviderCooldown,
+    run_template::RunTemplate,
+    telemetry::{Telemetry, TelemetryReport},
+    tenant::TenantId,
 };
+use futures_util::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use services::services::config::{
-    Config, ConfigError, SoundFile,
-    editor::{EditorConfig, EditorType},
-    save_config_to_file,
+use services::services::{
+    config::{
+        Config, ConfigError, SoundFile,
+        editor::{EditorConfig, EditorType},
+        save_config_to_file,
+    },
+    container::ContainerService,
 };
-use tokio::fs;
 use ts_rs::TS;
 use utils::{api::oauth::LoginStatus, assets::config_path, response::ApiResponse};
 
@@ -36,11 +50,24 @@ pub fn router() -> Router<DeploymentImpl> {
         .route("/sounds/{sound}", get(get_sound))
         .route("/mcp-config", get(get_mcp_servers).post(update_mcp_servers))
         .route("/profiles", get(get_profiles).put(update_profiles))
+        .route("/profiles/validate", post(validate_profiles))
+        .route("/run-templates", get(get_run_templates))
+        .route(
+            "/run-templates/{name}",
+            put(upsert_run_template).delete(delete_run_template),
+        )
         .route(
             "/editors/check-availability",
             get(check_editor_availability),
         )
         .route("/agents/check-availability", get(check_agent_availability))
+        .route("/agents/install", post(install_agent))
+        .route("/agents/install/stream", get(stream_agent_install))
+        .route("/agents/login", post(login_agent))
+        .route("/agents/login/stream", get(stream_agent_login))
+        .route("/telemetry", get(get_telemetry))
+        .route("/rate-limits", get(get_rate_limits))
+        .route("/describe", get(get_description))
 }
 
 #[derive(Debug, Serialize, Deserialize, TS)]
@@ -176,6 +203,10 @@ async fn track_config_events(deployment: &DeploymentImpl, old: &Config, new: &Co
 async fn handle_config_events(deployment: &DeploymentImpl, old: &Config, new: &Config) {
     track_config_events(deployment, old, new).await;
 
+    if old.executor_telemetry_enabled != new.executor_telemetry_enabled {
+        Telemetry::global().set_enabled(new.executor_telemetry_enabled);
+    }
+
     if !old.disclaimer_acknowledged && new.disclaimer_acknowledged {
         // Spawn auto project setup as background task to avoid blocking config response
         let deployment_clone = deployment.clone();
@@ -213,6 +244,10 @@ pub struct GetMcpServerResponse {
 #[derive(TS, Debug, Serialize, Deserialize)]
 pub struct UpdateMcpServersBody {
     servers: HashMap<String, Value>,
+    /// Overwrite servers that were changed outside vibe-kanban
+    #[serde(default)]
+    #[ts(optional)]
+    force: Option<bool>,
 }
 
 async fn get_mcp_servers(
@@ -280,7 +315,14 @@ async fn update_mcp_servers(
     };
 
     let mcpc = agent.get_mcp_config();
-    match update_mcp_servers_in_config(&config_path, &mcpc, payload.servers).await {
+    match update_mcp_servers_in_config(
+        &config_path,
+        &mcpc,
+        payload.servers,
+        payload.force.unwrap_or(false),
+    )
+    .await
+    {
         Ok(message) => Ok(ResponseJson(ApiResponse::success(message))),
         Err(e) => Ok(ResponseJson(ApiResponse::error(&format!(
             "Failed to update MCP servers: {}",
@@ -293,33 +335,28 @@ async fn update_mcp_servers_in_config(
     config_path: &std::path::Path,
     mcpc: &McpConfig,
     new_servers: HashMap<String, Value>,
+    force: bool,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    // Ensure parent directory exists
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent).await?;
-    }
-    // Read existing config (JSON or TOML depending on agent)
-    let mut config = read_agent_config(config_path, mcpc).await?;
-
-    // Get the current server count for comparison
-    let old_servers = get_mcp_servers_from_config_path(&config, &mcpc.servers_path).len();
-
-    // Set the MCP servers using the correct attribute path
-    set_mcp_servers_in_config_path(&mut config, &mcpc.servers_path, &new_servers)?;
-
-    // Write the updated config back to file (JSON or TOML depending on agent)
-    write_agent_config(config_path, mcpc, &config).await?;
-
-    let new_count = new_servers.len();
-    let message = match (old_servers, new_count) {
-        (0, 0) => "No MCP servers configured".to_string(),
-        (0, n) => format!("Added {} MCP server(s)", n),
-        (old, new) if old == new => format!("Updated MCP server configuration ({} server(s))", new),
-        (old, new) => format!(
-            "Updated MCP server configuration (was {}, now {})",
-            old, new
-        ),
+    // Merge into the existing config (JSON or TOML depending on agent),
+    // keeping servers the user defined there
+    let report = merge_mcp_servers_into_config(config_path, mcpc, &new_servers, force).await?;
+
+    let mut message = if report.is_unchanged() {
+        "No MCP server changes".to_string()
+    } else {
+        format!(
+            "Updated MCP server configuration ({} added, {} updated, {} removed)",
+            report.added.len(),
+            report.updated.len(),
+            report.removed.len()
+        )
     };
+    if !report.conflicts.is_empty() {
+        message.push_str(&format!(
+            "; kept your changes to {}",
+            report.conflicts.join(", ")
+        ));
+    }
 
     Ok(message)
 }
@@ -343,42 +380,6 @@ fn get_mcp_servers_from_config_path(raw_config: &Value, path: &[String]) -> Hash
     }
 }
 
-/// Helper function to set MCP servers in config using a path
-fn set_mcp_servers_in_config_path(
-    raw_config: &mut Value,
-    path: &[String],
-    servers: &HashMap<String, Value>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Ensure config is an object
-    if !raw_config.is_object() {
-        *raw_config = serde_json::json!({});
-    }
-
-    let mut current = raw_config;
-    // Navigate/create the nested structure (all parts except the last)
-    for part in &path[..path.len() - 1] {
-        if current.get(part).is_none() {
-            current
-                .as_object_mut()
-                .unwrap()
-                .insert(part.to_string(), serde_json::json!({}));
-        }
-        current = current.get_mut(part).unwrap();
-        if !current.is_object() {
-            *current = serde_json::json!({});
-        }
-    }
-
-    // Set the final attribute
-    let final_attr = path.last().unwrap();
-    current
-        .as_object_mut()
-        .unwrap()
-        .insert(final_attr.to_string(), serde_json::to_value(servers)?);
-
-    Ok(())
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProfilesContent {
     pub content: String,
@@ -438,6 +439,97 @@ async fn update_profiles(
     }
 }
 
+/// Problems in profiles.json content, before it is saved
+async fn validate_profiles(
+    State(_deployment): State<DeploymentImpl>,
+    body: String,
+) -> ResponseJson<ApiResponse<Vec<ProfileDiagnostic>>> {
+    ResponseJson(ApiResponse::success(ExecutorConfigs::validate_json(&body)))
+}
+
+async fn get_run_templates(
+    State(_deployment): State<DeploymentImpl>,
+) -> ResponseJson<ApiResponse<HashMap<String, RunTemplate>>> {
+    ResponseJson(ApiResponse::success(
+        ExecutorConfigs::get_cached().templates,
+    ))
+}
+
+/// Everything a client needs to drive the executors without hardcoding them
+async fn get_description(
+    State(_deployment): State<DeploymentImpl>,
+) -> ResponseJson<ApiResponse<CrateDescription>> {
+    ResponseJson(ApiResponse::success(describe(None)))
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+pub struct TelemetryQuery {
+    /// Tenant whose runs to report; the server's own runs if unset
+    #[serde(default)]
+    tenant: Option<TenantId>,
+}
+
+/// The executor telemetry collected so far, for the user to review before
+/// sending it
+async fn get_telemetry(
+    State(_deployment): State<DeploymentImpl>,
+    Query(query): Query<TelemetryQuery>,
+) -> ResponseJson<ApiResponse<Option<TelemetryReport>>> {
+    ResponseJson(ApiResponse::success(
+        Telemetry::global().export(query.tenant.as_ref()),
+    ))
+}
+
+/// Providers whose spawns are held back after rate limiting a run
+async fn get_rate_limits(
+    State(deployment): State<DeploymentImpl>,
+) -> ResponseJson<ApiResponse<Vec<ProviderCooldown>>> {
+    ResponseJson(ApiResponse::success(
+        deployment.container().runtime().rate_limiter().status(),
+    ))
+}
+
+async fn upsert_run_template(
+    State(_deployment): State<DeploymentImpl>,
+    Path(name): Path<String>,
+    Json(template): Json<RunTemplate>,
+) -> ResponseJson<ApiResponse<RunTemplate>> {
+    let mut profiles = ExecutorConfigs::get_cached();
+    profiles.set_template(name.clone(), template.clone());
+    match profiles.save_overrides() {
+        Ok(_) => {
+            tracing::info!("Run template '{}' saved", name);
+            ExecutorConfigs::reload();
+            ResponseJson(ApiResponse::success(template))
+        }
+        Err(e) => ResponseJson(ApiResponse::error(&format!(
+            "Failed to save run template: {}",
+            e
+        ))),
+    }
+}
+
+async fn delete_run_template(
+    State(_deployment): State<DeploymentImpl>,
+    Path(name): Path<String>,
+) -> ResponseJson<ApiResponse<RunTemplate>> {
+    let mut profiles = ExecutorConfigs::get_cached();
+    let result = profiles
+        .remove_template(&name)
+        .and_then(|template| profiles.save_overrides().map(|_| template));
+    match result {
+        Ok(template) => {
+            tracing::info!("Run template '{}' deleted", name);
+            ExecutorConfigs::reload();
+            ResponseJson(ApiResponse::success(template))
+        }
+        Err(e) => ResponseJson(ApiResponse::error(&format!(
+            "Failed to delete run template: {}",
+            e
+        ))),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, TS)]
 pub struct CheckEditorAvailabilityQuery {
     editor_type: EditorType,
@@ -479,9 +571,86 @@ async fn check_agent_availability(
     let profile_id = ExecutorProfileId::new(query.executor);
 
     let info = match profiles.get_coding_agent(&profile_id) {
-        Some(agent) => agent.get_availability_info(),
+        Some(agent) => agent.probe().await,
         None => AvailabilityInfo::NotFound,
     };
 
     ResponseJson(ApiResponse::success(info))
 }
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+pub struct AgentInstallQuery {
+    executor: BaseCodingAgent,
+}
+
+async fn install_agent(
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<AgentInstallQuery>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    let profile_id = ExecutorProfileId::new(query.executor);
+    let agent = ExecutorConfigs::get_cached()
+        .get_coding_agent(&profile_id)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown executor {}", query.executor)))?;
+    start_install(agent, query.executor)?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "agent_install_started",
+            serde_json::json!({ "executor": query.executor.to_string() }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(())))
+}
+
+/// Output of the latest install of an agent, replayed from the start
+async fn stream_agent_install(
+    Query(query): Query<AgentInstallQuery>,
+) -> Result<Sse<impl futures_util::Stream<Item = Result<Event, BoxError>>>, ApiError> {
+    let msg_store = install_progress(query.executor).ok_or_else(|| {
+        ApiError::BadRequest(format!("No install was started for {}", query.executor))
+    })?;
+    Ok(
+        Sse::new(msg_store.sse_stream().map_err(|e| -> BoxError { e.into() }))
+            .keep_alive(KeepAlive::default()),
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+pub struct AgentLoginQuery {
+    executor: BaseCodingAgent,
+}
+
+async fn login_agent(
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<AgentLoginQuery>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    let profile_id = ExecutorProfileId::new(query.executor);
+    let agent = ExecutorConfigs::get_cached()
+        .get_coding_agent(&profile_id)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown executor {}", query.executor)))?;
+    start_login(agent, query.executor)?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "agent_login_started",
+            serde_json::json!({ "executor": query.executor.to_string() }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(())))
+}
+
+/// Output of the latest login of an agent, with the URL and code to finish
+/// it in the browser, replayed from the start
+async fn stream_agent_login(
+    Query(query): Query<AgentLoginQuery>,
+) -> Result<Sse<impl futures_util::Stream<Item = Result<Event, BoxError>>>, ApiError> {
+    let msg_store = login_progress(query.executor).ok_or_else(|| {
+        ApiError::BadRequest(format!("No login was started for {}", query.executor))
+    })?;
+    Ok(
+        Sse::new(msg_store.sse_stream().map_err(|e| -> BoxError { e.into() }))
+            .keep_alive(KeepAlive::default()),
+    )
+}
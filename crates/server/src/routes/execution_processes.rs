@@ -1,12 +1,13 @@
 use anyhow;
 use axum::{
-    Extension, Router,
+    Extension, Json, Router,
     extract::{
         Path, Query, State,
         ws::{WebSocket, WebSocketUpgrade},
     },
+    http::header,
     middleware::from_fn_with_state,
-    response::{IntoResponse, Json as ResponseJson},
+    response::{IntoResponse, Json as ResponseJson, Response},
     routing::{get, post},
 };
 use db::models::{
@@ -14,10 +15,22 @@ use db::models::{
     execution_process_repo_state::ExecutionProcessRepoState,
 };
 use deployment::Deployment;
+use executors::logs::{
+    NormalizedConversation, NormalizedEntry,
+    export::ExportFormat,
+    file_changes::FileChangeEntry,
+    search::{LogSearchHit, LogSearchQuery},
+    transcript::{DigestOptions, TranscriptDigest, TranscriptOptions},
+    truncation::FullEntryStore,
+};
 use futures_util::{SinkExt, StreamExt, TryStreamExt};
 use serde::Deserialize;
-use services::services::container::ContainerService;
-use utils::{log_msg::LogMsg, response::ApiResponse};
+use services::services::{
+    container::{ContainerError, ContainerService, RepoRollback},
+    git::{FileSelection, RollbackStrategy},
+};
+use ts_rs::TS;
+use utils::{diff::Diff, log_msg::LogMsg, response::ApiResponse};
 use uuid::Uuid;
 
 use crate::{DeploymentImpl, error::ApiError, middleware::load_execution_process_middleware};
@@ -178,6 +191,204 @@ pub async fn stop_execution_process(
     Ok(ResponseJson(ApiResponse::success(())))
 }
 
+#[derive(Debug, Deserialize, TS)]
+pub struct SendUserInputRequest {
+    pub message: String,
+}
+
+/// Send a message to the agent while it runs; only interactive executions
+/// accept input
+pub async fn send_execution_process_input(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<SendUserInputRequest>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    deployment
+        .container()
+        .send_user_input(&execution_process, payload.message)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(())))
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct RollbackExecutionRequest {
+    #[serde(default)]
+    pub strategy: RollbackStrategy,
+}
+
+pub async fn rollback_execution_process(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<RollbackExecutionRequest>,
+) -> Result<ResponseJson<ApiResponse<Vec<RepoRollback>>>, ApiError> {
+    let results = deployment
+        .container()
+        .rollback_execution(execution_process.id, payload.strategy)
+        .await
+        .map_err(|e| match e {
+            // Surface conflicts and blocked rollbacks with their git-specific status
+            ContainerError::GitServiceError(git_err) => ApiError::GitService(git_err),
+            other => ApiError::from(other),
+        })?;
+
+    Ok(ResponseJson(ApiResponse::success(results)))
+}
+
+pub async fn get_execution_process_file_changes(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<FileChangeEntry>>>, ApiError> {
+    let changes = deployment
+        .container()
+        .execution_file_changes(&execution_process.id)
+        .await
+        .ok_or_else(|| {
+            ApiError::ExecutionProcess(ExecutionProcessError::ExecutionProcessNotFound)
+        })?;
+    Ok(ResponseJson(ApiResponse::success(changes)))
+}
+
+pub async fn search_execution_process_logs(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<LogSearchQuery>,
+) -> Result<ResponseJson<ApiResponse<Vec<LogSearchHit>>>, ApiError> {
+    let hits = deployment
+        .container()
+        .search_execution_logs(&execution_process.id, &query)
+        .await
+        .ok_or_else(|| {
+            ApiError::ExecutionProcess(ExecutionProcessError::ExecutionProcessNotFound)
+        })?;
+    Ok(ResponseJson(ApiResponse::success(hits)))
+}
+
+/// Full version of an entry the normalization pipeline truncated
+pub async fn get_execution_process_full_entry(
+    Path((_, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<ResponseJson<ApiResponse<NormalizedEntry>>, ApiError> {
+    let entry = FullEntryStore::default()
+        .load(entry_id)?
+        .ok_or_else(|| ApiError::BadRequest(format!("No full entry {entry_id}")))?;
+    Ok(ResponseJson(ApiResponse::success(entry)))
+}
+
+pub async fn get_execution_process_transcript(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Query(options): Query<TranscriptOptions>,
+) -> Result<ResponseJson<ApiResponse<NormalizedConversation>>, ApiError> {
+    let transcript = deployment
+        .container()
+        .execution_transcript(&execution_process, &options)
+        .await
+        .ok_or_else(|| {
+            ApiError::ExecutionProcess(ExecutionProcessError::ExecutionProcessNotFound)
+        })?;
+    Ok(ResponseJson(ApiResponse::success(transcript)))
+}
+
+pub async fn get_execution_process_transcript_digest(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Query(options): Query<DigestOptions>,
+) -> Result<ResponseJson<ApiResponse<TranscriptDigest>>, ApiError> {
+    let transcript_options = TranscriptOptions {
+        include_thinking: options.include_thinking,
+    };
+    let transcript = deployment
+        .container()
+        .execution_transcript(&execution_process, &transcript_options)
+        .await
+        .ok_or_else(|| {
+            ApiError::ExecutionProcess(ExecutionProcessError::ExecutionProcessNotFound)
+        })?;
+    Ok(ResponseJson(ApiResponse::success(
+        transcript.digest(&options),
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranscriptExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub include_thinking: bool,
+}
+
+pub async fn export_execution_process_transcript(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<TranscriptExportQuery>,
+) -> Result<Response, ApiError> {
+    let options = TranscriptOptions {
+        include_thinking: query.include_thinking,
+    };
+    let transcript = deployment
+        .container()
+        .execution_transcript(&execution_process, &options)
+        .await
+        .ok_or_else(|| {
+            ApiError::ExecutionProcess(ExecutionProcessError::ExecutionProcessNotFound)
+        })?;
+    let mut body = Vec::new();
+    transcript.export(query.format, &mut body)?;
+    let disposition = format!(
+        "attachment; filename=\"{}.{}\"",
+        execution_process.id,
+        query.format.file_extension()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExecutionDiffQuery {
+    pub repo_id: Uuid,
+}
+
+pub async fn get_execution_process_diff(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<ExecutionDiffQuery>,
+) -> Result<ResponseJson<ApiResponse<Vec<Diff>>>, ApiError> {
+    let diffs = deployment
+        .container()
+        .execution_diff(execution_process.id, query.repo_id)
+        .await?;
+    Ok(ResponseJson(ApiResponse::success(diffs)))
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct ApplyExecutionChangesRequest {
+    pub repo_id: Uuid,
+    pub files: Vec<FileSelection>,
+}
+
+pub async fn apply_execution_changes(
+    Extension(execution_process): Extension<ExecutionProcess>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<ApplyExecutionChangesRequest>,
+) -> Result<ResponseJson<ApiResponse<Option<String>>>, ApiError> {
+    let commit = deployment
+        .container()
+        .apply_execution_changes(execution_process.id, payload.repo_id, &payload.files)
+        .await
+        .map_err(|e| match e {
+            ContainerError::GitServiceError(git_err) => ApiError::GitService(git_err),
+            other => ApiError::from(other),
+        })?;
+
+    Ok(ResponseJson(ApiResponse::success(commit)))
+}
+
 pub async fn stream_execution_processes_by_session_ws(
     ws: WebSocketUpgrade,
     State(deployment): State<DeploymentImpl>,
@@ -247,6 +458,25 @@ pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
     let workspace_id_router = Router::new()
         .route("/", get(get_execution_process_by_id))
         .route("/stop", post(stop_execution_process))
+        .route("/input", post(send_execution_process_input))
+        .route("/rollback", post(rollback_execution_process))
+        .route("/diff", get(get_execution_process_diff))
+        .route("/file-changes", get(get_execution_process_file_changes))
+        .route("/transcript", get(get_execution_process_transcript))
+        .route("/logs/search", get(search_execution_process_logs))
+        .route(
+            "/full-entries/{entry_id}",
+            get(get_execution_process_full_entry),
+        )
+        .route(
+            "/transcript/digest",
+            get(get_execution_process_transcript_digest),
+        )
+        .route(
+            "/transcript/export",
+            get(export_execution_process_transcript),
+        )
+        .route("/apply", post(apply_execution_changes))
         .route("/repo-states", get(get_execution_process_repo_states))
         .route("/raw-logs/ws", get(stream_raw_logs_ws))
         .route("/normalized-logs/ws", get(stream_normalized_logs_ws))
@@ -21,7 +21,15 @@ use executors::{
     actions::{
         ExecutorAction, ExecutorActionType, coding_agent_follow_up::CodingAgentFollowUpRequest,
     },
-    executors::BaseCodingAgent,
+    approvals::audit::{ApprovalAuditEntry, ApprovalAuditLog, ApprovalAuditQuery},
+    attachments::Attachment,
+    executors::{
+        BaseCodingAgent,
+        claude_flow::workflow::{
+            WorkflowProgress, workflow_progress, workflow_progress_from_history,
+        },
+    },
+    logs::tool_stats::{SessionStats, ToolStats, ToolStatsCollector},
     profile::ExecutorProfileId,
 };
 use serde::Deserialize;
@@ -62,6 +70,88 @@ pub async fn get_session(
     Ok(ResponseJson(ApiResponse::success(session)))
 }
 
+pub async fn get_approval_audit(
+    Extension(session): Extension<Session>,
+    Query(query): Query<ApprovalAuditQuery>,
+) -> Result<ResponseJson<ApiResponse<Vec<ApprovalAuditEntry>>>, ApiError> {
+    let entries = ApprovalAuditLog::new(session.id).query(&query)?;
+    Ok(ResponseJson(ApiResponse::success(entries)))
+}
+
+/// Progress of the workflow run in the session's latest agent session, for
+/// executors that run workflows
+pub async fn get_workflow_progress(
+    Extension(session): Extension<Session>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Option<WorkflowProgress>>>, ApiError> {
+    let pool = &deployment.db().pool;
+    let running = ExecutionProcess::find_latest_coding_agent_turn_session_id(pool, session.id)
+        .await?
+        .and_then(|agent_session_id| workflow_progress(&agent_session_id));
+    if running.is_some() {
+        return Ok(ResponseJson(ApiResponse::success(running)));
+    }
+
+    // A finished run's last progress is in its logs
+    let Some(process) = ExecutionProcess::find_latest_by_session_and_run_reason(
+        pool,
+        session.id,
+        &ExecutionProcessRunReason::CodingAgent,
+    )
+    .await?
+    else {
+        return Ok(ResponseJson(ApiResponse::success(None)));
+    };
+    let progress = deployment
+        .container()
+        .execution_normalized_history(&process.id)
+        .await
+        .and_then(|history| workflow_progress_from_history(&history));
+    Ok(ResponseJson(ApiResponse::success(progress)))
+}
+
+/// Statistics of all of the session's coding agent runs
+async fn collect_session_stats(
+    session: &Session,
+    deployment: &DeploymentImpl,
+) -> Result<ToolStatsCollector, ApiError> {
+    let pool = &deployment.db().pool;
+    let mut stats = ToolStatsCollector::default();
+    for process in ExecutionProcess::find_by_session_id(pool, session.id, false).await? {
+        if process.run_reason != ExecutionProcessRunReason::CodingAgent {
+            continue;
+        }
+        if let Some(collector) = deployment
+            .container()
+            .execution_tool_stats(&process.id)
+            .await
+        {
+            stats.merge(&collector);
+        }
+    }
+    Ok(stats)
+}
+
+/// Latency and failure statistics of the tools the session's coding agent
+/// runs called
+pub async fn get_tool_stats(
+    Extension(session): Extension<Session>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<ToolStats>>>, ApiError> {
+    let stats = collect_session_stats(&session, &deployment).await?;
+    Ok(ResponseJson(ApiResponse::success(stats.stats())))
+}
+
+/// Tool statistics of the session's coding agent runs, along with how much
+/// of their output couldn't be parsed
+pub async fn get_session_stats(
+    Extension(session): Extension<Session>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<SessionStats>>, ApiError> {
+    let stats = collect_session_stats(&session, &deployment).await?;
+    Ok(ResponseJson(ApiResponse::success(stats.session_stats())))
+}
+
 pub async fn create_session(
     State(deployment): State<DeploymentImpl>,
     Json(payload): Json<CreateSessionRequest>,
@@ -95,6 +185,10 @@ pub struct CreateFollowUpAttempt {
     pub retry_process_id: Option<Uuid>,
     pub force_when_dirty: Option<bool>,
     pub perform_git_reset: Option<bool>,
+    /// Screenshots and files for the agent to look at
+    #[serde(default)]
+    #[ts(optional)]
+    pub attachments: Option<Vec<Attachment>>,
 }
 
 pub async fn follow_up(
@@ -195,6 +289,7 @@ pub async fn follow_up(
         ExecutionProcess::find_latest_coding_agent_turn_session_id(pool, session.id).await?;
 
     let prompt = payload.prompt;
+    let attachments = payload.attachments.unwrap_or_default();
 
     let project_repos = ProjectRepo::find_by_project_id_with_names(pool, project.id).await?;
     let cleanup_action = deployment
@@ -213,6 +308,9 @@ pub async fn follow_up(
             session_id: agent_session_id,
             executor_profile_id: executor_profile_id.clone(),
             working_dir: working_dir.clone(),
+            cwd_relative: None,
+            fork: false,
+            attachments: attachments.clone(),
         })
     } else {
         ExecutorActionType::CodingAgentInitialRequest(
@@ -220,6 +318,10 @@ pub async fn follow_up(
                 prompt,
                 executor_profile_id: executor_profile_id.clone(),
                 working_dir,
+                cwd_relative: None,
+                run_template: None,
+                attachments,
+                review: None,
             },
         )
     };
@@ -254,6 +356,10 @@ pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
     let session_id_router = Router::new()
         .route("/", get(get_session))
         .route("/follow-up", post(follow_up))
+        .route("/approval-audit", get(get_approval_audit))
+        .route("/workflow-progress", get(get_workflow_progress))
+        .route("/tool-stats", get(get_tool_stats))
+        .route("/stats", get(get_session_stats))
         .layer(from_fn_with_state(
             deployment.clone(),
             load_session_middleware,
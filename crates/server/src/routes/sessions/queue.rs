@@ -5,7 +5,7 @@ use axum::{
 use db::models::{scratch::DraftFollowUpData, session::Session};
 use deployment::Deployment;
 use serde::Deserialize;
-use services::services::queued_message::QueueStatus;
+use services::services::queued_message::{QueueMode, QueueStatus};
 use ts_rs::TS;
 use utils::response::ApiResponse;
 
@@ -16,6 +16,11 @@ use crate::{DeploymentImpl, error::ApiError, middleware::load_session_middleware
 pub struct QueueMessageRequest {
     pub message: String,
     pub variant: Option<String>,
+    /// How to add the message when others are already queued; replaces
+    /// them by default
+    #[serde(default)]
+    #[ts(optional)]
+    pub mode: Option<QueueMode>,
 }
 
 /// Queue a follow-up message to be executed when the current execution finishes
@@ -29,9 +34,8 @@ pub async fn queue_message(
         variant: payload.variant,
     };
 
-    let queued = deployment
-        .queued_message_service()
-        .queue_message(session.id, data);
+    let queue = deployment.queued_message_service();
+    queue.queue_message(session.id, data, payload.mode.unwrap_or_default());
 
     deployment
         .track_if_analytics_allowed(
@@ -39,13 +43,14 @@ pub async fn queue_message(
             serde_json::json!({
                 "session_id": session.id.to_string(),
                 "workspace_id": session.workspace_id.to_string(),
+                "mode": payload.mode.unwrap_or_default(),
             }),
         )
         .await;
 
-    Ok(ResponseJson(ApiResponse::success(QueueStatus::Queued {
-        message: queued,
-    })))
+    Ok(ResponseJson(ApiResponse::success(
+        queue.get_status(session.id),
+    )))
 }
 
 /// Cancel a queued follow-up message
@@ -1,3 +1,4 @@
+pub mod checkpoints;
 pub mod codex_setup;
 pub mod cursor_setup;
 pub mod gh_cli_setup;
@@ -143,6 +144,28 @@ pub struct CreateTaskAttemptBody {
     pub task_id: Uuid,
     pub executor_profile_id: ExecutorProfileId,
     pub repos: Vec<WorkspaceRepoInput>,
+    /// Run template to launch with; its profile takes precedence over
+    /// `executor_profile_id`
+    #[serde(default)]
+    pub run_template: Option<String>,
+}
+
+/// Resolve the profile a workspace starts with, taking it from the run
+/// template when one is given
+pub async fn launch_profile(
+    deployment: &DeploymentImpl,
+    executor_profile_id: &ExecutorProfileId,
+    run_template: Option<&str>,
+) -> Result<ExecutorProfileId, ApiError> {
+    let Some(name) = run_template else {
+        return Ok(executor_profile_id.clone());
+    };
+    // New workspaces run as the default tenant, with its templates
+    let tenant = deployment.container().default_tenant().await;
+    ExecutorConfigs::get_cached_for(tenant.as_ref())
+        .get_template(name)
+        .map(|template| template.executor_profile_id.clone())
+        .ok_or_else(|| ApiError::BadRequest(format!("Run template '{name}' not found")))
 }
 
 #[derive(Debug, Serialize, Deserialize, ts_rs::TS)]
@@ -164,7 +187,12 @@ pub async fn create_task_attempt(
     State(deployment): State<DeploymentImpl>,
     Json(payload): Json<CreateTaskAttemptBody>,
 ) -> Result<ResponseJson<ApiResponse<Workspace>>, ApiError> {
-    let executor_profile_id = payload.executor_profile_id.clone();
+    let executor_profile_id = launch_profile(
+        &deployment,
+        &payload.executor_profile_id,
+        payload.run_template.as_deref(),
+    )
+    .await?;
 
     if payload.repos.is_empty() {
         return Err(ApiError::BadRequest(
@@ -217,7 +245,11 @@ pub async fn create_task_attempt(
     WorkspaceRepo::create_many(pool, workspace.id, &workspace_repos).await?;
     if let Err(err) = deployment
         .container()
-        .start_workspace(&workspace, executor_profile_id.clone())
+        .start_workspace(
+            &workspace,
+            executor_profile_id.clone(),
+            payload.run_template.clone(),
+        )
         .await
     {
         tracing::error!("Failed to start task attempt: {}", err);
@@ -1252,6 +1284,7 @@ pub async fn start_dev_server(
             language: ScriptRequestLanguage::Bash,
             context: ScriptContext::DevServer,
             working_dir,
+            timeout_secs: None,
         }),
         None,
     );
@@ -1742,6 +1775,11 @@ pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
         .route("/repos", get(get_task_attempt_repos))
         .route("/first-message", get(get_first_user_message))
         .route("/mark-seen", put(mark_seen))
+        .route("/checkpoints", get(checkpoints::list_checkpoints))
+        .route(
+            "/checkpoints/rollback",
+            post(checkpoints::rollback_checkpoint),
+        )
         .layer(from_fn_with_state(
             deployment.clone(),
             load_workspace_middleware,
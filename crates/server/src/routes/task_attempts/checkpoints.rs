@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use axum::{Extension, Json, extract::State, response::Json as ResponseJson};
+use db::models::{
+    execution_process::ExecutionProcess, workspace::Workspace, workspace_repo::WorkspaceRepo,
+};
+use deployment::Deployment;
+use executors::checkpoint::{Checkpoint, CheckpointProvider, GitCheckpoints};
+use serde::{Deserialize, Serialize};
+use services::services::container::ContainerService;
+use ts_rs::TS;
+use utils::response::ApiResponse;
+
+use crate::{DeploymentImpl, error::ApiError};
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+pub struct RollbackCheckpointRequest {
+    pub checkpoint_id: String,
+}
+
+async fn workspace_checkpoints(
+    deployment: &DeploymentImpl,
+    workspace: &Workspace,
+) -> Result<GitCheckpoints, ApiError> {
+    let container_ref = deployment
+        .container()
+        .ensure_container_exists(workspace)
+        .await?;
+    let workspace_path = Path::new(&container_ref);
+    let repos =
+        WorkspaceRepo::find_repos_for_workspace(&deployment.db().pool, workspace.id).await?;
+    Ok(GitCheckpoints::new(
+        repos
+            .iter()
+            .map(|repo| workspace_path.join(&repo.name))
+            .collect(),
+    ))
+}
+
+pub async fn list_checkpoints(
+    Extension(workspace): Extension<Workspace>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<Checkpoint>>>, ApiError> {
+    let checkpoints = workspace_checkpoints(&deployment, &workspace)
+        .await?
+        .list()
+        .await?;
+    Ok(ResponseJson(ApiResponse::success(checkpoints)))
+}
+
+pub async fn rollback_checkpoint(
+    Extension(workspace): Extension<Workspace>,
+    State(deployment): State<DeploymentImpl>,
+    Json(request): Json<RollbackCheckpointRequest>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    let pool = &deployment.db().pool;
+    if ExecutionProcess::has_running_non_dev_server_processes_for_workspace(pool, workspace.id)
+        .await?
+    {
+        return Err(ApiError::Conflict(
+            "Stop the running agent before rolling back".to_string(),
+        ));
+    }
+
+    workspace_checkpoints(&deployment, &workspace)
+        .await?
+        .rollback(&request.checkpoint_id)
+        .await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "checkpoint_rolled_back",
+            serde_json::json!({
+                "workspace_id": workspace.id.to_string(),
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(())))
+}
@@ -9,7 +9,7 @@ use executors::{
         ExecutorAction, ExecutorActionType,
         script::{ScriptContext, ScriptRequest, ScriptRequestLanguage},
     },
-    command::{CommandBuilder, apply_overrides},
+    command::{CommandBuilder, apply_overrides, shell_join},
     executors::{ExecutorError, codex::Codex},
 };
 use services::services::container::ContainerService;
@@ -86,12 +86,15 @@ async fn get_setup_helper_action(codex: &Codex) -> Result<ExecutorAction, ApiErr
         .into_resolved()
         .await
         .map_err(ApiError::Executor)?;
-    let login_script = format!("{} {}", program_path.to_string_lossy(), args.join(" "));
+    let program_path = program_path.to_string_lossy();
+    let login_script =
+        shell_join(std::iter::once(program_path.as_ref()).chain(args.iter().map(String::as_str)));
     let login_request = ScriptRequest {
         script: login_script,
         language: ScriptRequestLanguage::Bash,
         context: ScriptContext::ToolInstallScript,
         working_dir: None,
+        timeout_secs: None,
     };
 
     Ok(ExecutorAction::new(
@@ -109,6 +109,7 @@ fi"#
             language: ScriptRequestLanguage::Bash,
             context: ScriptContext::ToolInstallScript,
             working_dir: None,
+            timeout_secs: None,
         };
         // Second action (chained): Login
         let login_script = format!(
@@ -123,6 +124,7 @@ export PATH="$HOME/.local/bin:$PATH"
             language: ScriptRequestLanguage::Bash,
             context: ScriptContext::ToolInstallScript,
             working_dir: None,
+            timeout_secs: None,
         };
 
         // Chain them: install → login
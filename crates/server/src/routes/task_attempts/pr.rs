@@ -169,12 +169,19 @@ async fn trigger_pr_description_follow_up(
             session_id: agent_session_id,
             executor_profile_id: executor_profile_id.clone(),
             working_dir: working_dir.clone(),
+            cwd_relative: None,
+            fork: false,
+            attachments: Vec::new(),
         })
     } else {
         ExecutorActionType::CodingAgentInitialRequest(CodingAgentInitialRequest {
             prompt,
             executor_profile_id: executor_profile_id.clone(),
             working_dir,
+            cwd_relative: None,
+            run_template: None,
+            attachments: Vec::new(),
+            review: None,
         })
     };
 
@@ -33,8 +33,10 @@ use utils::{api::oauth::LoginStatus, response::ApiResponse};
 use uuid::Uuid;
 
 use crate::{
-    DeploymentImpl, error::ApiError, middleware::load_task_middleware,
-    routes::task_attempts::WorkspaceRepoInput,
+    DeploymentImpl,
+    error::ApiError,
+    middleware::load_task_middleware,
+    routes::task_attempts::{WorkspaceRepoInput, launch_profile},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -145,6 +147,10 @@ pub struct CreateAndStartTaskRequest {
     pub task: CreateTask,
     pub executor_profile_id: ExecutorProfileId,
     pub repos: Vec<WorkspaceRepoInput>,
+    /// Run template to launch with; its profile takes precedence over
+    /// `executor_profile_id`
+    #[serde(default)]
+    pub run_template: Option<String>,
 }
 
 pub async fn create_task_and_start(
@@ -156,6 +162,12 @@ pub async fn create_task_and_start(
             "At least one repository is required".to_string(),
         ));
     }
+    let executor_profile_id = launch_profile(
+        &deployment,
+        &payload.executor_profile_id,
+        payload.run_template.as_deref(),
+    )
+    .await?;
 
     let pool = &deployment.db().pool;
 
@@ -217,7 +229,11 @@ pub async fn create_task_and_start(
 
     let is_attempt_running = deployment
         .container()
-        .start_workspace(&workspace, payload.executor_profile_id.clone())
+        .start_workspace(
+            &workspace,
+            executor_profile_id.clone(),
+            payload.run_template.clone(),
+        )
         .await
         .inspect_err(|err| tracing::error!("Failed to start task attempt: {}", err))
         .is_ok();
@@ -226,8 +242,8 @@ pub async fn create_task_and_start(
             "task_attempt_started",
             serde_json::json!({
                 "task_id": task.id.to_string(),
-                "executor": &payload.executor_profile_id.executor,
-                "variant": &payload.executor_profile_id.variant,
+                "executor": &executor_profile_id.executor,
+                "variant": &executor_profile_id.variant,
                 "workspace_id": workspace.id.to_string(),
             }),
         )
@@ -242,7 +258,7 @@ pub async fn create_task_and_start(
         task,
         has_in_progress_attempt: is_attempt_running,
         last_attempt_failed: false,
-        executor: payload.executor_profile_id.executor.to_string(),
+        executor: executor_profile_id.executor.to_string(),
     })))
 }
 
@@ -363,6 +363,8 @@ mod tests {
                 })
                 .unwrap(),
             ),
+            agent_id: None,
+            agent_role: None,
         }
     }
 
@@ -1,5 +1,8 @@
 use anyhow::Error;
-use executors::{executors::BaseCodingAgent, profile::ExecutorProfileId};
+use executors::{
+    authorization::Principal, executors::BaseCodingAgent, profile::ExecutorProfileId,
+    tenant::TenantId,
+};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 pub use v7::{
@@ -41,6 +44,20 @@ pub struct Config {
     pub pr_auto_description_enabled: bool,
     #[serde(default)]
     pub pr_auto_description_prompt: Option<String>,
+    /// Opt-in aggregation of anonymized executor usage
+    #[serde(default)]
+    pub executor_telemetry_enabled: bool,
+    /// Snapshot worktrees before approved edits so they can be rolled back
+    #[serde(default)]
+    pub checkpoints_enabled: bool,
+    /// Who executions run on behalf of, checked against the role policies
+    /// in profiles.json; unset runs them unchecked
+    #[serde(default)]
+    pub principal: Option<Principal>,
+    /// Tenant new sessions run as, with its profiles and credentials; unset
+    /// runs them as the server's own
+    #[serde(default)]
+    pub tenant: Option<TenantId>,
 }
 
 impl Config {
@@ -66,6 +83,10 @@ impl Config {
             showcases: old_config.showcases,
             pr_auto_description_enabled: true,
             pr_auto_description_prompt: None,
+            executor_telemetry_enabled: false,
+            checkpoints_enabled: false,
+            principal: None,
+            tenant: None,
         }
     }
 
@@ -116,6 +137,10 @@ impl Default for Config {
             showcases: ShowcaseState::default(),
             pr_auto_description_enabled: true,
             pr_auto_description_prompt: None,
+            executor_telemetry_enabled: false,
+            checkpoints_enabled: false,
+            principal: None,
+            tenant: None,
         }
     }
 }
@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::{Error as AnyhowError, anyhow};
@@ -31,25 +32,47 @@ use executors::{
     actions::{
         ExecutorAction, ExecutorActionType,
         coding_agent_initial::CodingAgentInitialRequest,
+        pipeline::{ExecutorActionPipeline, RunCondition},
         script::{ScriptContext, ScriptRequest, ScriptRequestLanguage},
     },
+    cancellation::{CancelReason, CancelScope},
+    context_overflow, encryption,
     executors::{ExecutorError, StandardCodingAgentExecutor},
-    logs::{NormalizedEntry, NormalizedEntryError, NormalizedEntryType, utils::ConversationPatch},
+    logs::{
+        NormalizedConversation, NormalizedEntry, NormalizedEntryError, NormalizedEntryType,
+        file_changes::{FileChangeEntry, file_changes_from_history},
+        search::{LogSearchHit, LogSearchIndex, LogSearchQuery, live_search_index},
+        stderr_processor::ExecutorFailureReason,
+        tool_stats::{ToolStatsCollector, live_tool_stats, spawn_tool_stats},
+        transcript::TranscriptOptions,
+        utils::ConversationPatch,
+    },
     profile::{ExecutorConfigs, ExecutorProfileId},
+    replay::{self, Recording},
+    retry::RetryPolicy,
+    runtime::ExecutorsRuntime,
+    sessions::SessionManifest,
+    telemetry::Telemetry,
+    tenant::TenantId,
 };
 use futures::{StreamExt, future};
+use serde::Serialize;
 use sqlx::Error as SqlxError;
 use thiserror::Error;
 use tokio::{sync::RwLock, task::JoinHandle};
+use ts_rs::TS;
 use utils::{
+    assets::asset_dir,
+    diff::Diff,
     log_msg::LogMsg,
-    msg_store::MsgStore,
+    msg_store::{self, MsgStore},
+    semantic_diff,
     text::{git_branch_id, short_uuid},
 };
 use uuid::Uuid;
 
 use crate::services::{
-    git::{GitService, GitServiceError},
+    git::{DiffTarget, FileSelection, GitService, GitServiceError, RollbackStrategy},
     notification::NotificationService,
     share::SharePublisher,
     workspace_manager::WorkspaceError as WorkspaceManagerError,
@@ -57,6 +80,32 @@ use crate::services::{
 };
 pub type ContainerRef = String;
 
+/// How long the log store of an execution is kept on disk after it was last
+/// written to; older executions stream their logs from the database
+pub const PERSISTED_MSG_STORE_RETENTION: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+fn persisted_msg_stores_dir() -> PathBuf {
+    asset_dir().join("execution-logs")
+}
+
+/// Directory the log store of an execution is persisted to
+pub fn persisted_msg_store_dir(execution_id: &Uuid) -> PathBuf {
+    persisted_msg_stores_dir().join(execution_id.to_string())
+}
+
+/// Remove the log stores past [`PERSISTED_MSG_STORE_RETENTION`], returning
+/// how many were removed
+pub fn prune_persisted_msg_stores() -> std::io::Result<usize> {
+    msg_store::prune_persisted(&persisted_msg_stores_dir(), PERSISTED_MSG_STORE_RETENTION)
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct RepoRollback {
+    pub repo_id: Uuid,
+    /// New branch head, or None when the execution made no commits in this repo
+    pub new_head: Option<String>,
+}
+
 #[derive(Debug, Error)]
 pub enum ContainerError {
     #[error(transparent)]
@@ -77,6 +126,8 @@ pub enum ContainerError {
     Io(#[from] std::io::Error),
     #[error("Failed to kill process: {0}")]
     KillFailed(std::io::Error),
+    #[error("Execution process {0} is not accepting input")]
+    InputNotAccepted(Uuid),
     #[error(transparent)]
     Other(#[from] AnyhowError), // Catches any unclassified errors
 }
@@ -93,8 +144,36 @@ pub trait ContainerService {
 
     fn notification_service(&self) -> &NotificationService;
 
+    /// Cancel scopes of running executions, one tree per workspace, and the
+    /// writes flushed on shutdown
+    fn runtime(&self) -> &ExecutorsRuntime;
+
     fn workspace_to_current_dir(&self, workspace: &Workspace) -> PathBuf;
 
+    /// Tenant new sessions run as; `None` runs them as the server's own
+    async fn default_tenant(&self) -> Option<TenantId>;
+
+    /// Tenant an execution in `session` runs as: the one `action` names, else
+    /// the one the session's executions ran as, else the default
+    async fn execution_tenant(
+        &self,
+        session: &Session,
+        action: &ExecutorAction,
+    ) -> Result<Option<TenantId>, ContainerError> {
+        if action.tenant.is_some() {
+            return Ok(action.tenant.clone());
+        }
+        let processes =
+            ExecutionProcess::find_by_session_id(&self.db().pool, session.id, true).await?;
+        match processes.last() {
+            Some(process) => Ok(process
+                .executor_action()
+                .ok()
+                .and_then(|action| action.tenant.clone())),
+            None => Ok(self.default_tenant().await),
+        }
+    }
+
     async fn create(&self, workspace: &Workspace) -> Result<ContainerRef, ContainerError>;
 
     async fn kill_all_running_processes(&self) -> Result<(), ContainerError>;
@@ -149,16 +228,17 @@ pub trait ContainerService {
             return false;
         }
 
-        // Always finalize failed or killed executions, regardless of next action
-        if matches!(
-            ctx.execution_process.status,
-            ExecutionProcessStatus::Failed | ExecutionProcessStatus::Killed
-        ) {
+        // Always finalize killed executions, regardless of next action
+        if matches!(ctx.execution_process.status, ExecutionProcessStatus::Killed) {
             return true;
         }
 
-        // Otherwise, finalize only if no next action
-        action.next_action.is_none()
+        // Otherwise, finalize only if nothing runs after this outcome
+        let succeeded = matches!(
+            ctx.execution_process.status,
+            ExecutionProcessStatus::Completed
+        );
+        action.next_action_on_exit(succeeded).is_none()
     }
 
     /// Finalize task execution by updating status to InReview and sending notifications
@@ -425,40 +505,7 @@ pub trait ContainerService {
     }
 
     fn cleanup_actions_for_repos(&self, repos: &[ProjectRepoWithName]) -> Option<ExecutorAction> {
-        let repos_with_cleanup: Vec<_> = repos
-            .iter()
-            .filter(|r| r.cleanup_script.is_some())
-            .collect();
-
-        if repos_with_cleanup.is_empty() {
-            return None;
-        }
-
-        let mut iter = repos_with_cleanup.iter();
-        let first = iter.next()?;
-        let mut root_action = ExecutorAction::new(
-            ExecutorActionType::ScriptRequest(ScriptRequest {
-                script: first.cleanup_script.clone().unwrap(),
-                language: ScriptRequestLanguage::Bash,
-                context: ScriptContext::CleanupScript,
-                working_dir: Some(first.repo_name.clone()),
-            }),
-            None,
-        );
-
-        for repo in iter {
-            root_action = root_action.append_action(ExecutorAction::new(
-                ExecutorActionType::ScriptRequest(ScriptRequest {
-                    script: repo.cleanup_script.clone().unwrap(),
-                    language: ScriptRequestLanguage::Bash,
-                    context: ScriptContext::CleanupScript,
-                    working_dir: Some(repo.repo_name.clone()),
-                }),
-                None,
-            ));
-        }
-
-        Some(root_action)
+        cleanup_pipeline(repos)
     }
 
     fn setup_actions_for_repos(&self, repos: &[ProjectRepoWithName]) -> Option<ExecutorAction> {
@@ -476,6 +523,7 @@ pub trait ContainerService {
                 language: ScriptRequestLanguage::Bash,
                 context: ScriptContext::SetupScript,
                 working_dir: Some(first.repo_name.clone()),
+                timeout_secs: None,
             }),
             None,
         );
@@ -487,6 +535,7 @@ pub trait ContainerService {
                     language: ScriptRequestLanguage::Bash,
                     context: ScriptContext::SetupScript,
                     working_dir: Some(repo.repo_name.clone()),
+                    timeout_secs: None,
                 }),
                 None,
             ));
@@ -503,6 +552,7 @@ pub trait ContainerService {
                     language: ScriptRequestLanguage::Bash,
                     context: ScriptContext::SetupScript,
                     working_dir: Some(repo.repo_name.clone()),
+                    timeout_secs: None,
                 }),
                 None,
             )
@@ -522,6 +572,7 @@ pub trait ContainerService {
                         language: ScriptRequestLanguage::Bash,
                         context: ScriptContext::SetupScript,
                         working_dir: Some(repo.repo_name.clone()),
+                        timeout_secs: None,
                     }),
                     Some(Box::new(chained)),
                 );
@@ -531,6 +582,11 @@ pub trait ContainerService {
     }
 
     async fn try_stop(&self, workspace: &Workspace, include_dev_server: bool) {
+        // Cancel first so that nothing chained from the running processes starts
+        self.runtime()
+            .cancel_tree()
+            .cancel_tree(&workspace.id, CancelReason::UserRequested);
+
         // stop execution processes for this workspace's sessions
         let sessions = match Session::find_by_workspace_id(&self.db().pool, workspace.id).await {
             Ok(s) => s,
@@ -577,6 +633,7 @@ pub trait ContainerService {
         workspace: &Workspace,
         execution_process: &ExecutionProcess,
         executor_action: &ExecutorAction,
+        cancel: &CancelScope,
     ) -> Result<(), ContainerError>;
 
     async fn stop_execution(
@@ -585,6 +642,13 @@ pub trait ContainerService {
         status: ExecutionProcessStatus,
     ) -> Result<(), ContainerError>;
 
+    /// Send `message` to the live session of an interactive execution
+    async fn send_user_input(
+        &self,
+        execution_process: &ExecutionProcess,
+        message: String,
+    ) -> Result<(), ContainerError>;
+
     async fn try_commit_changes(&self, ctx: &ExecutionContext) -> Result<bool, ContainerError>;
 
     async fn copy_project_files(
@@ -601,12 +665,172 @@ pub trait ContainerService {
         stats_only: bool,
     ) -> Result<futures::stream::BoxStream<'static, Result<LogMsg, std::io::Error>>, ContainerError>;
 
+    /// Undo the commits an execution produced in each of its repositories,
+    /// using the before/after head commits recorded for the process.
+    async fn rollback_execution(
+        &self,
+        execution_process_id: Uuid,
+        strategy: RollbackStrategy,
+    ) -> Result<Vec<RepoRollback>, ContainerError> {
+        let pool = &self.db().pool;
+        let ctx = ExecutionProcess::load_context(pool, execution_process_id).await?;
+
+        if ExecutionProcess::has_running_non_dev_server_processes_for_workspace(
+            pool,
+            ctx.workspace.id,
+        )
+        .await?
+        {
+            return Err(ContainerError::Other(anyhow!(
+                "Cannot roll back while the workspace has running processes"
+            )));
+        }
+
+        let repo_states =
+            ExecutionProcessRepoState::find_by_execution_process_id(pool, execution_process_id)
+                .await?;
+        let workspace_dir = PathBuf::from(self.ensure_container_exists(&ctx.workspace).await?);
+        let message = format!(
+            "Revert changes from execution {}",
+            short_uuid(&execution_process_id)
+        );
+
+        let mut results = Vec::with_capacity(ctx.repos.len());
+        for repo in &ctx.repos {
+            let state = repo_states.iter().find(|s| s.repo_id == repo.id);
+            let new_head = match state.map(|s| (&s.before_head_commit, &s.after_head_commit)) {
+                Some((Some(before), Some(after))) => self.git().rollback_commits(
+                    &workspace_dir.join(&repo.name),
+                    before,
+                    after,
+                    strategy,
+                    &message,
+                )?,
+                _ => None,
+            };
+            results.push(RepoRollback {
+                repo_id: repo.id,
+                new_head,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Resolve the workspace, repository and commit range an execution produced in it
+    async fn execution_commit_range(
+        &self,
+        execution_process_id: Uuid,
+        repo_id: Uuid,
+    ) -> Result<(Workspace, Repo, String, String), ContainerError> {
+        let pool = &self.db().pool;
+        let ctx = ExecutionProcess::load_context(pool, execution_process_id).await?;
+        let repo = ctx
+            .repos
+            .into_iter()
+            .find(|r| r.id == repo_id)
+            .ok_or_else(|| anyhow!("Repository {repo_id} is not part of this execution"))?;
+
+        let state =
+            ExecutionProcessRepoState::find_by_execution_process_id(pool, execution_process_id)
+                .await?
+                .into_iter()
+                .find(|s| s.repo_id == repo_id);
+        match state.map(|s| (s.before_head_commit, s.after_head_commit)) {
+            Some((Some(before), Some(after))) => Ok((ctx.workspace, repo, before, after)),
+            _ => Err(ContainerError::Other(anyhow!(
+                "Execution has no recorded commits for repository {}",
+                repo.name
+            ))),
+        }
+    }
+
+    /// Diff of the commits an execution produced in a repository, including
+    /// the hunk metadata used to select partial changes and a per-file summary
+    /// of changed functions and types.
+    async fn execution_diff(
+        &self,
+        execution_process_id: Uuid,
+        repo_id: Uuid,
+    ) -> Result<Vec<Diff>, ContainerError> {
+        let (_, repo, before, after) = self
+            .execution_commit_range(execution_process_id, repo_id)
+            .await?;
+        let mut diffs = self.git().get_diffs(
+            DiffTarget::Range {
+                repo_path: &repo.path,
+                from_commit: &before,
+                to_commit: &after,
+            },
+            None,
+        )?;
+        for diff in diffs.iter_mut().filter(|d| !d.content_omitted) {
+            diff.semantic_summary = semantic_diff::summarize_changes(
+                &GitService::diff_path(diff),
+                diff.old_content.as_deref().unwrap_or(""),
+                diff.new_content.as_deref().unwrap_or(""),
+            );
+        }
+        Ok(diffs)
+    }
+
+    /// Apply the selected files/hunks of an execution's commits to the
+    /// repository's target branch as a new commit.
+    async fn apply_execution_changes(
+        &self,
+        execution_process_id: Uuid,
+        repo_id: Uuid,
+        selection: &[FileSelection],
+    ) -> Result<Option<String>, ContainerError> {
+        let (workspace, repo, before, after) = self
+            .execution_commit_range(execution_process_id, repo_id)
+            .await?;
+        let workspace_repo =
+            WorkspaceRepo::find_by_workspace_and_repo_id(&self.db().pool, workspace.id, repo_id)
+                .await?
+                .ok_or(SqlxError::RowNotFound)?;
+
+        let message = format!(
+            "Apply selected changes from execution {}",
+            short_uuid(&execution_process_id)
+        );
+        Ok(self.git().apply_selected_changes(
+            &repo.path,
+            &before,
+            &after,
+            &workspace_repo.target_branch,
+            selection,
+            &message,
+        )?)
+    }
+
     /// Fetch the MsgStore for a given execution ID, panicking if missing.
     async fn get_msg_store_by_id(&self, uuid: &Uuid) -> Option<Arc<MsgStore>> {
         let map = self.msg_stores().read().await;
         map.get(uuid).cloned()
     }
 
+    /// Log store an execution persisted before it left memory, e.g. one that
+    /// ran before a restart
+    async fn load_persisted_msg_store(&self, id: &Uuid) -> Option<Arc<MsgStore>> {
+        let dir = persisted_msg_store_dir(id);
+        match tokio::task::spawn_blocking(move || {
+            MsgStore::load_persisted(&dir, encryption::LINE_CODEC)
+        })
+        .await
+        {
+            Ok(Ok(store)) => store.map(Arc::new),
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to load persisted logs for execution {}: {}", id, e);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load persisted logs for execution {}: {}", id, e);
+                None
+            }
+        }
+    }
+
     async fn git_branch_prefix(&self) -> String;
 
     async fn git_branch_from_workspace(&self, workspace_id: &Uuid, task_title: &str) -> String {
@@ -624,8 +848,12 @@ pub trait ContainerService {
         &self,
         id: &Uuid,
     ) -> Option<futures::stream::BoxStream<'static, Result<LogMsg, std::io::Error>>> {
-        if let Some(store) = self.get_msg_store_by_id(id).await {
-            // First try in-memory store
+        let store = match self.get_msg_store_by_id(id).await {
+            Some(store) => Some(store),
+            None => self.load_persisted_msg_store(id).await,
+        };
+        if let Some(store) = store {
+            // First try in-memory or persisted store
             return Some(
                 store
                     .history_plus_stream()
@@ -675,12 +903,27 @@ pub trait ContainerService {
         &self,
         id: &Uuid,
     ) -> Option<futures::stream::BoxStream<'static, Result<LogMsg, std::io::Error>>> {
-        // First try in-memory store (existing behavior)
-        if let Some(store) = self.get_msg_store_by_id(id).await {
+        // First try in-memory store (existing behavior), then a persisted one
+        // that already holds normalized entries
+        let store = match self.get_msg_store_by_id(id).await {
+            Some(store) => Some(store),
+            None => self.load_persisted_msg_store(id).await.filter(|store| {
+                store
+                    .get_history()
+                    .iter()
+                    .any(|msg| matches!(msg, LogMsg::JsonPatch(_)))
+            }),
+        };
+        if let Some(store) = store {
             Some(
                 store
                     .history_plus_stream() // BoxStream<Result<LogMsg, io::Error>>
-                    .filter(|msg| future::ready(matches!(msg, Ok(LogMsg::JsonPatch(..)))))
+                    .filter(|msg| {
+                        future::ready(matches!(
+                            msg,
+                            Ok(LogMsg::JsonPatch(..) | LogMsg::Heartbeat(..))
+                        ))
+                    })
                     .chain(futures::stream::once(async {
                         Ok::<_, std::io::Error>(LogMsg::Finished)
                     }))
@@ -773,18 +1016,25 @@ pub trait ContainerService {
             };
 
             // Spawn normalizer on populated store
+            let configs = ExecutorConfigs::get_cached_for(executor_action.tenant.as_ref());
             match executor_action.typ() {
                 ExecutorActionType::CodingAgentInitialRequest(request) => {
-                    let executor = ExecutorConfigs::get_cached()
-                        .get_coding_agent_or_default(&request.executor_profile_id);
-                    executor
-                        .normalize_logs(temp_store.clone(), &request.effective_dir(&current_dir));
+                    let executor =
+                        configs.get_coding_agent_or_default(&request.executor_profile_id);
+                    executor.normalize_logs_with_pipeline(
+                        temp_store.clone(),
+                        &request.effective_dir(&current_dir),
+                        &CancelScope::new(),
+                    );
                 }
                 ExecutorActionType::CodingAgentFollowUpRequest(request) => {
-                    let executor = ExecutorConfigs::get_cached()
-                        .get_coding_agent_or_default(&request.executor_profile_id);
-                    executor
-                        .normalize_logs(temp_store.clone(), &request.effective_dir(&current_dir));
+                    let executor =
+                        configs.get_coding_agent_or_default(&request.executor_profile_id);
+                    executor.normalize_logs_with_pipeline(
+                        temp_store.clone(),
+                        &request.effective_dir(&current_dir),
+                        &CancelScope::new(),
+                    );
                 }
                 _ => {
                     tracing::debug!(
@@ -806,12 +1056,85 @@ pub trait ContainerService {
         }
     }
 
+    /// Normalized log history of an execution, from its live store if it has
+    /// one and re-normalized from the stored raw logs otherwise
+    async fn execution_normalized_history(&self, id: &Uuid) -> Option<Vec<LogMsg>> {
+        if let Some(store) = self.get_msg_store_by_id(id).await {
+            return Some(store.get_history());
+        }
+        let history = self
+            .stream_normalized_logs(id)
+            .await?
+            .filter_map(|msg| future::ready(msg.ok()))
+            .collect()
+            .await;
+        Some(history)
+    }
+
+    /// File changes an execution made according to its normalized logs, including
+    /// edits made through tools the executor doesn't normalize itself
+    async fn execution_file_changes(&self, id: &Uuid) -> Option<Vec<FileChangeEntry>> {
+        let history = self.execution_normalized_history(id).await?;
+        Some(file_changes_from_history(&history))
+    }
+
+    /// Normalized entries of an execution matching `query`. Live executions
+    /// are searched through an index kept up to date as entries arrive.
+    async fn search_execution_logs(
+        &self,
+        id: &Uuid,
+        query: &LogSearchQuery,
+    ) -> Option<Vec<LogSearchHit>> {
+        if let Some(store) = self.get_msg_store_by_id(id).await {
+            let index = live_search_index(*id, &store);
+            return Some(index.read().unwrap().search(query));
+        }
+        let history = self.execution_normalized_history(id).await?;
+        Some(LogSearchIndex::from_history(&history).search(query))
+    }
+
+    /// Tool call statistics of an execution. Only running executions have
+    /// their calls timed.
+    async fn execution_tool_stats(&self, id: &Uuid) -> Option<ToolStatsCollector> {
+        if let Some(collector) = live_tool_stats(id) {
+            return Some(collector);
+        }
+        let history = self.execution_normalized_history(id).await?;
+        Some(ToolStatsCollector::from_history(&history))
+    }
+
+    /// Conversation of a coding agent execution, for export
+    async fn execution_transcript(
+        &self,
+        execution_process: &ExecutionProcess,
+        options: &TranscriptOptions,
+    ) -> Option<NormalizedConversation> {
+        let (executor_type, prompt) = match execution_process.executor_action().ok()?.typ() {
+            ExecutorActionType::CodingAgentInitialRequest(request) => {
+                (request.base_executor(), request.prompt.clone())
+            }
+            ExecutorActionType::CodingAgentFollowUpRequest(request) => {
+                (request.base_executor(), request.prompt.clone())
+            }
+            ExecutorActionType::ScriptRequest(_) => return None,
+        };
+        let history = self
+            .execution_normalized_history(&execution_process.id)
+            .await?;
+        Some(NormalizedConversation::from_history(
+            &history,
+            executor_type.to_string(),
+            Some(prompt),
+            options,
+        ))
+    }
+
     fn spawn_stream_raw_logs_to_db(&self, execution_id: &Uuid) -> JoinHandle<()> {
         let execution_id = *execution_id;
         let msg_stores = self.msg_stores().clone();
         let db = self.db().clone();
 
-        tokio::spawn(async move {
+        self.runtime().spawn_write(async move {
             // Get the message store for this execution
             let store = {
                 let map = msg_stores.read().await;
@@ -873,7 +1196,7 @@ pub trait ContainerService {
                         LogMsg::Finished => {
                             break;
                         }
-                        LogMsg::JsonPatch(_) | LogMsg::Ready => continue,
+                        LogMsg::JsonPatch(_) | LogMsg::Ready | LogMsg::Heartbeat(_) => continue,
                     }
                 }
             }
@@ -884,7 +1207,20 @@ pub trait ContainerService {
         &self,
         workspace: &Workspace,
         executor_profile_id: ExecutorProfileId,
+        run_template: Option<String>,
     ) -> Result<ExecutionProcess, ContainerError> {
+        let template = match &run_template {
+            Some(name) => Some(
+                ExecutorConfigs::get_cached_for(self.default_tenant().await.as_ref())
+                    .get_template(name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        ContainerError::Other(anyhow!("Run template '{name}' not found"))
+                    })?,
+            ),
+            None => None,
+        };
+
         // Create container
         self.create(workspace).await?;
 
@@ -918,7 +1254,10 @@ pub trait ContainerService {
         )
         .await?;
 
-        let prompt = task.to_prompt();
+        let prompt = match &template {
+            Some(template) => template.render_prompt(&task.to_prompt()),
+            None => task.to_prompt(),
+        };
 
         let repos_with_setup: Vec<_> = project_repos
             .iter()
@@ -927,7 +1266,7 @@ pub trait ContainerService {
 
         let all_parallel = repos_with_setup.iter().all(|pr| pr.parallel_setup_script);
 
-        let cleanup_action = self.cleanup_actions_for_repos(&project_repos);
+        let mut cleanup_action = self.cleanup_actions_for_repos(&project_repos);
 
         let working_dir = workspace
             .agent_working_dir
@@ -935,11 +1274,19 @@ pub trait ContainerService {
             .filter(|dir| !dir.is_empty())
             .cloned();
 
+        if let Some(template) = &template {
+            cleanup_action = template.post_step_actions(working_dir.clone(), cleanup_action);
+        }
+
         let coding_action = ExecutorAction::new(
             ExecutorActionType::CodingAgentInitialRequest(CodingAgentInitialRequest {
                 prompt,
                 executor_profile_id: executor_profile_id.clone(),
                 working_dir,
+                cwd_relative: None,
+                run_template,
+                attachments: Vec::new(),
+                review: None,
             }),
             cleanup_action.map(Box::new),
         );
@@ -989,6 +1336,9 @@ pub trait ContainerService {
         executor_action: &ExecutorAction,
         run_reason: &ExecutionProcessRunReason,
     ) -> Result<ExecutionProcess, ContainerError> {
+        // Refuse new executions once the runtime is shutting down
+        self.runtime().check_accepting()?;
+
         // Update task status to InProgress when starting an execution
         let task = workspace
             .parent_task(&self.db().pool)
@@ -1036,6 +1386,11 @@ pub trait ContainerService {
                 merge_commit: None,
             });
         }
+        // The tenant is stored with the execution, so everything done for it
+        // later uses the same profiles, secrets and budget
+        let executor_action = &executor_action
+            .clone()
+            .with_tenant(self.execution_tenant(session, executor_action).await?);
         let create_execution_process = CreateExecutionProcess {
             session_id: session.id,
             executor_action: executor_action.clone(),
@@ -1076,15 +1431,44 @@ pub trait ContainerService {
             .await?;
         }
 
+        // Dev servers outlive the agent runs in their workspace, so they are
+        // cancelled on their own
+        let cancel_root = match run_reason {
+            ExecutionProcessRunReason::DevServer => execution_process.id,
+            _ => workspace.id,
+        };
+        let cancel = self
+            .runtime()
+            .cancel_tree()
+            .start_execution(cancel_root, execution_process.id);
         if let Err(start_error) = self
-            .start_execution_inner(workspace, &execution_process, executor_action)
+            .start_execution_inner(workspace, &execution_process, executor_action, &cancel)
             .await
         {
-            // Mark process as failed
+            self.runtime()
+                .cancel_tree()
+                .finish_execution(&execution_process.id);
+
+            // Mark process as failed, or killed if it was cancelled before it started
+            let status = match &start_error {
+                ContainerError::ExecutorError(ExecutorError::Cancelled(_)) => {
+                    ExecutionProcessStatus::Killed
+                }
+                _ => ExecutionProcessStatus::Failed,
+            };
+            if let ContainerError::ExecutorError(error) = &start_error
+                && let Some(executor) = executor_action.base_executor()
+            {
+                Telemetry::global().record_spawn_failure(
+                    executor,
+                    executor_action.tenant.as_ref(),
+                    error,
+                );
+            }
             if let Err(update_error) = ExecutionProcess::update_completion(
                 &self.db().pool,
                 execution_process.id,
-                ExecutionProcessStatus::Failed,
+                status,
                 None,
             )
             .await
@@ -1120,6 +1504,8 @@ pub trait ContainerService {
                     },
                     content: help_text,
                     metadata: None,
+                    agent_id: None,
+                    agent_role: None,
                 };
                 let patch = ConversationPatch::add_normalized_entry(2, error_message);
                 if let Ok(json_line) = serde_json::to_string::<LogMsg>(&LogMsg::JsonPatch(patch)) {
@@ -1137,22 +1523,37 @@ pub trait ContainerService {
         // Start processing normalised logs for executor requests and follow ups
         let workspace_root = self.workspace_to_current_dir(workspace);
         if let Some(msg_store) = self.get_msg_store_by_id(&execution_process.id).await
-            && let Some((executor_profile_id, working_dir)) = match executor_action.typ() {
+            && let Some((executor_profile_id, working_dir, prompt)) = match executor_action.typ() {
                 ExecutorActionType::CodingAgentInitialRequest(request) => Some((
                     &request.executor_profile_id,
                     request.effective_dir(&workspace_root),
+                    &request.prompt,
                 )),
                 ExecutorActionType::CodingAgentFollowUpRequest(request) => Some((
                     &request.executor_profile_id,
                     request.effective_dir(&workspace_root),
+                    &request.prompt,
                 )),
                 _ => None,
             }
         {
-            if let Some(executor) =
-                ExecutorConfigs::get_cached().get_coding_agent(executor_profile_id)
+            if let Some(executor) = ExecutorConfigs::get_cached_for(executor_action.tenant.as_ref())
+                .get_coding_agent(executor_profile_id)
             {
-                executor.normalize_logs(msg_store, &working_dir);
+                if let Some(dir) = replay::record_dir() {
+                    Recording::spawn_recorder(
+                        msg_store.clone(),
+                        dir.join(format!("{}.jsonl", execution_process.id)),
+                    );
+                }
+                SessionManifest::for_worktree(&working_dir).spawn_recorder(
+                    msg_store.clone(),
+                    executor_profile_id.executor,
+                    working_dir.clone(),
+                    prompt,
+                );
+                spawn_tool_stats(execution_process.id, &msg_store);
+                executor.normalize_logs_with_pipeline(msg_store, &working_dir, &cancel);
             } else {
                 tracing::error!(
                     "Failed to resolve profile '{:?}' for normalization",
@@ -1167,7 +1568,11 @@ pub trait ContainerService {
 
     async fn try_start_next_action(&self, ctx: &ExecutionContext) -> Result<(), ContainerError> {
         let action = ctx.execution_process.executor_action()?;
-        let next_action = if let Some(next_action) = action.next_action() {
+        let succeeded = matches!(
+            ctx.execution_process.status,
+            ExecutionProcessStatus::Completed
+        );
+        let next_action = if let Some(next_action) = action.next_action_on_exit(succeeded) {
             next_action
         } else {
             tracing::debug!("No next action configured");
@@ -1176,6 +1581,11 @@ pub trait ContainerService {
 
         // Determine the run reason of the next action
         let next_run_reason = match (action.typ(), next_action.typ()) {
+            (ExecutorActionType::ScriptRequest(_), ExecutorActionType::ScriptRequest(next))
+                if next.context == ScriptContext::CleanupScript =>
+            {
+                ExecutionProcessRunReason::CleanupScript
+            }
             (ExecutorActionType::ScriptRequest(_), ExecutorActionType::ScriptRequest(_)) => {
                 ExecutionProcessRunReason::SetupScript
             }
@@ -1197,4 +1607,251 @@ pub trait ContainerService {
         tracing::debug!("Started next action: {:?}", next_action);
         Ok(())
     }
+
+    /// Start a failed coding agent run again when its profile's retry policy
+    /// covers `reason`, after the policy's backoff. Returns whether a retry
+    /// was started.
+    async fn retry_failed_execution(
+        &self,
+        ctx: &ExecutionContext,
+        reason: ExecutorFailureReason,
+    ) -> bool {
+        let Ok(action) = ctx.execution_process.executor_action() else {
+            return false;
+        };
+        let executor_profile_id = match action.typ() {
+            ExecutorActionType::CodingAgentInitialRequest(request) => &request.executor_profile_id,
+            ExecutorActionType::CodingAgentFollowUpRequest(request) => &request.executor_profile_id,
+            ExecutorActionType::ScriptRequest(_) => return false,
+        };
+        let configs = ExecutorConfigs::get_cached_for(action.tenant.as_ref());
+        let policy = RetryPolicy::from(
+            &configs
+                .get_coding_agent_or_default(executor_profile_id)
+                .run_overrides()
+                .runtime,
+        );
+        if !policy.retries_failure(reason) {
+            return false;
+        }
+
+        let processes = match ExecutionProcess::find_by_session_id(
+            &self.db().pool,
+            ctx.session.id,
+            false,
+        )
+        .await
+        {
+            Ok(processes) => processes,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to count attempts of session {}: {e}",
+                    ctx.session.id
+                );
+                return false;
+            }
+        };
+        let is_compaction = |process: &ExecutionProcess| {
+            process
+                .executor_action()
+                .is_ok_and(|action| context_overflow::is_compaction(action, &configs))
+        };
+        let mut coding_agent_runs = processes
+            .iter()
+            .rev()
+            .filter(|process| process.run_reason == ExecutionProcessRunReason::CodingAgent);
+        // Attempts so far are the failed coding agent runs at the end of the
+        // session, this one included, and the compactions between them
+        let attempts = coding_agent_runs
+            .clone()
+            .take_while(|process| {
+                process.status == ExecutionProcessStatus::Failed || is_compaction(process)
+            })
+            .count() as u32;
+        if attempts >= policy.max_attempts {
+            tracing::info!(
+                "Not retrying execution {} ({reason:?}): {attempts} of {} attempts used",
+                ctx.execution_process.id,
+                policy.max_attempts
+            );
+            return false;
+        }
+
+        // An overflowed context is compacted, or left behind, before the
+        // prompt is tried again
+        let retry_action = if reason == ExecutorFailureReason::ContextOverflow {
+            let compacted = coding_agent_runs
+                .find(|process| process.id != ctx.execution_process.id)
+                .is_some_and(is_compaction);
+            let history = self
+                .execution_normalized_history(&ctx.execution_process.id)
+                .await
+                .unwrap_or_default();
+            match context_overflow::overflow_retry(action, &history, compacted, &configs) {
+                Some(retry_action) => retry_action,
+                None => return false,
+            }
+        } else {
+            action.clone()
+        };
+
+        // Stopping the workspace during the backoff drops the retry, since a
+        // new run would start under a fresh, uncancelled root
+        let cancel = self
+            .runtime()
+            .cancel_tree()
+            .execution(&ctx.execution_process.id)
+            .unwrap_or_default();
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                tracing::info!(
+                    "Not retrying execution {} ({reason:?}): cancelled during backoff",
+                    ctx.execution_process.id
+                );
+                return false;
+            }
+            _ = tokio::time::sleep(policy.backoff(attempts)) => {}
+        }
+        match self
+            .start_execution(
+                &ctx.workspace,
+                &ctx.session,
+                &retry_action,
+                &ctx.execution_process.run_reason,
+            )
+            .await
+        {
+            Ok(retry) => {
+                tracing::info!(
+                    "Retrying execution {} ({reason:?}) as {}, attempt {} of {}",
+                    ctx.execution_process.id,
+                    retry.id,
+                    attempts + 1,
+                    policy.max_attempts
+                );
+                true
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to retry execution {}: {e}",
+                    ctx.execution_process.id
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Cleanup scripts of the repos that have one, in order. The first one runs
+/// once the step before it succeeded; each other one runs whether or not the
+/// cleanup before it did, so one failing repo doesn't keep the rest dirty.
+fn cleanup_pipeline(repos: &[ProjectRepoWithName]) -> Option<ExecutorAction> {
+    let mut actions = repos.iter().filter_map(|repo| {
+        Some(ExecutorActionType::ScriptRequest(ScriptRequest {
+            script: repo.cleanup_script.clone()?,
+            language: ScriptRequestLanguage::Bash,
+            context: ScriptContext::CleanupScript,
+            working_dir: Some(repo.repo_name.clone()),
+            timeout_secs: None,
+        }))
+    });
+    let pipeline = ExecutorActionPipeline::new(actions.next()?);
+    Some(
+        actions
+            .fold(pipeline, |pipeline, action| {
+                pipeline.then_if(RunCondition::Always, action)
+            })
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use executors::{executors::BaseCodingAgent, profile::ExecutorProfileId};
+
+    use super::*;
+
+    fn repo(name: &str, cleanup_script: Option<&str>) -> ProjectRepoWithName {
+        ProjectRepoWithName {
+            id: Uuid::new_v4(),
+            project_id: Uuid::nil(),
+            repo_id: Uuid::new_v4(),
+            repo_name: name.to_string(),
+            setup_script: None,
+            cleanup_script: cleanup_script.map(str::to_string),
+            copy_files: None,
+            parallel_setup_script: false,
+        }
+    }
+
+    /// Repos cleaned up after the agent, when each step ends as `succeeded`
+    /// says
+    fn cleaned_up(action: &ExecutorAction, succeeded: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut cleaned = Vec::new();
+        let mut current = action;
+        let mut outcome = succeeded("agent");
+        while let Some(next) = current.next_action_on_exit(outcome) {
+            let ExecutorActionType::ScriptRequest(script) = next.typ() else {
+                panic!("expected a cleanup script, got {:?}", next.typ());
+            };
+            let repo = script.working_dir.clone().unwrap();
+            outcome = succeeded(&repo);
+            cleaned.push(repo);
+            current = next;
+        }
+        cleaned
+    }
+
+    #[test]
+    fn test_cleanup_of_all_repos_follows_the_same_condition() {
+        let repos = [
+            repo("api", Some("make clean")),
+            repo("docs", None),
+            repo("web", Some("npm run clean")),
+            repo("cli", Some("cargo clean")),
+        ];
+        let agent = ExecutorAction::new(
+            ExecutorActionType::CodingAgentInitialRequest(CodingAgentInitialRequest {
+                prompt: "implement".to_string(),
+                executor_profile_id: ExecutorProfileId::new(BaseCodingAgent::ClaudeCode),
+                working_dir: None,
+                cwd_relative: None,
+                run_template: None,
+                attachments: Vec::new(),
+                review: None,
+            }),
+            cleanup_pipeline(&repos).map(Box::new),
+        );
+
+        assert_eq!(cleaned_up(&agent, |_| true), ["api", "web", "cli"]);
+        // A failed agent run skips the cleanup of every repo, not only the first
+        assert!(cleaned_up(&agent, |step| step != "agent").is_empty());
+        assert_eq!(
+            cleaned_up(&agent, |step| step != "web"),
+            ["api", "web", "cli"]
+        );
+        assert!(cleanup_pipeline(&[repo("docs", None)]).is_none());
+    }
+
+    #[test]
+    fn test_failed_cleanup_does_not_stop_the_next_repo() {
+        let cleanup = cleanup_pipeline(&[
+            repo("api", Some("make clean")),
+            repo("web", Some("npm run clean")),
+        ])
+        .unwrap();
+        let tests = ExecutorAction::new(
+            ExecutorActionType::ScriptRequest(ScriptRequest {
+                script: "npm test".to_string(),
+                language: ScriptRequestLanguage::Bash,
+                context: ScriptContext::TestScript,
+                working_dir: None,
+                timeout_secs: None,
+            }),
+            Some(Box::new(cleanup)),
+        );
+
+        assert_eq!(cleaned_up(&tests, |step| step != "api"), ["api", "web"]);
+    }
 }
@@ -8,7 +8,10 @@ use git2::{
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use ts_rs::TS;
-use utils::diff::{Diff, DiffChangeKind, FileDiffDetails, compute_line_change_counts};
+use utils::diff::{
+    Diff, DiffChangeKind, FileDiffDetails, apply_selected_hunks, compute_diff_hunks,
+    compute_line_change_counts,
+};
 
 mod cli;
 
@@ -37,6 +40,8 @@ pub enum GitServiceError {
     WorktreeDirty(String, String),
     #[error("Rebase in progress; resolve or abort it before retrying")]
     RebaseInProgress,
+    #[error("Cannot roll back: {0}")]
+    RollbackBlocked(String),
 }
 /// Service for managing Git operations in task execution workflows
 #[derive(Clone)]
@@ -119,6 +124,26 @@ pub struct WorktreeResetOutcome {
     pub applied: bool,
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, TS, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(rename_all = "snake_case")]
+pub enum RollbackStrategy {
+    // Add a new commit that reverts the changes, keeping history intact
+    #[default]
+    Revert,
+    // Move the branch back to the commit before the execution
+    Reset,
+}
+
+// A file to take from a set of changes, optionally limited to some of its hunks
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct FileSelection {
+    pub path: String,
+    /// Indices into the file's diff hunks; omit to take the whole file
+    #[serde(default)]
+    pub hunks: Option<Vec<usize>>,
+}
+
 /// Target for diff generation
 pub enum DiffTarget<'p> {
     /// Work-in-progress branch checked out in this worktree
@@ -137,6 +162,12 @@ pub enum DiffTarget<'p> {
         repo_path: &'p Path,
         commit_sha: &'p str,
     },
+    /// Changes between two commits, e.g. the heads before and after an execution
+    Range {
+        repo_path: &'p Path,
+        from_commit: &'p str,
+        to_commit: &'p str,
+    },
 }
 
 impl Default for GitService {
@@ -405,6 +436,32 @@ impl GitService {
                 let mut find_opts = git2::DiffFindOptions::new();
                 diff.find_similar(Some(&mut find_opts))?;
 
+                self.convert_diff_to_file_diffs(diff, &repo)
+            }
+            DiffTarget::Range {
+                repo_path,
+                from_commit,
+                to_commit,
+            } => {
+                let repo = self.open_repo(repo_path)?;
+                let from_tree = repo
+                    .find_commit(git2::Oid::from_str(from_commit)?)?
+                    .tree()?;
+                let to_tree = repo.find_commit(git2::Oid::from_str(to_commit)?)?.tree()?;
+
+                let mut diff_opts = DiffOptions::new();
+                diff_opts.include_typechange(true);
+                if let Some(paths) = path_filter {
+                    for path in paths {
+                        diff_opts.pathspec(*path);
+                    }
+                }
+
+                let mut diff =
+                    repo.diff_tree_to_tree(Some(&from_tree), Some(&to_tree), Some(&mut diff_opts))?;
+                let mut find_opts = DiffFindOptions::new();
+                diff.find_similar(Some(&mut find_opts))?;
+
                 self.convert_diff_to_file_diffs(diff, &repo)
             }
         }
@@ -526,6 +583,11 @@ impl GitService {
                     (None, None)
                 };
 
+                let hunks = compute_diff_hunks(
+                    old_content.as_deref().unwrap_or(""),
+                    new_content.as_deref().unwrap_or(""),
+                );
+
                 file_diffs.push(Diff {
                     change,
                     old_path,
@@ -535,6 +597,8 @@ impl GitService {
                     content_omitted,
                     additions,
                     deletions,
+                    hunks,
+                    semantic_summary: None,
                 });
 
                 delta_index += 1;
@@ -746,6 +810,10 @@ impl GitService {
             }
             (None, None) => (None, None),
         };
+        let hunks = compute_diff_hunks(
+            old_content.as_deref().unwrap_or(""),
+            new_content.as_deref().unwrap_or(""),
+        );
 
         Diff {
             change,
@@ -756,6 +824,8 @@ impl GitService {
             content_omitted,
             additions,
             deletions,
+            hunks,
+            semantic_summary: None,
         }
     }
 
@@ -1160,6 +1230,197 @@ impl GitService {
         Ok(())
     }
 
+    /// Undo the commits in `before_commit..after_commit` on the worktree's branch.
+    ///
+    /// `Reset` only proceeds when `after_commit` is still the branch head, so it
+    /// can never discard later work. `Revert` works as long as `after_commit` is
+    /// in the branch history, and aborts cleanly if the revert conflicts with
+    /// subsequent commits. Returns the new HEAD, or `None` if there was nothing
+    /// to undo.
+    pub fn rollback_commits(
+        &self,
+        worktree_path: &Path,
+        before_commit: &str,
+        after_commit: &str,
+        strategy: RollbackStrategy,
+        message: &str,
+    ) -> Result<Option<String>, GitServiceError> {
+        if before_commit == after_commit {
+            return Ok(None);
+        }
+
+        let repo = self.open_repo(worktree_path)?;
+        self.check_worktree_clean(&repo)?;
+
+        let head_oid = self.get_head_info(worktree_path)?.oid;
+        let head = git2::Oid::from_str(&head_oid)?;
+        let after = git2::Oid::from_str(after_commit)?;
+        let subsequent = if head == after {
+            0
+        } else if repo.graph_descendant_of(head, after)? {
+            repo.graph_ahead_behind(head, after)?.0
+        } else {
+            return Err(GitServiceError::RollbackBlocked(format!(
+                "commit {after_commit} is no longer part of the branch history"
+            )));
+        };
+
+        let cli = GitCli::new();
+        match strategy {
+            RollbackStrategy::Reset => {
+                if subsequent > 0 {
+                    return Err(GitServiceError::RollbackBlocked(format!(
+                        "{subsequent} commit(s) were made after this execution; use revert instead"
+                    )));
+                }
+                self.reset_worktree_to_commit(worktree_path, before_commit, false)?;
+            }
+            RollbackStrategy::Revert => {
+                let range = format!("{before_commit}..{after_commit}");
+                if let Err(e) = cli.git(worktree_path, ["revert", "--no-commit", range.as_str()]) {
+                    let _ = cli.git(worktree_path, ["revert", "--abort"]);
+                    return Err(GitServiceError::MergeConflicts(format!(
+                        "reverting {range} conflicts with later changes: {e}"
+                    )));
+                }
+                if !self.commit(worktree_path, message)? {
+                    return Ok(None);
+                }
+            }
+        }
+
+        Ok(Some(self.get_head_info(worktree_path)?.oid))
+    }
+
+    /// Apply the selected files/hunks of the changes between `before_commit` and
+    /// `after_commit` onto `target_branch` as a new commit.
+    ///
+    /// Each selected file must be unchanged on the target branch since
+    /// `before_commit`. If the target branch is checked out, the checkout is
+    /// fast-forwarded to the new commit. Returns `None` when the selection is empty.
+    pub fn apply_selected_changes(
+        &self,
+        repo_path: &Path,
+        before_commit: &str,
+        after_commit: &str,
+        target_branch: &str,
+        selection: &[FileSelection],
+        message: &str,
+    ) -> Result<Option<String>, GitServiceError> {
+        let repo = self.open_repo(repo_path)?;
+        let before_tree = repo
+            .find_commit(git2::Oid::from_str(before_commit)?)?
+            .tree()?;
+        let after_tree = repo
+            .find_commit(git2::Oid::from_str(after_commit)?)?
+            .tree()?;
+        let target_commit = Self::find_branch(&repo, target_branch)?
+            .get()
+            .peel_to_commit()?;
+        let target_tree = target_commit.tree()?;
+
+        let entry_of = |tree: &git2::Tree, path: &Path| tree.get_path(path).ok();
+        let mut update = git2::build::TreeUpdateBuilder::new();
+        for file in selection {
+            let path = Path::new(&file.path);
+            let old = entry_of(&before_tree, path);
+            let new = entry_of(&after_tree, path);
+            let identity =
+                |entry: &Option<git2::TreeEntry>| entry.as_ref().map(|e| (e.id(), e.filemode()));
+            if identity(&old) == identity(&new) {
+                return Err(GitServiceError::InvalidRepository(format!(
+                    "{} is not changed by the selected commits",
+                    file.path
+                )));
+            }
+            if entry_of(&target_tree, path).map(|e| e.id()) != old.as_ref().map(|e| e.id()) {
+                return Err(GitServiceError::MergeConflicts(format!(
+                    "{} has changed on {target_branch} since these changes were made",
+                    file.path
+                )));
+            }
+
+            let (oid, mode) = match (&file.hunks, &new) {
+                // Whole file, as of `after_commit`
+                (None, Some(new)) => (new.id(), new.filemode()),
+                (None, None) => {
+                    update.remove(path);
+                    continue;
+                }
+                (Some(hunks), _) => {
+                    let text_of =
+                        |entry: &Option<git2::TreeEntry>| -> Result<String, GitServiceError> {
+                            match entry {
+                                Some(entry) => Self::blob_to_string(&repo.find_blob(entry.id())?)
+                                    .ok_or_else(|| {
+                                        GitServiceError::InvalidRepository(format!(
+                                            "cannot select hunks of binary file {}",
+                                            file.path
+                                        ))
+                                    }),
+                                None => Ok(String::new()),
+                            }
+                        };
+                    let content = apply_selected_hunks(&text_of(&old)?, &text_of(&new)?, hunks);
+                    let mode = new
+                        .as_ref()
+                        .or(old.as_ref())
+                        .map_or(i32::from(git2::FileMode::Blob), |e| e.filemode());
+                    (repo.blob(content.as_bytes())?, mode)
+                }
+            };
+            update.upsert(path, oid, Self::file_mode(mode));
+        }
+
+        let tree_id = update.create_updated(&repo, &target_tree)?;
+        if tree_id == target_tree.id() {
+            return Ok(None);
+        }
+        let tree = repo.find_tree(tree_id)?;
+        let signature = self.signature_with_fallback(&repo)?;
+
+        match self.find_checkout_path_for_branch(repo_path, target_branch)? {
+            Some(checkout_path) => {
+                // Create the commit detached, then fast-forward the checkout so its
+                // working tree stays in sync (fails rather than clobbering local edits)
+                let commit_id = repo.commit(
+                    None,
+                    &signature,
+                    &signature,
+                    message,
+                    &tree,
+                    &[&target_commit],
+                )?;
+                GitCli::new().git(
+                    &checkout_path,
+                    ["merge", "--ff-only", commit_id.to_string().as_str()],
+                )?;
+                Ok(Some(commit_id.to_string()))
+            }
+            None => {
+                let refname = format!("refs/heads/{target_branch}");
+                let commit_id = repo.commit(
+                    Some(&refname),
+                    &signature,
+                    &signature,
+                    message,
+                    &tree,
+                    &[&target_commit],
+                )?;
+                Ok(Some(commit_id.to_string()))
+            }
+        }
+    }
+
+    fn file_mode(raw: i32) -> git2::FileMode {
+        match raw {
+            m if m == i32::from(git2::FileMode::BlobExecutable) => git2::FileMode::BlobExecutable,
+            m if m == i32::from(git2::FileMode::Link) => git2::FileMode::Link,
+            m if m == i32::from(git2::FileMode::Commit) => git2::FileMode::Commit,
+            _ => git2::FileMode::Blob,
+        }
+    }
+
     /// Add a worktree for a branch, optionally creating the branch
     pub fn add_worktree(
         &self,
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
@@ -19,6 +19,20 @@ pub struct QueuedMessage {
     pub queued_at: DateTime<Utc>,
 }
 
+/// How a message queued behind others is added to the queue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum QueueMode {
+    /// Replace everything queued with this message
+    #[default]
+    Replace,
+    /// Run this message after the ones already queued
+    Sequential,
+    /// Append this message to the last queued one, so they run as one prompt
+    Coalesce,
+}
+
 /// Status of the queue for a session (for frontend display)
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(tag = "status", rename_all = "snake_case")]
@@ -27,14 +41,19 @@ pub enum QueueStatus {
     /// No message queued
     Empty,
     /// Message is queued and waiting for execution to complete
-    Queued { message: QueuedMessage },
+    Queued {
+        message: QueuedMessage,
+        /// Messages that run one after another once `message` has run
+        #[serde(default)]
+        waiting: Vec<QueuedMessage>,
+    },
 }
 
 /// In-memory service for managing queued follow-up messages.
-/// One queued message per session.
+/// Messages of a session run one per execution, in the order queued.
 #[derive(Clone)]
 pub struct QueuedMessageService {
-    queue: Arc<DashMap<Uuid, QueuedMessage>>,
+    queue: Arc<DashMap<Uuid, VecDeque<QueuedMessage>>>,
 }
 
 impl QueuedMessageService {
@@ -44,31 +63,64 @@ impl QueuedMessageService {
         }
     }
 
-    /// Queue a message for a session. Replaces any existing queued message.
-    pub fn queue_message(&self, session_id: Uuid, data: DraftFollowUpData) -> QueuedMessage {
+    /// Queue a message for a session, returning the queued message it ends up
+    /// in; with [`QueueMode::Coalesce`] that's the one it was appended to.
+    pub fn queue_message(
+        &self,
+        session_id: Uuid,
+        data: DraftFollowUpData,
+        mode: QueueMode,
+    ) -> QueuedMessage {
+        let mut queue = self.queue.entry(session_id).or_default();
+        if mode == QueueMode::Coalesce
+            && let Some(last) = queue.back_mut()
+        {
+            last.data.message = format!("{}\n\n{}", last.data.message, data.message);
+            if data.variant.is_some() {
+                last.data.variant = data.variant;
+            }
+            last.queued_at = Utc::now();
+            return last.clone();
+        }
+        if mode == QueueMode::Replace {
+            queue.clear();
+        }
         let queued = QueuedMessage {
             session_id,
             data,
             queued_at: Utc::now(),
         };
-        self.queue.insert(session_id, queued.clone());
+        queue.push_back(queued.clone());
         queued
     }
 
-    /// Cancel/remove a queued message for a session
-    pub fn cancel_queued(&self, session_id: Uuid) -> Option<QueuedMessage> {
-        self.queue.remove(&session_id).map(|(_, v)| v)
+    /// Cancel/remove every queued message for a session
+    pub fn cancel_queued(&self, session_id: Uuid) -> Vec<QueuedMessage> {
+        self.queue
+            .remove(&session_id)
+            .map(|(_, queue)| queue.into())
+            .unwrap_or_default()
     }
 
-    /// Get the queued message for a session (if any)
+    /// Get the next queued message for a session (if any)
     pub fn get_queued(&self, session_id: Uuid) -> Option<QueuedMessage> {
-        self.queue.get(&session_id).map(|r| r.clone())
+        self.queue
+            .get(&session_id)
+            .and_then(|queue| queue.front().cloned())
     }
 
-    /// Take (remove and return) the queued message for a session.
+    /// Take (remove and return) the next queued message for a session.
     /// Used by finalization flow to consume the queued message.
     pub fn take_queued(&self, session_id: Uuid) -> Option<QueuedMessage> {
-        self.queue.remove(&session_id).map(|(_, v)| v)
+        let (next, now_empty) = {
+            let mut queue = self.queue.get_mut(&session_id)?;
+            (queue.pop_front(), queue.is_empty())
+        };
+        if now_empty {
+            self.queue
+                .remove_if(&session_id, |_, queue| queue.is_empty());
+        }
+        next
     }
 
     /// Check if a session has a queued message
@@ -78,8 +130,15 @@ impl QueuedMessageService {
 
     /// Get queue status for frontend display
     pub fn get_status(&self, session_id: Uuid) -> QueueStatus {
-        match self.get_queued(session_id) {
-            Some(msg) => QueueStatus::Queued { message: msg },
+        let Some(queue) = self.queue.get(&session_id) else {
+            return QueueStatus::Empty;
+        };
+        let mut messages = queue.iter().cloned();
+        match messages.next() {
+            Some(message) => QueueStatus::Queued {
+                message,
+                waiting: messages.collect(),
+            },
             None => QueueStatus::Empty,
         }
     }
@@ -90,3 +149,39 @@ impl Default for QueuedMessageService {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn follow_up(message: &str) -> DraftFollowUpData {
+        DraftFollowUpData {
+            message: message.to_string(),
+            variant: None,
+        }
+    }
+
+    #[test]
+    fn test_queue_runs_in_order_and_coalesces() {
+        let service = QueuedMessageService::new();
+        let session_id = Uuid::new_v4();
+        service.queue_message(session_id, follow_up("first"), QueueMode::Sequential);
+        service.queue_message(session_id, follow_up("second"), QueueMode::Sequential);
+        let coalesced = service.queue_message(session_id, follow_up("third"), QueueMode::Coalesce);
+        assert_eq!(coalesced.data.message, "second\n\nthird");
+        assert!(matches!(
+            service.get_status(session_id),
+            QueueStatus::Queued { waiting, .. } if waiting.len() == 1
+        ));
+
+        let taken: Vec<_> = std::iter::from_fn(|| service.take_queued(session_id))
+            .map(|queued| queued.data.message)
+            .collect();
+        assert_eq!(taken, ["first", "second\n\nthird"]);
+        assert!(!service.has_queued(session_id));
+
+        service.queue_message(session_id, follow_up("a"), QueueMode::Sequential);
+        service.queue_message(session_id, follow_up("b"), QueueMode::Replace);
+        assert_eq!(service.cancel_queued(session_id).len(), 1);
+    }
+}
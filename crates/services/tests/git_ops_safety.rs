@@ -5,7 +5,9 @@ use std::{
 };
 
 use git2::{PushOptions, Repository, build::CheckoutBuilder};
-use services::services::git::{GitCli, GitCliError, GitService};
+use services::services::git::{
+    FileSelection, GitCli, GitCliError, GitService, GitServiceError, RollbackStrategy,
+};
 use tempfile::TempDir;
 // Avoid direct git CLI usage in tests; exercise GitService instead.
 
@@ -1406,3 +1408,147 @@ fn merge_base_ahead_of_task_should_error() {
         "Merge should error when base branch is ahead of task branch"
     );
 }
+
+#[test]
+fn rollback_revert_undoes_execution_commits() {
+    let td = TempDir::new().unwrap();
+    let (_repo_path, worktree_path) = setup_repo_with_worktree(&td);
+    let wt_repo = Repository::open(&worktree_path).unwrap();
+    let after = wt_repo.head().unwrap().peel_to_commit().unwrap();
+    let before = after.parent(0).unwrap();
+
+    let service = GitService::new();
+    let new_head = service
+        .rollback_commits(
+            &worktree_path,
+            &before.id().to_string(),
+            &after.id().to_string(),
+            RollbackStrategy::Revert,
+            "undo feature",
+        )
+        .expect("revert should succeed");
+
+    assert!(new_head.is_some());
+    assert!(!worktree_path.join("feat.txt").exists());
+    // Revert keeps the original commit in history
+    let head = wt_repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.parent_id(0).unwrap(), after.id());
+}
+
+#[test]
+fn rollback_reset_refuses_when_later_commits_exist() {
+    let td = TempDir::new().unwrap();
+    let (_repo_path, worktree_path) = setup_repo_with_worktree(&td);
+    let wt_repo = Repository::open(&worktree_path).unwrap();
+    let after = wt_repo.head().unwrap().peel_to_commit().unwrap();
+    let before = after.parent(0).unwrap();
+
+    write_file(&worktree_path, "later.txt", "later work\n");
+    commit_all(&wt_repo, "later commit");
+
+    let service = GitService::new();
+    let res = service.rollback_commits(
+        &worktree_path,
+        &before.id().to_string(),
+        &after.id().to_string(),
+        RollbackStrategy::Reset,
+        "undo feature",
+    );
+
+    assert!(matches!(res, Err(GitServiceError::RollbackBlocked(_))));
+    assert!(worktree_path.join("feat.txt").exists());
+    assert!(worktree_path.join("later.txt").exists());
+}
+
+const LINES_BEFORE: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+const LINES_AFTER: &str = "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nL\n";
+
+// Commits `lines.txt` on old-base (checked out in the main repo), then makes an
+// "execution" on a separate worktree branch that edits both ends of the file
+// and adds another file. Returns (repo_path, before, after).
+fn setup_execution_with_hunks(root: &TempDir) -> (PathBuf, String, String) {
+    let (repo_path, _) = setup_repo_with_worktree(root);
+    let repo = Repository::open(&repo_path).unwrap();
+    write_file(&repo_path, "lines.txt", LINES_BEFORE);
+    commit_all(&repo, "add lines");
+    let before = repo.head().unwrap().peel_to_commit().unwrap().id();
+
+    let exec_path = root.path().join("wt-exec");
+    repo.branch("exec", &repo.find_commit(before).unwrap(), false)
+        .unwrap();
+    GitService::new()
+        .add_worktree(&repo_path, &exec_path, "exec", false)
+        .expect("create worktree");
+    let exec_repo = Repository::open(&exec_path).unwrap();
+    write_file(&exec_path, "lines.txt", LINES_AFTER);
+    write_file(&exec_path, "extra.txt", "extra\n");
+    commit_all(&exec_repo, "execution changes");
+    let after = exec_repo.head().unwrap().peel_to_commit().unwrap().id();
+
+    (repo_path, before.to_string(), after.to_string())
+}
+
+#[test]
+fn apply_selected_changes_takes_only_chosen_hunks() {
+    let td = TempDir::new().unwrap();
+    let (repo_path, before, after) = setup_execution_with_hunks(&td);
+
+    let service = GitService::new();
+    let diffs = service
+        .get_diffs(
+            DiffTarget::Range {
+                repo_path: &repo_path,
+                from_commit: &before,
+                to_commit: &after,
+            },
+            Some(&["lines.txt"]),
+        )
+        .unwrap();
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].hunks.len(), 2);
+
+    let selection = vec![FileSelection {
+        path: "lines.txt".to_string(),
+        hunks: Some(vec![1]),
+    }];
+    let sha = service
+        .apply_selected_changes(
+            &repo_path, &before, &after, "old-base", &selection, "partial",
+        )
+        .expect("apply should succeed")
+        .expect("a commit should be created");
+
+    // old-base is checked out in the main repo, which is fast-forwarded
+    let repo = Repository::open(&repo_path).unwrap();
+    assert_eq!(repo.head().unwrap().target().unwrap().to_string(), sha);
+    assert_eq!(
+        fs::read_to_string(repo_path.join("lines.txt")).unwrap(),
+        "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nL\n"
+    );
+    assert!(!repo_path.join("extra.txt").exists());
+}
+
+#[test]
+fn apply_selected_changes_conflicts_when_target_changed() {
+    let td = TempDir::new().unwrap();
+    let (repo_path, before, after) = setup_execution_with_hunks(&td);
+    let repo = Repository::open(&repo_path).unwrap();
+    write_file(&repo_path, "lines.txt", "changed on target\n");
+    commit_all(&repo, "target change");
+
+    let selection = vec![
+        FileSelection {
+            path: "extra.txt".to_string(),
+            hunks: None,
+        },
+        FileSelection {
+            path: "lines.txt".to_string(),
+            hunks: None,
+        },
+    ];
+    let res = GitService::new().apply_selected_changes(
+        &repo_path, &before, &after, "old-base", &selection, "partial",
+    );
+    assert!(matches!(res, Err(GitServiceError::MergeConflicts(_))));
+    assert!(!repo_path.join("extra.txt").exists());
+}
@@ -2,9 +2,11 @@ use std::borrow::Cow;
 
 use git2::{DiffOptions, Patch};
 use serde::{Deserialize, Serialize};
-use similar::TextDiff;
+use similar::{DiffTag, TextDiff};
 use ts_rs::TS;
 
+use crate::semantic_diff::SemanticSummary;
+
 // Structs compatable with props: https://github.com/MrWangJustToDo/git-diff-view
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -14,7 +16,8 @@ pub struct FileDiffDetails {
     pub content: Option<String>,
 }
 
-// Worktree diffs for the diffs tab: minimal, no hunks, optional full contents
+// Worktree diffs for the diffs tab: minimal, optional full contents. Hunks only
+// carry line ranges so that individual changes can be selected for applying.
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(rename_all = "camelCase")]
 pub struct Diff {
@@ -28,6 +31,25 @@ pub struct Diff {
     /// Optional precomputed stats for omitted content
     pub additions: Option<usize>,
     pub deletions: Option<usize>,
+    /// Change hunks, in file order; empty when contents are omitted or binary
+    #[serde(default)]
+    pub hunks: Vec<DiffHunk>,
+    /// Functions/types added, removed or modified; only computed for patch review
+    #[serde(default)]
+    pub semantic_summary: Option<SemanticSummary>,
+}
+
+// Line ranges of one hunk (with 3 lines of context), using unified diff numbering.
+// Hunks are addressed by their position in `Diff::hunks`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub additions: usize,
+    pub deletions: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -89,6 +111,78 @@ pub fn compute_line_change_counts(old: &str, new: &str) -> (usize, usize) {
     }
 }
 
+/// Split the changes between two text snapshots into hunks.
+pub fn compute_diff_hunks(old: &str, new: &str) -> Vec<DiffHunk> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(HUNK_CONTEXT_LINES)
+        .iter()
+        .filter_map(|group| {
+            let (first, last) = (group.first()?, group.last()?);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+
+            let (mut additions, mut deletions) = (0, 0);
+            for op in group {
+                match op.tag() {
+                    DiffTag::Equal => {}
+                    DiffTag::Insert => additions += op.new_range().len(),
+                    DiffTag::Delete => deletions += op.old_range().len(),
+                    DiffTag::Replace => {
+                        additions += op.new_range().len();
+                        deletions += op.old_range().len();
+                    }
+                }
+            }
+
+            Some(DiffHunk {
+                old_start: hunk_start(&old_range),
+                old_lines: old_range.len(),
+                new_start: hunk_start(&new_range),
+                new_lines: new_range.len(),
+                additions,
+                deletions,
+            })
+        })
+        .collect()
+}
+
+/// Produce `old` with only the selected hunks (indices into
+/// [`compute_diff_hunks`] for the same inputs) of the change to `new` applied.
+pub fn apply_selected_hunks(old: &str, new: &str, selected: &[usize]) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let (old_lines, new_lines) = (diff.old_slices(), diff.new_slices());
+
+    let mut result = String::with_capacity(old.len().max(new.len()));
+    let mut old_pos = 0;
+    for (index, group) in diff.grouped_ops(HUNK_CONTEXT_LINES).iter().enumerate() {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        result.extend(old_lines[old_pos..old_range.start].iter().copied());
+        if selected.contains(&index) {
+            let new_range = first.new_range().start..last.new_range().end;
+            result.extend(new_lines[new_range].iter().copied());
+        } else {
+            result.extend(old_lines[old_range.clone()].iter().copied());
+        }
+        old_pos = old_range.end;
+    }
+    result.extend(old_lines[old_pos..].iter().copied());
+    result
+}
+
+const HUNK_CONTEXT_LINES: usize = 3;
+
+// unified diff convention: 1-based start, or the preceding line for empty ranges
+fn hunk_start(range: &std::ops::Range<usize>) -> usize {
+    if range.is_empty() {
+        range.start
+    } else {
+        range.start + 1
+    }
+}
+
 // ensure a line ends with a newline character
 fn ensure_newline(line: &str) -> Cow<'_, str> {
     if line.ends_with('\n') {
@@ -244,3 +338,46 @@ pub fn normalize_unified_diff(file_path: &str, unified_diff: &str) -> String {
     let hunks = extract_unified_diff_hunks(unified_diff);
     concatenate_diff_hunks(file_path, &hunks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+    const NEW: &str = "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+
+    #[test]
+    fn test_compute_diff_hunks_splits_distant_changes() {
+        let hunks = compute_diff_hunks(OLD, NEW);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(
+            hunks[0],
+            DiffHunk {
+                old_start: 1,
+                old_lines: 4,
+                new_start: 1,
+                new_lines: 4,
+                additions: 1,
+                deletions: 1,
+            }
+        );
+        assert_eq!((hunks[1].old_start, hunks[1].old_lines), (10, 3));
+        assert_eq!((hunks[1].new_start, hunks[1].new_lines), (10, 4));
+        assert_eq!((hunks[1].additions, hunks[1].deletions), (1, 0));
+    }
+
+    #[test]
+    fn test_apply_selected_hunks() {
+        assert_eq!(apply_selected_hunks(OLD, NEW, &[]), OLD);
+        assert_eq!(apply_selected_hunks(OLD, NEW, &[0, 1]), NEW);
+        assert_eq!(
+            apply_selected_hunks(OLD, NEW, &[1]),
+            "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n"
+        );
+        assert_eq!(
+            apply_selected_hunks(OLD, NEW, &[0]),
+            "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n"
+        );
+        assert_eq!(apply_selected_hunks("", "x\n", &[0]), "x\n");
+    }
+}
@@ -14,6 +14,7 @@ pub mod msg_store;
 pub mod path;
 pub mod port_file;
 pub mod response;
+pub mod semantic_diff;
 pub mod sentry;
 pub mod shell;
 pub mod stream_lines;
@@ -1,6 +1,7 @@
 use axum::{extract::ws::Message, response::sse::Event};
 use json_patch::Patch;
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
 pub const EV_STDOUT: &str = "stdout";
 pub const EV_STDERR: &str = "stderr";
@@ -8,6 +9,7 @@ pub const EV_JSON_PATCH: &str = "json_patch";
 pub const EV_SESSION_ID: &str = "session_id";
 pub const EV_READY: &str = "ready";
 pub const EV_FINISHED: &str = "finished";
+pub const EV_HEARTBEAT: &str = "heartbeat";
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum LogMsg {
@@ -17,6 +19,20 @@ pub enum LogMsg {
     SessionId(String),
     Ready,
     Finished,
+    Heartbeat(Heartbeat),
+}
+
+/// Liveness of a running execution, sent periodically while it runs
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+pub struct Heartbeat {
+    /// Seconds since the execution started
+    pub elapsed_secs: u64,
+    /// Bytes of stdout and stderr produced so far
+    pub output_bytes: u64,
+    /// Seconds since the last output
+    pub idle_secs: u64,
+    /// Whether the execution has been quiet for longer than it should be
+    pub stalled: bool,
 }
 
 impl LogMsg {
@@ -28,6 +44,7 @@ impl LogMsg {
             LogMsg::SessionId(_) => EV_SESSION_ID,
             LogMsg::Ready => EV_READY,
             LogMsg::Finished => EV_FINISHED,
+            LogMsg::Heartbeat(_) => EV_HEARTBEAT,
         }
     }
 
@@ -42,6 +59,10 @@ impl LogMsg {
             LogMsg::SessionId(s) => Event::default().event(EV_SESSION_ID).data(s.clone()),
             LogMsg::Ready => Event::default().event(EV_READY).data(""),
             LogMsg::Finished => Event::default().event(EV_FINISHED).data(""),
+            LogMsg::Heartbeat(heartbeat) => {
+                let data = serde_json::to_string(heartbeat).unwrap_or_else(|_| "{}".to_string());
+                Event::default().event(EV_HEARTBEAT).data(data)
+            }
         }
     }
 
@@ -80,6 +101,7 @@ impl LogMsg {
             LogMsg::SessionId(s) => EV_SESSION_ID.len() + s.len() + OVERHEAD,
             LogMsg::Ready => EV_READY.len() + OVERHEAD,
             LogMsg::Finished => EV_FINISHED.len() + OVERHEAD,
+            LogMsg::Heartbeat(_) => EV_HEARTBEAT.len() + size_of::<Heartbeat>() + OVERHEAD,
         }
     }
 }
@@ -1,17 +1,50 @@
 use std::{
     collections::VecDeque,
-    sync::{Arc, RwLock},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
 };
 
 use axum::response::sse::Event;
 use futures::{StreamExt, TryStreamExt, future};
-use tokio::{sync::broadcast, task::JoinHandle};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore, broadcast, mpsc},
+    task::JoinHandle,
+};
 use tokio_stream::wrappers::BroadcastStream;
 
-use crate::{log_msg::LogMsg, stream_lines::LinesStreamExt};
+use crate::{
+    log_msg::{Heartbeat, LogMsg},
+    stream_lines::LinesStreamExt,
+};
 
 // 100 MB Limit
 const HISTORY_BYTES: usize = 100000 * 1024;
+/// Size after which a persisted store starts a new segment file
+const SEGMENT_BYTES: u64 = 16 * 1024 * 1024;
+/// Longest a persisted store keeps written messages buffered, as long as
+/// messages keep coming; a finished store is flushed right away
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Buffering used by [`MsgStore::spawn_buffered_forwarder`]
+#[derive(Debug, Clone, Copy)]
+pub struct ForwardBuffer {
+    /// Bytes read ahead of the store before reading pauses
+    pub high_water_bytes: usize,
+    /// Largest chunk built by joining consecutive stdout or stderr chunks
+    pub coalesce_bytes: usize,
+}
+
+impl Default for ForwardBuffer {
+    fn default() -> Self {
+        Self {
+            high_water_bytes: 8 * 1024 * 1024,
+            coalesce_bytes: 64 * 1024,
+        }
+    }
+}
 
 #[derive(Clone)]
 struct StoredMsg {
@@ -22,11 +55,137 @@ struct StoredMsg {
 struct Inner {
     history: VecDeque<StoredMsg>,
     total_bytes: usize,
+    /// Messages dropped from the front of `history` to stay under the limit
+    evicted: usize,
+}
+
+/// How a persistent store's lines are written to and read from disk, e.g.
+/// sealed with the encryption at rest the host configured
+#[derive(Debug, Clone, Copy)]
+pub struct LineCodec {
+    pub seal: fn(&str) -> io::Result<String>,
+    pub open: fn(&str) -> io::Result<String>,
+}
+
+impl LineCodec {
+    /// Lines written as they are
+    pub const PLAIN: Self = Self {
+        seal: plain_line,
+        open: plain_line,
+    };
+}
+
+fn plain_line(line: &str) -> io::Result<String> {
+    Ok(line.to_string())
+}
+
+/// JSONL segment files a persistent store appends to
+struct Segments {
+    dir: PathBuf,
+    index: usize,
+    file: BufWriter<File>,
+    written: u64,
+    flushed_at: Instant,
+}
+
+impl Segments {
+    fn open(dir: &Path, index: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(format!("{index:05}.jsonl")))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            index,
+            written: file.metadata()?.len(),
+            file: BufWriter::new(file),
+            flushed_at: Instant::now(),
+        })
+    }
+
+    /// Append `line`, flushing it and whatever was buffered before it when
+    /// `flush` is set or the buffer has been held for long enough
+    fn append(&mut self, line: &str, flush: bool) -> io::Result<()> {
+        if self.written >= SEGMENT_BYTES {
+            self.file.flush()?;
+            *self = Self::open(&self.dir, self.index + 1)?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.written += line.len() as u64 + 1;
+        if flush || self.flushed_at.elapsed() >= FLUSH_INTERVAL {
+            self.file.flush()?;
+            self.flushed_at = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+/// Where a persistent store writes its messages
+struct Persistence {
+    codec: LineCodec,
+    segments: Mutex<Segments>,
+}
+
+/// Segment files in `dir`, oldest first
+fn segment_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Remove the stores persisted in the directories under `root` that nothing
+/// was written to for `max_age`, returning how many were removed
+pub fn prune_persisted(root: &Path, max_age: Duration) -> io::Result<usize> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let dir = entry?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        let last_written = segment_paths(&dir)?
+            .iter()
+            .chain([&dir])
+            .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .max();
+        let expired = last_written
+            .and_then(|time| SystemTime::now().duration_since(time).ok())
+            .is_some_and(|age| age >= max_age);
+        if expired {
+            fs::remove_dir_all(&dir)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Messages in the segment files, skipping lines that don't parse, like one
+/// cut short by a crash
+fn read_segments(paths: &[PathBuf], codec: LineCodec) -> io::Result<Vec<LogMsg>> {
+    let mut msgs = Vec::new();
+    for path in paths {
+        for line in BufReader::new(File::open(path)?).lines() {
+            match serde_json::from_str(&(codec.open)(&line?)?) {
+                Ok(msg) => msgs.push(msg),
+                Err(e) => tracing::warn!("Skipping log line in {}: {e}", path.display()),
+            }
+        }
+    }
+    Ok(msgs)
 }
 
 pub struct MsgStore {
     inner: RwLock<Inner>,
     sender: broadcast::Sender<LogMsg>,
+    persistence: Option<Persistence>,
 }
 
 impl Default for MsgStore {
@@ -42,19 +201,78 @@ impl MsgStore {
             inner: RwLock::new(Inner {
                 history: VecDeque::with_capacity(32),
                 total_bytes: 0,
+                evicted: 0,
             }),
             sender,
+            persistence: None,
+        }
+    }
+
+    /// A store that also appends every message to JSONL segment files in
+    /// `dir`, each line written through `codec`, so its history survives a
+    /// restart. History already persisted in `dir` is loaded first. Writes
+    /// are buffered for up to a second, which a crash may lose.
+    pub fn persistent(dir: impl Into<PathBuf>, codec: LineCodec) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let paths = segment_paths(&dir)?;
+        let mut store = Self::new();
+        for msg in read_segments(&paths, codec)? {
+            store.push(msg);
         }
+        store.persistence = Some(Persistence {
+            codec,
+            segments: Mutex::new(Segments::open(&dir, paths.len())?),
+        });
+        Ok(store)
+    }
+
+    /// The history a [`Self::persistent`] store left in `dir`, ending in
+    /// [`LogMsg::Finished`] even if the process writing it died, or `None`
+    /// when nothing was persisted there
+    pub fn load_persisted(dir: &Path, codec: LineCodec) -> io::Result<Option<Self>> {
+        let paths = match segment_paths(dir) {
+            Ok(paths) if !paths.is_empty() => paths,
+            Ok(_) => return Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let store = Self::new();
+        let msgs = read_segments(&paths, codec)?;
+        let finished = matches!(msgs.last(), Some(LogMsg::Finished));
+        for msg in msgs {
+            store.push(msg);
+        }
+        if !finished {
+            store.push_finished();
+        }
+        Ok(Some(store))
     }
 
     pub fn push(&self, msg: LogMsg) {
         let _ = self.sender.send(msg.clone()); // live listeners
+        if let Some(persistence) = &self.persistence {
+            // Encoded before taking the lock, which only guards the buffer
+            let line = serde_json::to_string(&msg)
+                .map_err(io::Error::from)
+                .and_then(|line| (persistence.codec.seal)(&line));
+            let mut segments = persistence.segments.lock().unwrap();
+            if let Err(e) =
+                line.and_then(|line| segments.append(&line, matches!(msg, LogMsg::Finished)))
+            {
+                tracing::warn!(
+                    "Failed to persist log message to {}: {e}",
+                    segments.dir.display()
+                );
+            }
+        }
         let bytes = msg.approx_bytes();
 
         let mut inner = self.inner.write().unwrap();
         while inner.total_bytes.saturating_add(bytes) > HISTORY_BYTES {
             if let Some(front) = inner.history.pop_front() {
                 inner.total_bytes = inner.total_bytes.saturating_sub(front.bytes);
+                inner.evicted += 1;
             } else {
                 break;
             }
@@ -83,6 +301,10 @@ impl MsgStore {
         self.push(LogMsg::Finished);
     }
 
+    pub fn push_heartbeat(&self, heartbeat: Heartbeat) {
+        self.push(LogMsg::Heartbeat(heartbeat));
+    }
+
     pub fn get_receiver(&self) -> broadcast::Receiver<LogMsg> {
         self.sender.subscribe()
     }
@@ -97,6 +319,45 @@ impl MsgStore {
             .collect()
     }
 
+    /// Number of messages ever pushed, including ones no longer held. A
+    /// message's index is its position among them, so it never changes.
+    pub fn total_count(&self) -> usize {
+        let inner = self.inner.read().unwrap();
+        inner.evicted + inner.history.len()
+    }
+
+    /// Index of the oldest message still held; older ones were dropped to
+    /// keep the history under its size limit
+    pub fn first_index(&self) -> usize {
+        self.inner.read().unwrap().evicted
+    }
+
+    /// Up to `limit` messages starting at index `start_index`, for paging
+    /// through a long history without cloning all of it. Starts at
+    /// [`Self::first_index`] instead when `start_index` was dropped.
+    pub fn get_range(&self, start_index: usize, limit: usize) -> Vec<LogMsg> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .history
+            .iter()
+            .skip(start_index.saturating_sub(inner.evicted))
+            .take(limit)
+            .map(|s| s.msg.clone())
+            .collect()
+    }
+
+    /// Values `f` picks out of the history, without cloning the messages
+    pub fn filter_map_history<T>(&self, f: impl FnMut(&LogMsg) -> Option<T>) -> Vec<T> {
+        self.inner
+            .read()
+            .unwrap()
+            .history
+            .iter()
+            .map(|s| &s.msg)
+            .filter_map(f)
+            .collect()
+    }
+
     /// History then live, as `LogMsg`.
     pub fn history_plus_stream(
         &self,
@@ -174,4 +435,212 @@ impl MsgStore {
             }
         })
     }
+
+    /// Like [`Self::spawn_forwarder`], but reads ahead of the store into a
+    /// buffer of at most `buffer.high_water_bytes`, and joins output chunks
+    /// that queued up while the store was busy. A full buffer pauses reading
+    /// instead of growing.
+    pub fn spawn_buffered_forwarder<S, E>(
+        self: Arc<Self>,
+        stream: S,
+        buffer: ForwardBuffer,
+    ) -> JoinHandle<()>
+    where
+        S: futures::Stream<Item = Result<LogMsg, E>> + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        let high_water = buffer.high_water_bytes.clamp(1, Semaphore::MAX_PERMITS) as u32;
+        let budget = Arc::new(Semaphore::new(high_water as usize));
+        // Bounded by `budget`: every queued message holds permits for its size
+        let (tx, mut rx) = mpsc::unbounded_channel::<(LogMsg, OwnedSemaphorePermit)>();
+
+        tokio::spawn(async move {
+            tokio::pin!(stream);
+
+            while let Some(next) = stream.next().await {
+                let msg = next.unwrap_or_else(|e| LogMsg::Stderr(format!("stream error: {e}")));
+                let bytes = msg.approx_bytes().clamp(1, high_water as usize) as u32;
+                let Ok(permit) = budget.clone().acquire_many_owned(bytes).await else {
+                    break;
+                };
+                if tx.send((msg, permit)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            let mut next = rx.recv().await;
+            while let Some((mut msg, permit)) = next.take() {
+                let mut permits = vec![permit];
+                while let Ok((more, permit)) = rx.try_recv() {
+                    match coalesce(&mut msg, more, buffer.coalesce_bytes) {
+                        None => permits.push(permit),
+                        Some(more) => {
+                            next = Some((more, permit));
+                            break;
+                        }
+                    }
+                }
+                self.push(msg);
+                drop(permits);
+                if next.is_none() {
+                    next = rx.recv().await;
+                }
+            }
+        })
+    }
+}
+
+/// Append `more` to `msg` when both are output of the same stream and fit in
+/// `limit` bytes together; otherwise hand `more` back
+fn coalesce(msg: &mut LogMsg, more: LogMsg, limit: usize) -> Option<LogMsg> {
+    match (msg, more) {
+        (LogMsg::Stdout(out), LogMsg::Stdout(more)) if out.len() + more.len() <= limit => {
+            out.push_str(&more);
+            None
+        }
+        (LogMsg::Stderr(err), LogMsg::Stderr(more)) if err.len() + more.len() <= limit => {
+            err.push_str(&more);
+            None
+        }
+        (_, more) => Some(more),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_buffered_forwarder_coalesces_output() {
+        let store = Arc::new(MsgStore::new());
+        let chunks = ["a", "b", "c"]
+            .into_iter()
+            .map(|s| LogMsg::Stdout(s.to_string()))
+            .chain([LogMsg::Stderr("oops".to_string())])
+            .chain(
+                ["d", "e"]
+                    .into_iter()
+                    .map(|s| LogMsg::Stdout(s.to_string())),
+            )
+            .map(Ok::<_, std::io::Error>);
+        // Queue everything before the store starts draining
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        for chunk in chunks {
+            tx.unbounded_send(chunk).unwrap();
+        }
+        drop(tx);
+
+        store
+            .clone()
+            .spawn_buffered_forwarder(
+                rx,
+                ForwardBuffer {
+                    high_water_bytes: 1024,
+                    coalesce_bytes: 2,
+                },
+            )
+            .await
+            .unwrap();
+
+        let output: String = store
+            .get_history()
+            .iter()
+            .map(|msg| match msg {
+                LogMsg::Stdout(s) => format!("[{s}]"),
+                LogMsg::Stderr(s) => format!("<{s}>"),
+                _ => String::new(),
+            })
+            .collect();
+        // Nothing is lost or reordered; only sizes depend on timing
+        assert_eq!(output.replace("][", ""), "[abc]<oops>[de]");
+        assert!(store.get_history().iter().all(|msg| match msg {
+            LogMsg::Stdout(s) => s.len() <= 2,
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn test_get_range_pages_through_history() {
+        let store = MsgStore::new();
+        for i in 0..5 {
+            store.push_stdout(format!("{i}"));
+        }
+        assert_eq!(store.total_count(), 5);
+        let page = |start, limit| -> Vec<String> {
+            store
+                .get_range(start, limit)
+                .into_iter()
+                .map(|msg| match msg {
+                    LogMsg::Stdout(s) => s,
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect()
+        };
+        assert_eq!(page(0, 2), ["0", "1"]);
+        assert_eq!(page(3, 10), ["3", "4"]);
+        assert!(page(5, 10).is_empty());
+
+        // Dropping old messages keeps the indexes of the remaining ones
+        store.push_stdout("x".repeat(HISTORY_BYTES - 20));
+        assert_eq!(store.total_count(), 6);
+        assert_eq!(store.first_index(), 5);
+        assert_eq!(store.get_range(0, 10).len(), 1);
+        assert!(store.get_range(6, 10).is_empty());
+    }
+
+    #[test]
+    fn test_persistent_store_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("vk-msg-store-{}", uuid::Uuid::new_v4()));
+        let store = MsgStore::persistent(&dir, LineCodec::PLAIN).unwrap();
+        store.push_stdout("line one\n");
+        store.push_patch(json_patch::Patch(vec![]));
+        drop(store);
+
+        // The writer died before finishing
+        let loaded = MsgStore::load_persisted(&dir, LineCodec::PLAIN)
+            .unwrap()
+            .unwrap();
+        let history = loaded.get_history();
+        assert_eq!(history.len(), 3);
+        assert!(matches!(&history[0], LogMsg::Stdout(s) if s == "line one\n"));
+        assert!(matches!(history[2], LogMsg::Finished));
+
+        // Resuming appends to the same history
+        let resumed = MsgStore::persistent(&dir, LineCodec::PLAIN).unwrap();
+        resumed.push_stderr("again");
+        assert_eq!(resumed.get_history().len(), 3);
+        assert_eq!(segment_paths(&dir).unwrap().len(), 2);
+        assert!(
+            MsgStore::load_persisted(&dir.join("missing"), LineCodec::PLAIN)
+                .unwrap()
+                .is_none()
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_persisted_lines_sealed_and_pruned() {
+        let codec = LineCodec {
+            seal: |line| Ok(line.chars().rev().collect()),
+            open: |line| Ok(line.chars().rev().collect()),
+        };
+        let root = std::env::temp_dir().join(format!("vk-msg-stores-{}", uuid::Uuid::new_v4()));
+        let dir = root.join("execution");
+        let store = MsgStore::persistent(&dir, codec).unwrap();
+        store.push_stdout("secret output");
+        store.push_finished();
+
+        // Flushed once finished, while the store is still alive
+        let written = fs::read_to_string(&segment_paths(&dir).unwrap()[0]).unwrap();
+        assert!(!written.contains("secret output"));
+        let loaded = MsgStore::load_persisted(&dir, codec).unwrap().unwrap();
+        assert!(matches!(&loaded.get_history()[0], LogMsg::Stdout(s) if s == "secret output"));
+
+        assert_eq!(prune_persisted(&root, Duration::from_secs(3600)).unwrap(), 0);
+        assert_eq!(prune_persisted(&root, Duration::ZERO).unwrap(), 1);
+        assert!(!dir.exists());
+        fs::remove_dir_all(root).unwrap();
+    }
 }
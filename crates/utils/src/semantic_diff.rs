@@ -0,0 +1,273 @@
+//! Symbol-level summaries of file changes, for reviewing a patch before
+//! reading the raw diff.
+//!
+//! Both versions of a file are parsed with tree-sitter and their functions and
+//! type definitions are compared by qualified name: symbols only present in
+//! the new version are added, those only in the old one removed, and those
+//! whose source text differs modified.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Language, Node, Parser};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum SymbolKind {
+    Function,
+    Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum SymbolChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolChange {
+    pub kind: SymbolKind,
+    /// Name qualified by its enclosing types/modules, e.g. `Parser::parse`
+    pub name: String,
+    pub change: SymbolChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticSummary {
+    pub language: String,
+    pub changes: Vec<SymbolChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl Lang {
+    fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?;
+        Some(match ext {
+            "rs" => Lang::Rust,
+            "py" | "pyi" => Lang::Python,
+            "js" | "jsx" | "mjs" | "cjs" => Lang::JavaScript,
+            "ts" | "mts" | "cts" => Lang::TypeScript,
+            "tsx" => Lang::Tsx,
+            "go" => Lang::Go,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Lang::Rust => "rust",
+            Lang::Python => "python",
+            Lang::JavaScript => "javascript",
+            Lang::TypeScript | Lang::Tsx => "typescript",
+            Lang::Go => "go",
+        }
+    }
+
+    fn language(self) -> Language {
+        match self {
+            Lang::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Lang::Python => tree_sitter_python::LANGUAGE.into(),
+            Lang::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Lang::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Lang::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Lang::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    fn symbol_kind(self, node: Node) -> Option<SymbolKind> {
+        let kind = match (self, node.kind()) {
+            (Lang::Rust, "function_item" | "function_signature_item") => SymbolKind::Function,
+            (
+                Lang::Rust,
+                "struct_item" | "enum_item" | "union_item" | "trait_item" | "type_item",
+            ) => SymbolKind::Type,
+            (Lang::Python, "function_definition") => SymbolKind::Function,
+            (Lang::Python, "class_definition") => SymbolKind::Type,
+            (
+                Lang::JavaScript | Lang::TypeScript | Lang::Tsx,
+                "function_declaration" | "generator_function_declaration" | "method_definition",
+            ) => SymbolKind::Function,
+            // `const f = () => ...` and `const f = function () {...}`
+            (Lang::JavaScript | Lang::TypeScript | Lang::Tsx, "variable_declarator")
+                if node.child_by_field_name("value").is_some_and(|value| {
+                    matches!(value.kind(), "arrow_function" | "function_expression")
+                }) =>
+            {
+                SymbolKind::Function
+            }
+            (
+                Lang::JavaScript | Lang::TypeScript | Lang::Tsx,
+                "class_declaration" | "abstract_class_declaration",
+            ) => SymbolKind::Type,
+            (
+                Lang::TypeScript | Lang::Tsx,
+                "interface_declaration" | "type_alias_declaration" | "enum_declaration",
+            ) => SymbolKind::Type,
+            (Lang::Go, "function_declaration" | "method_declaration") => SymbolKind::Function,
+            (Lang::Go, "type_spec") => SymbolKind::Type,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Name that qualifies the symbols nested in `node`, for nodes that only
+    /// group other definitions (e.g. Rust `impl` blocks and modules)
+    fn scope_name<'a>(self, node: Node, source: &'a str) -> Option<&'a str> {
+        let field = match (self, node.kind()) {
+            (Lang::Rust, "impl_item") => "type",
+            (Lang::Rust, "mod_item") => "name",
+            _ => return None,
+        };
+        node_text(node.child_by_field_name(field)?, source)
+    }
+}
+
+fn node_text<'a>(node: Node, source: &'a str) -> Option<&'a str> {
+    source.get(node.byte_range())
+}
+
+type SymbolTable<'a> = BTreeMap<(SymbolKind, String), Vec<&'a str>>;
+
+fn collect_symbols<'a>(
+    lang: Lang,
+    node: Node,
+    source: &'a str,
+    scope: &mut Vec<String>,
+    symbols: &mut SymbolTable<'a>,
+) {
+    let symbol = lang.symbol_kind(node).and_then(|kind| {
+        let name = node_text(node.child_by_field_name("name")?, source)?;
+        Some((kind, name))
+    });
+    let scope_name = match symbol {
+        Some((_, name)) => Some(name),
+        None => lang.scope_name(node, source),
+    };
+
+    if let Some((kind, name)) = symbol {
+        let qualified = scope
+            .iter()
+            .map(String::as_str)
+            .chain([name])
+            .collect::<Vec<_>>()
+            .join("::");
+        let text = node_text(node, source).unwrap_or_default();
+        symbols.entry((kind, qualified)).or_default().push(text);
+    }
+
+    if let Some(name) = scope_name {
+        scope.push(name.to_string());
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_symbols(lang, child, source, scope, symbols);
+    }
+    if scope_name.is_some() {
+        scope.pop();
+    }
+}
+
+fn parse_symbols<'a>(lang: Lang, source: &'a str) -> Option<SymbolTable<'a>> {
+    let mut parser = Parser::new();
+    parser.set_language(&lang.language()).ok()?;
+    let tree = parser.parse(source, None)?;
+    let mut symbols = SymbolTable::new();
+    collect_symbols(lang, tree.root_node(), source, &mut vec![], &mut symbols);
+    Some(symbols)
+}
+
+/// Summarize the symbol-level changes between two versions of a file.
+///
+/// Returns `None` for languages without a grammar or sources that fail to
+/// parse. Either side may be empty for added or deleted files.
+pub fn summarize_changes(path: &str, old: &str, new: &str) -> Option<SemanticSummary> {
+    let lang = Lang::from_path(path)?;
+    let old_symbols = parse_symbols(lang, old)?;
+    let new_symbols = parse_symbols(lang, new)?;
+
+    let mut changes = Vec::new();
+    for ((kind, name), old_texts) in &old_symbols {
+        let change = match new_symbols.get(&(*kind, name.clone())) {
+            None => SymbolChangeKind::Removed,
+            Some(new_texts) if new_texts != old_texts => SymbolChangeKind::Modified,
+            Some(_) => continue,
+        };
+        changes.push(SymbolChange {
+            kind: *kind,
+            name: name.clone(),
+            change,
+        });
+    }
+    for (kind, name) in new_symbols.keys() {
+        if !old_symbols.contains_key(&(*kind, name.clone())) {
+            changes.push(SymbolChange {
+                kind: *kind,
+                name: name.clone(),
+                change: SymbolChangeKind::Added,
+            });
+        }
+    }
+
+    Some(SemanticSummary {
+        language: lang.name().to_string(),
+        changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(summary: &SemanticSummary) -> Vec<(SymbolChangeKind, &str)> {
+        summary
+            .changes
+            .iter()
+            .map(|c| (c.change, c.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_functions_and_methods() {
+        let old = "struct Parser;\nimpl Parser {\n    fn parse(&self) -> u32 { 1 }\n    fn reset(&self) {}\n}\nfn helper() {}\n";
+        let new = "struct Parser;\nimpl Parser {\n    fn parse(&self) -> u32 { 2 }\n    fn peek(&self) {}\n}\nfn helper() {}\n";
+
+        let summary = summarize_changes("src/parser.rs", old, new).unwrap();
+        assert_eq!(summary.language, "rust");
+        assert_eq!(
+            changes(&summary),
+            vec![
+                (SymbolChangeKind::Modified, "Parser::parse"),
+                (SymbolChangeKind::Removed, "Parser::reset"),
+                (SymbolChangeKind::Added, "Parser::peek"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_python_added_file() {
+        let new = "class Greeter:\n    def greet(self):\n        return 'hi'\n";
+        let summary = summarize_changes("greeter.py", "", new).unwrap();
+        assert_eq!(
+            changes(&summary),
+            vec![
+                (SymbolChangeKind::Added, "Greeter::greet"),
+                (SymbolChangeKind::Added, "Greeter"),
+            ]
+        );
+        assert!(summarize_changes("notes.txt", "", "text").is_none());
+    }
+}
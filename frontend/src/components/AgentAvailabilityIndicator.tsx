@@ -36,6 +36,21 @@ export function AgentAvailabilityIndicator({
           </p>
         </>
       )}
+      {availability.status === 'login_expired' && (
+        <>
+          <div className="flex items-center gap-2">
+            <AlertCircle className="h-4 w-4 text-warning" />
+            <span className="text-warning">
+              {t('settings.agents.availability.loginExpired')}
+            </span>
+          </div>
+          <p className="text-xs text-muted-foreground pl-6">
+            {t('settings.agents.availability.loginExpiredTooltip', {
+              date: availability.expiredAt.toLocaleString(),
+            })}
+          </p>
+        </>
+      )}
       {availability.status === 'installation_found' && (
         <>
           <div className="flex items-center gap-2">
@@ -49,6 +64,38 @@ export function AgentAvailabilityIndicator({
           </p>
         </>
       )}
+      {availability.status === 'installed_version' && (
+        <>
+          <div className="flex items-center gap-2">
+            <Check className="h-4 w-4 text-success" />
+            <span className="text-success">
+              {t('settings.agents.availability.installedVersion', {
+                version: availability.version,
+              })}
+            </span>
+          </div>
+          <p className="text-xs text-muted-foreground pl-6">
+            {t('settings.agents.availability.installedVersionTooltip')}
+          </p>
+        </>
+      )}
+      {availability.status === 'version_too_old' && (
+        <>
+          <div className="flex items-center gap-2">
+            <AlertCircle className="h-4 w-4 text-warning" />
+            <span className="text-warning">
+              {t('settings.agents.availability.versionTooOld', {
+                found: availability.found,
+              })}
+            </span>
+          </div>
+          <p className="text-xs text-muted-foreground pl-6">
+            {t('settings.agents.availability.versionTooOldTooltip', {
+              required: availability.required,
+            })}
+          </p>
+        </>
+      )}
       {availability.status === 'not_found' && (
         <>
           <div className="flex items-center gap-2">
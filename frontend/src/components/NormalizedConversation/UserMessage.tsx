@@ -22,10 +22,10 @@ const UserMessage = ({
     useRetryUi();
   const { isAttemptRunning } = useAttemptExecution(taskAttempt?.id);
 
-  const canFork = !!(
+  const canResume = !!(
     taskAttempt?.session?.executor &&
     capabilities?.[taskAttempt.session.executor]?.includes(
-      BaseAgentCapability.SESSION_FORK
+      BaseAgentCapability.RESUME_SESSION
     )
   );
 
@@ -49,8 +49,8 @@ const UserMessage = ({
     isProcessGreyed(executionProcessId) &&
     !showRetryEditor;
 
-  // Only show retry button when allowed (has process, can fork, not running)
-  const canRetry = executionProcessId && canFork && !isAttemptRunning;
+  // Only show retry button when allowed (has process, can resume, not running)
+  const canRetry = executionProcessId && canResume && !isAttemptRunning;
 
   return (
     <div className={`py-2 ${greyed ? 'opacity-50 pointer-events-none' : ''}`}>
@@ -38,6 +38,8 @@ export function getAgentName(
       return 'Copilot';
     case BaseCodingAgent.DROID:
       return 'Droid';
+    case BaseCodingAgent.CLAUDE_API:
+      return 'Claude API';
   }
 }
 
@@ -56,6 +58,7 @@ export function AgentIcon({ agent, className = 'h-4 w-4' }: AgentIconProps) {
 
   switch (agent) {
     case BaseCodingAgent.CLAUDE_CODE:
+    case BaseCodingAgent.CLAUDE_API:
       iconPath = `/agents/claude${suffix}.svg`;
       break;
     case BaseCodingAgent.AMP:
@@ -202,6 +202,7 @@ const TaskFormDialogImpl = NiceModal.create<TaskFormDialogProps>((props) => {
             task,
             executor_profile_id: value.executorProfileId!,
             repos,
+            run_template: null,
           },
           { onSuccess: () => modal.remove() }
         );
@@ -199,11 +199,12 @@ function renderToolUseEntry(
     );
   }
 
-  // Script entries (Setup Script, Cleanup Script, Tool Install Script)
+  // Script entries (setup, cleanup, tool install and test scripts)
   const scriptToolNames = [
     'Setup Script',
     'Cleanup Script',
     'Tool Install Script',
+    'Test Script',
   ];
   if (
     action_type.action === 'command_run' &&
@@ -278,6 +279,7 @@ function NewDisplayConversationEntry(props: Props) {
       );
 
     case 'system_message':
+    case 'raw_output':
       return (
         <SystemMessageEntry
           content={entry.content}
@@ -287,8 +289,9 @@ function NewDisplayConversationEntry(props: Props) {
 
     case 'thinking':
       return (
-        <ChatThinkingMessage
+        <ThinkingEntry
           content={entry.content}
+          expansionKey={expansionKey}
           taskAttemptId={taskAttempt?.id}
         />
       );
@@ -307,6 +310,12 @@ function NewDisplayConversationEntry(props: Props) {
 
     case 'user_feedback':
     case 'loading':
+    case 'run_summary':
+    case 'workspace_changes':
+    case 'run_hook':
+    case 'review_finding':
+    case 'task_list_update':
+    case 'loop_detected':
       // Fallback to legacy component for these entry types
       return (
         <DisplayConversationEntry
@@ -628,6 +637,33 @@ function SystemMessageEntry({
   );
 }
 
+/**
+ * Thinking entry, collapsed to its first line until expanded
+ */
+function ThinkingEntry({
+  content,
+  expansionKey,
+  taskAttemptId,
+}: {
+  content: string;
+  expansionKey: string;
+  taskAttemptId?: string;
+}) {
+  const [expanded, toggle] = usePersistedExpanded(
+    `thinking:${expansionKey}`,
+    false
+  );
+
+  return (
+    <ChatThinkingMessage
+      content={content}
+      taskAttemptId={taskAttemptId}
+      expanded={expanded}
+      onToggle={toggle}
+    />
+  );
+}
+
 /**
  * Error message entry with expandable content
  */
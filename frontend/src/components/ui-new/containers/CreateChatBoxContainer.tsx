@@ -162,6 +162,7 @@ export function CreateChatBoxContainer() {
         repo_id: r.id,
         target_branch: targetBranches[r.id] ?? 'main',
       })),
+      run_template: null,
     });
 
     // Clear attachments and draft after successful creation
@@ -6,19 +6,33 @@ interface ChatThinkingMessageProps {
   content: string;
   className?: string;
   taskAttemptId?: string;
+  expanded?: boolean;
+  onToggle?: () => void;
 }
 
 export function ChatThinkingMessage({
   content,
   className,
   taskAttemptId,
+  expanded = true,
+  onToggle,
 }: ChatThinkingMessageProps) {
   return (
     <div
-      className={cn('flex items-start gap-base text-sm text-low', className)}
+      className={cn(
+        'flex items-start gap-base text-sm text-low',
+        onToggle && 'cursor-pointer',
+        className
+      )}
+      onClick={onToggle}
+      role={onToggle ? 'button' : undefined}
     >
       <ChatDotsIcon className="shrink-0 size-icon-base mt-0.5" />
-      <ChatMarkdown content={content} workspaceId={taskAttemptId} />
+      {expanded ? (
+        <ChatMarkdown content={content} workspaceId={taskAttemptId} />
+      ) : (
+        <span className="truncate">{content.trim().split('\n')[0]}</span>
+      )}
     </div>
   );
 }
@@ -5,7 +5,10 @@ import { configApi } from '../lib/api';
 export type AgentAvailabilityState =
   | { status: 'checking' }
   | { status: 'login_detected' }
+  | { status: 'login_expired'; expiredAt: Date }
   | { status: 'installation_found' }
+  | { status: 'installed_version'; version: string }
+  | { status: 'version_too_old'; found: string; required: string }
   | { status: 'not_found' }
   | null;
 
@@ -31,9 +34,28 @@ export function useAgentAvailability(
           case 'LOGIN_DETECTED':
             setAvailability({ status: 'login_detected' });
             break;
+          case 'LOGIN_EXPIRED':
+            setAvailability({
+              status: 'login_expired',
+              expiredAt: new Date(Number(info.expired_at) * 1000),
+            });
+            break;
           case 'INSTALLATION_FOUND':
             setAvailability({ status: 'installation_found' });
             break;
+          case 'INSTALLED_VERSION':
+            setAvailability({
+              status: 'installed_version',
+              version: info.version,
+            });
+            break;
+          case 'VERSION_TOO_OLD':
+            setAvailability({
+              status: 'version_too_old',
+              found: info.found,
+              required: info.required,
+            });
+            break;
           case 'NOT_FOUND':
             setAvailability({ status: 'not_found' });
             break;
@@ -29,6 +29,7 @@ export function useAttemptCreation({
         task_id: taskId,
         executor_profile_id: profile,
         repos,
+        run_template: null,
       }),
     onSuccess: (newAttempt: Workspace) => {
       queryClient.setQueryData(
@@ -327,6 +327,9 @@ export const useConversationHistory = ({
               case 'ToolInstallScript':
                 toolName = 'Tool Install Script';
                 break;
+              case 'TestScript':
+                toolName = 'Test Script';
+                break;
               default:
                 return [];
             }
@@ -18,6 +18,8 @@ import {
   GitBranch,
   Project,
   ProjectRepo,
+  ProfileDiagnostic,
+  RunTemplate,
   Repo,
   RepoWithTargetBranch,
   CreateProject,
@@ -89,6 +91,16 @@ import {
   AbortConflictsRequest,
   Session,
   Workspace,
+  Checkpoint,
+  RollbackCheckpointRequest,
+  ApprovalAuditEntry,
+  WorkflowProgress,
+  ToolStats,
+  SessionStats,
+  ApprovalAuditQuery,
+  LogSearchHit,
+  LogSearchQuery,
+  NormalizedEntry,
 } from 'shared/types';
 import type { WorkspaceWithSession } from '@/types/attempt';
 import { createWorkspaceWithSession } from '@/types/attempt';
@@ -502,6 +514,39 @@ export const sessionsApi = {
     });
     return handleApiResponse<ExecutionProcess>(response);
   },
+
+  getApprovalAudit: async (
+    sessionId: string,
+    query: Partial<ApprovalAuditQuery> = {}
+  ): Promise<ApprovalAuditEntry[]> => {
+    const params = new URLSearchParams();
+    for (const [key, value] of Object.entries(query)) {
+      if (value != null) params.set(key, value);
+    }
+    const response = await makeRequest(
+      `/api/sessions/${sessionId}/approval-audit?${params.toString()}`
+    );
+    return handleApiResponse<ApprovalAuditEntry[]>(response);
+  },
+
+  getWorkflowProgress: async (
+    sessionId: string
+  ): Promise<WorkflowProgress | null> => {
+    const response = await makeRequest(
+      `/api/sessions/${sessionId}/workflow-progress`
+    );
+    return handleApiResponse<WorkflowProgress | null>(response);
+  },
+
+  getToolStats: async (sessionId: string): Promise<ToolStats[]> => {
+    const response = await makeRequest(`/api/sessions/${sessionId}/tool-stats`);
+    return handleApiResponse<ToolStats[]>(response);
+  },
+
+  getStats: async (sessionId: string): Promise<SessionStats> => {
+    const response = await makeRequest(`/api/sessions/${sessionId}/stats`);
+    return handleApiResponse<SessionStats>(response);
+  },
 };
 
 // Task Attempts APIs
@@ -618,6 +663,27 @@ export const attemptsApi = {
     return handleApiResponse<string | null>(response);
   },
 
+  getCheckpoints: async (attemptId: string): Promise<Checkpoint[]> => {
+    const response = await makeRequest(
+      `/api/task-attempts/${attemptId}/checkpoints`
+    );
+    return handleApiResponse<Checkpoint[]>(response);
+  },
+
+  rollbackCheckpoint: async (
+    attemptId: string,
+    data: RollbackCheckpointRequest
+  ): Promise<void> => {
+    const response = await makeRequest(
+      `/api/task-attempts/${attemptId}/checkpoints/rollback`,
+      {
+        method: 'POST',
+        body: JSON.stringify(data),
+      }
+    );
+    return handleApiResponse<void>(response);
+  },
+
   merge: async (
     attemptId: string,
     data: MergeTaskAttemptRequest
@@ -813,6 +879,30 @@ export const executionProcessesApi = {
     return handleApiResponse<ExecutionProcessRepoState[]>(response);
   },
 
+  searchLogs: async (
+    processId: string,
+    query: LogSearchQuery
+  ): Promise<LogSearchHit[]> => {
+    const params = new URLSearchParams();
+    for (const [key, value] of Object.entries(query)) {
+      if (value != null) params.set(key, String(value));
+    }
+    const response = await makeRequest(
+      `/api/execution-processes/${processId}/logs/search?${params.toString()}`
+    );
+    return handleApiResponse<LogSearchHit[]>(response);
+  },
+
+  getFullEntry: async (
+    processId: string,
+    entryId: string
+  ): Promise<NormalizedEntry> => {
+    const response = await makeRequest(
+      `/api/execution-processes/${processId}/full-entries/${entryId}`
+    );
+    return handleApiResponse<NormalizedEntry>(response);
+  },
+
   stopExecutionProcess: async (processId: string): Promise<void> => {
     const response = await makeRequest(
       `/api/execution-processes/${processId}/stop`,
@@ -921,6 +1011,27 @@ export const configApi = {
     );
     return handleApiResponse<AvailabilityInfo>(response);
   },
+  installAgent: async (agent: BaseCodingAgent): Promise<void> => {
+    const response = await makeRequest(
+      `/api/agents/install?executor=${encodeURIComponent(agent)}`,
+      { method: 'POST' }
+    );
+    return handleApiResponse<void>(response);
+  },
+  // Server-sent events with the output of the agent's latest install
+  getInstallStreamUrl: (agent: BaseCodingAgent): string =>
+    `/api/agents/install/stream?executor=${encodeURIComponent(agent)}`,
+  loginAgent: async (agent: BaseCodingAgent): Promise<void> => {
+    const response = await makeRequest(
+      `/api/agents/login?executor=${encodeURIComponent(agent)}`,
+      { method: 'POST' }
+    );
+    return handleApiResponse<void>(response);
+  },
+  // Server-sent events with the output of the agent's latest login; a JSON
+  // patch at /login_prompt carries the URL and code to finish it
+  getLoginStreamUrl: (agent: BaseCodingAgent): string =>
+    `/api/agents/login/stream?executor=${encodeURIComponent(agent)}`,
 };
 
 // Task Tags APIs (all tags are global)
@@ -1007,6 +1118,44 @@ export const profilesApi = {
     });
     return handleApiResponse<string>(response);
   },
+  validate: async (content: string): Promise<ProfileDiagnostic[]> => {
+    const response = await makeRequest('/api/profiles/validate', {
+      method: 'POST',
+      body: content,
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    return handleApiResponse<ProfileDiagnostic[]>(response);
+  },
+};
+
+// Run templates API
+export const runTemplatesApi = {
+  list: async (): Promise<Record<string, RunTemplate>> => {
+    const response = await makeRequest('/api/run-templates');
+    return handleApiResponse<Record<string, RunTemplate>>(response);
+  },
+  upsert: async (
+    name: string,
+    template: RunTemplate
+  ): Promise<RunTemplate> => {
+    const response = await makeRequest(
+      `/api/run-templates/${encodeURIComponent(name)}`,
+      {
+        method: 'PUT',
+        body: JSON.stringify(template),
+      }
+    );
+    return handleApiResponse<RunTemplate>(response);
+  },
+  delete: async (name: string): Promise<RunTemplate> => {
+    const response = await makeRequest(
+      `/api/run-templates/${encodeURIComponent(name)}`,
+      { method: 'DELETE' }
+    );
+    return handleApiResponse<RunTemplate>(response);
+  },
 };
 
 // Images API
@@ -461,6 +461,23 @@ export function GeneralSettings() {
               )}
             </p>
           </div>
+          <div className="flex items-center space-x-2">
+            <Checkbox
+              id="checkpoints-enabled"
+              checked={draft?.checkpoints_enabled ?? false}
+              onCheckedChange={(checked: boolean) =>
+                updateDraft({ checkpoints_enabled: checked })
+              }
+            />
+            <div className="space-y-0.5">
+              <Label htmlFor="checkpoints-enabled" className="cursor-pointer">
+                {t('settings.general.git.checkpoints.label')}
+              </Label>
+              <p className="text-sm text-muted-foreground">
+                {t('settings.general.git.checkpoints.helper')}
+              </p>
+            </div>
+          </div>
         </CardContent>
       </Card>
 
@@ -659,6 +676,26 @@ export function GeneralSettings() {
               </p>
             </div>
           </div>
+          <div className="flex items-center space-x-2">
+            <Checkbox
+              id="executor-telemetry-enabled"
+              checked={draft?.executor_telemetry_enabled ?? false}
+              onCheckedChange={(checked: boolean) =>
+                updateDraft({ executor_telemetry_enabled: checked })
+              }
+            />
+            <div className="space-y-0.5">
+              <Label
+                htmlFor="executor-telemetry-enabled"
+                className="cursor-pointer"
+              >
+                {t('settings.general.privacy.executorTelemetry.label')}
+              </Label>
+              <p className="text-sm text-muted-foreground">
+                {t('settings.general.privacy.executorTelemetry.helper')}
+              </p>
+            </div>
+          </div>
         </CardContent>
       </Card>
 
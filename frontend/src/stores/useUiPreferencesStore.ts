@@ -62,6 +62,7 @@ export type PersistKey =
   | `todo:${string}`
   | `user:${string}`
   | `system:${string}`
+  | `thinking:${string}`
   | `error:${string}`
   | `entry:${string}`;
 
@@ -122,10 +122,53 @@ contentOmitted: boolean,
 /**
  * Optional precomputed stats for omitted content
  */
-additions: number | null, deletions: number | null, };
+additions: number | null, deletions: number | null, 
+/**
+ * Change hunks, in file order; empty when contents are omitted or binary
+ */
+hunks: Array<DiffHunk>, 
+/**
+ * Functions/types added, removed or modified; only computed for patch review
+ */
+semanticSummary: SemanticSummary | null, };
 
 export type DiffChangeKind = "added" | "deleted" | "modified" | "renamed" | "copied" | "permissionChange";
 
+export type DiffHunk = { oldStart: number, oldLines: number, newStart: number, newLines: number, additions: number, deletions: number, };
+
+export type SemanticSummary = { language: string, changes: Array<SymbolChange>, };
+
+export type SymbolChange = { kind: SymbolKind, 
+/**
+ * Name qualified by its enclosing types/modules, e.g. `Parser::parse`
+ */
+name: string, change: SymbolChangeKind, };
+
+export type SymbolKind = "function" | "type";
+
+export type SymbolChangeKind = "added" | "removed" | "modified";
+
+/**
+ * Liveness of a running execution, sent periodically while it runs
+ */
+export type Heartbeat = { 
+/**
+ * Seconds since the execution started
+ */
+elapsed_secs: bigint, 
+/**
+ * Bytes of stdout and stderr produced so far
+ */
+output_bytes: bigint, 
+/**
+ * Seconds since the last output
+ */
+idle_secs: bigint, 
+/**
+ * Whether the execution has been quiet for longer than it should be
+ */
+stalled: boolean, };
+
 export type ApiResponse<T, E = T> = { success: boolean, data: T | null, error_data: E | null, message: string | null, };
 
 export type LoginStatus = { "status": "loggedout" } | { "status": "loggedin", profile: ProfileResponse, };
@@ -206,7 +249,11 @@ export type Environment = { os_type: string, os_version: string, os_architecture
 
 export type McpServerQuery = { executor: BaseCodingAgent, };
 
-export type UpdateMcpServersBody = { servers: { [key in string]?: JsonValue }, };
+export type UpdateMcpServersBody = { servers: { [key in string]?: JsonValue }, 
+/**
+ * Overwrite servers that were changed outside vibe-kanban
+ */
+force?: boolean, };
 
 export type GetMcpServerResponse = { mcp_config: McpConfig, config_path: string, };
 
@@ -216,9 +263,23 @@ export type CheckEditorAvailabilityResponse = { available: boolean, };
 
 export type CheckAgentAvailabilityQuery = { executor: BaseCodingAgent, };
 
+export type AgentInstallQuery = { executor: BaseCodingAgent, };
+
+export type AgentLoginQuery = { executor: BaseCodingAgent, };
+
+export type TelemetryQuery = { 
+/**
+ * Tenant whose runs to report; the server's own runs if unset
+ */
+tenant: string | null, };
+
 export type CurrentUserResponse = { user_id: string, };
 
-export type CreateFollowUpAttempt = { prompt: string, variant: string | null, retry_process_id: string | null, force_when_dirty: boolean | null, perform_git_reset: boolean | null, };
+export type CreateFollowUpAttempt = { prompt: string, variant: string | null, retry_process_id: string | null, force_when_dirty: boolean | null, perform_git_reset: boolean | null, 
+/**
+ * Screenshots and files for the agent to look at
+ */
+attachments?: Array<Attachment>, };
 
 export type ChangeTargetBranchRequest = { repo_id: string, new_target_branch: string, };
 
@@ -240,7 +301,12 @@ export type AssignSharedTaskRequest = { new_assignee_user_id: string | null, };
 
 export type ShareTaskResponse = { shared_task_id: string, };
 
-export type CreateAndStartTaskRequest = { task: CreateTask, executor_profile_id: ExecutorProfileId, repos: Array<WorkspaceRepoInput>, };
+export type CreateAndStartTaskRequest = { task: CreateTask, executor_profile_id: ExecutorProfileId, repos: Array<WorkspaceRepoInput>, 
+/**
+ * Run template to launch with; its profile takes precedence over
+ * `executor_profile_id`
+ */
+run_template: string | null, };
 
 export type CreatePrApiRequest = { title: string, body: string | null, target_branch: string | null, draft: boolean | null, repo_id: string, auto_generate_description: boolean, };
 
@@ -248,7 +314,12 @@ export type ImageResponse = { id: string, file_path: string, original_name: stri
 
 export type ImageMetadata = { exists: boolean, file_name: string | null, path: string | null, size_bytes: bigint | null, format: string | null, proxy_url: string | null, };
 
-export type CreateTaskAttemptBody = { task_id: string, executor_profile_id: ExecutorProfileId, repos: Array<WorkspaceRepoInput>, };
+export type CreateTaskAttemptBody = { task_id: string, executor_profile_id: ExecutorProfileId, repos: Array<WorkspaceRepoInput>, 
+/**
+ * Run template to launch with; its profile takes precedence over
+ * `executor_profile_id`
+ */
+run_template: string | null, };
 
 export type WorkspaceRepoInput = { repo_id: string, target_branch: string, };
 
@@ -362,11 +433,31 @@ pr_status: MergeStatus | null, };
 
 export type WorkspaceSummaryResponse = { summaries: Array<WorkspaceSummary>, };
 
+export type RollbackCheckpointRequest = { checkpoint_id: string, };
+
 export type DirectoryEntry = { name: string, path: string, is_directory: boolean, is_git_repo: boolean, last_modified: bigint | null, };
 
 export type DirectoryListResponse = { entries: Array<DirectoryEntry>, current_path: string, };
 
-export type Config = { config_version: string, theme: ThemeMode, executor_profile: ExecutorProfileId, disclaimer_acknowledged: boolean, onboarding_acknowledged: boolean, notifications: NotificationConfig, editor: EditorConfig, github: GitHubConfig, analytics_enabled: boolean, workspace_dir: string | null, last_app_version: string | null, show_release_notes: boolean, language: UiLanguage, git_branch_prefix: string, showcases: ShowcaseState, pr_auto_description_enabled: boolean, pr_auto_description_prompt: string | null, };
+export type Config = { config_version: string, theme: ThemeMode, executor_profile: ExecutorProfileId, disclaimer_acknowledged: boolean, onboarding_acknowledged: boolean, notifications: NotificationConfig, editor: EditorConfig, github: GitHubConfig, analytics_enabled: boolean, workspace_dir: string | null, last_app_version: string | null, show_release_notes: boolean, language: UiLanguage, git_branch_prefix: string, showcases: ShowcaseState, pr_auto_description_enabled: boolean, pr_auto_description_prompt: string | null, 
+/**
+ * Opt-in aggregation of anonymized executor usage
+ */
+executor_telemetry_enabled: boolean, 
+/**
+ * Snapshot worktrees before approved edits so they can be rolled back
+ */
+checkpoints_enabled: boolean, 
+/**
+ * Who executions run on behalf of, checked against the role policies
+ * in profiles.json; unset runs them unchecked
+ */
+principal: Principal | null, 
+/**
+ * Tenant new sessions run as, with its profiles and credentials; unset
+ * runs them as the server's own
+ */
+tenant: string | null, };
 
 export type NotificationConfig = { sound_enabled: boolean, push_enabled: boolean, sound_file: SoundFile, };
 
@@ -404,32 +495,75 @@ data: DraftFollowUpData,
  */
 queued_at: string, };
 
-export type QueueStatus = { "status": "empty" } | { "status": "queued", message: QueuedMessage, };
+export type QueueMode = "replace" | "sequential" | "coalesce";
+
+export type QueueStatus = { "status": "empty" } | { "status": "queued", message: QueuedMessage, 
+/**
+ * Messages that run one after another once `message` has run
+ */
+waiting: Array<QueuedMessage>, };
 
 export type ConflictOp = "rebase" | "merge" | "cherry_pick" | "revert";
 
-export type ExecutorAction = { typ: ExecutorActionType, next_action: ExecutorAction | null, };
+export type RollbackStrategy = "revert" | "reset";
+
+export type RepoRollback = { repo_id: string, 
+/**
+ * New branch head, or None when the execution made no commits in this repo
+ */
+new_head: string | null, };
+
+export type SendUserInputRequest = { message: string, };
+
+export type RollbackExecutionRequest = { strategy: RollbackStrategy, };
+
+export type FileSelection = { path: string, 
+/**
+ * Indices into the file's diff hunks; omit to take the whole file
+ */
+hunks: Array<number> | null, };
+
+export type ApplyExecutionChangesRequest = { repo_id: string, files: Array<FileSelection>, };
+
+export type ExecutorAction = { typ: ExecutorActionType, next_action: ExecutorAction | null, 
+/**
+ * Whether the action runs after the action before it succeeded, failed,
+ * or either way
+ */
+run_if: RunCondition, 
+/**
+ * Tenant whose profiles, secrets and budget the action runs with;
+ * `None` runs it as the server's own
+ */
+tenant?: string | null, };
 
 export type McpConfig = { servers: { [key in string]?: JsonValue }, servers_path: Array<string>, template: JsonValue, preconfigured: JsonValue, is_toml_config: boolean, };
 
 export type ExecutorActionType = { "type": "CodingAgentInitialRequest" } & CodingAgentInitialRequest | { "type": "CodingAgentFollowUpRequest" } & CodingAgentFollowUpRequest | { "type": "ScriptRequest" } & ScriptRequest;
 
-export type ScriptContext = "SetupScript" | "CleanupScript" | "DevServer" | "ToolInstallScript";
+export type RunCondition = "on_success" | "on_failure" | "always";
+
+export type ScriptContext = "SetupScript" | "CleanupScript" | "DevServer" | "ToolInstallScript" | "TestScript";
 
 export type ScriptRequest = { script: string, language: ScriptRequestLanguage, context: ScriptContext, 
 /**
  * Optional relative path to execute the script in (relative to container_ref).
  * If None, uses the container_ref directory directly.
  */
-working_dir: string | null, };
+working_dir: string | null, 
+/**
+ * Stop the script once it has run this many seconds, e.g. a hanging
+ * test suite. Dev servers are left without a limit.
+ */
+timeout_secs: bigint | null, };
 
 export type ScriptRequestLanguage = "Bash";
 
-export enum BaseCodingAgent { CLAUDE_CODE = "CLAUDE_CODE", AMP = "AMP", GEMINI = "GEMINI", CODEX = "CODEX", OPENCODE = "OPENCODE", CURSOR_AGENT = "CURSOR_AGENT", QWEN_CODE = "QWEN_CODE", COPILOT = "COPILOT", DROID = "DROID" }
+export enum BaseCodingAgent { CLAUDE_CODE = "CLAUDE_CODE", AMP = "AMP", GEMINI = "GEMINI", CODEX = "CODEX", OPENCODE = "OPENCODE", CURSOR_AGENT = "CURSOR_AGENT", QWEN_CODE = "QWEN_CODE", COPILOT = "COPILOT", DROID = "DROID", CLAUDE_API = "CLAUDE_API" }
 
-export type CodingAgent = { "CLAUDE_CODE": ClaudeCode } | { "AMP": Amp } | { "GEMINI": Gemini } | { "CODEX": Codex } | { "OPENCODE": Opencode } | { "CURSOR_AGENT": CursorAgent } | { "QWEN_CODE": QwenCode } | { "COPILOT": Copilot } | { "DROID": Droid };
+export type CodingAgent = { "CLAUDE_CODE": ClaudeCode } | { "AMP": Amp } | { "GEMINI": Gemini } | { "CODEX": Codex } | { "OPENCODE": Opencode } | { "CURSOR_AGENT": CursorAgent } | { "QWEN_CODE": QwenCode } | { "COPILOT": Copilot } | { "DROID": Droid } | { "CLAUDE_API": ClaudeApi };
 
-export type AvailabilityInfo = { "type": "LOGIN_DETECTED", last_auth_timestamp: bigint, } | { "type": "INSTALLATION_FOUND" } | { "type": "NOT_FOUND" };
+export type AvailabilityInfo = { "type": "LOGIN_DETECTED", last_auth_timestamp: bigint, } | { "type": "LOGIN_EXPIRED", expired_at: bigint, } | { "type": "INSTALLATION_FOUND" } | { "type": "INSTALLED_VERSION", version: string, } | { "type": "VERSION_TOO_OLD", found: string, required: string, } | { "type": "NOT_FOUND" };
 
 export type CommandBuilder = { 
 /**
@@ -441,6 +575,291 @@ base: string,
  */
 params: Array<string> | null, };
 
+export type NormalizationStage = { "stage": "strip_ansi" } | { "stage": "redact", patterns: Array<string>, builtin_patterns: boolean, } | { "stage": "classify", rules: Array<ClassificationRule>, } | { "stage": "coalesce", window_ms: bigint, } | { "stage": "delta_streaming" } | { "stage": "merge_streams" } | { "stage": "hide_entries", kinds: Array<EntryKind>, } | { "stage": "truncate_outputs", max_kb: bigint, };
+
+export type ClassificationRule = { 
+/**
+ * Regex matched against the entry content
+ */
+pattern: string, entry_type: ClassifiedAs, };
+
+export type ClassifiedAs = "system_message" | "error" | "setup_required";
+
+export type ThinkingVisibility = "show" | "collapse" | "hide";
+
+export type RetryPolicy = { 
+/**
+ * Total number of attempts, including the first
+ */
+max_attempts: number, 
+/**
+ * Delay before the first retry; doubled after each further failure
+ */
+initial_backoff_ms: bigint, 
+/**
+ * Upper bound for the delay between attempts
+ */
+max_backoff_ms: bigint, 
+/**
+ * Error classes that are worth retrying
+ */
+retry_on: Array<RetryableErrorClass>, };
+
+export type RetryableErrorClass = "io" | "executable_not_found" | "rate_limited" | "network" | "context_overflow";
+
+export type LoopAction = "warn" | "interrupt" | "correct";
+
+export type LoopDetection = { 
+/**
+ * Identical tool calls in a row that count as a loop; defaults to 5
+ */
+max_repeated_tool_calls?: number | null, 
+/**
+ * Identical assistant messages in a row that count as a loop; defaults to 3
+ */
+max_repeated_messages?: number | null, action: LoopAction, 
+/**
+ * Message sent to the agent by the `correct` action
+ */
+correction?: string | null, };
+
+export type Budget = { 
+/**
+ * Stop the run once it has cost more than this many US dollars, as
+ * reported by the agent
+ */
+max_cost_usd?: number | null, 
+/**
+ * Stop the run once it has used more than this many tokens, input
+ * (cached included) and output together
+ */
+max_tokens?: bigint | null, };
+
+export type Supervisor = { 
+/**
+ * Times a crashed agent is resumed within one execution; defaults to 2
+ */
+max_restarts?: number | null, 
+/**
+ * Milliseconds to wait before resuming it; defaults to 1000
+ */
+restart_delay_ms?: bigint | null, };
+
+export type LogTee = { 
+/**
+ * Size a log file may grow to before it is rotated; defaults to 10 MiB
+ */
+max_file_bytes?: bigint | null, 
+/**
+ * Files kept per stream, the one being written included; defaults to 5
+ */
+max_files?: number | null, };
+
+export type CancelReason = "user_requested" | "timeout" | "parent_cancelled" | "shutdown" | "loop_detected";
+
+/**
+ * Why a run failed, without any of its content
+ */
+export type FailureClass = "executable_not_found" | "spawn_error" | "auth_required" | "unauthorized" | "rate_limited" | "out_of_memory" | "resource_limit" | "network" | "context_overflow" | "non_zero_exit" | "killed" | "other";
+
+export type DurationBucket = "under_one_minute" | "under_ten_minutes" | "under_one_hour" | "one_hour_or_more";
+
+export type ExecutorUsage = { runs: number, succeeded: number, failed: number, cancelled: number, failure_classes: { [key in FailureClass]?: number }, durations: { [key in DurationBucket]?: number }, };
+
+/**
+ * Everything telemetry has collected, exactly as it would be sent
+ */
+export type TelemetryReport = { 
+/**
+ * When collection started
+ */
+since: string, executors: { [key in BaseCodingAgent]?: ExecutorUsage }, };
+
+export type ExecutorErrorKind = "not_installed" | "auth_expired" | "rate_limited" | "spawn_failed" | "stream_parse" | "interrupted" | "timeout" | "unsupported" | "invalid_request" | "forbidden" | "other";
+
+export type Remediation = "install_agent" | "upgrade_agent" | "log_in" | "wait_and_retry" | "retry" | "raise_timeout" | "fix_config" | "change_request" | "ask_admin" | "report_bug";
+
+/**
+ * An executor error as UIs receive it
+ */
+export type ExecutorErrorInfo = { kind: ExecutorErrorKind, remediation: Remediation, message: string, hint: string, };
+
+/**
+ * What the user needs to finish a login in the browser, published at
+ * `/login_prompt` as the login flow shows it
+ */
+export type LoginPrompt = { 
+/**
+ * Page to open to log in
+ */
+url: string | null, 
+/**
+ * Code to enter on that page, for device code flows
+ */
+code: string | null, };
+
+export type DiagnosticSeverity = "error" | "warning";
+
+export type ProfileDiagnostic = { 
+/**
+ * JSON Pointer to the value the diagnostic is about, e.g.
+ * `/executors/CLAUDE_CODE/PLAN/CLAUDE_CODE/model`
+ */
+path: string, message: string, severity: DiagnosticSeverity, };
+
+export type WorkflowTaskStatus = "pending" | "running" | "completed" | "failed";
+
+export type WorkflowTaskProgress = { id: string, name: string | null, 
+/**
+ * Agent running the task, as assigned or as claude-flow reports it
+ */
+agent: string | null, status: WorkflowTaskStatus, 
+/**
+ * Why the task failed
+ */
+error: string | null, };
+
+/**
+ * Where each task of a workflow run is at, published at
+ * `/workflow_progress` as claude-flow reports it
+ */
+export type WorkflowProgress = { workflow: string | null, 
+/**
+ * Tasks in the order the workflow declares them, then ones only seen in
+ * events
+ */
+tasks: Array<WorkflowTaskProgress>, };
+
+export type CrateDescription = { version: number, 
+/**
+ * Version of the executors crate that produced the document
+ */
+crate_version: string, executors: Array<ExecutorDescription>, 
+/**
+ * Run templates by name, usable as personas on top of any profile
+ */
+personas: { [key in string]?: RunTemplate }, features: FeatureFlags, };
+
+export type ExecutorDescription = { executor: BaseCodingAgent, 
+/**
+ * Configured variants, `DEFAULT` first
+ */
+variants: Array<string>, capabilities: Array<BaseAgentCapability>, 
+/**
+ * JSON schema (draft-07) of the executor's configuration
+ */
+config_schema: JsonValue, };
+
+/**
+ * Optional features and whether they are active
+ */
+export type FeatureFlags = { encryption_at_rest: boolean, telemetry: boolean, recording: boolean, role_policies: boolean, };
+
+/**
+ * A snapshot of the worktree taken before a tool call
+ */
+export type Checkpoint = { id: string, 
+/**
+ * Tool call the checkpoint was taken for
+ */
+tool_name: string, tool_call_id: string, created_at: string, };
+
+export type StandbyConfig = { 
+/**
+ * Seconds an unclaimed session is kept before it is recycled
+ */
+ttl_secs: bigint, };
+
+export type PolicyDecision = "allow" | "deny" | "ask";
+
+export type ApprovalRule = { decision: PolicyDecision, 
+/**
+ * Tool names the rule applies to, case-insensitive; empty matches any tool
+ */
+tools: Array<string>, 
+/**
+ * Globs of which one must match a file path in the tool input, as the
+ * agent reports it (usually absolute)
+ */
+paths: Array<string>, 
+/**
+ * Regex that must match the shell command in the tool input
+ */
+command?: string | null, 
+/**
+ * Told to the agent when the rule denies a call
+ */
+reason?: string | null, };
+
+export type ApprovalPolicy = { 
+/**
+ * Checked in order; the first matching rule decides
+ */
+rules: Array<ApprovalRule>, 
+/**
+ * Decision for calls no rule matches; asks when unset
+ */
+default?: PolicyDecision | null, };
+
+export type AgentPermissionMode = "plan" | "read_only" | "accept_edits" | "bypass";
+
+export type ApprovalDecider = "policy" | "user" | "auto" | "authorization";
+
+export type AuditDecision = "approved" | "denied" | "timed_out" | "failed";
+
+export type ApprovalAuditEntry = { session_id: string, execution_process_id: string, tenant?: string | null, tool_name: string, tool_call_id: string, 
+/**
+ * Hex SHA-256 of the tool input as JSON
+ */
+input_hash: string, decision: AuditDecision, decider: ApprovalDecider, reason?: string | null, latency_ms: bigint, requested_at: string, };
+
+export type ApprovalAuditQuery = { tool_name: string | null, decision: AuditDecision | null, decider: ApprovalDecider | null, };
+
+export type PromptTemplate = { system_prompt?: string | null, prepend?: string | null, append?: string | null, };
+
+export type PromptDelivery = "auto" | "inline" | "file";
+
+export type SshRemote = { 
+/**
+ * Host to connect to, as accepted by `ssh`
+ */
+host: string, user?: string | null, port?: number | null, 
+/**
+ * Private key to authenticate with
+ */
+identity_file?: string | null, 
+/**
+ * Where the host sees the worktrees
+ */
+worktree_root: string, 
+/**
+ * Extra `-o` options for `ssh`, e.g. `StrictHostKeyChecking=accept-new`
+ */
+ssh_options?: Array<string> | null, };
+
+export type ResourceLimits = { 
+/**
+ * CPU time each process may use, in seconds
+ */
+cpu_secs?: bigint | null, 
+/**
+ * Memory of the execution's cgroup, or address space of each process
+ * without one, in MiB
+ */
+memory_mb?: bigint | null, 
+/**
+ * Processes in the execution's cgroup, or of the server's user without one
+ */
+max_processes?: bigint | null, 
+/**
+ * Share of one CPU the execution may use; needs a cgroup
+ */
+cpu_percent?: number | null, };
+
+export type ScriptShell = "bash" | "zsh" | "pwsh" | "cmd";
+
+export type Attachment = { "type": "path", path: string, } | { "type": "image", media_type: string, data: string, name?: string | null, };
+
 export type ExecutorProfileId = { 
 /**
  * The executor type (e.g., "CLAUDE_CODE", "AMP")
@@ -453,17 +872,123 @@ variant: string | null, };
 
 export type ExecutorConfig = { [key in string]?: { "CLAUDE_CODE": ClaudeCode } | { "AMP": Amp } | { "GEMINI": Gemini } | { "CODEX": Codex } | { "OPENCODE": Opencode } | { "CURSOR_AGENT": CursorAgent } | { "QWEN_CODE": QwenCode } | { "COPILOT": Copilot } | { "DROID": Droid } };
 
-export type ExecutorConfigs = { executors: { [key in BaseCodingAgent]?: ExecutorConfig }, };
+export type ExecutorConfigs = { executors: { [key in BaseCodingAgent]?: ExecutorConfig }, 
+/**
+ * Named run configurations, keyed by name
+ */
+templates: { [key in string]?: RunTemplate }, 
+/**
+ * What each role may run, keyed by role name
+ */
+roles: { [key in string]?: RolePolicy }, 
+/**
+ * How many agent processes may run at once
+ */
+spawn_limits: SpawnLimits, 
+/**
+ * What a tenant's runs may use together; read from tenants' profiles
+ */
+tenant_budget: Budget, };
+
+export type SpawnLimits = { 
+/**
+ * Agent processes that may run at once across all executors
+ */
+max_concurrent?: number | null, 
+/**
+ * Agent processes that may run at once per executor
+ */
+per_executor: { [key in BaseCodingAgent]?: number }, };
+
+export type AgentProvider = "anthropic" | "openai" | "google" | "qwen" | "amp" | "cursor" | "copilot" | "droid" | "opencode";
+
+export type ProviderCooldown = { provider: AgentProvider, 
+/**
+ * When spawns for the provider are let through again
+ */
+until: string, 
+/**
+ * Rate limits in a row since the provider last served a run
+ */
+strikes: number, };
+
+export type RunTemplate = { executor_profile_id: ExecutorProfileId, permission_level: PermissionLevel, 
+/**
+ * Prompt sent to the agent; `{{task}}` is replaced by the task prompt,
+ * which is appended when the placeholder is missing
+ */
+prompt_template?: string | null, 
+/**
+ * Environment variables added to the profile's
+ */
+env: { [key in string]?: string }, 
+/**
+ * Scripts run in order after the agent finishes
+ */
+post_steps: Array<string>, };
 
-export enum BaseAgentCapability { SESSION_FORK = "SESSION_FORK", SETUP_HELPER = "SETUP_HELPER" }
+export type PermissionLevel = "PROFILE" | "READ_ONLY" | "SUPERVISED" | "AUTONOMOUS";
 
-export type ClaudeCode = { append_prompt: AppendPrompt, claude_code_router?: boolean | null, plan?: boolean | null, approvals?: boolean | null, model?: string | null, dangerously_skip_permissions?: boolean | null, disable_api_key?: boolean | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+export type RolePolicy = { 
+/**
+ * Profiles the role may use; empty allows any. An entry without a
+ * variant allows every variant of its executor.
+ */
+profiles: Array<ExecutorProfileId>, 
+/**
+ * Most permissive level the role may run agents at
+ */
+max_permission_level?: PermissionLevel | null, 
+/**
+ * Most the role may spend per run; profiles must set a budget within
+ * each of its limits
+ */
+max_budget?: Budget | null, };
 
-export type Gemini = { append_prompt: AppendPrompt, model?: string | null, yolo?: boolean | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+/**
+ * Who an execution runs on behalf of
+ */
+export type Principal = { id: string, roles: Array<string>, };
 
-export type Amp = { append_prompt: AppendPrompt, dangerously_allow_all?: boolean | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+export enum BaseAgentCapability { SESSION_FORK = "SESSION_FORK", SETUP_HELPER = "SETUP_HELPER", INSTALLER = "INSTALLER", PROMPT_FILE = "PROMPT_FILE", LIVE_INPUT = "LIVE_INPUT", RESUME_SESSION = "RESUME_SESSION", STREAM_JSON_INPUT = "STREAM_JSON_INPUT", INTERRUPT = "INTERRUPT", APPROVALS_HOOK = "APPROVALS_HOOK", IMAGE_INPUT = "IMAGE_INPUT", COST_REPORTING = "COST_REPORTING" }
 
-export type Codex = { append_prompt: AppendPrompt, sandbox?: SandboxMode | null, ask_for_approval?: AskForApproval | null, oss?: boolean | null, model?: string | null, model_reasoning_effort?: ReasoningEffort | null, model_reasoning_summary?: ReasoningSummary | null, model_reasoning_summary_format?: ReasoningSummaryFormat | null, profile?: string | null, base_instructions?: string | null, include_apply_patch_tool?: boolean | null, model_provider?: string | null, compact_prompt?: string | null, developer_instructions?: string | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+export type ClaudeCode = { append_prompt: AppendPrompt, claude_code_router?: boolean | null, plan?: boolean | null, approvals?: boolean | null, model?: ClaudeModel | null, 
+/**
+ * Model to switch to when the main one is overloaded
+ */
+fallback_model?: ClaudeModel | null, 
+/**
+ * Tokens the model may spend thinking before each answer
+ */
+max_thinking_tokens?: number | null, 
+/**
+ * Thinking budget by level, when `max_thinking_tokens` isn't set
+ */
+reasoning_effort?: ClaudeReasoningEffort | null, dangerously_skip_permissions?: boolean | null, disable_api_key?: boolean | null, 
+/**
+ * Secret holding the `ANTHROPIC_API_KEY` to run with, for headless
+ * deployments without a Claude login
+ */
+api_key_secret?: string | null, warm_standby?: StandbyConfig | null, approval_policy?: ApprovalPolicy | null, interactive?: boolean | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
+
+export type ClaudeModel = "sonnet" | "opus" | "haiku" | string;
+
+export type ClaudeReasoningEffort = "low" | "medium" | "high";
+
+export type Gemini = { append_prompt: AppendPrompt, model?: string | null, yolo?: boolean | null, approval_policy?: ApprovalPolicy | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
+
+export type Amp = { append_prompt: AppendPrompt, dangerously_allow_all?: boolean | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
+
+export type Codex = { append_prompt: AppendPrompt, sandbox?: SandboxMode | null, ask_for_approval?: AskForApproval | null, 
+/**
+ * Let commands reach the network in the workspace-write sandbox
+ */
+sandbox_network_access?: boolean | null, 
+/**
+ * Directories outside the workspace that commands may write to in the
+ * workspace-write sandbox
+ */
+sandbox_writable_roots?: Array<string> | null, oss?: boolean | null, model?: string | null, model_reasoning_effort?: ReasoningEffort | null, model_reasoning_summary?: ReasoningSummary | null, model_reasoning_summary_format?: ReasoningSummaryFormat | null, profile?: string | null, base_instructions?: string | null, include_apply_patch_tool?: boolean | null, model_provider?: string | null, compact_prompt?: string | null, developer_instructions?: string | null, approval_policy?: ApprovalPolicy | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
 
 export type SandboxMode = "auto" | "read-only" | "workspace-write" | "danger-full-access";
 
@@ -475,24 +1000,62 @@ export type ReasoningSummary = "auto" | "concise" | "detailed" | "none";
 
 export type ReasoningSummaryFormat = "none" | "experimental";
 
-export type CursorAgent = { append_prompt: AppendPrompt, force?: boolean | null, model?: string | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+export type CursorAgent = { append_prompt: AppendPrompt, force?: boolean | null, model?: string | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
 
-export type Copilot = { append_prompt: AppendPrompt, model?: string | null, allow_all_tools?: boolean | null, allow_tool?: string | null, deny_tool?: string | null, add_dir?: Array<string> | null, disable_mcp_server?: Array<string> | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+export type Copilot = { append_prompt: AppendPrompt, model?: string | null, allow_all_tools?: boolean | null, allow_tool?: string | null, deny_tool?: string | null, add_dir?: Array<string> | null, disable_mcp_server?: Array<string> | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
 
 export type Opencode = { append_prompt: AppendPrompt, model?: string | null, mode?: string | null, 
 /**
  * Auto-approve agent actions
  */
-auto_approve: boolean, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+auto_approve: boolean, approval_policy?: ApprovalPolicy | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
 
-export type QwenCode = { append_prompt: AppendPrompt, yolo?: boolean | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+export type QwenCode = { append_prompt: AppendPrompt, yolo?: boolean | null, model?: string | null, 
+/**
+ * OpenAI-compatible endpoint to run against, e.g. a self-hosted model
+ * at `http://localhost:11434/v1`
+ */
+base_url?: string | null, 
+/**
+ * Secret holding the endpoint's API key; local servers that take any
+ * key need none
+ */
+api_key_secret?: string | null, approval_policy?: ApprovalPolicy | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
 
-export type Droid = { append_prompt: AppendPrompt, autonomy: Autonomy, model?: string | null, reasoning_effort?: DroidReasoningEffort | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, };
+export type Droid = { append_prompt: AppendPrompt, autonomy: Autonomy, model?: string | null, reasoning_effort?: DroidReasoningEffort | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
 
 export type Autonomy = "normal" | "low" | "medium" | "high" | "skip-permissions-unsafe";
 
 export type DroidReasoningEffort = "none" | "dynamic" | "off" | "low" | "medium" | "high";
 
+export type ClaudeApi = { append_prompt: AppendPrompt, model?: ClaudeModel | null, 
+/**
+ * Secret holding the Anthropic API key; without one, `ANTHROPIC_API_KEY`
+ * from the environment is used
+ */
+api_key_secret?: string | null, 
+/**
+ * Messages API endpoint to use instead of Anthropic's, e.g. a gateway
+ */
+base_url?: string | null, 
+/**
+ * Tokens each response may take; defaults to 8192
+ */
+max_tokens?: number | null, 
+/**
+ * Requests to the model a run may make before it is stopped; defaults
+ * to 100
+ */
+max_turns?: number | null, 
+/**
+ * Only give the model the tools that read the worktree
+ */
+read_only?: boolean | null, 
+/**
+ * Run commands and edit files without asking for approval
+ */
+auto_approve: boolean, approval_policy?: ApprovalPolicy | null, base_command_override?: string | null, additional_params?: Array<string> | null, env?: { [key in string]?: string } | null, shell?: ScriptShell | null, permission_mode?: AgentPermissionMode | null, prompt?: PromptTemplate | null, remote?: SshRemote | null, dry_run?: boolean | null, cwd_relative?: string | null, auto_commit?: boolean | null, pre_run?: Array<RunHook> | null, post_run?: Array<RunHook> | null, timeout_secs?: bigint | null, idle_timeout_secs?: bigint | null, retry?: RetryPolicy | null, resource_limits?: ResourceLimits | null, loop_detection?: LoopDetection | null, budget?: Budget | null, supervisor?: Supervisor | null, normalization?: Array<NormalizationStage> | null, thinking_visibility?: ThinkingVisibility | null, log_tee?: LogTee | null, };
+
 export type AppendPrompt = string | null;
 
 export type CodingAgentInitialRequest = { prompt: string, 
@@ -504,7 +1067,26 @@ executor_profile_id: ExecutorProfileId,
  * Optional relative path to execute the agent in (relative to container_ref).
  * If None, uses the container_ref directory directly.
  */
-working_dir: string | null, };
+working_dir: string | null, 
+/**
+ * Subdirectory of the working directory to run the agent in, taking
+ * precedence over the profile's `cwd_relative`
+ */
+cwd_relative?: string | null, 
+/**
+ * Run template whose permission level and environment apply on top of
+ * the profile
+ */
+run_template?: string | null, 
+/**
+ * Files and images attached to the prompt
+ */
+attachments: Array<Attachment>, 
+/**
+ * Review the workspace's changes since this target instead of
+ * implementing; the prompt holds the reviewer's instructions
+ */
+review?: ReviewTarget | null, };
 
 export type CodingAgentFollowUpRequest = { prompt: string, session_id: string, 
 /**
@@ -515,15 +1097,107 @@ executor_profile_id: ExecutorProfileId,
  * Optional relative path to execute the agent in (relative to container_ref).
  * If None, uses the container_ref directory directly.
  */
-working_dir: string | null, };
+working_dir: string | null, 
+/**
+ * Subdirectory of the working directory to run the agent in, taking
+ * precedence over the profile's `cwd_relative`
+ */
+cwd_relative?: string | null, 
+/**
+ * Branch into a new session from `session_id` instead of continuing it
+ */
+fork: boolean, 
+/**
+ * Files and images attached to the prompt
+ */
+attachments: Array<Attachment>, };
 
 export type CommandExitStatus = { "type": "exit_code", code: number, } | { "type": "success", success: boolean, };
 
 export type CommandRunResult = { exit_status: CommandExitStatus | null, output: string | null, };
 
-export type NormalizedEntry = { timestamp: string | null, entry_type: NormalizedEntryType, content: string, };
+export type NormalizedEntry = { timestamp: string | null, entry_type: NormalizedEntryType, content: string, 
+/**
+ * Swarm agent the entry came from, for executors that run several
+ */
+agent_id?: string, 
+/**
+ * Role of that agent within the swarm, e.g. `coder` or `tester`
+ */
+agent_role?: string, };
+
+export type NormalizedEntryType = { "type": "user_message" } | { "type": "user_feedback", denied_tool: string, } | { "type": "assistant_message" } | { "type": "tool_use", tool_name: string, action_type: ActionType, status: ToolStatus, } | { "type": "system_message" } | { "type": "error_message", error_type: NormalizedEntryError, } | { "type": "thinking" } | { "type": "loading" } | { "type": "next_action", failed: boolean, execution_processes: number, needs_setup: boolean, } | { "type": "run_summary", summary: RunSummary, } | { "type": "workspace_changes", changes: WorkspaceChanges, } | { "type": "run_hook", stage: HookStage, command: string, status: ToolStatus, } | { "type": "review_finding", severity: ReviewSeverity, path: string | null, line: number | null, } | { "type": "task_list_update", task_list: TaskList, } | { "type": "loop_detected", tool_name: string | null, repeats: number, action: LoopAction, } | { "type": "raw_output" };
+
+/**
+ * Digest of a finished multi-agent run, as reported by the agent itself
+ */
+export type RunSummary = { tasks_completed: number | null, tasks_total: number | null, 
+/**
+ * Names or types of the agents that took part
+ */
+agents: Array<string>, duration_ms: bigint | null, 
+/**
+ * How responsive the run was, measured by the normalization pipeline
+ */
+metrics?: RunMetrics, };
+
+export type RunMetrics = { 
+/**
+ * From the start of the run to the agent's first output; `None` if it
+ * produced none
+ */
+time_to_first_output_ms: bigint | null, 
+/**
+ * Estimated tokens of the agent's messages and thinking
+ */
+output_tokens: bigint, 
+/**
+ * Output tokens per second between the first output and the end
+ */
+tokens_per_sec: number | null, wall_time_ms: bigint, };
+
+export type FileChangeStatus = "added" | "modified" | "deleted" | "renamed" | "untracked" | "conflicted";
+
+export type ChangedFile = { 
+/**
+ * Path relative to the workspace
+ */
+path: string, 
+/**
+ * Path the file was renamed from
+ */
+old_path: string | null, status: FileChangeStatus, 
+/**
+ * Lines added and removed; unknown for binary and very large files
+ */
+additions: number | null, deletions: number | null, };
+
+/**
+ * Uncommitted changes in a workspace once a run exited
+ */
+export type WorkspaceChanges = { files: Array<ChangedFile>, additions: number, deletions: number, };
+
+export type TaskListStatus = "pending" | "in_progress" | "completed" | "cancelled";
 
-export type NormalizedEntryType = { "type": "user_message" } | { "type": "user_feedback", denied_tool: string, } | { "type": "assistant_message" } | { "type": "tool_use", tool_name: string, action_type: ActionType, status: ToolStatus, } | { "type": "system_message" } | { "type": "error_message", error_type: NormalizedEntryError, } | { "type": "thinking" } | { "type": "loading" } | { "type": "next_action", failed: boolean, execution_processes: number, needs_setup: boolean, };
+export type TaskListItem = { content: string, status: TaskListStatus, priority: string | null, };
+
+export type TaskList = { items: Array<TaskListItem>, };
+
+export type RunHook = { command: string, on_failure: HookFailureMode, timeout_secs?: bigint | null, };
+
+export type HookFailureMode = "abort" | "continue";
+
+export type HookStage = "pre_run" | "post_run";
+
+export type ReviewTarget = { 
+/**
+ * Branch or commit the reviewed changes started from. The diff runs from
+ * its merge base with `HEAD` to the working tree, so uncommitted changes
+ * are reviewed too.
+ */
+base: string, };
+
+export type ReviewSeverity = "critical" | "major" | "minor" | "nit";
 
 export type FileChange = { "action": "write", content: string, } | { "action": "delete" } | { "action": "rename", new_path: string, } | { "action": "edit", 
 /**
@@ -535,10 +1209,70 @@ unified_diff: string,
  */
 has_line_numbers: boolean, };
 
-export type ActionType = { "action": "file_read", path: string, } | { "action": "file_edit", path: string, changes: Array<FileChange>, } | { "action": "command_run", command: string, result: CommandRunResult | null, } | { "action": "search", query: string, } | { "action": "web_fetch", url: string, } | { "action": "tool", tool_name: string, arguments: JsonValue | null, result: ToolResult | null, } | { "action": "task_create", description: string, } | { "action": "plan_presentation", plan: string, } | { "action": "todo_management", todos: Array<TodoItem>, operation: string, } | { "action": "other", description: string, };
+export type FileChangeEntry = { path: string, change: FileChange, };
+
+export type NormalizedConversation = { entries: Array<NormalizedEntry>, session_id: string | null, executor_type: string, prompt: string | null, summary: string | null, };
+
+export type TranscriptOptions = { include_thinking: boolean, };
+
+export type DigestOptions = { max_tokens: number, include_thinking: boolean, };
+
+export type TranscriptDigest = { text: string, estimated_tokens: number, 
+/**
+ * Entries left out entirely to stay within the budget
+ */
+omitted_entries: number, };
+
+export type ExportFormat = "ndjson" | "claude_transcript";
+
+export type EntryKind = "message" | "tool_use" | "error" | "thinking" | "system";
+
+export type LogSearchQuery = { kind?: EntryKind | null, 
+/**
+ * Case-insensitive text to find in the entry or its tool name
+ */
+text?: string | null, since?: string | null, until?: string | null, 
+/**
+ * Index of the entry to continue after
+ */
+after?: number | null, limit?: number | null, };
+
+export type LogSearchHit = { index: number, kind: EntryKind, entry: NormalizedEntry, };
+
+export type ToolStats = { tool_name: string, calls: number, 
+/**
+ * Calls that failed, were denied or timed out
+ */
+failures: number, error_rate: number, 
+/**
+ * Average time from call to result, over the calls that were timed
+ */
+avg_duration_ms: bigint | null, max_duration_ms: bigint | null, 
+/**
+ * Time spent in the timed calls altogether
+ */
+total_duration_ms: bigint, };
+
+/**
+ * Statistics of a session's coding agent runs
+ */
+export type SessionStats = { tool_stats: Array<ToolStats>, 
+/**
+ * Pieces of output the agent's log parser couldn't read and kept raw
+ */
+parse_errors: number, };
+
+/**
+ * A normalized entry along with its index in the conversation
+ */
+export type StreamedEntry = { index: number, entry: NormalizedEntry, };
+
+export type ActionType = { "action": "file_read", path: string, } | { "action": "file_edit", path: string, changes: Array<FileChange>, } | { "action": "command_run", command: string, result: CommandRunResult | null, } | { "action": "search", query: string, } | { "action": "web_fetch", url: string, } | { "action": "tool", tool_name: string, arguments: JsonValue | null, result: ToolResult | null, } | { "action": "task_create", description: string, } | { "action": "plan_presentation", plan: string, } | { "action": "todo_management", todos: Array<TodoItem>, operation: string, } | { "action": "memory", operation: MemoryOperation, namespace: string | null, key: string | null, } | { "action": "other", description: string, };
 
 export type TodoItem = { content: string, status: string, priority: string | null, };
 
+export type MemoryOperation = "store" | "retrieve" | "search" | "list" | "delete";
+
 export type NormalizedEntryError = { "type": "setup_required" } | { "type": "other" };
 
 export type ToolResult = { type: ToolResultValueType, 